@@ -0,0 +1,58 @@
+//! Command line helpers built on the sdk.
+//!
+//! Usage:
+//!
+//! ```text
+//! ckb-sdk-tool dump-cell-deps --rpc-url <url> --output <file>
+//! ```
+use std::{error::Error as StdErr, fs, process};
+
+use ckb_sdk::{traits::DefaultCellDepResolver, CkbRpcClient};
+use ckb_types::core::BlockView;
+
+const USAGE: &str = "Usage: ckb-sdk-tool dump-cell-deps --rpc-url <url> --output <file>";
+
+fn dump_cell_deps(rpc_url: &str, output: &str) -> Result<(), Box<dyn StdErr>> {
+    let ckb_client = CkbRpcClient::new(rpc_url);
+    let genesis_block = ckb_client
+        .get_block_by_number(0.into())?
+        .ok_or("genesis block not found")?;
+    let resolver = DefaultCellDepResolver::from_genesis(&BlockView::from(genesis_block))?;
+    fs::write(output, resolver.to_json_string())?;
+    Ok(())
+}
+
+// parse `--name value` pairs
+fn parse_options(args: &[String]) -> Result<(String, String), String> {
+    let mut rpc_url = None;
+    let mut output = None;
+    let mut iter = args.iter();
+    while let Some(name) = iter.next() {
+        let value = iter
+            .next()
+            .ok_or_else(|| format!("missing value of {}", name))?;
+        match name.as_str() {
+            "--rpc-url" => rpc_url = Some(value.clone()),
+            "--output" => output = Some(value.clone()),
+            _ => return Err(format!("unknown option: {}", name)),
+        }
+    }
+    Ok((
+        rpc_url.ok_or("missing --rpc-url")?,
+        output.ok_or("missing --output")?,
+    ))
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("dump-cell-deps") => parse_options(&args[1..])
+            .map_err(|err| format!("{}\n{}", err, USAGE).into())
+            .and_then(|(rpc_url, output)| dump_cell_deps(&rpc_url, &output)),
+        _ => Err(USAGE.into()),
+    };
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
@@ -8,6 +8,13 @@ pub const NETWORK_TESTNET: &str = "ckb_testnet";
 pub const NETWORK_STAGING: &str = "ckb_staging";
 pub const NETWORK_DEV: &str = "ckb_dev";
 
+/// The genesis block hash of mainnet (lina)
+pub const GENESIS_HASH_MAINNET: H256 =
+    h256!("0x92b197aa1fba0f63633922c61c92375c9c074a93e85963554f5499fe1450d0e5");
+/// The genesis block hash of testnet (pudge)
+pub const GENESIS_HASH_TESTNET: H256 =
+    h256!("0x10639e0895502b5688a6be8cf69460d76541bfa4821629d86d62ba0aae3f9606");
+
 pub const SECP_SIGNATURE_SIZE: usize = 65;
 
 // Since relative mask
@@ -24,6 +31,8 @@ pub const SIGHASH_GROUP_OUTPUT_LOC: (usize, usize) = (1, 0);
 pub const MULTISIG_GROUP_OUTPUT_LOC: (usize, usize) = (1, 1);
 
 pub const ONE_CKB: u64 = 100_000_000;
+/// The maximum serialized size of a block, a transaction can not be larger than it
+pub const MAX_BLOCK_BYTES: u64 = 597_000;
 pub const MIN_SECP_CELL_CAPACITY: u64 = 61 * ONE_CKB;
 // mainnet,testnet cellbase maturity
 pub const CELLBASE_MATURITY: EpochNumberWithFraction =
@@ -39,6 +48,14 @@ pub const MULTISIG_TYPE_HASH: H256 =
 pub const DAO_TYPE_HASH: H256 =
     h256!("0x82d76d1b75fe2fd9a27dfbaa65a039221a380d76c926f378d3f81cf3e7e13f2e");
 
+/// The data hash of the system script cells in the genesis block (copied from ckb-resource)
+pub const SIGHASH_DATA_HASH: H256 =
+    h256!("0x709f3fda12f561cfacf92273c57a98fede188a3f1a59b1f888d113f9cce08649");
+pub const MULTISIG_DATA_HASH: H256 =
+    h256!("0x43400de165f0821abf63dcac299bbdf7fd73898675ee4ddb099b0a0d8db63bfb");
+pub const DAO_DATA_HASH: H256 =
+    h256!("0x32064a14ce10d95d4b7343054cc19d73b25b16ae61a6c681011ca781a60c7923");
+
 /// anyone can pay script mainnet code hash, see:
 /// <https://github.com/nervosnetwork/rfcs/blob/master/rfcs/0026-anyone-can-pay/0026-anyone-can-pay.md#notes>
 pub const ACP_TYPE_HASH_LINA: H256 =
@@ -47,6 +64,29 @@ pub const ACP_TYPE_HASH_LINA: H256 =
 pub const ACP_TYPE_HASH_AGGRON: H256 =
     h256!("0x3419a1c09eb2567f6552ee7a8ecffd64155cffe0f1796e6e61ec088d740c1356");
 
+/// simple udt script mainnet type hash, see:
+/// <https://github.com/nervosnetwork/rfcs/blob/master/rfcs/0025-simple-udt/0025-simple-udt.md#deployment>
+pub const SUDT_TYPE_HASH_LINA: H256 =
+    h256!("0x5e7a36a77e68eecc013dfa2fe6a23f3b6c344b04005808694ae6dd45eea4cfd5");
+/// simple udt script testnet type hash
+pub const SUDT_TYPE_HASH_AGGRON: H256 =
+    h256!("0xc5e5dcf215925f7ef4dfaf5f4b4f105bc321c02776d6e7d52a1db3fcd9d011a4");
+/// extensible udt script mainnet code hash (hash_type = data1), see:
+/// <https://github.com/nervosnetwork/rfcs/blob/master/rfcs/0052-extensible-udt/0052-extensible-udt.md#deployment>
+pub const XUDT_DATA_HASH_LINA: H256 =
+    h256!("0x50bd8d6680b8b9cf98b73f3c08faf8b2a21914311954118ad6609be6e78a1b95");
+/// extensible udt script testnet type hash
+pub const XUDT_TYPE_HASH_AGGRON: H256 =
+    h256!("0x25c29dc317811a6f6f3985a7a9ebc4838bd388d19d0feeecf0bcd60f6c0975bb");
+
+/// spore NFT type script mainnet code hash (hash_type = data1), see:
+/// <https://github.com/sporeprotocol/spore-contract/blob/master/docs/VERSIONS.md>
+pub const SPORE_DATA_HASH_LINA: H256 =
+    h256!("0x4a4dce1df3dffff7f8b2cd7dff7303df3b6150c9788cb75dcf6747247132b9f5");
+/// spore NFT type script testnet code hash (hash_type = data1)
+pub const SPORE_DATA_HASH_AGGRON: H256 =
+    h256!("0x685a60219309029d01310311dba953d67029170ca4848a4ff638e57002130a0d");
+
 /// cheque withdraw since value
 pub const CHEQUE_CELL_SINCE: u64 = 0xA000000000000006;
 
@@ -0,0 +1,190 @@
+use ckb_types::{
+    bytes::Bytes,
+    core::ScriptHashType,
+    packed::{Byte32, Script},
+    prelude::*,
+};
+use thiserror::Error;
+
+use crate::constants::SIGHASH_TYPE_HASH;
+use crate::traits::GenesisInfo;
+use crate::types::{Address, AddressPayload, NetworkType};
+use crate::unlock::MultisigConfig;
+use crate::util::blake160;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ScriptError {
+    #[error("unexpected lock script: code_hash=`{0}`, hash_type=`{1}`")]
+    UnexpectedScript(Byte32, u8),
+
+    #[error("invalid lock args length, expected: `{expected}`, got: `{actual}`")]
+    InvalidArgsLength { expected: usize, actual: usize },
+}
+
+/// The secp256k1-blake160-sighash-all lock args of the public key, the first 20
+/// bytes of the blake2b hash of the compressed public key.
+pub fn blake160_of_pubkey(pubkey: &secp256k1::PublicKey) -> Bytes {
+    Bytes::from(blake160(&pubkey.serialize()).as_bytes().to_vec())
+}
+
+/// The standard secp256k1-blake160-sighash-all lock script of the public key, the
+/// code hash is the sighash type hash of the chain.
+pub fn secp256k1_blake160_lock(
+    pubkey: &secp256k1::PublicKey,
+    genesis_info: &GenesisInfo,
+) -> Script {
+    Script::new_builder()
+        .code_hash(genesis_info.sighash_type_hash.pack())
+        .hash_type(ScriptHashType::Type.into())
+        .args(blake160_of_pubkey(pubkey).pack())
+        .build()
+}
+
+/// The full format address of [`secp256k1_blake160_lock`].
+pub fn pubkey_to_ckb_address(
+    pubkey: &secp256k1::PublicKey,
+    network: NetworkType,
+    genesis_info: &GenesisInfo,
+) -> String {
+    let payload = AddressPayload::from(secp256k1_blake160_lock(pubkey, genesis_info));
+    Address::new(network, payload, true).to_string()
+}
+
+/// Extract the public key hash from a secp256k1-blake160-sighash-all lock script.
+pub fn secp256k1_pubkey_hash_from_lock_args(lock: &Script) -> Result<[u8; 20], ScriptError> {
+    if lock.code_hash() != SIGHASH_TYPE_HASH.pack()
+        || lock.hash_type() != ScriptHashType::Type.into()
+    {
+        return Err(ScriptError::UnexpectedScript(
+            lock.code_hash(),
+            lock.hash_type().into(),
+        ));
+    }
+    let args = lock.args().raw_data();
+    if args.len() != 20 {
+        return Err(ScriptError::InvalidArgsLength {
+            expected: 20,
+            actual: args.len(),
+        });
+    }
+    let mut hash = [0u8; 20];
+    hash.copy_from_slice(&args);
+    Ok(hash)
+}
+
+/// Check the lock script is the multisig lock of the config (the args is the hash of
+/// the multisig script of the public key hashes), with or without the since in the
+/// args.
+pub fn multisig_pubkey_hashes_from_lock(lock: &Script, config: &MultisigConfig) -> bool {
+    config.matches_lock_script(lock)
+}
+
+#[cfg(test)]
+mod tests {
+    use ckb_types::{packed::CellDep, H256};
+
+    use super::*;
+    use crate::constants::{DAO_TYPE_HASH, MULTISIG_TYPE_HASH};
+
+    fn genesis_info(sighash_type_hash: H256) -> GenesisInfo {
+        GenesisInfo {
+            genesis_hash: H256::default(),
+            sighash_type_hash,
+            multisig_type_hash: MULTISIG_TYPE_HASH.clone(),
+            dao_type_hash: DAO_TYPE_HASH.clone(),
+            sighash_dep: CellDep::default(),
+            multisig_dep: CellDep::default(),
+            dao_dep: CellDep::default(),
+        }
+    }
+
+    #[test]
+    fn test_pubkey_to_lock_and_address() {
+        let key = secp256k1::SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let pubkey = secp256k1::PublicKey::from_secret_key(&crate::SECP256K1, &key);
+        let info = genesis_info(SIGHASH_TYPE_HASH.clone());
+
+        let payload = AddressPayload::from_pubkey(&pubkey);
+        let lock = secp256k1_blake160_lock(&pubkey, &info);
+        assert_eq!(lock, Script::from(&payload));
+        let address = pubkey_to_ckb_address(&pubkey, NetworkType::Mainnet, &info);
+        assert_eq!(
+            address,
+            Address::new(NetworkType::Mainnet, payload, true).to_string()
+        );
+        assert!(address.starts_with("ckb1"));
+        let parsed: Address = address.parse().unwrap();
+        assert_eq!(Script::from(&parsed), lock);
+
+        // the code hash follows the genesis info, e.g. on a dev chain
+        let dev_info = genesis_info(H256::from([1u8; 32]));
+        let dev_lock = secp256k1_blake160_lock(&pubkey, &dev_info);
+        assert_eq!(dev_lock.code_hash(), H256::from([1u8; 32]).pack());
+        assert_eq!(dev_lock.args(), lock.args());
+        let dev_address = pubkey_to_ckb_address(&pubkey, NetworkType::Dev, &dev_info);
+        let parsed: Address = dev_address.parse().unwrap();
+        assert_eq!(Script::from(&parsed), dev_lock);
+    }
+    #[test]
+    fn test_pubkey_hash_from_lock_args() {
+        let key = secp256k1::SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let pubkey = secp256k1::PublicKey::from_secret_key(&crate::SECP256K1, &key);
+        let lock = secp256k1_blake160_lock(&pubkey, &genesis_info(SIGHASH_TYPE_HASH.clone()));
+        assert_eq!(
+            &secp256k1_pubkey_hash_from_lock_args(&lock).unwrap()[..],
+            blake160_of_pubkey(&pubkey).as_ref()
+        );
+
+        let data_lock = lock
+            .clone()
+            .as_builder()
+            .hash_type(ScriptHashType::Data.into())
+            .build();
+        assert_eq!(
+            secp256k1_pubkey_hash_from_lock_args(&data_lock),
+            Err(ScriptError::UnexpectedScript(
+                SIGHASH_TYPE_HASH.pack(),
+                ScriptHashType::Data as u8
+            ))
+        );
+        let long_lock = lock
+            .as_builder()
+            .args(Bytes::from(vec![0u8; 28]).pack())
+            .build();
+        assert_eq!(
+            secp256k1_pubkey_hash_from_lock_args(&long_lock),
+            Err(ScriptError::InvalidArgsLength {
+                expected: 20,
+                actual: 28
+            })
+        );
+    }
+
+    #[test]
+    fn test_multisig_pubkey_hashes_from_lock() {
+        use ckb_types::h160;
+
+        let config = MultisigConfig::new_with(
+            vec![
+                h160!("0x33f2a9fa0a0bff6d0b5d6dec2a1a8d4bff3bc1e8"),
+                h160!("0x9c2ec9e3b6e5e8b2c2f3b0f1ad4aa3fbd6e3fd0b"),
+            ],
+            0,
+            2,
+        )
+        .unwrap();
+        let lock = Script::new_builder()
+            .code_hash(MULTISIG_TYPE_HASH.pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(config.script_hash().to_vec()).pack())
+            .build();
+        assert!(multisig_pubkey_hashes_from_lock(&lock, &config));
+        let other = MultisigConfig::new_with(config.sighash_addresses().clone(), 0, 1).unwrap();
+        assert!(!multisig_pubkey_hashes_from_lock(&lock, &other));
+        let since_lock = lock
+            .as_builder()
+            .args(Bytes::from([&config.script_hash()[..], &[0u8; 8]].concat()).pack())
+            .build();
+        assert!(multisig_pubkey_hashes_from_lock(&since_lock, &config));
+    }
+}
@@ -1,5 +1,7 @@
 pub mod constants;
 pub mod core;
+pub mod key;
+#[cfg(feature = "async")]
 pub mod pubsub;
 pub mod rpc;
 pub mod traits;
@@ -16,11 +18,13 @@ pub mod test_util;
 #[cfg(test)]
 mod tests;
 
-pub use rpc::{CkbRpcClient, IndexerRpcClient, RpcError};
+pub use rpc::RpcError;
+#[cfg(feature = "rpc")]
+pub use rpc::{CkbRpcClient, IndexerRpcClient};
 pub use types::{
-    Address, AddressPayload, AddressType, CodeHashIndex, HumanCapacity, NetworkInfo, NetworkType,
-    OldAddress, OldAddressFormat, ScriptGroup, ScriptGroupType, ScriptId, Since, SinceType,
-    TransactionWithScriptGroups,
+    Address, AddressError, AddressPayload, AddressType, CodeHashIndex, HumanCapacity, NetworkInfo,
+    NetworkType, OldAddress, OldAddressFormat, ScriptGroup, ScriptGroupType, ScriptId, Since,
+    SinceType, TransactionWithScriptGroups,
 };
 
 pub use ckb_crypto::secp::SECP256K1;
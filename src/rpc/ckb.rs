@@ -1,159 +1,269 @@
+#[cfg(any(feature = "rpc", feature = "async-rpc"))]
+use ckb_jsonrpc_types::BlockResponse;
+#[cfg(feature = "rpc")]
+use ckb_jsonrpc_types::TxPoolIds;
 use ckb_jsonrpc_types::{
-    Alert, BannedAddr, Block, BlockEconomicState, BlockFilter, BlockNumber, BlockResponse,
-    BlockTemplate, BlockView, Capacity, CellWithStatus, ChainInfo, Consensus,
-    DaoWithdrawingCalculationKind, DeploymentsInfo, EpochNumber, EpochNumberWithFraction,
-    EpochView, EstimateCycles, ExtraLoggerConfig, FeeRateStatistics, HeaderView, JsonBytes,
-    LocalNode, MainLoggerConfig, OutPoint, OutputsValidator, PoolTxDetailInfo, RawTxPool,
-    RemoteNode, SyncState, Timestamp, Transaction, TransactionAndWitnessProof, TransactionProof,
-    TransactionWithStatusResponse, TxPoolInfo, Uint32, Uint64, Version,
+    Alert, BannedAddr, Block, BlockEconomicState, BlockFilter, BlockNumber, BlockTemplate,
+    BlockView, Capacity, CellWithStatus, ChainInfo, Consensus, DaoWithdrawingCalculationKind,
+    DeploymentsInfo, EpochNumber, EpochNumberWithFraction, EpochView, EstimateCycles,
+    ExtraLoggerConfig, FeeRateStatistics, HeaderView, JsonBytes, LocalNode, MainLoggerConfig,
+    OutPoint, OutputsValidator, PoolTxDetailInfo, RawTxPool, RemoteNode, SyncState, Timestamp,
+    Transaction, TransactionAndWitnessProof, TransactionProof, TransactionWithStatusResponse,
+    TxPoolInfo, Uint32, Uint64, Version,
 };
-use ckb_types::{core::Cycle, H256};
+#[cfg(any(feature = "rpc", feature = "async-rpc"))]
+use ckb_types::core::Cycle;
+use ckb_types::H256;
 
-use super::{ckb_indexer::CellsCapacity, ResponseFormatGetter};
+use super::ckb_indexer::CellsCapacity;
+use super::tx_pool::EntryCompleted;
+#[cfg(any(feature = "rpc", feature = "async-rpc"))]
+use super::ResponseFormatGetter;
 
 pub use super::ckb_indexer::{Cell, Order, Pagination, SearchKey, Tip, Tx};
 
-crate::jsonrpc!(pub struct CkbRpcClient {
-    // Chain
-    pub fn get_block(&self, hash: H256) -> Option<BlockView>;
-    pub fn get_block_by_number(&self, number: BlockNumber) -> Option<BlockView>;
-    pub fn get_block_hash(&self, number: BlockNumber) -> Option<H256>;
-    pub fn get_block_filter(&self, block_hash: H256) -> Option<BlockFilter>;
-    pub fn get_current_epoch(&self) -> EpochView;
-    pub fn get_epoch_by_number(&self, number: EpochNumber) -> Option<EpochView>;
-    pub fn get_header(&self, hash: H256) -> Option<HeaderView>;
-    pub fn get_header_by_number(&self, number: BlockNumber) -> Option<HeaderView>;
-    pub fn get_live_cell(&self, out_point: OutPoint, with_data: bool) -> CellWithStatus;
-    pub fn get_tip_block_number(&self) -> BlockNumber;
-    pub fn get_tip_header(&self) -> HeaderView;
-    pub fn get_transaction(&self, hash: H256) -> Option<TransactionWithStatusResponse>;
-    pub fn get_transaction_proof(
-        &self,
-        tx_hashes: Vec<H256>,
-        block_hash: Option<H256>
-    ) -> TransactionProof;
-    pub fn verify_transaction_proof(&self, tx_proof: TransactionProof) -> Vec<H256>;
-    pub fn get_transaction_and_witness_proof(&self, tx_hashes: Vec<H256>,
-        block_hash: Option<H256>) -> TransactionAndWitnessProof;
-    pub fn verify_transaction_and_witness_proof(&self, tx_proof: TransactionAndWitnessProof) -> Vec<H256>;
-    pub fn get_fork_block(&self, block_hash: H256) -> Option<BlockView>;
-    pub fn get_consensus(&self) -> Consensus;
-    pub fn get_deployments_info(&self) -> DeploymentsInfo;
-    pub fn get_block_median_time(&self, block_hash: H256) -> Option<Timestamp>;
-    pub fn get_block_economic_state(&self, block_hash: H256) -> Option<BlockEconomicState>;
-    pub fn estimate_cycles(&self, tx: Transaction)-> EstimateCycles;
-    pub fn get_fee_rate_statics(&self, target:Option<Uint64>) -> Option<FeeRateStatistics>;
-    pub fn get_fee_rate_statistics(&self, target:Option<Uint64>) -> Option<FeeRateStatistics>;
-
-    // Indexer
-    pub fn get_indexer_tip(&self) -> Option<Tip>;
-    pub fn get_cells(&self, search_key: SearchKey, order: Order, limit: Uint32, after: Option<JsonBytes>) -> Pagination<Cell>;
-    pub fn get_transactions(&self, search_key: SearchKey, order: Order, limit: Uint32, after: Option<JsonBytes>) -> Pagination<Tx>;
-    pub fn get_cells_capacity(&self, search_key: SearchKey) -> Option<CellsCapacity>;
-
-    // Net
-    pub fn get_banned_addresses(&self) -> Vec<BannedAddr>;
-    pub fn get_peers(&self) -> Vec<RemoteNode>;
-    pub fn local_node_info(&self) -> LocalNode;
-    pub fn set_ban(
-        &self,
-        address: String,
-        command: String,
-        ban_time: Option<Timestamp>,
-        absolute: Option<bool>,
-        reason: Option<String>
-    ) -> ();
-    pub fn sync_state(&self) -> SyncState;
-    pub fn set_network_active(&self, state: bool) -> ();
-    pub fn add_node(&self, peer_id: String, address: String) -> ();
-    pub fn remove_node(&self, peer_id: String) -> ();
-    pub fn clear_banned_addresses(&self) -> ();
-    pub fn ping_peers(&self) -> ();
-
-    // Pool
-    pub fn send_transaction(&self, tx: Transaction, outputs_validator: Option<OutputsValidator>) -> H256;
-    pub fn remove_transaction(&self, tx_hash: H256) -> bool;
-    pub fn tx_pool_info(&self) -> TxPoolInfo;
-    pub fn get_pool_tx_detail_info(&self, tx_hash: H256) -> PoolTxDetailInfo;
-    pub fn clear_tx_pool(&self) -> ();
-    pub fn get_raw_tx_pool(&self, verbose: Option<bool>) -> RawTxPool;
-    pub fn tx_pool_ready(&self) -> bool;
-
-    // Stats
-    pub fn get_blockchain_info(&self) -> ChainInfo;
-
-    // Miner
-    pub fn get_block_template(&self, bytes_limit: Option<Uint64>, proposals_limit: Option<Uint64>, max_version: Option<Version>) -> BlockTemplate;
-    pub fn submit_block(&self, _work_id: String, _data: Block) -> H256;
-
-    // Alert
-    pub fn send_alert(&self, alert: Alert) -> ();
-
-    // IntegrationTest
-    pub fn process_block_without_verify(&self, data: Block, broadcast: bool) -> Option<H256>;
-    pub fn truncate(&self, target_tip_hash: H256) -> ();
-    pub fn generate_block(&self) -> H256;
-    pub fn generate_epochs(&self, num_epochs: EpochNumberWithFraction) -> EpochNumberWithFraction;
-    pub fn notify_transaction(&self, tx: Transaction) -> H256;
-    pub fn calculate_dao_field(&self, block_template: BlockTemplate) -> JsonBytes;
-    pub fn generate_block_with_template(&self, block_template: BlockTemplate) -> H256;
-
-    // Debug
-    pub fn jemalloc_profiling_dump(&self) -> String;
-    pub fn update_main_logger(&self, config: MainLoggerConfig) -> ();
-    pub fn set_extra_logger(&self, name: String, config_opt: Option<ExtraLoggerConfig>) -> ();
-
-    // Experimental
-    pub fn calculate_dao_maximum_withdraw(&self, out_point: OutPoint, kind: DaoWithdrawingCalculationKind) -> Capacity;
-});
+/// The minimal fee rate (shannons/KB) accepted by the tx-pool with the default config
+#[cfg(feature = "rpc")]
+pub const MIN_FEE_RATE: u64 = 1000;
+
+/// Target confirmation blocks no more than this are estimated by the mean fee rate
+/// (which is biased upward by the urgent transactions), otherwise the median.
+#[cfg(feature = "rpc")]
+pub const FAST_CONFIRMATION_BLOCKS: u32 = 3;
+
+// The rpc methods of ckb node, shared by the blocking and the async clients
+macro_rules! ckb_rpc_client {
+    ($jsonrpc:ident, $name:ident) => {
+        crate::$jsonrpc!(pub struct $name {
+            // Chain
+            pub fn get_block(&self, hash: H256) -> Option<BlockView>;
+            pub fn get_block_by_number(&self, number: BlockNumber) -> Option<BlockView>;
+            pub fn get_block_hash(&self, number: BlockNumber) -> Option<H256>;
+            pub fn get_block_filter(&self, block_hash: H256) -> Option<BlockFilter>;
+            pub fn get_current_epoch(&self) -> EpochView;
+            pub fn get_epoch_by_number(&self, number: EpochNumber) -> Option<EpochView>;
+            pub fn get_header(&self, hash: H256) -> Option<HeaderView>;
+            pub fn get_header_by_number(&self, number: BlockNumber) -> Option<HeaderView>;
+            pub fn get_live_cell(&self, out_point: OutPoint, with_data: bool) -> CellWithStatus;
+            pub fn get_tip_block_number(&self) -> BlockNumber;
+            pub fn get_tip_header(&self) -> HeaderView;
+            pub fn get_transaction(&self, hash: H256) -> Option<TransactionWithStatusResponse>;
+            pub fn get_transaction_proof(
+                &self,
+                tx_hashes: Vec<H256>,
+                block_hash: Option<H256>
+            ) -> TransactionProof;
+            pub fn verify_transaction_proof(&self, tx_proof: TransactionProof) -> Vec<H256>;
+            pub fn get_transaction_and_witness_proof(&self, tx_hashes: Vec<H256>,
+                block_hash: Option<H256>) -> TransactionAndWitnessProof;
+            pub fn verify_transaction_and_witness_proof(&self, tx_proof: TransactionAndWitnessProof) -> Vec<H256>;
+            pub fn get_fork_block(&self, block_hash: H256) -> Option<BlockView>;
+            pub fn get_consensus(&self) -> Consensus;
+            pub fn get_deployments_info(&self) -> DeploymentsInfo;
+            pub fn get_block_median_time(&self, block_hash: H256) -> Option<Timestamp>;
+            pub fn get_block_economic_state(&self, block_hash: H256) -> Option<BlockEconomicState>;
+            pub fn estimate_cycles(&self, tx: Transaction)-> EstimateCycles;
+            pub fn get_fee_rate_statics(&self, target:Option<Uint64>) -> Option<FeeRateStatistics>;
+            pub fn get_fee_rate_statistics(&self, target:Option<Uint64>) -> Option<FeeRateStatistics>;
+
+            // Indexer
+            pub fn get_indexer_tip(&self) -> Option<Tip>;
+            pub fn get_cells(&self, search_key: SearchKey, order: Order, limit: Uint32, after: Option<JsonBytes>) -> Pagination<Cell>;
+            pub fn get_transactions(&self, search_key: SearchKey, order: Order, limit: Uint32, after: Option<JsonBytes>) -> Pagination<Tx>;
+            pub fn get_cells_capacity(&self, search_key: SearchKey) -> Option<CellsCapacity>;
+
+            // Net
+            pub fn get_banned_addresses(&self) -> Vec<BannedAddr>;
+            pub fn get_peers(&self) -> Vec<RemoteNode>;
+            pub fn local_node_info(&self) -> LocalNode;
+            pub fn set_ban(
+                &self,
+                address: String,
+                command: String,
+                ban_time: Option<Timestamp>,
+                absolute: Option<bool>,
+                reason: Option<String>
+            ) -> ();
+            pub fn sync_state(&self) -> SyncState;
+            pub fn set_network_active(&self, state: bool) -> ();
+            pub fn add_node(&self, peer_id: String, address: String) -> ();
+            pub fn remove_node(&self, peer_id: String) -> ();
+            pub fn clear_banned_addresses(&self) -> ();
+            pub fn ping_peers(&self) -> ();
+
+            // Pool
+            pub fn send_transaction(&self, tx: Transaction, outputs_validator: Option<OutputsValidator>) -> H256;
+            pub fn test_tx_pool_accept(&self, tx: Transaction, outputs_validator: Option<OutputsValidator>) -> EntryCompleted;
+            pub fn remove_transaction(&self, tx_hash: H256) -> bool;
+            pub fn tx_pool_info(&self) -> TxPoolInfo;
+            pub fn get_pool_tx_detail_info(&self, tx_hash: H256) -> PoolTxDetailInfo;
+            pub fn clear_tx_pool(&self) -> ();
+            pub fn get_raw_tx_pool(&self, verbose: Option<bool>) -> RawTxPool;
+            pub fn tx_pool_ready(&self) -> bool;
+
+            // Stats
+            pub fn get_blockchain_info(&self) -> ChainInfo;
+
+            // Miner
+            pub fn get_block_template(&self, bytes_limit: Option<Uint64>, proposals_limit: Option<Uint64>, max_version: Option<Version>) -> BlockTemplate;
+            pub fn submit_block(&self, _work_id: String, _data: Block) -> H256;
+
+            // Alert
+            pub fn send_alert(&self, alert: Alert) -> ();
+
+            // IntegrationTest
+            pub fn process_block_without_verify(&self, data: Block, broadcast: bool) -> Option<H256>;
+            pub fn truncate(&self, target_tip_hash: H256) -> ();
+            pub fn generate_block(&self) -> H256;
+            pub fn generate_epochs(&self, num_epochs: EpochNumberWithFraction) -> EpochNumberWithFraction;
+            pub fn notify_transaction(&self, tx: Transaction) -> H256;
+            pub fn calculate_dao_field(&self, block_template: BlockTemplate) -> JsonBytes;
+            pub fn generate_block_with_template(&self, block_template: BlockTemplate) -> H256;
 
+            // Debug
+            pub fn jemalloc_profiling_dump(&self) -> String;
+            pub fn update_main_logger(&self, config: MainLoggerConfig) -> ();
+            pub fn set_extra_logger(&self, name: String, config_opt: Option<ExtraLoggerConfig>) -> ();
+
+            // Experimental
+            pub fn calculate_dao_maximum_withdraw(&self, out_point: OutPoint, kind: DaoWithdrawingCalculationKind) -> Capacity;
+        });
+    };
+}
+
+#[cfg(feature = "rpc")]
+ckb_rpc_client!(jsonrpc, CkbRpcClient);
+#[cfg(feature = "async-rpc")]
+ckb_rpc_client!(jsonrpc_async, AsyncCkbRpcClient);
+
+#[cfg(any(feature = "rpc", feature = "async-rpc"))]
 fn transform_cycles(cycles: Option<Vec<ckb_jsonrpc_types::Cycle>>) -> Vec<Cycle> {
     cycles
         .map(|c| c.into_iter().map(Into::into).collect())
         .unwrap_or_default()
 }
 
+// turn block response into BlockView and cycle vec
+#[cfg(any(feature = "rpc", feature = "async-rpc"))]
+fn transform_block_view_with_cycle(
+    opt_resp: Option<BlockResponse>,
+) -> Result<Option<(BlockView, Vec<Cycle>)>, crate::rpc::RpcError> {
+    opt_resp
+        .map(|resp| match resp {
+            BlockResponse::Regular(block_view) => Ok((block_view.get_value()?, vec![])),
+            BlockResponse::WithCycles(block_cycles) => {
+                let cycles = transform_cycles(block_cycles.cycles);
+                Ok((block_cycles.block.get_value()?, cycles))
+            }
+        })
+        .transpose()
+}
+
+// turn BlockResponse to JsonBytes and Cycle tuple
+#[cfg(any(feature = "rpc", feature = "async-rpc"))]
+fn blockresponse2bytes(
+    opt_resp: Option<BlockResponse>,
+) -> Result<Option<(JsonBytes, Vec<Cycle>)>, crate::rpc::RpcError> {
+    opt_resp
+        .map(|resp| match resp {
+            BlockResponse::Regular(block_view) => Ok((block_view.get_json_bytes()?, vec![])),
+            BlockResponse::WithCycles(block_cycles) => {
+                let cycles = transform_cycles(block_cycles.cycles);
+                Ok((block_cycles.block.get_json_bytes()?, cycles))
+            }
+        })
+        .transpose()
+}
+
+/// Check the transaction is committed by fetching its proof (`get_transaction_proof`)
+/// and verifying it (`verify_transaction_proof`). Returns false if the transaction
+/// is not committed yet.
+///
+/// Both requests are served by the same node, run it against another node to not
+/// trust the node the transaction was sent to.
+#[cfg(feature = "rpc")]
+pub fn verify_tx_inclusion(tx_hash: &H256, client: &CkbRpcClient) -> Result<bool, crate::RpcError> {
+    let status = client
+        .get_transaction_status(tx_hash.clone())?
+        .tx_status
+        .status;
+    if status != ckb_jsonrpc_types::Status::Committed {
+        return Ok(false);
+    }
+    let proof = client.get_transaction_proof(vec![tx_hash.clone()], None)?;
+    let tx_hashes = client.verify_transaction_proof(proof)?;
+    Ok(tx_hashes.contains(tx_hash))
+}
+
+#[cfg(feature = "rpc")]
 impl CkbRpcClient {
-    pub fn get_packed_block(&self, hash: H256) -> Result<Option<JsonBytes>, crate::RpcError> {
-        self.post("get_block", (hash, Some(Uint32::from(0u32))))
+    /// List the hashes of the pending and the proposed transactions in the tx-pool, a
+    /// shortcut of `get_raw_tx_pool(Some(false))`.
+    pub fn get_pool_tx_hashes(&self) -> Result<TxPoolIds, crate::RpcError> {
+        Ok(match self.get_raw_tx_pool(Some(false))? {
+            RawTxPool::Ids(ids) => ids,
+            RawTxPool::Verbose(entries) => TxPoolIds {
+                pending: entries.pending.into_keys().collect(),
+                proposed: entries.proposed.into_keys().collect(),
+            },
+        })
     }
 
-    // turn block response into BlockView and cycle vec
-    fn transform_block_view_with_cycle(
-        opt_resp: Option<BlockResponse>,
-    ) -> Result<Option<(BlockView, Vec<Cycle>)>, crate::rpc::RpcError> {
-        opt_resp
-            .map(|resp| match resp {
-                BlockResponse::Regular(block_view) => Ok((block_view.get_value()?, vec![])),
-                BlockResponse::WithCycles(block_cycles) => {
-                    let cycles = transform_cycles(block_cycles.cycles);
-                    Ok((block_cycles.block.get_value()?, cycles))
+    /// Calculate the maximum withdraw capacity of the deposit cell `out_point` when it
+    /// is withdrawn in block `block_hash`, a shortcut of `calculate_dao_maximum_withdraw`
+    /// with `DaoWithdrawingCalculationKind::WithdrawingHeaderHash`.
+    pub fn calculate_dao_maximum_withdraw_at(
+        &self,
+        out_point: OutPoint,
+        block_hash: H256,
+    ) -> Result<u64, crate::RpcError> {
+        self.calculate_dao_maximum_withdraw(
+            out_point,
+            DaoWithdrawingCalculationKind::WithdrawingHeaderHash(block_hash),
+        )
+        .map(|capacity| capacity.value())
+    }
+
+    /// Estimate the fee rate (shannons/KB) for a transaction to be committed in about
+    /// `target_confirmation_blocks` blocks from the `get_fee_rate_statics` statistics,
+    /// never lower than [`MIN_FEE_RATE`]. Returns [`MIN_FEE_RATE`] if the node (before
+    /// v0.107) does not support the rpc or has no statistics.
+    pub fn estimate_fee_rate(
+        &self,
+        target_confirmation_blocks: u32,
+    ) -> Result<u64, crate::RpcError> {
+        let statistics = match self.get_fee_rate_statics(None) {
+            Ok(statistics) => statistics,
+            Err(crate::RpcError::Rpc(err))
+                if err.code == jsonrpc_core::ErrorCode::MethodNotFound =>
+            {
+                None
+            }
+            Err(err) => return Err(err),
+        };
+        let fee_rate = statistics
+            .map(|statistics| {
+                if target_confirmation_blocks <= FAST_CONFIRMATION_BLOCKS {
+                    statistics.mean.value().max(statistics.median.value())
+                } else {
+                    statistics.median.value()
                 }
             })
-            .transpose()
+            .unwrap_or_default();
+        Ok(fee_rate.max(MIN_FEE_RATE))
     }
+
+    pub fn get_packed_block(&self, hash: H256) -> Result<Option<JsonBytes>, crate::RpcError> {
+        self.post("get_block", (hash, Some(Uint32::from(0u32))))
+    }
+
     /// Same as get_block except with parameter with_cycles and return BlockResponse
     pub fn get_block_with_cycles(
         &self,
         hash: H256,
     ) -> Result<Option<(BlockView, Vec<Cycle>)>, crate::rpc::RpcError> {
         let res = self.post::<_, Option<BlockResponse>>("get_block", (hash, None::<u32>, true))?;
-        Self::transform_block_view_with_cycle(res)
-    }
-
-    // turn BlockResponse to JsonBytes and Cycle tuple
-    fn blockresponse2bytes(
-        opt_resp: Option<BlockResponse>,
-    ) -> Result<Option<(JsonBytes, Vec<Cycle>)>, crate::rpc::RpcError> {
-        opt_resp
-            .map(|resp| match resp {
-                BlockResponse::Regular(block_view) => Ok((block_view.get_json_bytes()?, vec![])),
-                BlockResponse::WithCycles(block_cycles) => {
-                    let cycles = transform_cycles(block_cycles.cycles);
-                    Ok((block_cycles.block.get_json_bytes()?, cycles))
-                }
-            })
-            .transpose()
+        transform_block_view_with_cycle(res)
     }
 
     pub fn get_packed_block_with_cycles(
@@ -164,7 +274,7 @@ impl CkbRpcClient {
             "get_block",
             (hash, Some(Uint32::from(0u32)), true),
         )?;
-        Self::blockresponse2bytes(res)
+        blockresponse2bytes(res)
     }
 
     /// Same as get_block_by_number except with parameter with_cycles and return BlockResponse
@@ -181,7 +291,7 @@ impl CkbRpcClient {
     ) -> Result<Option<(BlockView, Vec<Cycle>)>, crate::rpc::RpcError> {
         let res = self
             .post::<_, Option<BlockResponse>>("get_block_by_number", (number, None::<u32>, true))?;
-        Self::transform_block_view_with_cycle(res)
+        transform_block_view_with_cycle(res)
     }
 
     pub fn get_packed_block_by_number_with_cycles(
@@ -192,7 +302,7 @@ impl CkbRpcClient {
             "get_block_by_number",
             (number, Some(Uint32::from(0u32)), true),
         )?;
-        Self::blockresponse2bytes(res)
+        blockresponse2bytes(res)
     }
 
     pub fn get_packed_header(&self, hash: H256) -> Result<Option<JsonBytes>, crate::rpc::RpcError> {
@@ -274,4 +384,275 @@ impl CkbRpcClient {
     ) -> Result<Option<JsonBytes>, crate::rpc::RpcError> {
         self.post::<_, Option<JsonBytes>>("get_fork_block", (block_hash, Some(Uint32::from(0u32))))
     }
+
+    /// Get the epoch (with fraction) of the tip header
+    pub fn get_tip_epoch(
+        &self,
+    ) -> Result<ckb_types::core::EpochNumberWithFraction, crate::rpc::RpcError> {
+        let tip_header = self.get_tip_header()?;
+        Ok(ckb_types::core::EpochNumberWithFraction::from_full_value(
+            tip_header.inner.epoch.value(),
+        ))
+    }
+}
+
+#[cfg(feature = "async-rpc")]
+impl AsyncCkbRpcClient {
+    pub async fn get_packed_block(&self, hash: H256) -> Result<Option<JsonBytes>, crate::RpcError> {
+        self.post("get_block", (hash, Some(Uint32::from(0u32))))
+            .await
+    }
+
+    /// Same as get_block except with parameter with_cycles and return BlockResponse
+    pub async fn get_block_with_cycles(
+        &self,
+        hash: H256,
+    ) -> Result<Option<(BlockView, Vec<Cycle>)>, crate::rpc::RpcError> {
+        let res = self
+            .post::<_, Option<BlockResponse>>("get_block", (hash, None::<u32>, true))
+            .await?;
+        transform_block_view_with_cycle(res)
+    }
+
+    pub async fn get_packed_block_with_cycles(
+        &self,
+        hash: H256,
+    ) -> Result<Option<(JsonBytes, Vec<Cycle>)>, crate::rpc::RpcError> {
+        let res = self
+            .post::<_, Option<BlockResponse>>("get_block", (hash, Some(Uint32::from(0u32)), true))
+            .await?;
+        blockresponse2bytes(res)
+    }
+
+    /// Same as get_block_by_number except with parameter with_cycles and return BlockResponse
+    pub async fn get_packed_block_by_number(
+        &self,
+        number: BlockNumber,
+    ) -> Result<Option<JsonBytes>, crate::rpc::RpcError> {
+        self.post("get_block_by_number", (number, Some(Uint32::from(0u32))))
+            .await
+    }
+
+    pub async fn get_block_by_number_with_cycles(
+        &self,
+        number: BlockNumber,
+    ) -> Result<Option<(BlockView, Vec<Cycle>)>, crate::rpc::RpcError> {
+        let res = self
+            .post::<_, Option<BlockResponse>>("get_block_by_number", (number, None::<u32>, true))
+            .await?;
+        transform_block_view_with_cycle(res)
+    }
+
+    pub async fn get_packed_block_by_number_with_cycles(
+        &self,
+        number: BlockNumber,
+    ) -> Result<Option<(JsonBytes, Vec<Cycle>)>, crate::rpc::RpcError> {
+        let res = self
+            .post::<_, Option<BlockResponse>>(
+                "get_block_by_number",
+                (number, Some(Uint32::from(0u32)), true),
+            )
+            .await?;
+        blockresponse2bytes(res)
+    }
+
+    pub async fn get_packed_header(
+        &self,
+        hash: H256,
+    ) -> Result<Option<JsonBytes>, crate::rpc::RpcError> {
+        self.post::<_, Option<JsonBytes>>("get_header", (hash, Some(Uint32::from(0u32))))
+            .await
+    }
+
+    pub async fn get_packed_header_by_number(
+        &self,
+        number: BlockNumber,
+    ) -> Result<Option<JsonBytes>, crate::rpc::RpcError> {
+        self.post::<_, Option<JsonBytes>>(
+            "get_header_by_number",
+            (number, Some(Uint32::from(0u32))),
+        )
+        .await
+    }
+
+    // get transaction with only_committed=true
+    pub async fn get_only_committed_transaction(
+        &self,
+        hash: H256,
+    ) -> Result<TransactionWithStatusResponse, crate::rpc::RpcError> {
+        self.post::<_, TransactionWithStatusResponse>(
+            "get_transaction",
+            (hash, Some(Uint32::from(2u32)), true),
+        )
+        .await
+    }
+
+    // get transaction with verbosity=0
+    pub async fn get_packed_transaction(
+        &self,
+        hash: H256,
+    ) -> Result<TransactionWithStatusResponse, crate::rpc::RpcError> {
+        self.post::<_, TransactionWithStatusResponse>(
+            "get_transaction",
+            (hash, Some(Uint32::from(0u32))),
+        )
+        .await
+    }
+
+    // get transaction with verbosity=0 and only_committed=true
+    pub async fn get_only_committed_packed_transaction(
+        &self,
+        hash: H256,
+    ) -> Result<TransactionWithStatusResponse, crate::rpc::RpcError> {
+        self.post::<_, TransactionWithStatusResponse>(
+            "get_transaction",
+            (hash, Some(Uint32::from(0u32)), true),
+        )
+        .await
+    }
+
+    // get transaction with verbosity=1, so the result transaction field is None
+    pub async fn get_transaction_status(
+        &self,
+        hash: H256,
+    ) -> Result<TransactionWithStatusResponse, crate::rpc::RpcError> {
+        self.post::<_, TransactionWithStatusResponse>(
+            "get_transaction",
+            (hash, Some(Uint32::from(1u32))),
+        )
+        .await
+    }
+
+    // get transaction with verbosity=1 and only_committed=true, so the result transaction field is None
+    pub async fn get_only_committed_transaction_status(
+        &self,
+        hash: H256,
+    ) -> Result<TransactionWithStatusResponse, crate::rpc::RpcError> {
+        self.post::<_, TransactionWithStatusResponse>(
+            "get_transaction",
+            (hash, Some(Uint32::from(1u32)), true),
+        )
+        .await
+    }
+
+    pub async fn get_packed_tip_header(&self) -> Result<JsonBytes, crate::rpc::RpcError> {
+        self.post::<_, JsonBytes>("get_tip_header", (Some(Uint32::from(0u32)),))
+            .await
+    }
+
+    pub async fn get_packed_fork_block(
+        &self,
+        block_hash: H256,
+    ) -> Result<Option<JsonBytes>, crate::rpc::RpcError> {
+        self.post::<_, Option<JsonBytes>>("get_fork_block", (block_hash, Some(Uint32::from(0u32))))
+            .await
+    }
+}
+
+#[cfg(all(test, feature = "rpc"))]
+mod tests {
+    use ckb_jsonrpc_types::FeeRateStatistics;
+    use httpmock::prelude::*;
+
+    use super::*;
+    use crate::test_util::MockRpcResult;
+
+    #[test]
+    fn test_estimate_fee_rate() {
+        let server = MockServer::start();
+        let mut statistics = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("get_fee_rate_statics");
+            then.status(200).body(
+                MockRpcResult::new(Some(FeeRateStatistics {
+                    mean: 3000.into(),
+                    median: 2000.into(),
+                }))
+                .to_json(),
+            );
+        });
+        let client = CkbRpcClient::new(&server.base_url());
+        assert_eq!(client.estimate_fee_rate(1).unwrap(), 3000);
+        assert_eq!(client.estimate_fee_rate(10).unwrap(), 2000);
+        statistics.delete();
+
+        let mut statistics = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("get_fee_rate_statics");
+            then.status(200)
+                .body(MockRpcResult::<Option<FeeRateStatistics>>::new(None).to_json());
+        });
+        assert_eq!(client.estimate_fee_rate(1).unwrap(), MIN_FEE_RATE);
+        statistics.delete();
+
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("get_fee_rate_statics");
+            then.status(200).body(
+                serde_json::json!({
+                    "id": 42,
+                    "jsonrpc": "2.0",
+                    "error": { "code": -32601, "message": "Method not found" },
+                })
+                .to_string(),
+            );
+        });
+        assert_eq!(client.estimate_fee_rate(1).unwrap(), MIN_FEE_RATE);
+    }
+
+    #[test]
+    fn test_verify_tx_inclusion() {
+        let tx_hash = H256::from([1u8; 32]);
+        let server = MockServer::start();
+        let mock_rpc = |method: &str, result: serde_json::Value| {
+            let method = format!("\"{}\"", method);
+            server.mock(move |when, then| {
+                when.method(POST).path("/").body_contains(&method);
+                then.status(200).body(
+                    serde_json::json!({ "id": 42, "jsonrpc": "2.0", "result": result }).to_string(),
+                );
+            })
+        };
+        let tx_status = |status: &str| {
+            serde_json::json!({
+                "transaction": null,
+                "cycles": null,
+                "time_added_to_pool": null,
+                "tx_status": {
+                    "status": status,
+                    "block_number": null,
+                    "block_hash": null,
+                    "reason": null,
+                },
+                "fee": null,
+                "min_replace_fee": null,
+            })
+        };
+        let client = CkbRpcClient::new(&server.base_url());
+
+        let mut status = mock_rpc("get_transaction", tx_status("pending"));
+        let proof = mock_rpc(
+            "get_transaction_proof",
+            serde_json::json!({
+                "block_hash": H256::from([2u8; 32]),
+                "witnesses_root": H256::from([3u8; 32]),
+                "proof": { "indices": ["0x0"], "lemmas": [] },
+            }),
+        );
+        assert!(!verify_tx_inclusion(&tx_hash, &client).unwrap());
+        proof.assert_hits(0);
+        status.delete();
+
+        mock_rpc("get_transaction", tx_status("committed"));
+        let mut verify = mock_rpc("verify_transaction_proof", serde_json::json!([tx_hash]));
+        assert!(verify_tx_inclusion(&tx_hash, &client).unwrap());
+        proof.assert_hits(1);
+        verify.delete();
+
+        mock_rpc("verify_transaction_proof", serde_json::json!([]));
+        assert!(!verify_tx_inclusion(&tx_hash, &client).unwrap());
+    }
 }
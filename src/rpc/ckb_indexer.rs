@@ -18,6 +18,35 @@ pub struct SearchKey {
     pub group_by_transaction: Option<bool>,
 }
 
+impl SearchKey {
+    fn new(script: ckb_types::packed::Script, script_type: ScriptType) -> SearchKey {
+        SearchKey {
+            script: script.into(),
+            script_type,
+            script_search_mode: None,
+            filter: None,
+            with_data: None,
+            group_by_transaction: None,
+        }
+    }
+
+    /// Search the cells by the lock script
+    pub fn by_lock_script(lock_script: ckb_types::packed::Script) -> SearchKey {
+        SearchKey::new(lock_script, ScriptType::Lock)
+    }
+
+    /// Search the cells by the type script
+    pub fn by_type_script(type_script: ckb_types::packed::Script) -> SearchKey {
+        SearchKey::new(type_script, ScriptType::Type)
+    }
+
+    /// Set the filter, the old filter is replaced
+    pub fn with_filter(mut self, filter: SearchKeyFilter) -> SearchKey {
+        self.filter = Some(filter);
+        self
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum SearchMode {
@@ -49,8 +78,10 @@ impl From<CellQueryOptions> for SearchKey {
     fn from(opts: CellQueryOptions) -> SearchKey {
         let convert_range =
             |range: ValueRangeOption| [Uint64::from(range.start), Uint64::from(range.end)];
-        let filter = if opts.secondary_script.is_none()
-            && opts.secondary_script_len_range.is_none()
+        let secondary_script = opts.secondary_script_filter();
+        let secondary_script_len_range = opts.secondary_script_len_filter();
+        let filter = if secondary_script.is_none()
+            && secondary_script_len_range.is_none()
             && opts.data_len_range.is_none()
             && opts.capacity_range.is_none()
             && opts.block_range.is_none()
@@ -58,8 +89,8 @@ impl From<CellQueryOptions> for SearchKey {
             None
         } else {
             Some(SearchKeyFilter {
-                script: opts.secondary_script.map(|v| v.into()),
-                script_len_range: opts.secondary_script_len_range.map(convert_range),
+                script: secondary_script.map(|v| v.into()),
+                script_len_range: secondary_script_len_range.map(convert_range),
                 output_data: None,
                 output_data_filter_mode: None,
                 output_data_len_range: opts.data_len_range.map(convert_range),
@@ -78,6 +109,30 @@ impl From<CellQueryOptions> for SearchKey {
     }
 }
 
+/// Build the query from the search key, the `output_data` filter and the
+/// `group_by_transaction` option are not supported by the query and ignored.
+impl From<SearchKey> for CellQueryOptions {
+    fn from(key: SearchKey) -> CellQueryOptions {
+        let convert_range =
+            |range: [Uint64; 2]| ValueRangeOption::new(range[0].value(), range[1].value());
+        let primary_type = match key.script_type {
+            ScriptType::Lock => PrimaryScriptType::Lock,
+            ScriptType::Type => PrimaryScriptType::Type,
+        };
+        let mut query = CellQueryOptions::new(key.script.into(), primary_type);
+        query.script_search_mode = key.script_search_mode;
+        query.with_data = key.with_data;
+        if let Some(filter) = key.filter {
+            query.secondary_script = filter.script.map(Into::into);
+            query.secondary_script_len_range = filter.script_len_range.map(convert_range);
+            query.data_len_range = filter.output_data_len_range.map(convert_range);
+            query.capacity_range = filter.output_capacity_range.map(convert_range);
+            query.block_range = filter.block_range.map(convert_range);
+        }
+        query
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "snake_case")]
 pub enum ScriptType {
@@ -100,6 +155,22 @@ pub enum Order {
     Asc,
 }
 
+/// The version information of the ckb-indexer server
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct IndexerInfo {
+    pub version: String,
+    #[serde(default)]
+    pub db_info: Option<DbInfo>,
+}
+
+/// The database information reported by the ckb-indexer server, the fields vary among
+/// the ckb-indexer versions, so they are kept as is.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct DbInfo {
+    #[serde(flatten)]
+    pub fields: serde_json::Map<String, serde_json::Value>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Tip {
     pub block_hash: H256,
@@ -189,9 +260,272 @@ pub struct Pagination<T> {
     pub last_cursor: JsonBytes,
 }
 
-crate::jsonrpc!(pub struct IndexerRpcClient {
-    pub fn get_indexer_tip(&self) -> Option<Tip>;
-    pub fn get_cells(&self, search_key: SearchKey, order: Order, limit: Uint32, after: Option<JsonBytes>) -> Pagination<Cell>;
-    pub fn get_transactions(&self, search_key: SearchKey, order: Order, limit: Uint32, after: Option<JsonBytes>) -> Pagination<Tx>;
-    pub fn get_cells_capacity(&self, search_key: SearchKey) -> Option<CellsCapacity>;
-});
+// The rpc methods of ckb-indexer, shared by the blocking and the async clients
+#[allow(unused_macros)]
+macro_rules! indexer_rpc_client {
+    ($jsonrpc:ident, $name:ident) => {
+        crate::$jsonrpc!(pub struct $name {
+            pub fn get_indexer_tip(&self) -> Option<Tip>;
+            pub fn get_indexer_info(&self) -> IndexerInfo;
+            pub fn get_cells(&self, search_key: SearchKey, order: Order, limit: Uint32, after: Option<JsonBytes>) -> Pagination<Cell>;
+            pub fn get_transactions(&self, search_key: SearchKey, order: Order, limit: Uint32, after: Option<JsonBytes>) -> Pagination<Tx>;
+            pub fn get_cells_capacity(&self, search_key: SearchKey) -> Option<CellsCapacity>;
+        });
+    };
+}
+
+#[cfg(feature = "rpc")]
+indexer_rpc_client!(jsonrpc, IndexerRpcClient);
+#[cfg(feature = "async-rpc")]
+indexer_rpc_client!(jsonrpc_async, AsyncIndexerRpcClient);
+
+#[cfg(feature = "rpc")]
+impl IndexerRpcClient {
+    /// Same as `get_transactions` with `group_by_transaction = false`, every object is
+    /// an input or output cell of the transaction.
+    pub fn get_transactions_ungrouped(
+        &self,
+        mut search_key: SearchKey,
+        order: Order,
+        limit: Uint32,
+        after: Option<JsonBytes>,
+    ) -> Result<Pagination<TxWithCell>, crate::rpc::RpcError> {
+        search_key.group_by_transaction = Some(false);
+        self.post(
+            "get_transactions",
+            crate::serialize_parameters!(search_key, order, limit, after,),
+        )
+    }
+
+    /// Same as `get_transactions` with `group_by_transaction = true`, every object is
+    /// a transaction with all its matched cells.
+    pub fn get_transactions_grouped(
+        &self,
+        mut search_key: SearchKey,
+        order: Order,
+        limit: Uint32,
+        after: Option<JsonBytes>,
+    ) -> Result<Pagination<TxWithCells>, crate::rpc::RpcError> {
+        search_key.group_by_transaction = Some(true);
+        self.post(
+            "get_transactions",
+            crate::serialize_parameters!(search_key, order, limit, after,),
+        )
+    }
+}
+
+/// Wait until the ckb-indexer synced with the ckb node, the async version of
+/// `DefaultCellCollector::check_ckb_chain`. It checks every 50ms for 100 times, the
+/// `sleep` function is provided by the caller (e.g. `tokio::time::sleep`), so the
+/// async runtime is not picked here.
+#[cfg(feature = "async-rpc")]
+pub async fn wait_indexer_synced<S, F>(
+    ckb_client: &crate::rpc::AsyncCkbRpcClient,
+    indexer_client: &AsyncIndexerRpcClient,
+    acceptable_indexer_leftbehind: u64,
+    sleep: S,
+) -> Result<(), crate::rpc::RpcError>
+where
+    S: Fn(std::time::Duration) -> F,
+    F: std::future::Future<Output = ()>,
+{
+    let tip_number = ckb_client.get_tip_block_number().await?;
+    for _ in 0..100 {
+        match indexer_client.get_indexer_tip().await? {
+            Some(Tip { block_number, .. }) => {
+                if tip_number.value() > block_number.value() + acceptable_indexer_leftbehind {
+                    sleep(std::time::Duration::from_millis(50)).await;
+                } else {
+                    return Ok(());
+                }
+            }
+            None => {
+                return Err(anyhow::anyhow!("ckb-indexer server not synced").into());
+            }
+        }
+    }
+    Err(anyhow::anyhow!(
+        "ckb-indexer server inconsistent with currently connected ckb node or not synced!"
+    )
+    .into())
+}
+
+/// The rpc clients serving `get_cells_capacity`, both the standalone ckb-indexer and
+/// the ckb node with the built-in indexer are supported.
+#[cfg(feature = "rpc")]
+pub trait CellsCapacityProvider {
+    fn cells_capacity(&self, search_key: SearchKey) -> Result<u64, crate::rpc::RpcError>;
+}
+
+#[cfg(feature = "rpc")]
+impl CellsCapacityProvider for IndexerRpcClient {
+    fn cells_capacity(&self, search_key: SearchKey) -> Result<u64, crate::rpc::RpcError> {
+        Ok(self
+            .get_cells_capacity(search_key)?
+            .map(|cells_capacity| cells_capacity.capacity.value())
+            .unwrap_or(0))
+    }
+}
+
+#[cfg(feature = "rpc")]
+impl CellsCapacityProvider for crate::rpc::CkbRpcClient {
+    fn cells_capacity(&self, search_key: SearchKey) -> Result<u64, crate::rpc::RpcError> {
+        Ok(self
+            .get_cells_capacity(search_key)?
+            .map(|cells_capacity| cells_capacity.capacity.value())
+            .unwrap_or(0))
+    }
+}
+
+/// The capacity (in shannons) of all live cells of a lock script
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct Balance {
+    pub total: u64,
+    /// The capacity of cells with type script or data, they can not be spent as plain capacity
+    pub occupied: u64,
+    /// The capacity of cells without type script and data
+    pub free: u64,
+}
+
+/// Query the balance of the lock script by two `get_cells_capacity` requests instead of
+/// paging through all the cells.
+#[cfg(feature = "rpc")]
+pub fn get_balance(
+    provider: &dyn CellsCapacityProvider,
+    lock_script: ckb_types::packed::Script,
+) -> Result<Balance, crate::rpc::RpcError> {
+    let mut query = CellQueryOptions::new_lock(lock_script);
+    query.script_search_mode = Some(SearchMode::Exact);
+    let total = provider.cells_capacity(SearchKey::from(query.clone()))?;
+    query.secondary_script_len_range = Some(ValueRangeOption::new_exact(0));
+    query.data_len_range = Some(ValueRangeOption::new_exact(0));
+    let free = provider.cells_capacity(SearchKey::from(query))?;
+    Ok(Balance {
+        total,
+        occupied: total.saturating_sub(free),
+        free,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use ckb_types::{packed, prelude::*};
+
+    use crate::traits::ScriptMatcher;
+
+    use super::*;
+
+    #[test]
+    fn test_search_key_constructors() {
+        let script = packed::Script::new_builder()
+            .args(vec![1u8; 20].pack())
+            .build();
+        let search_key = SearchKey::by_type_script(script.clone());
+        let json = serde_json::to_value(&search_key).unwrap();
+        assert_eq!(json["script_type"], "type");
+        assert!(json["filter"].is_null());
+
+        let filter = SearchKeyFilter {
+            block_range: Some([1.into(), 10.into()]),
+            ..Default::default()
+        };
+        let search_key = SearchKey::by_lock_script(script).with_filter(filter);
+        let json = serde_json::to_value(&search_key).unwrap();
+        assert_eq!(json["script_type"], "lock");
+        assert_eq!(
+            json["filter"]["block_range"],
+            serde_json::json!(["0x1", "0xa"])
+        );
+    }
+
+    #[test]
+    fn test_search_key_to_query() {
+        let script = packed::Script::new_builder()
+            .args(vec![1u8; 20].pack())
+            .build();
+        let mut query = CellQueryOptions::new_type(script.clone());
+        query.secondary_script = Some(script.clone());
+        query.data_len_range = Some(ValueRangeOption::new_exact(0));
+        query.block_range = Some(ValueRangeOption::new(1, 10));
+        query.script_search_mode = Some(SearchMode::Exact);
+        query.with_data = Some(false);
+        assert_eq!(
+            CellQueryOptions::from(SearchKey::from(query.clone())),
+            query
+        );
+
+        let query = CellQueryOptions::new_lock(packed::Script::default());
+        assert_eq!(
+            CellQueryOptions::from(SearchKey::from(query.clone())),
+            query
+        );
+
+        // the args prefix is sent as the args of the filter script
+        let mut query = CellQueryOptions::new_lock(packed::Script::default());
+        query.secondary_script = Some(script);
+        query.filter_script_args_prefix = Some(vec![1u8; 4].into());
+        let search_key = SearchKey::from(query);
+        let filter_script = packed::Script::from(search_key.filter.unwrap().script.unwrap());
+        assert_eq!(filter_script.args().raw_data().as_ref(), &[1u8; 4]);
+
+        // the type side is primary without the lock matcher
+        let query = CellQueryOptions::new_with_matchers(
+            None,
+            Some(ScriptMatcher::new_exact(filter_script)),
+        )
+        .unwrap();
+        let search_key = SearchKey::from(query);
+        assert!(matches!(search_key.script_type, ScriptType::Type));
+        assert_eq!(search_key.script_search_mode, Some(SearchMode::Exact));
+        assert!(search_key.filter.is_none());
+    }
+
+    #[cfg(feature = "rpc")]
+    #[test]
+    fn test_get_balance() {
+        use httpmock::prelude::*;
+
+        use crate::{rpc::CkbRpcClient, test_util::MockRpcResult};
+
+        let server = MockServer::start();
+        let cells_capacity = |capacity: u64| CellsCapacity {
+            capacity: capacity.into(),
+            block_hash: H256::default(),
+            block_number: 1.into(),
+        };
+        let free_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("get_cells_capacity")
+                .body_contains("output_data_len_range");
+            then.status(200)
+                .body(MockRpcResult::new(Some(cells_capacity(300))).to_json());
+        });
+        let total_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("get_cells_capacity")
+                .body_contains("\"exact\"");
+            then.status(200)
+                .body(MockRpcResult::new(Some(cells_capacity(1000))).to_json());
+        });
+
+        let lock_script = packed::Script::new_builder()
+            .args(vec![1u8; 20].pack())
+            .build();
+        let expected = Balance {
+            total: 1000,
+            occupied: 700,
+            free: 300,
+        };
+        let indexer_client = IndexerRpcClient::new(&server.base_url());
+        assert_eq!(
+            get_balance(&indexer_client, lock_script.clone()).unwrap(),
+            expected
+        );
+        let ckb_client = CkbRpcClient::new(&server.base_url());
+        assert_eq!(get_balance(&ckb_client, lock_script).unwrap(), expected);
+        free_mock.assert_hits(2);
+        total_mock.assert_hits(2);
+    }
+}
@@ -1,9 +1,12 @@
 use serde::{Deserialize, Serialize};
 
 use ckb_jsonrpc_types::{
-    BlockNumber, BlockView, Cycle, EstimateCycles, HeaderView, JsonBytes, NodeAddress,
-    RemoteNodeProtocol, Script, Transaction, TransactionView, TxStatus, Uint32, Uint64,
+    BlockNumber, Cycle, HeaderView, NodeAddress, RemoteNodeProtocol, Script, TransactionView,
+    TxStatus, Uint32, Uint64,
 };
+#[cfg(feature = "rpc")]
+use ckb_jsonrpc_types::{BlockView, EstimateCycles, JsonBytes, Transaction};
+#[cfg(feature = "rpc")]
 use ckb_types::H256;
 
 pub use crate::rpc::ckb_indexer::{
@@ -70,6 +73,31 @@ pub struct TxWithCells {
     cells: Vec<(CellType, Uint32)>,
 }
 
+// The light client returns the full transaction, only the hash is kept
+impl From<Tx> for crate::rpc::ckb_indexer::Tx {
+    fn from(tx: Tx) -> crate::rpc::ckb_indexer::Tx {
+        match tx {
+            Tx::Ungrouped(tx) => {
+                crate::rpc::ckb_indexer::Tx::Ungrouped(crate::rpc::ckb_indexer::TxWithCell {
+                    tx_hash: tx.transaction.hash,
+                    block_number: tx.block_number,
+                    tx_index: tx.tx_index,
+                    io_index: tx.io_index,
+                    io_type: tx.io_type,
+                })
+            }
+            Tx::Grouped(tx) => {
+                crate::rpc::ckb_indexer::Tx::Grouped(crate::rpc::ckb_indexer::TxWithCells {
+                    tx_hash: tx.transaction.hash,
+                    block_number: tx.block_number,
+                    tx_index: tx.tx_index,
+                    cells: tx.cells,
+                })
+            }
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct RemoteNode {
     /// The remote node version.
@@ -136,6 +164,7 @@ pub struct PeerSyncState {
     pub proved_best_known_header: Option<HeaderView>,
 }
 
+#[cfg(feature = "rpc")]
 crate::jsonrpc!(pub struct LightClientRpcClient {
     // BlockFilter
     pub fn set_scripts(&self, scripts: Vec<ScriptStatus>, command: Option<SetScriptsCommand>) -> ();
@@ -0,0 +1,195 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+use super::RpcError;
+
+/// The default time a failed endpoint is skipped before it's retried
+pub const DEFAULT_ENDPOINT_COOLDOWN: Duration = Duration::from_secs(30);
+
+struct EndpointState {
+    // the index of the last healthy endpoint
+    current: usize,
+    // the endpoints are skipped until the instant
+    dead_until: Vec<Option<Instant>>,
+}
+
+/// A client wrapping an ordered list of endpoints (e.g. redundant ckb nodes), the
+/// request is sent to the last healthy endpoint first, and will fail over to the next
+/// one when the error is a connection error or timeout. A failed endpoint is skipped
+/// until the cooldown passed, unless all the endpoints are failed.
+///
+/// The health state is shared between the clones.
+pub struct MultiEndpointClient<C> {
+    endpoints: Vec<C>,
+    state: Arc<Mutex<EndpointState>>,
+    cooldown: Duration,
+}
+
+impl<C: Clone> Clone for MultiEndpointClient<C> {
+    fn clone(&self) -> Self {
+        MultiEndpointClient {
+            endpoints: self.endpoints.clone(),
+            state: Arc::clone(&self.state),
+            cooldown: self.cooldown,
+        }
+    }
+}
+
+impl<C> MultiEndpointClient<C> {
+    /// Create the client from endpoints ordered by priority.
+    ///
+    /// Panics if `endpoints` is empty.
+    pub fn new(endpoints: Vec<C>) -> MultiEndpointClient<C> {
+        assert!(!endpoints.is_empty(), "at least one endpoint is required");
+        let state = EndpointState {
+            current: 0,
+            dead_until: vec![None; endpoints.len()],
+        };
+        MultiEndpointClient {
+            endpoints,
+            state: Arc::new(Mutex::new(state)),
+            cooldown: DEFAULT_ENDPOINT_COOLDOWN,
+        }
+    }
+
+    /// Create the client from urls ordered by priority, e.g.
+    /// `MultiEndpointClient::from_urls(&urls, CkbRpcClient::new)`.
+    ///
+    /// Panics if `urls` is empty.
+    pub fn from_urls<F: Fn(&str) -> C>(urls: &[&str], build_client: F) -> MultiEndpointClient<C> {
+        Self::new(urls.iter().map(|url| build_client(url)).collect())
+    }
+
+    /// The time a failed endpoint is skipped (default = 30s)
+    pub fn cooldown(&self) -> Duration {
+        self.cooldown
+    }
+    /// Set the time a failed endpoint is skipped
+    pub fn set_cooldown(&mut self, cooldown: Duration) {
+        self.cooldown = cooldown;
+    }
+
+    pub fn endpoints(&self) -> &[C] {
+        &self.endpoints
+    }
+
+    /// The index of the endpoint which will be tried first
+    pub fn current_index(&self) -> usize {
+        self.state.lock().current
+    }
+
+    /// The endpoint which will be tried first
+    pub fn current(&self) -> &C {
+        &self.endpoints[self.current_index()]
+    }
+
+    // The last healthy endpoint first, then the others by priority, the endpoints
+    // still in cooldown are tried at last.
+    fn candidates(&self) -> Vec<usize> {
+        let state = self.state.lock();
+        let now = Instant::now();
+        let len = self.endpoints.len();
+        let (alive, dead): (Vec<usize>, Vec<usize>) = std::iter::once(state.current)
+            .chain((0..len).filter(|index| *index != state.current))
+            .partition(|index| {
+                state.dead_until[*index]
+                    .map(|until| until <= now)
+                    .unwrap_or(true)
+            });
+        alive.into_iter().chain(dead).collect()
+    }
+
+    fn mark_healthy(&self, index: usize) {
+        let mut state = self.state.lock();
+        state.current = index;
+        state.dead_until[index] = None;
+    }
+
+    fn mark_dead(&self, index: usize) {
+        let mut state = self.state.lock();
+        state.dead_until[index] = Some(Instant::now() + self.cooldown);
+    }
+
+    /// Call `f` with the endpoints until it succeeded, it fails over to the next endpoint
+    /// only when `should_failover` returns true for the error, and the last error is
+    /// returned when all the endpoints are failed.
+    ///
+    /// The whole `f` is retried on the next endpoint, so a multi-request procedure
+    /// (e.g. paging) should be restarted from scratch inside `f`.
+    pub fn call_with<T, E, P, F>(&self, should_failover: P, mut f: F) -> Result<T, E>
+    where
+        P: Fn(&E) -> bool,
+        F: FnMut(&C) -> Result<T, E>,
+    {
+        let mut last_err = None;
+        for index in self.candidates() {
+            match f(&self.endpoints[index]) {
+                Ok(value) => {
+                    self.mark_healthy(index);
+                    return Ok(value);
+                }
+                Err(err) if should_failover(&err) => {
+                    log::warn!("endpoint #{} failed, try next one", index);
+                    self.mark_dead(index);
+                    last_err = Some(err);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Err(last_err.expect("at least one endpoint"))
+    }
+
+    /// Call `f` with the endpoints, fail over on connection errors and timeouts.
+    pub fn call<T, F>(&self, f: F) -> Result<T, RpcError>
+    where
+        F: FnMut(&C) -> Result<T, RpcError>,
+    {
+        self.call_with(RpcError::is_connection_error, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::CkbRpcClient;
+    use crate::test_util::MockRpcResult;
+    use httpmock::prelude::*;
+
+    #[test]
+    fn test_failover() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("get_tip_block_number");
+            then.status(200)
+                .body(MockRpcResult::new(ckb_jsonrpc_types::Uint64::from(100)).to_json());
+        });
+        // nothing is listening on port 1
+        let dead_url = "http://127.0.0.1:1";
+        let client =
+            MultiEndpointClient::from_urls(&[dead_url, &server.base_url()], CkbRpcClient::new);
+        assert_eq!(client.current_index(), 0);
+        let tip = client.call(|c| c.get_tip_block_number()).unwrap();
+        assert_eq!(tip.value(), 100);
+        assert_eq!(client.current_index(), 1);
+
+        // the dead endpoint is in cooldown, the healthy one is used first
+        let mut tried = Vec::new();
+        let tip = client
+            .call(|c| {
+                tried.push(c.url.to_string());
+                c.get_tip_block_number()
+            })
+            .unwrap();
+        assert_eq!(tip.value(), 100);
+        assert_eq!(tried.len(), 1);
+
+        // all the endpoints are failed
+        let client = MultiEndpointClient::from_urls(&[dead_url], CkbRpcClient::new);
+        let err = client.call(|c| c.get_tip_block_number()).unwrap_err();
+        assert!(err.is_connection_error());
+    }
+}
@@ -0,0 +1,203 @@
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde_json::Value;
+
+use crate::rpc::RpcError;
+
+/// Hooks called around every request of the rpc clients, the interceptors are
+/// registered when constructing the client (see `new_with_interceptors`) and
+/// called in the registered order.
+pub trait RpcInterceptor: Send + Sync {
+    /// Called before the request is sent, the params can be modified.
+    fn before(&self, _method: &str, _params: &mut Value) {}
+
+    /// Called before the request is sent to add extra http headers.
+    fn headers(&self, _method: &str, _headers: &mut HeaderMap) {}
+
+    /// Called after the response is received (or the request is failed), the
+    /// result is the raw json value before deserializing.
+    fn after(&self, _method: &str, _result: &Result<Value, RpcError>, _elapsed: Duration) {}
+}
+
+/// Log every request and its result and latency.
+#[derive(Debug, Clone)]
+pub struct LoggingInterceptor {
+    level: log::Level,
+}
+
+impl LoggingInterceptor {
+    pub fn new(level: log::Level) -> LoggingInterceptor {
+        LoggingInterceptor { level }
+    }
+}
+
+impl Default for LoggingInterceptor {
+    fn default() -> LoggingInterceptor {
+        LoggingInterceptor::new(log::Level::Debug)
+    }
+}
+
+impl RpcInterceptor for LoggingInterceptor {
+    fn before(&self, method: &str, params: &mut Value) {
+        log::log!(
+            self.level,
+            "rpc request: method={}, params={}",
+            method,
+            params
+        );
+    }
+
+    fn after(&self, method: &str, result: &Result<Value, RpcError>, elapsed: Duration) {
+        match result {
+            Ok(_) => log::log!(
+                self.level,
+                "rpc response: method={}, elapsed={:?}",
+                method,
+                elapsed
+            ),
+            Err(err) => log::warn!(
+                "rpc failed: method={}, elapsed={:?}, error={}",
+                method,
+                elapsed,
+                err
+            ),
+        }
+    }
+}
+
+/// Add the same http headers to every request, e.g. the api key of a gateway.
+#[derive(Debug, Clone, Default)]
+pub struct StaticHeadersInterceptor {
+    headers: HeaderMap,
+}
+
+impl StaticHeadersInterceptor {
+    pub fn new(headers: HeaderMap) -> StaticHeadersInterceptor {
+        StaticHeadersInterceptor { headers }
+    }
+
+    /// Add a header, return error if the name or value is invalid.
+    pub fn with_header(mut self, name: &str, value: &str) -> Result<Self, RpcError> {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|err| anyhow::anyhow!("invalid header name: {}", err))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|err| anyhow::anyhow!("invalid header value: {}", err))?;
+        self.headers.insert(name, value);
+        Ok(self)
+    }
+}
+
+impl RpcInterceptor for StaticHeadersInterceptor {
+    fn headers(&self, _method: &str, headers: &mut HeaderMap) {
+        for (name, value) in &self.headers {
+            headers.insert(name.clone(), value.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use httpmock::prelude::*;
+
+    use super::*;
+    use crate::{rpc::CkbRpcClient, rpc::IndexerRpcClient, test_util::MockRpcResult};
+
+    #[derive(Default)]
+    struct RecordInterceptor {
+        calls: Mutex<Vec<(String, bool)>>,
+    }
+
+    impl RpcInterceptor for RecordInterceptor {
+        fn before(&self, method: &str, params: &mut Value) {
+            if method == "get_block_hash" {
+                *params = serde_json::json!(["0x2"]);
+            }
+        }
+
+        fn after(&self, method: &str, result: &Result<Value, RpcError>, _elapsed: Duration) {
+            self.calls
+                .lock()
+                .unwrap()
+                .push((method.to_string(), result.is_ok()));
+        }
+    }
+
+    #[test]
+    fn test_interceptor_hooks() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("get_block_hash")
+                .body_contains("0x2");
+            then.status(200)
+                .body(MockRpcResult::new(ckb_types::h256!("0x1")).to_json());
+        });
+        server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("get_indexer_tip");
+            then.status(500);
+        });
+
+        let recorder = Arc::new(RecordInterceptor::default());
+        let interceptors: Vec<Arc<dyn RpcInterceptor>> = vec![
+            Arc::new(LoggingInterceptor::default()),
+            Arc::clone(&recorder) as _,
+        ];
+        let client = CkbRpcClient::new_with_interceptors(&server.base_url(), interceptors.clone());
+        // the params are rewritten by the interceptor
+        assert_eq!(
+            client.get_block_hash(1.into()).unwrap(),
+            Some(ckb_types::h256!("0x1"))
+        );
+        mock.assert_hits(1);
+        // the interceptors are kept when cloning
+        client.clone().get_block_hash(1.into()).unwrap();
+        mock.assert_hits(2);
+
+        let indexer_client =
+            IndexerRpcClient::new_with_interceptors(&server.base_url(), interceptors);
+        assert!(indexer_client.get_indexer_tip().is_err());
+        assert_eq!(
+            *recorder.calls.lock().unwrap(),
+            vec![
+                ("get_block_hash".to_string(), true),
+                ("get_block_hash".to_string(), true),
+                ("get_indexer_tip".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_static_headers_interceptor() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .header("x-api-key", "secret")
+                .header("x-request-source", "sdk");
+            then.status(200)
+                .body(MockRpcResult::new(ckb_jsonrpc_types::BlockNumber::from(10)).to_json());
+        });
+
+        let interceptor = StaticHeadersInterceptor::default()
+            .with_header("x-api-key", "secret")
+            .unwrap()
+            .with_header("x-request-source", "sdk")
+            .unwrap();
+        assert!(StaticHeadersInterceptor::default()
+            .with_header("invalid name", "value")
+            .is_err());
+        let client =
+            CkbRpcClient::new_with_interceptors(&server.base_url(), vec![Arc::new(interceptor)]);
+        assert_eq!(client.get_tip_block_number().unwrap().value(), 10);
+        mock.assert_hits(1);
+
+        // no headers without the interceptor
+        assert!(CkbRpcClient::new(&server.base_url())
+            .get_tip_block_number()
+            .is_err());
+    }
+}
@@ -0,0 +1,267 @@
+use ckb_jsonrpc_types::{
+    BlockView, CellWithStatus, Consensus, EpochView, HeaderView, JsonBytes, OutPoint,
+    ResponseFormat, Transaction, TransactionWithStatusResponse, Uint32,
+};
+use ckb_types::H256;
+
+use super::{
+    ckb_indexer::{Cell, CellsCapacity, Order, Pagination, SearchKey, Tip, Tx},
+    CkbRpcClient, LightClientRpcClient, RpcError,
+};
+
+/// The rpc methods shared by the ckb node (with the indexer module enabled) and the ckb
+/// light client, the return types follow the ckb node. The methods only provided by the
+/// ckb node return `RpcError::Unsupported` by default.
+///
+/// `DefaultTransactionDependencyProvider::from_rpc_interface` and
+/// `DefaultCellCollector::from_rpc_interface` accept any implementation, e.g. a
+/// `LightClientRpcClient`.
+pub trait CkbRpcInterface {
+    fn get_tip_header(&self) -> Result<HeaderView, RpcError>;
+    fn get_header(&self, hash: H256) -> Result<Option<HeaderView>, RpcError>;
+    fn get_transaction(
+        &self,
+        hash: H256,
+    ) -> Result<Option<TransactionWithStatusResponse>, RpcError>;
+    fn send_transaction(&self, tx: Transaction) -> Result<H256, RpcError>;
+    fn get_cells(
+        &self,
+        search_key: SearchKey,
+        order: Order,
+        limit: Uint32,
+        after: Option<JsonBytes>,
+    ) -> Result<Pagination<Cell>, RpcError>;
+    fn get_transactions(
+        &self,
+        search_key: SearchKey,
+        order: Order,
+        limit: Uint32,
+        after: Option<JsonBytes>,
+    ) -> Result<Pagination<Tx>, RpcError>;
+    fn get_cells_capacity(&self, search_key: SearchKey) -> Result<Option<CellsCapacity>, RpcError>;
+
+    fn get_tip_block_number(&self) -> Result<u64, RpcError> {
+        self.get_tip_header()
+            .map(|header| header.inner.number.value())
+    }
+
+    fn get_live_cell(
+        &self,
+        _out_point: OutPoint,
+        _with_data: bool,
+    ) -> Result<CellWithStatus, RpcError> {
+        Err(RpcError::Unsupported("get_live_cell".to_string()))
+    }
+
+    fn get_block(&self, _hash: H256) -> Result<Option<BlockView>, RpcError> {
+        Err(RpcError::Unsupported("get_block".to_string()))
+    }
+
+    fn get_header_by_number(&self, _number: u64) -> Result<Option<HeaderView>, RpcError> {
+        Err(RpcError::Unsupported("get_header_by_number".to_string()))
+    }
+
+    fn get_block_hash(&self, _number: u64) -> Result<Option<H256>, RpcError> {
+        Err(RpcError::Unsupported("get_block_hash".to_string()))
+    }
+
+    fn get_consensus(&self) -> Result<Consensus, RpcError> {
+        Err(RpcError::Unsupported("get_consensus".to_string()))
+    }
+
+    fn get_epoch_by_number(&self, _number: u64) -> Result<Option<EpochView>, RpcError> {
+        Err(RpcError::Unsupported("get_epoch_by_number".to_string()))
+    }
+
+    fn get_indexer_tip(&self) -> Result<Option<Tip>, RpcError> {
+        Err(RpcError::Unsupported("get_indexer_tip".to_string()))
+    }
+}
+
+impl CkbRpcInterface for CkbRpcClient {
+    fn get_tip_header(&self) -> Result<HeaderView, RpcError> {
+        CkbRpcClient::get_tip_header(self)
+    }
+    fn get_header(&self, hash: H256) -> Result<Option<HeaderView>, RpcError> {
+        CkbRpcClient::get_header(self, hash)
+    }
+    fn get_transaction(
+        &self,
+        hash: H256,
+    ) -> Result<Option<TransactionWithStatusResponse>, RpcError> {
+        CkbRpcClient::get_transaction(self, hash)
+    }
+    fn send_transaction(&self, tx: Transaction) -> Result<H256, RpcError> {
+        CkbRpcClient::send_transaction(self, tx, None)
+    }
+    fn get_cells(
+        &self,
+        search_key: SearchKey,
+        order: Order,
+        limit: Uint32,
+        after: Option<JsonBytes>,
+    ) -> Result<Pagination<Cell>, RpcError> {
+        CkbRpcClient::get_cells(self, search_key, order, limit, after)
+    }
+    fn get_transactions(
+        &self,
+        search_key: SearchKey,
+        order: Order,
+        limit: Uint32,
+        after: Option<JsonBytes>,
+    ) -> Result<Pagination<Tx>, RpcError> {
+        CkbRpcClient::get_transactions(self, search_key, order, limit, after)
+    }
+    fn get_cells_capacity(&self, search_key: SearchKey) -> Result<Option<CellsCapacity>, RpcError> {
+        CkbRpcClient::get_cells_capacity(self, search_key)
+    }
+    fn get_tip_block_number(&self) -> Result<u64, RpcError> {
+        CkbRpcClient::get_tip_block_number(self).map(|number| number.value())
+    }
+    fn get_live_cell(
+        &self,
+        out_point: OutPoint,
+        with_data: bool,
+    ) -> Result<CellWithStatus, RpcError> {
+        CkbRpcClient::get_live_cell(self, out_point, with_data)
+    }
+    fn get_block(&self, hash: H256) -> Result<Option<BlockView>, RpcError> {
+        CkbRpcClient::get_block(self, hash)
+    }
+    fn get_header_by_number(&self, number: u64) -> Result<Option<HeaderView>, RpcError> {
+        CkbRpcClient::get_header_by_number(self, number.into())
+    }
+    fn get_block_hash(&self, number: u64) -> Result<Option<H256>, RpcError> {
+        CkbRpcClient::get_block_hash(self, number.into())
+    }
+    fn get_consensus(&self) -> Result<Consensus, RpcError> {
+        CkbRpcClient::get_consensus(self)
+    }
+    fn get_epoch_by_number(&self, number: u64) -> Result<Option<EpochView>, RpcError> {
+        CkbRpcClient::get_epoch_by_number(self, number.into())
+    }
+    fn get_indexer_tip(&self) -> Result<Option<Tip>, RpcError> {
+        CkbRpcClient::get_indexer_tip(self)
+    }
+}
+
+impl CkbRpcInterface for LightClientRpcClient {
+    fn get_tip_header(&self) -> Result<HeaderView, RpcError> {
+        LightClientRpcClient::get_tip_header(self)
+    }
+    fn get_header(&self, hash: H256) -> Result<Option<HeaderView>, RpcError> {
+        LightClientRpcClient::get_header(self, hash)
+    }
+    fn get_transaction(
+        &self,
+        hash: H256,
+    ) -> Result<Option<TransactionWithStatusResponse>, RpcError> {
+        let tx_with_status = LightClientRpcClient::get_transaction(self, hash)?;
+        Ok(tx_with_status.map(|tx| TransactionWithStatusResponse {
+            transaction: tx.transaction.map(ResponseFormat::json),
+            cycles: tx.cycles,
+            time_added_to_pool: tx.time_added_to_pool,
+            tx_status: tx.tx_status,
+            fee: None,
+            min_replace_fee: None,
+        }))
+    }
+    fn send_transaction(&self, tx: Transaction) -> Result<H256, RpcError> {
+        LightClientRpcClient::send_transaction(self, tx)
+    }
+    fn get_cells(
+        &self,
+        search_key: SearchKey,
+        order: Order,
+        limit: Uint32,
+        after: Option<JsonBytes>,
+    ) -> Result<Pagination<Cell>, RpcError> {
+        LightClientRpcClient::get_cells(self, search_key, order, limit, after)
+    }
+    fn get_transactions(
+        &self,
+        search_key: SearchKey,
+        order: Order,
+        limit: Uint32,
+        after: Option<JsonBytes>,
+    ) -> Result<Pagination<Tx>, RpcError> {
+        let page = LightClientRpcClient::get_transactions(self, search_key, order, limit, after)?;
+        Ok(Pagination {
+            objects: page.objects.into_iter().map(Tx::from).collect(),
+            last_cursor: page.last_cursor,
+        })
+    }
+    fn get_cells_capacity(&self, search_key: SearchKey) -> Result<Option<CellsCapacity>, RpcError> {
+        LightClientRpcClient::get_cells_capacity(self, search_key).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ckb_types::{core::TransactionBuilder, prelude::*};
+    use httpmock::prelude::*;
+
+    use super::*;
+    use crate::test_util::MockRpcResult;
+
+    #[test]
+    fn test_light_client_interface() {
+        let tx = TransactionBuilder::default().build();
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("get_transactions");
+            then.status(200).body(
+                MockRpcResult::new(serde_json::json!({
+                    "objects": [{
+                        "transaction": ckb_jsonrpc_types::TransactionView::from(tx.clone()),
+                        "block_number": "0x5",
+                        "tx_index": "0x1",
+                        "io_index": "0x0",
+                        "io_type": "output",
+                    }],
+                    "last_cursor": "0x01",
+                }))
+                .to_json(),
+            );
+        });
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("get_cells_capacity");
+            then.status(200).body(
+                MockRpcResult::new(CellsCapacity {
+                    capacity: 100.into(),
+                    block_hash: Default::default(),
+                    block_number: 5.into(),
+                })
+                .to_json(),
+            );
+        });
+
+        let client = LightClientRpcClient::new(&server.base_url());
+        let client: &dyn CkbRpcInterface = &client;
+        let page = client
+            .get_transactions(
+                SearchKey::by_lock_script(Default::default()),
+                Order::Asc,
+                10.into(),
+                None,
+            )
+            .unwrap();
+        assert_eq!(page.objects.len(), 1);
+        assert_eq!(page.objects[0].tx_hash(), tx.hash().unpack());
+        let capacity = client
+            .get_cells_capacity(SearchKey::by_lock_script(Default::default()))
+            .unwrap();
+        assert_eq!(
+            capacity.map(|capacity| capacity.capacity.value()),
+            Some(100)
+        );
+        assert!(matches!(
+            client.get_block(Default::default()),
+            Err(RpcError::Unsupported(_))
+        ));
+    }
+}
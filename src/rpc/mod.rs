@@ -1,12 +1,44 @@
+#[cfg(any(feature = "rpc", feature = "async-rpc"))]
 mod ckb;
 pub mod ckb_indexer;
 pub mod ckb_light_client;
+#[cfg(feature = "rpc")]
+pub mod failover;
+#[cfg(feature = "rpc")]
+pub mod interceptor;
+#[cfg(feature = "rpc")]
+mod interface;
+#[cfg(feature = "rpc")]
+pub mod tracer;
+#[cfg(feature = "rpc")]
+pub mod transport;
+#[cfg(any(feature = "rpc", feature = "async-rpc"))]
+pub mod tx_pool;
 
 use anyhow::anyhow;
-pub use ckb::CkbRpcClient;
-pub use ckb_indexer::IndexerRpcClient;
+#[cfg(feature = "async-rpc")]
+pub use ckb::AsyncCkbRpcClient;
+#[cfg(feature = "rpc")]
+pub use ckb::{verify_tx_inclusion, CkbRpcClient, FAST_CONFIRMATION_BLOCKS, MIN_FEE_RATE};
+#[cfg(feature = "rpc")]
+pub use ckb_indexer::{get_balance, Balance, CellsCapacityProvider, IndexerRpcClient};
+#[cfg(feature = "async-rpc")]
+pub use ckb_indexer::{wait_indexer_synced, AsyncIndexerRpcClient};
 use ckb_jsonrpc_types::{JsonBytes, ResponseFormat};
+#[cfg(feature = "rpc")]
 pub use ckb_light_client::LightClientRpcClient;
+#[cfg(feature = "rpc")]
+pub use failover::MultiEndpointClient;
+#[cfg(feature = "rpc")]
+pub use interceptor::{LoggingInterceptor, RpcInterceptor, StaticHeadersInterceptor};
+#[cfg(feature = "rpc")]
+pub use interface::CkbRpcInterface;
+#[cfg(feature = "rpc")]
+pub use tracer::{ScriptTrace, TracerError, TransactionTracer};
+#[cfg(feature = "rpc")]
+pub use transport::JsonRpcTransport;
+#[cfg(feature = "rpc")]
+pub use tx_pool::{send_transaction_checked, PoolRejection, SendTxError, TxPoolEntryInfo};
 
 use thiserror::Error;
 
@@ -14,14 +46,29 @@ use thiserror::Error;
 pub enum RpcError {
     #[error("parse json error: `{0}`")]
     Json(#[from] serde_json::Error),
+    #[cfg(any(feature = "rpc", feature = "async-rpc"))]
     #[error("http error: `{0}`")]
     Http(#[from] reqwest::Error),
     #[error("jsonrpc error: `{0}`")]
     Rpc(#[from] jsonrpc_core::Error),
+    #[error("unsupported rpc method: `{0}`")]
+    Unsupported(String),
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
 
+impl RpcError {
+    /// If the error is caused by connection failure or timeout, the request may
+    /// succeed with another endpoint.
+    pub fn is_connection_error(&self) -> bool {
+        match self {
+            #[cfg(any(feature = "rpc", feature = "async-rpc"))]
+            RpcError::Http(err) => err.is_connect() || err.is_timeout(),
+            _ => false,
+        }
+    }
+}
+
 #[macro_export]
 macro_rules! jsonrpc {
     (
@@ -37,18 +84,54 @@ macro_rules! jsonrpc {
             pub client: reqwest::blocking::Client,
             pub url: reqwest::Url,
             pub id: std::sync::atomic::AtomicU64,
+            pub interceptors: Vec<std::sync::Arc<dyn $crate::rpc::RpcInterceptor>>,
+            /// The custom transport of the requests, `None` to post the requests to `url`
+            /// by `client`. Use `http_url()` to get the url actually used.
+            pub transport: Option<std::sync::Arc<dyn $crate::rpc::JsonRpcTransport>>,
         }
 
         impl Clone for $struct_name {
             fn clone(&self) -> Self {
-                Self::new(&self.url.to_string())
+                $struct_name {
+                    client: reqwest::blocking::Client::new(),
+                    url: self.url.clone(),
+                    id: 0.into(),
+                    interceptors: self.interceptors.clone(),
+                    transport: self.transport.clone(),
+                }
             }
         }
 
         impl $struct_name {
             pub fn new(uri: &str) -> Self {
+                Self::new_with_interceptors(uri, Vec::new())
+            }
+
+            /// Create the client with interceptors, they are called in order around every request.
+            pub fn new_with_interceptors(
+                uri: &str,
+                interceptors: Vec<std::sync::Arc<dyn $crate::rpc::RpcInterceptor>>,
+            ) -> Self {
                 let url = reqwest::Url::parse(uri).expect("ckb uri, e.g. \"http://127.0.0.1:8114\"");
-                $struct_name { url, id: 0.into(), client: reqwest::blocking::Client::new(), }
+                $struct_name { url, id: 0.into(), client: reqwest::blocking::Client::new(), interceptors, transport: None }
+            }
+
+            /// Create the client sending the requests by a custom transport, the `url` is
+            /// not used then.
+            pub fn new_with_transport(transport: std::sync::Arc<dyn $crate::rpc::JsonRpcTransport>) -> Self {
+                let mut client = Self::new("http://localhost");
+                client.transport = Some(transport);
+                client
+            }
+
+            /// The url the requests are posted to, `None` if the client uses a custom
+            /// transport (the `url` field is a placeholder then).
+            pub fn http_url(&self) -> Option<&reqwest::Url> {
+                if self.transport.is_some() {
+                    None
+                } else {
+                    Some(&self.url)
+                }
             }
 
             pub fn post<PARAM, RET>(&self, method:&str, params: PARAM)->Result<RET, $crate::rpc::RpcError>
@@ -56,7 +139,23 @@ macro_rules! jsonrpc {
                 PARAM:serde::ser::Serialize,
                 RET: serde::de::DeserializeOwned,
             {
-                let params = serde_json::to_value(params)?;
+                let mut params = serde_json::to_value(params)?;
+                for interceptor in &self.interceptors {
+                    interceptor.before(method, &mut params);
+                }
+                let started_at = std::time::Instant::now();
+                let result = match self.transport.as_ref() {
+                    Some(transport) => transport.request(method, params),
+                    None => self.post_value(method, params),
+                };
+                let elapsed = started_at.elapsed();
+                for interceptor in &self.interceptors {
+                    interceptor.after(method, &result, elapsed);
+                }
+                serde_json::from_value(result?).map_err(Into::into)
+            }
+
+            fn post_value(&self, method: &str, params: serde_json::Value) -> Result<serde_json::Value, $crate::rpc::RpcError> {
                 let id = self.id.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
 
                 let mut req_json = serde_json::Map::new();
@@ -65,8 +164,78 @@ macro_rules! jsonrpc {
                 req_json.insert("method".to_owned(), serde_json::json!(method));
                 req_json.insert("params".to_owned(), params);
 
-                let resp = self.client.post(self.url.clone()).json(&req_json).send()?;
+                let mut headers = reqwest::header::HeaderMap::new();
+                for interceptor in &self.interceptors {
+                    interceptor.headers(method, &mut headers);
+                }
+                let resp = self.client.post(self.url.clone()).headers(headers).json(&req_json).send()?;
                 let output = resp.json::<jsonrpc_core::response::Output>()?;
+                match output {
+                    jsonrpc_core::response::Output::Success(success) => Ok(success.result),
+                    jsonrpc_core::response::Output::Failure(failure) => {
+                        Err(failure.error.into())
+                    }
+                }
+            }
+
+            $(
+                $(#[$attr])*
+                pub fn $method(&$selff $(, $arg_name: $arg_ty)*) -> Result<$return_ty, $crate::rpc::RpcError> {
+                    let params = $crate::serialize_parameters!($($arg_name,)*);
+                    $selff.post(stringify!($method), params)
+                }
+            )*
+        }
+    )
+}
+
+/// Generate a native async rpc client over the async `reqwest::Client`, it must be
+/// polled inside an async runtime supported by reqwest (e.g. tokio).
+#[macro_export]
+macro_rules! jsonrpc_async {
+    (
+        $(#[$struct_attr:meta])*
+        pub struct $struct_name:ident {$(
+            $(#[$attr:meta])*
+            pub fn $method:ident(& $selff:ident $(, $arg_name:ident: $arg_ty:ty)*)
+                -> $return_ty:ty;
+        )*}
+    ) => (
+        $(#[$struct_attr])*
+        pub struct $struct_name {
+            pub client: reqwest::Client,
+            pub url: reqwest::Url,
+            pub id: std::sync::atomic::AtomicU64,
+        }
+
+        impl Clone for $struct_name {
+            fn clone(&self) -> Self {
+                Self::new(&self.url.to_string())
+            }
+        }
+
+        impl $struct_name {
+            pub fn new(uri: &str) -> Self {
+                let url = reqwest::Url::parse(uri).expect("ckb uri, e.g. \"http://127.0.0.1:8114\"");
+                $struct_name { url, id: 0.into(), client: reqwest::Client::new(), }
+            }
+
+            pub async fn post<PARAM, RET>(&self, method:&str, params: PARAM)->Result<RET, $crate::rpc::RpcError>
+            where
+                PARAM:serde::ser::Serialize,
+                RET: serde::de::DeserializeOwned,
+            {
+                let params = serde_json::to_value(params)?;
+                let id = self.id.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+
+                let mut req_json = serde_json::Map::new();
+                req_json.insert("id".to_owned(), serde_json::json!(id));
+                req_json.insert("jsonrpc".to_owned(), serde_json::json!("2.0"));
+                req_json.insert("method".to_owned(), serde_json::json!(method));
+                req_json.insert("params".to_owned(), params);
+
+                let resp = self.client.post(self.url.clone()).json(&req_json).send().await?;
+                let output = resp.json::<jsonrpc_core::response::Output>().await?;
                 match output {
                     jsonrpc_core::response::Output::Success(success) => {
                         serde_json::from_value(success.result).map_err(Into::into)
@@ -75,32 +244,13 @@ macro_rules! jsonrpc {
                         Err(failure.error.into())
                     }
                 }
-
             }
 
             $(
                 $(#[$attr])*
-                pub fn $method(&$selff $(, $arg_name: $arg_ty)*) -> Result<$return_ty, $crate::rpc::RpcError> {
-                    let method = String::from(stringify!($method));
+                pub async fn $method(&$selff $(, $arg_name: $arg_ty)*) -> Result<$return_ty, $crate::rpc::RpcError> {
                     let params = $crate::serialize_parameters!($($arg_name,)*);
-                    let id = $selff.id.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-
-                    let mut req_json = serde_json::Map::new();
-                    req_json.insert("id".to_owned(), serde_json::json!(id));
-                    req_json.insert("jsonrpc".to_owned(), serde_json::json!("2.0"));
-                    req_json.insert("method".to_owned(), serde_json::json!(method));
-                    req_json.insert("params".to_owned(), params);
-
-                    let resp = $selff.client.post($selff.url.clone()).json(&req_json).send()?;
-                    let output = resp.json::<jsonrpc_core::response::Output>()?;
-                    match output {
-                        jsonrpc_core::response::Output::Success(success) => {
-                            serde_json::from_value(success.result).map_err(Into::into)
-                        },
-                        jsonrpc_core::response::Output::Failure(failure) => {
-                            Err(failure.error.into())
-                        }
-                    }
+                    $selff.post(stringify!($method), params).await
                 }
             )*
         }
@@ -153,3 +303,104 @@ mod anyhow_tests {
         println!("{}", error)
     }
 }
+
+#[cfg(all(test, feature = "async-rpc"))]
+mod async_tests {
+    use super::{wait_indexer_synced, AsyncCkbRpcClient, AsyncIndexerRpcClient};
+    use crate::rpc::ckb_indexer::Tip;
+    use crate::test_util::MockRpcResult;
+    use ckb_types::prelude::*;
+    use httpmock::prelude::*;
+
+    #[test]
+    fn test_async_client() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("get_tip_block_number");
+            then.status(200)
+                .body(MockRpcResult::new(ckb_jsonrpc_types::Uint64::from(100)).to_json());
+        });
+        server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("get_indexer_tip");
+            then.status(200).body(
+                MockRpcResult::new(Tip {
+                    block_hash: Default::default(),
+                    block_number: 99.into(),
+                })
+                .to_json(),
+            );
+        });
+        let ckb_client = AsyncCkbRpcClient::new(server.base_url().as_str());
+        let indexer_client = AsyncIndexerRpcClient::new(server.base_url().as_str());
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let tip = ckb_client.get_tip_block_number().await.unwrap();
+            assert_eq!(tip.value(), 100);
+            let sleep = |_| async {};
+            wait_indexer_synced(&ckb_client, &indexer_client, 1, sleep)
+                .await
+                .unwrap();
+            assert!(wait_indexer_synced(&ckb_client, &indexer_client, 0, sleep)
+                .await
+                .is_err());
+        });
+    }
+
+    #[test]
+    fn test_async_block_with_cycles() {
+        let block = ckb_types::core::BlockBuilder::default().build();
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("get_block")
+                .body_contains("null,true]");
+            then.status(200).body(
+                MockRpcResult::new(ckb_jsonrpc_types::BlockResponse::with_cycles(
+                    ckb_jsonrpc_types::ResponseFormat::json(ckb_jsonrpc_types::BlockView::from(
+                        block.clone(),
+                    )),
+                    Some(vec![100.into()]),
+                ))
+                .to_json(),
+            );
+        });
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("get_block")
+                .body_contains("\"0x0\"]");
+            then.status(200).body(
+                MockRpcResult::new(ckb_jsonrpc_types::JsonBytes::from_bytes(
+                    block.data().as_bytes(),
+                ))
+                .to_json(),
+            );
+        });
+        let ckb_client = AsyncCkbRpcClient::new(server.base_url().as_str());
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let (block_view, cycles) = ckb_client
+                .get_block_with_cycles(block.hash().unpack())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(block_view.header.hash, block.hash().unpack());
+            assert_eq!(cycles, vec![100]);
+            let packed = ckb_client
+                .get_packed_block(block.hash().unpack())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(packed.into_bytes(), block.data().as_bytes());
+        });
+    }
+}
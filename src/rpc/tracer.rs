@@ -0,0 +1,263 @@
+use ckb_types::{core::TransactionView, packed::Byte32};
+use thiserror::Error;
+
+use super::{CkbRpcClient, RpcError};
+use crate::types::{ScriptGroup, ScriptGroupType};
+
+/// The cell whose script failed, parsed from the `source` of the script error
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ScriptSource {
+    Input(usize),
+    Output(usize),
+    Unknown,
+}
+
+/// Why the script failed, parsed from the `cause` of the script error
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ScriptFailureCause {
+    /// The script exited with a non-zero code
+    ExitCode(i8),
+    /// The scripts exceeded the cycles limit
+    ExceededMaximumCycles(u64),
+    /// Other errors, e.g. an error of ckb-vm
+    Other(String),
+}
+
+/// The first failed script reported by the node
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ScriptFailure {
+    pub source: ScriptSource,
+    pub group_type: ScriptGroupType,
+    pub cause: ScriptFailureCause,
+    /// The original error message
+    pub message: String,
+}
+
+impl ScriptFailure {
+    /// Parse the script failure from the message of a `TransactionFailedToVerify`
+    /// error, e.g.
+    /// `Verification failed Script(TransactionScriptError { source: Inputs[0].Lock,
+    /// cause: ValidationFailure: see error code 5 on page ... })`
+    pub fn from_message(message: &str) -> Option<ScriptFailure> {
+        let start = message.find("TransactionScriptError { source: ")?
+            + "TransactionScriptError { source: ".len();
+        let (source, cause) = message[start..].split_once(", cause: ")?;
+        let cause = cause.rsplit_once(" })").map_or(cause, |(cause, _)| cause);
+
+        let (source, group_type) = match source.split_once('.') {
+            Some((source, "Lock")) => (source, ScriptGroupType::Lock),
+            Some((source, "Type")) => (source, ScriptGroupType::Type),
+            _ => return None,
+        };
+        let parse_index = |prefix: &str| {
+            source
+                .strip_prefix(prefix)?
+                .strip_suffix(']')?
+                .parse::<usize>()
+                .ok()
+        };
+        let source = if let Some(index) = parse_index("Inputs[") {
+            ScriptSource::Input(index)
+        } else if let Some(index) = parse_index("Outputs[") {
+            ScriptSource::Output(index)
+        } else {
+            ScriptSource::Unknown
+        };
+
+        let parse_number = |prefix: &str| {
+            cause
+                .strip_prefix(prefix)?
+                .split_whitespace()
+                .next()?
+                .parse::<i64>()
+                .ok()
+        };
+        let cause = if let Some(code) = parse_number("ValidationFailure: see error code ") {
+            ScriptFailureCause::ExitCode(code as i8)
+        } else if let Some(cycles) = parse_number("ExceededMaximumCycles: expect cycles <= ") {
+            ScriptFailureCause::ExceededMaximumCycles(cycles as u64)
+        } else {
+            ScriptFailureCause::Other(cause.to_string())
+        };
+        Some(ScriptFailure {
+            source,
+            group_type,
+            cause,
+            message: message.to_string(),
+        })
+    }
+
+    /// If the failed script belongs to the script group
+    pub fn is_in_group(&self, script_group: &ScriptGroup) -> bool {
+        if self.group_type != script_group.group_type {
+            return false;
+        }
+        match self.source {
+            ScriptSource::Input(index) => script_group.input_indices.contains(&index),
+            ScriptSource::Output(index) => script_group.output_indices.contains(&index),
+            ScriptSource::Unknown => false,
+        }
+    }
+}
+
+/// The result of [`TransactionTracer::trace_failed_script`]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ScriptTrace {
+    /// The hash of the traced script
+    pub script_hash: Byte32,
+    /// The cycles consumed by all the scripts, only known when the transaction
+    /// passes the verification
+    pub cycles: Option<u64>,
+    /// The first failed script, `None` if the transaction passes the verification
+    pub failure: Option<ScriptFailure>,
+    /// If the first failed script belongs to the traced script group, the scripts
+    /// after the first failure are not executed by the node
+    pub failed_in_group: bool,
+}
+
+#[derive(Error, Debug)]
+pub enum TracerError {
+    #[error("rpc error: `{0}`")]
+    Rpc(#[from] RpcError),
+
+    #[error("the transaction failed for a reason other than the scripts: `{0}`")]
+    NotScriptFailure(String),
+}
+
+/// Trace the script failures of a transaction by the node.
+///
+/// The node only reports the first failed script with its exit code, the debug
+/// output (`ckb_debug`) of the scripts is only written to the log of the node, so
+/// the instructions executed are not available by the rpc.
+pub struct TransactionTracer;
+
+impl TransactionTracer {
+    /// Run the scripts of the transaction by the `estimate_cycles` rpc (the
+    /// replacement of the removed `dry_run_transaction` rpc) and check whether the
+    /// failed script belongs to `failing_script_group`.
+    pub fn trace_failed_script(
+        client: &CkbRpcClient,
+        tx: &TransactionView,
+        failing_script_group: &ScriptGroup,
+    ) -> Result<ScriptTrace, TracerError> {
+        let script_hash = failing_script_group.script.calc_script_hash();
+        match client.estimate_cycles(tx.data().into()) {
+            Ok(result) => Ok(ScriptTrace {
+                script_hash,
+                cycles: Some(result.cycles.value()),
+                failure: None,
+                failed_in_group: false,
+            }),
+            // TransactionFailedToVerify
+            Err(RpcError::Rpc(err)) if err.code.code() == -302 => {
+                let failure = ScriptFailure::from_message(&err.message)
+                    .ok_or(TracerError::NotScriptFailure(err.message))?;
+                Ok(ScriptTrace {
+                    script_hash,
+                    cycles: None,
+                    failed_in_group: failure.is_in_group(failing_script_group),
+                    failure: Some(failure),
+                })
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ckb_types::{core::TransactionBuilder, packed::Script, prelude::*};
+    use httpmock::prelude::*;
+
+    use super::*;
+
+    const VALIDATION_FAILURE: &str = "TransactionFailedToVerify: Verification failed \
+        Script(TransactionScriptError { source: Inputs[1].Lock, cause: ValidationFailure: \
+        see error code -31 on page https://nervosnetwork.github.io/ckb-script-error-codes/\
+        by-type-hash/9bd7e06f3ecf4be0f2fcd2188b23f1b9fcc88e5d4b65a8637b17723bbda3cce8.html#-31 })";
+
+    #[test]
+    fn test_parse_script_failure() {
+        let failure = ScriptFailure::from_message(VALIDATION_FAILURE).unwrap();
+        assert_eq!(failure.source, ScriptSource::Input(1));
+        assert_eq!(failure.group_type, ScriptGroupType::Lock);
+        assert_eq!(failure.cause, ScriptFailureCause::ExitCode(-31));
+
+        let failure = ScriptFailure::from_message(
+            "Verification failed Script(TransactionScriptError { source: Outputs[0].Type, \
+            cause: ExceededMaximumCycles: expect cycles <= 70000000 })",
+        )
+        .unwrap();
+        assert_eq!(failure.source, ScriptSource::Output(0));
+        assert_eq!(failure.group_type, ScriptGroupType::Type);
+        assert_eq!(
+            failure.cause,
+            ScriptFailureCause::ExceededMaximumCycles(70_000_000)
+        );
+
+        let failure = ScriptFailure::from_message(
+            "Verification failed Script(TransactionScriptError { source: Inputs[2].Type, \
+            cause: VM Internal Error: MemOutOfBound })",
+        )
+        .unwrap();
+        assert_eq!(
+            failure.cause,
+            ScriptFailureCause::Other("VM Internal Error: MemOutOfBound".to_string())
+        );
+
+        assert!(ScriptFailure::from_message(
+            "TransactionFailedToVerify: Verification failed Transaction(InsufficientCellCapacity)"
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_trace_failed_script() {
+        let server = MockServer::start();
+        let mut mock = server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("estimate_cycles");
+            then.status(200).body(
+                serde_json::json!({
+                    "id": 42,
+                    "jsonrpc": "2.0",
+                    "error": { "code": -302, "message": VALIDATION_FAILURE },
+                })
+                .to_string(),
+            );
+        });
+        let client = CkbRpcClient::new(&server.base_url());
+        let tx = TransactionBuilder::default().build();
+        let lock = Script::new_builder().args(vec![1u8; 20].pack()).build();
+        let mut group = ScriptGroup::from_lock_script(&lock);
+        group.input_indices = vec![0, 1];
+
+        let trace = TransactionTracer::trace_failed_script(&client, &tx, &group).unwrap();
+        assert_eq!(trace.script_hash, lock.calc_script_hash());
+        assert_eq!(trace.cycles, None);
+        assert_eq!(
+            trace.failure.unwrap().cause,
+            ScriptFailureCause::ExitCode(-31)
+        );
+        assert!(trace.failed_in_group);
+
+        group.input_indices = vec![0];
+        let trace = TransactionTracer::trace_failed_script(&client, &tx, &group).unwrap();
+        assert!(!trace.failed_in_group);
+        mock.delete();
+
+        server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("estimate_cycles");
+            then.status(200).body(
+                serde_json::json!({
+                    "id": 42,
+                    "jsonrpc": "2.0",
+                    "result": { "cycles": "0x3e8" },
+                })
+                .to_string(),
+            );
+        });
+        let trace = TransactionTracer::trace_failed_script(&client, &tx, &group).unwrap();
+        assert_eq!(trace.cycles, Some(1000));
+        assert_eq!(trace.failure, None);
+    }
+}
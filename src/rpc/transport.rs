@@ -0,0 +1,52 @@
+use serde_json::Value;
+
+use crate::rpc::RpcError;
+
+/// The transport sending the jsonrpc requests of the rpc clients, implement it to route
+/// the requests over non-http channels (e.g. unix socket or an in-process handle).
+pub trait JsonRpcTransport: Send + Sync {
+    /// Send the request and return the `result` field of the response, the jsonrpc
+    /// error in the response should be returned as `RpcError::Rpc`.
+    fn request(&self, method: &str, params: Value) -> Result<Value, RpcError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        rpc::{ckb_indexer::Tip, CkbRpcClient, IndexerRpcClient},
+        test_util::MockRpcTransport,
+    };
+
+    #[test]
+    fn test_custom_transport() {
+        let transport = Arc::new(MockRpcTransport::default());
+        transport.mock("get_tip_block_number", ckb_jsonrpc_types::Uint64::from(100));
+        transport.mock(
+            "get_indexer_tip",
+            Tip {
+                block_hash: Default::default(),
+                block_number: 99.into(),
+            },
+        );
+
+        let ckb_client = CkbRpcClient::new_with_transport(Arc::clone(&transport) as _);
+        assert_eq!(ckb_client.get_tip_block_number().unwrap().value(), 100);
+        assert!(ckb_client.http_url().is_none());
+        assert!(ckb_client.clone().http_url().is_none());
+        let indexer_client = IndexerRpcClient::new_with_transport(Arc::clone(&transport) as _);
+        let tip = indexer_client.get_indexer_tip().unwrap().unwrap();
+        assert_eq!(tip.block_number.value(), 99);
+        // the methods not mocked are failed with the jsonrpc error
+        assert!(matches!(
+            ckb_client.get_consensus(),
+            Err(RpcError::Rpc(err)) if err.code == jsonrpc_core::ErrorCode::MethodNotFound
+        ));
+        assert_eq!(
+            transport.requests(),
+            vec!["get_tip_block_number", "get_indexer_tip", "get_consensus"]
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use ckb_jsonrpc_types::{Capacity, Cycle};
+#[cfg(feature = "rpc")]
+use ckb_jsonrpc_types::{OutputsValidator, Transaction};
+#[cfg(feature = "rpc")]
+use ckb_types::{packed, H256};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[cfg(feature = "rpc")]
+use super::CkbRpcClient;
+use super::RpcError;
+
+/// The result of `test_tx_pool_accept`, the transaction is accepted by the tx-pool.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct EntryCompleted {
+    /// The cycles consumed by the scripts
+    pub cycles: Cycle,
+    /// The transaction fee
+    pub fee: Capacity,
+}
+
+/// The reason of a transaction rejected by the tx-pool, parsed from the rpc error code.
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum PoolRejection {
+    #[error("failed to resolve: {0}")]
+    Resolve(String),
+    #[error("failed to verify: {0}")]
+    Verification(String),
+    #[error("rejected by outputs validator: {0}")]
+    OutputsValidator(String),
+    #[error("ill transaction: {0}")]
+    IllTransaction(String),
+    #[error("the fee rate is too low: {0}")]
+    LowFeeRate(String),
+    #[error("exceeded maximum ancestors count: {0}")]
+    ExceededMaximumAncestorsCount(String),
+    #[error("tx-pool is full: {0}")]
+    Full(String),
+    #[error("duplicated transaction: {0}")]
+    Duplicated(String),
+    #[error("malformed transaction: {0}")]
+    Malformed(String),
+    #[error("transaction expired: {0}")]
+    Expiry(String),
+    #[error("exceeded transaction size limit: {0}")]
+    ExceededTransactionSizeLimit(String),
+    #[error("RBF rejected: {0}")]
+    RBFRejected(String),
+    #[error("invalidated: {0}")]
+    Invalidated(String),
+}
+
+impl PoolRejection {
+    /// Parse the rejection from the error returned by `send_transaction` or
+    /// `test_tx_pool_accept`, return `None` if it is not a tx-pool rejection.
+    pub fn from_rpc_error(err: &RpcError) -> Option<PoolRejection> {
+        let err = match err {
+            RpcError::Rpc(err) => err,
+            _ => return None,
+        };
+        let message = err.message.clone();
+        let rejection = match err.code.code() {
+            -301 => PoolRejection::Resolve(message),
+            -302 => PoolRejection::Verification(message),
+            -1102 => PoolRejection::OutputsValidator(message),
+            -1103 => PoolRejection::IllTransaction(message),
+            -1104 => PoolRejection::LowFeeRate(message),
+            -1105 => PoolRejection::ExceededMaximumAncestorsCount(message),
+            -1106 => PoolRejection::Full(message),
+            -1107 => PoolRejection::Duplicated(message),
+            -1108 => PoolRejection::Malformed(message),
+            -1109 => PoolRejection::Expiry(message),
+            -1110 => PoolRejection::ExceededTransactionSizeLimit(message),
+            -1111 => PoolRejection::RBFRejected(message),
+            -1112 => PoolRejection::Invalidated(message),
+            _ => return None,
+        };
+        Some(rejection)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum SendTxError {
+    #[error("rejected by tx-pool: {0}")]
+    Rejected(PoolRejection),
+
+    #[error("aborted by caller after pre-check, cycles: {}, fee: {}", .0.cycles, .0.fee)]
+    Aborted(TxPoolEntryInfo),
+
+    #[error("rpc error: `{0}`")]
+    Rpc(RpcError),
+}
+
+impl From<RpcError> for SendTxError {
+    fn from(err: RpcError) -> SendTxError {
+        match PoolRejection::from_rpc_error(&err) {
+            Some(rejection) => SendTxError::Rejected(rejection),
+            None => SendTxError::Rpc(err),
+        }
+    }
+}
+
+/// The tx-pool entry of a transaction computed by the node before broadcasting.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct TxPoolEntryInfo {
+    pub cycles: u64,
+    /// The transaction fee in shannons
+    pub fee: u64,
+    /// The serialized size of the transaction in block
+    pub size: u64,
+}
+
+/// Check if the transaction will be accepted by the tx-pool without broadcasting it.
+#[cfg(feature = "rpc")]
+pub fn test_tx_pool_accept(
+    client: &CkbRpcClient,
+    tx: Transaction,
+    outputs_validator: Option<OutputsValidator>,
+) -> Result<TxPoolEntryInfo, SendTxError> {
+    let size = packed::Transaction::from(tx.clone())
+        .as_reader()
+        .serialized_size_in_block() as u64;
+    let entry = client.test_tx_pool_accept(tx, outputs_validator)?;
+    Ok(TxPoolEntryInfo {
+        cycles: entry.cycles.value(),
+        fee: entry.fee.value(),
+        size,
+    })
+}
+
+/// Pre-check the transaction by `test_tx_pool_accept` and send it if `confirm` returns
+/// true with the computed cycles and fee.
+#[cfg(feature = "rpc")]
+pub fn send_transaction_checked<F>(
+    client: &CkbRpcClient,
+    tx: Transaction,
+    outputs_validator: Option<OutputsValidator>,
+    confirm: F,
+) -> Result<(H256, TxPoolEntryInfo), SendTxError>
+where
+    F: FnOnce(&TxPoolEntryInfo) -> bool,
+{
+    let info = test_tx_pool_accept(client, tx.clone(), outputs_validator.clone())?;
+    if !confirm(&info) {
+        return Err(SendTxError::Aborted(info));
+    }
+    let tx_hash = client.send_transaction(tx, outputs_validator)?;
+    Ok((tx_hash, info))
+}
+
+#[cfg(all(test, feature = "rpc"))]
+mod tests {
+    use ckb_types::{core::TransactionBuilder, prelude::*};
+    use httpmock::prelude::*;
+
+    use super::*;
+    use crate::test_util::MockRpcResult;
+
+    #[test]
+    fn test_send_transaction_checked() {
+        let tx = TransactionBuilder::default().build();
+        let server = MockServer::start();
+        let accept = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("test_tx_pool_accept");
+            then.status(200).body(
+                MockRpcResult::new(EntryCompleted {
+                    cycles: 1000.into(),
+                    fee: 500.into(),
+                })
+                .to_json(),
+            );
+        });
+        let send = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("send_transaction");
+            then.status(200)
+                .body(MockRpcResult::<H256>::new(tx.hash().unpack()).to_json());
+        });
+
+        let client = CkbRpcClient::new(&server.base_url());
+        let result =
+            send_transaction_checked(&client, tx.data().into(), None, |info| info.fee < 500);
+        assert!(matches!(result, Err(SendTxError::Aborted(info)) if info.cycles == 1000));
+        send.assert_hits(0);
+
+        let (tx_hash, info) =
+            send_transaction_checked(&client, tx.data().into(), None, |_| true).unwrap();
+        assert_eq!(tx_hash, tx.hash().unpack());
+        assert_eq!(info.fee, 500);
+        assert_eq!(
+            info.size,
+            tx.data().as_reader().serialized_size_in_block() as u64
+        );
+        accept.assert_hits(2);
+        send.assert_hits(1);
+    }
+
+    #[test]
+    fn test_pool_rejection() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("test_tx_pool_accept");
+            then.status(200).body(
+                serde_json::json!({
+                    "id": 42,
+                    "jsonrpc": "2.0",
+                    "error": {
+                        "code": -1107,
+                        "message": "PoolRejectedDuplicatedTransaction: Transaction already exists",
+                    },
+                })
+                .to_string(),
+            );
+        });
+        let client = CkbRpcClient::new(&server.base_url());
+        let tx = TransactionBuilder::default().build();
+        let err = test_tx_pool_accept(&client, tx.data().into(), None).unwrap_err();
+        assert!(matches!(
+            err,
+            SendTxError::Rejected(PoolRejection::Duplicated(_))
+        ));
+    }
+}
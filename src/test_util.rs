@@ -508,7 +508,7 @@ impl CellCollector for LiveCellsContext {
         query: &CellQueryOptions,
         apply_changes: bool,
     ) -> Result<(Vec<LiveCell>, u64), CellCollectorError> {
-        let mut total_capacity = 0;
+        let mut total_capacity: u64 = 0;
         let mut cells = Vec::new();
         for (idx, item) in self.inputs.iter().enumerate() {
             if self.used_inputs.contains(&idx) {
@@ -531,7 +531,7 @@ impl CellCollector for LiveCellsContext {
                 tx_index: 0,
             };
             if query.match_cell(&live_cell, 0) {
-                total_capacity += capacity;
+                total_capacity = total_capacity.saturating_add(capacity);
                 cells.push(live_cell);
                 if apply_changes {
                     self.used_inputs.insert(idx);
@@ -546,10 +546,19 @@ impl CellCollector for LiveCellsContext {
 
     fn lock_cell(
         &mut self,
-        _out_point: OutPoint,
+        out_point: OutPoint,
         _tip_block_number: u64,
     ) -> Result<(), CellCollectorError> {
-        unimplemented!()
+        if let Some(idx) = self.input_index(&out_point) {
+            self.used_inputs.insert(idx);
+        }
+        Ok(())
+    }
+    fn unlock_cell(&mut self, out_point: OutPoint) -> Result<(), CellCollectorError> {
+        if let Some(idx) = self.input_index(&out_point) {
+            self.used_inputs.remove(&idx);
+        }
+        Ok(())
     }
     fn apply_tx(
         &mut self,
@@ -563,6 +572,14 @@ impl CellCollector for LiveCellsContext {
     }
 }
 
+impl LiveCellsContext {
+    fn input_index(&self, out_point: &OutPoint) -> Option<usize> {
+        self.inputs
+            .iter()
+            .position(|item| item.input.previous_output() == *out_point)
+    }
+}
+
 struct DummyLoader;
 impl MockResourceLoader for DummyLoader {
     fn get_header(&mut self, hash: H256) -> Result<Option<HeaderView>, String> {
@@ -610,6 +627,45 @@ impl<T: Serialize> MockRpcResult<T> {
     }
 }
 
+/// An in-process rpc transport returning the mocked results without network, the
+/// methods not mocked are failed with the jsonrpc `MethodNotFound` error.
+#[cfg(feature = "rpc")]
+#[derive(Default)]
+pub struct MockRpcTransport {
+    results: parking_lot::Mutex<HashMap<String, serde_json::Value>>,
+    requests: parking_lot::Mutex<Vec<String>>,
+}
+
+#[cfg(feature = "rpc")]
+impl MockRpcTransport {
+    /// Mock the result of the method, the previous result is replaced.
+    pub fn mock<T: Serialize>(&self, method: &str, result: T) {
+        let result = serde_json::to_value(result).unwrap();
+        self.results.lock().insert(method.to_string(), result);
+    }
+
+    /// The requested methods in order.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().clone()
+    }
+}
+
+#[cfg(feature = "rpc")]
+impl crate::rpc::JsonRpcTransport for MockRpcTransport {
+    fn request(
+        &self,
+        method: &str,
+        _params: serde_json::Value,
+    ) -> Result<serde_json::Value, crate::RpcError> {
+        self.requests.lock().push(method.to_string());
+        self.results
+            .lock()
+            .get(method)
+            .cloned()
+            .ok_or_else(|| jsonrpc_core::Error::method_not_found().into())
+    }
+}
+
 #[cfg(test)]
 mod anyhow_tests {
     use anyhow::anyhow;
@@ -0,0 +1,521 @@
+use ckb_jsonrpc_types as json_types;
+use ckb_types::{
+    bytes::Bytes,
+    core::{EpochNumberWithFraction, HeaderView},
+    packed::{CellInput, CellOutput, OutPoint, RawTransaction, Script, Transaction},
+    prelude::*,
+};
+use httpmock::prelude::*;
+
+use crate::{
+    rpc::ckb_indexer::{Cell, Pagination, Tip},
+    test_util::{random_out_point, MockRpcResult},
+    traits::{CellCollector, CellCollectorError, CellQueryOptions, DefaultCellCollector},
+};
+
+const TIP_NUMBER: u64 = 100;
+
+fn build_lock(arg: u8) -> Script {
+    Script::new_builder().args(vec![arg; 20].pack()).build()
+}
+
+fn indexer_cell(lock: &Script, capacity: u64) -> Cell {
+    Cell {
+        output: CellOutput::new_builder()
+            .lock(lock.clone())
+            .capacity(capacity.pack())
+            .build()
+            .into(),
+        output_data: None,
+        out_point: random_out_point().into(),
+        block_number: 1.into(),
+        tx_index: 1.into(),
+    }
+}
+
+/// Mock the rpc methods of ckb node and ckb-indexer except `get_cells`
+fn mock_chain(server: &MockServer) {
+    let consensus: json_types::Consensus = ckb_chain_spec::consensus::ConsensusBuilder::default()
+        .build()
+        .into();
+    server.mock(|when, then| {
+        when.method(POST).path("/").body_contains("get_consensus");
+        then.status(200)
+            .body(MockRpcResult::new(consensus).to_json());
+    });
+    let tip_header: json_types::HeaderView = HeaderView::new_advanced_builder()
+        .number(TIP_NUMBER.pack())
+        .epoch(
+            EpochNumberWithFraction::new(0, 100, 1000)
+                .full_value()
+                .pack(),
+        )
+        .build()
+        .into();
+    server.mock(|when, then| {
+        when.method(POST).path("/").body_contains("get_tip_header");
+        then.status(200)
+            .body(MockRpcResult::new(tip_header).to_json());
+    });
+    server.mock(|when, then| {
+        when.method(POST)
+            .path("/")
+            .body_contains("get_tip_block_number");
+        then.status(200)
+            .body(MockRpcResult::new(json_types::BlockNumber::from(TIP_NUMBER)).to_json());
+    });
+    server.mock(|when, then| {
+        when.method(POST).path("/").body_contains("get_indexer_tip");
+        then.status(200).body(
+            MockRpcResult::new(Tip {
+                block_hash: Default::default(),
+                block_number: TIP_NUMBER.into(),
+            })
+            .to_json(),
+        );
+    });
+}
+
+/// Mock a `get_cells` page requested with `limit`, the limit is doubled for every
+/// following page so it identifies the page.
+fn mock_cells_page(
+    server: &MockServer,
+    limit: u32,
+    cells: Vec<Cell>,
+    cursor: u8,
+) -> httpmock::Mock<'_> {
+    let limit = json_types::Uint32::from(limit);
+    let limit = serde_json::to_string(&limit).unwrap();
+    server.mock(|when, then| {
+        when.method(POST)
+            .path("/")
+            .body_contains("get_cells")
+            .body_contains(limit.as_str());
+        then.status(200).body(
+            MockRpcResult::new(Pagination {
+                objects: cells,
+                last_cursor: json_types::JsonBytes::from_vec(vec![cursor]),
+            })
+            .to_json(),
+        );
+    })
+}
+
+fn build_tx(inputs: Vec<OutPoint>, outputs: Vec<CellOutput>) -> Transaction {
+    let outputs_data = vec![Bytes::default().pack(); outputs.len()];
+    let raw = RawTransaction::new_builder()
+        .inputs(
+            inputs
+                .into_iter()
+                .map(|out_point| CellInput::new(out_point, 0))
+                .collect::<Vec<_>>()
+                .pack(),
+        )
+        .outputs(outputs.pack())
+        .outputs_data(outputs_data.pack())
+        .build();
+    Transaction::new_builder().raw(raw).build()
+}
+
+fn query(lock: &Script, min_total_capacity: u64, limit: u32) -> CellQueryOptions {
+    let mut query = CellQueryOptions::new_lock(lock.clone());
+    query.min_total_capacity = min_total_capacity;
+    query.limit = Some(limit);
+    query
+}
+
+#[test]
+fn test_collect_across_pages() {
+    let lock = build_lock(1);
+    let server = MockServer::start();
+    mock_chain(&server);
+    let page1 = mock_cells_page(&server, 1, vec![indexer_cell(&lock, 100)], 1);
+    let page2 = mock_cells_page(
+        &server,
+        2,
+        vec![indexer_cell(&lock, 200), indexer_cell(&lock, 300)],
+        2,
+    );
+    let page3 = mock_cells_page(&server, 4, vec![indexer_cell(&lock, 400)], 3);
+    let page4 = mock_cells_page(&server, 8, Vec::new(), 4);
+    let mut collector = DefaultCellCollector::new(server.base_url().as_str());
+
+    // the target is reached in the second page
+    let (cells, capacity) = collector
+        .collect_live_cells(&query(&lock, 250, 1), false)
+        .unwrap();
+    assert_eq!(cells.len(), 2);
+    assert_eq!(capacity, 300);
+    page1.assert_hits(1);
+    page2.assert_hits(1);
+    page3.assert_hits(0);
+
+    // all pages are requested with doubled limit when the target is not reachable
+    let (cells, capacity) = collector
+        .collect_live_cells(&query(&lock, 10000, 1), false)
+        .unwrap();
+    assert_eq!(cells.len(), 4);
+    assert_eq!(capacity, 1000);
+    page1.assert_hits(2);
+    page2.assert_hits(2);
+    page3.assert_hits(1);
+    page4.assert_hits(1);
+}
+
+#[test]
+fn test_collect_skip_locked_cells() {
+    let lock = build_lock(1);
+    let cells = vec![
+        indexer_cell(&lock, 100),
+        indexer_cell(&lock, 200),
+        indexer_cell(&lock, 300),
+    ];
+    let locked: OutPoint = cells[0].out_point.clone().into();
+    let server = MockServer::start();
+    mock_chain(&server);
+    mock_cells_page(&server, 16, cells, 1);
+    mock_cells_page(&server, 32, Vec::new(), 2);
+    let mut collector = DefaultCellCollector::new(server.base_url().as_str());
+
+    collector.lock_cell(locked.clone(), TIP_NUMBER).unwrap();
+    let (cells, capacity) = collector
+        .collect_live_cells(&query(&lock, 250, 16), false)
+        .unwrap();
+    assert_eq!(cells.len(), 2);
+    assert_eq!(capacity, 500);
+    assert!(cells.iter().all(|cell| cell.out_point != locked));
+
+    // the collected cells are locked when apply changes
+    let (cells, _) = collector
+        .collect_live_cells(&query(&lock, 250, 16), true)
+        .unwrap();
+    assert_eq!(cells.len(), 2);
+    let (cells, capacity) = collector
+        .collect_live_cells(&query(&lock, 250, 16), false)
+        .unwrap();
+    assert!(cells.is_empty());
+    assert_eq!(capacity, 0);
+}
+
+#[test]
+fn test_collect_offchain_cells_first() {
+    let lock = build_lock(1);
+    let other_lock = build_lock(2);
+    let server = MockServer::start();
+    mock_chain(&server);
+    let page1 = mock_cells_page(&server, 16, vec![indexer_cell(&lock, 1000)], 1);
+    mock_cells_page(&server, 32, Vec::new(), 2);
+    let mut collector = DefaultCellCollector::new(server.base_url().as_str());
+
+    let output = |lock: &Script, capacity: u64| {
+        CellOutput::new_builder()
+            .lock(lock.clone())
+            .capacity(capacity.pack())
+            .build()
+    };
+    let tx = build_tx(
+        vec![random_out_point()],
+        vec![
+            output(&lock, 100),
+            output(&other_lock, 200),
+            output(&lock, 300),
+        ],
+    );
+    let tx_hash = tx.calc_tx_hash();
+    collector.apply_tx(tx, TIP_NUMBER).unwrap();
+
+    // the offchain cells are enough, the indexer is not requested
+    let (cells, capacity) = collector
+        .collect_live_cells(&query(&lock, 400, 16), false)
+        .unwrap();
+    assert_eq!(capacity, 400);
+    let mut out_points = cells
+        .iter()
+        .map(|cell| cell.out_point.clone())
+        .collect::<Vec<_>>();
+    out_points.sort_by_key(|out_point| Unpack::<u32>::unpack(&out_point.index()));
+    assert_eq!(
+        out_points,
+        vec![
+            OutPoint::new(tx_hash.clone(), 0),
+            OutPoint::new(tx_hash.clone(), 2)
+        ]
+    );
+    page1.assert_hits(0);
+
+    // the rest capacity is collected from the indexer
+    let (cells, capacity) = collector
+        .collect_live_cells(&query(&lock, 500, 16), true)
+        .unwrap();
+    assert_eq!(cells.len(), 3);
+    assert_eq!(capacity, 1400);
+    page1.assert_hits(1);
+
+    // the consumed offchain cells are not collected again
+    let (cells, capacity) = collector
+        .collect_live_cells(&query(&other_lock, 200, 16), false)
+        .unwrap();
+    assert_eq!(cells.len(), 1);
+    assert_eq!(cells[0].out_point, OutPoint::new(tx_hash, 1));
+    assert_eq!(capacity, 200);
+}
+
+#[test]
+fn test_apply_tx_and_reset() {
+    let lock = build_lock(1);
+    let indexer_cells = vec![indexer_cell(&lock, 100), indexer_cell(&lock, 200)];
+    let spent: OutPoint = indexer_cells[0].out_point.clone().into();
+    let server = MockServer::start();
+    mock_chain(&server);
+    mock_cells_page(&server, 16, indexer_cells, 1);
+    mock_cells_page(&server, 32, Vec::new(), 2);
+    let mut collector = DefaultCellCollector::new(server.base_url().as_str());
+
+    // the inputs are locked and the outputs are new live cells
+    let output = CellOutput::new_builder()
+        .lock(lock.clone())
+        .capacity(50u64.pack())
+        .build();
+    let tx = build_tx(vec![spent.clone()], vec![output]);
+    let tx_hash = tx.calc_tx_hash();
+    collector.apply_tx(tx, TIP_NUMBER).unwrap();
+    let (cells, capacity) = collector
+        .collect_live_cells(&query(&lock, 1000, 16), false)
+        .unwrap();
+    assert_eq!(cells.len(), 2);
+    assert_eq!(capacity, 250);
+    assert!(cells.iter().all(|cell| cell.out_point != spent));
+    assert!(cells
+        .iter()
+        .any(|cell| cell.out_point == OutPoint::new(tx_hash.clone(), 0)));
+
+    // all offchain states are cleared
+    collector.reset();
+    let (cells, capacity) = collector
+        .collect_live_cells(&query(&lock, 1000, 16), false)
+        .unwrap();
+    assert_eq!(cells.len(), 2);
+    assert_eq!(capacity, 300);
+    assert!(cells.iter().any(|cell| cell.out_point == spent));
+    assert!(cells
+        .iter()
+        .all(|cell| cell.out_point != OutPoint::new(tx_hash.clone(), 0)));
+}
+
+#[test]
+fn test_collect_committed_offchain_cells() {
+    let lock = build_lock(1);
+    let output = CellOutput::new_builder()
+        .lock(lock.clone())
+        .capacity(100u64.pack())
+        .build();
+    let tx = build_tx(vec![random_out_point()], vec![output.clone(), output]);
+    let tx_hash = tx.calc_tx_hash();
+    // the transaction is committed and the indexer returns its outputs
+    let committed_cells = (0..2u32)
+        .map(|index| {
+            let mut cell = indexer_cell(&lock, 100);
+            cell.out_point = OutPoint::new(tx_hash.clone(), index).into();
+            cell
+        })
+        .collect::<Vec<_>>();
+    let server = MockServer::start();
+    mock_chain(&server);
+    let page1 = mock_cells_page(
+        &server,
+        16,
+        vec![
+            committed_cells[0].clone(),
+            indexer_cell(&lock, 300),
+            committed_cells[1].clone(),
+        ],
+        1,
+    );
+    mock_cells_page(&server, 32, Vec::new(), 2);
+    let mut collector = DefaultCellCollector::new(server.base_url().as_str());
+    collector.apply_tx(tx, TIP_NUMBER).unwrap();
+
+    // the committed offchain cells are only counted once
+    let (cells, capacity) = collector
+        .collect_live_cells(&query(&lock, 1000, 16), false)
+        .unwrap();
+    assert_eq!(cells.len(), 3);
+    assert_eq!(capacity, 500);
+    let mut out_points = cells
+        .iter()
+        .map(|cell| cell.out_point.clone())
+        .collect::<Vec<_>>();
+    out_points.sort();
+    out_points.dedup();
+    assert_eq!(out_points.len(), 3);
+    page1.assert_hits(1);
+
+    // the committed cells are pruned from the offchain live cells, so they are
+    // collected from the indexer now
+    let (cells, capacity) = collector
+        .collect_live_cells(&query(&lock, 100, 16), false)
+        .unwrap();
+    assert_eq!(cells.len(), 1);
+    assert_eq!(capacity, 100);
+    assert_eq!(cells[0].out_point, OutPoint::new(tx_hash, 0));
+    page1.assert_hits(2);
+}
+
+#[test]
+fn test_rollback_tx() {
+    let lock = build_lock(1);
+    let indexer_cells = vec![
+        indexer_cell(&lock, 100),
+        indexer_cell(&lock, 200),
+        indexer_cell(&lock, 300),
+    ];
+    let out_points: Vec<OutPoint> = indexer_cells
+        .iter()
+        .map(|cell| cell.out_point.clone().into())
+        .collect();
+    let server = MockServer::start();
+    mock_chain(&server);
+    mock_cells_page(&server, 16, indexer_cells, 1);
+    mock_cells_page(&server, 32, Vec::new(), 2);
+    let mut collector = DefaultCellCollector::new(server.base_url().as_str());
+    let output = |capacity: u64| {
+        CellOutput::new_builder()
+            .lock(lock.clone())
+            .capacity(capacity.pack())
+            .build()
+    };
+    let collect_all = |collector: &mut DefaultCellCollector| {
+        let (cells, capacity) = collector
+            .collect_live_cells(&query(&lock, 10000, 16), false)
+            .unwrap();
+        let mut out_points = cells
+            .into_iter()
+            .map(|cell| cell.out_point)
+            .collect::<Vec<_>>();
+        out_points.sort();
+        (out_points, capacity)
+    };
+
+    // the cell 2 is locked before applying, and the cell 0 is spent by both transactions
+    collector
+        .lock_cell(out_points[2].clone(), TIP_NUMBER)
+        .unwrap();
+    let tx1 = build_tx(
+        vec![out_points[0].clone(), out_points[2].clone()],
+        vec![output(10)],
+    );
+    let tx1_hash = tx1.calc_tx_hash();
+    collector.apply_tx(tx1, TIP_NUMBER).unwrap();
+    let tx2 = build_tx(
+        vec![out_points[1].clone(), out_points[0].clone()],
+        vec![output(20)],
+    );
+    let tx2_hash = tx2.calc_tx_hash();
+    collector.apply_tx(tx2, TIP_NUMBER).unwrap();
+    assert_eq!(collect_all(&mut collector).1, 30);
+
+    // the cells locked before tx1 applied are still locked
+    assert!(collector.rollback_tx(tx1_hash.clone()));
+    let (cells, capacity) = collect_all(&mut collector);
+    assert_eq!(capacity, 20);
+    assert_eq!(cells, vec![OutPoint::new(tx2_hash.clone(), 0)]);
+    assert!(!collector.rollback_tx(tx1_hash));
+
+    assert!(collector.rollback_tx(tx2_hash));
+    let (cells, capacity) = collect_all(&mut collector);
+    assert_eq!(capacity, 300);
+    let mut expected = out_points[0..2].to_vec();
+    expected.sort();
+    assert_eq!(cells, expected);
+
+    collector.unlock_cell(out_points[2].clone()).unwrap();
+    assert_eq!(collect_all(&mut collector).1, 600);
+}
+
+#[test]
+fn test_collect_exactly() {
+    let lock = build_lock(1);
+    let indexer_cells = vec![indexer_cell(&lock, 100), indexer_cell(&lock, 200)];
+    let server = MockServer::start();
+    mock_chain(&server);
+    mock_cells_page(&server, 16, indexer_cells, 1);
+    mock_cells_page(&server, 32, Vec::new(), 2);
+    let mut collector = DefaultCellCollector::new(server.base_url().as_str());
+
+    // the collected cells are unlocked again when the capacity is not enough
+    let err = collector
+        .collect_exactly(&query(&lock, 1000, 16), true)
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        CellCollectorError::InsufficientCapacity {
+            needed: 1000,
+            available: 300
+        }
+    ));
+    let (cells, capacity) = collector
+        .collect_exactly(&query(&lock, 300, 16), true)
+        .unwrap();
+    assert_eq!(cells.len(), 2);
+    assert_eq!(capacity, 300);
+
+    // all cells are locked by the previous collection
+    let err = collector
+        .collect_exactly(&query(&lock, 1, 16), true)
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        CellCollectorError::InsufficientCapacity {
+            needed: 1,
+            available: 0
+        }
+    ));
+}
+
+#[test]
+fn test_recover_locked_cells_after_failed_tx() {
+    let lock = build_lock(1);
+    let indexer_cells = vec![
+        indexer_cell(&lock, 100),
+        indexer_cell(&lock, 200),
+        indexer_cell(&lock, 300),
+    ];
+    let server = MockServer::start();
+    mock_chain(&server);
+    mock_cells_page(&server, 16, indexer_cells, 1);
+    mock_cells_page(&server, 32, Vec::new(), 2);
+    let mut collector = DefaultCellCollector::new(server.base_url().as_str());
+
+    // the cells 0 and 1 are locked for the failed transaction, the cell 2 is locked by others
+    let (cells, _) = collector
+        .collect_live_cells(&query(&lock, 300, 16), true)
+        .unwrap();
+    assert_eq!(cells.len(), 2);
+    let mut inputs: Vec<OutPoint> = cells.into_iter().map(|cell| cell.out_point).collect();
+    inputs.push(random_out_point());
+    let (cells, _) = collector
+        .collect_live_cells(&query(&lock, 1, 16), true)
+        .unwrap();
+    assert_eq!(cells.len(), 1);
+    let output = CellOutput::new_builder()
+        .lock(lock.clone())
+        .capacity(50u64.pack())
+        .build();
+    let failed_tx = build_tx(inputs.clone(), vec![output]);
+    collector.apply_tx(failed_tx.clone(), TIP_NUMBER).unwrap();
+    assert_eq!(
+        collector.recover_locked_cells_after_failed_tx(&failed_tx.into_view()),
+        3
+    );
+
+    // the outputs of the failed transaction are removed
+    let (cells, capacity) = collector
+        .collect_live_cells(&query(&lock, 1000, 16), false)
+        .unwrap();
+    assert_eq!(capacity, 300);
+    let mut out_points: Vec<OutPoint> = cells.into_iter().map(|cell| cell.out_point).collect();
+    out_points.sort();
+    inputs.truncate(2);
+    inputs.sort();
+    assert_eq!(out_points, inputs);
+}
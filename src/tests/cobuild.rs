@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use ckb_hash::blake2b_256;
+use ckb_types::{
+    bytes::Bytes,
+    core::{ScriptHashType, TransactionBuilder},
+    packed::{CellInput, CellOutput, Script, WitnessArgs},
+    prelude::*,
+    H256,
+};
+
+use crate::{
+    constants::{ONE_CKB, SIGHASH_TYPE_HASH},
+    test_util::random_out_point,
+    tests::{
+        build_sighash_script, init_context, ACCOUNT1_ARG, ACCOUNT1_KEY, ACCOUNT2_ARG, ACCOUNT2_KEY,
+        ALWAYS_SUCCESS_BIN, FEE_RATE,
+    },
+    traits::{CellDepResolver, SecpCkbRawKeySigner},
+    tx_builder::{fill_placeholder_witnesses, unlock_tx},
+    types::cobuild_mol::{Action, ActionVec, Message, WitnessLayoutUnion},
+    unlock::{
+        cobuild::{generate_cobuild_sighash_all_message, parse_witness_layout},
+        CobuildUnlocker, ScriptUnlocker, SecpSighashUnlocker,
+    },
+    ScriptId, SECP256K1,
+};
+
+fn build_cobuild_lock(args: &[u8]) -> Script {
+    Script::new_builder()
+        .code_hash(H256::from(blake2b_256(ALWAYS_SUCCESS_BIN)).pack())
+        .hash_type(ScriptHashType::Data1.into())
+        .args(Bytes::from(args.to_vec()).pack())
+        .build()
+}
+
+fn recover_seal(seal: &[u8], signing_message: &[u8]) -> secp256k1::PublicKey {
+    let recovery_id = secp256k1::ecdsa::RecoveryId::from_i32(seal[64] as i32).unwrap();
+    let signature =
+        secp256k1::ecdsa::RecoverableSignature::from_compact(&seal[..64], recovery_id).unwrap();
+    let message = secp256k1::Message::from_slice(signing_message).unwrap();
+    SECP256K1.recover_ecdsa(&message, &signature).unwrap()
+}
+
+#[test]
+fn test_cobuild_mixed_with_legacy_sighash() {
+    let sender = build_sighash_script(ACCOUNT1_ARG);
+    let cobuild_lock = build_cobuild_lock(ACCOUNT2_ARG.as_bytes());
+    let receiver = build_sighash_script(ACCOUNT2_ARG);
+    let mut ctx = init_context(
+        vec![(ALWAYS_SUCCESS_BIN, false)],
+        vec![(sender.clone(), Some(100 * ONE_CKB))],
+    );
+    let cobuild_input = CellInput::new(random_out_point(), 0);
+    ctx.add_live_cell(
+        cobuild_input.clone(),
+        CellOutput::new_builder()
+            .capacity((100 * ONE_CKB).pack())
+            .lock(cobuild_lock.clone())
+            .build(),
+        Bytes::default(),
+        None,
+    );
+    let sender_input = ctx
+        .inputs
+        .iter()
+        .find(|mock_input| mock_input.output.lock() == sender)
+        .map(|mock_input| mock_input.input.clone())
+        .unwrap();
+
+    let action = Action::new_builder()
+        .script_hash(cobuild_lock.calc_script_hash())
+        .data(Bytes::from(vec![1, 2, 3]).pack())
+        .build();
+    let message = Message::new_builder()
+        .actions(ActionVec::new_builder().push(action).build())
+        .build();
+
+    let output = CellOutput::new_builder()
+        .capacity((199 * ONE_CKB).pack())
+        .lock(receiver)
+        .build();
+    let tx = TransactionBuilder::default()
+        .input(sender_input)
+        .input(cobuild_input)
+        .output(output)
+        .output_data(Bytes::default().pack())
+        .cell_dep(ctx.resolve(&sender).unwrap())
+        .cell_dep(ctx.resolve(&cobuild_lock).unwrap())
+        .build();
+
+    let account1_key = secp256k1::SecretKey::from_slice(ACCOUNT1_KEY.as_bytes()).unwrap();
+    let account2_key = secp256k1::SecretKey::from_slice(ACCOUNT2_KEY.as_bytes()).unwrap();
+    let mut unlockers: HashMap<ScriptId, Box<dyn ScriptUnlocker>> = HashMap::default();
+    unlockers.insert(
+        ScriptId::new_type(SIGHASH_TYPE_HASH.clone()),
+        Box::new(SecpSighashUnlocker::from(
+            Box::new(SecpCkbRawKeySigner::new_with_secret_keys(vec![
+                account1_key,
+            ])) as Box<_>,
+        )),
+    );
+    unlockers.insert(
+        ScriptId::from(&cobuild_lock),
+        Box::new(CobuildUnlocker::new(
+            Box::new(SecpCkbRawKeySigner::new_with_secret_keys(vec![
+                account2_key,
+            ])),
+            Some(message.clone()),
+        )),
+    );
+
+    let (tx, _) = fill_placeholder_witnesses(tx, &ctx, &unlockers).unwrap();
+    assert_eq!(tx.witnesses().len(), 2);
+    let (tx, locked_groups) = unlock_tx(tx, &ctx, &unlockers).unwrap();
+    assert!(locked_groups.is_empty());
+
+    // the legacy witness is kept
+    let witness = tx.witnesses().get(0).unwrap().raw_data();
+    assert!(parse_witness_layout(&witness).is_none());
+    assert!(WitnessArgs::from_slice(&witness).is_ok());
+    // the cobuild seal is signed by the key of the lock args
+    let layout = parse_witness_layout(&tx.witnesses().get(1).unwrap().raw_data()).unwrap();
+    let sighash_all = match layout.to_enum() {
+        WitnessLayoutUnion::SighashAll(sighash_all) => sighash_all,
+        _ => panic!("expect SighashAll"),
+    };
+    assert_eq!(sighash_all.message().as_slice(), message.as_slice());
+    let signing_message = generate_cobuild_sighash_all_message(&tx, Some(&message), &ctx).unwrap();
+    let pubkey = recover_seal(&sighash_all.seal().raw_data(), &signing_message);
+    assert_eq!(
+        &blake2b_256(pubkey.serialize())[0..20],
+        ACCOUNT2_ARG.as_bytes()
+    );
+
+    ctx.verify(tx, FEE_RATE).unwrap();
+}
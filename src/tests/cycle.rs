@@ -5,7 +5,7 @@ use ckb_hash::blake2b_256;
 use ckb_types::{
     core::{ScriptHashType, TransactionView},
     packed::{self, CellOutput, Script, WitnessArgs},
-    prelude::{Builder, Entity, Pack},
+    prelude::{Builder, Entity, Pack, Unpack},
     H256,
 };
 
@@ -16,7 +16,7 @@ use crate::{
     traits::TransactionDependencyProvider,
     tx_builder::{
         fill_placeholder_witnesses, transfer::CapacityTransferBuilder, unlock_tx,
-        BalanceTxCapacityError, CapacityBalancer, TxBuilder, TxBuilderError,
+        BalanceTxCapacityError, CapacityBalancer, ChangeOutput, TxBuilder, TxBuilderError,
     },
     unlock::{ScriptUnlocker, UnlockError},
     ScriptGroup, ScriptId,
@@ -139,6 +139,93 @@ fn test_change_enough(loops: u64) {
     ctx.verify(tx, FEE_RATE).unwrap();
 }
 
+#[test]
+fn test_balance_unlocked_with_cycles() {
+    let loops = 3;
+    let sender = build_script(loops);
+    let receiver = build_sighash_script(ACCOUNT2_ARG);
+
+    let ctx: &'static Context = Box::leak(Box::new(init_context(
+        vec![(CYCLE_BIN, true)],
+        vec![(sender.clone(), Some(200 * ONE_CKB))],
+    )));
+
+    let output = CellOutput::new_builder()
+        .capacity((140 * ONE_CKB).pack())
+        .lock(receiver)
+        .build();
+    let builder = CapacityTransferBuilder::new(vec![(output.clone(), Bytes::default())]);
+    let placeholder_witness = WitnessArgs::default();
+    let balancer = CapacityBalancer::new_simple(sender.clone(), placeholder_witness, FEE_RATE);
+
+    let mut cell_collector = ctx.to_live_cells_context();
+    let unlockers = build_cycle_unlockers(loops);
+    let (tx, new_locked_groups, cycles) = builder
+        .build_balance_unlocked_with_cycles(
+            &mut cell_collector,
+            ctx,
+            ctx,
+            ctx,
+            &balancer,
+            &unlockers,
+        )
+        .unwrap();
+
+    assert!(new_locked_groups.is_empty());
+    assert!(cycles.unwrap() > loops);
+    assert_eq!(tx.outputs().len(), 2);
+    assert_eq!(tx.output(0).unwrap(), output);
+    assert_eq!(tx.output(1).unwrap().lock(), sender);
+    ctx.verify(tx, FEE_RATE).unwrap();
+}
+
+#[test]
+fn test_balance_unlocked_result() {
+    let loops = 3;
+    let sender = build_script(loops);
+    let receiver = build_sighash_script(ACCOUNT2_ARG);
+
+    let ctx: &'static Context = Box::leak(Box::new(init_context(
+        vec![(CYCLE_BIN, true)],
+        vec![(sender.clone(), Some(200 * ONE_CKB))],
+    )));
+
+    let output = CellOutput::new_builder()
+        .capacity((140 * ONE_CKB).pack())
+        .lock(receiver)
+        .build();
+    let builder = CapacityTransferBuilder::new(vec![(output, Bytes::default())]);
+    let placeholder_witness = WitnessArgs::default();
+    let balancer = CapacityBalancer::new_simple(sender.clone(), placeholder_witness, FEE_RATE);
+
+    let mut cell_collector = ctx.to_live_cells_context();
+    let unlockers = build_cycle_unlockers(loops);
+    let (built_tx, new_locked_groups) = builder
+        .build_balance_unlocked_result(&mut cell_collector, ctx, ctx, ctx, &balancer, &unlockers)
+        .unwrap();
+
+    assert!(new_locked_groups.is_empty());
+    let tx = built_tx.tx.clone();
+    assert_eq!(built_tx.tx_hash, tx.hash());
+    assert_eq!(
+        built_tx.change_outputs,
+        vec![ChangeOutput {
+            index: 1,
+            lock_script: sender,
+        }]
+    );
+    let change_capacity: u64 = tx.output(1).unwrap().capacity().unpack();
+    assert_eq!(
+        built_tx.fee,
+        200 * ONE_CKB - 140 * ONE_CKB - change_capacity
+    );
+    assert_eq!(
+        built_tx.consumed_out_points,
+        tx.input_pts_iter().collect::<Vec<_>>()
+    );
+    ctx.verify(tx, FEE_RATE).unwrap();
+}
+
 #[test]
 fn vsize_big_and_fee_enough() {
     let loops = 640 * 1024;
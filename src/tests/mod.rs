@@ -1,13 +1,16 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use ckb_dao_utils::pack_dao_data;
 use ckb_hash::blake2b_256;
 use ckb_jsonrpc_types as json_types;
 use ckb_types::{
     bytes::Bytes,
-    core::{BlockView, Capacity, EpochNumberWithFraction, HeaderBuilder, ScriptHashType},
+    core::{
+        BlockView, Capacity, EpochNumberWithFraction, HeaderBuilder, HeaderView, ScriptHashType,
+        TransactionView,
+    },
     h160, h256,
-    packed::{CellInput, CellOutput, Script, ScriptOpt, WitnessArgs},
+    packed::{Byte32, CellInput, CellOutput, OutPoint, Script, ScriptOpt, WitnessArgs},
     prelude::*,
     H160, H256,
 };
@@ -15,23 +18,37 @@ use ckb_types::{
 use crate::constants::{
     CHEQUE_CELL_SINCE, DAO_TYPE_HASH, MULTISIG_TYPE_HASH, ONE_CKB, SIGHASH_TYPE_HASH,
 };
-use crate::traits::SecpCkbRawKeySigner;
+use crate::traits::{
+    CellCollector, CellDepResolver, CellQueryOptions, HeaderDepResolver, LiveCell,
+    SecpCkbRawKeySigner,
+};
 use crate::tx_builder::{
     acp::{AcpTransferBuilder, AcpTransferReceiver},
+    auto_complete_transaction, balance_tx_capacity, check_capacity_balance, check_change_output,
     cheque::{ChequeClaimBuilder, ChequeWithdrawBuilder},
     dao::{
         DaoDepositBuilder, DaoDepositReceiver, DaoPrepareBuilder, DaoWithdrawBuilder,
         DaoWithdrawItem, DaoWithdrawReceiver,
     },
+    fill_placeholder_witnesses, fill_placeholder_witnesses_with_report, gen_script_groups,
+    intent::PaymentIntent,
+    preview_witnesses, resolve_cell_deps_for_transaction,
+    store::{read_data, ReadDataError, StoreDataBuilder},
     transfer::CapacityTransferBuilder,
+    tx_fee,
     udt::{UdtIssueBuilder, UdtTargetReceiver, UdtTransferBuilder, UdtType},
-    unlock_tx, CapacityBalancer, TransferAction, TxBuilder,
+    unlock_tx, unlock_tx_with_report, BalanceTxCapacityError, CapacityBalancer, CapacityError,
+    CapacityProvider, ChangeOutput, ChangeStrategy, ResolutionError, ScriptGroups, SinceSource,
+    SmallChangePolicy, TransferAction, TxBuilder, TxBuilderError, WitnessPreview,
 };
 use crate::unlock::{
-    AcpUnlocker, ChequeAction, ChequeUnlocker, MultisigConfig, ScriptUnlocker,
-    SecpMultisigUnlocker, SecpSighashUnlocker,
+    generate_message_for_field, group_witness_index, verify_signatures, AcpUnlocker, ChequeAction,
+    ChequeUnlocker, MultisigConfig, ScriptUnlocker, SecpMultisigUnlocker, SecpSighashUnlocker,
+    UnlockError, WitnessField,
+};
+use crate::util::{
+    blake160, calculate_dao_maximum_withdraw4, minimal_unlock_point, SinceCheckResult,
 };
-use crate::util::{calculate_dao_maximum_withdraw4, minimal_unlock_point};
 use crate::{ScriptId, Since, SinceType};
 
 use crate::test_util::{random_out_point, Context};
@@ -114,6 +131,37 @@ fn build_multisig_unlockers(
     unlockers
 }
 
+// The signatures are written at the previewed positions and nothing else in the
+// witnesses is changed by signing.
+fn assert_signed_at_preview(
+    unsigned_tx: &TransactionView,
+    signed_tx: &TransactionView,
+    previews: &[WitnessPreview],
+) {
+    for preview in previews {
+        let unsigned = unsigned_tx
+            .witnesses()
+            .get(preview.input_index)
+            .unwrap()
+            .raw_data();
+        let signed = signed_tx
+            .witnesses()
+            .get(preview.input_index)
+            .unwrap()
+            .raw_data();
+        assert_eq!(unsigned.len(), preview.total_size);
+        assert_eq!(signed.len(), preview.total_size);
+        let offset = preview.signature_offset.unwrap();
+        let signature = offset..offset + preview.signature_len;
+        assert!(unsigned[signature.clone()].iter().all(|b| *b == 0));
+        for chunk in signed[signature.clone()].chunks(65) {
+            assert!(chunk.iter().any(|b| *b != 0));
+        }
+        assert_eq!(unsigned[..signature.start], signed[..signature.start]);
+        assert_eq!(unsigned[signature.end..], signed[signature.end..]);
+    }
+}
+
 fn init_context(contracts: Vec<(&[u8], bool)>, live_cells: Vec<(Script, Option<u64>)>) -> Context {
     // ckb-cli --url https://testnet.ckb.dev rpc get_block_by_number --number 0 --output-format json --raw-data > genensis_block.json
     let genesis_block: json_types::BlockView = serde_json::from_str(GENESIS_JSON).unwrap();
@@ -182,6 +230,351 @@ fn test_transfer_from_sighash() {
     ctx.verify(tx, FEE_RATE).unwrap();
 }
 
+#[test]
+fn test_transfer_from_immature_cellbase() {
+    let sender = build_sighash_script(ACCOUNT1_ARG);
+    let receiver = build_sighash_script(ACCOUNT2_ARG);
+    let mut ctx = init_context(Vec::new(), vec![(sender.clone(), Some(100 * ONE_CKB))]);
+    let header = HeaderBuilder::default()
+        .number(100.pack())
+        .epoch(EpochNumberWithFraction::new(10, 7, 10).pack())
+        .build();
+    let cellbase_output = CellOutput::new_builder()
+        .capacity((1000 * ONE_CKB).pack())
+        .lock(sender.clone())
+        .build();
+    let cellbase_input = CellInput::new(random_out_point(), 0);
+    ctx.add_live_cell(
+        cellbase_input.clone(),
+        cellbase_output,
+        Bytes::default(),
+        Some(header.hash()),
+    );
+    ctx.add_header(header);
+
+    let output = CellOutput::new_builder()
+        .capacity((500 * ONE_CKB).pack())
+        .lock(receiver)
+        .build();
+    let builder = CapacityTransferBuilder::new(vec![(output, Bytes::default())]);
+    let placeholder_witness = WitnessArgs::new_builder()
+        .lock(Some(Bytes::from(vec![0u8; 65])).pack())
+        .build();
+    let mut balancer =
+        CapacityBalancer::new_simple(sender.clone(), placeholder_witness.clone(), FEE_RATE);
+
+    // the immature cellbase cell is not collected by default
+    let mut cell_collector = ctx.to_live_cells_context();
+    let err = builder
+        .build_balanced(
+            &mut cell_collector,
+            &ctx,
+            &ctx,
+            &ctx,
+            &balancer,
+            &HashMap::new(),
+        )
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        TxBuilderError::BalanceCapacity(BalanceTxCapacityError::CapacityNotEnough(_))
+    ));
+
+    balancer.set_cellbase_maturity(Some(EpochNumberWithFraction::new(4, 0, 1)));
+    let mut cell_collector = ctx.to_live_cells_context();
+    let tx = builder
+        .build_balanced(
+            &mut cell_collector,
+            &ctx,
+            &ctx,
+            &ctx,
+            &balancer,
+            &HashMap::new(),
+        )
+        .unwrap();
+    let mature_since = Since::new(
+        SinceType::EpochNumberWithFraction,
+        EpochNumberWithFraction::new(14, 7, 10).full_value(),
+        false,
+    );
+    let sinces: Vec<(_, u64)> = tx
+        .inputs()
+        .into_iter()
+        .map(|input| (input.previous_output(), input.since().unpack()))
+        .collect();
+    assert!(sinces.contains(&(cellbase_input.previous_output(), mature_since.value())));
+    assert!(sinces
+        .iter()
+        .all(|(out_point, since)| *out_point == cellbase_input.previous_output() || *since == 0));
+}
+
+#[test]
+fn test_store_data() {
+    let sender = build_sighash_script(ACCOUNT1_ARG);
+    let mut ctx = init_context(
+        Vec::new(),
+        vec![
+            (sender.clone(), Some(3000 * ONE_CKB)),
+            (sender.clone(), Some(3000 * ONE_CKB)),
+            (sender.clone(), Some(3000 * ONE_CKB)),
+        ],
+    );
+    let payload = Bytes::from((0..2500u32).map(|i| i as u8).collect::<Vec<_>>());
+    let mut builder = StoreDataBuilder::new(payload.clone(), 1000, sender.clone());
+    builder.max_chunks_size_per_tx = 2500;
+    assert_eq!(
+        builder
+            .chunk_builders()
+            .unwrap()
+            .iter()
+            .map(|builder| builder.outputs.len())
+            .collect::<Vec<_>>(),
+        vec![2, 1]
+    );
+
+    let placeholder_witness = WitnessArgs::new_builder()
+        .lock(Some(Bytes::from(vec![0u8; 65])).pack())
+        .build();
+    let balancer = CapacityBalancer::new_simple(sender, placeholder_witness, FEE_RATE);
+    let account1_key = secp256k1::SecretKey::from_slice(ACCOUNT1_KEY.as_bytes()).unwrap();
+    let signer = SecpCkbRawKeySigner::new_with_secret_keys(vec![account1_key]);
+    let script_unlocker = SecpSighashUnlocker::from(Box::new(signer) as Box<_>);
+    let mut unlockers: HashMap<ScriptId, Box<dyn ScriptUnlocker>> = HashMap::default();
+    unlockers.insert(
+        ScriptId::new_type(SIGHASH_TYPE_HASH.clone()),
+        Box::new(script_unlocker),
+    );
+
+    let mut cell_collector = ctx.to_live_cells_context();
+    let stored = builder
+        .build_unlocked(&mut cell_collector, &ctx, &ctx, &ctx, &balancer, &unlockers)
+        .unwrap();
+    assert_eq!(stored.txs.len(), 3);
+    let (index_tx, _) = stored.txs.last().unwrap();
+    assert_eq!(stored.index_out_point, OutPoint::new(index_tx.hash(), 0));
+    for (tx, locked_groups) in &stored.txs {
+        assert!(locked_groups.is_empty());
+        ctx.verify(tx.clone(), FEE_RATE).unwrap();
+    }
+    for (tx, _) in &stored.txs {
+        for (index, (output, data)) in tx.outputs_with_data_iter().enumerate() {
+            ctx.add_live_cell(
+                CellInput::new(OutPoint::new(tx.hash(), index as u32), 0),
+                output,
+                data,
+                None,
+            );
+        }
+    }
+    assert_eq!(read_data(&ctx, &stored.index_out_point).unwrap(), payload);
+
+    // a chunk cell is changed
+    let (chunk_tx, _) = &stored.txs[0];
+    let chunk_out_point = OutPoint::new(chunk_tx.hash(), 1);
+    let (chunk_output, _) = ctx.get_input(&chunk_out_point).unwrap();
+    ctx.add_live_cell(
+        CellInput::new(chunk_out_point, 0),
+        chunk_output,
+        Bytes::from(vec![0u8; 1000]),
+        None,
+    );
+    assert!(matches!(
+        read_data(&ctx, &stored.index_out_point),
+        Err(ReadDataError::PayloadMismatch { .. })
+    ));
+}
+
+#[test]
+fn test_transfer_built_result() {
+    let sender = build_sighash_script(ACCOUNT1_ARG);
+    let receiver = build_sighash_script(ACCOUNT2_ARG);
+    let ctx = init_context(
+        Vec::new(),
+        vec![
+            (sender.clone(), Some(100 * ONE_CKB)),
+            (sender.clone(), Some(200 * ONE_CKB)),
+        ],
+    );
+
+    let output = CellOutput::new_builder()
+        .capacity((120 * ONE_CKB).pack())
+        .lock(receiver)
+        .build();
+    let builder = CapacityTransferBuilder::new(vec![(output, Bytes::default())]);
+    let placeholder_witness = WitnessArgs::new_builder()
+        .lock(Some(Bytes::from(vec![0u8; 65])).pack())
+        .build();
+    let balancer =
+        CapacityBalancer::new_simple(sender.clone(), placeholder_witness.clone(), FEE_RATE);
+
+    let account1_key = secp256k1::SecretKey::from_slice(ACCOUNT1_KEY.as_bytes()).unwrap();
+    let signer = SecpCkbRawKeySigner::new_with_secret_keys(vec![account1_key]);
+    let script_unlocker = SecpSighashUnlocker::from(Box::new(signer) as Box<_>);
+    let mut unlockers: HashMap<ScriptId, Box<dyn ScriptUnlocker>> = HashMap::default();
+    unlockers.insert(
+        ScriptId::new_type(SIGHASH_TYPE_HASH.clone()),
+        Box::new(script_unlocker),
+    );
+
+    let mut cell_collector = ctx.to_live_cells_context();
+    let (built_tx, locked_groups) = builder
+        .build_unlocked_result(&mut cell_collector, &ctx, &ctx, &ctx, &balancer, &unlockers)
+        .unwrap();
+
+    assert!(locked_groups.is_empty());
+    let tx = built_tx.tx.clone();
+    assert_eq!(built_tx.tx_hash, tx.hash());
+    assert_eq!(
+        built_tx.change_outputs,
+        vec![ChangeOutput {
+            index: 1,
+            lock_script: sender,
+        }]
+    );
+    let change_capacity: u64 = tx.output(1).unwrap().capacity().unpack();
+    assert_eq!(
+        built_tx.fee,
+        300 * ONE_CKB - 120 * ONE_CKB - change_capacity
+    );
+    assert_eq!(
+        built_tx.consumed_out_points,
+        tx.input_pts_iter().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        built_tx.change_out_points(),
+        vec![ckb_types::packed::OutPoint::new(tx.hash(), 1)]
+    );
+    ctx.verify(tx, FEE_RATE).unwrap();
+}
+
+#[test]
+fn test_transfer_dry_run() {
+    let sender = build_sighash_script(ACCOUNT1_ARG);
+    let receiver = build_sighash_script(ACCOUNT2_ARG);
+    let ctx = init_context(
+        Vec::new(),
+        vec![
+            (sender.clone(), Some(100 * ONE_CKB)),
+            (sender.clone(), Some(200 * ONE_CKB)),
+        ],
+    );
+
+    let output = CellOutput::new_builder()
+        .capacity((120 * ONE_CKB).pack())
+        .lock(receiver)
+        .build();
+    let builder = CapacityTransferBuilder::new(vec![(output, Bytes::default())]);
+    let placeholder_witness = WitnessArgs::new_builder()
+        .lock(Some(Bytes::from(vec![0u8; 65])).pack())
+        .build();
+    let balancer =
+        CapacityBalancer::new_simple(sender.clone(), placeholder_witness.clone(), FEE_RATE);
+
+    let account1_key = secp256k1::SecretKey::from_slice(ACCOUNT1_KEY.as_bytes()).unwrap();
+    let signer = SecpCkbRawKeySigner::new_with_secret_keys(vec![account1_key]);
+    let script_unlocker = SecpSighashUnlocker::from(Box::new(signer) as Box<_>);
+    let mut unlockers: HashMap<ScriptId, Box<dyn ScriptUnlocker>> = HashMap::default();
+    unlockers.insert(
+        ScriptId::new_type(SIGHASH_TYPE_HASH.clone()),
+        Box::new(script_unlocker),
+    );
+
+    let mut cell_collector = ctx.to_live_cells_context();
+    let (dry_run_tx, estimate) = builder
+        .dry_run(&cell_collector, &ctx, &ctx, &ctx, &balancer, &unlockers)
+        .unwrap();
+    assert_eq!(estimate.inputs_count, 2);
+    assert_eq!(estimate.fee_rate_used, FEE_RATE);
+    assert_eq!(estimate.cycles, None);
+    // the collector is not changed, dry run again get the same result
+    let (_, estimate_again) = builder
+        .dry_run(&cell_collector, &ctx, &ctx, &ctx, &balancer, &unlockers)
+        .unwrap();
+    assert_eq!(estimate_again, estimate);
+
+    let (tx, locked_groups) = builder
+        .build_unlocked(&mut cell_collector, &ctx, &ctx, &ctx, &balancer, &unlockers)
+        .unwrap();
+    assert!(locked_groups.is_empty());
+    assert_eq!(
+        tx.input_pts_iter().collect::<Vec<_>>(),
+        dry_run_tx.input_pts_iter().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        tx.data().as_reader().serialized_size_in_block() as u64,
+        estimate.tx_size
+    );
+    assert_eq!(tx_fee(tx.clone(), &ctx, &ctx).unwrap(), estimate.fee);
+    ctx.verify(tx, FEE_RATE).unwrap();
+}
+
+#[test]
+fn test_transfer_exceed_size_limit() {
+    let sender = build_sighash_script(ACCOUNT1_ARG);
+    let receiver = build_sighash_script(ACCOUNT2_ARG);
+    let ctx = init_context(Vec::new(), vec![(sender.clone(), Some(1000 * ONE_CKB)); 3]);
+
+    let output = CellOutput::new_builder()
+        .capacity((100 * ONE_CKB).pack())
+        .lock(receiver)
+        .build();
+    let builder = CapacityTransferBuilder::new(vec![(output, Bytes::default()); 6]);
+    let placeholder_witness = WitnessArgs::new_builder()
+        .lock(Some(Bytes::from(vec![0u8; 65])).pack())
+        .build();
+    let mut balancer =
+        CapacityBalancer::new_simple(sender.clone(), placeholder_witness.clone(), FEE_RATE);
+    balancer.set_max_tx_size(Some(600));
+
+    let account1_key = secp256k1::SecretKey::from_slice(ACCOUNT1_KEY.as_bytes()).unwrap();
+    let signer = SecpCkbRawKeySigner::new_with_secret_keys(vec![account1_key]);
+    let script_unlocker = SecpSighashUnlocker::from(Box::new(signer) as Box<_>);
+    let mut unlockers: HashMap<ScriptId, Box<dyn ScriptUnlocker>> = HashMap::default();
+    unlockers.insert(
+        ScriptId::new_type(SIGHASH_TYPE_HASH.clone()),
+        Box::new(script_unlocker),
+    );
+
+    let mut cell_collector = ctx.to_live_cells_context();
+    let err = builder
+        .build_unlocked(&mut cell_collector, &ctx, &ctx, &ctx, &balancer, &unlockers)
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        TxBuilderError::BalanceCapacity(BalanceTxCapacityError::ExceedTxSizeLimit(_, 600, _))
+    ));
+
+    let builders = builder.split_by_size(300);
+    assert_eq!(builders.len(), 3);
+    for split_builder in &builders {
+        assert_eq!(split_builder.outputs.len(), 2);
+        let mut cell_collector = ctx.to_live_cells_context();
+        let (tx, locked_groups) = split_builder
+            .build_unlocked(&mut cell_collector, &ctx, &ctx, &ctx, &balancer, &unlockers)
+            .unwrap();
+        assert!(locked_groups.is_empty());
+        assert!(tx.data().as_reader().serialized_size_in_block() <= 600);
+        ctx.verify(tx, FEE_RATE).unwrap();
+    }
+
+    balancer.set_auto_split(true);
+    let mut cell_collector = ctx.to_live_cells_context();
+    let txs = builder
+        .build_split_unlocked(&mut cell_collector, &ctx, &ctx, &ctx, &balancer, &unlockers)
+        .unwrap();
+    assert_eq!(txs.len(), 3);
+    let mut spent = HashSet::new();
+    for (tx, locked_groups) in txs {
+        assert!(locked_groups.is_empty());
+        assert_eq!(tx.outputs().len(), 3);
+        assert!(tx.data().as_reader().serialized_size_in_block() <= 600);
+        for input in tx.inputs() {
+            assert!(spent.insert(input.previous_output()));
+        }
+        ctx.verify(tx, FEE_RATE).unwrap();
+    }
+}
+
 #[test]
 fn test_transfer_from_multisig() {
     let lock_args = vec![
@@ -249,6 +642,145 @@ fn test_transfer_from_multisig() {
     ctx.verify(tx, FEE_RATE).unwrap();
 }
 
+// transfer from a multisig config with `n` addresses, the first `threshold` addresses
+// are signed, return the signed transaction and the context. The keys and the other
+// addresses are generated from `seed`.
+fn transfer_from_large_multisig(
+    n: usize,
+    require_first_n: u8,
+    threshold: u8,
+    seed: u64,
+) -> (ckb_types::core::TransactionView, Context, MultisigConfig) {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    let case = format!(
+        "seed={}, (n, require_first_n, threshold)=({}, {}, {})",
+        seed, n, require_first_n, threshold
+    );
+    let mut rng = StdRng::seed_from_u64(seed);
+    let keys: Vec<secp256k1::SecretKey> = (0..threshold)
+        .map(|_| loop {
+            let mut data = [0u8; 32];
+            rng.fill(&mut data[..]);
+            if let Ok(key) = secp256k1::SecretKey::from_slice(&data) {
+                break key;
+            }
+        })
+        .collect();
+    let mut lock_args: Vec<H160> = keys
+        .iter()
+        .map(|key| {
+            let pubkey = secp256k1::PublicKey::from_secret_key(&crate::SECP256K1, key);
+            crate::util::blake160(&pubkey.serialize())
+        })
+        .collect();
+    while lock_args.len() < n {
+        let mut arg = [0u8; 20];
+        rng.fill(&mut arg[..]);
+        lock_args.push(H160(arg));
+    }
+    let cfg = MultisigConfig::new_with(lock_args, require_first_n, threshold)
+        .unwrap_or_else(|err| panic!("{}: {}", case, err));
+    assert_eq!(
+        cfg.witness_lock_len(),
+        4 + 20 * n + 65 * threshold as usize,
+        "{}",
+        case
+    );
+    assert_eq!(
+        cfg.placeholder_witness_lock().len(),
+        cfg.witness_lock_len(),
+        "{}",
+        case
+    );
+
+    let sender = build_multisig_script(&cfg);
+    let ctx = init_context(
+        Vec::new(),
+        vec![
+            (sender.clone(), Some(100 * ONE_CKB)),
+            (sender.clone(), Some(200 * ONE_CKB)),
+        ],
+    );
+    let output = CellOutput::new_builder()
+        .capacity((120 * ONE_CKB).pack())
+        .lock(build_sighash_script(ACCOUNT2_ARG))
+        .build();
+    let builder = CapacityTransferBuilder::new(vec![(output, Bytes::default())]);
+    let placeholder_witness = cfg.placeholder_witness();
+    let balancer = CapacityBalancer::new_simple(sender, placeholder_witness.clone(), FEE_RATE);
+    let signer = SecpCkbRawKeySigner::new_with_secret_keys(keys);
+    let mut unlockers: HashMap<ScriptId, Box<dyn ScriptUnlocker>> = HashMap::default();
+    unlockers.insert(
+        ScriptId::new_type(MULTISIG_TYPE_HASH.clone()),
+        Box::new(SecpMultisigUnlocker::from((
+            Box::new(signer) as Box<_>,
+            cfg.clone(),
+        ))),
+    );
+
+    let mut cell_collector = ctx.to_live_cells_context();
+    let unsigned_tx = builder
+        .build_balanced(&mut cell_collector, &ctx, &ctx, &ctx, &balancer, &unlockers)
+        .unwrap_or_else(|err| panic!("{}: {}", case, err));
+    let (tx, locked_groups) = unlock_tx(unsigned_tx.clone(), &ctx, &unlockers)
+        .unwrap_or_else(|err| panic!("{}: {}", case, err));
+    assert!(locked_groups.is_empty(), "{}", case);
+    // the placeholder has the exact size, the fee estimated before signing is exact
+    assert_eq!(
+        tx.witnesses().get(0).unwrap().raw_data().len(),
+        placeholder_witness.as_slice().len(),
+        "{}",
+        case
+    );
+    assert_eq!(
+        tx.data().as_reader().serialized_size_in_block(),
+        unsigned_tx.data().as_reader().serialized_size_in_block(),
+        "{}",
+        case
+    );
+    assert!(
+        crate::unlock::verify_signatures(&tx, &ctx, &[])
+            .unwrap_or_else(|err| panic!("{}: {}", case, err))
+            .is_empty(),
+        "{}",
+        case
+    );
+    (tx, ctx, cfg)
+}
+
+#[test]
+fn test_large_multisig_witness_size() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    // the seed is included in the failure messages to reproduce the cases
+    let seed: u64 = rand::thread_rng().gen();
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut cases = vec![(1, 0, 1), (255, 0, 1), (255, 255, 255)];
+    for _ in 0..8 {
+        let n = rng.gen_range(1, 256);
+        let threshold = rng.gen_range(1, n + 1);
+        let require_first_n = rng.gen_range(0, threshold + 1);
+        cases.push((n, require_first_n, threshold));
+    }
+    for (n, require_first_n, threshold) in cases {
+        transfer_from_large_multisig(n, require_first_n as u8, threshold as u8, seed);
+    }
+
+    // run the multisig script for the largest config
+    let (tx, ctx, _) = transfer_from_large_multisig(255, 2, 3, seed);
+    ctx.verify(tx, FEE_RATE).unwrap();
+
+    let lock_args: Vec<H160> = (0..=255u16)
+        .map(|i| H160::from_slice(&[&i.to_le_bytes()[..], &[0u8; 18]].concat()).unwrap())
+        .collect();
+    assert!(MultisigConfig::new_with(lock_args.clone(), 0, 1).is_err());
+    assert!(MultisigConfig::new_with(lock_args[..255].to_vec(), 0, 1).is_ok());
+    assert!(MultisigConfig::new_with(lock_args[..3].to_vec(), 0, 0).is_err());
+    assert!(MultisigConfig::new_with(lock_args[..3].to_vec(), 0, 4).is_err());
+    assert!(MultisigConfig::new_with(Vec::new(), 0, 0).is_err());
+}
+
 #[test]
 fn test_transfer_from_acp() {
     let data_hash = H256::from(blake2b_256(ACP_BIN));
@@ -607,24 +1139,251 @@ fn test_cheque_withdraw() {
     ctx.verify(tx, FEE_RATE).unwrap();
 }
 
+// The header dep resolver of the context knowing the tip
+struct TipResolver<'a> {
+    ctx: &'a Context,
+    tip: HeaderView,
+}
+
+impl<'a> HeaderDepResolver for TipResolver<'a> {
+    fn resolve_by_tx(&self, tx_hash: &Byte32) -> Result<Option<HeaderView>, anyhow::Error> {
+        self.ctx.resolve_by_tx(tx_hash)
+    }
+    fn resolve_by_number(&self, number: u64) -> Result<Option<HeaderView>, anyhow::Error> {
+        self.ctx.resolve_by_number(number)
+    }
+    fn resolve_tip(&self) -> Result<Option<HeaderView>, anyhow::Error> {
+        Ok(Some(self.tip.clone()))
+    }
+}
+
 #[test]
-fn test_dao_deposit() {
+fn test_cheque_withdraw_check_since() {
+    let sudt_data_hash = H256::from(blake2b_256(SUDT_BIN));
+    let cheque_data_hash = H256::from(blake2b_256(CHEQUE_BIN));
     let sender = build_sighash_script(ACCOUNT1_ARG);
-    let ctx = init_context(
-        Vec::new(),
-        vec![
-            (sender.clone(), Some(100 * ONE_CKB)),
-            (sender.clone(), Some(200 * ONE_CKB)),
-            (sender.clone(), Some(300 * ONE_CKB)),
-        ],
-    );
-
-    let deposit_receiver = DaoDepositReceiver::new(sender.clone(), 120 * ONE_CKB);
-    let builder = DaoDepositBuilder::new(vec![deposit_receiver]);
-    let placeholder_witness = WitnessArgs::new_builder()
-        .lock(Some(Bytes::from(vec![0u8; 65])).pack())
+    let receiver = build_sighash_script(ACCOUNT2_ARG);
+    let cheque_script = build_cheque_script(&sender, &receiver, cheque_data_hash);
+    let type_script = Script::new_builder()
+        .code_hash(sudt_data_hash.pack())
+        .hash_type(ScriptHashType::Data1.into())
+        .args(Bytes::from(vec![9u8; 32]).pack())
         .build();
-    let balancer =
+    let mut ctx = init_context(vec![(CHEQUE_BIN, true), (SUDT_BIN, false)], Vec::new());
+    let cheque_header = HeaderBuilder::default()
+        .number(1000.pack())
+        .epoch(EpochNumberWithFraction::new(10, 0, 1000).pack())
+        .build();
+    let cheque_out_point = random_out_point();
+    let cheque_output = CellOutput::new_builder()
+        .capacity((220 * ONE_CKB).pack())
+        .lock(cheque_script)
+        .type_(Some(type_script).pack())
+        .build();
+    ctx.add_live_cell(
+        CellInput::new(cheque_out_point.clone(), CHEQUE_CELL_SINCE),
+        cheque_output,
+        Bytes::from(500u128.to_le_bytes().to_vec()),
+        Some(cheque_header.hash()),
+    );
+    ctx.add_header(cheque_header);
+
+    let mut builder = ChequeWithdrawBuilder::new(vec![cheque_out_point.clone()], sender, None);
+    let build_at = |builder: &ChequeWithdrawBuilder, epoch: EpochNumberWithFraction| {
+        let tip = HeaderBuilder::default()
+            .number(2000.pack())
+            .epoch(epoch.pack())
+            .build();
+        let resolver = TipResolver { ctx: &ctx, tip };
+        let mut cell_collector = ctx.to_live_cells_context();
+        builder.build_base(&mut cell_collector, &ctx, &resolver, &ctx)
+    };
+    // the since is not checked by default
+    let tx = build_at(&builder, EpochNumberWithFraction::new(12, 0, 1000)).unwrap();
+    assert_eq!(tx.inputs().len(), 1);
+
+    // the cheque cell can be withdrawn after 6 epochs
+    builder.set_check_since(true);
+    match build_at(&builder, EpochNumberWithFraction::new(12, 0, 1000)) {
+        Err(TxBuilderError::SinceNotSatisfied(out_point, result)) => {
+            assert_eq!(out_point, cheque_out_point);
+            assert_eq!(
+                result,
+                SinceCheckResult::EarliestEpoch(EpochNumberWithFraction::new(16, 0, 1000))
+            );
+        }
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+    let tx = build_at(&builder, EpochNumberWithFraction::new(16, 0, 1000)).unwrap();
+    assert_eq!(tx.inputs().len(), 1);
+}
+
+#[test]
+fn test_multisig_timelock_check_since() {
+    let cfg = MultisigConfig::new_with(vec![ACCOUNT1_ARG, ACCOUNT2_ARG], 0, 1).unwrap();
+    let since = Since::new_absolute_epoch(200);
+    let mut lock_args = cfg.hash160().as_bytes().to_vec();
+    lock_args.extend_from_slice(&since.value().to_le_bytes());
+    let sender = build_multisig_script(&cfg)
+        .as_builder()
+        .args(Bytes::from(lock_args).pack())
+        .build();
+    let receiver = build_sighash_script(ACCOUNT3_ARG);
+    let ctx = init_context(Vec::new(), vec![(sender.clone(), Some(300 * ONE_CKB))]);
+    let tx = ckb_types::core::TransactionBuilder::default()
+        .output(
+            CellOutput::new_builder()
+                .capacity((200 * ONE_CKB).pack())
+                .lock(receiver)
+                .build(),
+        )
+        .output_data(Bytes::default().pack())
+        .build();
+    let placeholder_witness = cfg.placeholder_witness();
+    let mut balancer = CapacityBalancer::new_simple_with_since(
+        sender.clone(),
+        placeholder_witness.clone(),
+        SinceSource::LockArgs(20),
+        FEE_RATE,
+    );
+    let balance_at = |balancer: &CapacityBalancer, epoch_number: u64| {
+        let tip = HeaderBuilder::default()
+            .number(2000.pack())
+            .epoch(EpochNumberWithFraction::new(epoch_number, 0, 1000).pack())
+            .build();
+        let resolver = TipResolver { ctx: &ctx, tip };
+        let mut cell_collector = ctx.to_live_cells_context();
+        balance_tx_capacity(&tx, balancer, &mut cell_collector, &ctx, &ctx, &resolver)
+    };
+    // the since is not checked by default
+    assert!(balance_at(&balancer, 199).is_ok());
+
+    balancer.set_check_since(true);
+    match balance_at(&balancer, 199) {
+        Err(BalanceTxCapacityError::SinceNotSatisfied(_, result)) => assert_eq!(
+            result,
+            SinceCheckResult::EarliestEpoch(EpochNumberWithFraction::new(200, 0, 1))
+        ),
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+    let tx = balance_at(&balancer, 199 + 1).unwrap();
+    let input_since: u64 = tx.inputs().get(0).unwrap().since().unpack();
+    assert_eq!(input_since, since.value());
+}
+
+#[test]
+fn test_balance_skip_time_locked_cells() {
+    let cfg = MultisigConfig::new_with(vec![ACCOUNT1_ARG, ACCOUNT2_ARG], 0, 1).unwrap();
+    let since = Since::new_absolute_epoch(200);
+    let mut lock_args = cfg.hash160().as_bytes().to_vec();
+    lock_args.extend_from_slice(&since.value().to_le_bytes());
+    let time_locked = build_multisig_script(&cfg)
+        .as_builder()
+        .args(Bytes::from(lock_args).pack())
+        .build();
+    let sender = build_sighash_script(ACCOUNT1_ARG);
+    let receiver = build_sighash_script(ACCOUNT3_ARG);
+    let mut ctx = init_context(
+        Vec::new(),
+        vec![
+            (time_locked.clone(), Some(300 * ONE_CKB)),
+            (sender.clone(), Some(300 * ONE_CKB)),
+        ],
+    );
+    // a time-locked cell created by a transaction not on chain, the since is unknown
+    let relative_since = Since::new(SinceType::BlockNumber, 10, true).value();
+    let offchain_out_point = random_out_point();
+    ctx.add_live_cell(
+        CellInput::new(offchain_out_point.clone(), 0),
+        CellOutput::new_builder()
+            .capacity((300 * ONE_CKB).pack())
+            .lock(build_sighash_script(ACCOUNT2_ARG))
+            .build(),
+        Bytes::default(),
+        None,
+    );
+    let tx = ckb_types::core::TransactionBuilder::default()
+        .output(
+            CellOutput::new_builder()
+                .capacity((200 * ONE_CKB).pack())
+                .lock(receiver)
+                .build(),
+        )
+        .output_data(Bytes::default().pack())
+        .build();
+    let placeholder_witness = WitnessArgs::new_builder()
+        .lock(Some(Bytes::from(vec![0u8; 65])).pack())
+        .build();
+    let mut balancer = CapacityBalancer::new_with_provider(
+        FEE_RATE,
+        CapacityProvider::new(vec![
+            (
+                time_locked.clone(),
+                cfg.placeholder_witness(),
+                SinceSource::LockArgs(20),
+            ),
+            (
+                sender.clone(),
+                placeholder_witness.clone(),
+                SinceSource::Value(0),
+            ),
+        ]),
+    );
+    balancer.set_check_since(true);
+    let tip = HeaderBuilder::default()
+        .number(2000.pack())
+        .epoch(EpochNumberWithFraction::new(199, 0, 1000).pack())
+        .build();
+    let resolver = TipResolver { ctx: &ctx, tip };
+
+    // the time-locked cell is skipped, the cell of the next lock script is used
+    let mut cell_collector = ctx.to_live_cells_context();
+    let balanced =
+        balance_tx_capacity(&tx, &balancer, &mut cell_collector, &ctx, &ctx, &resolver).unwrap();
+    assert_eq!(balanced.inputs().len(), 1);
+    let input = ctx.get_input(&balanced.inputs().get(0).unwrap().previous_output());
+    assert_eq!(input.unwrap().0.lock(), sender);
+    // the skipped cell is not locked
+    let (cells, _) = cell_collector
+        .collect_live_cells(&CellQueryOptions::new_lock(time_locked), false)
+        .unwrap();
+    assert_eq!(cells.len(), 1);
+
+    // the header of the offchain cell is unknown, it's not skipped
+    let mut balancer = CapacityBalancer::new_simple_with_since(
+        build_sighash_script(ACCOUNT2_ARG),
+        placeholder_witness,
+        SinceSource::Value(relative_since),
+        FEE_RATE,
+    );
+    balancer.set_check_since(true);
+    let mut cell_collector = ctx.to_live_cells_context();
+    let balanced =
+        balance_tx_capacity(&tx, &balancer, &mut cell_collector, &ctx, &ctx, &resolver).unwrap();
+    assert_eq!(
+        balanced.inputs().get(0).unwrap().previous_output(),
+        offchain_out_point
+    );
+}
+
+#[test]
+fn test_dao_deposit() {
+    let sender = build_sighash_script(ACCOUNT1_ARG);
+    let ctx = init_context(
+        Vec::new(),
+        vec![
+            (sender.clone(), Some(100 * ONE_CKB)),
+            (sender.clone(), Some(200 * ONE_CKB)),
+            (sender.clone(), Some(300 * ONE_CKB)),
+        ],
+    );
+
+    let deposit_receiver = DaoDepositReceiver::new(sender.clone(), 120 * ONE_CKB);
+    let builder = DaoDepositBuilder::new(vec![deposit_receiver]);
+    let placeholder_witness = WitnessArgs::new_builder()
+        .lock(Some(Bytes::from(vec![0u8; 65])).pack())
+        .build();
+    let balancer =
         CapacityBalancer::new_simple(sender.clone(), placeholder_witness.clone(), FEE_RATE);
 
     let account1_key = secp256k1::SecretKey::from_slice(ACCOUNT1_KEY.as_bytes()).unwrap();
@@ -1086,9 +1845,831 @@ fn test_udt_transfer() {
     ctx.verify(tx, FEE_RATE).unwrap();
 }
 
+#[test]
+fn test_check_capacity_balance() {
+    let lock = build_sighash_script(ACCOUNT1_ARG);
+    let live_cell = |capacity: u64| LiveCell {
+        output: CellOutput::new_builder()
+            .lock(lock.clone())
+            .capacity(capacity.pack())
+            .build(),
+        output_data: Bytes::default(),
+        out_point: random_out_point(),
+        block_number: 1,
+        tx_index: 0,
+    };
+    let output = |capacity: u64| CellOutput::new_builder().capacity(capacity.pack()).build();
+    let inputs = vec![live_cell(100 * ONE_CKB), live_cell(200 * ONE_CKB)];
+
+    assert_eq!(
+        check_capacity_balance(&inputs, &[output(120 * ONE_CKB), output(179 * ONE_CKB)]),
+        Ok(ONE_CKB)
+    );
+    assert_eq!(
+        check_capacity_balance(&inputs, &[output(300 * ONE_CKB)]),
+        Ok(0)
+    );
+    assert_eq!(
+        check_capacity_balance(&inputs, &[output(250 * ONE_CKB), output(51 * ONE_CKB)]),
+        Err(CapacityError::OutputsExceedInputs { excess: ONE_CKB })
+    );
+    assert_eq!(
+        check_capacity_balance(&[], &[output(u64::MAX), output(1)]),
+        Err(CapacityError::OutputsExceedInputs { excess: u64::MAX })
+    );
+}
+
+#[test]
+fn test_resolve_cell_deps_for_transaction() {
+    let sender = build_sighash_script(ACCOUNT1_ARG);
+    let mut ctx = init_context(
+        vec![(SUDT_BIN, false)],
+        vec![(sender.clone(), None), (sender.clone(), None)],
+    );
+    let sudt_script = Script::new_builder()
+        .code_hash(H256::from(blake2b_256(SUDT_BIN)).pack())
+        .hash_type(ScriptHashType::Data1.into())
+        .build();
+    let sudt_input = CellInput::new(random_out_point(), 0);
+    let sudt_output = CellOutput::new_builder()
+        .lock(sender.clone())
+        .type_(Some(sudt_script.clone()).pack())
+        .build();
+    ctx.add_live_cell(
+        sudt_input.clone(),
+        sudt_output,
+        Bytes::from(100u128.to_le_bytes().to_vec()),
+        None,
+    );
+    let mut tx_builder = ckb_types::core::TransactionBuilder::default().input(sudt_input);
+    for mock_input in &ctx.inputs {
+        if mock_input.output.type_().is_none() {
+            tx_builder = tx_builder.input(mock_input.input.clone());
+        }
+    }
+    let tx = tx_builder.build();
+
+    let cell_deps = resolve_cell_deps_for_transaction(&tx, &ctx, &ctx).unwrap();
+    assert_eq!(
+        cell_deps,
+        vec![
+            ctx.resolve(&sender).unwrap(),
+            ctx.resolve(&sudt_script).unwrap()
+        ]
+    );
+
+    let unknown = Script::new_builder()
+        .code_hash(h256!("0x1234").pack())
+        .hash_type(ScriptHashType::Type.into())
+        .build();
+    let unknown_input = CellInput::new(random_out_point(), 0);
+    ctx.add_live_cell(
+        unknown_input.clone(),
+        CellOutput::new_builder().lock(unknown.clone()).build(),
+        Bytes::new(),
+        None,
+    );
+    let tx = tx.as_advanced_builder().input(unknown_input).build();
+    match resolve_cell_deps_for_transaction(&tx, &ctx, &ctx) {
+        Err(ResolutionError::UnknownScript(script_id)) => {
+            assert_eq!(script_id, ScriptId::from(&unknown));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn test_auto_complete_transaction() {
+    let sender = build_sighash_script(ACCOUNT1_ARG);
+    let receiver = build_sighash_script(ACCOUNT2_ARG);
+    let ctx = init_context(
+        Vec::new(),
+        vec![
+            (sender.clone(), Some(100 * ONE_CKB)),
+            (sender.clone(), Some(200 * ONE_CKB)),
+        ],
+    );
+    let output = CellOutput::new_builder()
+        .capacity((120 * ONE_CKB).pack())
+        .lock(receiver)
+        .build();
+    // the trailing plain cell of the change lock is adjusted as the change output
+    let change_output = CellOutput::new_builder().lock(sender.clone()).build();
+    let skeleton = ckb_types::core::TransactionBuilder::default()
+        .input(ctx.inputs[0].input.clone())
+        .output(output.clone())
+        .output_data(Bytes::default().pack())
+        .output(change_output)
+        .output_data(Bytes::default().pack())
+        .build();
+
+    let mut cell_collector = ctx.to_live_cells_context();
+    cell_collector.used_inputs.insert(0);
+    let tx = auto_complete_transaction(
+        skeleton,
+        &mut cell_collector,
+        &ctx,
+        &ctx,
+        sender.clone(),
+        FEE_RATE,
+    )
+    .unwrap();
+    assert_eq!(tx.cell_deps().len(), 1);
+    assert_eq!(tx.inputs().len(), 2);
+    assert_eq!(tx.outputs().len(), 2);
+    assert_eq!(tx.output(0).unwrap(), output);
+    assert_eq!(tx.output(1).unwrap().lock(), sender);
+    let witnesses_len = tx
+        .witnesses()
+        .into_iter()
+        .map(|w| w.raw_data().len())
+        .collect::<Vec<_>>();
+    assert_eq!(witnesses_len, vec![85, 0]);
+
+    let account1_key = secp256k1::SecretKey::from_slice(ACCOUNT1_KEY.as_bytes()).unwrap();
+    let signer = SecpCkbRawKeySigner::new_with_secret_keys(vec![account1_key]);
+    let mut unlockers: HashMap<ScriptId, Box<dyn ScriptUnlocker>> = HashMap::default();
+    unlockers.insert(
+        ScriptId::new_type(SIGHASH_TYPE_HASH.clone()),
+        Box::new(SecpSighashUnlocker::from(Box::new(signer) as Box<_>)),
+    );
+    let (tx, locked_groups) = unlock_tx(tx, &ctx, &unlockers).unwrap();
+    assert!(locked_groups.is_empty());
+    ctx.verify(tx, FEE_RATE).unwrap();
+}
+
+#[test]
+fn test_placeholder_report() {
+    let sender1 = build_sighash_script(ACCOUNT1_ARG);
+    let sender2 = build_sighash_script(ACCOUNT2_ARG);
+    let receiver = build_sighash_script(ACCOUNT3_ARG);
+    let ctx = init_context(
+        Vec::new(),
+        vec![
+            (sender1.clone(), Some(100 * ONE_CKB)),
+            (sender2.clone(), Some(100 * ONE_CKB)),
+        ],
+    );
+    let tx = ckb_types::core::TransactionBuilder::default()
+        .cell_dep(ctx.resolve(&sender1).unwrap())
+        .input(ctx.inputs[0].input.clone())
+        .input(ctx.inputs[1].input.clone())
+        .output(
+            CellOutput::new_builder()
+                .capacity((199 * ONE_CKB).pack())
+                .lock(receiver)
+                .build(),
+        )
+        .output_data(Bytes::default().pack())
+        .build();
+    let build_unlockers = |keys: &[&H256]| {
+        let keys = keys
+            .iter()
+            .map(|key| secp256k1::SecretKey::from_slice(key.as_bytes()).unwrap())
+            .collect();
+        let signer = SecpCkbRawKeySigner::new_with_secret_keys(keys);
+        let mut unlockers: HashMap<ScriptId, Box<dyn ScriptUnlocker>> = HashMap::default();
+        unlockers.insert(
+            ScriptId::new_type(SIGHASH_TYPE_HASH.clone()),
+            Box::new(SecpSighashUnlocker::from(Box::new(signer) as Box<_>)),
+        );
+        unlockers
+    };
+
+    // only the first group is claimed
+    let unlockers = build_unlockers(&[&ACCOUNT1_KEY]);
+    let (filled_tx, report) =
+        fill_placeholder_witnesses_with_report(tx.clone(), &ctx, &unlockers, false).unwrap();
+    assert_eq!(report.groups.len(), 2);
+    assert_eq!(report.groups[0].script_group.script, sender1);
+    assert_eq!(report.groups[0].script_group.input_indices, vec![0]);
+    assert_eq!(report.groups[0].placeholder_size, 85);
+    assert!(report.groups[0].claimed);
+    assert_eq!(report.groups[1].script_group.script, sender2);
+    assert_eq!(report.groups[1].placeholder_size, 0);
+    assert!(!report.groups[1].claimed);
+    assert!(!report.is_all_claimed());
+    match fill_placeholder_witnesses_with_report(tx.clone(), &ctx, &unlockers, true) {
+        Err(UnlockError::UnclaimedScriptGroups(scripts)) => assert_eq!(scripts, vec![sender2]),
+        other => panic!("unexpected result: {:?}", other.map(|(_, report)| report)),
+    }
+    let (_, not_matched) = fill_placeholder_witnesses(tx.clone(), &ctx, &unlockers).unwrap();
+    assert_eq!(not_matched, vec![report.groups[1].script_group.clone()]);
+
+    let (_, unlock_report) =
+        unlock_tx_with_report(filled_tx, &ctx, &unlockers, Some(&report)).unwrap();
+    assert!(unlock_report.groups[0].unlocked);
+    assert_eq!(unlock_report.groups[0].placeholder_size, 85);
+    assert!(!unlock_report.groups[1].unlocked);
+
+    // all the groups are claimed
+    let unlockers = build_unlockers(&[&ACCOUNT1_KEY, &ACCOUNT2_KEY]);
+    let (filled_tx, report) =
+        fill_placeholder_witnesses_with_report(tx, &ctx, &unlockers, true).unwrap();
+    assert!(report.is_all_claimed());
+    let (tx, unlock_report) =
+        unlock_tx_with_report(filled_tx, &ctx, &unlockers, Some(&report)).unwrap();
+    assert!(unlock_report.groups.iter().all(|group| group.unlocked));
+    ctx.verify(tx, 0).unwrap();
+}
+
+#[test]
+fn test_unlock_type_script_groups() {
+    let sender = build_sighash_script(ACCOUNT1_ARG);
+    let receiver = build_sighash_script(ACCOUNT3_ARG);
+    // type scripts requiring a signature of ACCOUNT2 in the type fields of the witnesses
+    let build_governance_script = |code_hash: H256| {
+        Script::new_builder()
+            .code_hash(code_hash.pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(ACCOUNT2_ARG.0.to_vec()).pack())
+            .build()
+    };
+    let input_type = build_governance_script(h256!("0x1234"));
+    let output_type = build_governance_script(h256!("0x5678"));
+    let mut ctx = init_context(Vec::new(), vec![(sender.clone(), Some(100 * ONE_CKB))]);
+    let typed_input = CellInput::new(random_out_point(), 0);
+    ctx.add_live_cell(
+        typed_input.clone(),
+        CellOutput::new_builder()
+            .capacity((200 * ONE_CKB).pack())
+            .lock(sender.clone())
+            .type_(Some(input_type.clone()).pack())
+            .build(),
+        Bytes::default(),
+        None,
+    );
+    let tx = ckb_types::core::TransactionBuilder::default()
+        .cell_dep(ctx.resolve(&sender).unwrap())
+        .input(typed_input)
+        .input(ctx.inputs[0].input.clone())
+        .output(
+            CellOutput::new_builder()
+                .capacity((150 * ONE_CKB).pack())
+                .lock(receiver.clone())
+                .type_(Some(input_type.clone()).pack())
+                .build(),
+        )
+        .output(
+            CellOutput::new_builder()
+                .capacity((149 * ONE_CKB).pack())
+                .lock(receiver)
+                .type_(Some(output_type.clone()).pack())
+                .build(),
+        )
+        .output_data(Bytes::default().pack())
+        .output_data(Bytes::default().pack())
+        .build();
+
+    let build_unlocker = |key: &H256| -> Box<dyn ScriptUnlocker> {
+        let key = secp256k1::SecretKey::from_slice(key.as_bytes()).unwrap();
+        let signer = SecpCkbRawKeySigner::new_with_secret_keys(vec![key]);
+        Box::new(SecpSighashUnlocker::from(Box::new(signer) as Box<_>))
+    };
+    let mut unlockers: HashMap<ScriptId, Box<dyn ScriptUnlocker>> = HashMap::default();
+    unlockers.insert(
+        ScriptId::new_type(SIGHASH_TYPE_HASH.clone()),
+        build_unlocker(&ACCOUNT1_KEY),
+    );
+    unlockers.insert(ScriptId::from(&input_type), build_unlocker(&ACCOUNT2_KEY));
+    unlockers.insert(ScriptId::from(&output_type), build_unlocker(&ACCOUNT2_KEY));
+
+    let (tx, not_matched) = fill_placeholder_witnesses(tx, &ctx, &unlockers).unwrap();
+    assert!(not_matched.is_empty());
+    let witness = |tx: &TransactionView, index: usize| {
+        WitnessArgs::from_slice(&tx.witnesses().get(index).unwrap().raw_data()).unwrap()
+    };
+    let zero_sig = Bytes::from(vec![0u8; 65]);
+    assert_eq!(
+        witness(&tx, 0).lock().to_opt().unwrap().raw_data(),
+        zero_sig
+    );
+    assert_eq!(
+        witness(&tx, 0).input_type().to_opt().unwrap().raw_data(),
+        zero_sig
+    );
+    assert!(witness(&tx, 1).lock().is_none());
+    assert_eq!(
+        witness(&tx, 1).output_type().to_opt().unwrap().raw_data(),
+        zero_sig
+    );
+
+    let (tx, still_locked_groups) = unlock_tx(tx, &ctx, &unlockers).unwrap();
+    assert!(still_locked_groups.is_empty());
+    // the lock signatures are generated after the type fields are signed
+    assert!(verify_signatures(&tx, &ctx, &[]).unwrap().is_empty());
+
+    let ScriptGroups { type_groups, .. } = gen_script_groups(&tx, &ctx).unwrap();
+    for (group, field) in [
+        (
+            &type_groups[&input_type.calc_script_hash()],
+            WitnessField::InputType,
+        ),
+        (
+            &type_groups[&output_type.calc_script_hash()],
+            WitnessField::OutputType,
+        ),
+    ] {
+        assert_eq!(WitnessField::of_group(group), field);
+        let signature = field
+            .get(&witness(&tx, group_witness_index(group)))
+            .unwrap();
+        let message = generate_message_for_field(&tx, group, field, zero_sig.clone()).unwrap();
+        let recovery_id = secp256k1::ecdsa::RecoveryId::from_i32(i32::from(signature[64])).unwrap();
+        let signature =
+            secp256k1::ecdsa::RecoverableSignature::from_compact(&signature[..64], recovery_id)
+                .unwrap();
+        let message = secp256k1::Message::from_slice(&message).unwrap();
+        let pubkey = crate::SECP256K1
+            .recover_ecdsa(&message, &signature)
+            .unwrap();
+        assert_eq!(blake160(&pubkey.serialize()), ACCOUNT2_ARG);
+    }
+}
+
+#[test]
+fn test_get_input_cells() {
+    use crate::traits::TransactionDependencyProvider;
+
+    let sender = build_sighash_script(ACCOUNT1_ARG);
+    let ctx = init_context(
+        Vec::new(),
+        vec![
+            (sender.clone(), Some(100 * ONE_CKB)),
+            (sender, Some(200 * ONE_CKB)),
+        ],
+    );
+    let tx = ckb_types::core::TransactionBuilder::default()
+        .input(ctx.inputs[1].input.clone())
+        .input(ctx.inputs[0].input.clone())
+        .build();
+    let cells = ctx.get_input_cells(&tx).unwrap();
+    assert_eq!(
+        cells,
+        vec![ctx.inputs[1].output.clone(), ctx.inputs[0].output.clone()]
+    );
+
+    let tx = tx
+        .as_advanced_builder()
+        .input(CellInput::new(random_out_point(), 0))
+        .build();
+    assert!(ctx.get_input_cells(&tx).is_err());
+}
+
+#[test]
+fn test_change_strategy() {
+    let sender = build_sighash_script(ACCOUNT1_ARG);
+    let receiver = build_sighash_script(ACCOUNT2_ARG);
+    let ctx = init_context(
+        Vec::new(),
+        vec![
+            (sender.clone(), Some(100 * ONE_CKB)),
+            (sender.clone(), Some(200 * ONE_CKB)),
+        ],
+    );
+    let placeholder_witness = WitnessArgs::new_builder()
+        .lock(Some(Bytes::from(vec![0u8; 65])).pack())
+        .build();
+    let output = CellOutput::new_builder()
+        .capacity((100 * ONE_CKB).pack())
+        .lock(receiver)
+        .build();
+    // a change output template inherited the type script and data
+    let typed_change = CellOutput::new_builder()
+        .lock(sender.clone())
+        .type_(Some(build_dao_script()).pack())
+        .build();
+    let typed_data = Bytes::from(vec![1u8; 16]);
+    let tx = ckb_types::core::TransactionBuilder::default()
+        .input(ctx.inputs[0].input.clone())
+        .witness(placeholder_witness.as_bytes().pack())
+        .output(typed_change.clone())
+        .output_data(typed_data.pack())
+        .output(output.clone())
+        .output_data(Bytes::default().pack())
+        .build();
+
+    let mut balancer =
+        CapacityBalancer::new_simple(sender.clone(), placeholder_witness.clone(), FEE_RATE);
+    let mut cell_collector = ctx.to_live_cells_context();
+    cell_collector.used_inputs.insert(0);
+    let err = balancer
+        .rebalance_tx_capacity(&tx, &mut cell_collector, &ctx, &ctx, &ctx, 1000, Some(0))
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        BalanceTxCapacityError::InvalidChangeOutput(0, _)
+    ));
+
+    balancer.set_change_strategy(ChangeStrategy::KeepTypeAndData);
+    let (tx, change_index) = balancer
+        .rebalance_tx_capacity(&tx, &mut cell_collector, &ctx, &ctx, &ctx, 1000, Some(0))
+        .unwrap();
+    assert_eq!(change_index, Some(1));
+    assert_eq!(tx.inputs().len(), 2);
+    assert_eq!(tx.output(0).unwrap(), output);
+    assert_eq!(tx.output(1).unwrap().type_(), typed_change.type_());
+    assert_eq!(tx.outputs_data().get(0).unwrap().raw_data(), Bytes::new());
+    assert_eq!(tx.outputs_data().get(1).unwrap().raw_data(), typed_data);
+    check_change_output(&tx, 1, ChangeStrategy::KeepTypeAndData).unwrap();
+    assert!(check_change_output(&tx, 1, ChangeStrategy::Plain).is_err());
+    assert!(check_change_output(&tx, 0, ChangeStrategy::Plain).is_ok());
+    let empty_change = tx
+        .output(0)
+        .unwrap()
+        .as_builder()
+        .capacity(0u64.pack())
+        .build();
+    let tx = tx
+        .as_advanced_builder()
+        .set_outputs(vec![empty_change])
+        .build();
+    assert!(matches!(
+        check_change_output(&tx, 0, ChangeStrategy::Plain),
+        Err(BalanceTxCapacityError::InvalidChangeOutput(0, _))
+    ));
+}
+
+#[test]
+fn test_small_change_policy() {
+    let sender = build_sighash_script(ACCOUNT1_ARG);
+    let receiver = build_sighash_script(ACCOUNT2_ARG);
+    let ctx = init_context(
+        Vec::new(),
+        vec![
+            (sender.clone(), Some(100 * ONE_CKB)),
+            (sender.clone(), Some(200 * ONE_CKB)),
+        ],
+    );
+    let placeholder_witness = WitnessArgs::new_builder()
+        .lock(Some(Bytes::from(vec![0u8; 65])).pack())
+        .build();
+    // the left capacity (1 CKB) can not hold a change output
+    let tx = ckb_types::core::TransactionBuilder::default()
+        .input(ctx.inputs[0].input.clone())
+        .witness(placeholder_witness.as_bytes().pack())
+        .output(
+            CellOutput::new_builder()
+                .capacity((99 * ONE_CKB).pack())
+                .lock(receiver)
+                .build(),
+        )
+        .output_data(Bytes::default().pack())
+        .build();
+    let balance = |balancer: &CapacityBalancer| {
+        let mut cell_collector = ctx.to_live_cells_context();
+        cell_collector.used_inputs.insert(0);
+        balance_tx_capacity(&tx, balancer, &mut cell_collector, &ctx, &ctx, &ctx)
+    };
+
+    let mut balancer = CapacityBalancer::new_simple(sender, placeholder_witness, FEE_RATE);
+    let balanced_tx = balance(&balancer).unwrap();
+    assert_eq!(balanced_tx.inputs().len(), 2);
+    assert_eq!(balanced_tx.outputs().len(), 2);
+
+    balancer.set_small_change_policy(SmallChangePolicy::AsFee);
+    let balanced_tx = balance(&balancer).unwrap();
+    assert_eq!(balanced_tx.inputs().len(), 1);
+    assert_eq!(balanced_tx.outputs().len(), 1);
+    ctx.verify_tx_fee(&balanced_tx, FEE_RATE).unwrap();
+
+    balancer.set_max_fee(Some(1000));
+    assert!(matches!(
+        balance(&balancer),
+        Err(BalanceTxCapacityError::ForceSmallChangeAsFeeFailed(_))
+    ));
+}
+
+#[test]
+fn test_dao_withdraw_verify_maximum_withdraw() {
+    use crate::rpc::CkbRpcClient;
+    use crate::test_util::MockRpcResult;
+    use crate::traits::{TransactionDependencyError, TransactionDependencyProvider};
+    use ckb_types::{
+        core::{HeaderView, TransactionBuilder, TransactionView},
+        packed::{Byte32, OutPoint},
+    };
+    use httpmock::prelude::*;
+
+    // Serve the prepare transaction with its deposit input, delegate the rest to the context
+    struct PrepareTxProvider<'a> {
+        ctx: &'a Context,
+        prepare_tx: TransactionView,
+    }
+    impl<'a> TransactionDependencyProvider for PrepareTxProvider<'a> {
+        fn get_transaction(
+            &self,
+            tx_hash: &Byte32,
+        ) -> Result<TransactionView, TransactionDependencyError> {
+            if tx_hash == &self.prepare_tx.hash() {
+                Ok(self.prepare_tx.clone())
+            } else {
+                self.ctx.get_transaction(tx_hash)
+            }
+        }
+        fn get_cell(&self, out_point: &OutPoint) -> Result<CellOutput, TransactionDependencyError> {
+            self.ctx.get_cell(out_point)
+        }
+        fn get_cell_data(&self, out_point: &OutPoint) -> Result<Bytes, TransactionDependencyError> {
+            self.ctx.get_cell_data(out_point)
+        }
+        fn get_header(
+            &self,
+            block_hash: &Byte32,
+        ) -> Result<HeaderView, TransactionDependencyError> {
+            self.ctx.get_header(block_hash)
+        }
+        fn get_block_extension(
+            &self,
+            block_hash: &Byte32,
+        ) -> Result<Option<ckb_types::packed::Bytes>, TransactionDependencyError> {
+            self.ctx.get_block_extension(block_hash)
+        }
+    }
+
+    let sender = build_sighash_script(ACCOUNT1_ARG);
+    let mut ctx = init_context(Vec::new(), Vec::new());
+    let deposit_number = 5 * 1000 + 5;
+    let prepare_number = 184 * 1000 + 4;
+    let deposit_header = HeaderBuilder::default()
+        .epoch(EpochNumberWithFraction::new(5, 5, 1000).full_value().pack())
+        .number(deposit_number.pack())
+        .dao(pack_dao_data(
+            10_000_000_000_123_456,
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        ))
+        .build();
+    let prepare_header = HeaderBuilder::default()
+        .epoch(
+            EpochNumberWithFraction::new(184, 4, 1000)
+                .full_value()
+                .pack(),
+        )
+        .number(prepare_number.pack())
+        .dao(pack_dao_data(
+            10_000_000_001_123_456,
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        ))
+        .build();
+
+    let prepare_output = CellOutput::new_builder()
+        .capacity((220 * ONE_CKB).pack())
+        .lock(sender.clone())
+        .type_(Some(build_dao_script()).pack())
+        .build();
+    let prepare_data = Bytes::from(deposit_number.to_le_bytes().to_vec());
+    let deposit_out_point = random_out_point();
+    let prepare_tx = TransactionBuilder::default()
+        .input(CellInput::new(deposit_out_point.clone(), 0))
+        .output(prepare_output.clone())
+        .output_data(prepare_data.pack())
+        .build();
+    let prepare_out_point = OutPoint::new(prepare_tx.hash(), 0);
+    ctx.add_live_cell(
+        CellInput::new(prepare_out_point.clone(), 0),
+        prepare_output.clone(),
+        prepare_data,
+        Some(prepare_header.hash()),
+    );
+    ctx.add_header(deposit_header.clone());
+    ctx.add_header(prepare_header.clone());
+    let provider = PrepareTxProvider {
+        ctx: &ctx,
+        prepare_tx,
+    };
+
+    let occupied_capacity = prepare_output
+        .occupied_capacity(Capacity::bytes(8).unwrap())
+        .unwrap()
+        .as_u64();
+    let expected_capacity = calculate_dao_maximum_withdraw4(
+        &deposit_header,
+        &prepare_header,
+        &prepare_output,
+        occupied_capacity,
+    );
+    let builder = DaoWithdrawBuilder::new(
+        vec![DaoWithdrawItem::new(prepare_out_point, None)],
+        DaoWithdrawReceiver::LockScript {
+            script: sender,
+            fee_rate: None,
+        },
+    );
+
+    let deposit_out_point_json = json_types::OutPoint::from(deposit_out_point);
+    let prepare_block_hash: H256 = prepare_header.hash().unpack();
+    for (node_capacity, matched) in [(expected_capacity, true), (expected_capacity + 1, false)] {
+        let server = MockServer::start();
+        let rpc_mock = server.mock(|when, then| {
+            when.method(POST).path("/").json_body_partial(
+                serde_json::json!({
+                    "method": "calculate_dao_maximum_withdraw",
+                    "params": [deposit_out_point_json, prepare_block_hash],
+                })
+                .to_string(),
+            );
+            then.status(200)
+                .body(MockRpcResult::new(json_types::Capacity::from(node_capacity)).to_json());
+        });
+        let ckb_client = CkbRpcClient::new(&server.base_url());
+        let result = builder.verify_maximum_withdraw(&ckb_client, &ctx, &provider);
+        rpc_mock.assert();
+        if matched {
+            assert_eq!(result.unwrap(), expected_capacity);
+        } else {
+            assert!(matches!(result, Err(TxBuilderError::Other(_))));
+        }
+    }
+}
+
+pub mod cell_collector;
 pub mod ckb_indexer_rpc;
 pub mod ckb_rpc;
+pub mod cobuild;
 pub mod cycle;
 pub mod omni_lock;
 pub mod omni_lock_util;
+pub mod otx;
+pub mod serde_roundtrip;
+pub mod simple_transfer;
 pub mod transaction;
+pub mod transfer;
+pub mod tx_dep_provider;
+
+#[test]
+fn test_payment_intent() {
+    let sender = build_sighash_script(ACCOUNT1_ARG);
+    let receiver = build_sighash_script(ACCOUNT2_ARG);
+    let ctx = init_context(
+        Vec::new(),
+        (1..=6)
+            .map(|n| (sender.clone(), Some(n * 100 * ONE_CKB)))
+            .collect(),
+    );
+    let output = CellOutput::new_builder()
+        .capacity((250 * ONE_CKB).pack())
+        .lock(receiver)
+        .build();
+    let intent = PaymentIntent::new(
+        "payout-1".to_string(),
+        vec![(output.clone(), Bytes::default())],
+        FEE_RATE,
+    );
+    let placeholder_witness = WitnessArgs::new_builder()
+        .lock(Some(Bytes::from(vec![0u8; 65])).pack())
+        .build();
+    let balancer = intent.balancer(sender.clone(), placeholder_witness);
+    let account1_key = secp256k1::SecretKey::from_slice(ACCOUNT1_KEY.as_bytes()).unwrap();
+    let signer = SecpCkbRawKeySigner::new_with_secret_keys(vec![account1_key]);
+    let script_unlocker = SecpSighashUnlocker::from(Box::new(signer) as Box<_>);
+    let mut unlockers: HashMap<ScriptId, Box<dyn ScriptUnlocker>> = HashMap::default();
+    unlockers.insert(
+        ScriptId::new_type(SIGHASH_TYPE_HASH.clone()),
+        Box::new(script_unlocker),
+    );
+
+    let build = |intent: &PaymentIntent, reverse: bool| {
+        let mut live_cells = ctx.to_live_cells_context();
+        if reverse {
+            live_cells.inputs.reverse();
+        }
+        let mut cell_collector = intent.cell_collector(Box::new(live_cells));
+        let (tx, locked_groups) = intent
+            .builder()
+            .build_unlocked(&mut cell_collector, &ctx, &ctx, &ctx, &balancer, &unlockers)
+            .unwrap();
+        assert!(locked_groups.is_empty());
+        let consumed: Vec<_> = tx.input_pts_iter().collect();
+        assert_eq!(cell_collector.consumed().len(), consumed.len());
+        assert!(consumed
+            .iter()
+            .all(|out_point| cell_collector.consumed().contains(out_point)));
+        // the consumed cells are locked in the inner collector too
+        let mut query = CellQueryOptions::new_lock(sender.clone());
+        query.min_total_capacity = u64::MAX;
+        let (rest_cells, _) = cell_collector
+            .into_inner()
+            .collect_live_cells(&query, false)
+            .unwrap();
+        assert_eq!(rest_cells.len(), 6 - consumed.len());
+        assert!(rest_cells
+            .iter()
+            .all(|cell| !consumed.contains(&cell.out_point)));
+        tx
+    };
+    // the same live cells in a different order
+    let tx = build(&intent, false);
+    assert_eq!(tx.output(0).unwrap(), output);
+    assert_eq!(build(&intent, true).hash(), tx.hash());
+    ctx.verify(tx.clone(), FEE_RATE).unwrap();
+
+    let record = intent.record(&tx);
+    assert_eq!(record.tx_hash, tx.hash().unpack());
+    assert_eq!(record.inputs.len(), tx.inputs().len());
+
+    let other = PaymentIntent::new("payout-2".to_string(), intent.receivers.clone(), FEE_RATE);
+    assert_ne!(other.seed(), intent.seed());
+}
+
+#[test]
+fn test_payment_intent_capacity_overflow() {
+    let sender = build_sighash_script(ACCOUNT1_ARG);
+    let ctx = init_context(
+        Vec::new(),
+        vec![
+            (sender.clone(), Some(u64::MAX - ONE_CKB)),
+            (sender.clone(), Some(2 * ONE_CKB)),
+        ],
+    );
+    let intent = PaymentIntent::new("payout-1".to_string(), Vec::new(), FEE_RATE);
+    let mut cell_collector = intent.cell_collector(Box::new(ctx.to_live_cells_context()));
+    let mut query = CellQueryOptions::new_lock(sender);
+    query.min_total_capacity = u64::MAX;
+    let err = cell_collector.collect_live_cells(&query, true).unwrap_err();
+    assert_eq!(err.to_string(), "the total capacity overflows");
+    assert!(cell_collector.consumed().is_empty());
+}
+
+#[test]
+fn test_preview_witnesses() {
+    let cfg = MultisigConfig::new_with(
+        vec![
+            ACCOUNT0_ARG.clone(),
+            ACCOUNT1_ARG.clone(),
+            ACCOUNT2_ARG.clone(),
+        ],
+        0,
+        2,
+    )
+    .unwrap();
+    let sighash_sender = build_sighash_script(ACCOUNT1_ARG);
+    let multisig_sender = build_multisig_script(&cfg);
+    let ctx = init_context(
+        Vec::new(),
+        vec![
+            (multisig_sender.clone(), Some(100 * ONE_CKB)),
+            (sighash_sender.clone(), Some(200 * ONE_CKB)),
+            (multisig_sender, Some(300 * ONE_CKB)),
+        ],
+    );
+    let tx = ckb_types::core::TransactionBuilder::default()
+        .inputs(ctx.inputs.iter().map(|input| input.input.clone()))
+        .output(
+            CellOutput::new_builder()
+                .capacity((500 * ONE_CKB).pack())
+                .lock(build_sighash_script(ACCOUNT2_ARG))
+                .build(),
+        )
+        .output_data(Bytes::default().pack())
+        .build();
+
+    let account0_key = secp256k1::SecretKey::from_slice(ACCOUNT0_KEY.as_bytes()).unwrap();
+    let account1_key = secp256k1::SecretKey::from_slice(ACCOUNT1_KEY.as_bytes()).unwrap();
+    let account2_key = secp256k1::SecretKey::from_slice(ACCOUNT2_KEY.as_bytes()).unwrap();
+    let mut unlockers = build_multisig_unlockers(account0_key, cfg.clone());
+    let signer = SecpCkbRawKeySigner::new_with_secret_keys(vec![account1_key]);
+    unlockers.insert(
+        ScriptId::new_type(SIGHASH_TYPE_HASH.clone()),
+        Box::new(SecpSighashUnlocker::from(Box::new(signer) as Box<_>)),
+    );
+
+    let previews = preview_witnesses(&tx, &ctx, &unlockers).unwrap();
+    let multisig_config_len = cfg.to_witness_data().len();
+    assert_eq!(
+        previews,
+        vec![
+            WitnessPreview {
+                input_index: 0,
+                group_index: 0,
+                lock_script_id: ScriptId::new_type(MULTISIG_TYPE_HASH.clone()),
+                total_size: 20 + cfg.witness_lock_len(),
+                signature_offset: Some(20 + multisig_config_len),
+                signature_len: 2 * 65,
+            },
+            WitnessPreview {
+                input_index: 1,
+                group_index: 1,
+                lock_script_id: ScriptId::new_type(SIGHASH_TYPE_HASH.clone()),
+                total_size: 20 + 65,
+                signature_offset: Some(20),
+                signature_len: 65,
+            },
+        ]
+    );
+    // the preview doesn't depend on the unclaimed groups
+    let multisig_only = build_multisig_unlockers(account0_key, cfg.clone());
+    assert_eq!(
+        preview_witnesses(&tx, &ctx, &multisig_only).unwrap(),
+        previews[0..1].to_vec()
+    );
+
+    let (unsigned_tx, _) = fill_placeholder_witnesses(tx, &ctx, &unlockers).unwrap();
+    let (mut signed_tx, _) = unlock_tx(unsigned_tx.clone(), &ctx, &unlockers).unwrap();
+    let unlockers = build_multisig_unlockers(account2_key, cfg);
+    signed_tx = unlock_tx(signed_tx, &ctx, &unlockers).unwrap().0;
+    assert_signed_at_preview(&unsigned_tx, &signed_tx, &previews);
+}
@@ -4,23 +4,24 @@ use crate::{
     constants::{ONE_CKB, SIGHASH_TYPE_HASH},
     test_util::random_out_point,
     tests::{
-        build_sighash_script, init_context, omni_lock_util::generate_rc, ACCOUNT0_ARG,
-        ACCOUNT0_KEY, ACCOUNT1_ARG, ACCOUNT1_KEY, ACCOUNT2_ARG, ACCOUNT2_KEY, ACCOUNT3_ARG,
-        ACCOUNT3_KEY, ALWAYS_SUCCESS_BIN, FEE_RATE, SUDT_BIN,
+        assert_signed_at_preview, build_sighash_script, init_context, omni_lock_util::generate_rc,
+        ACCOUNT0_ARG, ACCOUNT0_KEY, ACCOUNT1_ARG, ACCOUNT1_KEY, ACCOUNT2_ARG, ACCOUNT2_KEY,
+        ACCOUNT3_ARG, ACCOUNT3_KEY, ALWAYS_SUCCESS_BIN, FEE_RATE, SUDT_BIN,
     },
     traits::{CellDepResolver, SecpCkbRawKeySigner},
     tx_builder::{
         acp::{AcpTransferBuilder, AcpTransferReceiver},
         balance_tx_capacity, fill_placeholder_witnesses,
         omni_lock::OmniLockTransferBuilder,
+        preview_witnesses,
         udt::{UdtTargetReceiver, UdtTransferBuilder},
-        CapacityProvider, TransferAction,
+        BalancerOptions, CapacityProvider, TransferAction,
     },
     types::xudt_rce_mol::SmtProofEntryVec,
     unlock::{
         omni_lock::{AdminConfig, Identity},
-        IdentityFlag, InfoCellData, MultisigConfig, OmniLockAcpConfig, OmniLockConfig,
-        OmniLockScriptSigner, OmniLockUnlocker, OmniUnlockMode, ScriptUnlocker,
+        verify_signatures, IdentityFlag, InfoCellData, MultisigConfig, OmniLockAcpConfig,
+        OmniLockConfig, OmniLockScriptSigner, OmniLockUnlocker, OmniUnlockMode, ScriptUnlocker,
         SecpSighashUnlocker,
     },
     util::{blake160, keccak160},
@@ -123,9 +124,14 @@ fn test_omnilock_simple_hash(cfg: OmniLockConfig) {
         .unwrap();
 
     let unlockers = build_omnilock_unlockers(account2_key, cfg, unlock_mode);
+    let previews = preview_witnesses(&tx, &ctx, &unlockers).unwrap();
+    assert_eq!(previews.len(), 1);
+    assert_eq!(previews[0].signature_len, 65);
+    let unsigned_tx = tx.clone();
     let (new_tx, new_locked_groups) = unlock_tx(tx.clone(), &ctx, &unlockers).unwrap();
     assert!(new_locked_groups.is_empty());
     tx = new_tx;
+    assert_signed_at_preview(&unsigned_tx, &tx, &previews);
 
     assert_eq!(tx.header_deps().len(), 0);
     assert_eq!(tx.cell_deps().len(), 1);
@@ -144,6 +150,11 @@ fn test_omnilock_simple_hash(cfg: OmniLockConfig) {
     assert_eq!(witnesses.len(), 2);
     assert_eq!(witnesses[0].len(), placeholder_witness.as_slice().len());
     assert_eq!(witnesses[1].len(), 0);
+    let omni_lock_id = ScriptId::from(&sender);
+    assert!(verify_signatures(&tx, &ctx, &[omni_lock_id])
+        .unwrap()
+        .is_empty());
+    assert_eq!(verify_signatures(&tx, &ctx, &[]).unwrap().len(), 1);
     ctx.verify(tx, FEE_RATE).unwrap();
 }
 
@@ -619,6 +630,10 @@ fn test_omnilock_transfer_from_multisig() {
         .build_balanced(&mut cell_collector, &ctx, &ctx, &ctx, &balancer, &unlockers)
         .unwrap();
 
+    let previews = preview_witnesses(&tx, &ctx, &unlockers).unwrap();
+    assert_eq!(previews.len(), 1);
+    assert_eq!(previews[0].signature_len, 2 * 65);
+    let unsigned_tx = tx.clone();
     let mut locked_groups = None;
     for key in [account0_key, account2_key] {
         let unlockers = build_omnilock_unlockers(key, cfg.clone(), unlock_mode);
@@ -627,6 +642,7 @@ fn test_omnilock_transfer_from_multisig() {
         tx = new_tx;
         locked_groups = Some(new_locked_groups);
     }
+    assert_signed_at_preview(&unsigned_tx, &tx, &previews);
 
     assert_eq!(locked_groups, Some(Vec::new()));
     assert_eq!(tx.header_deps().len(), 0);
@@ -646,6 +662,11 @@ fn test_omnilock_transfer_from_multisig() {
     assert_eq!(witnesses.len(), 2);
     assert_eq!(witnesses[0].len(), placeholder_witness.as_slice().len());
     assert_eq!(witnesses[1].len(), 0);
+    let omni_lock_id = ScriptId::from(&sender);
+    assert!(verify_signatures(&tx, &ctx, &[omni_lock_id])
+        .unwrap()
+        .is_empty());
+    assert_eq!(verify_signatures(&tx, &ctx, &[]).unwrap().len(), 1);
     ctx.verify(tx, FEE_RATE).unwrap();
 }
 
@@ -734,6 +755,10 @@ fn test_omnilock_transfer_from_multisig_wl_commnon(unlock_mode: OmniUnlockMode)
     )
     .unwrap();
 
+    let previews = preview_witnesses(&tx, &ctx, &unlockers).unwrap();
+    assert_eq!(previews.len(), 1);
+    assert_eq!(previews[0].signature_len, 2 * 65);
+    let unsigned_tx = tx.clone();
     let mut locked_groups = None;
     for key in [account0_key, account2_key] {
         let unlockers = build_omnilock_unlockers(key, cfg.clone(), unlock_mode);
@@ -742,6 +767,7 @@ fn test_omnilock_transfer_from_multisig_wl_commnon(unlock_mode: OmniUnlockMode)
         tx = new_tx;
         locked_groups = Some(new_locked_groups);
     }
+    assert_signed_at_preview(&unsigned_tx, &tx, &previews);
 
     assert_eq!(locked_groups, Some(Vec::new()));
     assert_eq!(tx.header_deps().len(), 0);
@@ -800,6 +826,7 @@ fn test_omnilock_transfer_from_ownerlock() {
         ]),
         change_lock_script: None,
         force_small_change_as_fee: Some(ONE_CKB),
+        options: BalancerOptions::default(),
     };
 
     let mut cell_collector = ctx.to_live_cells_context();
@@ -900,6 +927,7 @@ fn test_omnilock_transfer_from_ownerlock_wl_admin() {
         ]),
         change_lock_script: None,
         force_small_change_as_fee: Some(ONE_CKB),
+        options: BalancerOptions::default(),
     };
 
     let mut cell_collector = ctx.to_live_cells_context();
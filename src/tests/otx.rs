@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+
+use ckb_hash::blake2b_256;
+use ckb_types::{
+    bytes::Bytes,
+    core::{ScriptHashType, TransactionView},
+    packed::{CellInput, CellOutput, Script, WitnessArgs},
+    prelude::*,
+    H256,
+};
+
+use crate::{
+    constants::{ONE_CKB, SIGHASH_TYPE_HASH},
+    test_util::{random_out_point, Context},
+    tests::{
+        build_sighash_script, init_context, ACCOUNT1_ARG, ACCOUNT1_KEY, ACCOUNT2_ARG,
+        ALWAYS_SUCCESS_BIN, FEE_RATE,
+    },
+    traits::{CellDepResolver, SecpCkbRawKeySigner},
+    tx_builder::{otx, CapacityBalancer},
+    types::cobuild_mol::{Otx, OtxStart, WitnessLayout, WitnessLayoutUnion},
+    unlock::{
+        cobuild::{collect_otxs, generate_cobuild_otx_message},
+        ScriptUnlocker, SecpSighashUnlocker,
+    },
+    ScriptId,
+};
+
+fn build_otx_lock(arg: u8) -> Script {
+    Script::new_builder()
+        .code_hash(H256::from(blake2b_256(ALWAYS_SUCCESS_BIN)).pack())
+        .hash_type(ScriptHashType::Data1.into())
+        .args(Bytes::from(vec![arg; 20]).pack())
+        .build()
+}
+
+fn layout_witness(layout: WitnessLayoutUnion) -> Bytes {
+    WitnessLayout::new_builder().set(layout).build().as_bytes()
+}
+
+/// An open transaction spending a cell of `input_capacity` to `receiver`
+fn build_part(
+    ctx: &mut Context,
+    lock: &Script,
+    input_capacity: u64,
+    receiver: &Script,
+    output_capacity: u64,
+) -> TransactionView {
+    let input = CellInput::new(random_out_point(), 0);
+    ctx.add_live_cell(
+        input.clone(),
+        CellOutput::new_builder()
+            .capacity(input_capacity.pack())
+            .lock(lock.clone())
+            .build(),
+        Bytes::default(),
+        None,
+    );
+    let otx = Otx::new_builder()
+        .input_cells(1u32.pack())
+        .output_cells(1u32.pack())
+        .build();
+    TransactionView::new_advanced_builder()
+        .input(input)
+        .output(
+            CellOutput::new_builder()
+                .capacity(output_capacity.pack())
+                .lock(receiver.clone())
+                .build(),
+        )
+        .output_data(Bytes::default().pack())
+        .cell_dep(ctx.resolve(lock).unwrap())
+        .witness(layout_witness(OtxStart::default().into()).pack())
+        .witness(layout_witness(otx.into()).pack())
+        .build()
+}
+
+#[test]
+fn test_otx_merge_and_complete() {
+    let matcher = build_sighash_script(ACCOUNT1_ARG);
+    let receiver = build_sighash_script(ACCOUNT2_ARG);
+    let mut ctx = init_context(
+        vec![(ALWAYS_SUCCESS_BIN, false)],
+        vec![(matcher.clone(), Some(200 * ONE_CKB))],
+    );
+    let part0 = build_part(
+        &mut ctx,
+        &build_otx_lock(1),
+        100 * ONE_CKB,
+        &receiver,
+        100 * ONE_CKB,
+    );
+    let part1 = build_part(
+        &mut ctx,
+        &build_otx_lock(2),
+        150 * ONE_CKB,
+        &receiver,
+        120 * ONE_CKB,
+    );
+    let part_messages = [&part0, &part1]
+        .iter()
+        .map(|part| {
+            let otxs = collect_otxs(part).unwrap();
+            generate_cobuild_otx_message(part, &otxs[0], &ctx).unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    let merged = otx::merge(vec![part0, part1]).unwrap();
+    assert_eq!(merged.inputs().len(), 2);
+    // the always_success cell dep is shared
+    assert_eq!(merged.cell_deps().len(), 1);
+
+    let placeholder_witness = WitnessArgs::new_builder()
+        .lock(Some(Bytes::from(vec![0u8; 65])).pack())
+        .build();
+    let balancer = CapacityBalancer::new_simple(matcher.clone(), placeholder_witness, FEE_RATE);
+    let account1_key = secp256k1::SecretKey::from_slice(ACCOUNT1_KEY.as_bytes()).unwrap();
+    let signer = SecpCkbRawKeySigner::new_with_secret_keys(vec![account1_key]);
+    let mut unlockers: HashMap<ScriptId, Box<dyn ScriptUnlocker>> = HashMap::default();
+    unlockers.insert(
+        ScriptId::new_type(SIGHASH_TYPE_HASH.clone()),
+        Box::new(SecpSighashUnlocker::from(Box::new(signer) as Box<_>)),
+    );
+    let mut cell_collector = ctx.to_live_cells_context();
+    let (tx, locked_groups) = otx::complete(
+        &merged,
+        &balancer,
+        &mut cell_collector,
+        &ctx,
+        &ctx,
+        &ctx,
+        &unlockers,
+    )
+    .unwrap();
+    assert!(locked_groups.is_empty());
+
+    // the matcher's input and change output are placed after the otxs
+    assert_eq!(tx.inputs().len(), 3);
+    assert_eq!(tx.outputs().len(), 3);
+    assert_eq!(tx.output(2).unwrap().lock(), matcher);
+    // the signing messages of the otxs are not changed by merging
+    let otxs = collect_otxs(&tx).unwrap();
+    assert_eq!(otxs.len(), 2);
+    assert_eq!(otxs[0].witness_index, 4);
+    for (otx, message) in otxs.iter().zip(part_messages) {
+        assert_eq!(
+            generate_cobuild_otx_message(&tx, otx, &ctx).unwrap(),
+            message
+        );
+    }
+    ctx.verify(tx, FEE_RATE).unwrap();
+}
+
+#[test]
+fn test_otx_complete_not_merged() {
+    let matcher = build_sighash_script(ACCOUNT1_ARG);
+    let ctx = init_context(Vec::new(), vec![(matcher.clone(), Some(200 * ONE_CKB))]);
+    let tx = TransactionView::new_advanced_builder()
+        .input(CellInput::new(random_out_point(), 0))
+        .build();
+    let balancer = CapacityBalancer::new_simple(matcher, WitnessArgs::default(), FEE_RATE);
+    let mut cell_collector = ctx.to_live_cells_context();
+    let result = otx::complete(
+        &tx,
+        &balancer,
+        &mut cell_collector,
+        &ctx,
+        &ctx,
+        &ctx,
+        &HashMap::default(),
+    );
+    assert!(result.is_err());
+}
@@ -0,0 +1,215 @@
+use std::fmt::Debug;
+
+use ckb_jsonrpc_types as json_types;
+use ckb_types::{
+    core::{BlockView, ScriptHashType},
+    h160, h256,
+    packed::{Byte, CellDep, OutPoint},
+    prelude::*,
+    H256,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    tests::{ACCOUNT0_ARG, ACCOUNT1_ARG, ACCOUNT2_ARG, GENESIS_JSON},
+    traits::{default_impls::CellDepItem, DefaultCellDepResolver, GenesisInfo},
+    types::{
+        xudt_rce_mol::{SmtProof, SmtProofEntry, SmtProofEntryVec},
+        AddressType, CodeHashIndex, ScriptGroupType,
+    },
+    unlock::{
+        omni_lock::{AdminConfig, Identity, OmniLockFlags},
+        IdentityFlag, MultisigConfig, OmniLockAcpConfig, OmniLockConfig,
+    },
+    NetworkType, ScriptId,
+};
+
+fn assert_roundtrip<T: Serialize + DeserializeOwned + PartialEq + Debug>(value: &T) {
+    let json = serde_json::to_string(value).unwrap();
+    let decoded: T = serde_json::from_str(&json).unwrap();
+    assert_eq!(&decoded, value, "json: {}", json);
+}
+
+fn build_multisig_config() -> MultisigConfig {
+    MultisigConfig::new_with(
+        vec![
+            ACCOUNT0_ARG.clone(),
+            ACCOUNT1_ARG.clone(),
+            ACCOUNT2_ARG.clone(),
+        ],
+        1,
+        2,
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_serde_address_types() {
+    for network in [
+        NetworkType::Mainnet,
+        NetworkType::Testnet,
+        NetworkType::Staging,
+        NetworkType::Dev,
+    ] {
+        assert_roundtrip(&network);
+    }
+    for address_type in [
+        AddressType::Full,
+        AddressType::Short,
+        AddressType::FullData,
+        AddressType::FullType,
+    ] {
+        assert_roundtrip(&address_type);
+    }
+    for index in [
+        CodeHashIndex::Sighash,
+        CodeHashIndex::Multisig,
+        CodeHashIndex::Acp,
+    ] {
+        assert_roundtrip(&index);
+    }
+}
+
+#[test]
+fn test_serde_script_group_type() {
+    assert_roundtrip(&ScriptGroupType::Lock);
+    assert_roundtrip(&ScriptGroupType::Type);
+    assert_eq!(
+        serde_json::to_string(&ScriptGroupType::Lock).unwrap(),
+        "\"lock\""
+    );
+}
+
+#[test]
+fn test_serde_multisig_config() {
+    let config = build_multisig_config();
+    assert_roundtrip(&config);
+    let decoded: MultisigConfig =
+        serde_json::from_str(&serde_json::to_string(&config).unwrap()).unwrap();
+    assert_eq!(decoded.hash160(), config.hash160());
+}
+
+#[test]
+fn test_serde_omni_lock_config() {
+    for flag in [
+        IdentityFlag::PubkeyHash,
+        IdentityFlag::Ethereum,
+        IdentityFlag::Eos,
+        IdentityFlag::Tron,
+        IdentityFlag::Bitcoin,
+        IdentityFlag::Dogecoin,
+        IdentityFlag::Multisig,
+        IdentityFlag::OwnerLock,
+        IdentityFlag::Exec,
+        IdentityFlag::Dl,
+    ] {
+        assert_roundtrip(&flag);
+    }
+    assert_roundtrip(&(OmniLockFlags::ADMIN | OmniLockFlags::ACP));
+    assert_roundtrip(&OmniLockAcpConfig::new(3, 5));
+
+    let pubkey_hash = ACCOUNT0_ARG.clone();
+    assert_roundtrip(&OmniLockConfig::new_pubkey_hash(pubkey_hash.clone()));
+    assert_roundtrip(&OmniLockConfig::new_ethereum(pubkey_hash.clone()));
+    assert_roundtrip(&OmniLockConfig::new_ownerlock(h160!(
+        "0x0102030405060708090a0b0c0d0e0f1011121314"
+    )));
+    assert_roundtrip(&OmniLockConfig::new_multisig(build_multisig_config()));
+
+    let mut config = OmniLockConfig::new_pubkey_hash(pubkey_hash.clone());
+    config.set_acp_config(OmniLockAcpConfig::new(1, 2));
+    config.set_time_lock_config(0x2000_0000_0000_0010);
+    config.set_info_cell(h256!("0x1234"));
+    let proof = SmtProofEntry::new_builder()
+        .mask(Byte::new(3))
+        .proof(
+            SmtProof::new_builder()
+                .extend(vec![1u8, 2, 3].into_iter().map(Byte::new))
+                .build(),
+        )
+        .build();
+    let proofs = SmtProofEntryVec::new_builder().push(proof).build();
+    config.set_admin_config(AdminConfig::new(
+        h256!("0x5678"),
+        proofs,
+        Identity::new_pubkey_hash(ACCOUNT1_ARG.clone()),
+        Some(build_multisig_config()),
+        true,
+    ));
+    assert_roundtrip(&config);
+}
+
+#[test]
+fn test_serde_cell_dep_item() {
+    let item = CellDepItem {
+        name: "test".to_string(),
+        code_hash: h256!("0x1234"),
+        hash_type: json_types::ScriptHashType::Data1,
+        cell_dep: CellDep::new_builder()
+            .out_point(OutPoint::new(h256!("0x5678").pack(), 1))
+            .build()
+            .into(),
+    };
+    let json = serde_json::to_string(&item).unwrap();
+    let decoded: CellDepItem = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded.name, item.name);
+    assert_eq!(decoded.code_hash, item.code_hash);
+    assert_eq!(decoded.hash_type, item.hash_type);
+    assert_eq!(decoded.cell_dep, item.cell_dep);
+    assert_eq!(serde_json::to_string(&decoded).unwrap(), json);
+}
+
+#[test]
+fn test_serde_cell_dep_resolver_json() {
+    let genesis_block: json_types::BlockView = serde_json::from_str(GENESIS_JSON).unwrap();
+    let genesis_block: BlockView = genesis_block.into();
+    let code_hash = H256::from([0x12u8; 32]);
+    let cell_dep = CellDep::new_builder()
+        .out_point(OutPoint::new(h256!("0x5678").pack(), 1))
+        .build();
+    let items = vec![CellDepItem {
+        name: "test".to_string(),
+        code_hash: code_hash.clone(),
+        hash_type: json_types::ScriptHashType::Type,
+        cell_dep: cell_dep.clone().into(),
+    }];
+    let json = serde_json::to_string(&items).unwrap();
+
+    let info = GenesisInfo::from_genesis_block(&genesis_block).unwrap();
+    let resolver = DefaultCellDepResolver::from_genesis_info_and_json(&info, &json).unwrap();
+    let script_id = ScriptId::new(code_hash, ScriptHashType::Type);
+    assert_eq!(
+        resolver.get(&script_id),
+        Some(&(cell_dep, "test".to_string()))
+    );
+    assert!(resolver.sighash_dep().is_some());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_script_id_and_live_cell() {
+    use crate::{test_util::random_out_point, traits::LiveCell};
+    use ckb_types::{bytes::Bytes, packed::CellOutput};
+
+    let script_id = ScriptId::new_data1(h256!("0x1234"));
+    assert_roundtrip(&script_id);
+    let json = serde_json::to_value(&script_id).unwrap();
+    assert_eq!(json["hash_type"], "data1");
+
+    let cell = LiveCell {
+        output: CellOutput::new_builder().capacity(100u64.pack()).build(),
+        output_data: Bytes::from(vec![1u8, 2, 3]),
+        out_point: random_out_point(),
+        block_number: 10,
+        tx_index: 1,
+    };
+    let json = serde_json::to_value(&cell).unwrap();
+    assert_eq!(json["block_number"], "0xa");
+    assert_eq!(json["output_data"], "0x010203");
+    let decoded: LiveCell = serde_json::from_value(json).unwrap();
+    assert_eq!(decoded.output, cell.output);
+    assert_eq!(decoded.output_data, cell.output_data);
+    assert_eq!(decoded.out_point, cell.out_point);
+    assert_eq!(decoded.block_number, cell.block_number);
+    assert_eq!(decoded.tx_index, cell.tx_index);
+}
@@ -0,0 +1,215 @@
+use std::str::FromStr;
+
+use ckb_hash::blake2b_256;
+use ckb_types::{
+    bytes::Bytes,
+    core::ScriptHashType,
+    packed::{CellInput, CellOutput, Script},
+    prelude::*,
+    H160, H256,
+};
+
+use crate::{
+    constants::ONE_CKB,
+    test_util::random_out_point,
+    tests::{
+        build_multisig_script, build_sighash_script, init_context, ACCOUNT0_ARG, ACCOUNT0_KEY,
+        ACCOUNT1_ARG, ACCOUNT1_KEY, ACCOUNT2_ARG, ACCOUNT2_KEY, FEE_RATE, SUDT_BIN,
+    },
+    tx_builder::{simple::SimpleTransferBuilder, TxBuilderError},
+    unlock::{verify_signatures, MultisigConfig, SignatureError},
+    Address, AddressPayload, HumanCapacity, NetworkInfo, NetworkType,
+};
+
+fn to_address(script: &Script) -> Address {
+    Address::new(
+        NetworkType::Testnet,
+        AddressPayload::from(script.clone()),
+        true,
+    )
+}
+
+fn secret_key(key: &H256) -> secp256k1::SecretKey {
+    secp256k1::SecretKey::from_slice(key.as_bytes()).unwrap()
+}
+
+#[test]
+fn test_simple_transfer_from_sighash() {
+    let sender = build_sighash_script(ACCOUNT1_ARG);
+    let receiver = build_sighash_script(ACCOUNT2_ARG);
+    let ctx = init_context(
+        Vec::new(),
+        vec![
+            (sender.clone(), Some(100 * ONE_CKB)),
+            (sender.clone(), Some(200 * ONE_CKB)),
+        ],
+    );
+
+    let builder = SimpleTransferBuilder::new(NetworkInfo::testnet())
+        .add_sighash_key(secret_key(&ACCOUNT1_KEY))
+        .transfer(
+            &to_address(&receiver),
+            HumanCapacity::from_str("120").unwrap(),
+        );
+    assert_eq!(builder.sender_script(), Some(sender.clone()));
+    let mut cell_collector = ctx.to_live_cells_context();
+    let (built, locked_groups) = builder
+        .build_and_sign_with(&mut cell_collector, &ctx, &ctx, &ctx)
+        .unwrap();
+
+    assert!(locked_groups.is_empty());
+    let tx = built.tx;
+    assert_eq!(tx.inputs().len(), 2);
+    assert_eq!(tx.outputs().len(), 2);
+    assert_eq!(tx.output(0).unwrap().lock(), receiver);
+    let capacity: u64 = tx.output(0).unwrap().capacity().unpack();
+    assert_eq!(capacity, 120 * ONE_CKB);
+    assert_eq!(tx.output(1).unwrap().lock(), sender);
+    assert_eq!(built.change_outputs.len(), 1);
+    assert!(verify_signatures(&tx, &ctx, &[]).unwrap().is_empty());
+    // the signature is not valid after the transaction changed
+    let changed_tx = tx
+        .as_advanced_builder()
+        .output(tx.output(0).unwrap())
+        .output_data(Bytes::default().pack())
+        .build();
+    assert!(matches!(
+        verify_signatures(&changed_tx, &ctx, &[]),
+        Err(SignatureError::InvalidSignature { index: 0, .. })
+    ));
+    ctx.verify(tx, FEE_RATE).unwrap();
+}
+
+#[test]
+fn test_simple_transfer_from_multisig() {
+    let lock_args = vec![
+        ACCOUNT0_ARG.clone(),
+        ACCOUNT1_ARG.clone(),
+        ACCOUNT2_ARG.clone(),
+    ];
+    let cfg = MultisigConfig::new_with(lock_args, 0, 2).unwrap();
+    let sender = build_multisig_script(&cfg);
+    let receiver = build_sighash_script(ACCOUNT2_ARG);
+    let ctx = init_context(
+        Vec::new(),
+        vec![
+            (sender.clone(), Some(100 * ONE_CKB)),
+            (sender.clone(), Some(200 * ONE_CKB)),
+        ],
+    );
+
+    // only one key, the multisig witness is partially signed
+    let mut cell_collector = ctx.to_live_cells_context();
+    let (built, locked_groups) = SimpleTransferBuilder::new(NetworkInfo::testnet())
+        .with_multisig(cfg.clone())
+        .add_sighash_key(secret_key(&ACCOUNT0_KEY))
+        .transfer(
+            &to_address(&receiver),
+            HumanCapacity::from_str("120").unwrap(),
+        )
+        .build_and_sign_with(&mut cell_collector, &ctx, &ctx, &ctx)
+        .unwrap();
+    assert!(locked_groups.is_empty());
+    assert!(verify_signatures(&built.tx, &ctx, &[]).is_err());
+    assert!(ctx.verify(built.tx, FEE_RATE).is_err());
+
+    let mut cell_collector = ctx.to_live_cells_context();
+    let builder = SimpleTransferBuilder::new(NetworkInfo::testnet())
+        .with_multisig(cfg)
+        .add_sighash_key(secret_key(&ACCOUNT0_KEY))
+        .add_sighash_key(secret_key(&ACCOUNT2_KEY))
+        .transfer(
+            &to_address(&receiver),
+            HumanCapacity::from_str("120").unwrap(),
+        );
+    assert_eq!(builder.sender_script(), Some(sender.clone()));
+    let (built, locked_groups) = builder
+        .build_and_sign_with(&mut cell_collector, &ctx, &ctx, &ctx)
+        .unwrap();
+
+    assert!(locked_groups.is_empty());
+    let tx = built.tx;
+    assert_eq!(tx.outputs().len(), 2);
+    assert_eq!(tx.output(0).unwrap().lock(), receiver);
+    assert_eq!(tx.output(1).unwrap().lock(), sender);
+    assert!(verify_signatures(&tx, &ctx, &[]).unwrap().is_empty());
+    ctx.verify(tx, FEE_RATE).unwrap();
+}
+
+#[test]
+fn test_simple_transfer_udt() {
+    let sudt_data_hash = H256::from(blake2b_256(SUDT_BIN));
+    let sender = build_sighash_script(ACCOUNT1_ARG);
+    let receiver = build_sighash_script(ACCOUNT2_ARG);
+    let owner = build_sighash_script(H160::default());
+    let type_script = Script::new_builder()
+        .code_hash(sudt_data_hash.pack())
+        .hash_type(ScriptHashType::Data1.into())
+        .args(owner.calc_script_hash().as_bytes().pack())
+        .build();
+    let mut ctx = init_context(
+        vec![(SUDT_BIN, false)],
+        vec![
+            (sender.clone(), Some(100 * ONE_CKB)),
+            (sender.clone(), Some(300 * ONE_CKB)),
+        ],
+    );
+    let sender_output = CellOutput::new_builder()
+        .capacity((200 * ONE_CKB).pack())
+        .lock(sender.clone())
+        .type_(Some(type_script.clone()).pack())
+        .build();
+    ctx.add_live_cell(
+        CellInput::new(random_out_point(), 0),
+        sender_output.clone(),
+        Bytes::from(500u128.to_le_bytes().to_vec()),
+        None,
+    );
+
+    let mut cell_collector = ctx.to_live_cells_context();
+    let (built, locked_groups) = SimpleTransferBuilder::new(NetworkInfo::testnet())
+        .add_sighash_key(secret_key(&ACCOUNT1_KEY))
+        .transfer_udt(type_script.clone(), &to_address(&receiver), 300)
+        .transfer(
+            &to_address(&receiver),
+            HumanCapacity::from_str("100").unwrap(),
+        )
+        .build_and_sign_with(&mut cell_collector, &ctx, &ctx, &ctx)
+        .unwrap();
+
+    assert!(locked_groups.is_empty());
+    let tx = built.tx;
+    let outputs = tx.outputs().into_iter().collect::<Vec<_>>();
+    assert_eq!(outputs.len(), 4);
+    assert_eq!(outputs[0], sender_output);
+    assert_eq!(outputs[1].lock(), receiver);
+    assert_eq!(outputs[1].type_().to_opt(), Some(type_script));
+    assert_eq!(outputs[2].lock(), receiver);
+    assert_eq!(outputs[3].lock(), sender);
+    let outputs_data = tx
+        .outputs_data()
+        .into_iter()
+        .map(|data| data.raw_data())
+        .collect::<Vec<_>>();
+    assert_eq!(outputs_data[0], Bytes::from(200u128.to_le_bytes().to_vec()));
+    assert_eq!(outputs_data[1], Bytes::from(300u128.to_le_bytes().to_vec()));
+    ctx.verify(tx, FEE_RATE).unwrap();
+}
+
+#[test]
+fn test_simple_transfer_invalid_parameter() {
+    let ctx = init_context(Vec::new(), Vec::new());
+    let receiver = to_address(&build_sighash_script(ACCOUNT2_ARG));
+
+    let mut cell_collector = ctx.to_live_cells_context();
+    let result = SimpleTransferBuilder::new(NetworkInfo::testnet())
+        .transfer(&receiver, HumanCapacity::from_str("100").unwrap())
+        .build_and_sign_with(&mut cell_collector, &ctx, &ctx, &ctx);
+    assert!(matches!(result, Err(TxBuilderError::InvalidParameter(_))));
+
+    let mut cell_collector = ctx.to_live_cells_context();
+    let result = SimpleTransferBuilder::new(NetworkInfo::testnet())
+        .add_sighash_key(secret_key(&ACCOUNT1_KEY))
+        .build_and_sign_with(&mut cell_collector, &ctx, &ctx, &ctx);
+    assert!(matches!(result, Err(TxBuilderError::InvalidParameter(_))));
+}
@@ -0,0 +1,144 @@
+use ckb_dao_utils::pack_dao_data;
+use ckb_types::{
+    bytes::Bytes,
+    core::{Capacity, EpochNumberWithFraction, HeaderBuilder},
+    packed::{CellInput, CellOutput, WitnessArgs},
+    prelude::*,
+};
+
+use crate::{
+    constants::ONE_CKB,
+    test_util::random_out_point,
+    tests::{build_dao_script, build_sighash_script, init_context, ACCOUNT1_ARG},
+    traits::LiveCell,
+    transaction::{
+        builder::{CkbTransactionBuilder, SimpleTransactionBuilder},
+        handler::{dao::DaoContext, HandlerContexts},
+        input::{InputIterator, TransactionInput},
+        TransactionBuilderConfiguration,
+    },
+    util::minimal_unlock_point,
+    NetworkInfo, Since, SinceType,
+};
+
+#[test]
+fn test_dao_header_deps() {
+    let sender = build_sighash_script(ACCOUNT1_ARG);
+    let mut ctx = init_context(Vec::new(), vec![(sender.clone(), Some(300 * ONE_CKB))]);
+
+    let (deposit_point, prepare_point) = ((5, 5, 1000), (184, 4, 1000));
+    let deposit_number = deposit_point.0 * deposit_point.2 + deposit_point.1;
+    let prepare_number = prepare_point.0 * prepare_point.2 + prepare_point.1;
+    let deposit_point =
+        EpochNumberWithFraction::new(deposit_point.0, deposit_point.1, deposit_point.2);
+    let prepare_point =
+        EpochNumberWithFraction::new(prepare_point.0, prepare_point.1, prepare_point.2);
+    let deposit_header = HeaderBuilder::default()
+        .epoch(deposit_point.full_value().pack())
+        .number(deposit_number.pack())
+        .dao(pack_dao_data(
+            10_000_000_000_123_456,
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        ))
+        .build();
+    let prepare_header = HeaderBuilder::default()
+        .epoch(prepare_point.full_value().pack())
+        .number(prepare_number.pack())
+        .dao(pack_dao_data(
+            10_000_000_001_123_456,
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        ))
+        .build();
+    let deposit_block_hash = deposit_header.hash();
+    let prepare_block_hash = prepare_header.hash();
+
+    // a deposit cell (withdraw phase 1)
+    let deposit_out_point = random_out_point();
+    let deposit_output = CellOutput::new_builder()
+        .capacity((220 * ONE_CKB).pack())
+        .lock(sender.clone())
+        .type_(Some(build_dao_script()).pack())
+        .build();
+    let deposit_data = Bytes::from(vec![0u8; 8]);
+    ctx.add_live_cell(
+        CellInput::new(deposit_out_point.clone(), 0),
+        deposit_output.clone(),
+        deposit_data.clone(),
+        Some(deposit_block_hash.clone()),
+    );
+
+    // a prepared cell (withdraw phase 2)
+    let unlock_point = minimal_unlock_point(&deposit_header, &prepare_header);
+    let since = Since::new(
+        SinceType::EpochNumberWithFraction,
+        unlock_point.full_value(),
+        false,
+    );
+    let prepare_out_point = random_out_point();
+    let prepare_data = Bytes::from(deposit_number.to_le_bytes().to_vec());
+    ctx.add_live_cell(
+        CellInput::new(prepare_out_point.clone(), since.value()),
+        deposit_output.clone(),
+        prepare_data.clone(),
+        Some(prepare_block_hash.clone()),
+    );
+    ctx.add_header(deposit_header);
+    ctx.add_header(prepare_header);
+
+    let network_info = NetworkInfo::testnet();
+    let configuration =
+        TransactionBuilderConfiguration::new_with_network(network_info.clone()).unwrap();
+    let mut iterator = InputIterator::new_with_cell_collector(
+        vec![sender.clone()],
+        Box::new(ctx.to_live_cells_context()) as Box<_>,
+    );
+    iterator.push_input(TransactionInput::new(
+        LiveCell {
+            output: deposit_output.clone(),
+            output_data: prepare_data,
+            out_point: prepare_out_point,
+            block_number: prepare_number,
+            tx_index: 1,
+        },
+        since.value(),
+    ));
+    iterator.push_input(TransactionInput::new(
+        LiveCell {
+            output: deposit_output,
+            output_data: deposit_data,
+            out_point: deposit_out_point,
+            block_number: deposit_number,
+            tx_index: 1,
+        },
+        0,
+    ));
+    let mut builder = SimpleTransactionBuilder::new(configuration, iterator);
+    builder.add_output(sender, Capacity::shannons(300 * ONE_CKB));
+
+    let mut contexts = HandlerContexts::default();
+    contexts.add_context(Box::new(DaoContext::new(
+        Box::new(ctx.clone()),
+        Box::new(ctx),
+    )));
+    let tx_with_groups = builder.build(&contexts).expect("build failed");
+    let tx = tx_with_groups.get_tx_view();
+
+    assert_eq!(tx.inputs().len(), 2);
+    assert_eq!(
+        tx.header_deps().into_iter().collect::<Vec<_>>(),
+        vec![deposit_block_hash, prepare_block_hash]
+    );
+    // the deposit cell's witness is untouched
+    let witness = WitnessArgs::from_slice(&tx.witnesses().get(0).unwrap().raw_data()).unwrap();
+    assert!(witness.input_type().is_none());
+    // the prepared cell's witness points to the deposit header
+    let witness = WitnessArgs::from_slice(&tx.witnesses().get(1).unwrap().raw_data()).unwrap();
+    assert_eq!(
+        witness.input_type().to_opt().unwrap().raw_data(),
+        Bytes::from(0u64.to_le_bytes().to_vec())
+    );
+}
@@ -1,2 +1,3 @@
+pub mod dao;
 pub mod sighash;
 pub mod typeid;
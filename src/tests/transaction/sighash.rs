@@ -11,6 +11,7 @@ use crate::{
         signer::{SignContexts, TransactionSigner},
         TransactionBuilderConfiguration,
     },
+    tx_builder::{BalanceTxCapacityError, ChangeOutput, TxBuilderError},
     NetworkInfo,
 };
 
@@ -76,3 +77,95 @@ fn test_transfer_from_sighash() {
 
     ctx.verify(tx, FEE_RATE).unwrap();
 }
+
+#[test]
+fn test_transfer_built_result() {
+    let sender = build_sighash_script(ACCOUNT1_ARG);
+    let receiver = build_sighash_script(ACCOUNT2_ARG);
+    let ctx = init_context(
+        Vec::new(),
+        vec![
+            (sender.clone(), Some(100 * ONE_CKB)),
+            (sender.clone(), Some(200 * ONE_CKB)),
+        ],
+    );
+
+    let network_info = NetworkInfo::testnet();
+    let output = CellOutput::new_builder()
+        .capacity((120 * ONE_CKB).pack())
+        .lock(receiver)
+        .build();
+    let configuration =
+        TransactionBuilderConfiguration::new_with_network(network_info.clone()).unwrap();
+
+    let iterator = InputIterator::new_with_cell_collector(
+        vec![sender.clone()],
+        Box::new(ctx.to_live_cells_context()) as Box<_>,
+    );
+    let mut builder = SimpleTransactionBuilder::new(configuration, iterator);
+    builder.add_output_and_data(output, ckb_types::packed::Bytes::default());
+    builder.set_change_lock(sender.clone());
+    let (mut tx_with_groups, built_tx) = builder
+        .build_result(&Default::default())
+        .expect("build failed");
+
+    assert_eq!(
+        built_tx.change_outputs,
+        vec![ChangeOutput {
+            index: 1,
+            lock_script: sender,
+        }]
+    );
+    let tx = tx_with_groups.get_tx_view().clone();
+    let change_capacity: u64 = tx.output(1).unwrap().capacity().unpack();
+    assert_eq!(built_tx.fee, (100 + 200 - 120) * ONE_CKB - change_capacity);
+    assert_eq!(
+        built_tx.consumed_out_points,
+        tx.input_pts_iter().collect::<Vec<_>>()
+    );
+
+    TransactionSigner::new(&network_info)
+        .sign_transaction(
+            &mut tx_with_groups,
+            &SignContexts::new_sighash_h256(vec![ACCOUNT1_KEY.clone()]).unwrap(),
+        )
+        .unwrap();
+    let tx = tx_with_groups.get_tx_view().clone();
+    assert_eq!(built_tx.tx_hash, tx.hash());
+    ctx.verify(tx, FEE_RATE).unwrap();
+}
+
+#[test]
+fn test_transfer_exceed_size_limit() {
+    let sender = build_sighash_script(ACCOUNT1_ARG);
+    let receiver = build_sighash_script(ACCOUNT2_ARG);
+    let ctx = init_context(
+        Vec::new(),
+        vec![
+            (sender.clone(), Some(100 * ONE_CKB)),
+            (sender.clone(), Some(200 * ONE_CKB)),
+        ],
+    );
+
+    let output = CellOutput::new_builder()
+        .capacity((120 * ONE_CKB).pack())
+        .lock(receiver)
+        .build();
+    let mut configuration =
+        TransactionBuilderConfiguration::new_with_network(NetworkInfo::testnet()).unwrap();
+    configuration.set_max_tx_size(Some(300));
+
+    let iterator = InputIterator::new_with_cell_collector(
+        vec![sender.clone()],
+        Box::new(ctx.to_live_cells_context()) as Box<_>,
+    );
+    let mut builder = SimpleTransactionBuilder::new(configuration, iterator);
+    builder.add_output_and_data(output, ckb_types::packed::Bytes::default());
+    let result = builder.build(&Default::default());
+    assert!(matches!(
+        result,
+        Err(TxBuilderError::BalanceCapacity(
+            BalanceTxCapacityError::ExceedTxSizeLimit(_, 300, _)
+        ))
+    ));
+}
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+use ckb_types::{core::TransactionView, prelude::*};
+
+use crate::{
+    constants::{ONE_CKB, SIGHASH_TYPE_HASH},
+    test_util::Context,
+    tests::{
+        build_sighash_script, init_context, ACCOUNT1_ARG, ACCOUNT1_KEY, ACCOUNT2_ARG, FEE_RATE,
+    },
+    traits::{CellCollectorError, SecpCkbRawKeySigner},
+    transaction::transfer::{build_consolidate_tx, build_transfer_tx},
+    tx_builder::{unlock_tx, BalanceTxCapacityError, TxBuilderError},
+    unlock::{ScriptUnlocker, SecpSighashUnlocker},
+    ScriptId,
+};
+
+fn sign_tx(ctx: &Context, tx: TransactionView) -> TransactionView {
+    let account1_key = secp256k1::SecretKey::from_slice(ACCOUNT1_KEY.as_bytes()).unwrap();
+    let signer = SecpCkbRawKeySigner::new_with_secret_keys(vec![account1_key]);
+    let mut unlockers: HashMap<ScriptId, Box<dyn ScriptUnlocker>> = HashMap::default();
+    unlockers.insert(
+        ScriptId::new_type(SIGHASH_TYPE_HASH.clone()),
+        Box::new(SecpSighashUnlocker::from(Box::new(signer) as Box<_>)),
+    );
+    let (tx, locked_groups) = unlock_tx(tx, ctx, &unlockers).unwrap();
+    assert!(locked_groups.is_empty());
+    tx
+}
+
+#[test]
+fn test_build_transfer_tx() {
+    let sender = build_sighash_script(ACCOUNT1_ARG);
+    let receiver = build_sighash_script(ACCOUNT2_ARG);
+    let ctx = init_context(
+        Vec::new(),
+        vec![
+            (sender.clone(), Some(100 * ONE_CKB)),
+            (sender.clone(), Some(200 * ONE_CKB)),
+        ],
+    );
+
+    let mut cell_collector = ctx.to_live_cells_context();
+    let tx = build_transfer_tx(
+        &mut cell_collector,
+        &ctx,
+        sender.clone(),
+        receiver.clone(),
+        120 * ONE_CKB,
+        FEE_RATE,
+    )
+    .unwrap();
+    assert_eq!(tx.cell_deps().len(), 1);
+    assert_eq!(tx.inputs().len(), 2);
+    assert_eq!(tx.outputs().len(), 2);
+    assert_eq!(tx.output(0).unwrap().lock(), receiver);
+    let capacity: u64 = tx.output(0).unwrap().capacity().unpack();
+    assert_eq!(capacity, 120 * ONE_CKB);
+    assert_eq!(tx.output(1).unwrap().lock(), sender);
+
+    ctx.verify(sign_tx(&ctx, tx), FEE_RATE).unwrap();
+
+    // all cells are locked by the previous transfer
+    let err = build_transfer_tx(
+        &mut cell_collector,
+        &ctx,
+        sender,
+        receiver,
+        ONE_CKB * 100,
+        FEE_RATE,
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        TxBuilderError::CellCollector(CellCollectorError::InsufficientCapacity {
+            available: 0,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_build_transfer_tx_without_change() {
+    let sender = build_sighash_script(ACCOUNT1_ARG);
+    let receiver = build_sighash_script(ACCOUNT2_ARG);
+    let ctx = init_context(Vec::new(), vec![(sender.clone(), Some(100 * ONE_CKB))]);
+
+    // the small change can not be a cell, it is paid as fee
+    let mut cell_collector = ctx.to_live_cells_context();
+    let tx = build_transfer_tx(
+        &mut cell_collector,
+        &ctx,
+        sender.clone(),
+        receiver.clone(),
+        90 * ONE_CKB,
+        FEE_RATE,
+    )
+    .unwrap();
+    assert_eq!(tx.inputs().len(), 1);
+    assert_eq!(tx.outputs().len(), 1);
+
+    // the amount is less than the occupied capacity
+    let mut cell_collector = ctx.to_live_cells_context();
+    assert!(matches!(
+        build_transfer_tx(
+            &mut cell_collector,
+            &ctx,
+            sender,
+            receiver,
+            ONE_CKB,
+            FEE_RATE
+        ),
+        Err(TxBuilderError::InvalidParameter(_))
+    ));
+}
+
+#[test]
+fn test_build_transfer_tx_failed() {
+    let sender = build_sighash_script(ACCOUNT1_ARG);
+    let receiver = build_sighash_script(ACCOUNT2_ARG);
+    let ctx = init_context(Vec::new(), vec![(sender.clone(), Some(100 * ONE_CKB))]);
+
+    let mut cell_collector = ctx.to_live_cells_context();
+    assert!(matches!(
+        build_transfer_tx(
+            &mut cell_collector,
+            &ctx,
+            sender.clone(),
+            receiver.clone(),
+            u64::MAX,
+            FEE_RATE
+        ),
+        Err(TxBuilderError::InvalidParameter(_))
+    ));
+    assert!(matches!(
+        build_transfer_tx(
+            &mut cell_collector,
+            &ctx,
+            sender.clone(),
+            receiver.clone(),
+            200 * ONE_CKB,
+            FEE_RATE
+        ),
+        Err(TxBuilderError::CellCollector(
+            CellCollectorError::InsufficientCapacity { .. }
+        ))
+    ));
+    // the cells are not locked by the failed attempts
+    let tx = build_transfer_tx(
+        &mut cell_collector,
+        &ctx,
+        sender,
+        receiver,
+        70 * ONE_CKB,
+        FEE_RATE,
+    )
+    .unwrap();
+    assert_eq!(tx.inputs().len(), 1);
+}
+
+#[test]
+fn test_build_consolidate_tx() {
+    let owner = build_sighash_script(ACCOUNT1_ARG);
+    let other = build_sighash_script(ACCOUNT2_ARG);
+    let ctx = init_context(
+        Vec::new(),
+        vec![
+            (owner.clone(), Some(100 * ONE_CKB)),
+            (owner.clone(), Some(200 * ONE_CKB)),
+            (owner.clone(), Some(300 * ONE_CKB)),
+            (other.clone(), Some(400 * ONE_CKB)),
+        ],
+    );
+
+    let mut cell_collector = ctx.to_live_cells_context();
+    let tx = build_consolidate_tx(&mut cell_collector, &ctx, owner.clone(), 2, FEE_RATE).unwrap();
+    assert_eq!(tx.inputs().len(), 2);
+    assert_eq!(tx.outputs().len(), 1);
+    assert_eq!(tx.output(0).unwrap().lock(), owner);
+    let capacity: u64 = tx.output(0).unwrap().capacity().unpack();
+    assert!(capacity < 300 * ONE_CKB && capacity > 299 * ONE_CKB);
+    for out_point in tx.input_pts_iter() {
+        assert_eq!(ctx.get_input(&out_point).unwrap().0.lock(), owner);
+    }
+    ctx.verify(sign_tx(&ctx, tx), FEE_RATE).unwrap();
+
+    // only one cell is left
+    assert!(matches!(
+        build_consolidate_tx(&mut cell_collector, &ctx, owner, 2, FEE_RATE),
+        Err(TxBuilderError::InvalidParameter(_))
+    ));
+}
+
+#[test]
+fn test_build_consolidate_tx_fee_consumes_inputs() {
+    let owner = build_sighash_script(ACCOUNT1_ARG);
+    let ctx = init_context(
+        Vec::new(),
+        vec![
+            (owner.clone(), Some(40 * ONE_CKB)),
+            (owner.clone(), Some(41 * ONE_CKB)),
+        ],
+    );
+
+    // the fee is paid with a very high fee rate
+    let mut cell_collector = ctx.to_live_cells_context();
+    assert!(matches!(
+        build_consolidate_tx(&mut cell_collector, &ctx, owner.clone(), 10, 100 * ONE_CKB),
+        Err(TxBuilderError::BalanceCapacity(
+            BalanceTxCapacityError::CapacityNotEnough(_)
+        ))
+    ));
+    // the cells are not locked by the failed attempt
+    let tx = build_consolidate_tx(&mut cell_collector, &ctx, owner, 10, FEE_RATE).unwrap();
+    assert_eq!(tx.inputs().len(), 2);
+}
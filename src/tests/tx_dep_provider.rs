@@ -0,0 +1,118 @@
+use std::{sync::mpsc, thread, time::Duration};
+
+use ckb_jsonrpc_types as json_types;
+use ckb_types::{
+    bytes::Bytes,
+    core::{EpochNumberWithFraction, HeaderView, TransactionBuilder},
+    h256,
+    packed::{CellInput, CellOutput},
+    prelude::*,
+};
+use httpmock::prelude::*;
+
+use crate::{
+    test_util::{random_out_point, MockRpcResult},
+    traits::{DefaultTransactionDependencyProvider, TransactionDependencyProvider},
+};
+
+const THREADS: usize = 16;
+const ROUNDS: usize = 8;
+
+#[test]
+fn test_tx_dep_provider_concurrent_access() {
+    let server = MockServer::start();
+    let output = CellOutput::new_builder().capacity(100u64.pack()).build();
+    let cell_with_status = json_types::CellWithStatus {
+        cell: Some(json_types::CellInfo {
+            output: output.clone().into(),
+            data: Some(json_types::CellData {
+                content: json_types::JsonBytes::from_vec(vec![1, 2, 3]),
+                hash: Default::default(),
+            }),
+        }),
+        status: "live".to_string(),
+    };
+    let cell_mock = server.mock(|when, then| {
+        when.method(POST).path("/").body_contains("get_live_cell");
+        then.status(200)
+            .delay(Duration::from_millis(10))
+            .body(MockRpcResult::new(cell_with_status).to_json());
+    });
+    let tx = TransactionBuilder::default()
+        .input(CellInput::new(random_out_point(), 0))
+        .output(output.clone())
+        .output_data(Bytes::default().pack())
+        .build();
+    let tx_with_status = json_types::TransactionWithStatusResponse {
+        transaction: Some(json_types::ResponseFormat::json(tx.clone().into())),
+        cycles: None,
+        time_added_to_pool: None,
+        tx_status: json_types::TxStatus::committed(1.into(), h256!("0x1")),
+        fee: None,
+        min_replace_fee: None,
+    };
+    let tx_mock = server.mock(|when, then| {
+        when.method(POST).path("/").body_contains("get_transaction");
+        then.status(200)
+            .delay(Duration::from_millis(10))
+            .body(MockRpcResult::new(tx_with_status).to_json());
+    });
+    let header = HeaderView::new_advanced_builder()
+        .number(1.pack())
+        .epoch(EpochNumberWithFraction::new(0, 1, 1000).full_value().pack())
+        .build();
+    let header_json: json_types::HeaderView = header.clone().into();
+    let header_mock = server.mock(|when, then| {
+        when.method(POST).path("/").body_contains("get_header");
+        then.status(200)
+            .delay(Duration::from_millis(10))
+            .body(MockRpcResult::new(Some(header_json)).to_json());
+    });
+
+    let provider = DefaultTransactionDependencyProvider::new(server.base_url().as_str(), 10);
+    let out_point = random_out_point();
+    let (sender, receiver) = mpsc::channel();
+    let handles = (0..THREADS)
+        .map(|_| {
+            let provider = provider.clone();
+            let sender = sender.clone();
+            let out_point = out_point.clone();
+            let tx_hash = tx.hash();
+            let block_hash = header.hash();
+            thread::spawn(move || {
+                let mut results = Vec::with_capacity(ROUNDS);
+                for _ in 0..ROUNDS {
+                    let tx = provider.get_transaction(&tx_hash).unwrap();
+                    let cell = provider.get_cell(&out_point).unwrap();
+                    let data = provider.get_cell_data(&out_point).unwrap();
+                    let header = provider.get_header(&block_hash).unwrap();
+                    results.push((tx.hash(), cell, data, header.hash()));
+                }
+                sender.send(results).unwrap();
+            })
+        })
+        .collect::<Vec<_>>();
+    drop(sender);
+
+    // a deadlock makes the threads never finish
+    for _ in 0..THREADS {
+        let results = receiver
+            .recv_timeout(Duration::from_secs(30))
+            .expect("the provider is deadlocked");
+        for (tx_hash, cell, data, block_hash) in results {
+            assert_eq!(tx_hash, tx.hash());
+            assert_eq!(cell, output);
+            assert_eq!(data, Bytes::from(vec![1, 2, 3]));
+            assert_eq!(block_hash, header.hash());
+        }
+    }
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    // the cache is shared by all clones, every resource is only fetched once, the cell
+    // output and the cell data are fetched separately
+    tx_mock.assert_hits(1);
+    cell_mock.assert_hits(2);
+    header_mock.assert_hits(1);
+}
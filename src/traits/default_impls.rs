@@ -1,49 +1,73 @@
-use std::collections::HashMap;
-use std::sync::Arc;
-use std::thread;
-use std::time::Duration;
+#[cfg(feature = "rpc")]
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    collections::HashSet,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+use std::{collections::HashMap, convert::TryFrom};
 
+#[cfg(feature = "rpc")]
 use anyhow::anyhow;
 use ckb_crypto::secp::Pubkey;
+#[cfg(feature = "rpc")]
 use lru::LruCache;
+#[cfg(feature = "rpc")]
 use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use ckb_hash::blake2b_256;
-use ckb_jsonrpc_types::{self as json_types, Either};
+use ckb_jsonrpc_types as json_types;
+#[cfg(feature = "rpc")]
+use ckb_jsonrpc_types::Either;
 use ckb_types::{
     bytes::Bytes,
-    core::{BlockView, DepType, HeaderView, TransactionView},
-    packed::{Byte32, CellDep, CellOutput, OutPoint, Script, Transaction, TransactionReader},
+    core::{BlockView, DepType, ScriptHashType, TransactionView},
+    packed::{Byte32, CellDep, CellOutput, OutPoint, Script},
     prelude::*,
-    H160,
+    H160, H256,
+};
+#[cfg(feature = "rpc")]
+use ckb_types::{
+    core::{EpochNumberWithFraction, HeaderView},
+    packed::{Transaction, TransactionReader},
 };
 
+#[cfg(feature = "disk-cache")]
+use super::disk_cache::{DiskCache, DiskCacheKind};
+use super::OffchainCellDepResolver;
+#[cfg(feature = "rpc")]
 use super::{
-    offchain_impls::CollectResult, OffchainCellCollector, OffchainCellDepResolver,
-    OffchainTransactionDependencyProvider,
+    offchain_impls::CollectResult, OffchainCellCollector, OffchainTransactionDependencyProvider,
+};
+#[cfg(feature = "rpc")]
+use crate::rpc::{
+    ckb_indexer::{Cell, CellsCapacityProvider, Order, Pagination, SearchKey, SearchMode, Tip},
+    CkbRpcClient, CkbRpcInterface, IndexerRpcClient, MultiEndpointClient, RpcError,
 };
-use crate::rpc::ckb_indexer::{Order, SearchKey, Tip};
-use crate::rpc::{CkbRpcClient, IndexerRpcClient};
+#[cfg(feature = "rpc")]
 use crate::traits::{
-    CellCollector, CellCollectorError, CellDepResolver, CellQueryOptions, HeaderDepResolver,
-    LiveCell, QueryOrder, Signer, SignerError, TransactionDependencyError,
-    TransactionDependencyProvider,
+    CellCollector, CellCollectorError, CellQueryOptions, HeaderDepResolver, LiveCell, QueryOrder,
+    TransactionDependencyError, TransactionDependencyProvider, ValueRangeOption,
 };
+use crate::traits::{CellDepResolver, Signer, SignerError};
 use crate::types::ScriptId;
-use crate::util::{get_max_mature_number, serialize_signature, zeroize_privkey};
+#[cfg(feature = "rpc")]
+use crate::util::get_max_mature_number_inner;
+use crate::util::{blake160, serialize_signature, zeroize_privkey};
 use crate::SECP256K1;
 use crate::{
     constants::{
-        DAO_OUTPUT_LOC, DAO_TYPE_HASH, MULTISIG_GROUP_OUTPUT_LOC, MULTISIG_OUTPUT_LOC,
-        MULTISIG_TYPE_HASH, SIGHASH_GROUP_OUTPUT_LOC, SIGHASH_OUTPUT_LOC, SIGHASH_TYPE_HASH,
+        DAO_DATA_HASH, DAO_OUTPUT_LOC, DAO_TYPE_HASH, MULTISIG_DATA_HASH,
+        MULTISIG_GROUP_OUTPUT_LOC, MULTISIG_OUTPUT_LOC, MULTISIG_TYPE_HASH, SIGHASH_DATA_HASH,
+        SIGHASH_GROUP_OUTPUT_LOC, SIGHASH_OUTPUT_LOC, SIGHASH_TYPE_HASH,
     },
     util::keccak160,
 };
-use ckb_resource::{
-    CODE_HASH_DAO, CODE_HASH_SECP256K1_BLAKE160_MULTISIG_ALL,
-    CODE_HASH_SECP256K1_BLAKE160_SIGHASH_ALL,
-};
+#[cfg(feature = "disk-cache")]
+use ckb_types::packed::HeaderReader;
 
 /// Parse Genesis Info errors
 #[derive(Error, Debug)]
@@ -54,17 +78,82 @@ pub enum ParseGenesisInfoError {
     DataHashNotFound(String),
     #[error("type not found: `{0}`")]
     TypeHashNotFound(String),
+    #[error("invalid cell deps json: `{0}`")]
+    InvalidCellDepsJson(String),
+    #[error("invalid genesis info: `{0}`")]
+    InvalidGenesisInfo(String),
 }
 
-/// A cell_dep resolver use genesis info resolve system scripts and can register more cell_dep info.
-#[derive(Clone)]
-pub struct DefaultCellDepResolver {
-    offchain: OffchainCellDepResolver,
+/// A cell dep item in json format, used by [`DefaultCellDepResolver::from_genesis_info_and_json`]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CellDepItem {
+    pub name: String,
+    pub code_hash: H256,
+    pub hash_type: json_types::ScriptHashType,
+    pub cell_dep: json_types::CellDep,
 }
-impl DefaultCellDepResolver {
-    pub fn from_genesis(
+
+/// The system scripts info parsed from the genesis block, it can be exported to json by
+/// an online machine, then the offline machines build the [`DefaultCellDepResolver`]
+/// from it without calling `get_block_by_number(0)`.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+#[serde(try_from = "JsonGenesisInfo", into = "JsonGenesisInfo")]
+pub struct GenesisInfo {
+    pub genesis_hash: H256,
+    pub sighash_type_hash: H256,
+    pub multisig_type_hash: H256,
+    pub dao_type_hash: H256,
+    pub sighash_dep: CellDep,
+    pub multisig_dep: CellDep,
+    pub dao_dep: CellDep,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonGenesisInfo {
+    genesis_hash: H256,
+    sighash_type_hash: H256,
+    multisig_type_hash: H256,
+    dao_type_hash: H256,
+    sighash_dep: json_types::CellDep,
+    multisig_dep: json_types::CellDep,
+    dao_dep: json_types::CellDep,
+}
+
+impl TryFrom<JsonGenesisInfo> for GenesisInfo {
+    type Error = ParseGenesisInfoError;
+    fn try_from(json: JsonGenesisInfo) -> Result<GenesisInfo, ParseGenesisInfoError> {
+        let info = GenesisInfo {
+            genesis_hash: json.genesis_hash,
+            sighash_type_hash: json.sighash_type_hash,
+            multisig_type_hash: json.multisig_type_hash,
+            dao_type_hash: json.dao_type_hash,
+            sighash_dep: json.sighash_dep.into(),
+            multisig_dep: json.multisig_dep.into(),
+            dao_dep: json.dao_dep.into(),
+        };
+        info.check()?;
+        Ok(info)
+    }
+}
+
+impl From<GenesisInfo> for JsonGenesisInfo {
+    fn from(info: GenesisInfo) -> JsonGenesisInfo {
+        JsonGenesisInfo {
+            genesis_hash: info.genesis_hash,
+            sighash_type_hash: info.sighash_type_hash,
+            multisig_type_hash: info.multisig_type_hash,
+            dao_type_hash: info.dao_type_hash,
+            sighash_dep: info.sighash_dep.into(),
+            multisig_dep: info.multisig_dep.into(),
+            dao_dep: info.dao_dep.into(),
+        }
+    }
+}
+
+impl GenesisInfo {
+    pub fn from_genesis_block(
         genesis_block: &BlockView,
-    ) -> Result<DefaultCellDepResolver, ParseGenesisInfoError> {
+    ) -> Result<GenesisInfo, ParseGenesisInfoError> {
         let header = genesis_block.header();
         if header.number() != 0 {
             return Err(ParseGenesisInfoError::InvalidBlockNumber(header.number()));
@@ -88,11 +177,11 @@ impl DefaultCellDepResolver {
                                 .to_opt()
                                 .map(|script| script.calc_script_hash());
                             let data_hash = CellOutput::calc_data_hash(&data.raw_data());
-                            if data_hash != CODE_HASH_SECP256K1_BLAKE160_SIGHASH_ALL.pack() {
+                            if data_hash != SIGHASH_DATA_HASH.pack() {
                                 log::error!(
                                     "System sighash script code hash error! found: {}, expected: {}",
                                     data_hash,
-                                    CODE_HASH_SECP256K1_BLAKE160_SIGHASH_ALL,
+                                    SIGHASH_DATA_HASH,
                                 );
                             }
                         }
@@ -102,11 +191,11 @@ impl DefaultCellDepResolver {
                                 .to_opt()
                                 .map(|script| script.calc_script_hash());
                             let data_hash = CellOutput::calc_data_hash(&data.raw_data());
-                            if data_hash != CODE_HASH_SECP256K1_BLAKE160_MULTISIG_ALL.pack() {
+                            if data_hash != MULTISIG_DATA_HASH.pack() {
                                 log::error!(
                                     "System multisig script code hash error! found: {}, expected: {}",
                                     data_hash,
-                                    CODE_HASH_SECP256K1_BLAKE160_MULTISIG_ALL,
+                                    MULTISIG_DATA_HASH,
                                 );
                             }
                         }
@@ -116,11 +205,11 @@ impl DefaultCellDepResolver {
                                 .to_opt()
                                 .map(|script| script.calc_script_hash());
                             let data_hash = CellOutput::calc_data_hash(&data.raw_data());
-                            if data_hash != CODE_HASH_DAO.pack() {
+                            if data_hash != DAO_DATA_HASH.pack() {
                                 log::error!(
                                     "System dao script code hash error! found: {}, expected: {}",
                                     data_hash,
-                                    CODE_HASH_DAO,
+                                    DAO_DATA_HASH,
                                 );
                             }
                         }
@@ -152,22 +241,137 @@ impl DefaultCellDepResolver {
             .out_point(out_points[DAO_OUTPUT_LOC.0][DAO_OUTPUT_LOC.1].clone())
             .build();
 
+        Ok(GenesisInfo {
+            genesis_hash: header.hash().unpack(),
+            sighash_type_hash: sighash_type_hash.unpack(),
+            multisig_type_hash: multisig_type_hash.unpack(),
+            dao_type_hash: dao_type_hash.unpack(),
+            sighash_dep,
+            multisig_dep,
+            dao_dep,
+        })
+    }
+
+    /// Check the cell deps are at the locations of the system cells in the genesis
+    /// block and the type hashes are distinct, it is checked when deserialized.
+    pub fn check(&self) -> Result<(), ParseGenesisInfoError> {
+        let invalid = |reason: &str| {
+            Err(ParseGenesisInfoError::InvalidGenesisInfo(
+                reason.to_string(),
+            ))
+        };
+        let location = |cell_dep: &CellDep| -> (Byte32, usize, DepType) {
+            let out_point = cell_dep.out_point();
+            let index: u32 = out_point.index().unpack();
+            let dep_type = DepType::try_from(cell_dep.dep_type()).unwrap_or(DepType::Code);
+            (out_point.tx_hash(), index as usize, dep_type)
+        };
+        let (sighash_tx, sighash_index, sighash_dep_type) = location(&self.sighash_dep);
+        let (multisig_tx, multisig_index, multisig_dep_type) = location(&self.multisig_dep);
+        let (dao_tx, dao_index, dao_dep_type) = location(&self.dao_dep);
+        if sighash_index != SIGHASH_GROUP_OUTPUT_LOC.1 || sighash_dep_type != DepType::DepGroup {
+            return invalid("the sighash dep is not the genesis sighash dep group");
+        }
+        if multisig_index != MULTISIG_GROUP_OUTPUT_LOC.1 || multisig_dep_type != DepType::DepGroup {
+            return invalid("the multisig dep is not the genesis multisig dep group");
+        }
+        if sighash_tx != multisig_tx {
+            return invalid("the sighash and multisig dep groups are not in the same transaction");
+        }
+        if dao_index != DAO_OUTPUT_LOC.1 || dao_dep_type != DepType::Code || dao_tx == sighash_tx {
+            return invalid("the dao dep is not the genesis dao cell");
+        }
+        if self.sighash_type_hash == self.multisig_type_hash
+            || self.sighash_type_hash == self.dao_type_hash
+            || self.multisig_type_hash == self.dao_type_hash
+        {
+            return invalid("the type hashes of the system scripts are not distinct");
+        }
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("serialize genesis info")
+    }
+
+    pub fn from_json(json: &str) -> Result<GenesisInfo, ParseGenesisInfoError> {
+        serde_json::from_str(json)
+            .map_err(|err| ParseGenesisInfoError::InvalidGenesisInfo(err.to_string()))
+    }
+}
+
+/// A cell_dep resolver use genesis info resolve system scripts and can register more cell_dep info.
+#[derive(Clone)]
+pub struct DefaultCellDepResolver {
+    offchain: OffchainCellDepResolver,
+}
+impl DefaultCellDepResolver {
+    pub fn new(info: &GenesisInfo) -> DefaultCellDepResolver {
         let mut items = HashMap::default();
         items.insert(
-            ScriptId::new_type(sighash_type_hash.unpack()),
-            (sighash_dep, "Secp256k1 blake160 sighash all".to_string()),
+            ScriptId::new_type(info.sighash_type_hash.clone()),
+            (
+                info.sighash_dep.clone(),
+                "Secp256k1 blake160 sighash all".to_string(),
+            ),
         );
         items.insert(
-            ScriptId::new_type(multisig_type_hash.unpack()),
-            (multisig_dep, "Secp256k1 blake160 multisig all".to_string()),
+            ScriptId::new_type(info.multisig_type_hash.clone()),
+            (
+                info.multisig_dep.clone(),
+                "Secp256k1 blake160 multisig all".to_string(),
+            ),
         );
         items.insert(
-            ScriptId::new_type(dao_type_hash.unpack()),
-            (dao_dep, "Nervos DAO".to_string()),
+            ScriptId::new_type(info.dao_type_hash.clone()),
+            (info.dao_dep.clone(), "Nervos DAO".to_string()),
         );
         let offchain = OffchainCellDepResolver { items };
-        Ok(DefaultCellDepResolver { offchain })
+        DefaultCellDepResolver { offchain }
+    }
+
+    pub fn from_genesis(
+        genesis_block: &BlockView,
+    ) -> Result<DefaultCellDepResolver, ParseGenesisInfoError> {
+        GenesisInfo::from_genesis_block(genesis_block)
+            .map(|info| DefaultCellDepResolver::new(&info))
+    }
+
+    /// Create the resolver from the genesis info, then merge the cell deps from the json
+    /// string, the json is an array of [`CellDepItem`], and the items in json will
+    /// override the system scripts resolved from the genesis info.
+    pub fn from_genesis_info_and_json(
+        genesis_info: &GenesisInfo,
+        json: &str,
+    ) -> Result<DefaultCellDepResolver, ParseGenesisInfoError> {
+        let items: Vec<CellDepItem> = serde_json::from_str(json)
+            .map_err(|err| ParseGenesisInfoError::InvalidCellDepsJson(err.to_string()))?;
+        let mut resolver = Self::new(genesis_info);
+        for item in items {
+            let script_id = ScriptId::new(item.code_hash, ScriptHashType::from(item.hash_type));
+            resolver.insert(script_id, item.cell_dep.into(), item.name);
+        }
+        Ok(resolver)
+    }
+
+    /// Serialize all the registered cell deps to a json array of [`CellDepItem`] (sorted
+    /// by name and code hash), it can be loaded by `from_genesis_info_and_json`.
+    pub fn to_json_string(&self) -> String {
+        let mut items: Vec<CellDepItem> = self
+            .offchain
+            .items
+            .iter()
+            .map(|(script_id, (cell_dep, name))| CellDepItem {
+                name: name.clone(),
+                code_hash: script_id.code_hash.clone(),
+                hash_type: script_id.hash_type.into(),
+                cell_dep: cell_dep.clone().into(),
+            })
+            .collect();
+        items.sort_by(|a, b| (&a.name, &a.code_hash).cmp(&(&b.name, &b.code_hash)));
+        serde_json::to_string_pretty(&items).expect("serialize cell deps")
     }
+
     pub fn insert(
         &mut self,
         script_id: ScriptId,
@@ -203,15 +407,18 @@ impl CellDepResolver for DefaultCellDepResolver {
 }
 
 /// A header_dep resolver use ckb jsonrpc client as backend
+#[cfg(feature = "rpc")]
 pub struct DefaultHeaderDepResolver {
     ckb_client: CkbRpcClient,
 }
+#[cfg(feature = "rpc")]
 impl DefaultHeaderDepResolver {
     pub fn new(ckb_client: &str) -> DefaultHeaderDepResolver {
         let ckb_client = CkbRpcClient::new(ckb_client);
         DefaultHeaderDepResolver { ckb_client }
     }
 }
+#[cfg(feature = "rpc")]
 impl HeaderDepResolver for DefaultHeaderDepResolver {
     fn resolve_by_tx(&self, tx_hash: &Byte32) -> Result<Option<HeaderView>, anyhow::Error> {
         if let Some(block_hash) = self
@@ -236,26 +443,207 @@ impl HeaderDepResolver for DefaultHeaderDepResolver {
             .map_err(|e| anyhow!(e))?
             .map(Into::into))
     }
+    fn resolve_tip(&self) -> Result<Option<HeaderView>, anyhow::Error> {
+        let header = self.ckb_client.get_tip_header().map_err(|e| anyhow!(e))?;
+        Ok(Some(header.into()))
+    }
+}
+
+/// A ckb node and the ckb-indexer server serving the same chain
+#[cfg(feature = "rpc")]
+#[derive(Clone)]
+pub struct CollectorEndpoint {
+    pub indexer_client: IndexerRpcClient,
+    pub ckb_client: CkbRpcClient,
+}
+
+#[cfg(feature = "rpc")]
+impl CollectorEndpoint {
+    pub fn new(ckb_client: &str) -> CollectorEndpoint {
+        CollectorEndpoint {
+            indexer_client: IndexerRpcClient::new(ckb_client),
+            ckb_client: CkbRpcClient::new(ckb_client),
+        }
+    }
+}
+
+/// The rpc backend of the cell collector
+#[cfg(feature = "rpc")]
+#[derive(Clone)]
+enum CollectorBackend {
+    Endpoints(MultiEndpointClient<CollectorEndpoint>),
+    Interface(Arc<dyn CkbRpcInterface + Send + Sync>),
+}
+
+// One rpc backend for a single call: a ckb node with its ckb-indexer, or a `CkbRpcInterface`
+// serving both the chain and the indexer methods.
+#[cfg(feature = "rpc")]
+#[derive(Clone, Copy)]
+enum CollectorRpc<'a> {
+    Endpoint(&'a CollectorEndpoint),
+    Interface(&'a (dyn CkbRpcInterface + Send + Sync)),
+}
+
+#[cfg(feature = "rpc")]
+impl<'a> CollectorRpc<'a> {
+    fn ckb_client(self) -> &'a dyn CkbRpcInterface {
+        match self {
+            CollectorRpc::Endpoint(endpoint) => &endpoint.ckb_client,
+            CollectorRpc::Interface(rpc) => rpc,
+        }
+    }
+
+    fn get_cells(
+        self,
+        search_key: SearchKey,
+        order: Order,
+        limit: json_types::Uint32,
+        after: Option<json_types::JsonBytes>,
+    ) -> Result<Pagination<Cell>, RpcError> {
+        match self {
+            CollectorRpc::Endpoint(endpoint) => endpoint
+                .indexer_client
+                .get_cells(search_key, order, limit, after),
+            CollectorRpc::Interface(rpc) => rpc.get_cells(search_key, order, limit, after),
+        }
+    }
+
+    fn get_indexer_tip(self) -> Result<Option<Tip>, RpcError> {
+        match self {
+            CollectorRpc::Endpoint(endpoint) => endpoint.indexer_client.get_indexer_tip(),
+            CollectorRpc::Interface(rpc) => rpc.get_indexer_tip(),
+        }
+    }
+
+    fn cells_capacity(self, search_key: SearchKey) -> Result<u64, RpcError> {
+        match self {
+            CollectorRpc::Endpoint(endpoint) => endpoint.indexer_client.cells_capacity(search_key),
+            CollectorRpc::Interface(rpc) => Ok(rpc
+                .get_cells_capacity(search_key)?
+                .map(|cells_capacity| cells_capacity.capacity.value())
+                .unwrap_or(0)),
+        }
+    }
+}
+
+// Fail over to the next endpoint when the current one is down or lagging behind
+#[cfg(feature = "rpc")]
+fn should_failover(err: &CellCollectorError) -> bool {
+    match err {
+        CellCollectorError::Rpc(err) => err.is_connection_error(),
+        CellCollectorError::IndexerNotSynced { .. } => true,
+        CellCollectorError::Internal(err) => err
+            .downcast_ref::<RpcError>()
+            .map(RpcError::is_connection_error)
+            .unwrap_or(false),
+        CellCollectorError::InsufficientCapacity { .. } | CellCollectorError::Other(_) => false,
+    }
+}
+
+/// How far the ckb-indexer tip may fall behind the ckb node tip before the cells are
+/// collected from it.
+#[cfg(feature = "rpc")]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SyncTolerance {
+    /// Wait for the ckb-indexer to catch up within the acceptable leftbehind (default)
+    Strict,
+    /// Accept the ckb-indexer tip at most n blocks behind the ckb node tip
+    Blocks(u64),
+    /// Collect from the ckb-indexer without checking its tip
+    Skip,
 }
 
 /// A cell collector use ckb-indexer as backend
+///
+/// A clone has its own copy of the offchain state (the locked cells and the offchain
+/// live cells) and shares the rpc clients, it can be used to build a transaction
+/// speculatively without touching the original collector.
+#[cfg(feature = "rpc")]
 #[derive(Clone)]
 pub struct DefaultCellCollector {
-    indexer_client: IndexerRpcClient,
-    ckb_client: CkbRpcClient,
+    backend: CollectorBackend,
     offchain: OffchainCellCollector,
     acceptable_indexer_leftbehind: u64,
+    sync_tolerance: SyncTolerance,
+    last_indexer_tip: Option<u64>,
 }
 
+#[cfg(feature = "rpc")]
 impl DefaultCellCollector {
     pub fn new(ckb_client: &str) -> DefaultCellCollector {
-        let indexer_client = IndexerRpcClient::new(ckb_client);
-        let ckb_client = CkbRpcClient::new(ckb_client);
+        Self::new_with_urls(&[ckb_client])
+    }
+
+    /// Create the cell collector with redundant ckb node (with ckb-indexer enabled) urls
+    /// ordered by priority, it fails over to the next node when the current one is down
+    /// or the ckb-indexer is lagging behind.
+    ///
+    /// Panics if `urls` is empty.
+    pub fn new_with_urls(urls: &[&str]) -> DefaultCellCollector {
+        Self::new_with_backend(CollectorBackend::Endpoints(MultiEndpointClient::from_urls(
+            urls,
+            CollectorEndpoint::new,
+        )))
+    }
+
+    /// Create the cell collector with any rpc backend implemented [`CkbRpcInterface`]
+    /// serving both the chain and the indexer methods, e.g. a `LightClientRpcClient`.
+    ///
+    /// The ckb-indexer sync check is skipped if the backend does not support
+    /// `get_indexer_tip`, and the cellbase cells (except the genesis ones) are treated
+    /// as immature if it does not support `get_consensus`.
+    pub fn from_rpc_interface(rpc: Arc<dyn CkbRpcInterface + Send + Sync>) -> DefaultCellCollector {
+        Self::new_with_backend(CollectorBackend::Interface(rpc))
+    }
+
+    fn new_with_backend(backend: CollectorBackend) -> DefaultCellCollector {
         DefaultCellCollector {
-            indexer_client,
-            ckb_client,
+            backend,
             offchain: OffchainCellCollector::default(),
             acceptable_indexer_leftbehind: 1,
+            sync_tolerance: SyncTolerance::Strict,
+            last_indexer_tip: None,
+        }
+    }
+
+    /// The endpoints, `None` if the collector is created by `from_rpc_interface`.
+    pub fn endpoints(&self) -> Option<&MultiEndpointClient<CollectorEndpoint>> {
+        match &self.backend {
+            CollectorBackend::Endpoints(endpoints) => Some(endpoints),
+            CollectorBackend::Interface(_) => None,
+        }
+    }
+    pub fn endpoints_mut(&mut self) -> Option<&mut MultiEndpointClient<CollectorEndpoint>> {
+        match &mut self.backend {
+            CollectorBackend::Endpoints(endpoints) => Some(endpoints),
+            CollectorBackend::Interface(_) => None,
+        }
+    }
+
+    // Call the rpc backend, fail over to the next endpoint as `should_failover`
+    fn call_with<T, F>(&self, mut f: F) -> Result<T, CellCollectorError>
+    where
+        F: FnMut(CollectorRpc) -> Result<T, CellCollectorError>,
+    {
+        match &self.backend {
+            CollectorBackend::Endpoints(endpoints) => endpoints
+                .call_with(should_failover, |endpoint| {
+                    f(CollectorRpc::Endpoint(endpoint))
+                }),
+            CollectorBackend::Interface(rpc) => f(CollectorRpc::Interface(rpc.as_ref())),
+        }
+    }
+
+    // Call the rpc backend, fail over to the next endpoint on connection errors
+    fn call<T, F>(&self, f: F) -> Result<T, RpcError>
+    where
+        F: Fn(CollectorRpc) -> Result<T, RpcError>,
+    {
+        match &self.backend {
+            CollectorBackend::Endpoints(endpoints) => {
+                endpoints.call(|endpoint| f(CollectorRpc::Endpoint(endpoint)))
+            }
+            CollectorBackend::Interface(rpc) => f(CollectorRpc::Interface(rpc.as_ref())),
         }
     }
 
@@ -268,114 +656,301 @@ impl DefaultCellCollector {
         self.acceptable_indexer_leftbehind = value;
     }
 
+    /// The ckb-indexer sync tolerance (default = `SyncTolerance::Strict`)
+    pub fn sync_tolerance(&self) -> SyncTolerance {
+        self.sync_tolerance
+    }
+    /// Set the ckb-indexer sync tolerance, relax it only when stale cells are acceptable
+    /// (e.g. read-only analytics).
+    pub fn set_sync_tolerance(&mut self, value: SyncTolerance) {
+        self.sync_tolerance = value;
+    }
+
+    /// The ckb-indexer tip block number checked by the last collecting, `None` if the
+    /// ckb-indexer is not queried yet or the check is skipped.
+    pub fn last_indexer_tip(&self) -> Option<u64> {
+        self.last_indexer_tip
+    }
+
+    // `None` means the check is skipped
+    fn indexer_leftbehind(&self) -> Option<u64> {
+        match self.sync_tolerance {
+            SyncTolerance::Strict => Some(self.acceptable_indexer_leftbehind),
+            SyncTolerance::Blocks(n) => Some(n),
+            SyncTolerance::Skip => None,
+        }
+    }
+
     /// Check if ckb-indexer synced with ckb node. This will check every 50ms for 100 times (more than 5s in total, since ckb-indexer's poll interval is 2.0s).
     pub fn check_ckb_chain(&mut self) -> Result<(), CellCollectorError> {
-        let tip_number = self
-            .ckb_client
-            .get_tip_block_number()
-            .map_err(|err| CellCollectorError::Internal(err.into()))?;
+        let leftbehind = self.indexer_leftbehind();
+        self.last_indexer_tip = self.call_with(|rpc| check_indexer_synced(rpc, leftbehind))?;
+        Ok(())
+    }
 
-        for _ in 0..100 {
-            match self
-                .indexer_client
-                .get_indexer_tip()
-                .map_err(|err| CellCollectorError::Internal(err.into()))?
-            {
-                Some(Tip { block_number, .. }) => {
-                    if tip_number.value()
-                        > block_number.value() + self.acceptable_indexer_leftbehind
-                    {
-                        thread::sleep(Duration::from_millis(50));
-                    } else {
-                        return Ok(());
-                    }
-                }
-                None => {
-                    return Err(CellCollectorError::Other(anyhow!(
-                        "ckb-indexer server not synced"
-                    )));
-                }
+    pub(crate) fn get_tip_block_number(&self) -> Result<u64, CellCollectorError> {
+        self.call(|rpc| rpc.ckb_client().get_tip_block_number())
+            .map_err(CellCollectorError::Rpc)
+    }
+
+    fn get_max_mature_number(&self) -> Result<u64, CellCollectorError> {
+        match self.call(|rpc| get_max_mature_number_inner(rpc.ckb_client())) {
+            // no cellbase cell is mature without the consensus
+            Err(RpcError::Unsupported(_)) => Ok(0),
+            result => result.map_err(CellCollectorError::Rpc),
+        }
+    }
+
+    /// Only mark all inputs of the transaction as dead cells, the outputs are ignored.
+    pub fn apply_tx_inputs_only(&mut self, tx: Transaction) -> Result<(), CellCollectorError> {
+        let tip_num = self.get_tip_block_number()?;
+        self.offchain.apply_tx_inputs(&tx.into_view(), tip_num)
+    }
+
+    /// Only add all outputs of the transaction as live cells, the inputs are ignored.
+    pub fn apply_tx_outputs_only(&mut self, tx: Transaction) -> Result<(), CellCollectorError> {
+        let tip_num = self.get_tip_block_number()?;
+        self.offchain.apply_tx_outputs(&tx.into_view(), tip_num)
+    }
+
+    /// Find the live cell by the out point, the offchain live cells are checked first, the
+    /// cells locked by this collector are treated as dead.
+    ///
+    /// The `block_number` and `tx_index` of the cell returned by the ckb node are 0, as
+    /// the offchain live cells.
+    pub fn find_cell_by_out_point(
+        &self,
+        out_point: &OutPoint,
+    ) -> Result<Option<LiveCell>, CellCollectorError> {
+        if is_locked(&self.offchain.locked_cells, out_point) {
+            return Ok(None);
+        }
+        if let Some((cell, _tip_num)) = self
+            .offchain
+            .live_cells
+            .iter()
+            .find(|(cell, _tip_num)| &cell.out_point == out_point)
+        {
+            return Ok(Some(cell.clone()));
+        }
+        let cell_with_status = self
+            .call(|rpc| {
+                rpc.ckb_client()
+                    .get_live_cell(out_point.clone().into(), true)
+            })
+            .map_err(CellCollectorError::Rpc)?;
+        if cell_with_status.status != "live" {
+            return Ok(None);
+        }
+        LiveCell::from_rpc(cell_with_status, out_point.clone(), 0, 0).map(Some)
+    }
+
+    /// The offchain live cells, e.g. to save a snapshot restored by `set_offchain_cells`.
+    pub fn offchain_cells(&self) -> Vec<LiveCell> {
+        self.offchain
+            .live_cells
+            .iter()
+            .map(|(cell, _tip_num)| cell.clone())
+            .collect()
+    }
+
+    /// Replace all the offchain live cells without applying the transactions, the cells
+    /// expire from the current tip as the outputs added by `apply_tx`.
+    pub fn set_offchain_cells(&mut self, cells: Vec<LiveCell>) -> Result<(), CellCollectorError> {
+        let tip_num = self.get_tip_block_number()?;
+        self.offchain.live_cells = cells.into_iter().map(|cell| (cell, tip_num)).collect();
+        Ok(())
+    }
+
+    /// Add an offchain live cell without applying the transaction, the cell with the
+    /// same out point is replaced.
+    pub fn add_offchain_cell(&mut self, cell: LiveCell) -> Result<(), CellCollectorError> {
+        let tip_num = self.get_tip_block_number()?;
+        self.offchain
+            .live_cells
+            .retain(|(live_cell, _tip_num)| live_cell.out_point != cell.out_point);
+        self.offchain.live_cells.push((cell, tip_num));
+        Ok(())
+    }
+
+    /// Revert the changes made by `apply_tx` for the transaction: the input cells
+    /// locked by it are unlocked and its outputs are removed from the offchain
+    /// live cells, the changes of other applied transactions are kept.
+    ///
+    /// Return false if nothing is changed.
+    pub fn rollback_tx(&mut self, tx_hash: Byte32) -> bool {
+        self.offchain.rollback_tx(&tx_hash)
+    }
+
+    /// Drop the offchain outputs of a committed transaction, they can be collected from
+    /// ckb-indexer now. Unlike `rollback_tx` the input cells are kept locked.
+    ///
+    /// Return false if nothing is changed.
+    pub fn forget_committed_tx(&mut self, tx_hash: &Byte32) -> bool {
+        self.offchain.forget_tx(tx_hash)
+    }
+
+    /// Release the cells locked for a transaction rejected by the node: the input cells
+    /// of the transaction are unlocked (and the changes of `apply_tx` for it are
+    /// reverted), the other locked cells are kept locked, unlike `reset`.
+    ///
+    /// Return the number of unlocked cells.
+    pub fn recover_locked_cells_after_failed_tx(&mut self, tx: &TransactionView) -> usize {
+        self.offchain.recover_tx_inputs(tx)
+    }
+
+    /// Refresh the state when a new tip arrived:
+    ///   * refresh the cached max mature block number
+    ///   * drop the offchain live cells which creating transactions are committed,
+    ///     since they can be collected from ckb-indexer now
+    ///
+    /// Return the number of dropped offchain live cells.
+    pub fn on_new_tip(&mut self, tip_header: &HeaderView) -> Result<usize, CellCollectorError> {
+        let max_mature_number = self.get_max_mature_number()?;
+        #[allow(clippy::mutable_key_type)]
+        let mut committed_tx_hashes = HashSet::new();
+        for tx_hash in self.offchain_tx_hashes() {
+            let is_committed = self
+                .call(|rpc| is_tx_committed(rpc.ckb_client(), &tx_hash))
+                .map_err(CellCollectorError::Rpc)?;
+            if is_committed {
+                committed_tx_hashes.insert(tx_hash);
             }
         }
-        Err(CellCollectorError::Other(anyhow!(
-            "ckb-indexer server inconsistent with currently connected ckb node or not synced!"
-        )))
+        Ok(self.prune_offchain_cells(tip_header, max_mature_number, &committed_tx_hashes))
     }
-}
 
-impl CellCollector for DefaultCellCollector {
-    fn collect_live_cells(
+    /// The hashes of the transactions creating the offchain live cells, without duplicates.
+    pub fn offchain_tx_hashes(&self) -> Vec<Byte32> {
+        #[allow(clippy::mutable_key_type)]
+        let mut visited = HashSet::new();
+        self.offchain
+            .live_cells
+            .iter()
+            .map(|(cell, _tip_num)| cell.out_point.tx_hash())
+            .filter(|tx_hash| visited.insert(tx_hash.clone()))
+            .collect()
+    }
+
+    /// The state changes of `on_new_tip` without rpc calls, the max mature block number
+    /// and the committed transactions are queried by the caller. It's used when the
+    /// collector is shared behind a lock, which should not be held during the rpc calls.
+    ///
+    /// Return the number of dropped offchain live cells.
+    #[allow(clippy::mutable_key_type)]
+    pub fn prune_offchain_cells(
         &mut self,
-        query: &CellQueryOptions,
+        tip_header: &HeaderView,
+        max_mature_number: u64,
+        committed_tx_hashes: &HashSet<Byte32>,
+    ) -> usize {
+        self.offchain.max_mature_number = max_mature_number;
+        let total = self.offchain.live_cells.len();
+        self.offchain
+            .live_cells
+            .retain(|(cell, _tip_num)| !committed_tx_hashes.contains(&cell.out_point.tx_hash()));
+        let dropped = total - self.offchain.live_cells.len();
+        log::debug!(
+            "new tip {}, dropped {} committed offchain live cells",
+            tip_header.number(),
+            dropped
+        );
+        dropped
+    }
+
+    /// Collect the cells with exactly the `type_script` (any lock script) until the total
+    /// capacity reach `min_capacity`, return the cells and the total capacity.
+    pub fn collect_cells_by_type(
+        &mut self,
+        type_script: Script,
+        min_capacity: u64,
         apply_changes: bool,
     ) -> Result<(Vec<LiveCell>, u64), CellCollectorError> {
-        let max_mature_number = get_max_mature_number(&self.ckb_client)
-            .map_err(|err| CellCollectorError::Internal(anyhow!(err)))?;
+        let mut query = CellQueryOptions::new_type(type_script);
+        // the default prefix search mode also returns the cells with longer args,
+        // they are skipped by `match_cell` too
+        query.script_search_mode = Some(SearchMode::Exact);
+        query.min_total_capacity = min_capacity;
+        self.collect_live_cells(&query, apply_changes)
+    }
+
+    /// Collect the SUDT cells of `lock` until the total SUDT amount reach `min_amount`,
+    /// return the cells and the total SUDT amount. The offchain live cells are used first.
+    pub fn collect_sudt_cells(
+        &mut self,
+        lock: Script,
+        sudt_type: Script,
+        min_amount: u128,
+        apply_changes: bool,
+    ) -> Result<(Vec<LiveCell>, u128), CellCollectorError> {
+        let mut query = CellQueryOptions::new_lock(lock);
+        query.secondary_script = Some(sudt_type);
+        query.data_len_range = Some(ValueRangeOption::new_min(16));
+        // the collecting is stopped by the SUDT amount
+        query.min_total_capacity = u64::MAX;
 
+        let max_mature_number = self.get_max_mature_number()?;
         self.offchain.max_mature_number = max_mature_number;
-        let tip_num = self
-            .ckb_client
-            .get_tip_block_number()
-            .map_err(|err| CellCollectorError::Internal(anyhow!(err)))?
-            .value();
+        let tip_num = self.get_tip_block_number()?;
         let CollectResult {
-            cells,
-            rest_cells,
-            mut total_capacity,
-        } = self.offchain.collect(query, tip_num);
-        let mut cells: Vec<_> = cells.into_iter().map(|c| c.0).collect();
-
-        if total_capacity < query.min_total_capacity {
-            self.check_ckb_chain()?;
-            let order = match query.order {
-                QueryOrder::Asc => Order::Asc,
-                QueryOrder::Desc => Order::Desc,
-            };
-            let mut ret_cells: HashMap<_, _> = cells
-                .into_iter()
-                .map(|c| (c.out_point.clone(), c))
-                .collect();
-            let locked_cells = self.offchain.locked_cells.clone();
-            let search_key = SearchKey::from(query.clone());
-            const MAX_LIMIT: u32 = 4096;
-            let mut limit: u32 = query.limit.unwrap_or(16);
-            let mut last_cursor: Option<json_types::JsonBytes> = None;
-            while total_capacity < query.min_total_capacity {
-                let page = self
-                    .indexer_client
-                    .get_cells(search_key.clone(), order.clone(), limit.into(), last_cursor)
-                    .map_err(|err| CellCollectorError::Internal(err.into()))?;
-                if page.objects.is_empty() {
-                    break;
-                }
-                for cell in page.objects {
-                    let live_cell = LiveCell::from(cell);
-                    if !query.match_cell(&live_cell, max_mature_number)
-                        || locked_cells.contains_key(&(
-                            live_cell.out_point.tx_hash().unpack(),
-                            live_cell.out_point.index().unpack(),
-                        ))
-                    {
-                        continue;
-                    }
-                    let capacity: u64 = live_cell.output.capacity().unpack();
-                    // use cell from indexer to replace offchain cell
-                    if ret_cells
-                        .insert(live_cell.out_point.clone(), live_cell)
-                        .is_none()
-                    {
-                        total_capacity += capacity;
-                    }
-                    if total_capacity >= query.min_total_capacity {
-                        break;
-                    }
-                }
-                last_cursor = Some(page.last_cursor);
-                if limit < MAX_LIMIT {
-                    limit *= 2;
+            cells: offchain_cells,
+            mut rest_cells,
+            ..
+        } = self.offchain.collect(&query, tip_num);
+        let mut cells = Vec::new();
+        let mut total_amount: u128 = 0;
+        for (cell, cell_tip_num) in offchain_cells {
+            match sudt_amount(&cell) {
+                Some(amount) if total_amount < min_amount => {
+                    total_amount = add_sudt_amount(total_amount, amount)?;
+                    cells.push(cell);
                 }
+                _ => rest_cells.push((cell, cell_tip_num)),
             }
-            cells = ret_cells.into_values().collect();
+        }
+
+        if total_amount < min_amount {
+            let leftbehind = self.indexer_leftbehind();
+            let mut indexer_tip = None;
+            let locked_cells = &self.offchain.locked_cells;
+            let offchain_taken: HashSet<OutPoint> =
+                cells.iter().map(|cell| cell.out_point.clone()).collect();
+            let offchain_cells = cells;
+            let offchain_amount = total_amount;
+            // the indexer is queried from scratch again after a failover, so the
+            // cursors of different servers are never mixed
+            (cells, total_amount) = self.call_with(|rpc| {
+                indexer_tip = check_indexer_synced(rpc, leftbehind)?;
+                let mut cells = offchain_cells.clone();
+                let mut total_amount = offchain_amount;
+                let mut overflow = None;
+                page_indexer_cells(
+                    rpc,
+                    &query,
+                    max_mature_number,
+                    |out_point| {
+                        is_locked(locked_cells, out_point) || offchain_taken.contains(out_point)
+                    },
+                    |cell| {
+                        if let Some(amount) = sudt_amount(&cell) {
+                            match add_sudt_amount(total_amount, amount) {
+                                Ok(amount) => total_amount = amount,
+                                Err(err) => {
+                                    overflow = Some(err);
+                                    return true;
+                                }
+                            }
+                            cells.push(cell);
+                        }
+                        total_amount >= min_amount
+                    },
+                )?;
+                if let Some(err) = overflow {
+                    return Err(err);
+                }
+                Ok((cells, total_amount))
+            })?;
+            self.last_indexer_tip = indexer_tip;
         }
         if apply_changes {
             self.offchain.live_cells = rest_cells;
@@ -383,42 +958,618 @@ impl CellCollector for DefaultCellCollector {
                 self.lock_cell(cell.out_point.clone(), tip_num)?;
             }
         }
-        Ok((cells, total_capacity))
+        Ok((cells, total_amount))
     }
 
-    fn lock_cell(
+    /// Collect live cells for several independent queries, the ckb-indexer paging of
+    /// the queries is issued concurrently. The results are in the same order as
+    /// `queries`, a cell matched by more than one query is only returned (and locked)
+    /// for the first query.
+    #[allow(clippy::mutable_key_type)]
+    pub fn collect_many(
         &mut self,
-        out_point: OutPoint,
-        tip_block_number: u64,
-    ) -> Result<(), CellCollectorError> {
-        self.offchain.lock_cell(out_point, tip_block_number)
-    }
-    fn apply_tx(
-        &mut self,
-        tx: Transaction,
-        tip_block_number: u64,
-    ) -> Result<(), CellCollectorError> {
-        self.offchain.apply_tx(tx, tip_block_number)
+        queries: &[CellQueryOptions],
+        apply_changes: bool,
+    ) -> Result<Vec<(Vec<LiveCell>, u64)>, CellCollectorError> {
+        let max_mature_number = self.get_max_mature_number()?;
+        self.offchain.max_mature_number = max_mature_number;
+        let tip_num = self.get_tip_block_number()?;
+
+        // offchain cells are taken by the first matched query
+        let mut offchain = self.offchain.clone();
+        let mut results = Vec::with_capacity(queries.len());
+        for query in queries {
+            let CollectResult {
+                cells,
+                rest_cells,
+                total_capacity,
+            } = offchain.collect(query, tip_num);
+            offchain.live_cells = rest_cells;
+            let cells: HashMap<_, _> = cells
+                .into_iter()
+                .map(|(c, _)| (c.out_point.clone(), c))
+                .collect();
+            results.push((cells, total_capacity));
+        }
+
+        let unsatisfied = |results: &[(HashMap<OutPoint, LiveCell>, u64)], index: usize| {
+            results[index].1 < queries[index].min_total_capacity
+        };
+        if (0..queries.len()).any(|index| unsatisfied(&results, index)) {
+            let leftbehind = self.indexer_leftbehind();
+            let mut indexer_tip = None;
+            let locked_cells = &offchain.locked_cells;
+            let offchain_taken: HashSet<OutPoint> = results
+                .iter()
+                .flat_map(|(cells, _)| cells.keys().cloned())
+                .collect();
+            let offchain_results = results;
+            // the indexer is queried from scratch again after a failover, so the
+            // cursors of different servers are never mixed
+            results = self.call_with(|rpc| {
+                indexer_tip = check_indexer_synced(rpc, leftbehind)?;
+                let mut results = offchain_results.clone();
+                let fetched = thread::scope(|scope| {
+                    let handles: Vec<_> = queries
+                        .iter()
+                        .enumerate()
+                        .map(|(index, query)| {
+                            if !unsatisfied(&results, index) {
+                                return None;
+                            }
+                            let (mut cells, mut total_capacity) = results[index].clone();
+                            let offchain_taken = &offchain_taken;
+                            Some(scope.spawn(move || {
+                                let own_cells: HashSet<OutPoint> = cells.keys().cloned().collect();
+                                collect_from_indexer(
+                                    rpc,
+                                    query,
+                                    max_mature_number,
+                                    |out_point| {
+                                        is_locked(locked_cells, out_point)
+                                            || (offchain_taken.contains(out_point)
+                                                && !own_cells.contains(out_point))
+                                    },
+                                    &mut cells,
+                                    &mut total_capacity,
+                                )?;
+                                Ok((cells, total_capacity))
+                            }))
+                        })
+                        .collect();
+                    handles
+                        .into_iter()
+                        .map(|handle| {
+                            handle
+                                .map(|handle| handle.join().expect("collect cells thread panicked"))
+                                .transpose()
+                        })
+                        .collect::<Result<Vec<_>, CellCollectorError>>()
+                })?;
+
+                // remove the cells already taken by previous queries
+                let mut taken: HashSet<OutPoint> = HashSet::new();
+                for (index, fetched_result) in fetched.into_iter().enumerate() {
+                    if let Some(fetched_result) = fetched_result {
+                        results[index] = fetched_result;
+                    }
+                    let (cells, total_capacity) = &mut results[index];
+                    cells.retain(|out_point, cell| {
+                        if taken.contains(out_point) {
+                            let capacity: u64 = cell.output.capacity().unpack();
+                            *total_capacity -= capacity;
+                            false
+                        } else {
+                            true
+                        }
+                    });
+                    if unsatisfied(&results, index) {
+                        let (cells, total_capacity) = &mut results[index];
+                        collect_from_indexer(
+                            rpc,
+                            &queries[index],
+                            max_mature_number,
+                            |out_point| {
+                                is_locked(locked_cells, out_point)
+                                    || taken.contains(out_point)
+                                    || offchain_taken.contains(out_point)
+                            },
+                            cells,
+                            total_capacity,
+                        )?;
+                    }
+                    taken.extend(results[index].0.keys().cloned());
+                }
+                Ok(results)
+            })?;
+            self.last_indexer_tip = indexer_tip;
+        }
+
+        let results: Vec<_> = results
+            .into_iter()
+            .map(|(cells, total_capacity)| {
+                (cells.into_values().collect::<Vec<_>>(), total_capacity)
+            })
+            .collect();
+        if apply_changes {
+            self.offchain = offchain;
+            for (cells, _) in &results {
+                for cell in cells {
+                    self.lock_cell(cell.out_point.clone(), tip_num)?;
+                }
+            }
+        }
+        Ok(results)
     }
-    fn reset(&mut self) {
-        self.offchain.reset();
+}
+
+/// Check if the ckb-indexer server of the endpoint is synced with the ckb node of the same
+/// endpoint, return the checked ckb-indexer tip. The check is skipped when
+/// `acceptable_indexer_leftbehind` is `None`.
+#[cfg(feature = "rpc")]
+fn check_indexer_synced(
+    rpc: CollectorRpc,
+    acceptable_indexer_leftbehind: Option<u64>,
+) -> Result<Option<u64>, CellCollectorError> {
+    let acceptable_indexer_leftbehind = match acceptable_indexer_leftbehind {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+    let node_tip = rpc
+        .ckb_client()
+        .get_tip_block_number()
+        .map_err(CellCollectorError::Rpc)?;
+
+    let mut indexer_tip = None;
+    for _ in 0..100 {
+        let tip = match rpc.get_indexer_tip() {
+            // e.g. the light client, the cells are always synced with its tip
+            Err(RpcError::Unsupported(_)) => return Ok(None),
+            result => result.map_err(CellCollectorError::Rpc)?,
+        };
+        match tip {
+            Some(Tip { block_number, .. }) => {
+                if node_tip > block_number.value() + acceptable_indexer_leftbehind {
+                    indexer_tip = Some(block_number.value());
+                    thread::sleep(Duration::from_millis(50));
+                } else {
+                    return Ok(Some(block_number.value()));
+                }
+            }
+            None => {
+                return Err(CellCollectorError::IndexerNotSynced {
+                    node_tip,
+                    indexer_tip: None,
+                    indexer_version: get_indexer_version(rpc),
+                });
+            }
+        }
     }
+    Err(CellCollectorError::IndexerNotSynced {
+        node_tip,
+        indexer_tip,
+        indexer_version: get_indexer_version(rpc),
+    })
 }
 
-struct DefaultTxDepProviderInner {
-    rpc_client: CkbRpcClient,
-    tx_cache: LruCache<Byte32, TransactionView>,
-    cell_cache: LruCache<OutPoint, (CellOutput, Bytes)>,
-    header_cache: LruCache<Byte32, HeaderView>,
-    offchain_cache: OffchainTransactionDependencyProvider,
+/// Check if the transaction is committed in the main chain of the ckb node.
+#[cfg(feature = "rpc")]
+pub(crate) fn is_tx_committed(
+    ckb_client: &dyn CkbRpcInterface,
+    tx_hash: &Byte32,
+) -> Result<bool, RpcError> {
+    Ok(ckb_client
+        .get_transaction(tx_hash.unpack())?
+        .map(|tx_with_status| tx_with_status.tx_status.status == json_types::Status::Committed)
+        .unwrap_or(false))
 }
 
-/// A transaction dependency provider use ckb rpc client as backend, and with LRU cache supported
+// The version of the ckb-indexer for diagnosing, the ckb node integrated indexer does not
+// support `get_indexer_info`, the version is `None` then.
+#[cfg(feature = "rpc")]
+fn get_indexer_version(rpc: CollectorRpc) -> Option<String> {
+    match rpc {
+        CollectorRpc::Endpoint(endpoint) => endpoint
+            .indexer_client
+            .get_indexer_info()
+            .map(|info| info.version)
+            .ok(),
+        CollectorRpc::Interface(_) => None,
+    }
+}
+
+#[cfg(feature = "rpc")]
+fn is_locked(locked_cells: &HashMap<(H256, u32), u64>, out_point: &OutPoint) -> bool {
+    locked_cells.contains_key(&(out_point.tx_hash().unpack(), out_point.index().unpack()))
+}
+
+/// Collect live cells from ckb-indexer until `total_capacity` reach the query's
+/// `min_total_capacity`, the cells matched `is_excluded` are skipped.
+#[cfg(feature = "rpc")]
+#[allow(clippy::mutable_key_type)]
+fn collect_from_indexer<F: Fn(&OutPoint) -> bool>(
+    rpc: CollectorRpc,
+    query: &CellQueryOptions,
+    max_mature_number: u64,
+    is_excluded: F,
+    ret_cells: &mut HashMap<OutPoint, LiveCell>,
+    total_capacity: &mut u64,
+) -> Result<(), CellCollectorError> {
+    if *total_capacity >= query.min_total_capacity {
+        return Ok(());
+    }
+    page_indexer_cells(rpc, query, max_mature_number, is_excluded, |live_cell| {
+        let capacity: u64 = live_cell.output.capacity().unpack();
+        // use cell from indexer to replace offchain cell
+        if ret_cells
+            .insert(live_cell.out_point.clone(), live_cell)
+            .is_none()
+        {
+            *total_capacity += capacity;
+        }
+        *total_capacity >= query.min_total_capacity
+    })
+}
+
+/// Page the live cells matched the query from ckb-indexer until `on_cell` returns true
+/// or no more cells, the cells matched `is_excluded` are skipped.
+#[cfg(feature = "rpc")]
+fn page_indexer_cells<F, C>(
+    rpc: CollectorRpc,
+    query: &CellQueryOptions,
+    max_mature_number: u64,
+    is_excluded: F,
+    mut on_cell: C,
+) -> Result<(), CellCollectorError>
+where
+    F: Fn(&OutPoint) -> bool,
+    C: FnMut(LiveCell) -> bool,
+{
+    let order = match query.order {
+        QueryOrder::Asc => Order::Asc,
+        QueryOrder::Desc => Order::Desc,
+    };
+    let search_key = SearchKey::from(query.clone());
+    const MAX_LIMIT: u32 = 4096;
+    let mut limit: u32 = query.limit.unwrap_or(16);
+    let mut last_cursor: Option<json_types::JsonBytes> = None;
+    loop {
+        let page = rpc
+            .get_cells(search_key.clone(), order.clone(), limit.into(), last_cursor)
+            .map_err(CellCollectorError::Rpc)?;
+        if page.objects.is_empty() {
+            return Ok(());
+        }
+        for cell in page.objects {
+            let live_cell = LiveCell::from(cell);
+            if !query.match_cell(&live_cell, max_mature_number) || is_excluded(&live_cell.out_point)
+            {
+                continue;
+            }
+            if on_cell(live_cell) {
+                return Ok(());
+            }
+        }
+        last_cursor = Some(page.last_cursor);
+        if limit < MAX_LIMIT {
+            limit *= 2;
+        }
+    }
+}
+
+#[cfg(feature = "rpc")]
+fn add_sudt_amount(total_amount: u128, amount: u128) -> Result<u128, CellCollectorError> {
+    total_amount
+        .checked_add(amount)
+        .ok_or_else(|| CellCollectorError::Other(anyhow!("the total SUDT amount overflows")))
+}
+
+/// Decode the SUDT amount from the first 16 bytes of the cell data
+#[cfg(feature = "rpc")]
+fn sudt_amount(cell: &LiveCell) -> Option<u128> {
+    let mut amount_bytes = [0u8; 16];
+    amount_bytes.copy_from_slice(cell.output_data.get(0..16)?);
+    Some(u128::from_le_bytes(amount_bytes))
+}
+
+#[cfg(feature = "rpc")]
+impl CellsCapacityProvider for DefaultCellCollector {
+    fn cells_capacity(&self, search_key: SearchKey) -> Result<u64, RpcError> {
+        self.call(|rpc| rpc.cells_capacity(search_key.clone()))
+    }
+}
+
+#[cfg(feature = "rpc")]
+impl CellCollector for DefaultCellCollector {
+    fn collect_live_cells(
+        &mut self,
+        query: &CellQueryOptions,
+        apply_changes: bool,
+    ) -> Result<(Vec<LiveCell>, u64), CellCollectorError> {
+        let max_mature_number = self.get_max_mature_number()?;
+
+        self.offchain.max_mature_number = max_mature_number;
+        let tip_num = self.get_tip_block_number()?;
+        let CollectResult {
+            cells,
+            mut rest_cells,
+            mut total_capacity,
+        } = self.offchain.collect(query, tip_num);
+        let mut cells: Vec<_> = cells.into_iter().map(|c| c.0).collect();
+
+        if total_capacity < query.min_total_capacity {
+            let leftbehind = self.indexer_leftbehind();
+            let mut indexer_tip = None;
+            let offchain_cells: HashMap<_, _> = cells
+                .into_iter()
+                .map(|c| (c.out_point.clone(), c))
+                .collect();
+            let offchain_capacity = total_capacity;
+            let locked_cells = &self.offchain.locked_cells;
+            let offchain_out_points: HashSet<OutPoint> = self
+                .offchain
+                .live_cells
+                .iter()
+                .map(|(cell, _)| cell.out_point.clone())
+                .collect();
+            // the indexer is queried from scratch again after a failover, so the
+            // cursors of different servers are never mixed
+            let (ret_cells, capacity, committed) = self.call_with(|rpc| {
+                indexer_tip = check_indexer_synced(rpc, leftbehind)?;
+                let mut ret_cells = offchain_cells.clone();
+                let mut total_capacity = offchain_capacity;
+                // the offchain cells already seen on chain
+                let committed = RefCell::new(HashSet::new());
+                collect_from_indexer(
+                    rpc,
+                    query,
+                    max_mature_number,
+                    |out_point| {
+                        if offchain_out_points.contains(out_point) {
+                            committed.borrow_mut().insert(out_point.clone());
+                        }
+                        // the cells taken from offchain are already counted
+                        is_locked(locked_cells, out_point) || offchain_cells.contains_key(out_point)
+                    },
+                    &mut ret_cells,
+                    &mut total_capacity,
+                )?;
+                Ok((ret_cells, total_capacity, committed.into_inner()))
+            })?;
+            self.last_indexer_tip = indexer_tip;
+            cells = ret_cells.into_values().collect();
+            total_capacity = capacity;
+            self.offchain
+                .live_cells
+                .retain(|(cell, _)| !committed.contains(&cell.out_point));
+            rest_cells.retain(|(cell, _)| !committed.contains(&cell.out_point));
+        }
+        if apply_changes {
+            self.offchain.live_cells = rest_cells;
+            for cell in &cells {
+                self.lock_cell(cell.out_point.clone(), tip_num)?;
+            }
+        }
+        Ok((cells, total_capacity))
+    }
+
+    fn lock_cell(
+        &mut self,
+        out_point: OutPoint,
+        tip_block_number: u64,
+    ) -> Result<(), CellCollectorError> {
+        self.offchain.lock_cell(out_point, tip_block_number)
+    }
+    fn unlock_cell(&mut self, out_point: OutPoint) -> Result<(), CellCollectorError> {
+        self.offchain.unlock_cell(&out_point);
+        Ok(())
+    }
+    fn apply_tx(
+        &mut self,
+        tx: Transaction,
+        tip_block_number: u64,
+    ) -> Result<(), CellCollectorError> {
+        self.offchain.apply_tx(tx, tip_block_number)
+    }
+    fn reset(&mut self) {
+        self.offchain.reset();
+    }
+}
+
+// The methods not supported by the rpc backend (e.g. the light client) are reported as
+// `TransactionDependencyError::Unsupported`, so the callers can fall back.
+#[cfg(feature = "rpc")]
+fn rpc_error(err: RpcError) -> TransactionDependencyError {
+    match err {
+        RpcError::Unsupported(method) => TransactionDependencyError::Unsupported(method),
+        err => TransactionDependencyError::Rpc(err),
+    }
+}
+
+#[cfg(feature = "rpc")]
+struct DefaultTxDepProviderInner {
+    // `None` in offline mode
+    rpc_client: Option<Arc<dyn CkbRpcInterface + Send + Sync>>,
+    tx_cache: LruCache<Byte32, TransactionView>,
+    cell_cache: LruCache<OutPoint, CellOutput>,
+    // bounded by the total data size instead of the entries count
+    cell_data_cache: LruCache<OutPoint, Bytes>,
+    cell_data_cache_size: usize,
+    cell_data_cache_limit: usize,
+    header_cache: LruCache<Byte32, HeaderView>,
+    // block number => block hash of the main chain, the headers are in `header_cache`
+    header_number_index: LruCache<u64, Byte32>,
+    epoch_cache: LruCache<Byte32, EpochNumberWithFraction>,
+    offchain_cache: OffchainTransactionDependencyProvider,
+    consensus: Option<json_types::Consensus>,
+    consensus_fetched_at: Option<Instant>,
+    consensus_ttl: Duration,
+    // accept the pending and proposed transactions in `get_transaction`
+    allow_unconfirmed: bool,
+    #[cfg(feature = "disk-cache")]
+    disk_cache: Option<DiskCache>,
+    // the tip number seen by `on_new_tip`, the headers within `REORG_CHECK_DEPTH` from
+    // it are not persisted
+    #[cfg(feature = "disk-cache")]
+    tip_number: Option<u64>,
+}
+
+#[cfg(feature = "rpc")]
+impl DefaultTxDepProviderInner {
+    fn rpc_client(&self) -> Result<&dyn CkbRpcInterface, TransactionDependencyError> {
+        match self.rpc_client.as_ref() {
+            Some(rpc_client) => Ok(rpc_client.as_ref()),
+            None => Err(TransactionDependencyError::Other(anyhow!("offline mode"))),
+        }
+    }
+
+    fn get_live_cell(
+        &self,
+        out_point: &OutPoint,
+        with_data: bool,
+    ) -> Result<(CellOutput, Option<Bytes>), TransactionDependencyError> {
+        let cell_with_status = self
+            .rpc_client()?
+            .get_live_cell(out_point.clone().into(), with_data)
+            .map_err(rpc_error)?;
+        if cell_with_status.status != "live" {
+            return Err(TransactionDependencyError::CellNotLive {
+                out_point: out_point.clone(),
+                status: cell_with_status.status,
+            });
+        }
+        let cell = cell_with_status.cell.unwrap();
+        let output_data = cell.data.map(|data| data.content.into_bytes());
+        Ok((CellOutput::from(cell.output), output_data))
+    }
+
+    fn put_cell_data(&mut self, out_point: OutPoint, data: Bytes) {
+        if data.len() > self.cell_data_cache_limit {
+            return;
+        }
+        self.cell_data_cache_size += data.len();
+        if let Some(old) = self.cell_data_cache.put(out_point, data) {
+            self.cell_data_cache_size -= old.len();
+        }
+        self.shrink_cell_data_cache();
+    }
+
+    // evict the least recently used cell data until the total size is within the limit
+    fn shrink_cell_data_cache(&mut self) {
+        while self.cell_data_cache_size > self.cell_data_cache_limit {
+            match self.cell_data_cache.pop_lru() {
+                Some((_, data)) => self.cell_data_cache_size -= data.len(),
+                None => break,
+            }
+        }
+    }
+
+    fn clear_cell_caches(&mut self) {
+        self.cell_cache.clear();
+        self.cell_data_cache.clear();
+        self.cell_data_cache_size = 0;
+    }
+}
+
+// The committed data are immutable, they are persisted by the disk cache, the failures
+// of the disk cache are logged and treated as cache misses.
+#[cfg(feature = "disk-cache")]
+impl DefaultTxDepProviderInner {
+    fn disk_get(&self, kind: DiskCacheKind, key: &[u8]) -> Option<Bytes> {
+        self.disk_cache.as_ref()?.get(kind, key)
+    }
+
+    fn disk_put(&mut self, kind: DiskCacheKind, key: &[u8], value: &[u8]) {
+        if let Some(disk_cache) = self.disk_cache.as_mut() {
+            if let Err(err) = disk_cache.put(kind, key, value) {
+                log::warn!("write disk cache failed: {}", err);
+            }
+        }
+    }
+
+    fn load_tx(&self, tx_hash: &Byte32) -> Option<TransactionView> {
+        let data = self.disk_get(DiskCacheKind::Transaction, tx_hash.as_slice())?;
+        let tx = TransactionReader::from_slice(&data)
+            .ok()?
+            .to_entity()
+            .into_view();
+        (&tx.hash() == tx_hash).then_some(tx)
+    }
+
+    fn store_tx(&mut self, tx: &TransactionView) {
+        self.disk_put(
+            DiskCacheKind::Transaction,
+            tx.hash().as_slice(),
+            tx.data().as_slice(),
+        );
+    }
+
+    fn load_cell(&self, out_point: &OutPoint) -> Option<CellOutput> {
+        let data = self.disk_get(DiskCacheKind::Cell, out_point.as_slice())?;
+        CellOutput::from_slice(&data).ok()
+    }
+
+    fn store_cell(&mut self, out_point: &OutPoint, output: &CellOutput) {
+        self.disk_put(DiskCacheKind::Cell, out_point.as_slice(), output.as_slice());
+    }
+
+    fn load_cell_data(&self, out_point: &OutPoint) -> Option<Bytes> {
+        self.disk_get(DiskCacheKind::CellData, out_point.as_slice())
+    }
+
+    fn store_cell_data(&mut self, out_point: &OutPoint, data: &Bytes) {
+        self.disk_put(DiskCacheKind::CellData, out_point.as_slice(), data);
+    }
+
+    fn load_header(&self, block_hash: &Byte32) -> Option<HeaderView> {
+        let data = self.disk_get(DiskCacheKind::Header, block_hash.as_slice())?;
+        let header = HeaderReader::from_slice(&data)
+            .ok()?
+            .to_entity()
+            .into_view();
+        (&header.hash() == block_hash).then_some(header)
+    }
+
+    // the recent headers may be reorged out, only persist the headers deep enough
+    fn store_header(&mut self, header: &HeaderView) {
+        let deep_enough = self
+            .tip_number
+            .map(|tip_number| header.number() + REORG_CHECK_DEPTH <= tip_number)
+            .unwrap_or(false);
+        if deep_enough {
+            self.disk_put(
+                DiskCacheKind::Header,
+                header.hash().as_slice(),
+                header.data().as_slice(),
+            );
+        }
+    }
+}
+
+/// The cached headers within this depth from the tip are checked for reorg when a new tip arrived
+#[cfg(feature = "rpc")]
+pub const REORG_CHECK_DEPTH: u64 = 24;
+
+/// The default time-to-live of the cached consensus info
+#[cfg(feature = "rpc")]
+pub const DEFAULT_CONSENSUS_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// The maximum capacity of the block epoch cache
+#[cfg(feature = "rpc")]
+pub const EPOCH_CACHE_CAPACITY: usize = 64;
+
+/// The default maximum total size of the cached cell data
+#[cfg(feature = "rpc")]
+pub const DEFAULT_CELL_DATA_CACHE_LIMIT: usize = 16 * 1024 * 1024;
+
+/// A transaction dependency provider use ckb rpc client as backend, and with LRU cache supported
+#[cfg(feature = "rpc")]
 pub struct DefaultTransactionDependencyProvider {
     // since we will mainly deal with LruCache, so use Mutex here
     inner: Arc<Mutex<DefaultTxDepProviderInner>>,
 }
 
+#[cfg(feature = "rpc")]
 impl Clone for DefaultTransactionDependencyProvider {
     fn clone(&self) -> DefaultTransactionDependencyProvider {
         let inner = Arc::clone(&self.inner);
@@ -426,24 +1577,96 @@ impl Clone for DefaultTransactionDependencyProvider {
     }
 }
 
+/// Same as [`DefaultTransactionDependencyProvider::offline`]
+#[cfg(feature = "rpc")]
+impl Default for DefaultTransactionDependencyProvider {
+    fn default() -> DefaultTransactionDependencyProvider {
+        DefaultTransactionDependencyProvider::offline()
+    }
+}
+
+#[cfg(feature = "rpc")]
 impl DefaultTransactionDependencyProvider {
     /// Arguments:
     ///   * `url` is the ckb http jsonrpc server url
     ///   * When `cache_capacity` is 0 for not using cache.
     pub fn new(url: &str, cache_capacity: usize) -> DefaultTransactionDependencyProvider {
-        let rpc_client = CkbRpcClient::new(url);
+        Self::new_with_client(Some(Arc::new(CkbRpcClient::new(url))), cache_capacity)
+    }
+
+    /// Create the provider with any rpc backend implemented [`CkbRpcInterface`], e.g. a
+    /// `LightClientRpcClient`. The methods not supported by the backend return
+    /// `TransactionDependencyError::Unsupported`.
+    pub fn from_rpc_interface(
+        rpc_client: Arc<dyn CkbRpcInterface + Send + Sync>,
+        cache_capacity: usize,
+    ) -> DefaultTransactionDependencyProvider {
+        Self::new_with_client(Some(rpc_client), cache_capacity)
+    }
+
+    /// Create a provider without rpc client, every method needs the rpc returns
+    /// `TransactionDependencyError::Other("offline mode")`, only the transactions
+    /// applied by `apply_tx` are served. For the tests, prefer an in-memory provider
+    /// like [`OffchainTransactionDependencyProvider`].
+    pub fn offline() -> DefaultTransactionDependencyProvider {
+        Self::new_with_client(None, 0)
+    }
+
+    fn new_with_client(
+        rpc_client: Option<Arc<dyn CkbRpcInterface + Send + Sync>>,
+        cache_capacity: usize,
+    ) -> DefaultTransactionDependencyProvider {
         let inner = DefaultTxDepProviderInner {
             rpc_client,
             tx_cache: LruCache::new(cache_capacity),
             cell_cache: LruCache::new(cache_capacity),
+            cell_data_cache: LruCache::unbounded(),
+            cell_data_cache_size: 0,
+            cell_data_cache_limit: if cache_capacity == 0 {
+                0
+            } else {
+                DEFAULT_CELL_DATA_CACHE_LIMIT
+            },
             header_cache: LruCache::new(cache_capacity),
+            header_number_index: LruCache::new(cache_capacity),
+            epoch_cache: LruCache::new(cache_capacity.min(EPOCH_CACHE_CAPACITY)),
             offchain_cache: OffchainTransactionDependencyProvider::new(),
+            consensus: None,
+            consensus_fetched_at: None,
+            consensus_ttl: DEFAULT_CONSENSUS_TTL,
+            allow_unconfirmed: false,
+            #[cfg(feature = "disk-cache")]
+            disk_cache: None,
+            #[cfg(feature = "disk-cache")]
+            tip_number: None,
         };
         DefaultTransactionDependencyProvider {
             inner: Arc::new(Mutex::new(inner)),
         }
     }
 
+    /// Same as `new`, and the committed transactions, the cells and the headers are
+    /// persisted in the directory `path` behind the memory caches, the lookups fall
+    /// through memory => disk => rpc. The total size of the persisted data is capped
+    /// by `max_disk_bytes`, the oldest written entries are removed first.
+    ///
+    /// The headers are only persisted when they are at least [`REORG_CHECK_DEPTH`]
+    /// blocks below the tip seen by `on_new_tip`, and the headers by number are never
+    /// persisted, since they may be changed by chain reorg.
+    #[cfg(feature = "disk-cache")]
+    pub fn with_disk_cache<P: AsRef<std::path::Path>>(
+        url: &str,
+        mem_capacity: usize,
+        path: P,
+        max_disk_bytes: u64,
+    ) -> Result<DefaultTransactionDependencyProvider, TransactionDependencyError> {
+        let disk_cache = DiskCache::open(path, max_disk_bytes)
+            .map_err(|err| anyhow!("open disk cache failed: {}", err))?;
+        let provider = DefaultTransactionDependencyProvider::new(url, mem_capacity);
+        provider.inner.lock().disk_cache = Some(disk_cache);
+        Ok(provider)
+    }
+
     pub fn apply_tx(
         &mut self,
         tx: Transaction,
@@ -454,35 +1677,187 @@ impl DefaultTransactionDependencyProvider {
         Ok(())
     }
 
+    /// Get the cell output and data from the node, the output and the data are cached
+    /// separately, only `get_cell_data` and this method request the cell data.
     pub fn get_cell_with_data(
         &self,
         out_point: &OutPoint,
     ) -> Result<(CellOutput, Bytes), TransactionDependencyError> {
         let mut inner = self.inner.lock();
-        if let Some(pair) = inner.cell_cache.get(out_point) {
-            return Ok(pair.clone());
+        if let Some(output_data) = inner.cell_data_cache.get(out_point).cloned() {
+            if let Some(output) = inner.cell_cache.get(out_point) {
+                return Ok((output.clone(), output_data));
+            }
         }
 
-        let cell_with_status = inner
-            .rpc_client
-            .get_live_cell(out_point.clone().into(), true)
-            .map_err(|err| TransactionDependencyError::Other(err.into()))?;
-        if cell_with_status.status != "live" {
-            return Err(TransactionDependencyError::Other(anyhow!(
-                "invalid cell status: {:?}",
-                cell_with_status.status
-            )));
+        #[cfg(feature = "disk-cache")]
+        if let (Some(output), Some(output_data)) =
+            (inner.load_cell(out_point), inner.load_cell_data(out_point))
+        {
+            inner.cell_cache.put(out_point.clone(), output.clone());
+            inner.put_cell_data(out_point.clone(), output_data.clone());
+            return Ok((output, output_data));
         }
-        let cell = cell_with_status.cell.unwrap();
-        let output = CellOutput::from(cell.output);
-        let output_data = cell.data.unwrap().content.into_bytes();
-        inner
-            .cell_cache
-            .put(out_point.clone(), (output.clone(), output_data.clone()));
+
+        let (output, output_data) = inner.get_live_cell(out_point, true)?;
+        let output_data = output_data.unwrap();
+        #[cfg(feature = "disk-cache")]
+        {
+            inner.store_cell(out_point, &output);
+            inner.store_cell_data(out_point, &output_data);
+        }
+        inner.cell_cache.put(out_point.clone(), output.clone());
+        inner.put_cell_data(out_point.clone(), output_data.clone());
         Ok((output, output_data))
     }
+
+    /// Set the maximum total size of the cached cell data in bytes, default is 16MB. The
+    /// cell data larger than the limit is never cached.
+    pub fn set_cell_data_cache_limit(&self, limit: usize) {
+        let mut inner = self.inner.lock();
+        inner.cell_data_cache_limit = limit;
+        inner.shrink_cell_data_cache();
+    }
+
+    /// Accept the transactions in the tx-pool (pending or proposed) in `get_transaction`,
+    /// default is false. The unconfirmed transactions are not cached, since they may be
+    /// rejected or never committed.
+    pub fn set_allow_unconfirmed(&self, allow_unconfirmed: bool) {
+        self.inner.lock().allow_unconfirmed = allow_unconfirmed;
+    }
+
+    /// Resolve all input cells of the transaction and put them into the cell cache,
+    /// the cells already in the cache are skipped. Return the number of newly cached cells.
+    pub fn warm_cache_from_tx(
+        &self,
+        tx: &TransactionView,
+    ) -> Result<usize, TransactionDependencyError> {
+        let mut count = 0;
+        for out_point in tx.input_pts_iter() {
+            let cached = {
+                let inner = self.inner.lock();
+                inner.cell_cache.contains(&out_point) && inner.cell_data_cache.contains(&out_point)
+            };
+            if cached {
+                continue;
+            }
+            self.get_cell_with_data(&out_point)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Get the epoch of the block, the result is cached separately from the headers.
+    pub fn get_block_epoch(
+        &self,
+        block_hash: &Byte32,
+    ) -> Result<EpochNumberWithFraction, TransactionDependencyError> {
+        if let Some(epoch) = self.inner.lock().epoch_cache.get(block_hash) {
+            return Ok(*epoch);
+        }
+        let epoch = self.get_header(block_hash)?.epoch();
+        self.inner.lock().epoch_cache.put(block_hash.clone(), epoch);
+        Ok(epoch)
+    }
+
+    /// Get the epoch of the current tip header, the result is not cached.
+    pub fn get_current_epoch(&self) -> Result<EpochNumberWithFraction, TransactionDependencyError> {
+        let inner = self.inner.lock();
+        inner
+            .rpc_client()?
+            .get_tip_header()
+            .map(|header| EpochNumberWithFraction::from_full_value(header.inner.epoch.value()))
+            .map_err(rpc_error)
+    }
+
+    /// Get the consensus info of the chain, the result is cached and will be
+    /// re-fetched from the node after the consensus ttl expired.
+    pub fn get_consensus(&self) -> Result<json_types::Consensus, TransactionDependencyError> {
+        let mut inner = self.inner.lock();
+        if let (Some(consensus), Some(fetched_at)) = (&inner.consensus, inner.consensus_fetched_at)
+        {
+            if fetched_at.elapsed() < inner.consensus_ttl {
+                return Ok(consensus.clone());
+            }
+        }
+        let consensus = inner.rpc_client()?.get_consensus().map_err(rpc_error)?;
+        inner.consensus = Some(consensus.clone());
+        inner.consensus_fetched_at = Some(Instant::now());
+        Ok(consensus)
+    }
+
+    /// Set the time-to-live of the cached consensus info, default is 10 minutes.
+    pub fn set_consensus_ttl(&self, ttl: Duration) {
+        self.inner.lock().consensus_ttl = ttl;
+    }
+
+    /// Drop the cached consensus info, next `get_consensus` call will fetch it from the node.
+    pub fn invalidate_consensus_cache(&self) {
+        let mut inner = self.inner.lock();
+        inner.consensus = None;
+        inner.consensus_fetched_at = None;
+    }
+
+    /// Prune the caches invalidated by chain reorg when a new tip arrived. The cached
+    /// headers above the new tip or not in the main chain any more (checked within
+    /// [`REORG_CHECK_DEPTH`]) are removed, and when any header is removed the
+    /// transaction and cell caches are cleared since they may be affected too.
+    ///
+    /// Return the number of removed headers.
+    pub fn on_new_tip(&self, tip_header: &HeaderView) -> Result<usize, TransactionDependencyError> {
+        let tip_number = tip_header.number();
+        #[cfg(feature = "disk-cache")]
+        {
+            self.inner.lock().tip_number = Some(tip_number);
+        }
+        // the lock is released during the rpc calls, so the provider is not blocked by them
+        let (candidates, rpc_client) = {
+            let inner = self.inner.lock();
+            let check_from = tip_number.saturating_sub(REORG_CHECK_DEPTH);
+            let candidates: Vec<(Byte32, u64)> = inner
+                .header_cache
+                .iter()
+                .filter(|(_, header)| header.number() >= check_from)
+                .map(|(hash, header)| (hash.clone(), header.number()))
+                .collect();
+            (candidates, inner.rpc_client.clone())
+        };
+        let mut stale_headers = Vec::new();
+        for (hash, number) in candidates {
+            let is_stale = match number.cmp(&tip_number) {
+                Ordering::Greater => true,
+                Ordering::Equal => hash != tip_header.hash(),
+                Ordering::Less => {
+                    let main_hash = rpc_client
+                        .as_ref()
+                        .ok_or_else(|| TransactionDependencyError::Other(anyhow!("offline mode")))?
+                        .get_block_hash(number)
+                        .map_err(rpc_error)?;
+                    main_hash.map(|main_hash| main_hash.pack()) != Some(hash.clone())
+                }
+            };
+            if is_stale {
+                stale_headers.push((hash, number));
+            }
+        }
+        if stale_headers.is_empty() {
+            return Ok(0);
+        }
+        let mut inner = self.inner.lock();
+        for (hash, number) in &stale_headers {
+            inner.header_cache.pop(hash);
+            inner.epoch_cache.pop(hash);
+            if inner.header_number_index.peek(number) == Some(hash) {
+                inner.header_number_index.pop(number);
+            }
+        }
+        inner.tx_cache.clear();
+        inner.clear_cell_caches();
+        Ok(stale_headers.len())
+    }
 }
 
+#[cfg(feature = "rpc")]
 impl TransactionDependencyProvider for DefaultTransactionDependencyProvider {
     fn get_transaction(
         &self,
@@ -496,16 +1871,26 @@ impl TransactionDependencyProvider for DefaultTransactionDependencyProvider {
         if ret.is_ok() {
             return ret;
         }
+        #[cfg(feature = "disk-cache")]
+        if let Some(tx) = inner.load_tx(tx_hash) {
+            inner.tx_cache.put(tx_hash.clone(), tx.clone());
+            return Ok(tx);
+        }
         let tx_with_status = inner
-            .rpc_client
+            .rpc_client()?
             .get_transaction(tx_hash.unpack())
-            .map_err(|err| TransactionDependencyError::Other(err.into()))?
+            .map_err(rpc_error)?
             .ok_or_else(|| TransactionDependencyError::NotFound("transaction".to_string()))?;
-        if tx_with_status.tx_status.status != json_types::Status::Committed {
-            return Err(TransactionDependencyError::Other(anyhow!(
-                "invalid transaction status: {:?}",
-                tx_with_status.tx_status
-            )));
+        let status = tx_with_status.tx_status.status;
+        let in_pool = matches!(
+            status,
+            json_types::Status::Pending | json_types::Status::Proposed
+        );
+        if status != json_types::Status::Committed && !(in_pool && inner.allow_unconfirmed) {
+            return Err(TransactionDependencyError::TxNotCommitted {
+                tx_hash: tx_hash.clone(),
+                status,
+            });
         }
         let tx = match tx_with_status.transaction.unwrap().inner {
             Either::Left(t) => Transaction::from(t.inner).into_view(),
@@ -513,26 +1898,45 @@ impl TransactionDependencyProvider for DefaultTransactionDependencyProvider {
                 .map(|reader| reader.to_entity().into_view())
                 .map_err(|err| anyhow!("invalid molecule encoded TransactionView: {}", err))?,
         };
+        if in_pool {
+            return Ok(tx);
+        }
+        #[cfg(feature = "disk-cache")]
+        inner.store_tx(&tx);
         inner.tx_cache.put(tx_hash.clone(), tx.clone());
         Ok(tx)
     }
     fn get_cell(&self, out_point: &OutPoint) -> Result<CellOutput, TransactionDependencyError> {
-        {
-            let inner = self.inner.lock();
-            let ret = inner.offchain_cache.get_cell(out_point);
-            if ret.is_ok() {
-                return ret;
-            }
+        let mut inner = self.inner.lock();
+        let ret = inner.offchain_cache.get_cell(out_point);
+        if ret.is_ok() {
+            return ret;
+        }
+        if let Some(output) = inner.cell_cache.get(out_point) {
+            return Ok(output.clone());
+        }
+        #[cfg(feature = "disk-cache")]
+        if let Some(output) = inner.load_cell(out_point) {
+            inner.cell_cache.put(out_point.clone(), output.clone());
+            return Ok(output);
         }
-        self.get_cell_with_data(out_point).map(|(output, _)| output)
+        // the data of the contract cells may be large, only fetch the output here
+        let (output, _) = inner.get_live_cell(out_point, false)?;
+        #[cfg(feature = "disk-cache")]
+        inner.store_cell(out_point, &output);
+        inner.cell_cache.put(out_point.clone(), output.clone());
+        Ok(output)
     }
     fn get_cell_data(&self, out_point: &OutPoint) -> Result<Bytes, TransactionDependencyError> {
         {
-            let inner = self.inner.lock();
+            let mut inner = self.inner.lock();
             let ret = inner.offchain_cache.get_cell_data(out_point);
             if ret.is_ok() {
                 return ret;
             }
+            if let Some(output_data) = inner.cell_data_cache.get(out_point) {
+                return Ok(output_data.clone());
+            }
         }
         self.get_cell_with_data(out_point)
             .map(|(_, output_data)| output_data)
@@ -542,15 +1946,43 @@ impl TransactionDependencyProvider for DefaultTransactionDependencyProvider {
         if let Some(header) = inner.header_cache.get(block_hash) {
             return Ok(header.clone());
         }
+        #[cfg(feature = "disk-cache")]
+        if let Some(header) = inner.load_header(block_hash) {
+            inner.header_cache.put(block_hash.clone(), header.clone());
+            return Ok(header);
+        }
         let header = inner
-            .rpc_client
+            .rpc_client()?
             .get_header(block_hash.unpack())
-            .map_err(|err| TransactionDependencyError::Other(err.into()))?
+            .map_err(rpc_error)?
             .map(HeaderView::from)
             .ok_or_else(|| TransactionDependencyError::NotFound("header".to_string()))?;
+        #[cfg(feature = "disk-cache")]
+        inner.store_header(&header);
         inner.header_cache.put(block_hash.clone(), header.clone());
         Ok(header)
     }
+    fn get_header_by_number(&self, number: u64) -> Result<HeaderView, TransactionDependencyError> {
+        let mut inner = self.inner.lock();
+        if let Some(block_hash) = inner.header_number_index.get(&number).cloned() {
+            if let Some(header) = inner.header_cache.get(&block_hash) {
+                return Ok(header.clone());
+            }
+        }
+        // only the cached headers are known in offline mode
+        let rpc_client = inner
+            .rpc_client
+            .as_ref()
+            .ok_or_else(|| TransactionDependencyError::NotFound("header".to_string()))?;
+        let header = rpc_client
+            .get_header_by_number(number)
+            .map_err(rpc_error)?
+            .map(HeaderView::from)
+            .ok_or_else(|| TransactionDependencyError::NotFound("header".to_string()))?;
+        inner.header_cache.put(header.hash(), header.clone());
+        inner.header_number_index.put(number, header.hash());
+        Ok(header)
+    }
 
     fn get_block_extension(
         &self,
@@ -559,9 +1991,9 @@ impl TransactionDependencyProvider for DefaultTransactionDependencyProvider {
         let inner = self.inner.lock();
 
         let block = inner
-            .rpc_client
+            .rpc_client()?
             .get_block(block_hash.unpack())
-            .map_err(|err| TransactionDependencyError::Other(err.into()))?;
+            .map_err(rpc_error)?;
         match block {
             Some(block) => Ok(block.extension.map(ckb_types::packed::Bytes::from)),
             None => Ok(None),
@@ -588,9 +2020,7 @@ impl SecpCkbRawKeySigner {
     }
     pub fn add_secret_key(&mut self, key: secp256k1::SecretKey) {
         let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, &key);
-        let hash160 = H160::from_slice(&blake2b_256(&pubkey.serialize()[..])[0..20])
-            .expect("Generate hash(H160) from pubkey failed");
-        self.keys.insert(hash160, key);
+        self.keys.insert(blake160(&pubkey.serialize()), key);
     }
 
     /// Create SecpkRawKeySigner from secret keys for ethereum algorithm.
@@ -659,3 +2089,1267 @@ mod anyhow_tests {
         assert_eq!("data not found: `DataHashNotFound`", error.to_string());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{random_out_point, MockRpcResult};
+    use ckb_jsonrpc_types::BlockNumber;
+    use ckb_types::packed::{CellInput, TransactionBuilder};
+    use httpmock::prelude::*;
+
+    fn mock_tip_block_number(server: &MockServer, number: u64) {
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("get_tip_block_number");
+            then.status(200)
+                .body(MockRpcResult::new(BlockNumber::from(number)).to_json());
+        });
+    }
+
+    fn build_tx() -> Transaction {
+        let raw = ckb_types::packed::RawTransaction::new_builder()
+            .inputs(vec![CellInput::new(random_out_point(), 0)].pack())
+            .outputs(vec![CellOutput::default(), CellOutput::default()].pack())
+            .outputs_data(vec![Bytes::default().pack(), Bytes::default().pack()].pack())
+            .build();
+        TransactionBuilder::default().raw(raw).build()
+    }
+
+    #[test]
+    fn test_apply_tx_inputs_only() {
+        let server = MockServer::start();
+        mock_tip_block_number(&server, 100);
+        let mut collector = DefaultCellCollector::new(server.base_url().as_str());
+        let tx = build_tx();
+        let input: OutPoint = tx.raw().inputs().get(0).unwrap().previous_output();
+
+        collector.apply_tx_inputs_only(tx).unwrap();
+        assert!(collector.offchain.live_cells.is_empty());
+        assert_eq!(
+            collector
+                .offchain
+                .locked_cells
+                .get(&(input.tx_hash().unpack(), input.index().unpack())),
+            Some(&100)
+        );
+    }
+
+    #[test]
+    fn test_apply_tx_outputs_only() {
+        let server = MockServer::start();
+        mock_tip_block_number(&server, 100);
+        let mut collector = DefaultCellCollector::new(server.base_url().as_str());
+        let tx = build_tx();
+        let tx_hash = tx.calc_tx_hash();
+
+        collector.apply_tx_outputs_only(tx).unwrap();
+        assert!(collector.offchain.locked_cells.is_empty());
+        assert_eq!(collector.offchain.live_cells.len(), 2);
+        for (index, (cell, tip_num)) in collector.offchain.live_cells.iter().enumerate() {
+            assert_eq!(cell.out_point, OutPoint::new(tx_hash.clone(), index as u32));
+            assert_eq!(*tip_num, 100);
+        }
+    }
+
+    #[test]
+    fn test_set_offchain_cells() {
+        let server = MockServer::start();
+        mock_tip_block_number(&server, 100);
+        let mut collector = DefaultCellCollector::new(server.base_url().as_str());
+        let live_cell = |capacity: u64| LiveCell {
+            output: CellOutput::new_builder().capacity(capacity.pack()).build(),
+            output_data: Bytes::default(),
+            out_point: random_out_point(),
+            block_number: 0,
+            tx_index: 0,
+        };
+        collector.apply_tx_outputs_only(build_tx()).unwrap();
+
+        let cells = vec![live_cell(100), live_cell(200)];
+        collector.set_offchain_cells(cells.clone()).unwrap();
+        let out_points = |collector: &DefaultCellCollector| {
+            collector
+                .offchain_cells()
+                .into_iter()
+                .map(|cell| cell.out_point)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            out_points(&collector),
+            vec![cells[0].out_point.clone(), cells[1].out_point.clone()]
+        );
+        assert!(collector
+            .offchain
+            .live_cells
+            .iter()
+            .all(|(_cell, tip_num)| *tip_num == 100));
+
+        let mut replaced = cells[0].clone();
+        replaced.output = CellOutput::new_builder().capacity(300u64.pack()).build();
+        collector.add_offchain_cell(replaced.clone()).unwrap();
+        let new_cell = live_cell(400);
+        collector.add_offchain_cell(new_cell.clone()).unwrap();
+        assert_eq!(
+            out_points(&collector),
+            vec![
+                cells[1].out_point.clone(),
+                replaced.out_point.clone(),
+                new_cell.out_point
+            ]
+        );
+        assert_eq!(collector.offchain_cells()[1].output, replaced.output);
+    }
+
+    #[test]
+    fn test_find_cell_by_out_point() {
+        let server = MockServer::start();
+        mock_tip_block_number(&server, 100);
+        let output = CellOutput::new_builder().capacity(100u64.pack()).build();
+        let node_out_point = random_out_point();
+        let get_live_cell_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("get_live_cell")
+                .body_contains(format!("{:#x}", node_out_point.tx_hash()));
+            then.status(200).body(
+                MockRpcResult::new(json_types::CellWithStatus {
+                    cell: Some(json_types::CellInfo {
+                        output: output.clone().into(),
+                        data: Some(json_types::CellData {
+                            content: Default::default(),
+                            hash: Default::default(),
+                        }),
+                    }),
+                    status: "live".to_string(),
+                })
+                .to_json(),
+            );
+        });
+        server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("get_live_cell");
+            then.status(200).body(
+                MockRpcResult::new(json_types::CellWithStatus {
+                    cell: None,
+                    status: "unknown".to_string(),
+                })
+                .to_json(),
+            );
+        });
+        let mut collector = DefaultCellCollector::new(server.base_url().as_str());
+        let tx = build_tx();
+        let offchain_out_point = OutPoint::new(tx.calc_tx_hash(), 0);
+        collector.apply_tx_outputs_only(tx).unwrap();
+
+        let cell = collector
+            .find_cell_by_out_point(&offchain_out_point)
+            .unwrap()
+            .unwrap();
+        assert_eq!(cell.out_point, offchain_out_point);
+        let cell = collector
+            .find_cell_by_out_point(&node_out_point)
+            .unwrap()
+            .unwrap();
+        assert_eq!(cell.output, output);
+        get_live_cell_mock.assert_hits(1);
+        assert!(collector
+            .find_cell_by_out_point(&random_out_point())
+            .unwrap()
+            .is_none());
+
+        collector.lock_cell(node_out_point.clone(), 100).unwrap();
+        assert!(collector
+            .find_cell_by_out_point(&node_out_point)
+            .unwrap()
+            .is_none());
+        get_live_cell_mock.assert_hits(1);
+    }
+
+    fn mock_indexer(server: &MockServer, cells: Vec<crate::rpc::ckb_indexer::Cell>) {
+        let consensus: json_types::Consensus =
+            ckb_chain_spec::consensus::ConsensusBuilder::default()
+                .build()
+                .into();
+        server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("get_consensus");
+            then.status(200)
+                .body(MockRpcResult::new(consensus).to_json());
+        });
+        let tip_header: json_types::HeaderView = HeaderView::new_advanced_builder()
+            .number(100.pack())
+            .epoch(
+                ckb_types::core::EpochNumberWithFraction::new(0, 100, 1000)
+                    .full_value()
+                    .pack(),
+            )
+            .build()
+            .into();
+        server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("get_tip_header");
+            then.status(200)
+                .body(MockRpcResult::new(tip_header).to_json());
+        });
+        mock_tip_block_number(server, 100);
+        server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("get_indexer_tip");
+            then.status(200).body(
+                MockRpcResult::new(Tip {
+                    block_hash: Default::default(),
+                    block_number: 100.into(),
+                })
+                .to_json(),
+            );
+        });
+        server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("get_cells");
+            then.status(200).body(
+                MockRpcResult::new(crate::rpc::ckb_indexer::Pagination {
+                    objects: cells,
+                    last_cursor: Default::default(),
+                })
+                .to_json(),
+            );
+        });
+    }
+
+    fn indexer_cell(lock: &Script, capacity: u64) -> crate::rpc::ckb_indexer::Cell {
+        crate::rpc::ckb_indexer::Cell {
+            output: CellOutput::new_builder()
+                .lock(lock.clone())
+                .capacity(capacity.pack())
+                .build()
+                .into(),
+            output_data: None,
+            out_point: random_out_point().into(),
+            block_number: 1.into(),
+            tx_index: 1.into(),
+        }
+    }
+
+    #[test]
+    fn test_collect_many() {
+        let lock = Script::new_builder().args(vec![1u8; 20].pack()).build();
+        let server = MockServer::start();
+        let cells = vec![indexer_cell(&lock, 100), indexer_cell(&lock, 200)];
+        mock_indexer(&server, cells.clone());
+        let mut collector = DefaultCellCollector::new(server.base_url().as_str());
+
+        let mut query = CellQueryOptions::new_lock(lock.clone());
+        query.min_total_capacity = 100;
+        let results = collector
+            .collect_many(&[query.clone(), query.clone()], true)
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        let (cells0, capacity0) = &results[0];
+        let (cells1, capacity1) = &results[1];
+        assert_eq!(cells0.len(), 1);
+        assert_eq!(cells1.len(), 1);
+        assert_eq!(*capacity0, 100);
+        assert_eq!(*capacity1, 200);
+        assert_ne!(cells0[0].out_point, cells1[0].out_point);
+        assert_eq!(collector.offchain.locked_cells.len(), 2);
+    }
+
+    #[test]
+    fn test_collector_clone() {
+        let lock = Script::new_builder().args(vec![1u8; 20].pack()).build();
+        let server = MockServer::start();
+        let cell = indexer_cell(&lock, 100);
+        let out_point = OutPoint::from(cell.out_point.clone());
+        mock_indexer(&server, vec![cell]);
+        let mut collector = DefaultCellCollector::new(server.base_url().as_str());
+
+        let mut forked = collector.clone();
+        forked.lock_cell(out_point.clone(), 100).unwrap();
+        forked
+            .add_offchain_cell(LiveCell {
+                output: CellOutput::new_builder().lock(lock.clone()).build(),
+                output_data: Bytes::new(),
+                out_point: random_out_point(),
+                block_number: 100,
+                tx_index: 0,
+            })
+            .unwrap();
+        assert_eq!(forked.offchain.locked_cells.len(), 1);
+        assert_eq!(forked.offchain_cells().len(), 1);
+        assert!(collector.offchain.locked_cells.is_empty());
+        assert!(collector.offchain_cells().is_empty());
+
+        let mut query = CellQueryOptions::new_lock(lock);
+        query.min_total_capacity = 100;
+        let (cells, _) = collector.collect_live_cells(&query, false).unwrap();
+        assert_eq!(cells.len(), 1);
+        assert_eq!(cells[0].out_point, out_point);
+    }
+
+    #[test]
+    fn test_collect_sudt_cells() {
+        let lock = Script::new_builder().args(vec![1u8; 20].pack()).build();
+        let sudt_type = Script::new_builder().args(vec![2u8; 32].pack()).build();
+        let sudt_cell = |amount: u128| {
+            let mut cell = indexer_cell(&lock, 142_0000_0000);
+            cell.output = CellOutput::new_builder()
+                .lock(lock.clone())
+                .type_(Some(sudt_type.clone()).pack())
+                .capacity(142_0000_0000u64.pack())
+                .build()
+                .into();
+            cell.output_data = Some(json_types::JsonBytes::from_vec(
+                amount.to_le_bytes().to_vec(),
+            ));
+            cell
+        };
+        let server = MockServer::start();
+        mock_indexer(&server, vec![sudt_cell(10), sudt_cell(20), sudt_cell(30)]);
+        let mut collector = DefaultCellCollector::new(server.base_url().as_str());
+
+        let (cells, amount) = collector
+            .collect_sudt_cells(lock.clone(), sudt_type.clone(), 25, false)
+            .unwrap();
+        assert_eq!(cells.len(), 2);
+        assert_eq!(amount, 30);
+        assert!(collector.offchain.locked_cells.is_empty());
+
+        let (cells, amount) = collector
+            .collect_sudt_cells(lock, sudt_type, 60, true)
+            .unwrap();
+        assert_eq!(cells.len(), 3);
+        assert_eq!(amount, 60);
+        assert_eq!(collector.offchain.locked_cells.len(), 3);
+    }
+
+    #[test]
+    fn test_collect_sudt_cells_overflow() {
+        let lock = Script::new_builder().args(vec![1u8; 20].pack()).build();
+        let sudt_type = Script::new_builder().args(vec![2u8; 32].pack()).build();
+        let mut cell = indexer_cell(&lock, 142_0000_0000);
+        cell.output = CellOutput::new_builder()
+            .lock(lock.clone())
+            .type_(Some(sudt_type.clone()).pack())
+            .capacity(142_0000_0000u64.pack())
+            .build()
+            .into();
+        // the total amount of the two cells overflows
+        cell.output_data = Some(json_types::JsonBytes::from_vec(
+            (u128::MAX / 2 + 1).to_le_bytes().to_vec(),
+        ));
+        let mut other_cell = cell.clone();
+        other_cell.out_point = random_out_point().into();
+        let server = MockServer::start();
+        mock_indexer(&server, vec![cell, other_cell]);
+        let mut collector = DefaultCellCollector::new(server.base_url().as_str());
+
+        assert!(matches!(
+            collector.collect_sudt_cells(lock, sudt_type, u128::MAX, true),
+            Err(CellCollectorError::Other(_))
+        ));
+        assert!(collector.offchain.locked_cells.is_empty());
+    }
+
+    #[test]
+    fn test_collect_cells_by_type() {
+        let type_script = Script::new_builder().args(vec![2u8; 32].pack()).build();
+        // the args of the type script is prefixed by the queried one
+        let longer_type = Script::new_builder().args(vec![2u8; 33].pack()).build();
+        let typed_cell = |lock_arg: u8, type_script: &Script| {
+            let lock = Script::new_builder()
+                .args(vec![lock_arg; 20].pack())
+                .build();
+            let mut cell = indexer_cell(&lock, 100);
+            cell.output = CellOutput::new_builder()
+                .lock(lock)
+                .type_(Some(type_script.clone()).pack())
+                .capacity(100u64.pack())
+                .build()
+                .into();
+            cell
+        };
+        let server = MockServer::start();
+        mock_indexer(
+            &server,
+            vec![
+                typed_cell(1, &type_script),
+                typed_cell(2, &longer_type),
+                typed_cell(3, &type_script),
+            ],
+        );
+        let mut collector = DefaultCellCollector::new(server.base_url().as_str());
+
+        let (cells, capacity) = collector
+            .collect_cells_by_type(type_script.clone(), 200, true)
+            .unwrap();
+        assert_eq!(cells.len(), 2);
+        assert_eq!(capacity, 200);
+        assert!(cells
+            .iter()
+            .all(|cell| cell.output.type_().to_opt() == Some(type_script.clone())));
+        assert_eq!(collector.offchain.locked_cells.len(), 2);
+    }
+
+    #[test]
+    fn test_collector_failover() {
+        let lock = Script::new_builder().args(vec![1u8; 20].pack()).build();
+        let server = MockServer::start();
+        mock_indexer(&server, vec![indexer_cell(&lock, 100)]);
+        // nothing is listening on port 1
+        let mut collector =
+            DefaultCellCollector::new_with_urls(&["http://127.0.0.1:1", &server.base_url()]);
+
+        let mut query = CellQueryOptions::new_lock(lock);
+        query.min_total_capacity = 100;
+        let (cells, capacity) = collector.collect_live_cells(&query, false).unwrap();
+        assert_eq!(cells.len(), 1);
+        assert_eq!(capacity, 100);
+        assert_eq!(collector.endpoints().unwrap().current_index(), 1);
+    }
+
+    #[test]
+    fn test_light_client_backend() {
+        let lock = Script::new_builder().args(vec![1u8; 20].pack()).build();
+        let server = MockServer::start();
+        let mut cellbase_cell = indexer_cell(&lock, 100);
+        cellbase_cell.tx_index = 0.into();
+        let cell = indexer_cell(&lock, 100);
+        let out_point = OutPoint::from(cell.out_point.clone());
+        mock_indexer(&server, vec![cellbase_cell, cell]);
+        let rpc: Arc<dyn CkbRpcInterface + Send + Sync> =
+            Arc::new(crate::rpc::LightClientRpcClient::new(&server.base_url()));
+
+        // no indexer tip check and the cellbase cell is immature without the consensus
+        let mut collector = DefaultCellCollector::from_rpc_interface(Arc::clone(&rpc));
+        assert!(collector.endpoints().is_none());
+        let mut query = CellQueryOptions::new_lock(lock);
+        query.min_total_capacity = 100;
+        let (cells, capacity) = collector.collect_live_cells(&query, false).unwrap();
+        assert_eq!(cells.len(), 1);
+        assert_eq!(cells[0].out_point, out_point);
+        assert_eq!(capacity, 100);
+        assert_eq!(collector.last_indexer_tip(), None);
+
+        let provider = DefaultTransactionDependencyProvider::from_rpc_interface(rpc, 10);
+        assert!(matches!(
+            provider.get_header_by_number(1),
+            Err(TransactionDependencyError::Unsupported(_))
+        ));
+        assert_eq!(
+            crate::util::get_header_by_number(&provider, 1).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_provider_on_new_tip() {
+        let server = MockServer::start();
+        let build_header = |number: u64| {
+            HeaderView::new_advanced_builder()
+                .number(number.pack())
+                .epoch(
+                    ckb_types::core::EpochNumberWithFraction::new(0, number, 1000)
+                        .full_value()
+                        .pack(),
+                )
+                .build()
+        };
+        let header3 = build_header(3);
+        let header10 = build_header(10);
+        for header in [&header3, &header10] {
+            let json_header = json_types::HeaderView::from(header.clone());
+            let hash = format!("{:#x}", Unpack::<ckb_types::H256>::unpack(&header.hash()));
+            server.mock(|when, then| {
+                when.method(POST)
+                    .path("/")
+                    .body_contains("get_header\"")
+                    .body_contains(hash.as_str());
+                then.status(200)
+                    .body(MockRpcResult::new(json_header).to_json());
+            });
+        }
+        // block 3 is reorged
+        server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("get_block_hash");
+            then.status(200)
+                .body(MockRpcResult::new(ckb_types::h256!("0x3")).to_json());
+        });
+
+        let provider = DefaultTransactionDependencyProvider::new(server.base_url().as_str(), 10);
+        provider.get_header(&header3.hash()).unwrap();
+        provider.get_header(&header10.hash()).unwrap();
+        assert_eq!(provider.inner.lock().header_cache.len(), 2);
+
+        // header 10 is above the new tip and header 3 is not in main chain
+        assert_eq!(provider.on_new_tip(&build_header(5)).unwrap(), 2);
+        assert_eq!(provider.inner.lock().header_cache.len(), 0);
+    }
+
+    #[cfg(feature = "disk-cache")]
+    #[test]
+    fn test_provider_disk_cache() {
+        let server = MockServer::start();
+        let build_header = |number: u64| {
+            HeaderView::new_advanced_builder()
+                .number(number.pack())
+                .epoch(
+                    ckb_types::core::EpochNumberWithFraction::new(0, number, 1000)
+                        .full_value()
+                        .pack(),
+                )
+                .build()
+        };
+        let header3 = build_header(3);
+        let header90 = build_header(90);
+        let header_mocks: Vec<_> = [&header3, &header90]
+            .iter()
+            .map(|header| {
+                let json_header = json_types::HeaderView::from((*header).clone());
+                let hash = format!("{:#x}", Unpack::<ckb_types::H256>::unpack(&header.hash()));
+                server.mock(|when, then| {
+                    when.method(POST)
+                        .path("/")
+                        .body_contains("get_header\"")
+                        .body_contains(hash.as_str());
+                    then.status(200)
+                        .body(MockRpcResult::new(json_header).to_json());
+                })
+            })
+            .collect();
+        let output = CellOutput::new_builder().capacity(100u64.pack()).build();
+        let data = Bytes::from(vec![1u8; 100]);
+        let cell_mock = server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("get_live_cell");
+            then.status(200).body(
+                MockRpcResult::new(json_types::CellWithStatus {
+                    cell: Some(json_types::CellInfo {
+                        output: output.clone().into(),
+                        data: Some(json_types::CellData {
+                            content: json_types::JsonBytes::from_bytes(data.clone()),
+                            hash: Default::default(),
+                        }),
+                    }),
+                    status: "live".to_string(),
+                })
+                .to_json(),
+            );
+        });
+
+        let path = std::env::temp_dir().join(format!(
+            "ckb-sdk-provider-disk-cache-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&path);
+        let url = server.base_url();
+        let out_point = random_out_point();
+        {
+            let provider =
+                DefaultTransactionDependencyProvider::with_disk_cache(&url, 10, &path, 1 << 20)
+                    .unwrap();
+            provider.on_new_tip(&build_header(100)).unwrap();
+            provider.get_header(&header3.hash()).unwrap();
+            provider.get_header(&header90.hash()).unwrap();
+            assert_eq!(provider.get_cell_data(&out_point).unwrap(), data);
+        }
+        header_mocks[0].assert_hits(1);
+        header_mocks[1].assert_hits(1);
+        cell_mock.assert_hits(1);
+
+        // the new provider reads the persisted data from disk
+        let provider =
+            DefaultTransactionDependencyProvider::with_disk_cache(&url, 10, &path, 1 << 20)
+                .unwrap();
+        assert_eq!(provider.get_header(&header3.hash()).unwrap(), header3);
+        assert_eq!(provider.get_cell(&out_point).unwrap(), output);
+        assert_eq!(provider.get_cell_data(&out_point).unwrap(), data);
+        header_mocks[0].assert_hits(1);
+        cell_mock.assert_hits(1);
+        // header 90 is too close to the tip to be persisted
+        assert_eq!(provider.get_header(&header90.hash()).unwrap(), header90);
+        header_mocks[1].assert_hits(2);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn test_get_block_epoch() {
+        let server = MockServer::start();
+        let epoch = EpochNumberWithFraction::new(12, 34, 1800);
+        let header = HeaderView::new_advanced_builder()
+            .number(100.pack())
+            .epoch(epoch.full_value().pack())
+            .build();
+        let header_mock = server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("get_header\"");
+            then.status(200)
+                .body(MockRpcResult::new(json_types::HeaderView::from(header.clone())).to_json());
+        });
+
+        let provider = DefaultTransactionDependencyProvider::new(server.base_url().as_str(), 10);
+        assert_eq!(provider.get_block_epoch(&header.hash()).unwrap(), epoch);
+        provider.inner.lock().header_cache.clear();
+        assert_eq!(provider.get_block_epoch(&header.hash()).unwrap(), epoch);
+        header_mock.assert_hits(1);
+    }
+
+    #[test]
+    fn test_get_header_by_number() {
+        let server = MockServer::start();
+        let header = HeaderView::new_advanced_builder()
+            .number(100.pack())
+            .epoch(EpochNumberWithFraction::new(1, 0, 1000).full_value().pack())
+            .build();
+        let by_number_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("get_header_by_number");
+            then.status(200)
+                .body(MockRpcResult::new(json_types::HeaderView::from(header.clone())).to_json());
+        });
+        let by_hash_mock = server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("get_header\"");
+            then.status(200)
+                .body(MockRpcResult::new(json_types::HeaderView::from(header.clone())).to_json());
+        });
+
+        let provider = DefaultTransactionDependencyProvider::new(server.base_url().as_str(), 10);
+        let by_number = provider.get_header_by_number(100).unwrap();
+        assert_eq!(by_number, header);
+        // both lookup paths share the header cache
+        assert_eq!(provider.get_header(&header.hash()).unwrap(), by_number);
+        assert_eq!(provider.get_header_by_number(100).unwrap(), by_number);
+        by_number_mock.assert_hits(1);
+        by_hash_mock.assert_hits(0);
+
+        // the header is reorged
+        assert_eq!(
+            provider
+                .on_new_tip(
+                    &HeaderView::new_advanced_builder()
+                        .number(99.pack())
+                        .epoch(
+                            EpochNumberWithFraction::new(0, 999, 1000)
+                                .full_value()
+                                .pack()
+                        )
+                        .build()
+                )
+                .unwrap(),
+            1
+        );
+        assert!(provider.inner.lock().header_number_index.is_empty());
+        provider.get_header_by_number(100).unwrap();
+        by_number_mock.assert_hits(2);
+    }
+
+    #[test]
+    fn test_get_current_epoch() {
+        let server = MockServer::start();
+        let epoch = EpochNumberWithFraction::new(12, 34, 1800);
+        let header = HeaderView::new_advanced_builder()
+            .number(100.pack())
+            .epoch(epoch.full_value().pack())
+            .build();
+        server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("get_tip_header");
+            then.status(200)
+                .body(MockRpcResult::new(json_types::HeaderView::from(header)).to_json());
+        });
+
+        let provider = DefaultTransactionDependencyProvider::new(server.base_url().as_str(), 10);
+        assert_eq!(provider.get_current_epoch().unwrap(), epoch);
+    }
+
+    #[test]
+    fn test_warm_cache_from_tx() {
+        let server = MockServer::start();
+        let output = CellOutput::new_builder().capacity(100u64.pack()).build();
+        let cell_with_status = json_types::CellWithStatus {
+            cell: Some(json_types::CellInfo {
+                output: output.clone().into(),
+                data: Some(json_types::CellData {
+                    content: Default::default(),
+                    hash: Default::default(),
+                }),
+            }),
+            status: "live".to_string(),
+        };
+        let mock = server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("get_live_cell");
+            then.status(200)
+                .body(MockRpcResult::new(cell_with_status).to_json());
+        });
+        let tx = ckb_types::core::TransactionBuilder::default()
+            .input(CellInput::new(random_out_point(), 0))
+            .input(CellInput::new(random_out_point(), 0))
+            .build();
+
+        let provider = DefaultTransactionDependencyProvider::new(server.base_url().as_str(), 10);
+        assert_eq!(provider.warm_cache_from_tx(&tx).unwrap(), 2);
+        assert_eq!(provider.warm_cache_from_tx(&tx).unwrap(), 0);
+        mock.assert_hits(2);
+        for out_point in tx.input_pts_iter() {
+            assert_eq!(provider.get_cell(&out_point).unwrap(), output);
+        }
+        mock.assert_hits(2);
+    }
+
+    #[test]
+    fn test_cell_data_cache() {
+        let server = MockServer::start();
+        let output = CellOutput::new_builder().capacity(100u64.pack()).build();
+        let cell_with_status = |data: Option<Bytes>| json_types::CellWithStatus {
+            cell: Some(json_types::CellInfo {
+                output: output.clone().into(),
+                data: data.map(|data| json_types::CellData {
+                    content: json_types::JsonBytes::from_bytes(data),
+                    hash: Default::default(),
+                }),
+            }),
+            status: "live".to_string(),
+        };
+        let without_data = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("get_live_cell")
+                .body_contains("false");
+            then.status(200)
+                .body(MockRpcResult::new(cell_with_status(None)).to_json());
+        });
+        let data = Bytes::from(vec![1u8; 1000]);
+        let with_data = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("get_live_cell")
+                .body_contains("true");
+            then.status(200)
+                .body(MockRpcResult::new(cell_with_status(Some(data.clone()))).to_json());
+        });
+
+        let provider = DefaultTransactionDependencyProvider::new(server.base_url().as_str(), 10);
+        let out_point = random_out_point();
+        assert_eq!(provider.get_cell(&out_point).unwrap(), output);
+        assert_eq!(provider.get_cell(&out_point).unwrap(), output);
+        without_data.assert_hits(1);
+        with_data.assert_hits(0);
+        assert_eq!(provider.get_cell_data(&out_point).unwrap(), data);
+        assert_eq!(provider.get_cell_data(&out_point).unwrap(), data);
+        with_data.assert_hits(1);
+
+        // the data larger than the limit is not cached, the output is kept
+        provider.set_cell_data_cache_limit(500);
+        assert_eq!(provider.inner.lock().cell_data_cache.len(), 0);
+        assert_eq!(provider.get_cell_data(&out_point).unwrap(), data);
+        assert_eq!(provider.inner.lock().cell_data_cache_size, 0);
+        with_data.assert_hits(2);
+        assert_eq!(provider.get_cell(&out_point).unwrap(), output);
+        without_data.assert_hits(1);
+    }
+
+    #[test]
+    fn test_provider_typed_errors() {
+        let server = MockServer::start();
+        let cell_with_status = json_types::CellWithStatus {
+            cell: None,
+            status: "dead".to_string(),
+        };
+        server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("get_live_cell");
+            then.status(200)
+                .body(MockRpcResult::new(cell_with_status).to_json());
+        });
+        let tx_with_status = json_types::TransactionWithStatusResponse {
+            transaction: None,
+            cycles: None,
+            time_added_to_pool: None,
+            tx_status: json_types::TxStatus::pending(),
+            fee: None,
+            min_replace_fee: None,
+        };
+        server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("get_transaction");
+            then.status(200)
+                .body(MockRpcResult::new(tx_with_status).to_json());
+        });
+        let provider = DefaultTransactionDependencyProvider::new(server.base_url().as_str(), 10);
+
+        let out_point = random_out_point();
+        match provider.get_cell(&out_point) {
+            Err(TransactionDependencyError::CellNotLive {
+                out_point: err_out_point,
+                status,
+            }) => {
+                assert_eq!(err_out_point, out_point);
+                assert_eq!(status, "dead");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let tx_hash = out_point.tx_hash();
+        match provider.get_transaction(&tx_hash) {
+            Err(TransactionDependencyError::TxNotCommitted {
+                tx_hash: err_tx_hash,
+                status,
+            }) => {
+                assert_eq!(err_tx_hash, tx_hash);
+                assert_eq!(status, json_types::Status::Pending);
+            }
+            other => panic!("unexpected result: {:?}", other.map(|tx| tx.hash())),
+        }
+
+        // nothing is listening on port 1
+        let provider = DefaultTransactionDependencyProvider::new("http://127.0.0.1:1", 10);
+        assert!(matches!(
+            provider.get_cell(&out_point),
+            Err(TransactionDependencyError::Rpc(err)) if err.is_connection_error()
+        ));
+    }
+
+    #[test]
+    fn test_get_unconfirmed_transaction() {
+        let server = MockServer::start();
+        let tx = build_tx().into_view();
+        let tx_with_status = json_types::TransactionWithStatusResponse {
+            transaction: Some(json_types::ResponseFormat::json(
+                json_types::TransactionView::from(tx.clone()),
+            )),
+            cycles: None,
+            time_added_to_pool: None,
+            tx_status: json_types::TxStatus::proposed(),
+            fee: None,
+            min_replace_fee: None,
+        };
+        let tx_mock = server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("get_transaction");
+            then.status(200)
+                .body(MockRpcResult::new(tx_with_status).to_json());
+        });
+        server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("get_raw_tx_pool");
+            then.status(200).body(
+                MockRpcResult::new(json_types::RawTxPool::Ids(json_types::TxPoolIds {
+                    pending: Vec::new(),
+                    proposed: vec![tx.hash().unpack()],
+                }))
+                .to_json(),
+            );
+        });
+
+        let provider = DefaultTransactionDependencyProvider::new(server.base_url().as_str(), 10);
+        assert!(matches!(
+            provider.get_transaction(&tx.hash()),
+            Err(TransactionDependencyError::TxNotCommitted {
+                status: json_types::Status::Proposed,
+                ..
+            })
+        ));
+        provider.set_allow_unconfirmed(true);
+        assert_eq!(
+            provider.get_transaction(&tx.hash()).unwrap().hash(),
+            tx.hash()
+        );
+        // the unconfirmed transaction is not cached
+        assert_eq!(
+            provider.get_transaction(&tx.hash()).unwrap().hash(),
+            tx.hash()
+        );
+        tx_mock.assert_hits(3);
+
+        let pool = CkbRpcClient::new(server.base_url().as_str())
+            .get_pool_tx_hashes()
+            .unwrap();
+        assert!(pool.pending.is_empty());
+        assert_eq!(pool.proposed, vec![tx.hash().unpack()]);
+    }
+
+    #[test]
+    fn test_offline_provider() {
+        let mut provider = DefaultTransactionDependencyProvider::default();
+        let output = CellOutput::new_builder().capacity(100u64.pack()).build();
+        let tx = ckb_types::core::TransactionBuilder::default()
+            .output(output.clone())
+            .output_data(Bytes::from(vec![1, 2, 3]).pack())
+            .build();
+        assert!(matches!(
+            provider.get_transaction(&tx.hash()),
+            Err(TransactionDependencyError::Other(err)) if err.to_string() == "offline mode"
+        ));
+        assert!(provider.get_header(&Byte32::default()).is_err());
+
+        provider.apply_tx(tx.data(), 1).unwrap();
+        assert_eq!(
+            provider.get_transaction(&tx.hash()).unwrap().hash(),
+            tx.hash()
+        );
+        let out_point = OutPoint::new(tx.hash(), 0);
+        assert_eq!(provider.get_cell(&out_point).unwrap(), output);
+        assert_eq!(
+            provider.get_cell_data(&out_point).unwrap(),
+            Bytes::from(vec![1, 2, 3])
+        );
+    }
+
+    #[test]
+    fn test_collect_indexer_not_synced() {
+        let lock = Script::new_builder().args(vec![1u8; 20].pack()).build();
+        let server = MockServer::start();
+        let indexer_tip_mock = server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("get_indexer_tip");
+            then.status(200)
+                .body(MockRpcResult::new(Option::<Tip>::None).to_json());
+        });
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("get_indexer_info");
+            then.status(200).body(
+                MockRpcResult::new(crate::rpc::ckb_indexer::IndexerInfo {
+                    version: "0.4.1".to_string(),
+                    db_info: None,
+                })
+                .to_json(),
+            );
+        });
+        mock_indexer(&server, vec![indexer_cell(&lock, 100)]);
+        let mut collector = DefaultCellCollector::new(server.base_url().as_str());
+
+        let mut query = CellQueryOptions::new_lock(lock);
+        query.min_total_capacity = 100;
+        match collector.collect_live_cells(&query, false) {
+            Err(CellCollectorError::IndexerNotSynced {
+                node_tip,
+                indexer_tip,
+                indexer_version,
+            }) => {
+                assert_eq!(node_tip, 100);
+                assert_eq!(indexer_tip, None);
+                assert_eq!(indexer_version.as_deref(), Some("0.4.1"));
+            }
+            other => panic!("unexpected result: {:?}", other.map(|(cells, _)| cells)),
+        }
+        indexer_tip_mock.assert_hits(1);
+    }
+
+    #[test]
+    fn test_collect_sync_tolerance() {
+        let lock = Script::new_builder().args(vec![1u8; 20].pack()).build();
+        let server = MockServer::start();
+        let indexer_tip_mock = server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("get_indexer_tip");
+            then.status(200).body(
+                MockRpcResult::new(Tip {
+                    block_hash: Default::default(),
+                    block_number: 97.into(),
+                })
+                .to_json(),
+            );
+        });
+        mock_indexer(&server, vec![indexer_cell(&lock, 100)]);
+        let mut collector = DefaultCellCollector::new(server.base_url().as_str());
+        assert_eq!(collector.sync_tolerance(), SyncTolerance::Strict);
+
+        let mut query = CellQueryOptions::new_lock(lock);
+        query.min_total_capacity = 100;
+        collector.set_sync_tolerance(SyncTolerance::Blocks(3));
+        let (cells, _) = collector.collect_live_cells(&query, false).unwrap();
+        assert_eq!(cells.len(), 1);
+        assert_eq!(collector.last_indexer_tip(), Some(97));
+        indexer_tip_mock.assert_hits(1);
+
+        collector.set_sync_tolerance(SyncTolerance::Skip);
+        let (cells, _) = collector.collect_live_cells(&query, false).unwrap();
+        assert_eq!(cells.len(), 1);
+        assert_eq!(collector.last_indexer_tip(), None);
+        indexer_tip_mock.assert_hits(1);
+    }
+
+    fn load_genesis_block() -> BlockView {
+        let genesis_block: json_types::BlockView =
+            serde_json::from_str(include_str!("../test-data/genesis_block.json")).unwrap();
+        genesis_block.into()
+    }
+
+    #[test]
+    fn test_cell_dep_resolver_from_genesis() {
+        let genesis_block = load_genesis_block();
+        let resolver = DefaultCellDepResolver::from_genesis(&genesis_block).unwrap();
+        let genesis_txs = genesis_block.transactions();
+
+        let sighash_dep = CellDep::new_builder()
+            .out_point(OutPoint::new(
+                genesis_txs[SIGHASH_GROUP_OUTPUT_LOC.0].hash(),
+                SIGHASH_GROUP_OUTPUT_LOC.1 as u32,
+            ))
+            .dep_type(DepType::DepGroup.into())
+            .build();
+        let multisig_dep = CellDep::new_builder()
+            .out_point(OutPoint::new(
+                genesis_txs[MULTISIG_GROUP_OUTPUT_LOC.0].hash(),
+                MULTISIG_GROUP_OUTPUT_LOC.1 as u32,
+            ))
+            .dep_type(DepType::DepGroup.into())
+            .build();
+        let dao_dep = CellDep::new_builder()
+            .out_point(OutPoint::new(
+                genesis_txs[DAO_OUTPUT_LOC.0].hash(),
+                DAO_OUTPUT_LOC.1 as u32,
+            ))
+            .dep_type(DepType::Code.into())
+            .build();
+        assert_eq!(resolver.sighash_dep().unwrap().0, sighash_dep);
+        assert_eq!(resolver.multisig_dep().unwrap().0, multisig_dep);
+        assert_eq!(resolver.dao_dep().unwrap().0, dao_dep);
+        assert_eq!(
+            resolver.sighash_dep().unwrap().1,
+            "Secp256k1 blake160 sighash all"
+        );
+
+        let block = genesis_block
+            .as_advanced_builder()
+            .number(1.pack())
+            .epoch(EpochNumberWithFraction::new(0, 1, 1000).full_value().pack())
+            .build();
+        assert!(matches!(
+            DefaultCellDepResolver::from_genesis(&block),
+            Err(ParseGenesisInfoError::InvalidBlockNumber(1))
+        ));
+        let block = BlockView::new_advanced_builder().build();
+        assert!(matches!(
+            DefaultCellDepResolver::from_genesis(&block),
+            Err(ParseGenesisInfoError::TypeHashNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_cell_dep_resolver_insert_remove() {
+        let mut resolver = DefaultCellDepResolver::from_genesis(&load_genesis_block()).unwrap();
+        let script_id = ScriptId::new_data1(ckb_types::h256!("0x1"));
+        let cell_dep1 = CellDep::new_builder().out_point(random_out_point()).build();
+        let cell_dep2 = CellDep::new_builder()
+            .out_point(random_out_point())
+            .dep_type(DepType::DepGroup.into())
+            .build();
+
+        assert!(!resolver.contains(&script_id));
+        assert_eq!(resolver.get(&script_id), None);
+        assert_eq!(
+            resolver.insert(script_id.clone(), cell_dep1.clone(), "dep1".to_string()),
+            None
+        );
+        assert!(resolver.contains(&script_id));
+        assert_eq!(
+            resolver.get(&script_id),
+            Some(&(cell_dep1.clone(), "dep1".to_string()))
+        );
+        assert_eq!(
+            resolver.insert(script_id.clone(), cell_dep2.clone(), "dep2".to_string()),
+            Some((cell_dep1, "dep1".to_string()))
+        );
+        assert_eq!(
+            resolver.remove(&script_id),
+            Some((cell_dep2, "dep2".to_string()))
+        );
+        assert_eq!(resolver.remove(&script_id), None);
+        assert!(!resolver.contains(&script_id));
+
+        // the system scripts are not affected
+        assert!(resolver.contains(&ScriptId::new_type(SIGHASH_TYPE_HASH)));
+        assert!(resolver.contains(&ScriptId::new_type(MULTISIG_TYPE_HASH)));
+        assert!(resolver.contains(&ScriptId::new_type(DAO_TYPE_HASH)));
+    }
+
+    #[test]
+    fn test_cell_dep_resolver_resolve() {
+        let mut resolver = DefaultCellDepResolver::from_genesis(&load_genesis_block()).unwrap();
+        let sighash_script = Script::new_builder()
+            .code_hash(SIGHASH_TYPE_HASH.pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(vec![0u8; 20]).pack())
+            .build();
+        assert_eq!(
+            resolver.resolve(&sighash_script),
+            resolver.sighash_dep().map(|(cell_dep, _)| cell_dep.clone())
+        );
+
+        let code_hash = ckb_types::h256!("0x1");
+        let cell_dep = CellDep::new_builder().out_point(random_out_point()).build();
+        resolver.insert(
+            ScriptId::new_data1(code_hash.clone()),
+            cell_dep.clone(),
+            "my script".to_string(),
+        );
+        let script = Script::new_builder()
+            .code_hash(code_hash.pack())
+            .hash_type(ScriptHashType::Data1.into())
+            .build();
+        assert_eq!(resolver.resolve(&script), Some(cell_dep.clone()));
+        // resolve through the trait object
+        let dyn_resolver: &dyn CellDepResolver = &resolver;
+        assert_eq!(dyn_resolver.resolve(&script), Some(cell_dep));
+
+        // the hash type is part of the script id
+        let data2_dep = CellDep::new_builder().out_point(random_out_point()).build();
+        resolver.insert(
+            ScriptId::new_data2(code_hash),
+            data2_dep.clone(),
+            "my script v2".to_string(),
+        );
+        let data2_script = script
+            .clone()
+            .as_builder()
+            .hash_type(ScriptHashType::Data2.into())
+            .build();
+        assert_eq!(resolver.resolve(&data2_script), Some(data2_dep));
+        let script = script
+            .as_builder()
+            .hash_type(ScriptHashType::Data.into())
+            .build();
+        assert_eq!(resolver.resolve(&script), None);
+        let unknown = Script::new_builder()
+            .code_hash(ckb_types::h256!("0x2").pack())
+            .hash_type(ScriptHashType::Type.into())
+            .build();
+        assert_eq!(resolver.resolve(&unknown), None);
+    }
+
+    #[test]
+    fn test_from_genesis_info_and_json() {
+        let genesis_block = load_genesis_block();
+        let out_point = random_out_point();
+        let json = format!(
+            r#"[{{
+                "name": "my script",
+                "code_hash": "0x0000000000000000000000000000000000000000000000000000000000000001",
+                "hash_type": "data1",
+                "cell_dep": {{
+                    "out_point": {{ "tx_hash": "{:#x}", "index": "0x0" }},
+                    "dep_type": "code"
+                }}
+            }}]"#,
+            Unpack::<ckb_types::H256>::unpack(&out_point.tx_hash())
+        );
+
+        let info = GenesisInfo::from_genesis_block(&genesis_block).unwrap();
+        let resolver = DefaultCellDepResolver::from_genesis_info_and_json(&info, &json).unwrap();
+        assert!(resolver.sighash_dep().is_some());
+        assert!(resolver.multisig_dep().is_some());
+        assert!(resolver.dao_dep().is_some());
+        let script_id = ScriptId::new_data1(ckb_types::h256!("0x1"));
+        let (cell_dep, name) = resolver.get(&script_id).unwrap();
+        assert_eq!(name, "my script");
+        assert_eq!(cell_dep.out_point(), out_point);
+        assert_eq!(cell_dep.dep_type(), DepType::Code.into());
+
+        assert!(matches!(
+            DefaultCellDepResolver::from_genesis_info_and_json(&info, "{}"),
+            Err(ParseGenesisInfoError::InvalidCellDepsJson(_))
+        ));
+    }
+
+    #[test]
+    fn test_system_script_data_hash() {
+        assert_eq!(
+            SIGHASH_DATA_HASH,
+            ckb_resource::CODE_HASH_SECP256K1_BLAKE160_SIGHASH_ALL
+        );
+        assert_eq!(
+            MULTISIG_DATA_HASH,
+            ckb_resource::CODE_HASH_SECP256K1_BLAKE160_MULTISIG_ALL
+        );
+        assert_eq!(DAO_DATA_HASH, ckb_resource::CODE_HASH_DAO);
+    }
+
+    #[test]
+    fn test_genesis_info_json() {
+        let genesis_block = load_genesis_block();
+        let info = GenesisInfo::from_genesis_block(&genesis_block).unwrap();
+        assert_eq!(info.genesis_hash, crate::constants::GENESIS_HASH_TESTNET);
+        assert_eq!(
+            GenesisInfo::from_json(include_str!("../test-data/genesis_info_testnet.json")).unwrap(),
+            info
+        );
+        assert_eq!(GenesisInfo::from_json(&info.to_json()).unwrap(), info);
+        let resolver = DefaultCellDepResolver::new(&info);
+        let from_block = DefaultCellDepResolver::from_genesis(&genesis_block).unwrap();
+        assert_eq!(resolver.to_json_string(), from_block.to_json_string());
+
+        let mainnet =
+            GenesisInfo::from_json(include_str!("../test-data/genesis_info_mainnet.json")).unwrap();
+        assert_eq!(mainnet.genesis_hash, crate::constants::GENESIS_HASH_MAINNET);
+        let resolver = DefaultCellDepResolver::new(&mainnet);
+        assert_eq!(resolver.sighash_dep().unwrap().0, mainnet.sighash_dep);
+        assert_eq!(resolver.multisig_dep().unwrap().0, mainnet.multisig_dep);
+        assert_eq!(resolver.dao_dep().unwrap().0, mainnet.dao_dep);
+
+        // the deps not at the genesis locations are rejected
+        let mut invalid = info.clone();
+        invalid.multisig_dep = invalid
+            .multisig_dep
+            .as_builder()
+            .out_point(OutPoint::new(info.dao_dep.out_point().tx_hash(), 1))
+            .build();
+        assert!(matches!(
+            GenesisInfo::from_json(&invalid.to_json()),
+            Err(ParseGenesisInfoError::InvalidGenesisInfo(_))
+        ));
+        let mut invalid = info.clone();
+        invalid.dao_dep = invalid
+            .dao_dep
+            .as_builder()
+            .dep_type(DepType::DepGroup.into())
+            .build();
+        assert!(invalid.check().is_err());
+        let mut invalid = info;
+        invalid.dao_type_hash = invalid.sighash_type_hash.clone();
+        assert!(invalid.check().is_err());
+        assert!(matches!(
+            GenesisInfo::from_json("{}"),
+            Err(ParseGenesisInfoError::InvalidGenesisInfo(_))
+        ));
+    }
+
+    #[test]
+    fn test_cell_dep_resolver_to_json_string() {
+        let genesis_block = load_genesis_block();
+        let mut resolver = DefaultCellDepResolver::from_genesis(&genesis_block).unwrap();
+        let script_id = ScriptId::new_data1(ckb_types::h256!("0x1"));
+        let cell_dep = CellDep::new_builder().out_point(random_out_point()).build();
+        resolver.insert(script_id.clone(), cell_dep.clone(), "my script".to_string());
+
+        let json = resolver.to_json_string();
+        let items: Vec<CellDepItem> = serde_json::from_str(&json).unwrap();
+        assert_eq!(items.len(), 4);
+        assert_eq!(items[0].name, "Nervos DAO");
+        let item = items.iter().find(|item| item.name == "my script").unwrap();
+        assert_eq!(item.code_hash, script_id.code_hash);
+        assert_eq!(item.hash_type, json_types::ScriptHashType::Data1);
+        assert_eq!(CellDep::from(item.cell_dep.clone()), cell_dep);
+
+        let info = GenesisInfo::from_genesis_block(&genesis_block).unwrap();
+        let loaded = DefaultCellDepResolver::from_genesis_info_and_json(&info, &json).unwrap();
+        assert_eq!(loaded.get(&script_id), resolver.get(&script_id));
+        assert_eq!(loaded.to_json_string(), json);
+    }
+
+    #[test]
+    fn test_get_consensus_cache() {
+        let server = MockServer::start();
+        let consensus: json_types::Consensus =
+            ckb_chain_spec::consensus::ConsensusBuilder::default()
+                .build()
+                .into();
+        let mock = server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("get_consensus");
+            then.status(200)
+                .body(MockRpcResult::new(consensus.clone()).to_json());
+        });
+        let provider = DefaultTransactionDependencyProvider::new(server.base_url().as_str(), 10);
+
+        assert_eq!(provider.get_consensus().unwrap().id, consensus.id);
+        assert_eq!(provider.get_consensus().unwrap().id, consensus.id);
+        mock.assert_hits(1);
+
+        provider.invalidate_consensus_cache();
+        provider.get_consensus().unwrap();
+        mock.assert_hits(2);
+
+        provider.set_consensus_ttl(Duration::from_secs(0));
+        provider.get_consensus().unwrap();
+        mock.assert_hits(3);
+    }
+}
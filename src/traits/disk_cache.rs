@@ -0,0 +1,220 @@
+//! A file-per-key store persisting the immutable data fetched by
+//! `DefaultTransactionDependencyProvider` across the process restarts.
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use ckb_types::bytes::Bytes;
+
+/// The kinds of the data persisted by the disk cache, each kind is stored in its own
+/// sub directory.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum DiskCacheKind {
+    /// The committed transactions keyed by the transaction hash
+    Transaction,
+    /// The cell outputs keyed by the out point
+    Cell,
+    /// The cell data keyed by the out point
+    CellData,
+    /// The headers keyed by the block hash
+    Header,
+}
+
+impl DiskCacheKind {
+    const ALL: [DiskCacheKind; 4] = [
+        DiskCacheKind::Transaction,
+        DiskCacheKind::Cell,
+        DiskCacheKind::CellData,
+        DiskCacheKind::Header,
+    ];
+
+    fn dir_name(self) -> &'static str {
+        match self {
+            DiskCacheKind::Transaction => "tx",
+            DiskCacheKind::Cell => "cell",
+            DiskCacheKind::CellData => "cell_data",
+            DiskCacheKind::Header => "header",
+        }
+    }
+}
+
+/// Store each entry in a file named by the hex of its key, the total size of the files
+/// is capped by `max_bytes`, the oldest written files are removed first when exceeded.
+///
+/// The entries are only written and never updated, only put immutable data (e.g. the
+/// data keyed by its hash) into the cache.
+#[derive(Debug)]
+pub struct DiskCache {
+    root: PathBuf,
+    max_bytes: u64,
+    size: u64,
+}
+
+impl DiskCache {
+    /// Open the cache directory, it is created if not exists.
+    pub fn open<P: AsRef<Path>>(path: P, max_bytes: u64) -> io::Result<DiskCache> {
+        let root = path.as_ref().to_path_buf();
+        for kind in DiskCacheKind::ALL {
+            fs::create_dir_all(root.join(kind.dir_name()))?;
+        }
+        let mut cache = DiskCache {
+            root,
+            max_bytes,
+            size: 0,
+        };
+        cache.size = cache.entries()?.iter().map(|entry| entry.2).sum();
+        cache.gc()?;
+        Ok(cache)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes
+    }
+    /// The total size of the cached entries in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Read the entry, the unreadable entry is treated as missing.
+    pub fn get(&self, kind: DiskCacheKind, key: &[u8]) -> Option<Bytes> {
+        fs::read(self.entry_path(kind, key)).ok().map(Bytes::from)
+    }
+
+    /// Write the entry and remove the oldest entries if the size limit is exceeded, the
+    /// entry larger than the limit is not written.
+    pub fn put(&mut self, kind: DiskCacheKind, key: &[u8], value: &[u8]) -> io::Result<()> {
+        let len = value.len() as u64;
+        let path = self.entry_path(kind, key);
+        if len > self.max_bytes || path.exists() {
+            return Ok(());
+        }
+        // write to a temporary file first, a crash never leaves a partial entry
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, value)?;
+        fs::rename(&tmp_path, &path)?;
+        self.size += len;
+        if self.size > self.max_bytes {
+            self.gc()?;
+        }
+        Ok(())
+    }
+
+    /// Remove the oldest written entries until the total size is within the limit.
+    ///
+    /// Return the number of removed entries.
+    pub fn gc(&mut self) -> io::Result<usize> {
+        if self.size <= self.max_bytes {
+            return Ok(0);
+        }
+        // evict a little more than required, so the next few writes won't scan again
+        let target = self.max_bytes - self.max_bytes / 10;
+        let mut entries = self.entries()?;
+        entries.sort_by_key(|entry| entry.1);
+        let mut removed = 0;
+        for (path, _modified, len) in entries {
+            if self.size <= target {
+                break;
+            }
+            fs::remove_file(path)?;
+            self.size = self.size.saturating_sub(len);
+            removed += 1;
+        }
+        Ok(removed)
+    }
+
+    /// Remove all the entries.
+    pub fn clear(&mut self) -> io::Result<()> {
+        for (path, _modified, _len) in self.entries()? {
+            fs::remove_file(path)?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+
+    fn entry_path(&self, kind: DiskCacheKind, key: &[u8]) -> PathBuf {
+        let name: String = key.iter().map(|byte| format!("{:02x}", byte)).collect();
+        self.root.join(kind.dir_name()).join(name)
+    }
+
+    // (path, modified time, size) of all the entries
+    fn entries(&self) -> io::Result<Vec<(PathBuf, SystemTime, u64)>> {
+        let mut entries = Vec::new();
+        for kind in DiskCacheKind::ALL {
+            for entry in fs::read_dir(self.root.join(kind.dir_name()))? {
+                let entry = entry?;
+                let metadata = entry.metadata()?;
+                if metadata.is_file() {
+                    entries.push((entry.path(), metadata.modified()?, metadata.len()));
+                }
+            }
+        }
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "ckb-sdk-disk-cache-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&path);
+        path
+    }
+
+    #[test]
+    fn test_disk_cache() {
+        let path = temp_dir("basic");
+        let mut cache = DiskCache::open(&path, 100).unwrap();
+        cache
+            .put(DiskCacheKind::Transaction, &[1u8; 32], &[1u8; 40])
+            .unwrap();
+        cache
+            .put(DiskCacheKind::CellData, &[1u8; 36], &[2u8; 40])
+            .unwrap();
+        // too large to be cached
+        cache
+            .put(DiskCacheKind::Header, &[1u8; 32], &[3u8; 101])
+            .unwrap();
+        assert_eq!(cache.size(), 80);
+        assert_eq!(
+            cache.get(DiskCacheKind::Transaction, &[1u8; 32]),
+            Some(Bytes::from(vec![1u8; 40]))
+        );
+        assert_eq!(cache.get(DiskCacheKind::Cell, &[1u8; 36]), None);
+        assert_eq!(cache.get(DiskCacheKind::Header, &[1u8; 32]), None);
+
+        // the entries are kept after reopen
+        drop(cache);
+        let mut cache = DiskCache::open(&path, 100).unwrap();
+        assert_eq!(cache.size(), 80);
+        assert_eq!(
+            cache.get(DiskCacheKind::CellData, &[1u8; 36]),
+            Some(Bytes::from(vec![2u8; 40]))
+        );
+
+        // exceed the limit, the oldest entries are removed
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        cache
+            .put(DiskCacheKind::Header, &[2u8; 32], &[4u8; 40])
+            .unwrap();
+        assert!(cache.size() <= 90);
+        assert!(cache.get(DiskCacheKind::Header, &[2u8; 32]).is_some());
+        let reopened = DiskCache::open(&path, 100).unwrap();
+        assert_eq!(reopened.size(), cache.size());
+
+        cache.clear().unwrap();
+        assert_eq!(cache.size(), 0);
+        assert_eq!(cache.get(DiskCacheKind::Header, &[2u8; 32]), None);
+        fs::remove_dir_all(&path).unwrap();
+    }
+}
@@ -123,14 +123,14 @@ impl TransactionDependencyProvider for LightClientTransactionDependencyProvider
         match self
             .client
             .fetch_transaction(tx_hash.unpack())
-            .map_err(|err| TransactionDependencyError::Other(anyhow!(err)))?
+            .map_err(TransactionDependencyError::Rpc)?
         {
             FetchStatus::Fetched { data } => {
                 if let Some(block_hash) = data.tx_status.block_hash {
                     match self
                         .client
                         .fetch_header(block_hash)
-                        .map_err(|err| TransactionDependencyError::Other(anyhow!(err)))?
+                        .map_err(TransactionDependencyError::Rpc)?
                     {
                         FetchStatus::Fetched { data: header_view } => {
                             let header: HeaderView = header_view.into();
@@ -206,7 +206,7 @@ impl TransactionDependencyProvider for LightClientTransactionDependencyProvider
         match self
             .client
             .fetch_header(block_hash.unpack())
-            .map_err(|err| TransactionDependencyError::Other(anyhow!(err)))?
+            .map_err(TransactionDependencyError::Rpc)?
         {
             FetchStatus::Fetched { data } => {
                 let header: HeaderView = data.into();
@@ -261,7 +261,7 @@ impl CellCollector for LightClientCellCollector {
         let tip_num = self
             .light_client
             .get_tip_header()
-            .map_err(|err| CellCollectorError::Internal(anyhow!(err)))?
+            .map_err(CellCollectorError::Rpc)?
             .inner
             .number
             .value();
@@ -290,7 +290,7 @@ impl CellCollector for LightClientCellCollector {
                 let page = self
                     .light_client
                     .get_cells(search_key.clone(), order.clone(), limit.into(), last_cursor)
-                    .map_err(|err| CellCollectorError::Internal(err.into()))?;
+                    .map_err(CellCollectorError::Rpc)?;
                 if page.objects.is_empty() {
                     break;
                 }
@@ -338,6 +338,10 @@ impl CellCollector for LightClientCellCollector {
     ) -> Result<(), CellCollectorError> {
         self.offchain.lock_cell(out_point, tip_number)
     }
+    fn unlock_cell(&mut self, out_point: OutPoint) -> Result<(), CellCollectorError> {
+        self.offchain.unlock_cell(&out_point);
+        Ok(())
+    }
     fn apply_tx(&mut self, tx: Transaction, tip_number: u64) -> Result<(), CellCollectorError> {
         self.offchain.apply_tx(tx, tip_number)
     }
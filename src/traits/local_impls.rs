@@ -0,0 +1,205 @@
+//! The implementations backed by the local file system
+
+use std::{fs, io, path::Path};
+
+use ckb_jsonrpc_types as json_types;
+use ckb_types::{
+    core::{DepType, ScriptHashType},
+    packed::{CellDep, OutPoint, Script},
+    prelude::*,
+    H256,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{CellDepResolver, OffchainCellDepResolver};
+use crate::types::ScriptId;
+
+/// The description of a deployed script, stored in a `.json` file
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct LocalScriptInfo {
+    pub name: String,
+    pub code_hash: H256,
+    pub hash_type: json_types::ScriptHashType,
+    pub dep_tx_hash: H256,
+    pub dep_index: u32,
+    /// The dep type of the cell dep, the default value is `code`
+    #[serde(default)]
+    pub dep_type: Option<json_types::DepType>,
+}
+
+impl LocalScriptInfo {
+    pub fn script_id(&self) -> ScriptId {
+        ScriptId::new(
+            self.code_hash.clone(),
+            ScriptHashType::from(self.hash_type.clone()),
+        )
+    }
+
+    pub fn cell_dep(&self) -> CellDep {
+        let dep_type = self
+            .dep_type
+            .clone()
+            .map(DepType::from)
+            .unwrap_or(DepType::Code);
+        CellDep::new_builder()
+            .out_point(OutPoint::new(self.dep_tx_hash.pack(), self.dep_index))
+            .dep_type(dep_type.into())
+            .build()
+    }
+}
+
+/// A cell_dep resolver reading the scripts from a directory, each `.json` file in the
+/// directory describes one script as [`LocalScriptInfo`], e.g. the scripts compiled
+/// and deployed to a local dev chain. It can be chained with the
+/// [`DefaultCellDepResolver`](super::DefaultCellDepResolver) by
+/// [`ChainedCellDepResolver`](super::ChainedCellDepResolver).
+#[derive(Default, Clone)]
+pub struct LocalCellDepResolver {
+    offchain: OffchainCellDepResolver,
+}
+
+impl LocalCellDepResolver {
+    /// Scan the `.json` files in the directory (not recursively) in the order of the
+    /// file names, when two files describe the same script the latter one is used.
+    pub fn from_dir(path: &Path) -> Result<LocalCellDepResolver, io::Error> {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(path)? {
+            let path = entry?.path();
+            if path.is_file() && path.extension().map_or(false, |ext| ext == "json") {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        let mut resolver = LocalCellDepResolver::default();
+        for path in paths {
+            let info: LocalScriptInfo =
+                serde_json::from_slice(&fs::read(&path)?).map_err(|err| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("invalid script file {}: {}", path.display(), err),
+                    )
+                })?;
+            resolver.insert(info);
+        }
+        Ok(resolver)
+    }
+
+    pub fn insert(&mut self, info: LocalScriptInfo) -> Option<(CellDep, String)> {
+        self.offchain
+            .items
+            .insert(info.script_id(), (info.cell_dep(), info.name))
+    }
+    pub fn get(&self, script_id: &ScriptId) -> Option<&(CellDep, String)> {
+        self.offchain.items.get(script_id)
+    }
+    pub fn len(&self) -> usize {
+        self.offchain.items.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.offchain.items.is_empty()
+    }
+}
+
+impl CellDepResolver for LocalCellDepResolver {
+    fn resolve(&self, script: &Script) -> Option<CellDep> {
+        self.offchain.resolve(script)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use ckb_types::{core::BlockView, h256};
+
+    use super::*;
+    use crate::{
+        constants::SIGHASH_TYPE_HASH,
+        traits::{ChainedCellDepResolver, DefaultCellDepResolver},
+    };
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "ckb-sdk-local-cell-deps-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_local_cell_dep_resolver() {
+        let dir = temp_dir("resolve");
+        let my_lock = LocalScriptInfo {
+            name: "my-lock".to_string(),
+            code_hash: h256!("0x1234"),
+            hash_type: json_types::ScriptHashType::Data1,
+            dep_tx_hash: h256!("0xabcd"),
+            dep_index: 2,
+            dep_type: None,
+        };
+        fs::write(
+            dir.join("my-lock.json"),
+            serde_json::to_string(&my_lock).unwrap(),
+        )
+        .unwrap();
+        // override the sighash script by a local build
+        fs::write(
+            dir.join("sighash.json"),
+            format!(
+                r#"{{
+                    "name": "local sighash",
+                    "code_hash": "{:#x}",
+                    "hash_type": "type",
+                    "dep_tx_hash": "0x{}",
+                    "dep_index": 0,
+                    "dep_type": "dep_group"
+                }}"#,
+                SIGHASH_TYPE_HASH,
+                "ef".repeat(32)
+            ),
+        )
+        .unwrap();
+        fs::write(dir.join("README.md"), "not a script").unwrap();
+
+        let local = LocalCellDepResolver::from_dir(&dir).unwrap();
+        assert_eq!(local.len(), 2);
+        let my_script = Script::new_builder()
+            .code_hash(my_lock.code_hash.pack())
+            .hash_type(ScriptHashType::Data1.into())
+            .build();
+        let my_dep = CellDep::new_builder()
+            .out_point(OutPoint::new(h256!("0xabcd").pack(), 2))
+            .dep_type(DepType::Code.into())
+            .build();
+        assert_eq!(local.resolve(&my_script), Some(my_dep.clone()));
+
+        let genesis_block: json_types::BlockView =
+            serde_json::from_str(include_str!("../test-data/genesis_block.json")).unwrap();
+        let genesis_block: BlockView = genesis_block.into();
+        let default = DefaultCellDepResolver::from_genesis(&genesis_block).unwrap();
+        let sighash_script = Script::new_builder()
+            .code_hash(SIGHASH_TYPE_HASH.pack())
+            .hash_type(ScriptHashType::Type.into())
+            .build();
+        let local_sighash_dep = local.resolve(&sighash_script).unwrap();
+        let genesis_sighash_dep = default.resolve(&sighash_script).unwrap();
+        assert_ne!(local_sighash_dep, genesis_sighash_dep);
+
+        let chained =
+            ChainedCellDepResolver::new(vec![Box::new(local.clone()), Box::new(default.clone())]);
+        assert_eq!(chained.resolve(&my_script), Some(my_dep));
+        assert_eq!(chained.resolve(&sighash_script), Some(local_sighash_dep));
+        let chained = ChainedCellDepResolver::new(vec![Box::new(default), Box::new(local)]);
+        assert_eq!(chained.resolve(&sighash_script), Some(genesis_sighash_dep));
+        assert_eq!(chained.resolve(&Script::default()), None);
+
+        fs::write(dir.join("broken.json"), "{}").unwrap();
+        let err = LocalCellDepResolver::from_dir(&dir).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
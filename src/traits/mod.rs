@@ -2,27 +2,44 @@
 //!  library code and only implemented the trait in upper level code.
 
 pub mod default_impls;
+#[cfg(feature = "disk-cache")]
+pub mod disk_cache;
 pub mod dummy_impls;
+#[cfg(feature = "rpc")]
 pub mod light_client_impls;
+pub mod local_impls;
 pub mod offchain_impls;
+#[cfg(feature = "rpc")]
+pub mod tip_watcher;
 
+#[cfg(feature = "rpc")]
 pub use default_impls::{
-    DefaultCellCollector, DefaultCellDepResolver, DefaultHeaderDepResolver,
-    DefaultTransactionDependencyProvider, SecpCkbRawKeySigner,
+    DefaultCellCollector, DefaultHeaderDepResolver, DefaultTransactionDependencyProvider,
+    SyncTolerance,
 };
+pub use default_impls::{DefaultCellDepResolver, GenesisInfo, SecpCkbRawKeySigner};
+#[cfg(feature = "rpc")]
 pub use light_client_impls::{
     LightClientCellCollector, LightClientHeaderDepResolver,
     LightClientTransactionDependencyProvider,
 };
+pub use local_impls::{LocalCellDepResolver, LocalScriptInfo};
 pub use offchain_impls::{
     OffchainCellCollector, OffchainCellDepResolver, OffchainHeaderDepResolver,
     OffchainTransactionDependencyProvider,
 };
+#[cfg(all(feature = "rpc", feature = "async"))]
+pub use tip_watcher::watch_tip_subscription;
+#[cfg(feature = "rpc")]
+pub use tip_watcher::{watch_tip, TipWatcher};
+
+use std::str::FromStr;
 
 use dyn_clone::DynClone;
 use thiserror::Error;
 
-use ckb_hash::blake2b_256;
+use anyhow::anyhow;
+use ckb_jsonrpc_types as json_types;
 use ckb_traits::{CellDataProvider, ExtensionProvider, HeaderProvider};
 use ckb_types::{
     bytes::Bytes,
@@ -31,11 +48,16 @@ use ckb_types::{
         error::OutPointError,
         HeaderView, TransactionView,
     },
-    packed::{Byte32, CellDep, CellOutput, OutPoint, Script, Transaction},
+    packed::{Byte32, CellDep, CellInput, CellOutput, OutPoint, Script, Transaction},
     prelude::*,
 };
 
-use crate::{rpc::ckb_indexer::SearchMode, util::is_mature};
+use crate::{
+    rpc::ckb_indexer::SearchMode,
+    tx_builder::BuiltTransaction,
+    util::{ckb_blake2b_256, is_mature},
+    Address, AddressError, RpcError,
+};
 
 /// Signer errors
 #[derive(Error, Debug)]
@@ -82,6 +104,21 @@ pub enum TransactionDependencyError {
     #[error("the resource is not found in the provider: `{0}`")]
     NotFound(String),
 
+    #[error("rpc error: `{0}`")]
+    Rpc(#[from] RpcError),
+
+    #[error("the cell is not live: out_point=`{out_point}`, status=`{status}`")]
+    CellNotLive { out_point: OutPoint, status: String },
+
+    #[error("the transaction is not committed: tx_hash=`{tx_hash}`, status=`{status:?}`")]
+    TxNotCommitted {
+        tx_hash: Byte32,
+        status: json_types::Status,
+    },
+
+    #[error("the method is not supported by the provider: `{0}`")]
+    Unsupported(String),
+
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
@@ -102,12 +139,35 @@ pub trait TransactionDependencyProvider: Sync + Send {
     fn get_cell_data(&self, out_point: &OutPoint) -> Result<Bytes, TransactionDependencyError>;
     /// For get the header information of header_deps
     fn get_header(&self, block_hash: &Byte32) -> Result<HeaderView, TransactionDependencyError>;
+    /// For get the header of the main chain block by number, the default
+    /// implementation returns [`TransactionDependencyError::Unsupported`]
+    fn get_header_by_number(&self, _number: u64) -> Result<HeaderView, TransactionDependencyError> {
+        Err(TransactionDependencyError::Unsupported(
+            "get_header_by_number".to_string(),
+        ))
+    }
 
     /// For get_block_extension
     fn get_block_extension(
         &self,
         block_hash: &Byte32,
     ) -> Result<Option<ckb_types::packed::Bytes>, TransactionDependencyError>;
+
+    /// For get the input cells of the transaction in the inputs order, the default
+    /// implementation calls `get_cell` for each input. A cellbase transaction has no
+    /// input cell.
+    fn get_input_cells(
+        &self,
+        tx: &TransactionView,
+    ) -> Result<Vec<CellOutput>, TransactionDependencyError> {
+        if tx.is_cellbase() {
+            return Ok(Vec::new());
+        }
+        tx.inputs()
+            .into_iter()
+            .map(|input| self.get_cell(&input.previous_output()))
+            .collect()
+    }
 }
 
 // Implement CellDataProvider trait is currently for `DaoCalculator`
@@ -118,7 +178,7 @@ impl CellDataProvider for &dyn TransactionDependencyProvider {
     fn get_cell_data_hash(&self, out_point: &OutPoint) -> Option<Byte32> {
         TransactionDependencyProvider::get_cell_data(*self, out_point)
             .ok()
-            .map(|data| blake2b_256(data.as_ref()).pack())
+            .map(|data| ckb_blake2b_256(data.as_ref()).pack())
     }
 }
 
@@ -174,11 +234,43 @@ pub enum CellCollectorError {
     #[error(transparent)]
     Internal(anyhow::Error),
 
+    #[error("rpc error: `{0}`")]
+    Rpc(#[from] RpcError),
+
+    /// The ckb-indexer is lagging behind the ckb node, `indexer_tip` is `None`
+    /// when the ckb-indexer has not indexed any block. `indexer_version` is reported by
+    /// `get_indexer_info`, it is `None` when the ckb-indexer does not support it.
+    #[error(
+        "ckb-indexer not synced: node_tip=`{node_tip}`, indexer_tip=`{indexer_tip:?}`{}",
+        fmt_indexer_version(.indexer_version)
+    )]
+    IndexerNotSynced {
+        node_tip: u64,
+        indexer_tip: Option<u64>,
+        indexer_version: Option<String>,
+    },
+
+    /// The collected capacity is less than `CellQueryOptions::min_total_capacity`
+    #[error("insufficient capacity: needed=`{needed}`, available=`{available}`")]
+    InsufficientCapacity { needed: u64, available: u64 },
+
     #[error(transparent)]
     Other(anyhow::Error),
 }
 
+fn fmt_indexer_version(indexer_version: &Option<String>) -> String {
+    indexer_version
+        .as_ref()
+        .map(|version| format!(", indexer_version=`{}`", version))
+        .unwrap_or_default()
+}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "JsonLiveCell", into = "JsonLiveCell")
+)]
 pub struct LiveCell {
     pub output: CellOutput,
     pub output_data: Bytes,
@@ -187,6 +279,81 @@ pub struct LiveCell {
     pub tx_index: u32,
 }
 
+// The json form of `LiveCell`, the fields are serialized as in the ckb rpc
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct JsonLiveCell {
+    output: json_types::CellOutput,
+    output_data: json_types::JsonBytes,
+    out_point: json_types::OutPoint,
+    block_number: json_types::BlockNumber,
+    tx_index: json_types::Uint32,
+}
+
+#[cfg(feature = "serde")]
+impl From<JsonLiveCell> for LiveCell {
+    fn from(json: JsonLiveCell) -> LiveCell {
+        LiveCell {
+            output: json.output.into(),
+            output_data: json.output_data.into_bytes(),
+            out_point: json.out_point.into(),
+            block_number: json.block_number.value(),
+            tx_index: json.tx_index.value(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<LiveCell> for JsonLiveCell {
+    fn from(cell: LiveCell) -> JsonLiveCell {
+        JsonLiveCell {
+            output: cell.output.into(),
+            output_data: json_types::JsonBytes::from_bytes(cell.output_data),
+            out_point: cell.out_point.into(),
+            block_number: cell.block_number.into(),
+            tx_index: cell.tx_index.into(),
+        }
+    }
+}
+
+impl LiveCell {
+    /// Create a live cell from the response of `get_live_cell` rpc, the cell data
+    /// must be included in the response (call the rpc with `with_data = true`).
+    pub fn from_rpc(
+        cell_with_status: json_types::CellWithStatus,
+        out_point: OutPoint,
+        block_number: u64,
+        tx_index: u32,
+    ) -> Result<LiveCell, CellCollectorError> {
+        if cell_with_status.status != "live" {
+            return Err(CellCollectorError::Other(anyhow!(
+                "invalid cell status: {}",
+                cell_with_status.status
+            )));
+        }
+        let cell = cell_with_status
+            .cell
+            .ok_or_else(|| CellCollectorError::Other(anyhow!("cell info not found")))?;
+        let output_data = cell
+            .data
+            .ok_or_else(|| CellCollectorError::Other(anyhow!("cell data not included")))?
+            .content
+            .into_bytes();
+        Ok(LiveCell {
+            output: cell.output.into(),
+            output_data,
+            out_point,
+            block_number,
+            tx_index,
+        })
+    }
+
+    /// Build the cell input to spend this cell with the given since.
+    pub fn to_cell_input(&self, since: u64) -> CellInput {
+        CellInput::new(self.out_point.clone(), since)
+    }
+}
+
 /// The value range option: `start <= value < end`
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct ValueRangeOption {
@@ -214,6 +381,66 @@ impl ValueRangeOption {
     }
 }
 
+/// How the script of a `ScriptMatcher` is compared with the script of a cell
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ScriptMatchMode {
+    /// The code hash, the hash type and the args are all equal
+    Exact,
+    /// The code hash and the hash type are equal, the args start with the args of
+    /// the matcher script
+    Prefix,
+}
+
+/// Match the lock script or the type script of a cell
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ScriptMatcher {
+    pub script: Script,
+    pub mode: ScriptMatchMode,
+    /// The length range of the args of the cell script
+    pub args_len_range: Option<ValueRangeOption>,
+}
+impl ScriptMatcher {
+    pub fn new(script: Script, mode: ScriptMatchMode) -> ScriptMatcher {
+        ScriptMatcher {
+            script,
+            mode,
+            args_len_range: None,
+        }
+    }
+    pub fn new_exact(script: Script) -> ScriptMatcher {
+        ScriptMatcher::new(script, ScriptMatchMode::Exact)
+    }
+    pub fn new_prefix(script: Script) -> ScriptMatcher {
+        ScriptMatcher::new(script, ScriptMatchMode::Prefix)
+    }
+    pub fn with_args_len_range(mut self, range: ValueRangeOption) -> ScriptMatcher {
+        self.args_len_range = Some(range);
+        self
+    }
+    pub fn match_script(&self, script: &Script) -> bool {
+        let args = script.args().raw_data();
+        if let Some(range) = self.args_len_range {
+            if !range.match_value(args.len() as u64) {
+                return false;
+            }
+        }
+        match self.mode {
+            ScriptMatchMode::Exact => script == &self.script,
+            ScriptMatchMode::Prefix => {
+                script.code_hash() == self.script.code_hash()
+                    && script.hash_type() == self.script.hash_type()
+                    && args.starts_with(&self.script.args().raw_data())
+            }
+        }
+    }
+    fn search_mode(&self) -> SearchMode {
+        match self.mode {
+            ScriptMatchMode::Exact => SearchMode::Exact,
+            ScriptMatchMode::Prefix => SearchMode::Prefix,
+        }
+    }
+}
+
 /// The primary serach script type
 ///   * if primary script type is `lock` then secondary script type is `type`
 ///   * if primary script type is `type` then secondary script type is `lock`
@@ -243,6 +470,10 @@ pub struct CellQueryOptions {
 
     // Options for SearchKeyFilter
     pub secondary_script: Option<Script>,
+    /// Match the secondary script by the prefix of its args (e.g. the xUDT cells issued
+    /// by an owner), the code hash and the hash type are taken from `secondary_script`
+    /// and its args are ignored. It has no effect if `secondary_script` is `None`.
+    pub filter_script_args_prefix: Option<Bytes>,
     pub secondary_script_len_range: Option<ValueRangeOption>,
     pub data_len_range: Option<ValueRangeOption>,
     pub capacity_range: Option<ValueRangeOption>,
@@ -257,6 +488,12 @@ pub struct CellQueryOptions {
     /// collect only one cell at most.
     pub min_total_capacity: u64,
     pub script_search_mode: Option<SearchMode>,
+
+    /// The lock script constraint, checked in addition to the primary and the
+    /// secondary script
+    pub lock: Option<ScriptMatcher>,
+    /// The type script constraint, a cell without type script never matches it
+    pub type_: Option<ScriptMatcher>,
 }
 impl CellQueryOptions {
     pub fn new(primary_script: Script, primary_type: PrimaryScriptType) -> CellQueryOptions {
@@ -264,6 +501,7 @@ impl CellQueryOptions {
             primary_script,
             primary_type,
             secondary_script: None,
+            filter_script_args_prefix: None,
             secondary_script_len_range: None,
             data_len_range: None,
             capacity_range: None,
@@ -274,14 +512,74 @@ impl CellQueryOptions {
             maturity: MaturityOption::Mature,
             min_total_capacity: 1,
             script_search_mode: None,
+            lock: None,
+            type_: None,
         }
     }
+    /// Query the cells matching both the lock matcher and the type matcher. The lock
+    /// side is the primary script if it is given, otherwise the type side, the other
+    /// side is used as the secondary script filter. Return `None` if both are `None`.
+    pub fn new_with_matchers(
+        lock: Option<ScriptMatcher>,
+        type_: Option<ScriptMatcher>,
+    ) -> Option<CellQueryOptions> {
+        let (primary, primary_type) = match (lock.as_ref(), type_.as_ref()) {
+            (Some(lock), _) => (lock, PrimaryScriptType::Lock),
+            (None, Some(type_)) => (type_, PrimaryScriptType::Type),
+            (None, None) => return None,
+        };
+        let mut query = CellQueryOptions::new(primary.script.clone(), primary_type);
+        query.script_search_mode = Some(primary.search_mode());
+        query.lock = lock;
+        query.type_ = type_;
+        Some(query)
+    }
     pub fn new_lock(primary_script: Script) -> CellQueryOptions {
         CellQueryOptions::new(primary_script, PrimaryScriptType::Lock)
     }
     pub fn new_type(primary_script: Script) -> CellQueryOptions {
         CellQueryOptions::new(primary_script, PrimaryScriptType::Type)
     }
+    /// Query all the cells locked by the address, the address is parsed from the
+    /// string. The `min_total_capacity` is `u64::MAX` so the collector never stops
+    /// before all the cells are collected.
+    pub fn for_address(address: &str) -> Result<CellQueryOptions, AddressError> {
+        let address = Address::from_str(address).map_err(AddressError::Invalid)?;
+        let mut query = CellQueryOptions::new_lock(Script::from(&address));
+        query.min_total_capacity = u64::MAX;
+        Ok(query)
+    }
+    fn secondary_matcher(&self) -> Option<&ScriptMatcher> {
+        match self.primary_type {
+            PrimaryScriptType::Lock => self.type_.as_ref(),
+            PrimaryScriptType::Type => self.lock.as_ref(),
+        }
+    }
+    /// The secondary script used as the prefix filter, the args of `secondary_script`
+    /// are replaced by `filter_script_args_prefix` if it is set. The script of the
+    /// secondary side matcher is used if `secondary_script` is `None`.
+    pub fn secondary_script_filter(&self) -> Option<Script> {
+        let script = match self.secondary_script.as_ref() {
+            Some(script) => script,
+            None => &self.secondary_matcher()?.script,
+        };
+        match self.filter_script_args_prefix.as_ref() {
+            Some(prefix) => Some(script.clone().as_builder().args(prefix.pack()).build()),
+            None => Some(script.clone()),
+        }
+    }
+    /// The length range of the secondary script (code hash + hash type + args), taken
+    /// from the args length range of the secondary side matcher if
+    /// `secondary_script_len_range` is `None`.
+    pub fn secondary_script_len_filter(&self) -> Option<ValueRangeOption> {
+        self.secondary_script_len_range.or_else(|| {
+            let range = self.secondary_matcher()?.args_len_range?;
+            Some(ValueRangeOption::new(
+                range.start.saturating_add(33),
+                range.end.saturating_add(33),
+            ))
+        })
+    }
     pub fn match_cell(&self, cell: &LiveCell, max_mature_number: u64) -> bool {
         fn extract_raw_data(script: &Script) -> Vec<u8> {
             [
@@ -291,17 +589,24 @@ impl CellQueryOptions {
             ]
             .concat()
         }
-        let filter_prefix = self.secondary_script.as_ref().map(|script| {
-            if script != &Script::default() {
-                extract_raw_data(script)
+        let filter_prefix = self.secondary_script_filter().map(|script| {
+            if script != Script::default() {
+                extract_raw_data(&script)
             } else {
                 Vec::new()
             }
         });
+        let match_primary = |script: &Script| {
+            if self.script_search_mode == Some(SearchMode::Prefix) {
+                extract_raw_data(script).starts_with(&extract_raw_data(&self.primary_script))
+            } else {
+                script == &self.primary_script
+            }
+        };
         match self.primary_type {
             PrimaryScriptType::Lock => {
                 // check primary script
-                if cell.output.lock() != self.primary_script {
+                if !match_primary(&cell.output.lock()) {
                     return false;
                 }
 
@@ -326,7 +631,13 @@ impl CellQueryOptions {
             }
             PrimaryScriptType::Type => {
                 // check primary script
-                if cell.output.type_().to_opt().as_ref() != Some(&self.primary_script) {
+                if !cell
+                    .output
+                    .type_()
+                    .to_opt()
+                    .as_ref()
+                    .map_or(false, match_primary)
+                {
                     return false;
                 }
 
@@ -360,6 +671,18 @@ impl CellQueryOptions {
             }
         }
 
+        if let Some(matcher) = self.lock.as_ref() {
+            if !matcher.match_script(&cell.output.lock()) {
+                return false;
+            }
+        }
+        if let Some(matcher) = self.type_.as_ref() {
+            match cell.output.type_().to_opt() {
+                Some(script) if matcher.match_script(&script) => {}
+                _ => return false,
+            }
+        }
+
         if let Some(range) = self.data_len_range {
             if !range.match_value(cell.output_data.len() as u64) {
                 return false;
@@ -393,12 +716,39 @@ pub trait CellCollector: DynClone {
         apply_changes: bool,
     ) -> Result<(Vec<LiveCell>, u64), CellCollectorError>;
 
+    /// Same as `collect_live_cells`, but return `InsufficientCapacity` error if the
+    /// collected capacity is less than `query.min_total_capacity`, the collected cells
+    /// are unlocked again in that case.
+    fn collect_exactly(
+        &mut self,
+        query: &CellQueryOptions,
+        apply_changes: bool,
+    ) -> Result<(Vec<LiveCell>, u64), CellCollectorError> {
+        let (cells, total_capacity) = self.collect_live_cells(query, apply_changes)?;
+        if total_capacity < query.min_total_capacity {
+            if apply_changes {
+                for cell in cells {
+                    self.unlock_cell(cell.out_point)?;
+                }
+            }
+            return Err(CellCollectorError::InsufficientCapacity {
+                needed: query.min_total_capacity,
+                available: total_capacity,
+            });
+        }
+        Ok((cells, total_capacity))
+    }
+
     /// Mark this cell as dead cell
     fn lock_cell(
         &mut self,
         out_point: OutPoint,
         tip_block_number: u64,
     ) -> Result<(), CellCollectorError>;
+    /// Mark this cell as live cell again, the default implementation does nothing
+    fn unlock_cell(&mut self, _out_point: OutPoint) -> Result<(), CellCollectorError> {
+        Ok(())
+    }
     /// Mark all inputs as dead cells and outputs as live cells in the transaction.
     fn apply_tx(
         &mut self,
@@ -408,6 +758,16 @@ pub trait CellCollector: DynClone {
 
     /// Clear cache and locked cells
     fn reset(&mut self);
+
+    /// Same as `apply_tx`, but use the transaction returned by the builder directly,
+    /// so the offchain states can't drift apart from the builder output.
+    fn apply_built_tx(
+        &mut self,
+        built_tx: &BuiltTransaction,
+        tip_block_number: u64,
+    ) -> Result<(), CellCollectorError> {
+        self.apply_tx(built_tx.tx.data(), tip_block_number)
+    }
 }
 
 pub trait CellDepResolver {
@@ -416,12 +776,45 @@ pub trait CellDepResolver {
     /// When a new script is added, transaction builders use CellDepResolver to find the corresponding cell deps and add them to the transaction.
     fn resolve(&self, script: &Script) -> Option<CellDep>;
 }
+
+/// A cell_dep resolver trying the resolvers in order, the first resolved cell dep
+/// is used, e.g. the local scripts before the system scripts.
+#[derive(Default)]
+pub struct ChainedCellDepResolver {
+    resolvers: Vec<Box<dyn CellDepResolver>>,
+}
+
+impl ChainedCellDepResolver {
+    pub fn new(resolvers: Vec<Box<dyn CellDepResolver>>) -> ChainedCellDepResolver {
+        ChainedCellDepResolver { resolvers }
+    }
+
+    /// Append a resolver with the lowest priority
+    pub fn push(&mut self, resolver: Box<dyn CellDepResolver>) {
+        self.resolvers.push(resolver);
+    }
+}
+
+impl CellDepResolver for ChainedCellDepResolver {
+    fn resolve(&self, script: &Script) -> Option<CellDep> {
+        self.resolvers
+            .iter()
+            .find_map(|resolver| resolver.resolve(script))
+    }
+}
 pub trait HeaderDepResolver {
     /// Resolve header dep by trancation hash
     fn resolve_by_tx(&self, tx_hash: &Byte32) -> Result<Option<HeaderView>, anyhow::Error>;
 
     /// Resolve header dep by block number
     fn resolve_by_number(&self, number: u64) -> Result<Option<HeaderView>, anyhow::Error>;
+
+    /// Resolve the header of the current tip block, it's used to check the since of the
+    /// inputs before building the transaction. The default implementation returns `None`
+    /// for the resolvers not knowing the tip, then the since is not checked.
+    fn resolve_tip(&self) -> Result<Option<HeaderView>, anyhow::Error> {
+        Ok(None)
+    }
 }
 
 // test cases make sure new added exception won't breadk `anyhow!(e_variable)` usage,
@@ -471,5 +864,204 @@ mod anyhow_tests {
         let error = CellCollectorError::Other(anyhow!("Other"));
         let error = anyhow!(error);
         assert_eq!("Other", error.to_string());
+
+        let error = CellCollectorError::IndexerNotSynced {
+            node_tip: 100,
+            indexer_tip: Some(90),
+            indexer_version: None,
+        };
+        assert_eq!(
+            "ckb-indexer not synced: node_tip=`100`, indexer_tip=`Some(90)`",
+            error.to_string()
+        );
+        let error = CellCollectorError::IndexerNotSynced {
+            node_tip: 100,
+            indexer_tip: None,
+            indexer_version: Some("0.4.1".to_string()),
+        };
+        assert_eq!(
+            "ckb-indexer not synced: node_tip=`100`, indexer_tip=`None`, indexer_version=`0.4.1`",
+            error.to_string()
+        );
+
+        let error = CellCollectorError::InsufficientCapacity {
+            needed: 100,
+            available: 60,
+        };
+        assert_eq!(
+            "insufficient capacity: needed=`100`, available=`60`",
+            error.to_string()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::random_out_point;
+    use ckb_types::{core::ScriptHashType, h256};
+
+    #[test]
+    fn test_live_cell_from_rpc() {
+        let output = CellOutput::new_builder().capacity(100u64.pack()).build();
+        let data = Bytes::from(vec![1u8, 2, 3]);
+        let cell_with_status = json_types::CellWithStatus {
+            cell: Some(json_types::CellInfo {
+                output: output.clone().into(),
+                data: Some(json_types::CellData {
+                    content: json_types::JsonBytes::from_bytes(data.clone()),
+                    hash: CellOutput::calc_data_hash(&data).unpack(),
+                }),
+            }),
+            status: "live".to_string(),
+        };
+        let out_point = random_out_point();
+
+        let cell = LiveCell::from_rpc(cell_with_status.clone(), out_point.clone(), 10, 1).unwrap();
+        assert_eq!(cell.output, output);
+        assert_eq!(cell.output_data, data);
+        assert_eq!(cell.out_point, out_point);
+        assert_eq!(cell.block_number, 10);
+        assert_eq!(cell.tx_index, 1);
+        let input = cell.to_cell_input(42);
+        assert_eq!(input.previous_output(), out_point);
+        assert_eq!(Unpack::<u64>::unpack(&input.since()), 42);
+
+        let mut dead_cell = cell_with_status.clone();
+        dead_cell.status = "dead".to_string();
+        assert!(LiveCell::from_rpc(dead_cell, out_point.clone(), 10, 1).is_err());
+        let mut no_data = cell_with_status;
+        no_data.cell.as_mut().unwrap().data = None;
+        assert!(LiveCell::from_rpc(no_data, out_point, 10, 1).is_err());
+    }
+
+    #[test]
+    fn test_filter_script_args_prefix() {
+        let owner = Script::new_builder().args(vec![1u8; 20].pack()).build();
+        let build_cell = |args: Vec<u8>| {
+            let xudt = Script::new_builder()
+                .code_hash(h256!("0x1234").pack())
+                .hash_type(ScriptHashType::Type.into())
+                .args(args.pack())
+                .build();
+            LiveCell {
+                output: CellOutput::new_builder()
+                    .lock(owner.clone())
+                    .type_(Some(xudt).pack())
+                    .build(),
+                output_data: Bytes::new(),
+                out_point: OutPoint::default(),
+                block_number: 0,
+                tx_index: 0,
+            }
+        };
+        let issuer = [2u8; 32];
+        let token1 = build_cell([&issuer[..], &[0u8; 4]].concat());
+        let token2 = build_cell([3u8; 36].to_vec());
+
+        let mut query = CellQueryOptions::new_lock(owner);
+        query.secondary_script = token2.output.type_().to_opt();
+        query.filter_script_args_prefix = Some(Bytes::from(issuer.to_vec()));
+        assert!(query.match_cell(&token1, 0));
+        assert!(!query.match_cell(&token2, 0));
+        assert_eq!(
+            query.secondary_script_filter().unwrap().args().raw_data(),
+            Bytes::from(issuer.to_vec())
+        );
+
+        query.filter_script_args_prefix = None;
+        assert!(!query.match_cell(&token1, 0));
+        assert!(query.match_cell(&token2, 0));
+    }
+
+    #[test]
+    fn test_query_with_matchers() {
+        let owner = Script::new_builder().args(vec![1u8; 20].pack()).build();
+        let udt = Script::new_builder()
+            .code_hash(h256!("0x1234").pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(vec![2u8; 32].pack())
+            .build();
+        let build_cell = |lock: Script, type_: Option<Script>, data_len: usize| LiveCell {
+            output: CellOutput::new_builder()
+                .lock(lock)
+                .type_(type_.pack())
+                .build(),
+            output_data: Bytes::from(vec![0u8; data_len]),
+            out_point: OutPoint::default(),
+            block_number: 0,
+            tx_index: 0,
+        };
+        let other_udt = udt.clone().as_builder().args(vec![3u8; 32].pack()).build();
+        let longer_owner = owner
+            .clone()
+            .as_builder()
+            .args(vec![1u8; 28].pack())
+            .build();
+
+        assert!(CellQueryOptions::new_with_matchers(None, None).is_none());
+        let mut query = CellQueryOptions::new_with_matchers(
+            Some(ScriptMatcher::new_exact(owner.clone())),
+            Some(ScriptMatcher::new_exact(udt.clone())),
+        )
+        .unwrap();
+        query.data_len_range = Some(ValueRangeOption::new_exact(16));
+        assert_eq!(query.primary_type, PrimaryScriptType::Lock);
+        assert_eq!(query.primary_script, owner);
+        assert_eq!(query.script_search_mode, Some(SearchMode::Exact));
+        assert_eq!(query.secondary_script_filter(), Some(udt.clone()));
+        assert!(query.match_cell(&build_cell(owner.clone(), Some(udt.clone()), 16), 0));
+        assert!(!query.match_cell(&build_cell(owner.clone(), Some(udt.clone()), 8), 0));
+        assert!(!query.match_cell(&build_cell(owner.clone(), Some(other_udt.clone()), 16), 0));
+        assert!(!query.match_cell(&build_cell(owner.clone(), None, 16), 0));
+        assert!(!query.match_cell(&build_cell(longer_owner.clone(), Some(udt.clone()), 16), 0));
+
+        // the type side is primary without lock matcher
+        let prefix = udt.clone().as_builder().args(vec![2u8; 4].pack()).build();
+        let query = CellQueryOptions::new_with_matchers(
+            None,
+            Some(
+                ScriptMatcher::new_prefix(prefix.clone())
+                    .with_args_len_range(ValueRangeOption::new_exact(32)),
+            ),
+        )
+        .unwrap();
+        assert_eq!(query.primary_type, PrimaryScriptType::Type);
+        assert_eq!(query.primary_script, prefix);
+        assert_eq!(query.script_search_mode, Some(SearchMode::Prefix));
+        assert!(query.secondary_script_filter().is_none());
+        assert!(query.match_cell(&build_cell(longer_owner.clone(), Some(udt.clone()), 0), 0));
+        assert!(!query.match_cell(&build_cell(owner.clone(), Some(other_udt), 0), 0));
+        let long_udt = udt.as_builder().args(vec![2u8; 36].pack()).build();
+        assert!(!query.match_cell(&build_cell(owner.clone(), Some(long_udt), 0), 0));
+
+        // the lock matcher on the secondary side goes to the search key filter
+        let lock_prefix = owner.as_builder().args(Bytes::new().pack()).build();
+        let mut query = CellQueryOptions::new_type(prefix);
+        query.lock = Some(
+            ScriptMatcher::new_prefix(lock_prefix.clone())
+                .with_args_len_range(ValueRangeOption::new(20, 29)),
+        );
+        assert_eq!(query.secondary_script_filter(), Some(lock_prefix));
+        assert_eq!(
+            query.secondary_script_len_filter(),
+            Some(ValueRangeOption::new(53, 62))
+        );
+    }
+
+    #[test]
+    fn test_query_for_address() {
+        let address = "ckt1qzda0cr08m85hc8jlnfp3zer7xulejywt49kt2rr0vthywaa50xwsqwgx292hnvmn68xf779vmzrshpmm6epn4c0cgwga";
+        let query = CellQueryOptions::for_address(address).unwrap();
+        assert_eq!(
+            query.primary_script,
+            Script::from(&Address::from_str(address).unwrap())
+        );
+        assert_eq!(query.primary_type, PrimaryScriptType::Lock);
+        assert_eq!(query.min_total_capacity, u64::MAX);
+        assert!(matches!(
+            CellQueryOptions::for_address("ckt1invalid"),
+            Err(AddressError::Invalid(_))
+        ));
     }
 }
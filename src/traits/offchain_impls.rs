@@ -47,6 +47,10 @@ impl HeaderDepResolver for OffchainHeaderDepResolver {
     }
 }
 
+/// The cells locked by an applied transaction and the tip block number when it's applied
+pub type AppliedTx = (Vec<(H256, u32)>, u64);
+
+#[cfg_attr(not(feature = "rpc"), allow(dead_code))]
 const KEEP_BLOCK_PERIOD: u64 = 13;
 /// A cell collector only use offchain data
 #[derive(Default, Clone)]
@@ -56,14 +60,25 @@ pub struct OffchainCellCollector {
     // (live_cell, tip_block_number)
     pub live_cells: Vec<(LiveCell, u64)>,
     pub max_mature_number: u64,
+    // tx_hash => (cells locked by the applied transaction, tip_block_number), the
+    // cells locked by `lock_cell` before the transaction applied are not included
+    applied_txs: HashMap<H256, AppliedTx>,
 }
 
+#[cfg_attr(not(feature = "rpc"), allow(dead_code))]
 pub(crate) struct CollectResult {
     pub(crate) cells: Vec<(LiveCell, u64)>,
     pub(crate) rest_cells: Vec<(LiveCell, u64)>,
     pub(crate) total_capacity: u64,
 }
+#[cfg_attr(not(feature = "rpc"), allow(dead_code))]
 impl OffchainCellCollector {
+    /// The applied transactions by tx_hash, the cells locked by `lock_cell` before
+    /// the transaction applied are not included.
+    pub fn applied_txs(&self) -> &HashMap<H256, AppliedTx> {
+        &self.applied_txs
+    }
+
     fn truncate(&mut self, current_tip_block_number: u64) {
         self.live_cells = self
             .live_cells
@@ -83,6 +98,10 @@ impl OffchainCellCollector {
                     || (current_tip_block_number - block_num) <= KEEP_BLOCK_PERIOD
             })
             .collect();
+        self.applied_txs.retain(|_tx_hash, (_locked, block_num)| {
+            *block_num >= current_tip_block_number
+                || (current_tip_block_number - *block_num) <= KEEP_BLOCK_PERIOD
+        });
     }
 
     pub(crate) fn collect(
@@ -125,16 +144,108 @@ impl OffchainCellCollector {
         );
         Ok(())
     }
+
+    fn is_locked_by_applied_tx(&self, key: &(H256, u32)) -> bool {
+        self.applied_txs
+            .values()
+            .any(|(locked, _)| locked.contains(key))
+    }
+
+    /// Mark the cell as live again, return if the cell was locked.
+    pub(crate) fn unlock_cell(&mut self, out_point: &OutPoint) -> bool {
+        self.locked_cells
+            .remove(&(out_point.tx_hash().unpack(), out_point.index().unpack()))
+            .is_some()
+    }
+
+    /// Revert the changes of an applied transaction: unlock the cells locked by
+    /// it and remove its outputs from the live cells. The cells already locked
+    /// before the transaction applied are kept locked.
+    ///
+    /// Return false if the transaction is not applied.
+    pub(crate) fn rollback_tx(&mut self, tx_hash: &Byte32) -> bool {
+        let tx_hash_h256: H256 = tx_hash.unpack();
+        let applied = self.applied_txs.remove(&tx_hash_h256);
+        let outputs_count = self.live_cells.len();
+        self.live_cells
+            .retain(|(cell, _)| &cell.out_point.tx_hash() != tx_hash);
+        let removed_outputs = outputs_count != self.live_cells.len();
+        if let Some((locked, _)) = applied.as_ref() {
+            for key in locked {
+                // the cell is still spent by other applied transaction
+                if !self.is_locked_by_applied_tx(key) {
+                    self.locked_cells.remove(key);
+                }
+            }
+        }
+        applied.is_some() || removed_outputs
+    }
+
+    /// Forget a committed transaction: its outputs are removed from the live cells
+    /// since they can be collected from the indexer, the input cells are kept locked
+    /// until they expire.
+    ///
+    /// Return false if the transaction is not applied.
+    pub(crate) fn forget_tx(&mut self, tx_hash: &Byte32) -> bool {
+        let applied = self.applied_txs.remove(&tx_hash.unpack()).is_some();
+        let outputs_count = self.live_cells.len();
+        self.live_cells
+            .retain(|(cell, _)| &cell.out_point.tx_hash() != tx_hash);
+        applied || outputs_count != self.live_cells.len()
+    }
+
+    /// Unlock the input cells of a failed transaction: the changes of `apply_tx` for it
+    /// are reverted, and the inputs locked by `collect_live_cells` are unlocked unless
+    /// they are spent by other applied transactions. Return the number of unlocked cells.
+    pub(crate) fn recover_tx_inputs(&mut self, tx: &TransactionView) -> usize {
+        let locked_count = self.locked_cells.len();
+        self.rollback_tx(&tx.hash());
+        for out_point in tx.input_pts_iter() {
+            let key = (out_point.tx_hash().unpack(), out_point.index().unpack());
+            if !self.is_locked_by_applied_tx(&key) {
+                self.locked_cells.remove(&key);
+            }
+        }
+        locked_count - self.locked_cells.len()
+    }
+
     pub(crate) fn apply_tx(
         &mut self,
         tx: Transaction,
         tip_blocknumber: u64,
     ) -> Result<(), CellCollectorError> {
         let tx_view = tx.into_view();
-        let tx_hash = tx_view.hash();
+        self.apply_tx_inputs(&tx_view, tip_blocknumber)?;
+        self.apply_tx_outputs(&tx_view, tip_blocknumber)
+    }
+
+    /// Mark all inputs of the transaction as dead cells.
+    pub(crate) fn apply_tx_inputs(
+        &mut self,
+        tx_view: &TransactionView,
+        tip_blocknumber: u64,
+    ) -> Result<(), CellCollectorError> {
+        let mut newly_locked = Vec::new();
         for out_point in tx_view.input_pts_iter() {
+            let key = (out_point.tx_hash().unpack(), out_point.index().unpack());
+            if !self.locked_cells.contains_key(&key) || self.is_locked_by_applied_tx(&key) {
+                newly_locked.push(key);
+            }
             self.lock_cell(out_point, tip_blocknumber)?;
         }
+        let (locked, block_num) = self.applied_txs.entry(tx_view.hash().unpack()).or_default();
+        locked.extend(newly_locked);
+        *block_num = tip_blocknumber;
+        Ok(())
+    }
+
+    /// Add all outputs of the transaction as live cells.
+    pub(crate) fn apply_tx_outputs(
+        &mut self,
+        tx_view: &TransactionView,
+        tip_blocknumber: u64,
+    ) -> Result<(), CellCollectorError> {
+        let tx_hash = tx_view.hash();
         for (output_index, (output, data)) in tx_view.outputs_with_data_iter().enumerate() {
             let out_point = OutPoint::new(tx_hash.clone(), output_index as u32);
             let info = LiveCell {
@@ -152,6 +263,7 @@ impl OffchainCellCollector {
     pub(crate) fn reset(&mut self) {
         self.locked_cells.clear();
         self.live_cells.clear();
+        self.applied_txs.clear();
     }
 }
 
@@ -163,6 +275,7 @@ pub struct OffchainTransactionDependencyProvider {
     pub cells: HashMap<(H256, u32), (CellOutput, Bytes)>,
 }
 
+#[cfg_attr(not(feature = "rpc"), allow(dead_code))]
 impl OffchainTransactionDependencyProvider {
     /// create a new OffchainTransactionDependencyProvider
     pub(crate) fn new() -> Self {
@@ -0,0 +1,311 @@
+use std::collections::HashSet;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use ckb_types::{core::HeaderView, packed::Byte32};
+use parking_lot::Mutex;
+
+use super::default_impls::is_tx_committed;
+use super::{DefaultCellCollector, DefaultTransactionDependencyProvider};
+use crate::rpc::{CkbRpcClient, RpcError};
+use crate::util::get_max_mature_number_inner;
+
+/// The handle of the background tip watching thread, see [`watch_tip`].
+pub struct TipWatcher {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl TipWatcher {
+    /// Check if the watching thread is still running.
+    pub fn is_running(&self) -> bool {
+        self.handle
+            .as_ref()
+            .map(|handle| !handle.is_finished())
+            .unwrap_or(false)
+    }
+
+    /// Stop the watching thread and wait for it to exit.
+    pub fn stop(mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for TipWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Refresh the default providers on each new tip. The shared collector is only locked
+/// to read and update its state, the rpc calls are made without holding the lock.
+struct TipRefresher {
+    ckb_client: CkbRpcClient,
+    provider: DefaultTransactionDependencyProvider,
+    collector: Option<Arc<Mutex<DefaultCellCollector>>>,
+    last_tip_hash: Option<Byte32>,
+}
+
+impl TipRefresher {
+    fn new(
+        ckb_client: &str,
+        provider: DefaultTransactionDependencyProvider,
+        collector: Option<Arc<Mutex<DefaultCellCollector>>>,
+    ) -> TipRefresher {
+        TipRefresher {
+            ckb_client: CkbRpcClient::new(ckb_client),
+            provider,
+            collector,
+            last_tip_hash: None,
+        }
+    }
+
+    fn on_new_tip(&mut self, tip_header: &HeaderView) {
+        if self.last_tip_hash.as_ref() == Some(&tip_header.hash()) {
+            return;
+        }
+        if let Err(err) = self.provider.on_new_tip(tip_header) {
+            log::warn!("refresh transaction dependency provider failed: {}", err);
+        }
+        if let Some(collector) = self.collector.as_ref() {
+            if let Err(err) = self.refresh_collector(collector, tip_header) {
+                log::warn!("refresh cell collector failed: {}", err);
+            }
+        }
+        self.last_tip_hash = Some(tip_header.hash());
+    }
+
+    // Same as `DefaultCellCollector::on_new_tip`, except the lock is released during the rpc calls
+    fn refresh_collector(
+        &self,
+        collector: &Mutex<DefaultCellCollector>,
+        tip_header: &HeaderView,
+    ) -> Result<usize, RpcError> {
+        let tx_hashes = collector.lock().offchain_tx_hashes();
+        let max_mature_number = get_max_mature_number_inner(&self.ckb_client)?;
+        #[allow(clippy::mutable_key_type)]
+        let mut committed_tx_hashes = HashSet::new();
+        for tx_hash in tx_hashes {
+            if is_tx_committed(&self.ckb_client, &tx_hash)? {
+                committed_tx_hashes.insert(tx_hash);
+            }
+        }
+        Ok(collector.lock().prune_offchain_cells(
+            tip_header,
+            max_mature_number,
+            &committed_tx_hashes,
+        ))
+    }
+}
+
+/// Spawn a background thread to watch the tip header of the ckb node, on each new
+/// tip the caches of `provider` and the offchain state of `collector` are refreshed
+/// (see `DefaultTransactionDependencyProvider::on_new_tip` and
+/// `DefaultCellCollector::on_new_tip`).
+///
+/// The tip is polled every `poll_interval`, use [`watch_tip_subscription`] (with the
+/// `async` feature) to be notified by the `new_tip_header` subscription instead.
+pub fn watch_tip(
+    ckb_client: &str,
+    provider: DefaultTransactionDependencyProvider,
+    collector: Option<Arc<Mutex<DefaultCellCollector>>>,
+    poll_interval: Duration,
+) -> TipWatcher {
+    let ckb_client = ckb_client.to_string();
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_flag = Arc::clone(&stop);
+    let handle = thread::spawn(move || {
+        let mut refresher = TipRefresher::new(&ckb_client, provider, collector);
+        while !stop_flag.load(Ordering::SeqCst) {
+            match refresher.ckb_client.get_tip_header() {
+                Ok(tip_header) => refresher.on_new_tip(&HeaderView::from(tip_header)),
+                Err(err) => log::warn!("get tip header failed: {}", err),
+            }
+            // sleep in small steps, so the thread can be stopped quickly
+            let mut slept = Duration::from_millis(0);
+            while slept < poll_interval && !stop_flag.load(Ordering::SeqCst) {
+                let step = Duration::from_millis(50).min(poll_interval - slept);
+                thread::sleep(step);
+                slept += step;
+            }
+        }
+    });
+    TipWatcher {
+        stop,
+        handle: Some(handle),
+    }
+}
+
+/// Refresh `provider` and `collector` on each new tip header pushed by the subscription
+/// `handle` (subscribed to the `new_tip_header` topic, see [`crate::pubsub`]), until the
+/// subscription is closed. Spawn the returned future on the tokio runtime to run it in
+/// background, and abort the task to stop it.
+///
+/// The blocking rpc calls of the refreshing are made by `tokio::task::spawn_blocking`.
+#[cfg(feature = "async")]
+pub async fn watch_tip_subscription<T>(
+    mut handle: crate::pubsub::Handle<T, ckb_jsonrpc_types::HeaderView>,
+    ckb_client: &str,
+    provider: DefaultTransactionDependencyProvider,
+    collector: Option<Arc<Mutex<DefaultCellCollector>>>,
+) -> std::io::Result<()>
+where
+    T: tokio::io::AsyncWrite + tokio::io::AsyncRead + Unpin,
+{
+    use futures::StreamExt;
+
+    let join_err = |err| std::io::Error::new(std::io::ErrorKind::Other, err);
+    let ckb_client = ckb_client.to_string();
+    // the blocking rpc client can not be created or dropped in the async context
+    let mut refresher =
+        tokio::task::spawn_blocking(move || TipRefresher::new(&ckb_client, provider, collector))
+            .await
+            .map_err(join_err)?;
+    let mut result = Ok(());
+    while let Some(item) = handle.next().await {
+        let tip_header = match item {
+            Ok((_topic, tip_header)) => HeaderView::from(tip_header),
+            Err(err) => {
+                result = Err(err);
+                break;
+            }
+        };
+        refresher = tokio::task::spawn_blocking(move || {
+            refresher.on_new_tip(&tip_header);
+            refresher
+        })
+        .await
+        .map_err(join_err)?;
+    }
+    tokio::task::spawn_blocking(move || drop(refresher))
+        .await
+        .map_err(join_err)?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::MockRpcResult;
+    use ckb_jsonrpc_types as json_types;
+    use ckb_types::{
+        bytes::Bytes,
+        core::EpochNumberWithFraction,
+        packed::{CellInput, CellOutput, Transaction},
+        prelude::*,
+    };
+    use httpmock::prelude::*;
+    use std::time::Instant;
+
+    fn tip_header() -> HeaderView {
+        HeaderView::new_advanced_builder()
+            .number(100.pack())
+            .epoch(EpochNumberWithFraction::new(1, 0, 1000).full_value().pack())
+            .build()
+    }
+
+    fn mock_node(server: &MockServer, tx_status: json_types::Status) -> httpmock::Mock {
+        let consensus: json_types::Consensus =
+            ckb_chain_spec::consensus::ConsensusBuilder::default()
+                .build()
+                .into();
+        server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("get_consensus");
+            then.status(200)
+                .body(MockRpcResult::new(consensus).to_json());
+        });
+        let tip_header: json_types::HeaderView = tip_header().into();
+        server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("get_tip_header");
+            then.status(200)
+                .body(MockRpcResult::new(tip_header).to_json());
+        });
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("get_tip_block_number");
+            then.status(200)
+                .body(MockRpcResult::new(json_types::BlockNumber::from(100u64)).to_json());
+        });
+        let status = json_types::TxStatus {
+            status: tx_status,
+            block_number: None,
+            block_hash: None,
+            reason: None,
+        };
+        server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("get_transaction");
+            then.status(200).body(
+                MockRpcResult::new(json_types::TransactionWithStatusResponse {
+                    transaction: None,
+                    cycles: None,
+                    time_added_to_pool: None,
+                    tx_status: status,
+                    fee: None,
+                    min_replace_fee: None,
+                })
+                .to_json(),
+            );
+        })
+    }
+
+    fn offchain_collector(server: &MockServer) -> DefaultCellCollector {
+        let mut collector = DefaultCellCollector::new(server.base_url().as_str());
+        let raw = ckb_types::packed::RawTransaction::new_builder()
+            .inputs(vec![CellInput::new(crate::test_util::random_out_point(), 0)].pack())
+            .outputs(vec![CellOutput::default(), CellOutput::default()].pack())
+            .outputs_data(vec![Bytes::default().pack(), Bytes::default().pack()].pack())
+            .build();
+        let tx = Transaction::new_builder().raw(raw).build();
+        collector.apply_tx_outputs_only(tx).unwrap();
+        assert_eq!(collector.offchain_cells().len(), 2);
+        collector
+    }
+
+    #[test]
+    fn test_collector_on_new_tip() {
+        let server = MockServer::start();
+        let get_transaction = mock_node(&server, json_types::Status::Pending);
+        let mut collector = offchain_collector(&server);
+        let tip_header = tip_header();
+        assert_eq!(collector.on_new_tip(&tip_header).unwrap(), 0);
+        assert_eq!(collector.offchain_cells().len(), 2);
+        // the outputs are created by the same transaction, it's checked only once
+        get_transaction.assert_hits(1);
+
+        let server = MockServer::start();
+        mock_node(&server, json_types::Status::Committed);
+        let mut collector = offchain_collector(&server);
+        assert_eq!(collector.on_new_tip(&tip_header).unwrap(), 2);
+        assert!(collector.offchain_cells().is_empty());
+    }
+
+    #[test]
+    fn test_watch_tip() {
+        let server = MockServer::start();
+        mock_node(&server, json_types::Status::Committed);
+        let collector = Arc::new(Mutex::new(offchain_collector(&server)));
+        let provider = DefaultTransactionDependencyProvider::new(&server.base_url(), 10);
+
+        let watcher = watch_tip(
+            &server.base_url(),
+            provider,
+            Some(Arc::clone(&collector)),
+            Duration::from_millis(10),
+        );
+        let started_at = Instant::now();
+        while !collector.lock().offchain_cells().is_empty() {
+            assert!(started_at.elapsed() < Duration::from_secs(10));
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(watcher.is_running());
+        watcher.stop();
+    }
+}
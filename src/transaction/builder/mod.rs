@@ -5,7 +5,9 @@ use crate::{
     core::TransactionBuilder,
     traits::CellCollectorError,
     transaction::TransactionBuilderConfiguration,
-    tx_builder::{BalanceTxCapacityError, TxBuilderError},
+    tx_builder::{
+        check_tx_size, BalanceTxCapacityError, BuiltTransaction, ChangeOutput, TxBuilderError,
+    },
     ScriptGroup, TransactionWithScriptGroups,
 };
 use ckb_types::{
@@ -133,7 +135,8 @@ impl<'a> ChangeBuilder for DefaultChangeBuilder<'a> {
     }
 }
 
-/// a helper fn to build a transaction with common logic
+/// a helper fn to build a transaction with common logic, the change output is
+/// the last output of the transaction.
 fn inner_build<
     CB: ChangeBuilder,
     I: Iterator<Item = Result<TransactionInput, CellCollectorError>>,
@@ -143,7 +146,7 @@ fn inner_build<
     input_iter: I,
     configuration: &TransactionBuilderConfiguration,
     contexts: &HandlerContexts,
-) -> Result<TransactionWithScriptGroups, TxBuilderError> {
+) -> Result<(TransactionWithScriptGroups, BuiltTransaction), TxBuilderError> {
     let mut lock_groups: HashMap<Byte32, ScriptGroup> = HashMap::default();
     let mut type_groups: HashMap<Byte32, ScriptGroup> = HashMap::default();
 
@@ -162,8 +165,10 @@ fn inner_build<
     change_builder.init(&mut tx);
 
     // collect inputs
+    let mut inputs_capacity = 0u64;
     for (input_index, input) in input_iter.enumerate() {
         let input = input?;
+        inputs_capacity += Unpack::<u64>::unpack(&input.previous_output().capacity());
         tx.input(input.cell_input());
         tx.witness(packed::Bytes::default());
 
@@ -202,8 +207,27 @@ fn inner_build<
             }
 
             let tx_view = change_builder.finalize(tx);
-
-            return Ok(TransactionWithScriptGroups::new(tx_view, script_groups));
+            check_tx_size(&tx_view, configuration.max_tx_size)?;
+
+            let outputs_capacity: u64 = tx_view
+                .outputs()
+                .into_iter()
+                .map(|output| Unpack::<u64>::unpack(&output.capacity()))
+                .sum();
+            let change_index = tx_view.outputs().len() - 1;
+            let change_output = ChangeOutput {
+                index: change_index,
+                lock_script: tx_view.output(change_index).expect("change output").lock(),
+            };
+            let built_tx = BuiltTransaction::with_fee(
+                tx_view.clone(),
+                vec![change_output],
+                inputs_capacity - outputs_capacity,
+            );
+            return Ok((
+                TransactionWithScriptGroups::new(tx_view, script_groups),
+                built_tx,
+            ));
         }
     }
 
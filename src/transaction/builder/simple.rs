@@ -3,7 +3,7 @@ use crate::{
     transaction::{
         handler::HandlerContexts, input::InputIterator, TransactionBuilderConfiguration,
     },
-    tx_builder::TxBuilderError,
+    tx_builder::{BuiltTransaction, TxBuilderError},
     TransactionWithScriptGroups,
 };
 use ckb_types::{
@@ -59,13 +59,13 @@ impl SimpleTransactionBuilder {
             .build();
         self.add_output_and_data(output, packed::Bytes::default());
     }
-}
 
-impl CkbTransactionBuilder for SimpleTransactionBuilder {
-    fn build(
+    /// Same as `build`, also return the transaction as a [`BuiltTransaction`], which
+    /// reports the change output, the paid fee and the consumed cells.
+    pub fn build_result(
         self,
         contexts: &HandlerContexts,
-    ) -> Result<TransactionWithScriptGroups, TxBuilderError> {
+    ) -> Result<(TransactionWithScriptGroups, BuiltTransaction), TxBuilderError> {
         let Self {
             change_lock,
             configuration,
@@ -82,3 +82,13 @@ impl CkbTransactionBuilder for SimpleTransactionBuilder {
         inner_build(tx, change_builder, input_iter, &configuration, contexts)
     }
 }
+
+impl CkbTransactionBuilder for SimpleTransactionBuilder {
+    fn build(
+        self,
+        contexts: &HandlerContexts,
+    ) -> Result<TransactionWithScriptGroups, TxBuilderError> {
+        let (tx_with_groups, _) = self.build_result(contexts)?;
+        Ok(tx_with_groups)
+    }
+}
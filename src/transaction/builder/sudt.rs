@@ -5,7 +5,7 @@ use crate::{
     transaction::{
         handler::HandlerContexts, input::InputIterator, TransactionBuilderConfiguration,
     },
-    tx_builder::{BalanceTxCapacityError, TxBuilderError},
+    tx_builder::{BalanceTxCapacityError, BuiltTransaction, ChangeOutput, TxBuilderError},
     NetworkInfo, NetworkType, TransactionWithScriptGroups,
 };
 use anyhow::anyhow;
@@ -102,11 +102,14 @@ fn parse_u128(data: &[u8]) -> Result<u128, TxBuilderError> {
     Ok(u128::from_le_bytes(data_bytes.try_into().unwrap()))
 }
 
-impl CkbTransactionBuilder for SudtTransactionBuilder {
-    fn build(
+impl SudtTransactionBuilder {
+    /// Same as `build`, also return the transaction as a [`BuiltTransaction`], which
+    /// reports the change outputs (include the sUDT change output when not in owner
+    /// mode), the paid fee and the consumed cells.
+    pub fn build_result(
         mut self,
         contexts: &HandlerContexts,
-    ) -> Result<TransactionWithScriptGroups, TxBuilderError> {
+    ) -> Result<(TransactionWithScriptGroups, BuiltTransaction), TxBuilderError> {
         if !self.owner_mode {
             // Add change output for sudt with zero amount as placeholder
             self.add_output(self.change_lock.clone(), 0);
@@ -153,8 +156,18 @@ impl CkbTransactionBuilder for SudtTransactionBuilder {
                     let change_output_data: Bytes = (inputs_sudt_amount - outputs_sudt_amount)
                         .to_le_bytes()
                         .pack();
-                    tx.set_output_data(tx.outputs_data.len() - 1, change_output_data);
-                    return inner_build(tx, change_builder, input_iter, &configuration, contexts);
+                    let sudt_change_index = tx.outputs_data.len() - 1;
+                    tx.set_output_data(sudt_change_index, change_output_data);
+                    let (tx_with_groups, mut built_tx) =
+                        inner_build(tx, change_builder, input_iter, &configuration, contexts)?;
+                    built_tx.change_outputs.insert(
+                        0,
+                        ChangeOutput {
+                            index: sudt_change_index,
+                            lock_script: built_tx.tx.output(sudt_change_index).unwrap().lock(),
+                        },
+                    );
+                    return Ok((tx_with_groups, built_tx));
                 }
             }
 
@@ -166,6 +179,16 @@ impl CkbTransactionBuilder for SudtTransactionBuilder {
     }
 }
 
+impl CkbTransactionBuilder for SudtTransactionBuilder {
+    fn build(
+        self,
+        contexts: &HandlerContexts,
+    ) -> Result<TransactionWithScriptGroups, TxBuilderError> {
+        let (tx_with_groups, _) = self.build_result(contexts)?;
+        Ok(tx_with_groups)
+    }
+}
+
 fn build_sudt_type_script(network_info: &NetworkInfo, sudt_owner_lock_script: &Script) -> Script {
     // code_hash from https://github.com/nervosnetwork/rfcs/blob/master/rfcs/0025-simple-udt/0025-simple-udt.md#notes
     let code_hash = match network_info.network_type {
@@ -0,0 +1,182 @@
+use anyhow::anyhow;
+use ckb_types::{
+    bytes::Bytes,
+    core::DepType,
+    h256,
+    packed::{CellDep, OutPoint, WitnessArgs},
+    prelude::*,
+};
+
+use crate::{
+    core::TransactionBuilder,
+    traits::{HeaderDepResolver, TransactionDependencyProvider},
+    tx_builder::TxBuilderError,
+    unlock::UnlockError,
+    util::get_header_by_number,
+    NetworkInfo, NetworkType, ScriptGroup, ScriptId,
+};
+
+use super::{HandlerContext, ScriptHandler};
+
+/// Nervos DAO script handler, it will setup the header deps of the DAO inputs automatically:
+///   * deposit cell (withdraw phase 1): the deposit block header
+///   * prepared cell (withdraw phase 2): the deposit block header and the withdraw(prepare) block header,
+///     and the `input_type` of the witness is set to the index of the deposit block header.
+pub struct DaoHandler {
+    cell_deps: Vec<CellDep>,
+}
+
+/// The context for [`DaoHandler`], the header dep resolver is used to find the
+/// deposit/withdraw block headers, and the transaction dependency provider is used
+/// to get the input cell data.
+pub struct DaoContext {
+    pub header_dep_resolver: Box<dyn HeaderDepResolver>,
+    pub tx_dep_provider: Box<dyn TransactionDependencyProvider>,
+}
+
+impl HandlerContext for DaoContext {}
+
+impl DaoContext {
+    pub fn new(
+        header_dep_resolver: Box<dyn HeaderDepResolver>,
+        tx_dep_provider: Box<dyn TransactionDependencyProvider>,
+    ) -> Self {
+        Self {
+            header_dep_resolver,
+            tx_dep_provider,
+        }
+    }
+}
+
+impl DaoHandler {
+    pub fn new_with_network(network: &NetworkInfo) -> Result<Self, TxBuilderError> {
+        let mut ret = Self { cell_deps: vec![] };
+        ret.init(network)?;
+        Ok(ret)
+    }
+
+    fn add_input_header_deps(
+        tx_builder: &mut TransactionBuilder,
+        input_index: usize,
+        context: &DaoContext,
+    ) -> Result<(), TxBuilderError> {
+        let out_point = tx_builder
+            .get_inputs()
+            .get(input_index)
+            .ok_or_else(|| {
+                TxBuilderError::InvalidParameter(anyhow!("input index `{}` not found", input_index))
+            })?
+            .previous_output();
+        let tx_hash = out_point.tx_hash();
+        let block_header = context
+            .header_dep_resolver
+            .resolve_by_tx(&tx_hash)
+            .map_err(TxBuilderError::Other)?
+            .ok_or_else(|| TxBuilderError::ResolveHeaderDepByTxHashFailed(tx_hash.clone()))?;
+
+        let deposit_number = Self::deposit_number(&out_point, context)?;
+        if deposit_number == 0 {
+            // deposit cell, the input's block is the deposit block
+            tx_builder.dedup_header_dep(block_header.hash());
+            return Ok(());
+        }
+
+        // prepared cell, the input's block is the withdraw block
+        let deposit_header =
+            match get_header_by_number(context.tx_dep_provider.as_ref(), deposit_number)? {
+                Some(header) => header,
+                None => context
+                    .header_dep_resolver
+                    .resolve_by_number(deposit_number)
+                    .map_err(TxBuilderError::Other)?
+                    .ok_or(TxBuilderError::ResolveHeaderDepByNumberFailed(
+                        deposit_number,
+                    ))?,
+            };
+        let deposit_block_hash = deposit_header.hash();
+        tx_builder.dedup_header_dep(deposit_block_hash.clone());
+        tx_builder.dedup_header_dep(block_header.hash());
+        let header_idx = tx_builder
+            .get_header_deps()
+            .iter()
+            .position(|hash| *hash == deposit_block_hash)
+            .expect("deposit header dep");
+
+        let witness = match tx_builder.get_witnesses().get(input_index) {
+            Some(witness) if !witness.raw_data().is_empty() => {
+                WitnessArgs::from_slice(witness.raw_data().as_ref())
+                    .map_err(|_| UnlockError::InvalidWitnessArgs(input_index))?
+                    .as_builder()
+            }
+            _ => WitnessArgs::new_builder(),
+        }
+        .input_type(Some(Bytes::from((header_idx as u64).to_le_bytes().to_vec())).pack())
+        .build();
+        tx_builder.set_witness(input_index, witness.as_bytes().pack());
+        Ok(())
+    }
+
+    fn deposit_number(out_point: &OutPoint, context: &DaoContext) -> Result<u64, TxBuilderError> {
+        let data = context.tx_dep_provider.get_cell_data(out_point)?;
+        if data.len() != 8 {
+            return Err(TxBuilderError::InvalidParameter(anyhow!(
+                "the dao input cell has invalid data length, expected: 8, got: {}",
+                data.len()
+            )));
+        }
+        let mut number_bytes = [0u8; 8];
+        number_bytes.copy_from_slice(data.as_ref());
+        Ok(u64::from_le_bytes(number_bytes))
+    }
+}
+
+impl ScriptHandler for DaoHandler {
+    fn build_transaction(
+        &self,
+        tx_builder: &mut TransactionBuilder,
+        script_group: &mut ScriptGroup,
+        context: &dyn HandlerContext,
+    ) -> Result<bool, TxBuilderError> {
+        if !ScriptId::from(&script_group.script).is_dao() {
+            return Ok(false);
+        }
+        if let Some(context) = context.as_any().downcast_ref::<DaoContext>() {
+            tx_builder.dedup_cell_deps(self.cell_deps.clone());
+            for input_index in &script_group.input_indices {
+                Self::add_input_header_deps(tx_builder, *input_index, context)?;
+            }
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn init(&mut self, network: &NetworkInfo) -> Result<(), TxBuilderError> {
+        let out_point = if network.network_type == NetworkType::Mainnet {
+            OutPoint::new_builder()
+                .tx_hash(
+                    h256!("0xe2fb199810d49a4d8beec56718ba2593b665db9d52299a0f9e6e75416d73ff5c")
+                        .pack(),
+                )
+                .index(2u32.pack())
+                .build()
+        } else if network.network_type == NetworkType::Testnet {
+            OutPoint::new_builder()
+                .tx_hash(
+                    h256!("0x8f8c79eb6671709633fe6a46de93c0fedc9c1b8a6527a18d3983879542635c9f")
+                        .pack(),
+                )
+                .index(2u32.pack())
+                .build()
+        } else {
+            return Err(TxBuilderError::UnsupportedNetworkType(network.network_type));
+        };
+
+        let cell_dep = CellDep::new_builder()
+            .out_point(out_point)
+            .dep_type(DepType::Code.into())
+            .build();
+        self.cell_deps.push(cell_dep);
+        Ok(())
+    }
+}
@@ -9,6 +9,7 @@ use self::{
     sighash::Secp256k1Blake160SighashAllScriptContext, sudt::SudtContext, typeid::TypeIdContext,
 };
 
+pub mod dao;
 pub mod multisig;
 pub mod sighash;
 pub mod sudt;
@@ -64,6 +64,14 @@ impl SudtHandler {
             sudt_script_id,
         })
     }
+
+    pub fn sudt_script_id(&self) -> &ScriptId {
+        &self.sudt_script_id
+    }
+
+    pub fn cell_deps(&self) -> &[CellDep] {
+        &self.cell_deps
+    }
 }
 
 impl ScriptHandler for SudtHandler {
@@ -1,11 +1,8 @@
-use ckb_hash::new_blake2b;
-use ckb_types::{
-    packed::{Bytes, CellInput},
-    prelude::*,
-};
+use ckb_types::{packed::Bytes, prelude::*};
 
 use crate::{
-    core::TransactionBuilder, tx_builder::TxBuilderError, NetworkInfo, ScriptGroup, ScriptId,
+    core::TransactionBuilder, tx_builder::TxBuilderError, util::calc_type_id, NetworkInfo,
+    ScriptGroup, ScriptId,
 };
 
 use super::{HandlerContext, ScriptHandler};
@@ -31,7 +28,7 @@ impl ScriptHandler for TypeIdHandler {
         {
             let input = tx_builder.get_inputs().first().unwrap();
             let index = *script_group.output_indices.last().unwrap();
-            let args: Bytes = calculate_type_id(input, index as u64).to_vec().pack();
+            let args: Bytes = calc_type_id(input, index as u64).as_bytes().pack();
             let output = tx_builder.get_outputs().get(index).unwrap().clone();
             let output_type_script = output
                 .type_()
@@ -55,12 +52,3 @@ impl ScriptHandler for TypeIdHandler {
         Ok(())
     }
 }
-
-fn calculate_type_id(first_cell_input: &CellInput, output_index: u64) -> [u8; 32] {
-    let mut blake2b = new_blake2b();
-    blake2b.update(first_cell_input.as_slice());
-    blake2b.update(&output_index.to_le_bytes());
-    let mut ret = [0u8; 32];
-    blake2b.finalize(&mut ret);
-    ret
-}
@@ -5,12 +5,10 @@ pub use transaction_input::TransactionInput;
 
 use crate::{
     rpc::ckb_indexer::SearchMode,
-    traits::{
-        CellCollector, CellCollectorError, CellQueryOptions, DefaultCellCollector, ValueRangeOption,
-    },
-    types::NetworkInfo,
-    Address,
+    traits::{CellCollector, CellCollectorError, CellQueryOptions, ValueRangeOption},
 };
+#[cfg(feature = "rpc")]
+use crate::{traits::DefaultCellCollector, types::NetworkInfo, Address};
 
 pub struct InputIterator {
     buffer_inputs: Vec<TransactionInput>,
@@ -31,6 +29,7 @@ impl Clone for InputIterator {
 }
 
 impl InputIterator {
+    #[cfg(feature = "rpc")]
     pub fn new(lock_scripts: Vec<Script>, network_info: &NetworkInfo) -> Self {
         let mut lock_scripts = lock_scripts;
         lock_scripts.reverse();
@@ -56,6 +55,7 @@ impl InputIterator {
         }
     }
 
+    #[cfg(feature = "rpc")]
     pub fn new_with_address(address: &[Address], network_info: &NetworkInfo) -> Self {
         let lock_scripts = address.iter().map(|addr| addr.into()).collect::<Vec<_>>();
         Self::new(lock_scripts, network_info)
@@ -1,11 +1,15 @@
-use crate::{tx_builder::TxBuilderError, NetworkInfo};
+use crate::{constants::MAX_BLOCK_BYTES, tx_builder::TxBuilderError, NetworkInfo};
 
 use self::{builder::FeeCalculator, handler::ScriptHandler};
 
 pub mod builder;
 pub mod handler;
 pub mod input;
+#[cfg(feature = "rpc")]
+pub mod pool;
 pub mod signer;
+pub mod transfer;
+pub mod xudt;
 
 pub struct TransactionBuilderConfiguration {
     /// The network for transaction builder.
@@ -18,6 +22,9 @@ pub struct TransactionBuilderConfiguration {
     /// a typical TWO_IN_TWO_OUT secp256k1-sig-hash-all transaction size is about 597 bytes,
     /// we set the default value to 128000, it's enough for most cases, and user can change it if needed.
     pub estimate_tx_size: u64,
+    /// The maximum serialized size (in block) of the built transaction, building fails when
+    /// exceeded, the default value is the block size limit, `None` for no limit.
+    pub max_tx_size: Option<u64>,
 }
 
 impl TransactionBuilderConfiguration {
@@ -36,6 +43,7 @@ impl TransactionBuilderConfiguration {
             script_handlers,
             fee_rate: 1000,
             estimate_tx_size: 128000,
+            max_tx_size: Some(MAX_BLOCK_BYTES),
         })
     }
 
@@ -55,6 +63,7 @@ impl TransactionBuilderConfiguration {
             ) as Box<_>,
             Box::new(handler::sudt::SudtHandler::new_with_network(network)?) as Box<_>,
             Box::new(handler::typeid::TypeIdHandler) as Box<_>,
+            Box::new(handler::dao::DaoHandler::new_with_network(network)?) as Box<_>,
         ];
         Ok(ret)
     }
@@ -75,6 +84,10 @@ impl TransactionBuilderConfiguration {
         self.fee_rate
     }
 
+    pub fn set_max_tx_size(&mut self, max_tx_size: Option<u64>) {
+        self.max_tx_size = max_tx_size;
+    }
+
     pub fn fee_calculator(&self) -> FeeCalculator {
         FeeCalculator::new(self.fee_rate)
     }
@@ -0,0 +1,192 @@
+use ckb_types::{
+    core::TransactionView,
+    packed::{OutPoint, Transaction},
+    prelude::*,
+    H256,
+};
+
+use crate::traits::{
+    CellCollector, CellCollectorError, CellQueryOptions, DefaultCellCollector, LiveCell,
+};
+
+/// Track a chain of transactions sent without waiting for confirmation: the outputs of
+/// the pending transactions are collected by the following transactions, and dropped
+/// from the offchain states once the transaction is committed.
+///
+/// The pool is a `CellCollector` itself, pass it to the transaction builders directly.
+#[derive(Clone)]
+pub struct OffchainCellPool {
+    collector: DefaultCellCollector,
+    // the hashes of the pending transactions in sending order
+    pending_txs: Vec<H256>,
+}
+
+impl OffchainCellPool {
+    pub fn new(collector: DefaultCellCollector) -> OffchainCellPool {
+        OffchainCellPool {
+            collector,
+            pending_txs: Vec::new(),
+        }
+    }
+
+    pub fn collector(&self) -> &DefaultCellCollector {
+        &self.collector
+    }
+    pub fn collector_mut(&mut self) -> &mut DefaultCellCollector {
+        &mut self.collector
+    }
+    pub fn into_inner(self) -> DefaultCellCollector {
+        self.collector
+    }
+
+    /// Apply the sent transaction to the collector: the inputs are locked and the
+    /// outputs can be collected by the next transactions.
+    pub fn push_pending_tx(&mut self, tx: TransactionView) -> Result<(), CellCollectorError> {
+        let tip_number = self.collector.get_tip_block_number()?;
+        self.apply_tx(tx.data(), tip_number)
+    }
+
+    /// Drop the outputs of the committed transaction from the offchain states, they are
+    /// collected from ckb-indexer from now on.
+    ///
+    /// Return false if the transaction is not pending.
+    pub fn pop_committed_tx(&mut self, tx_hash: &H256) -> bool {
+        let pending_count = self.pending_txs.len();
+        self.pending_txs.retain(|hash| hash != tx_hash);
+        self.collector.forget_committed_tx(&tx_hash.pack());
+        pending_count != self.pending_txs.len()
+    }
+
+    /// The number of the sent transactions not committed yet.
+    pub fn outstanding_tx_count(&self) -> usize {
+        self.pending_txs.len()
+    }
+}
+
+impl CellCollector for OffchainCellPool {
+    fn collect_live_cells(
+        &mut self,
+        query: &CellQueryOptions,
+        apply_changes: bool,
+    ) -> Result<(Vec<LiveCell>, u64), CellCollectorError> {
+        self.collector.collect_live_cells(query, apply_changes)
+    }
+
+    fn lock_cell(
+        &mut self,
+        out_point: OutPoint,
+        tip_block_number: u64,
+    ) -> Result<(), CellCollectorError> {
+        self.collector.lock_cell(out_point, tip_block_number)
+    }
+
+    fn unlock_cell(&mut self, out_point: OutPoint) -> Result<(), CellCollectorError> {
+        self.collector.unlock_cell(out_point)
+    }
+
+    fn apply_tx(
+        &mut self,
+        tx: Transaction,
+        tip_block_number: u64,
+    ) -> Result<(), CellCollectorError> {
+        let tx_hash: H256 = tx.calc_tx_hash().unpack();
+        self.collector.apply_tx(tx, tip_block_number)?;
+        if !self.pending_txs.contains(&tx_hash) {
+            self.pending_txs.push(tx_hash);
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.collector.reset();
+        self.pending_txs.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ckb_jsonrpc_types as json_types;
+    use ckb_types::{
+        bytes::Bytes,
+        core::{EpochNumberWithFraction, HeaderView, TransactionBuilder},
+        packed::{CellInput, CellOutput, Script},
+    };
+    use httpmock::prelude::*;
+
+    use super::*;
+    use crate::test_util::{random_out_point, MockRpcResult};
+
+    fn mock_node(server: &MockServer) {
+        let consensus: json_types::Consensus =
+            ckb_chain_spec::consensus::ConsensusBuilder::default()
+                .build()
+                .into();
+        server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("get_consensus");
+            then.status(200)
+                .body(MockRpcResult::new(consensus).to_json());
+        });
+        let tip_header: json_types::HeaderView = HeaderView::new_advanced_builder()
+            .number(100.pack())
+            .epoch(
+                EpochNumberWithFraction::new(0, 100, 1000)
+                    .full_value()
+                    .pack(),
+            )
+            .build()
+            .into();
+        server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("get_tip_header");
+            then.status(200)
+                .body(MockRpcResult::new(tip_header).to_json());
+        });
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("get_tip_block_number");
+            then.status(200)
+                .body(MockRpcResult::new(json_types::BlockNumber::from(100)).to_json());
+        });
+    }
+
+    fn build_tx(input: OutPoint, lock: &Script) -> TransactionView {
+        TransactionBuilder::default()
+            .input(CellInput::new(input, 0))
+            .output(
+                CellOutput::new_builder()
+                    .lock(lock.clone())
+                    .capacity(100u64.pack())
+                    .build(),
+            )
+            .output_data(Bytes::new().pack())
+            .build()
+    }
+
+    #[test]
+    fn test_offchain_cell_pool() {
+        let server = MockServer::start();
+        mock_node(&server);
+        let lock = Script::new_builder().args(vec![1u8; 20].pack()).build();
+        let mut pool = OffchainCellPool::new(DefaultCellCollector::new(&server.base_url()));
+
+        let first = build_tx(random_out_point(), &lock);
+        pool.push_pending_tx(first.clone()).unwrap();
+        // the second transaction spends the output of the pending one
+        let query = CellQueryOptions::new_lock(lock.clone());
+        let (cells, _) = pool.collect_live_cells(&query, true).unwrap();
+        assert_eq!(cells[0].out_point, OutPoint::new(first.hash(), 0));
+        let second = build_tx(cells[0].out_point.clone(), &lock);
+        pool.push_pending_tx(second.clone()).unwrap();
+        assert_eq!(pool.outstanding_tx_count(), 2);
+
+        assert!(pool.pop_committed_tx(&first.hash().unpack()));
+        assert!(!pool.pop_committed_tx(&first.hash().unpack()));
+        assert_eq!(pool.outstanding_tx_count(), 1);
+        // the output of the pending transaction is still collectable
+        let (cells, _) = pool.collect_live_cells(&query, false).unwrap();
+        assert_eq!(cells[0].out_point, OutPoint::new(second.hash(), 0));
+
+        pool.reset();
+        assert_eq!(pool.outstanding_tx_count(), 0);
+    }
+}
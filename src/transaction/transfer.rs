@@ -0,0 +1,237 @@
+use anyhow::anyhow;
+use ckb_types::{
+    bytes::Bytes,
+    core::{Capacity, TransactionView},
+    packed::{self, CellInput, CellOutput, WitnessArgs},
+    prelude::*,
+};
+
+use crate::{
+    traits::{
+        CellCollector, CellCollectorError, CellDepResolver, CellQueryOptions, LiveCell,
+        ValueRangeOption,
+    },
+    tx_builder::{BalanceTxCapacityError, TxBuilderError},
+};
+
+use super::builder::FeeCalculator;
+
+// Collecting more inputs increases the fee, the loop ends in few rounds
+const MAX_ROUNDS: usize = 16;
+
+/// Build an unsigned capacity transfer transaction: the plain capacity cells (no type
+/// script and no data) of `from_lock` are collected as inputs, `amount_shannons` is sent
+/// to `to_lock` and the change is sent back to `from_lock`. If the change is less than
+/// the occupied capacity of a cell it is paid as fee. The inputs are locked in `collector`,
+/// they are unlocked again if the transaction can not be built.
+///
+/// The witness of the first input is filled with a 65 bytes lock placeholder (the
+/// secp256k1 signature size) for the fee estimation, it should be replaced by the
+/// unlocker of `from_lock`.
+pub fn build_transfer_tx(
+    collector: &mut dyn CellCollector,
+    dep_resolver: &dyn CellDepResolver,
+    from_lock: packed::Script,
+    to_lock: packed::Script,
+    amount_shannons: u64,
+    fee_rate: u64,
+) -> Result<TransactionView, TxBuilderError> {
+    let cell_dep = dep_resolver
+        .resolve(&from_lock)
+        .ok_or_else(|| TxBuilderError::ResolveCellDepFailed(from_lock.clone()))?;
+    let output = CellOutput::new_builder()
+        .lock(to_lock)
+        .capacity(amount_shannons.pack())
+        .build();
+    let output_occupied = occupied_capacity(&output);
+    if amount_shannons < output_occupied {
+        return Err(TxBuilderError::InvalidParameter(anyhow!(
+            "transfer amount {} is less than the occupied capacity {}",
+            amount_shannons,
+            output_occupied
+        )));
+    }
+    let change_output = CellOutput::new_builder().lock(from_lock.clone()).build();
+    let change_occupied = occupied_capacity(&change_output);
+
+    let build = |inputs: &[LiveCell], change: Option<u64>| {
+        let mut outputs = vec![output.clone()];
+        if let Some(change) = change {
+            outputs.push(
+                change_output
+                    .clone()
+                    .as_builder()
+                    .capacity(change.pack())
+                    .build(),
+            );
+        }
+        build_tx(&cell_dep, inputs, outputs)
+    };
+    let fee_calculator = FeeCalculator::new(fee_rate);
+    let fee = |tx: &TransactionView| tx_fee(&fee_calculator, tx);
+
+    let mut query = plain_cells_query(from_lock);
+    let mut change_fee = 0;
+    for _ in 0..MAX_ROUNDS {
+        query.min_total_capacity = checked_sum(&[amount_shannons, change_occupied, change_fee])?;
+        // the inputs are locked when collected, so the transaction is built from exactly
+        // the priced inputs, they are unlocked again before collecting more
+        let (inputs, total) = collector.collect_live_cells(&query, true)?;
+        let tx = build(&inputs, Some(change_occupied));
+        change_fee = fee(&tx);
+        let needed = checked_sum(&[amount_shannons, change_fee])?;
+        if total >= checked_sum(&[needed, change_occupied])? {
+            return Ok(build(&inputs, Some(total - needed)));
+        }
+        if total >= query.min_total_capacity {
+            // the fee increased with the inputs, collect more
+            unlock_cells(collector, &inputs)?;
+            continue;
+        }
+        // all cells are collected, try to pay the small change as fee
+        let tx = build(&inputs, None);
+        if total >= checked_sum(&[amount_shannons, fee(&tx)])? {
+            return Ok(tx);
+        }
+        unlock_cells(collector, &inputs)?;
+        return Err(CellCollectorError::InsufficientCapacity {
+            needed: needed.saturating_add(change_occupied),
+            available: total,
+        }
+        .into());
+    }
+    Err(TxBuilderError::ExceedCycleMaxLoopTimes(MAX_ROUNDS as u32))
+}
+
+/// Build an unsigned transaction merging the plain capacity cells (no type script and no
+/// data) of `lock` into one output, at most `max_inputs` cells are collected. The inputs
+/// are locked in `collector`, they are unlocked again if the transaction can not be built.
+/// The witness placeholder is the same as `build_transfer_tx`.
+pub fn build_consolidate_tx(
+    collector: &mut dyn CellCollector,
+    dep_resolver: &dyn CellDepResolver,
+    lock: packed::Script,
+    max_inputs: usize,
+    fee_rate: u64,
+) -> Result<TransactionView, TxBuilderError> {
+    if max_inputs < 2 {
+        return Err(TxBuilderError::InvalidParameter(anyhow!(
+            "max_inputs must be at least 2, got {}",
+            max_inputs
+        )));
+    }
+    let cell_dep = dep_resolver
+        .resolve(&lock)
+        .ok_or_else(|| TxBuilderError::ResolveCellDepFailed(lock.clone()))?;
+
+    let mut query = plain_cells_query(lock.clone());
+    query.limit = Some(max_inputs as u32);
+    // one more cell is collected and locked in each round, so the collector stops
+    // after `max_inputs` cells instead of paging through all cells of the lock
+    query.min_total_capacity = 1;
+    let mut inputs: Vec<LiveCell> = Vec::new();
+    while inputs.len() < max_inputs {
+        let (cells, _) = collector.collect_live_cells(&query, true)?;
+        let len = inputs.len();
+        for cell in cells {
+            if inputs.len() < max_inputs
+                && inputs.iter().all(|input| input.out_point != cell.out_point)
+            {
+                inputs.push(cell);
+            } else {
+                collector.unlock_cell(cell.out_point)?;
+            }
+        }
+        if inputs.len() == len {
+            break;
+        }
+    }
+    if inputs.len() < 2 {
+        unlock_cells(collector, &inputs)?;
+        return Err(TxBuilderError::InvalidParameter(anyhow!(
+            "not enough cells to consolidate: {}",
+            inputs.len()
+        )));
+    }
+    let total: u64 = inputs
+        .iter()
+        .map(|cell| -> u64 { cell.output.capacity().unpack() })
+        .sum();
+
+    let output = CellOutput::new_builder().lock(lock).build();
+    let fee = tx_fee(
+        &FeeCalculator::new(fee_rate),
+        &build_tx(&cell_dep, &inputs, vec![output.clone()]),
+    );
+    let needed = fee + occupied_capacity(&output);
+    if total < needed {
+        unlock_cells(collector, &inputs)?;
+        return Err(BalanceTxCapacityError::CapacityNotEnough(format!(
+            "the fee consumes the inputs, total: {}, fee: {}",
+            total, fee
+        ))
+        .into());
+    }
+    let output = output.as_builder().capacity((total - fee).pack()).build();
+    Ok(build_tx(&cell_dep, &inputs, vec![output]))
+}
+
+fn checked_sum(values: &[u64]) -> Result<u64, TxBuilderError> {
+    values
+        .iter()
+        .try_fold(0u64, |sum, value| sum.checked_add(*value))
+        .ok_or_else(|| TxBuilderError::InvalidParameter(anyhow!("capacity overflow")))
+}
+
+// Unlock the cells collected by a failed attempt, so they can be collected again
+fn unlock_cells(
+    collector: &mut dyn CellCollector,
+    cells: &[LiveCell],
+) -> Result<(), CellCollectorError> {
+    for cell in cells {
+        collector.unlock_cell(cell.out_point.clone())?;
+    }
+    Ok(())
+}
+
+fn plain_cells_query(lock: packed::Script) -> CellQueryOptions {
+    let mut query = CellQueryOptions::new_lock(lock);
+    query.secondary_script_len_range = Some(ValueRangeOption::new_exact(0));
+    query.data_len_range = Some(ValueRangeOption::new_exact(0));
+    query
+}
+
+fn occupied_capacity(output: &CellOutput) -> u64 {
+    output
+        .occupied_capacity(Capacity::zero())
+        .expect("occupied capacity")
+        .as_u64()
+}
+
+fn tx_fee(fee_calculator: &FeeCalculator, tx: &TransactionView) -> u64 {
+    fee_calculator.fee(tx.data().as_reader().serialized_size_in_block() as u64)
+}
+
+// The outputs are plain capacity cells, the witness of the first input is a placeholder
+fn build_tx(
+    cell_dep: &packed::CellDep,
+    inputs: &[LiveCell],
+    outputs: Vec<CellOutput>,
+) -> TransactionView {
+    let placeholder = WitnessArgs::new_builder()
+        .lock(Some(Bytes::from(vec![0u8; 65])).pack())
+        .build();
+    let outputs_data = vec![packed::Bytes::default(); outputs.len()];
+    TransactionView::new_advanced_builder()
+        .cell_dep(cell_dep.clone())
+        .inputs(
+            inputs
+                .iter()
+                .map(|cell| CellInput::new(cell.out_point.clone(), 0)),
+        )
+        .outputs(outputs)
+        .outputs_data(outputs_data)
+        .witness(placeholder.as_bytes().pack())
+        .witnesses((1..inputs.len()).map(|_| packed::Bytes::default()))
+        .build()
+}
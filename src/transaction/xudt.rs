@@ -0,0 +1,77 @@
+use ckb_types::{
+    bytes::{Bytes, BytesMut},
+    core::Capacity,
+    packed::{self, CellOutput},
+    prelude::*,
+};
+
+/// Build the token information cell of a xUDT token, owned by `owner_lock`.
+///
+/// The cell data is the molecule table
+/// `table TokenInfo { decimals: byte, name: Bytes, symbol: Bytes }`, the name and the
+/// symbol are encoded in utf-8. The capacity of the cell is the minimal occupied
+/// capacity.
+pub fn build_xudt_token_info_cell(
+    name: &str,
+    symbol: &str,
+    decimals: u8,
+    owner_lock: packed::Script,
+) -> (CellOutput, Bytes) {
+    let fields = [
+        packed::Byte::new(decimals).as_bytes(),
+        name.as_bytes().pack().as_bytes(),
+        symbol.as_bytes().pack().as_bytes(),
+    ];
+    // total size and the offsets of the fields
+    let header_size = 4 * (1 + fields.len());
+    let total_size = header_size + fields.iter().map(Bytes::len).sum::<usize>();
+    let mut data = BytesMut::with_capacity(total_size);
+    data.extend_from_slice(&(total_size as u32).to_le_bytes());
+    let mut offset = header_size;
+    for field in &fields {
+        data.extend_from_slice(&(offset as u32).to_le_bytes());
+        offset += field.len();
+    }
+    for field in &fields {
+        data.extend_from_slice(field);
+    }
+    let data = data.freeze();
+
+    let output = CellOutput::new_builder().lock(owner_lock).build();
+    let capacity = output
+        .occupied_capacity(Capacity::bytes(data.len()).expect("data capacity"))
+        .expect("occupied capacity");
+    (output.as_builder().capacity(capacity.pack()).build(), data)
+}
+
+#[cfg(test)]
+mod tests {
+    use ckb_types::{core::ScriptHashType, h256};
+
+    use super::*;
+    use crate::constants::{ONE_CKB, SIGHASH_TYPE_HASH};
+
+    #[test]
+    fn test_build_xudt_token_info_cell() {
+        let owner_lock = packed::Script::new_builder()
+            .code_hash(SIGHASH_TYPE_HASH.pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(h256!("0x1").as_bytes()[..20].pack())
+            .build();
+        let (output, data) = build_xudt_token_info_cell("Token", "TKN", 8, owner_lock.clone());
+        let expected = [
+            &[33u8, 0, 0, 0, 16, 0, 0, 0, 17, 0, 0, 0, 26, 0, 0, 0, 8][..],
+            &[5, 0, 0, 0],
+            b"Token",
+            &[3, 0, 0, 0],
+            b"TKN",
+        ]
+        .concat();
+        assert_eq!(data.as_ref(), &expected[..]);
+        assert_eq!(output.lock(), owner_lock);
+        assert!(output.type_().is_none());
+        // 8 bytes capacity + 53 bytes lock + 33 bytes data
+        let capacity: u64 = output.capacity().unpack();
+        assert_eq!(capacity, 94 * ONE_CKB);
+    }
+}
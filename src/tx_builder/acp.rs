@@ -2,16 +2,21 @@ use std::collections::HashSet;
 
 use anyhow::anyhow;
 use ckb_types::{
-    core::{TransactionBuilder, TransactionView},
-    packed::{CellInput, Script},
+    bytes::Bytes,
+    core::{Capacity, ScriptHashType, TransactionBuilder, TransactionView},
+    packed::{CellInput, CellOutput, Script},
     prelude::*,
 };
 
 use super::{TxBuilder, TxBuilderError};
+use crate::constants::{
+    SUDT_TYPE_HASH_AGGRON, SUDT_TYPE_HASH_LINA, XUDT_DATA_HASH_LINA, XUDT_TYPE_HASH_AGGRON,
+};
 use crate::traits::{
     CellCollector, CellDepResolver, CellQueryOptions, HeaderDepResolver,
     TransactionDependencyProvider,
 };
+use crate::{NetworkType, ScriptId};
 
 #[derive(Clone, Debug)]
 pub struct AcpTransferReceiver {
@@ -95,3 +100,145 @@ impl TxBuilder for AcpTransferBuilder {
             .build())
     }
 }
+
+/// The sUDT and xUDT script ids deployed on the network, empty for other networks.
+pub fn known_udt_script_ids(network: NetworkType) -> Vec<ScriptId> {
+    match network {
+        NetworkType::Mainnet => vec![
+            ScriptId::new_type(SUDT_TYPE_HASH_LINA),
+            ScriptId::new(XUDT_DATA_HASH_LINA, ScriptHashType::Data1),
+        ],
+        NetworkType::Testnet => vec![
+            ScriptId::new_type(SUDT_TYPE_HASH_AGGRON),
+            ScriptId::new_type(XUDT_TYPE_HASH_AGGRON),
+        ],
+        _ => Vec::new(),
+    }
+}
+
+/// A new acp cell (the anyone-can-pay lock or the omni-lock in acp mode), which can be
+/// used to receive capacity or UDT.
+#[derive(Clone, Debug)]
+pub struct AcpCellOutput {
+    pub network: NetworkType,
+    pub lock_script: Script,
+    pub type_script: Option<Script>,
+    /// The xUDT data after the amount, only used when `type_script` is set
+    pub xudt_extension: Option<Bytes>,
+}
+
+impl AcpCellOutput {
+    pub fn new(network: NetworkType, lock_script: Script) -> AcpCellOutput {
+        AcpCellOutput {
+            network,
+            lock_script,
+            type_script: None,
+            xudt_extension: None,
+        }
+    }
+
+    /// Receive the UDT by this cell, the cell data is initialized to zero amount
+    /// (16 bytes) followed by the xUDT extension data. A warning is logged if the type
+    /// script is not a known UDT of the network.
+    pub fn for_udt(mut self, type_script: Script, xudt_extension: Option<Bytes>) -> Self {
+        if !known_udt_script_ids(self.network).contains(&ScriptId::from(&type_script)) {
+            log::warn!(
+                "the type script is not a known UDT of network `{}`: {}",
+                self.network,
+                type_script
+            );
+        }
+        self.type_script = Some(type_script);
+        self.xudt_extension = xudt_extension;
+        self
+    }
+
+    /// The initial cell data
+    pub fn output_data(&self) -> Bytes {
+        if self.type_script.is_none() {
+            return Bytes::new();
+        }
+        let extension = self.xudt_extension.clone().unwrap_or_default();
+        let mut data = Vec::with_capacity(16 + extension.len());
+        data.extend_from_slice(&0u128.to_le_bytes());
+        data.extend_from_slice(&extension);
+        Bytes::from(data)
+    }
+
+    fn output(&self, capacity: u64) -> CellOutput {
+        CellOutput::new_builder()
+            .lock(self.lock_script.clone())
+            .type_(self.type_script.clone().pack())
+            .capacity(capacity.pack())
+            .build()
+    }
+
+    /// The minimal capacity of the cell with the initial cell data
+    pub fn min_capacity(&self) -> u64 {
+        self.output(0)
+            .occupied_capacity(Capacity::bytes(self.output_data().len()).expect("data capacity"))
+            .expect("occupied capacity")
+            .as_u64()
+    }
+
+    /// Build the cell output and the cell data, return error if the capacity is less
+    /// than `min_capacity`.
+    pub fn build(&self, capacity: u64) -> Result<(CellOutput, Bytes), TxBuilderError> {
+        let min_capacity = self.min_capacity();
+        if capacity < min_capacity {
+            return Err(TxBuilderError::InvalidParameter(anyhow!(
+                "acp cell capacity {} is less than the occupied capacity {}",
+                capacity,
+                min_capacity
+            )));
+        }
+        Ok((self.output(capacity), self.output_data()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{ACP_TYPE_HASH_LINA, ONE_CKB};
+
+    #[test]
+    fn test_acp_cell_for_udt() {
+        let lock_script = Script::new_builder()
+            .code_hash(ACP_TYPE_HASH_LINA.pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(vec![1u8; 20]).pack())
+            .build();
+        let acp_cell = AcpCellOutput::new(NetworkType::Mainnet, lock_script.clone());
+        assert!(acp_cell.output_data().is_empty());
+        assert_eq!(acp_cell.min_capacity(), 61 * ONE_CKB);
+
+        let sudt_script = Script::new_builder()
+            .code_hash(SUDT_TYPE_HASH_LINA.pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(vec![2u8; 32]).pack())
+            .build();
+        let acp_cell = acp_cell.for_udt(sudt_script.clone(), None);
+        assert_eq!(acp_cell.output_data(), Bytes::from(vec![0u8; 16]));
+        // lock: 61, type: 32 + 1 + 32, data: 16
+        assert_eq!(acp_cell.min_capacity(), 142 * ONE_CKB);
+        let (output, data) = acp_cell.build(200 * ONE_CKB).unwrap();
+        assert_eq!(output.type_().to_opt(), Some(sudt_script.clone()));
+        assert_eq!(output.lock(), lock_script);
+        assert_eq!(data.len(), 16);
+        assert!(acp_cell.build(100 * ONE_CKB).is_err());
+
+        let xudt_script = Script::new_builder()
+            .code_hash(XUDT_DATA_HASH_LINA.pack())
+            .hash_type(ScriptHashType::Data1.into())
+            .args(Bytes::from(vec![2u8; 32]).pack())
+            .build();
+        let acp_cell = AcpCellOutput::new(NetworkType::Mainnet, lock_script)
+            .for_udt(xudt_script, Some(Bytes::from(vec![3u8; 8])));
+        let data = acp_cell.output_data();
+        assert_eq!(&data[..16], &[0u8; 16]);
+        assert_eq!(&data[16..], &[3u8; 8]);
+        assert_eq!(acp_cell.min_capacity(), 150 * ONE_CKB);
+        assert!(known_udt_script_ids(NetworkType::Mainnet).contains(&ScriptId::from(&sudt_script)));
+        assert!(known_udt_script_ids(NetworkType::Dev).is_empty());
+    }
+}
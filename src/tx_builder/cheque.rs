@@ -3,7 +3,7 @@ use std::collections::HashSet;
 use anyhow::anyhow;
 use ckb_types::{
     bytes::Bytes,
-    core::{Capacity, ScriptHashType, TransactionBuilder, TransactionView},
+    core::{Capacity, HeaderView, ScriptHashType, TransactionBuilder, TransactionView},
     packed::{CellInput, CellOutput, OutPoint, Script},
     prelude::*,
 };
@@ -15,6 +15,7 @@ use crate::traits::{
     TransactionDependencyProvider, ValueRangeOption,
 };
 use crate::types::ScriptId;
+use crate::util::{check_input_since, SinceCheckError, SinceCheckResult};
 
 pub struct ChequeClaimBuilder {
     /// The cheque cells to claim, all cells must have same lock script and same
@@ -191,6 +192,11 @@ pub struct ChequeWithdrawBuilder {
 
     /// If `acp_script_id` provided, will withdraw to anyone-can-pay address
     pub acp_script_id: Option<ScriptId>,
+
+    /// Check the since of the cheque cells against the tip (see [`HeaderDepResolver::resolve_tip`])
+    /// in `build_base`, it's disabled by default, so the withdraw transaction can be built
+    /// before the cheque cells are withdrawable.
+    pub check_since: bool,
 }
 
 impl ChequeWithdrawBuilder {
@@ -203,8 +209,42 @@ impl ChequeWithdrawBuilder {
             out_points,
             sender_lock_script,
             acp_script_id,
+            check_since: false,
         }
     }
+
+    /// Enable or disable checking the since of the cheque cells in `build_base`.
+    pub fn set_check_since(&mut self, check_since: bool) {
+        self.check_since = check_since;
+    }
+
+    /// Check if the cheque cells can be withdrawn at current tip, the cheque
+    /// cells can only be withdrawn after 6 epochs since they are created. The
+    /// result is in the same order as `out_points`.
+    ///
+    /// It's called by `build_base` when `check_since` is enabled and the header dep
+    /// resolver knows the tip (see [`HeaderDepResolver::resolve_tip`]).
+    pub fn check_since(
+        &self,
+        header_dep_resolver: &dyn HeaderDepResolver,
+        tx_dep_provider: &dyn TransactionDependencyProvider,
+        tip_header: &HeaderView,
+    ) -> Result<Vec<SinceCheckResult>, SinceCheckError> {
+        self.out_points
+            .iter()
+            .enumerate()
+            .map(|(index, out_point)| {
+                check_input_since(
+                    index,
+                    out_point,
+                    CHEQUE_CELL_SINCE,
+                    header_dep_resolver,
+                    tx_dep_provider,
+                    tip_header,
+                )
+            })
+            .collect()
+    }
 }
 
 impl TxBuilder for ChequeWithdrawBuilder {
@@ -212,7 +252,7 @@ impl TxBuilder for ChequeWithdrawBuilder {
         &self,
         cell_collector: &mut dyn CellCollector,
         cell_dep_resolver: &dyn CellDepResolver,
-        _header_dep_resolver: &dyn HeaderDepResolver,
+        header_dep_resolver: &dyn HeaderDepResolver,
         tx_dep_provider: &dyn TransactionDependencyProvider,
     ) -> Result<TransactionView, TxBuilderError> {
         if self.out_points.is_empty() {
@@ -220,6 +260,24 @@ impl TxBuilder for ChequeWithdrawBuilder {
                 "empty withdraw inputs"
             )));
         }
+        let tip_header = if self.check_since {
+            header_dep_resolver
+                .resolve_tip()
+                .map_err(TxBuilderError::Other)?
+        } else {
+            None
+        };
+        if let Some(tip_header) = tip_header {
+            let results = self.check_since(header_dep_resolver, tx_dep_provider, &tip_header)?;
+            if let Some((out_point, result)) = self
+                .out_points
+                .iter()
+                .zip(results)
+                .find(|(_, result)| !result.is_satisfied())
+            {
+                return Err(TxBuilderError::SinceNotSatisfied(out_point.clone(), result));
+            }
+        }
 
         let mut inputs = Vec::new();
         let mut last_lock_script = None;
@@ -3,7 +3,7 @@ use std::collections::HashSet;
 use anyhow::anyhow;
 use ckb_types::{
     bytes::Bytes,
-    core::{Capacity, FeeRate, ScriptHashType, TransactionBuilder, TransactionView},
+    core::{Capacity, FeeRate, HeaderView, ScriptHashType, TransactionBuilder, TransactionView},
     packed::{CellInput, CellOutput, OutPoint, Script, WitnessArgs},
     prelude::*,
 };
@@ -13,8 +13,7 @@ use crate::constants::DAO_TYPE_HASH;
 use crate::traits::{
     CellCollector, CellDepResolver, HeaderDepResolver, TransactionDependencyProvider,
 };
-use crate::types::{Since, SinceType};
-use crate::util::{calculate_dao_maximum_withdraw4, minimal_unlock_point};
+use crate::util::{calculate_dao_maximum_withdraw4, dao::withdraw_since, get_header_by_number};
 
 /// Deposit target
 #[derive(Debug, Clone)]
@@ -237,6 +236,70 @@ impl DaoWithdrawBuilder {
     pub fn new(items: Vec<DaoWithdrawItem>, receiver: DaoWithdrawReceiver) -> DaoWithdrawBuilder {
         DaoWithdrawBuilder { items, receiver }
     }
+
+    /// Check the locally computed maximum withdraw capacity of every item against
+    /// the node's `calculate_dao_maximum_withdraw`, return the total capacity.
+    ///
+    /// The deposit cell of an item is the input of the prepare transaction at the
+    /// same index as the prepared cell, as built by [`DaoPrepareBuilder`].
+    #[cfg(feature = "rpc")]
+    pub fn verify_maximum_withdraw(
+        &self,
+        ckb_client: &crate::rpc::CkbRpcClient,
+        header_dep_resolver: &dyn HeaderDepResolver,
+        tx_dep_provider: &dyn TransactionDependencyProvider,
+    ) -> Result<u64, TxBuilderError> {
+        let dao_type_script = Script::new_builder()
+            .code_hash(DAO_TYPE_HASH.pack())
+            .hash_type(ScriptHashType::Type.into())
+            .build();
+        let mut total = 0;
+        for DaoWithdrawItem { out_point, .. } in &self.items {
+            let WithdrawItemInfo {
+                prepare_header,
+                deposit_header,
+                input_cell,
+                data,
+            } = resolve_withdraw_item(
+                out_point,
+                &dao_type_script,
+                header_dep_resolver,
+                tx_dep_provider,
+            )?;
+            let local_capacity =
+                maximum_withdraw(&deposit_header, &prepare_header, &input_cell, &data);
+
+            let prepare_tx = tx_dep_provider.get_transaction(&out_point.tx_hash())?;
+            let index: u32 = out_point.index().unpack();
+            let deposit_out_point = prepare_tx
+                .inputs()
+                .get(index as usize)
+                .map(|input| input.previous_output())
+                .ok_or_else(|| {
+                    TxBuilderError::InvalidParameter(anyhow!(
+                        "no deposit input at index {} in prepare transaction {}",
+                        index,
+                        out_point.tx_hash()
+                    ))
+                })?;
+            let node_capacity = ckb_client
+                .calculate_dao_maximum_withdraw_at(
+                    deposit_out_point.into(),
+                    prepare_header.hash().unpack(),
+                )
+                .map_err(|err| TxBuilderError::Other(err.into()))?;
+            if node_capacity != local_capacity {
+                return Err(TxBuilderError::Other(anyhow!(
+                    "maximum withdraw capacity of {} mismatch, local: {}, node: {}",
+                    out_point,
+                    local_capacity,
+                    node_capacity
+                )));
+            }
+            total += local_capacity;
+        }
+        Ok(total)
+    }
 }
 
 impl TxBuilder for DaoWithdrawBuilder {
@@ -274,55 +337,23 @@ impl TxBuilder for DaoWithdrawBuilder {
             init_witness,
         } in &self.items
         {
-            let tx_hash = out_point.tx_hash();
-            let prepare_header = header_dep_resolver
-                .resolve_by_tx(&tx_hash)
-                .map_err(TxBuilderError::Other)?
-                .ok_or_else(|| TxBuilderError::ResolveHeaderDepByTxHashFailed(tx_hash.clone()))?;
+            let WithdrawItemInfo {
+                prepare_header,
+                deposit_header,
+                input_cell,
+                data,
+            } = resolve_withdraw_item(
+                out_point,
+                &dao_type_script,
+                header_dep_resolver,
+                tx_dep_provider,
+            )?;
             prepare_block_hashes.push(prepare_header.hash());
-            let input_cell = tx_dep_provider.get_cell(out_point)?;
-            if input_cell.type_().to_opt().as_ref() != Some(&dao_type_script) {
-                return Err(TxBuilderError::InvalidParameter(anyhow!(
-                    "the input cell has invalid type script"
-                )));
-            }
             let input_lock_cell_dep = cell_dep_resolver
                 .resolve(&input_cell.lock())
                 .ok_or_else(|| TxBuilderError::ResolveCellDepFailed(input_cell.lock()))?;
-            let data = tx_dep_provider.get_cell_data(out_point)?;
-            if data.len() != 8 {
-                return Err(TxBuilderError::InvalidParameter(anyhow!(
-                    "the input cell has invalid data length, expected: 8, got: {}",
-                    data.len()
-                )));
-            }
-            let deposit_number = {
-                let mut number_bytes = [0u8; 8];
-                number_bytes.copy_from_slice(data.as_ref());
-                u64::from_le_bytes(number_bytes)
-            };
-            let deposit_header = header_dep_resolver
-                .resolve_by_number(deposit_number)
-                .or_else(|_err| {
-                    // for light client
-                    let prepare_tx = tx_dep_provider.get_transaction(&tx_hash)?;
-                    for input in prepare_tx.inputs() {
-                        let _ = header_dep_resolver
-                            .resolve_by_tx(&input.previous_output().tx_hash())?;
-                    }
-                    header_dep_resolver.resolve_by_number(deposit_number)
-                })
-                .map_err(TxBuilderError::Other)?
-                .ok_or(TxBuilderError::ResolveHeaderDepByNumberFailed(
-                    deposit_number,
-                ))?;
             let input = {
-                let unlock_point = minimal_unlock_point(&deposit_header, &prepare_header);
-                let since = Since::new(
-                    SinceType::EpochNumberWithFraction,
-                    unlock_point.full_value(),
-                    false,
-                );
+                let since = withdraw_since(&deposit_header, &prepare_header);
                 CellInput::new(out_point.clone(), since.value())
             };
             let deposit_block_hash = deposit_header.hash();
@@ -340,16 +371,7 @@ impl TxBuilder for DaoWithdrawBuilder {
                     .build()
                     .as_bytes()
             };
-            let occupied_capacity = input_cell
-                .occupied_capacity(Capacity::bytes(data.len()).unwrap())
-                .unwrap();
-            let input_capacity = calculate_dao_maximum_withdraw4(
-                &deposit_header,
-                &prepare_header,
-                &input_cell,
-                occupied_capacity.as_u64(),
-            );
-            input_total += input_capacity;
+            input_total += maximum_withdraw(&deposit_header, &prepare_header, &input_cell, &data);
 
             cell_deps.insert(input_lock_cell_dep);
             if header_idx == header_deps.len() {
@@ -422,3 +444,82 @@ impl TxBuilder for DaoWithdrawBuilder {
             .build())
     }
 }
+
+struct WithdrawItemInfo {
+    prepare_header: HeaderView,
+    deposit_header: HeaderView,
+    input_cell: CellOutput,
+    data: Bytes,
+}
+
+fn resolve_withdraw_item(
+    out_point: &OutPoint,
+    dao_type_script: &Script,
+    header_dep_resolver: &dyn HeaderDepResolver,
+    tx_dep_provider: &dyn TransactionDependencyProvider,
+) -> Result<WithdrawItemInfo, TxBuilderError> {
+    let tx_hash = out_point.tx_hash();
+    let prepare_header = header_dep_resolver
+        .resolve_by_tx(&tx_hash)
+        .map_err(TxBuilderError::Other)?
+        .ok_or_else(|| TxBuilderError::ResolveHeaderDepByTxHashFailed(tx_hash.clone()))?;
+    let input_cell = tx_dep_provider.get_cell(out_point)?;
+    if input_cell.type_().to_opt().as_ref() != Some(dao_type_script) {
+        return Err(TxBuilderError::InvalidParameter(anyhow!(
+            "the input cell has invalid type script"
+        )));
+    }
+    let data = tx_dep_provider.get_cell_data(out_point)?;
+    if data.len() != 8 {
+        return Err(TxBuilderError::InvalidParameter(anyhow!(
+            "the input cell has invalid data length, expected: 8, got: {}",
+            data.len()
+        )));
+    }
+    let deposit_number = {
+        let mut number_bytes = [0u8; 8];
+        number_bytes.copy_from_slice(data.as_ref());
+        u64::from_le_bytes(number_bytes)
+    };
+    let deposit_header = match get_header_by_number(tx_dep_provider, deposit_number)? {
+        Some(header) => header,
+        None => header_dep_resolver
+            .resolve_by_number(deposit_number)
+            .or_else(|_err| {
+                // for light client
+                let prepare_tx = tx_dep_provider.get_transaction(&tx_hash)?;
+                for input in prepare_tx.inputs() {
+                    let _ =
+                        header_dep_resolver.resolve_by_tx(&input.previous_output().tx_hash())?;
+                }
+                header_dep_resolver.resolve_by_number(deposit_number)
+            })
+            .map_err(TxBuilderError::Other)?
+            .ok_or(TxBuilderError::ResolveHeaderDepByNumberFailed(
+                deposit_number,
+            ))?,
+    };
+    Ok(WithdrawItemInfo {
+        prepare_header,
+        deposit_header,
+        input_cell,
+        data,
+    })
+}
+
+fn maximum_withdraw(
+    deposit_header: &HeaderView,
+    prepare_header: &HeaderView,
+    input_cell: &CellOutput,
+    data: &Bytes,
+) -> u64 {
+    let occupied_capacity = input_cell
+        .occupied_capacity(Capacity::bytes(data.len()).unwrap())
+        .unwrap();
+    calculate_dao_maximum_withdraw4(
+        deposit_header,
+        prepare_header,
+        input_cell,
+        occupied_capacity.as_u64(),
+    )
+}
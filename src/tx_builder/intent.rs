@@ -0,0 +1,339 @@
+use std::collections::HashSet;
+
+use anyhow::anyhow;
+use ckb_types::{
+    bytes::Bytes,
+    core::TransactionView,
+    packed::{CellOutput, OutPoint, Script, Transaction, WitnessArgs},
+    prelude::*,
+    H256,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{transfer::CapacityTransferBuilder, CapacityBalancer};
+use crate::traits::{CellCollector, CellCollectorError, CellQueryOptions, LiveCell};
+use crate::util::CkbHasher;
+
+/// A logical payment identified by `id`, which may be built (and sent) more than
+/// once, e.g. when a payout job retries after a crash.
+///
+/// The cells are selected by [`SeededCellCollector`] in an order seeded by the
+/// intent, so the same intent builds the same transaction (and the same tx hash)
+/// from the same set of live cells. It's only best-effort: any change of the live
+/// cells (a new cell, a cell spent by another transaction, an indexer lagging
+/// behind) may change the selection. Always check the previous attempt by
+/// [`resume`] with the saved [`PaymentRecord`] before building again.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PaymentIntent {
+    pub id: String,
+    pub receivers: Vec<(CellOutput, Bytes)>,
+    /// The fee rate (shannons/KB)
+    pub fee_rate: u64,
+}
+
+impl PaymentIntent {
+    pub fn new(id: String, receivers: Vec<(CellOutput, Bytes)>, fee_rate: u64) -> PaymentIntent {
+        PaymentIntent {
+            id,
+            receivers,
+            fee_rate,
+        }
+    }
+
+    /// The seed of the cell selection, derived from all the fields of the intent
+    pub fn seed(&self) -> [u8; 32] {
+        let mut hasher = CkbHasher::new();
+        hasher
+            .update(&(self.id.len() as u64).to_le_bytes())
+            .update(self.id.as_bytes())
+            .update(&self.fee_rate.to_le_bytes());
+        for (output, data) in &self.receivers {
+            hasher
+                .update(output.as_slice())
+                .update(data.pack().as_slice());
+        }
+        hasher.finalize()
+    }
+
+    pub fn builder(&self) -> CapacityTransferBuilder {
+        CapacityTransferBuilder::new(self.receivers.clone())
+    }
+
+    /// A balancer collecting capacity from `sender` with the fee rate of the intent
+    pub fn balancer(&self, sender: Script, placeholder_witness: WitnessArgs) -> CapacityBalancer {
+        CapacityBalancer::new_simple(sender, placeholder_witness, self.fee_rate)
+    }
+
+    /// Wrap the cell collector to select the cells in the order seeded by the intent
+    pub fn cell_collector(&self, inner: Box<dyn CellCollector>) -> SeededCellCollector {
+        SeededCellCollector::new(inner, self.seed())
+    }
+
+    /// The record of the built transaction, it should be saved before the
+    /// transaction is sent.
+    pub fn record(&self, tx: &TransactionView) -> PaymentRecord {
+        PaymentRecord {
+            id: self.id.clone(),
+            tx_hash: tx.hash().unpack(),
+            inputs: tx.input_pts_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// The consumed out points and the hash of a transaction built for a
+/// [`PaymentIntent`].
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PaymentRecord {
+    pub id: String,
+    pub tx_hash: H256,
+    pub inputs: Vec<ckb_jsonrpc_types::OutPoint>,
+}
+
+/// A cell collector collecting all the matched cells from the inner collector and
+/// taking them in the order of `blake2b(seed || out_point)`.
+///
+/// Every call reads all the matched cells from the inner collector, so it's slower
+/// than the inner collector for the lock scripts with a lot of cells.
+///
+/// The cells collected with `apply_changes` are also locked in the inner collector,
+/// at the highest tip block number passed to `lock_cell`/`apply_tx` so far (or the
+/// block number of the cell if it's higher).
+pub struct SeededCellCollector {
+    inner: Box<dyn CellCollector>,
+    seed: [u8; 32],
+    // the cells collected with `apply_changes`
+    consumed: HashSet<OutPoint>,
+    // the highest tip block number passed to `lock_cell`/`apply_tx`
+    tip_block_number: u64,
+}
+
+impl Clone for SeededCellCollector {
+    fn clone(&self) -> Self {
+        SeededCellCollector {
+            inner: dyn_clone::clone_box(&*self.inner),
+            seed: self.seed,
+            consumed: self.consumed.clone(),
+            tip_block_number: self.tip_block_number,
+        }
+    }
+}
+
+impl SeededCellCollector {
+    pub fn new(inner: Box<dyn CellCollector>, seed: [u8; 32]) -> SeededCellCollector {
+        SeededCellCollector {
+            inner,
+            seed,
+            consumed: HashSet::new(),
+            tip_block_number: 0,
+        }
+    }
+
+    /// Unwrap the inner collector, the cells collected with `apply_changes` are
+    /// still locked in it.
+    pub fn into_inner(self) -> Box<dyn CellCollector> {
+        self.inner
+    }
+
+    /// The out points collected with `apply_changes`
+    pub fn consumed(&self) -> &HashSet<OutPoint> {
+        &self.consumed
+    }
+
+    fn sort_key(&self, out_point: &OutPoint) -> [u8; 32] {
+        let mut hasher = CkbHasher::new();
+        hasher.update(&self.seed).update(out_point.as_slice());
+        hasher.finalize()
+    }
+}
+
+impl CellCollector for SeededCellCollector {
+    fn collect_live_cells(
+        &mut self,
+        query: &CellQueryOptions,
+        apply_changes: bool,
+    ) -> Result<(Vec<LiveCell>, u64), CellCollectorError> {
+        let mut all_query = query.clone();
+        all_query.min_total_capacity = u64::MAX;
+        let (mut candidates, _) = self.inner.collect_live_cells(&all_query, false)?;
+        candidates.retain(|cell| !self.consumed.contains(&cell.out_point));
+        candidates.sort_by_cached_key(|cell| self.sort_key(&cell.out_point));
+
+        let mut total_capacity: u64 = 0;
+        let mut cells = Vec::new();
+        for cell in candidates {
+            if total_capacity >= query.min_total_capacity {
+                break;
+            }
+            let capacity: u64 = cell.output.capacity().unpack();
+            total_capacity = total_capacity.checked_add(capacity).ok_or_else(|| {
+                CellCollectorError::Other(anyhow!("the total capacity overflows"))
+            })?;
+            cells.push(cell);
+        }
+        if apply_changes {
+            for cell in &cells {
+                let tip_block_number = self.tip_block_number.max(cell.block_number);
+                self.inner
+                    .lock_cell(cell.out_point.clone(), tip_block_number)?;
+                self.consumed.insert(cell.out_point.clone());
+            }
+        }
+        Ok((cells, total_capacity))
+    }
+
+    fn lock_cell(
+        &mut self,
+        out_point: OutPoint,
+        tip_block_number: u64,
+    ) -> Result<(), CellCollectorError> {
+        self.tip_block_number = self.tip_block_number.max(tip_block_number);
+        self.inner.lock_cell(out_point, tip_block_number)
+    }
+    fn unlock_cell(&mut self, out_point: OutPoint) -> Result<(), CellCollectorError> {
+        self.consumed.remove(&out_point);
+        self.inner.unlock_cell(out_point)
+    }
+    fn apply_tx(
+        &mut self,
+        tx: Transaction,
+        tip_block_number: u64,
+    ) -> Result<(), CellCollectorError> {
+        self.tip_block_number = self.tip_block_number.max(tip_block_number);
+        self.inner.apply_tx(tx, tip_block_number)
+    }
+    fn reset(&mut self) {
+        self.consumed.clear();
+        self.inner.reset();
+    }
+}
+
+/// The state of a previous attempt of a [`PaymentIntent`]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ResumeStatus {
+    /// The recorded transaction is in the tx-pool or committed, don't build again
+    Sent(H256),
+    /// Some recorded inputs are spent but the recorded transaction is unknown to the
+    /// node, the payment may or may not be done, check it manually
+    InputsSpent(Vec<OutPoint>),
+    /// No previous attempt reached the node, it's safe to build the transaction
+    Build,
+}
+
+/// Check the previous attempt of the payment before building a new transaction:
+/// query the recorded transaction by `get_transaction`, and the recorded inputs by
+/// `get_live_cell` if the transaction is not found.
+#[cfg(feature = "rpc")]
+pub fn resume(
+    record: Option<&PaymentRecord>,
+    rpc_client: &crate::rpc::CkbRpcClient,
+) -> Result<ResumeStatus, crate::RpcError> {
+    use ckb_jsonrpc_types::Status;
+
+    let record = match record {
+        Some(record) => record,
+        None => return Ok(ResumeStatus::Build),
+    };
+    if let Some(tx) = rpc_client.get_transaction(record.tx_hash.clone())? {
+        if matches!(
+            tx.tx_status.status,
+            Status::Pending | Status::Proposed | Status::Committed
+        ) {
+            return Ok(ResumeStatus::Sent(record.tx_hash.clone()));
+        }
+    }
+    let mut spent = Vec::new();
+    for out_point in &record.inputs {
+        let cell = rpc_client.get_live_cell(out_point.clone(), false)?;
+        if cell.status != "live" {
+            spent.push(out_point.clone().into());
+        }
+    }
+    if spent.is_empty() {
+        Ok(ResumeStatus::Build)
+    } else {
+        Ok(ResumeStatus::InputsSpent(spent))
+    }
+}
+
+#[cfg(all(test, feature = "rpc"))]
+mod tests {
+    use httpmock::prelude::*;
+
+    use super::*;
+    use crate::{rpc::CkbRpcClient, test_util::random_out_point};
+
+    fn mock_rpc<'a>(
+        server: &'a MockServer,
+        method: &str,
+        result: serde_json::Value,
+    ) -> httpmock::Mock<'a> {
+        let method = format!("\"{}\"", method);
+        server.mock(|when, then| {
+            when.method(POST).path("/").body_contains(&method);
+            then.status(200).body(
+                serde_json::json!({ "id": 42, "jsonrpc": "2.0", "result": result }).to_string(),
+            );
+        })
+    }
+
+    fn tx_status(status: &str) -> serde_json::Value {
+        serde_json::json!({
+            "transaction": null,
+            "cycles": null,
+            "time_added_to_pool": null,
+            "tx_status": {
+                "status": status,
+                "block_number": null,
+                "block_hash": null,
+                "reason": null,
+            },
+            "fee": null,
+            "min_replace_fee": null,
+        })
+    }
+
+    #[test]
+    fn test_resume() {
+        let server = MockServer::start();
+        let client = CkbRpcClient::new(&server.base_url());
+        let input = random_out_point();
+        let record = PaymentRecord {
+            id: "payout-1".to_string(),
+            tx_hash: H256::from([1u8; 32]),
+            inputs: vec![input.clone().into()],
+        };
+        assert_eq!(resume(None, &client).unwrap(), ResumeStatus::Build);
+
+        let mut tx = mock_rpc(&server, "get_transaction", tx_status("proposed"));
+        assert_eq!(
+            resume(Some(&record), &client).unwrap(),
+            ResumeStatus::Sent(record.tx_hash.clone())
+        );
+        tx.delete();
+
+        mock_rpc(&server, "get_transaction", tx_status("rejected"));
+        let mut cell = mock_rpc(
+            &server,
+            "get_live_cell",
+            serde_json::json!({ "cell": null, "status": "dead" }),
+        );
+        assert_eq!(
+            resume(Some(&record), &client).unwrap(),
+            ResumeStatus::InputsSpent(vec![input])
+        );
+        cell.delete();
+
+        mock_rpc(
+            &server,
+            "get_live_cell",
+            serde_json::json!({ "cell": null, "status": "live" }),
+        );
+        assert_eq!(resume(Some(&record), &client).unwrap(), ResumeStatus::Build);
+
+        let json = serde_json::to_string(&record).unwrap();
+        assert_eq!(
+            serde_json::from_str::<PaymentRecord>(&json).unwrap(),
+            record
+        );
+    }
+}
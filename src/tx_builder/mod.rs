@@ -1,39 +1,59 @@
 pub mod acp;
 pub mod cheque;
 pub mod dao;
+pub mod intent;
 pub mod omni_lock;
+pub mod otx;
+pub mod simple;
+pub mod store;
 pub mod transfer;
 pub mod udt;
 
 use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+#[cfg(feature = "rpc")]
 use std::sync::Arc;
 
 use anyhow::anyhow;
+#[cfg(feature = "rpc")]
 use ckb_chain_spec::consensus::Consensus;
+#[cfg(feature = "rpc")]
 use ckb_script::{TransactionScriptsVerifier, TxVerifyEnv};
+#[cfg(feature = "rpc")]
 use ckb_traits::{CellDataProvider, ExtensionProvider, HeaderProvider};
 use thiserror::Error;
 
-use ckb_types::core::cell::{CellProvider, HeaderChecker};
-use ckb_types::core::HeaderView;
+#[cfg(feature = "rpc")]
+use ckb_types::core::{
+    cell::{resolve_transaction, CellProvider, HeaderChecker},
+    HeaderView,
+};
 use ckb_types::{
+    bytes::Bytes,
     core::{
-        cell::resolve_transaction, error::OutPointError, Capacity, CapacityError, FeeRate,
-        TransactionView,
+        error::OutPointError, Capacity, CapacityError as CoreCapacityError,
+        EpochNumberWithFraction, FeeRate, TransactionView,
+    },
+    packed::{
+        Byte32, CellDep, CellInput, CellOutput, OutPoint, Script, WitnessArgs, WitnessArgsReader,
     },
-    packed::{Byte32, CellInput, CellOutput, Script, WitnessArgs},
     prelude::*,
 };
 
-use crate::types::ScriptGroup;
-use crate::types::{HumanCapacity, ScriptId};
-use crate::unlock::{ScriptUnlocker, UnlockError};
-use crate::util::calculate_dao_maximum_withdraw4;
+use crate::constants::{MAX_BLOCK_BYTES, SECP_SIGNATURE_SIZE};
+use crate::types::{HumanCapacity, ScriptId, Since, SinceType};
+use crate::types::{ScriptGroup, ScriptGroupType};
+use crate::unlock::{group_witness_index, subslice_offset, ScriptUnlocker, UnlockError};
+use crate::util::{
+    calculate_dao_maximum_withdraw4, cellbase_mature_epoch, check_input_since,
+    get_header_by_number, SinceCheckError, SinceCheckResult,
+};
 use crate::{constants::DAO_TYPE_HASH, NetworkType};
 use crate::{
     traits::{
-        CellCollector, CellCollectorError, CellDepResolver, CellQueryOptions, HeaderDepResolver,
-        TransactionDependencyError, TransactionDependencyProvider, ValueRangeOption,
+        dummy_impls::DummyHeaderDepResolver, CellCollector, CellCollectorError, CellDepResolver,
+        CellQueryOptions, HeaderDepResolver, LiveCell, MaturityOption, TransactionDependencyError,
+        TransactionDependencyProvider, ValueRangeOption,
     },
     RpcError,
 };
@@ -76,6 +96,15 @@ pub enum TxBuilderError {
     #[error("can not find specifed output to put small change")]
     NoOutputForSmallChange,
 
+    #[error("resolve cell deps error: `{0}`")]
+    Resolution(#[from] ResolutionError),
+
+    #[error("check since error: `{0}`")]
+    SinceCheck(#[from] SinceCheckError),
+
+    #[error("input `{0}` can not be spent at the tip, earliest: `{1:?}`")]
+    SinceNotSatisfied(OutPoint, SinceCheckResult),
+
     #[error("other error: `{0}`")]
     Other(anyhow::Error),
 }
@@ -104,22 +133,51 @@ pub trait TxBuilder {
         balancer: &CapacityBalancer,
         unlockers: &HashMap<ScriptId, Box<dyn ScriptUnlocker>>,
     ) -> Result<TransactionView, TxBuilderError> {
-        let base_tx = self.build_base(
+        let (balanced_tx, _) = build_balanced_inner(
+            self,
             cell_collector,
             cell_dep_resolver,
             header_dep_resolver,
             tx_dep_provider,
+            balancer,
+            unlockers,
         )?;
-        let (tx_filled_witnesses, _) =
-            fill_placeholder_witnesses(base_tx, tx_dep_provider, unlockers)?;
-        Ok(balance_tx_capacity(
-            &tx_filled_witnesses,
+        Ok(balanced_tx)
+    }
+
+    /// Estimate the transaction without signing it or locking any cell: the transaction
+    /// is built and balanced as `build_balanced`, but on a clone of `cell_collector`, so
+    /// the cells collected here are still available to the real build afterwards.
+    ///
+    /// Return value:
+    ///   * The balanced transaction with the placeholder witnesses
+    ///   * The estimate of the transaction, the `cycles` is not estimated, see
+    ///     `BuildEstimate::estimate_cycles`
+    fn dry_run(
+        &self,
+        cell_collector: &dyn CellCollector,
+        cell_dep_resolver: &dyn CellDepResolver,
+        header_dep_resolver: &dyn HeaderDepResolver,
+        tx_dep_provider: &dyn TransactionDependencyProvider,
+        balancer: &CapacityBalancer,
+        unlockers: &HashMap<ScriptId, Box<dyn ScriptUnlocker>>,
+    ) -> Result<(TransactionView, BuildEstimate), TxBuilderError> {
+        let mut cell_collector = dyn_clone::clone_box(cell_collector);
+        let balanced_tx = self.build_balanced(
+            cell_collector.as_mut(),
+            cell_dep_resolver,
+            header_dep_resolver,
+            tx_dep_provider,
             balancer,
-            cell_collector,
+            unlockers,
+        )?;
+        let estimate = BuildEstimate::new(
+            &balanced_tx,
+            balancer.fee_rate.as_u64(),
             tx_dep_provider,
-            cell_dep_resolver,
             header_dep_resolver,
-        )?)
+        )?;
+        Ok((balanced_tx, estimate))
     }
 
     /// Build unlocked transaction that ready to send or for further unlock:
@@ -139,7 +197,8 @@ pub trait TxBuilder {
         balancer: &CapacityBalancer,
         unlockers: &HashMap<ScriptId, Box<dyn ScriptUnlocker>>,
     ) -> Result<(TransactionView, Vec<ScriptGroup>), TxBuilderError> {
-        let balanced_tx = self.build_balanced(
+        let (tx, _, still_locked_groups) = build_unlocked_inner(
+            self,
             cell_collector,
             cell_dep_resolver,
             header_dep_resolver,
@@ -147,7 +206,41 @@ pub trait TxBuilder {
             balancer,
             unlockers,
         )?;
-        Ok(unlock_tx(balanced_tx, tx_dep_provider, unlockers)?)
+        Ok((tx, still_locked_groups))
+    }
+
+    /// Same as `build_unlocked`, except the transaction is returned as a [`BuiltTransaction`],
+    /// which reports the transaction hash, the change output, the paid fee and the consumed
+    /// cells, it can be applied to the cell collector by `CellCollector::apply_built_tx`.
+    ///
+    /// Return value:
+    ///   * The built transaction
+    ///   * The script groups that not unlocked by given `unlockers`
+    fn build_unlocked_result(
+        &self,
+        cell_collector: &mut dyn CellCollector,
+        cell_dep_resolver: &dyn CellDepResolver,
+        header_dep_resolver: &dyn HeaderDepResolver,
+        tx_dep_provider: &dyn TransactionDependencyProvider,
+        balancer: &CapacityBalancer,
+        unlockers: &HashMap<ScriptId, Box<dyn ScriptUnlocker>>,
+    ) -> Result<(BuiltTransaction, Vec<ScriptGroup>), TxBuilderError> {
+        let (tx, change_idx, still_locked_groups) = build_unlocked_inner(
+            self,
+            cell_collector,
+            cell_dep_resolver,
+            header_dep_resolver,
+            tx_dep_provider,
+            balancer,
+            unlockers,
+        )?;
+        let built_tx = BuiltTransaction::new(
+            tx,
+            balancer.change_outputs(change_idx),
+            tx_dep_provider,
+            header_dep_resolver,
+        )?;
+        Ok((built_tx, still_locked_groups))
     }
 
     /// Build unlocked transaction that ready to send or for further unlock, it's similar to `build_unlocked`,
@@ -158,6 +251,7 @@ pub trait TxBuilder {
     /// Return value:
     ///   * The built transaction
     ///   * The script groups that not unlocked by given `unlockers`
+    #[cfg(feature = "rpc")]
     fn build_balance_unlocked(
         &self,
         cell_collector: &mut dyn CellCollector,
@@ -167,54 +261,192 @@ pub trait TxBuilder {
         balancer: &CapacityBalancer,
         unlockers: &HashMap<ScriptId, Box<dyn ScriptUnlocker>>,
     ) -> Result<(TransactionView, Vec<ScriptGroup>), TxBuilderError> {
-        let base_tx = self.build_base(
+        let (tx, unlocked_group, _) = self.build_balance_unlocked_with_cycles(
             cell_collector,
             cell_dep_resolver,
             header_dep_resolver,
             tx_dep_provider,
-        )?;
-        let (tx_filled_witnesses, _) =
-            fill_placeholder_witnesses(base_tx, tx_dep_provider, unlockers)?;
-        let (balanced_tx, mut change_idx) = rebalance_tx_capacity(
-            &tx_filled_witnesses,
             balancer,
+            unlockers,
+        )?;
+        Ok((tx, unlocked_group))
+    }
+
+    /// Same as `build_balance_unlocked`, also return the cycles measured by local
+    /// verification, `None` when some script groups are not unlocked (the cycles
+    /// are not measured).
+    #[cfg(feature = "rpc")]
+    fn build_balance_unlocked_with_cycles(
+        &self,
+        cell_collector: &mut dyn CellCollector,
+        cell_dep_resolver: &dyn CellDepResolver,
+        header_dep_resolver: &dyn HeaderDepResolver,
+        tx_dep_provider: &'static dyn TransactionDependencyProvider,
+        balancer: &CapacityBalancer,
+        unlockers: &HashMap<ScriptId, Box<dyn ScriptUnlocker>>,
+    ) -> Result<(TransactionView, Vec<ScriptGroup>, Option<u64>), TxBuilderError> {
+        let (tx, _, unlocked_group, cycles) = build_balance_unlocked_inner(
+            self,
             cell_collector,
+            cell_dep_resolver,
+            header_dep_resolver,
             tx_dep_provider,
+            balancer,
+            unlockers,
+        )?;
+        Ok((tx, unlocked_group, cycles))
+    }
+
+    /// Same as `build_balance_unlocked`, except the transaction is returned as a
+    /// [`BuiltTransaction`], see `build_unlocked_result`.
+    ///
+    /// Return value:
+    ///   * The built transaction
+    ///   * The script groups that not unlocked by given `unlockers`
+    #[cfg(feature = "rpc")]
+    fn build_balance_unlocked_result(
+        &self,
+        cell_collector: &mut dyn CellCollector,
+        cell_dep_resolver: &dyn CellDepResolver,
+        header_dep_resolver: &dyn HeaderDepResolver,
+        tx_dep_provider: &'static dyn TransactionDependencyProvider,
+        balancer: &CapacityBalancer,
+        unlockers: &HashMap<ScriptId, Box<dyn ScriptUnlocker>>,
+    ) -> Result<(BuiltTransaction, Vec<ScriptGroup>), TxBuilderError> {
+        let (tx, change_idx, unlocked_group, _) = build_balance_unlocked_inner(
+            self,
+            cell_collector,
             cell_dep_resolver,
             header_dep_resolver,
-            0,
-            None,
+            tx_dep_provider,
+            balancer,
+            unlockers,
+        )?;
+        let built_tx = BuiltTransaction::new(
+            tx,
+            balancer.change_outputs(change_idx),
+            tx_dep_provider,
+            header_dep_resolver,
         )?;
-        let (mut tx, unlocked_group) = unlock_tx(balanced_tx, tx_dep_provider, unlockers)?;
-        if unlocked_group.is_empty() {
-            let mut ready = false;
-            const MAX_LOOP_TIMES: u32 = 16;
-            let mut n = 0;
-            while !ready && n < MAX_LOOP_TIMES {
-                n += 1;
-
-                let (new_tx, new_change_idx, ok) = balancer.check_cycle_fee(
-                    tx,
-                    cell_collector,
-                    tx_dep_provider,
-                    cell_dep_resolver,
-                    header_dep_resolver,
-                    change_idx,
-                )?;
-                tx = new_tx;
-                ready = ok;
+        Ok((built_tx, unlocked_group))
+    }
+}
+
+/// Build the base transaction, fill the placeholder witnesses and balance the
+/// capacity, also return the index of the change output.
+fn build_balanced_inner<B: TxBuilder + ?Sized>(
+    builder: &B,
+    cell_collector: &mut dyn CellCollector,
+    cell_dep_resolver: &dyn CellDepResolver,
+    header_dep_resolver: &dyn HeaderDepResolver,
+    tx_dep_provider: &dyn TransactionDependencyProvider,
+    balancer: &CapacityBalancer,
+    unlockers: &HashMap<ScriptId, Box<dyn ScriptUnlocker>>,
+) -> Result<(TransactionView, Option<usize>), TxBuilderError> {
+    let base_tx = builder.build_base(
+        cell_collector,
+        cell_dep_resolver,
+        header_dep_resolver,
+        tx_dep_provider,
+    )?;
+    let (tx_filled_witnesses, _) = fill_placeholder_witnesses(base_tx, tx_dep_provider, unlockers)?;
+    Ok(rebalance_tx_capacity(
+        &tx_filled_witnesses,
+        balancer,
+        cell_collector,
+        tx_dep_provider,
+        cell_dep_resolver,
+        header_dep_resolver,
+        0,
+        None,
+    )?)
+}
+
+/// The shared implementation of `build_unlocked` and `build_unlocked_result`.
+fn build_unlocked_inner<B: TxBuilder + ?Sized>(
+    builder: &B,
+    cell_collector: &mut dyn CellCollector,
+    cell_dep_resolver: &dyn CellDepResolver,
+    header_dep_resolver: &dyn HeaderDepResolver,
+    tx_dep_provider: &dyn TransactionDependencyProvider,
+    balancer: &CapacityBalancer,
+    unlockers: &HashMap<ScriptId, Box<dyn ScriptUnlocker>>,
+) -> Result<(TransactionView, Option<usize>, Vec<ScriptGroup>), TxBuilderError> {
+    let (balanced_tx, change_idx) = build_balanced_inner(
+        builder,
+        cell_collector,
+        cell_dep_resolver,
+        header_dep_resolver,
+        tx_dep_provider,
+        balancer,
+        unlockers,
+    )?;
+    let (tx, still_locked_groups) = unlock_tx(balanced_tx, tx_dep_provider, unlockers)?;
+    Ok((tx, change_idx, still_locked_groups))
+}
+
+/// The shared implementation of `build_balance_unlocked_with_cycles` and
+/// `build_balance_unlocked_result`.
+#[cfg(feature = "rpc")]
+#[allow(clippy::type_complexity)]
+fn build_balance_unlocked_inner<B: TxBuilder + ?Sized>(
+    builder: &B,
+    cell_collector: &mut dyn CellCollector,
+    cell_dep_resolver: &dyn CellDepResolver,
+    header_dep_resolver: &dyn HeaderDepResolver,
+    tx_dep_provider: &'static dyn TransactionDependencyProvider,
+    balancer: &CapacityBalancer,
+    unlockers: &HashMap<ScriptId, Box<dyn ScriptUnlocker>>,
+) -> Result<
+    (
+        TransactionView,
+        Option<usize>,
+        Vec<ScriptGroup>,
+        Option<u64>,
+    ),
+    TxBuilderError,
+> {
+    let mut cycles = None;
+    let (balanced_tx, mut change_idx) = build_balanced_inner(
+        builder,
+        cell_collector,
+        cell_dep_resolver,
+        header_dep_resolver,
+        tx_dep_provider,
+        balancer,
+        unlockers,
+    )?;
+    let (mut tx, unlocked_group) = unlock_tx(balanced_tx, tx_dep_provider, unlockers)?;
+    if unlocked_group.is_empty() {
+        let mut ready = false;
+        const MAX_LOOP_TIMES: u32 = 16;
+        let mut n = 0;
+        while !ready && n < MAX_LOOP_TIMES {
+            n += 1;
+
+            let (new_tx, new_change_idx, ok, cycle) = balancer.check_cycle_fee_with_cycles(
+                tx,
+                cell_collector,
+                tx_dep_provider,
+                cell_dep_resolver,
+                header_dep_resolver,
+                change_idx,
+            )?;
+            tx = new_tx;
+            ready = ok;
+            cycles = Some(cycle);
+            if !ready {
+                // the change index is only returned when the transaction is rebalanced
                 change_idx = new_change_idx;
-                if !ready {
-                    let (new_tx, _) = unlock_tx(tx, tx_dep_provider, unlockers)?;
-                    tx = new_tx
-                }
-            }
-            if !ready && n >= MAX_LOOP_TIMES {
-                return Err(TxBuilderError::ExceedCycleMaxLoopTimes(n));
+                let (new_tx, _) = unlock_tx(tx, tx_dep_provider, unlockers)?;
+                tx = new_tx
             }
         }
-        Ok((tx, unlocked_group))
+        if !ready && n >= MAX_LOOP_TIMES {
+            return Err(TxBuilderError::ExceedCycleMaxLoopTimes(n));
+        }
     }
+    Ok((tx, change_idx, unlocked_group, cycles))
 }
 
 #[derive(Debug, Eq, PartialEq, Hash, Clone)]
@@ -240,12 +472,155 @@ pub enum TransactionFeeError {
     UnexpectedDaoWithdrawInput,
 
     #[error("capacity error: `{0}`")]
-    CapacityError(#[from] CapacityError),
+    CapacityError(#[from] CoreCapacityError),
 
     #[error("capacity sub overflow, delta: `{0}`")]
     CapacityOverflow(u64),
 }
 
+/// A change output of the built transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeOutput {
+    /// The output index in the transaction
+    pub index: usize,
+    /// The lock script of the change output, it's the capacity provider's lock
+    /// script which produced the change
+    pub lock_script: Script,
+}
+
+/// The built transaction with the information needed before sending it, e.g.
+/// register the change cell as an expected deposit or chain a follow-up transaction.
+#[derive(Debug, Clone)]
+pub struct BuiltTransaction {
+    pub tx: TransactionView,
+    /// The transaction hash, since the witnesses are not included, it won't
+    /// be changed by unlocking (signing) the transaction.
+    pub tx_hash: Byte32,
+    pub change_outputs: Vec<ChangeOutput>,
+    /// The actual fee paid by the transaction, include dao withdraw capacity
+    pub fee: u64,
+    /// The consumed cells (the inputs)
+    pub consumed_out_points: Vec<OutPoint>,
+}
+
+impl BuiltTransaction {
+    pub fn new(
+        tx: TransactionView,
+        change_outputs: Vec<ChangeOutput>,
+        tx_dep_provider: &dyn TransactionDependencyProvider,
+        header_dep_resolver: &dyn HeaderDepResolver,
+    ) -> Result<BuiltTransaction, TxBuilderError> {
+        let fee = tx_fee(tx.clone(), tx_dep_provider, header_dep_resolver)
+            .map_err(BalanceTxCapacityError::from)?;
+        Ok(BuiltTransaction::with_fee(tx, change_outputs, fee))
+    }
+
+    /// Same as `new`, but the fee is already known by the caller.
+    pub fn with_fee(
+        tx: TransactionView,
+        change_outputs: Vec<ChangeOutput>,
+        fee: u64,
+    ) -> BuiltTransaction {
+        let consumed_out_points = tx
+            .inputs()
+            .into_iter()
+            .map(|input| input.previous_output())
+            .collect();
+        BuiltTransaction {
+            tx_hash: tx.hash(),
+            tx,
+            change_outputs,
+            fee,
+            consumed_out_points,
+        }
+    }
+
+    /// The out points of the change outputs
+    pub fn change_out_points(&self) -> Vec<OutPoint> {
+        self.change_outputs
+            .iter()
+            .map(|change| OutPoint::new(self.tx_hash.clone(), change.index as u32))
+            .collect()
+    }
+}
+
+/// The estimate of a transaction built by `TxBuilder::dry_run`, the witnesses are
+/// placeholders so the signed transaction has the same size and fee.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildEstimate {
+    pub inputs_count: usize,
+    /// The serialized size (in block) of the transaction
+    pub tx_size: u64,
+    /// The actual fee paid by the transaction, include dao withdraw capacity
+    pub fee: u64,
+    /// The fee rate (shannons/KB) used to balance the transaction
+    pub fee_rate_used: u64,
+    /// The cycles consumed by the scripts, `None` if not estimated
+    pub cycles: Option<u64>,
+}
+
+impl BuildEstimate {
+    pub fn new(
+        tx: &TransactionView,
+        fee_rate: u64,
+        tx_dep_provider: &dyn TransactionDependencyProvider,
+        header_dep_resolver: &dyn HeaderDepResolver,
+    ) -> Result<BuildEstimate, TxBuilderError> {
+        let fee = tx_fee(tx.clone(), tx_dep_provider, header_dep_resolver)
+            .map_err(BalanceTxCapacityError::from)?;
+        Ok(BuildEstimate {
+            inputs_count: tx.inputs().len(),
+            tx_size: tx.data().as_reader().serialized_size_in_block() as u64,
+            fee,
+            fee_rate_used: fee_rate,
+            cycles: None,
+        })
+    }
+
+    /// Estimate the cycles of the dry run transaction by the `estimate_cycles` rpc.
+    ///
+    /// The scripts run with the placeholder witnesses, the lock scripts verifying the
+    /// signatures (e.g. sighash) will fail, only use it for the transactions whose
+    /// scripts accept the unsigned witnesses.
+    #[cfg(feature = "rpc")]
+    pub fn estimate_cycles(
+        &mut self,
+        tx: &TransactionView,
+        ckb_client: &crate::rpc::CkbRpcClient,
+    ) -> Result<u64, RpcError> {
+        let cycles = ckb_client.estimate_cycles(tx.data().into())?.cycles.value();
+        self.cycles = Some(cycles);
+        Ok(cycles)
+    }
+}
+
+#[derive(Error, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CapacityError {
+    #[error("outputs capacity exceeds inputs capacity by `{excess}` shannons")]
+    OutputsExceedInputs { excess: u64 },
+}
+
+/// Check the outputs capacity is covered by the inputs capacity before paying the
+/// fee, return the capacity left for the fee.
+///
+/// The dao withdraw capacity is not included, use `tx_fee` for the dao withdraw
+/// transactions.
+pub fn check_capacity_balance(
+    inputs: &[LiveCell],
+    outputs: &[CellOutput],
+) -> Result<u64, CapacityError> {
+    let capacity = |output: &CellOutput| u128::from(Unpack::<u64>::unpack(&output.capacity()));
+    let inputs_capacity: u128 = inputs.iter().map(|cell| capacity(&cell.output)).sum();
+    let outputs_capacity: u128 = outputs.iter().map(capacity).sum();
+    if outputs_capacity > inputs_capacity {
+        let excess = outputs_capacity - inputs_capacity;
+        return Err(CapacityError::OutputsExceedInputs {
+            excess: u64::try_from(excess).unwrap_or(u64::MAX),
+        });
+    }
+    Ok(u64::try_from(inputs_capacity - outputs_capacity).unwrap_or(u64::MAX))
+}
+
 /// Calculate the actual transaction fee of the transaction, include dao
 /// withdraw capacity.
 #[allow(clippy::unnecessary_lazy_evaluations)]
@@ -284,15 +659,18 @@ pub fn tx_fee(
                 number_bytes.copy_from_slice(data.as_ref());
                 u64::from_le_bytes(number_bytes)
             };
-            let deposit_header = header_dep_resolver
-                .resolve_by_number(deposit_number)
-                .map_err(TransactionFeeError::HeaderDep)?
-                .ok_or_else(|| {
-                    TransactionFeeError::HeaderDep(anyhow!(
-                        "resolve deposit header by block number failed: {}",
-                        deposit_number
-                    ))
-                })?;
+            let deposit_header = match get_header_by_number(tx_dep_provider, deposit_number)? {
+                Some(header) => header,
+                None => header_dep_resolver
+                    .resolve_by_number(deposit_number)
+                    .map_err(TransactionFeeError::HeaderDep)?
+                    .ok_or_else(|| {
+                        TransactionFeeError::HeaderDep(anyhow!(
+                            "resolve deposit header by block number failed: {}",
+                            deposit_number
+                        ))
+                    })?,
+            };
             let occupied_capacity = cell
                 .occupied_capacity(Capacity::bytes(data.len()).unwrap())
                 .unwrap();
@@ -384,6 +762,12 @@ pub enum BalanceTxCapacityError {
     #[error("Fail to parse since value from args, offset: `{0}`, args length: `{1}`")]
     InvalidSinceValue(usize, usize),
 
+    #[error("resolve the header of cellbase cell `{0}` failed: `{1}`")]
+    ResolveCellbaseHeader(OutPoint, String),
+
+    #[error("the since source of cellbase cell `{0}` is not zero")]
+    CellbaseSinceConflict(OutPoint),
+
     #[error("change index not found at given index: `{0}`")]
     ChangeIndexNotFound(usize),
 
@@ -395,6 +779,151 @@ pub enum BalanceTxCapacityError {
 
     #[error("should not try to rebalance, orignal fee {0}, required fee: {1},")]
     AlreadyBalance(u64, u64),
+
+    #[error("transaction size `{0}` exceeds the limit `{1}`, {2}")]
+    ExceedTxSizeLimit(u64, u64, String),
+
+    #[error("transaction cycles `{0}` exceeds the limit `{1}`, try to split off some inputs into another transaction")]
+    ExceedCyclesLimit(u64, u64),
+
+    #[error("invalid change output at index `{0}`: {1}")]
+    InvalidChangeOutput(usize, String),
+
+    #[error("check since error: `{0}`")]
+    SinceCheck(#[from] SinceCheckError),
+
+    #[error("input `{0}` can not be spent at the tip, earliest: `{1:?}`")]
+    SinceNotSatisfied(OutPoint, SinceCheckResult),
+}
+
+/// What the change output of the balanced transaction may carry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChangeStrategy {
+    /// The change output is a plain capacity cell: only the lock script, no type
+    /// script and empty data.
+    #[default]
+    Plain,
+    /// The existing output picked as the change output (by the change index) keeps
+    /// its type script and data, e.g. topping up an anyone-can-pay cell.
+    KeepTypeAndData,
+}
+
+/// What to do when the left capacity is not enough to create the change output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SmallChangePolicy {
+    /// Collect one more input of the capacity provider to create the change output,
+    /// the small change is only taken as fee when there is no more cell and
+    /// `force_small_change_as_fee` is set.
+    #[default]
+    CollectMore,
+    /// Take the small change as fee without collecting more inputs, it's limited by
+    /// `force_small_change_as_fee` if set.
+    AsFee,
+}
+
+// A plain change output must have no type script and empty data
+fn check_change_template(
+    tx: &TransactionView,
+    index: usize,
+    strategy: ChangeStrategy,
+) -> Result<(CellOutput, Bytes), BalanceTxCapacityError> {
+    let output = tx
+        .outputs()
+        .get(index)
+        .ok_or(BalanceTxCapacityError::ChangeIndexNotFound(index))?;
+    let data = tx
+        .outputs_data()
+        .get(index)
+        .map(|data| data.raw_data())
+        .unwrap_or_default();
+    if strategy == ChangeStrategy::Plain {
+        if output.type_().is_some() {
+            return Err(BalanceTxCapacityError::InvalidChangeOutput(
+                index,
+                "plain change output has type script".to_string(),
+            ));
+        }
+        if !data.is_empty() {
+            return Err(BalanceTxCapacityError::InvalidChangeOutput(
+                index,
+                format!("plain change output has {} bytes data", data.len()),
+            ));
+        }
+    }
+    Ok((output, data))
+}
+
+/// Check the change output against the strategy: a plain change output must have no
+/// type script and empty data, and the capacity must cover its occupied capacity.
+pub fn check_change_output(
+    tx: &TransactionView,
+    index: usize,
+    strategy: ChangeStrategy,
+) -> Result<(), BalanceTxCapacityError> {
+    let (output, data) = check_change_template(tx, index, strategy)?;
+    let capacity: u64 = output.capacity().unpack();
+    let occupied_capacity = output
+        .occupied_capacity(Capacity::bytes(data.len()).expect("data capacity"))
+        .expect("change occupied capacity")
+        .as_u64();
+    if capacity < occupied_capacity {
+        return Err(BalanceTxCapacityError::InvalidChangeOutput(
+            index,
+            format!(
+                "capacity {} is less than the occupied capacity {}",
+                HumanCapacity(capacity),
+                HumanCapacity(occupied_capacity)
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// The optional limits and policies of [`CapacityBalancer`].
+#[derive(Debug, Clone)]
+pub struct BalancerOptions {
+    /// The maximum serialized size (in block) of the balanced transaction, the
+    /// default value is [`MAX_BLOCK_BYTES`], `None` for no limit.
+    pub max_tx_size: Option<u64>,
+
+    /// The maximum cycles of the transaction, only checked when the cycles
+    /// is estimated (see `TxBuilder::build_balance_unlocked`), `None` for no limit.
+    pub max_cycles: Option<u64>,
+
+    /// What the change output may carry, the default is a plain capacity cell.
+    pub change_strategy: ChangeStrategy,
+
+    /// What to do when the left capacity is not enough for the change output.
+    pub small_change_policy: SmallChangePolicy,
+
+    /// The `cellbase_maturity` of the consensus, when set the immature cellbase
+    /// cells of the capacity provider are also collected, the since of such an
+    /// input is the absolute epoch from which the cell can be spent (see
+    /// [`cellbase_mature_epoch`]). `None` to collect the mature cells only.
+    pub cellbase_maturity: Option<EpochNumberWithFraction>,
+
+    /// Split the outputs of the multi-recipient builders into several transactions
+    /// when they can not fit into `max_tx_size` (see `CapacityTransferBuilder::build_split_unlocked`).
+    pub auto_split: bool,
+
+    /// Check the since of the collected time-locked cells (e.g. the multisig cells with
+    /// since in the lock args) against the tip (see [`HeaderDepResolver::resolve_tip`]),
+    /// the cells can not be spent yet are skipped. It's disabled by default.
+    pub check_since: bool,
+}
+
+impl Default for BalancerOptions {
+    fn default() -> Self {
+        BalancerOptions {
+            max_tx_size: Some(MAX_BLOCK_BYTES),
+            max_cycles: None,
+            change_strategy: ChangeStrategy::Plain,
+            small_change_policy: SmallChangePolicy::CollectMore,
+            cellbase_maturity: None,
+            auto_split: false,
+            check_since: false,
+        }
+    }
 }
 
 /// Transaction capacity balancer config.
@@ -415,6 +944,9 @@ pub struct CapacityBalancer {
     /// transaction capacity, force the addition capacity as fee, the value is
     /// actual maximum transaction fee.
     pub force_small_change_as_fee: Option<u64>,
+
+    /// The limits and policies added on top of the fields above, see [`BalancerOptions`].
+    pub options: BalancerOptions,
 }
 
 impl CapacityBalancer {
@@ -440,6 +972,7 @@ impl CapacityBalancer {
             )]),
             change_lock_script: None,
             force_small_change_as_fee: None,
+            options: BalancerOptions::default(),
         }
     }
 
@@ -459,6 +992,7 @@ impl CapacityBalancer {
             )]),
             change_lock_script: None,
             force_small_change_as_fee: None,
+            options: BalancerOptions::default(),
         }
     }
 
@@ -468,6 +1002,7 @@ impl CapacityBalancer {
             capacity_provider,
             change_lock_script: None,
             force_small_change_as_fee: None,
+            options: BalancerOptions::default(),
         }
     }
 
@@ -476,6 +1011,96 @@ impl CapacityBalancer {
         self.force_small_change_as_fee = max_fee;
     }
 
+    /// The lock script of the change output, if `change_lock_script` is `None`
+    /// use capacity_provider's first lock script.
+    ///
+    /// Panics if both `change_lock_script` and capacity_provider's lock scripts are empty.
+    pub fn change_lock_script(&self) -> Script {
+        self.change_lock_script
+            .clone()
+            .unwrap_or_else(|| self.capacity_provider.lock_scripts[0].0.clone())
+    }
+
+    /// The change output of the balanced transaction, `change_idx` is the index
+    /// returned by the balancing.
+    fn change_outputs(&self, change_idx: Option<usize>) -> Vec<ChangeOutput> {
+        change_idx
+            .map(|index| ChangeOutput {
+                index,
+                lock_script: self.change_lock_script(),
+            })
+            .into_iter()
+            .collect()
+    }
+
+    /// The maximum serialized size (in block) of the balanced transaction, the
+    /// default value is [`MAX_BLOCK_BYTES`], `None` for no limit.
+    pub fn max_tx_size(&self) -> Option<u64> {
+        self.options.max_tx_size
+    }
+
+    /// Set or clear the maximum transaction size
+    pub fn set_max_tx_size(&mut self, max_tx_size: Option<u64>) {
+        self.options.max_tx_size = max_tx_size;
+    }
+
+    /// The maximum cycles of the transaction, only checked when the cycles
+    /// is estimated (see `TxBuilder::build_balance_unlocked`), `None` for no limit.
+    pub fn max_cycles(&self) -> Option<u64> {
+        self.options.max_cycles
+    }
+
+    /// Set or clear the maximum transaction cycles
+    pub fn set_max_cycles(&mut self, max_cycles: Option<u64>) {
+        self.options.max_cycles = max_cycles;
+    }
+
+    /// What the change output may carry, the default is a plain capacity cell.
+    pub fn change_strategy(&self) -> ChangeStrategy {
+        self.options.change_strategy
+    }
+
+    /// Set the change strategy
+    pub fn set_change_strategy(&mut self, strategy: ChangeStrategy) {
+        self.options.change_strategy = strategy;
+    }
+
+    /// What to do when the left capacity is not enough for the change output.
+    pub fn small_change_policy(&self) -> SmallChangePolicy {
+        self.options.small_change_policy
+    }
+
+    /// Set the small change policy
+    pub fn set_small_change_policy(&mut self, policy: SmallChangePolicy) {
+        self.options.small_change_policy = policy;
+    }
+
+    /// The `cellbase_maturity` used to collect the immature cellbase cells.
+    pub fn cellbase_maturity(&self) -> Option<EpochNumberWithFraction> {
+        self.options.cellbase_maturity
+    }
+
+    /// Set or clear the cellbase maturity for collecting the immature cellbase cells
+    pub fn set_cellbase_maturity(&mut self, cellbase_maturity: Option<EpochNumberWithFraction>) {
+        self.options.cellbase_maturity = cellbase_maturity;
+    }
+
+    /// Whether the multi-recipient builders split the outputs into several transactions.
+    pub fn auto_split(&self) -> bool {
+        self.options.auto_split
+    }
+
+    /// Enable or disable splitting the outputs into several transactions when
+    /// they can not fit into `max_tx_size`.
+    pub fn set_auto_split(&mut self, auto_split: bool) {
+        self.options.auto_split = auto_split;
+    }
+
+    /// Enable or disable checking the since of the collected time-locked cells.
+    pub fn set_check_since(&mut self, check_since: bool) {
+        self.options.check_since = check_since;
+    }
+
     pub fn balance_tx_capacity(
         &mut self,
         tx: &TransactionView,
@@ -506,12 +1131,9 @@ impl CapacityBalancer {
         change_index: Option<usize>,
     ) -> Result<(TransactionView, Option<usize>), BalanceTxCapacityError> {
         if let Some(idx) = change_index {
-            let output = tx
-                .outputs()
-                .get(idx)
-                .ok_or(BalanceTxCapacityError::ChangeIndexNotFound(idx))?;
+            let (output, data) = check_change_template(tx, idx, self.options.change_strategy)?;
             let base_change_occupied_capacity = output
-                .occupied_capacity(Capacity::zero())
+                .occupied_capacity(Capacity::bytes(data.len()).expect("data capacity"))
                 .expect("init change occupied capacity")
                 .as_u64();
             let output_header_extra = 4 + 4 + 4;
@@ -555,6 +1177,7 @@ impl CapacityBalancer {
         )
     }
 
+    #[cfg(feature = "rpc")]
     pub fn check_cycle_fee(
         &self,
         tx: TransactionView,
@@ -564,18 +1187,45 @@ impl CapacityBalancer {
         header_dep_resolver: &dyn HeaderDepResolver,
         change_index: Option<usize>,
     ) -> Result<(TransactionView, Option<usize>, bool), BalanceTxCapacityError> {
+        let (tx, idx, ok, _) = self.check_cycle_fee_with_cycles(
+            tx,
+            cell_collector,
+            tx_dep_provider,
+            cell_dep_resolver,
+            header_dep_resolver,
+            change_index,
+        )?;
+        Ok((tx, idx, ok))
+    }
+
+    /// Same as `check_cycle_fee`, also return the measured cycles of the given transaction.
+    #[cfg(feature = "rpc")]
+    pub fn check_cycle_fee_with_cycles(
+        &self,
+        tx: TransactionView,
+        cell_collector: &mut dyn CellCollector,
+        tx_dep_provider: &'static dyn TransactionDependencyProvider,
+        cell_dep_resolver: &dyn CellDepResolver,
+        header_dep_resolver: &dyn HeaderDepResolver,
+        change_index: Option<usize>,
+    ) -> Result<(TransactionView, Option<usize>, bool, u64), BalanceTxCapacityError> {
         let cycle_resolver = CycleResolver::new(tx_dep_provider);
         let cycle = cycle_resolver.estimate_cycles(&tx)?;
+        if let Some(max_cycles) = self.options.max_cycles {
+            if cycle > max_cycles {
+                return Err(BalanceTxCapacityError::ExceedCyclesLimit(cycle, max_cycles));
+            }
+        }
         let cycle_size = (cycle as f64 * bytes_per_cycle()) as usize;
         let serialized_size = tx.data().as_reader().serialized_size_in_block();
         if serialized_size >= cycle_size {
-            return Ok((tx, None, true));
+            return Ok((tx, None, true, cycle));
         }
         let fee = tx_fee(tx.clone(), tx_dep_provider, header_dep_resolver).unwrap();
         let cycle_fee = self.fee_rate.fee(cycle_size as u64).as_u64();
 
         if fee >= cycle_fee {
-            return Ok((tx, None, true));
+            return Ok((tx, None, true, cycle));
         }
 
         let (tx, idx) = self.rebalance_tx_capacity(
@@ -587,7 +1237,7 @@ impl CapacityBalancer {
             cycle_fee,
             change_index,
         )?;
-        Ok((tx, idx, false))
+        Ok((tx, idx, false, cycle))
     }
 }
 
@@ -596,12 +1246,14 @@ pub const fn bytes_per_cycle() -> f64 {
     DEFAULT_BYTES_PER_CYCLE
 }
 
+#[cfg(feature = "rpc")]
 pub struct CycleResolver<DL> {
     tx_dep_provider: DL,
     tip_header: HeaderView,
     consensus: Arc<Consensus>,
 }
 
+#[cfg(feature = "rpc")]
 impl<
         DL: CellDataProvider
             + HeaderProvider
@@ -670,6 +1322,63 @@ pub fn balance_tx_capacity(
     Ok(tx)
 }
 
+/// Check the serialized size (in block) of the transaction against the limit,
+/// when exceeded the error will suggest how many inputs or outputs should be
+/// split off into another transaction. Return the transaction size.
+pub fn check_tx_size(
+    tx: &TransactionView,
+    max_tx_size: Option<u64>,
+) -> Result<u64, BalanceTxCapacityError> {
+    let tx_size = tx.data().as_reader().serialized_size_in_block() as u64;
+    let max_tx_size = match max_tx_size {
+        Some(max_tx_size) if tx_size > max_tx_size => max_tx_size,
+        _ => return Ok(tx_size),
+    };
+    let exceeded = tx_size - max_tx_size;
+    let split_count = |total_size: usize, count: usize| {
+        if count == 0 {
+            return None;
+        }
+        let average = (total_size / count).max(1) as u64;
+        Some((exceeded + average - 1) / average)
+    };
+    let inputs_size = tx
+        .inputs()
+        .into_iter()
+        .zip(tx.witnesses().into_iter())
+        .map(|(input, witness)| input.as_slice().len() + witness.as_slice().len() + 4)
+        .sum();
+    let outputs_size = tx
+        .outputs()
+        .into_iter()
+        .zip(tx.outputs_data().into_iter())
+        .map(|(output, data)| output.as_slice().len() + data.as_slice().len() + 8)
+        .sum();
+    let suggestion = match (
+        split_count(inputs_size, tx.inputs().len()),
+        split_count(outputs_size, tx.outputs().len()),
+    ) {
+        (Some(inputs), Some(outputs)) => format!(
+            "try to split off at least {} inputs or {} outputs into another transaction",
+            inputs, outputs
+        ),
+        (Some(inputs), None) => format!(
+            "try to split off at least {} inputs into another transaction",
+            inputs
+        ),
+        (None, Some(outputs)) => format!(
+            "try to split off at least {} outputs into another transaction",
+            outputs
+        ),
+        (None, None) => "the transaction can not be split".to_string(),
+    };
+    Err(BalanceTxCapacityError::ExceedTxSizeLimit(
+        tx_size,
+        max_tx_size,
+        suggestion,
+    ))
+}
+
 #[allow(clippy::too_many_arguments)]
 fn rebalance_tx_capacity(
     tx: &TransactionView,
@@ -680,46 +1389,98 @@ fn rebalance_tx_capacity(
     header_dep_resolver: &dyn HeaderDepResolver,
     accepted_min_fee: u64,
     change_index: Option<usize>,
+) -> Result<(TransactionView, Option<usize>), BalanceTxCapacityError> {
+    let mut skipped = Vec::new();
+    let result = rebalance_tx_capacity_inner(
+        tx,
+        balancer,
+        cell_collector,
+        tx_dep_provider,
+        cell_dep_resolver,
+        header_dep_resolver,
+        accepted_min_fee,
+        change_index,
+        &mut skipped,
+    );
+    // the skipped cells are locked when collected, they are live cells again
+    for (out_point, _) in &skipped {
+        cell_collector.unlock_cell(out_point.clone())?;
+    }
+    let (tx, change_index) = match result {
+        // report why the time-locked cells can not be used
+        Err(BalanceTxCapacityError::CapacityNotEnough(_)) if !skipped.is_empty() => {
+            let (out_point, result) = skipped.swap_remove(0);
+            return Err(BalanceTxCapacityError::SinceNotSatisfied(out_point, result));
+        }
+        result => result?,
+    };
+    if let Some(idx) = change_index {
+        check_change_output(&tx, idx, balancer.options.change_strategy)?;
+    }
+    check_tx_size(&tx, balancer.options.max_tx_size)?;
+    Ok((tx, change_index))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn rebalance_tx_capacity_inner(
+    tx: &TransactionView,
+    balancer: &CapacityBalancer,
+    cell_collector: &mut dyn CellCollector,
+    tx_dep_provider: &dyn TransactionDependencyProvider,
+    cell_dep_resolver: &dyn CellDepResolver,
+    header_dep_resolver: &dyn HeaderDepResolver,
+    accepted_min_fee: u64,
+    change_index: Option<usize>,
+    skipped: &mut Vec<(OutPoint, SinceCheckResult)>,
 ) -> Result<(TransactionView, Option<usize>), BalanceTxCapacityError> {
     let capacity_provider = &balancer.capacity_provider;
     if capacity_provider.lock_scripts.is_empty() {
         return Err(BalanceTxCapacityError::EmptyCapacityProvider);
     }
-    let change_lock_script = balancer
-        .change_lock_script
-        .clone()
-        .unwrap_or_else(|| capacity_provider.lock_scripts[0].0.clone());
-    let (tx, base_change_output, base_change_occupied_capacity) = if let Some(idx) = change_index {
-        let outputs = tx.outputs();
-        let output = tx
-            .outputs()
-            .get(idx)
-            .ok_or(BalanceTxCapacityError::ChangeIndexNotFound(idx))?;
-
-        // remove change output
-        let outputs: Vec<_> = outputs
-            .into_iter()
-            .enumerate()
-            .filter_map(|(i, output)| if idx == i { None } else { Some(output) })
-            .collect();
-        let base_change_occupied_capacity = output
-            .occupied_capacity(Capacity::zero())
-            .expect("init change occupied capacity")
-            .as_u64();
-        let tx = tx.data().as_advanced_builder().set_outputs(outputs).build();
-        (tx, output, base_change_occupied_capacity)
-    } else {
-        let base_change_output = CellOutput::new_builder().lock(change_lock_script).build();
-        let base_change_occupied_capacity = base_change_output
-            .occupied_capacity(Capacity::zero())
-            .expect("init change occupied capacity")
-            .as_u64();
-        (
-            tx.clone(),
-            base_change_output,
-            base_change_occupied_capacity,
-        )
-    };
+    let change_lock_script = balancer.change_lock_script();
+    let (tx, base_change_output, base_change_data, base_change_occupied_capacity) =
+        if let Some(idx) = change_index {
+            let (output, data) = check_change_template(tx, idx, balancer.options.change_strategy)?;
+
+            // remove change output
+            let outputs: Vec<_> = tx
+                .outputs()
+                .into_iter()
+                .enumerate()
+                .filter_map(|(i, output)| if idx == i { None } else { Some(output) })
+                .collect();
+            let outputs_data: Vec<_> = tx
+                .outputs_data()
+                .into_iter()
+                .enumerate()
+                .filter_map(|(i, data)| if idx == i { None } else { Some(data) })
+                .collect();
+            let base_change_occupied_capacity = output
+                .occupied_capacity(Capacity::bytes(data.len()).expect("data capacity"))
+                .expect("init change occupied capacity")
+                .as_u64();
+            let tx = tx
+                .data()
+                .as_advanced_builder()
+                .set_outputs(outputs)
+                .set_outputs_data(outputs_data)
+                .build();
+            (tx, output, data.pack(), base_change_occupied_capacity)
+        } else {
+            // always build the change output from scratch: only the lock script, no type
+            // script and empty data
+            let base_change_output = CellOutput::new_builder().lock(change_lock_script).build();
+            let base_change_occupied_capacity = base_change_output
+                .occupied_capacity(Capacity::zero())
+                .expect("init change occupied capacity")
+                .as_u64();
+            (
+                tx.clone(),
+                base_change_output,
+                Default::default(),
+                base_change_occupied_capacity,
+            )
+        };
 
     let mut lock_scripts = Vec::new();
     // remove duplicated lock script
@@ -728,6 +1489,18 @@ fn rebalance_tx_capacity(
             lock_scripts.push((script.clone(), placeholder.clone(), since_source.clone()));
         }
     }
+    // the tip is only needed when the collected cells may carry a since
+    let tip_header = if balancer.options.check_since
+        && lock_scripts
+            .iter()
+            .any(|(_, _, since_source)| !matches!(since_source, SinceSource::Value(0)))
+    {
+        header_dep_resolver
+            .resolve_tip()
+            .map_err(|err| BalanceTxCapacityError::SinceCheck(err.into()))?
+    } else {
+        None
+    };
     let mut lock_script_idx = 0;
     let mut cell_deps = Vec::new();
     #[allow(clippy::mutable_key_type)]
@@ -746,6 +1519,9 @@ fn rebalance_tx_capacity(
             let mut query = CellQueryOptions::new_lock(lock_script.clone());
             query.secondary_script_len_range = Some(ValueRangeOption::new_exact(0));
             query.data_len_range = Some(ValueRangeOption::new_exact(0));
+            if balancer.options.cellbase_maturity.is_some() {
+                query.maturity = MaturityOption::Both;
+            }
             query
         };
         // check if capacity provider lock script already in inputs
@@ -777,7 +1553,7 @@ fn rebalance_tx_capacity(
                 .set_witnesses(all_witnesses);
             if let Some(output) = change_output.clone() {
                 ret_change_index = Some(output_len);
-                builder = builder.output(output).output_data(Default::default());
+                builder = builder.output(output).output_data(base_change_data.clone());
             }
             builder.build()
         };
@@ -812,7 +1588,12 @@ fn rebalance_tx_capacity(
                     // NOTE: extra_min_fee +1 is for `FeeRate::fee` round
                     let extra_min_fee = balancer
                         .fee_rate
-                        .fee(base_change_output.as_slice().len() as u64 + output_header_extra)
+                        .fee(
+                            (base_change_output.as_slice().len()
+                                + base_change_data.as_slice().len())
+                                as u64
+                                + output_header_extra,
+                        )
                         .as_u64()
                         + 1;
                     // The extra capacity (delta - extra_min_fee) is enough to hold the change cell.
@@ -826,6 +1607,15 @@ fn rebalance_tx_capacity(
                                 .build(),
                         );
                         need_more_capacity = 0;
+                    } else if balancer.options.small_change_policy == SmallChangePolicy::AsFee {
+                        match balancer.force_small_change_as_fee {
+                            Some(capacity) if fee > capacity => {
+                                return Err(BalanceTxCapacityError::ForceSmallChangeAsFeeFailed(
+                                    fee,
+                                ));
+                            }
+                            _ => return Ok((new_tx, ret_change_index)),
+                        }
                     } else {
                         // peek if there is more live cell owned by this capacity provider
                         let (more_cells, _more_capacity) =
@@ -890,6 +1680,84 @@ fn rebalance_tx_capacity(
                     continue;
                 }
             }
+            let since = match since_source {
+                SinceSource::LockArgs(offset) => {
+                    let lock_arg = lock_script.args().raw_data();
+                    if lock_arg.len() < offset + 8 {
+                        return Err(BalanceTxCapacityError::InvalidSinceValue(
+                            *offset,
+                            lock_arg.len(),
+                        ));
+                    }
+                    let mut since_bytes = [0u8; 8];
+                    since_bytes.copy_from_slice(&lock_arg[*offset..*offset + 8]);
+                    u64::from_le_bytes(since_bytes)
+                }
+                SinceSource::Value(since_value) => *since_value,
+            };
+            let mut new_inputs = Vec::new();
+            for cell in more_cells {
+                let since = match balancer.options.cellbase_maturity {
+                    // a cellbase cell not in the genesis block
+                    Some(maturity) if cell.tx_index == 0 && cell.block_number > 0 => {
+                        if since != 0 {
+                            return Err(BalanceTxCapacityError::CellbaseSinceConflict(
+                                cell.out_point,
+                            ));
+                        }
+                        let header = get_header_by_number(tx_dep_provider, cell.block_number)
+                            .map_err(|err| err.to_string())
+                            .and_then(|header| match header {
+                                Some(header) => Ok(Some(header)),
+                                None => header_dep_resolver
+                                    .resolve_by_number(cell.block_number)
+                                    .map_err(|err| err.to_string()),
+                            })
+                            .and_then(|header| header.ok_or_else(|| "not found".to_string()))
+                            .map_err(|err| {
+                                BalanceTxCapacityError::ResolveCellbaseHeader(
+                                    cell.out_point.clone(),
+                                    err,
+                                )
+                            })?;
+                        let mature_epoch = cellbase_mature_epoch(header.epoch(), maturity);
+                        Since::new(
+                            SinceType::EpochNumberWithFraction,
+                            mature_epoch.full_value(),
+                            false,
+                        )
+                        .value()
+                    }
+                    _ => since,
+                };
+                // skip the time-locked cells can not be spent at the tip, e.g. the
+                // multisig cells with since in the lock args
+                if since != 0 {
+                    if let Some(tip) = tip_header.as_ref() {
+                        match check_input_since(
+                            tx.inputs().len() + inputs.len() + new_inputs.len(),
+                            &cell.out_point,
+                            since,
+                            header_dep_resolver,
+                            tx_dep_provider,
+                            tip,
+                        ) {
+                            Ok(result) if !result.is_satisfied() => {
+                                skipped.push((cell.out_point, result));
+                                continue;
+                            }
+                            // the header of an offchain cell is unknown, it can't be checked
+                            Ok(_) | Err(SinceCheckError::HeaderNotFound(_)) => {}
+                            Err(err) => return Err(err.into()),
+                        }
+                    }
+                }
+                new_inputs.push(CellInput::new(cell.out_point, since));
+            }
+            if new_inputs.is_empty() {
+                // all collected cells are skipped, collect more
+                continue;
+            }
             if !resolved_scripts.contains(lock_script) {
                 let provider_cell_dep =
                     cell_dep_resolver.resolve(lock_script).ok_or_else(|| {
@@ -938,26 +1806,7 @@ fn rebalance_tx_capacity(
                     witnesses.push(placeholder_witness.as_bytes().pack());
                 }
             }
-            let since = match since_source {
-                SinceSource::LockArgs(offset) => {
-                    let lock_arg = lock_script.args().raw_data();
-                    if lock_arg.len() < offset + 8 {
-                        return Err(BalanceTxCapacityError::InvalidSinceValue(
-                            *offset,
-                            lock_arg.len(),
-                        ));
-                    }
-                    let mut since_bytes = [0u8; 8];
-                    since_bytes.copy_from_slice(&lock_arg[*offset..*offset + 8]);
-                    u64::from_le_bytes(since_bytes)
-                }
-                SinceSource::Value(since_value) => *since_value,
-            };
-            inputs.extend(
-                more_cells
-                    .into_iter()
-                    .map(|cell| CellInput::new(cell.out_point, since)),
-            );
+            inputs.extend(new_inputs);
         }
     }
 }
@@ -976,6 +1825,10 @@ pub fn gen_script_groups(
     #[allow(clippy::mutable_key_type)]
     let mut type_groups: HashMap<Byte32, ScriptGroup> = HashMap::default();
     for (i, input) in tx.inputs().into_iter().enumerate() {
+        // the null input of a cellbase transaction refers to no cell
+        if tx.is_cellbase() {
+            break;
+        }
         let output = tx_dep_provider.get_cell(&input.previous_output())?;
         let lock_group_entry = lock_groups
             .entry(output.calc_lock_hash())
@@ -1002,7 +1855,222 @@ pub fn gen_script_groups(
     })
 }
 
-/// Fill placeholder lock script witnesses
+#[derive(Error, Debug)]
+pub enum ResolutionError {
+    #[error("transaction dependency provider error: `{0}`")]
+    TxDep(#[from] TransactionDependencyError),
+
+    #[error("unknown script: `{0}`")]
+    UnknownScript(ScriptId),
+}
+
+/// Resolve the cell deps of the lock scripts and type scripts of all input cells, the
+/// cell deps are deduplicated and in the order of the inputs.
+pub fn resolve_cell_deps_for_transaction(
+    tx: &TransactionView,
+    dep_provider: &dyn TransactionDependencyProvider,
+    dep_resolver: &dyn CellDepResolver,
+) -> Result<Vec<CellDep>, ResolutionError> {
+    let mut resolved_scripts = HashSet::new();
+    let mut cell_deps = Vec::new();
+    for out_point in tx.input_pts_iter() {
+        let output = dep_provider.get_cell(&out_point)?;
+        for script in Some(output.lock())
+            .into_iter()
+            .chain(output.type_().to_opt())
+        {
+            let script_id = ScriptId::from(&script);
+            if !resolved_scripts.insert(script_id.clone()) {
+                continue;
+            }
+            let cell_dep = dep_resolver
+                .resolve(&script)
+                .ok_or(ResolutionError::UnknownScript(script_id))?;
+            if !cell_deps.contains(&cell_dep) {
+                cell_deps.push(cell_dep);
+            }
+        }
+    }
+    Ok(cell_deps)
+}
+
+/// Complete the transaction skeleton (inputs and outputs) into an unsigned transaction:
+///   * The cell deps of the input lock/type scripts and the output type scripts are added.
+///   * The witness of the first input of each lock script group is filled with a 65 bytes
+///     lock placeholder (the secp256k1 signature size) if it is empty.
+///   * More plain capacity cells of `change_lock` are collected to pay the fee, the change
+///     is sent to `change_lock`. If the last output is a plain capacity cell of
+///     `change_lock` it is adjusted as the change output, otherwise a new one is added.
+///
+/// The DAO withdraw inputs are not supported since no header dep resolver is given.
+pub fn auto_complete_transaction(
+    skeleton: TransactionView,
+    collector: &mut dyn CellCollector,
+    dep_resolver: &dyn CellDepResolver,
+    dep_provider: &dyn TransactionDependencyProvider,
+    change_lock: Script,
+    fee_rate: u64,
+) -> Result<TransactionView, TxBuilderError> {
+    let mut cell_deps: Vec<CellDep> = skeleton.cell_deps().into_iter().collect();
+    let input_cell_deps = resolve_cell_deps_for_transaction(&skeleton, dep_provider, dep_resolver)?;
+    let output_cell_deps = skeleton
+        .outputs()
+        .into_iter()
+        .filter_map(|output| output.type_().to_opt())
+        .map(|type_script| {
+            dep_resolver
+                .resolve(&type_script)
+                .ok_or_else(|| ResolutionError::UnknownScript(ScriptId::from(&type_script)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    for cell_dep in input_cell_deps.into_iter().chain(output_cell_deps) {
+        if !cell_deps.contains(&cell_dep) {
+            cell_deps.push(cell_dep);
+        }
+    }
+
+    let placeholder_witness = WitnessArgs::new_builder()
+        .lock(Some(Bytes::from(vec![0u8; SECP_SIGNATURE_SIZE])).pack())
+        .build();
+    let mut witnesses: Vec<_> = skeleton.witnesses().into_iter().collect();
+    if witnesses.len() < skeleton.inputs().len() {
+        witnesses.resize(skeleton.inputs().len(), Default::default());
+    }
+    let ScriptGroups { lock_groups, .. } = gen_script_groups(&skeleton, dep_provider)?;
+    for script_group in lock_groups.values() {
+        let idx = script_group.input_indices[0];
+        if witnesses[idx].raw_data().is_empty() {
+            witnesses[idx] = placeholder_witness.as_bytes().pack();
+        }
+    }
+    let tx = skeleton
+        .as_advanced_builder()
+        .set_cell_deps(cell_deps)
+        .set_witnesses(witnesses)
+        .build();
+
+    let change_index = tx.outputs().len().checked_sub(1).filter(|idx| {
+        let output = tx.output(*idx).expect("last output");
+        output.lock() == change_lock
+            && output.type_().is_none()
+            && tx.outputs_data().get(*idx).map(|data| data.is_empty()) == Some(true)
+    });
+    let mut balancer =
+        CapacityBalancer::new_simple(change_lock.clone(), placeholder_witness, fee_rate);
+    balancer.change_lock_script = Some(change_lock);
+    let (tx, _change_index) = rebalance_tx_capacity(
+        &tx,
+        &balancer,
+        collector,
+        dep_provider,
+        dep_resolver,
+        &DummyHeaderDepResolver,
+        0,
+        change_index,
+    )?;
+    Ok(tx)
+}
+
+/// The placeholder filling (or unlocking) result of a script group
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlaceholderGroupReport {
+    pub script_id: ScriptId,
+    /// The script group, the witness of [`group_witness_index`] holds the placeholder
+    pub script_group: ScriptGroup,
+    /// The size of the witness of the group after the placeholder filled, it's the
+    /// size of the existing witness if the group is not claimed.
+    pub placeholder_size: usize,
+    /// Whether a registered unlocker matched the script group
+    pub claimed: bool,
+    /// Whether the script group is unlocked, only set by [`unlock_tx_with_report`]
+    pub unlocked: bool,
+}
+
+/// The per script group report of [`fill_placeholder_witnesses_with_report`] and
+/// [`unlock_tx_with_report`]. The type script groups with a registered unlocker come
+/// first, then the lock script groups, each ordered by the witness index.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlaceholderReport {
+    pub groups: Vec<PlaceholderGroupReport>,
+}
+
+impl PlaceholderReport {
+    /// The script groups not claimed by any unlocker, their witnesses are not
+    /// filled so the transaction fee may be underestimated.
+    pub fn unclaimed(&self) -> impl Iterator<Item = &PlaceholderGroupReport> {
+        self.groups.iter().filter(|group| !group.claimed)
+    }
+
+    pub fn is_all_claimed(&self) -> bool {
+        self.unclaimed().next().is_none()
+    }
+
+    /// Return `UnlockError::UnclaimedScriptGroups` if any group is not claimed
+    pub fn check_all_claimed(&self) -> Result<(), UnlockError> {
+        if self.is_all_claimed() {
+            Ok(())
+        } else {
+            Err(UnlockError::UnclaimedScriptGroups(
+                self.unclaimed()
+                    .map(|group| group.script_group.script.clone())
+                    .collect(),
+            ))
+        }
+    }
+
+    fn find(&self, script_group: &ScriptGroup) -> Option<&PlaceholderGroupReport> {
+        self.groups.iter().find(|group| {
+            group.script_group.script == script_group.script
+                && group.script_group.group_type == script_group.group_type
+        })
+    }
+
+    fn into_not_claimed(self) -> Vec<ScriptGroup> {
+        self.groups
+            .into_iter()
+            .filter(|group| !group.claimed)
+            .map(|group| group.script_group)
+            .collect()
+    }
+}
+
+/// The lock script groups and the type script groups with a registered unlocker,
+/// the type script groups come first: the signature of a lock script usually covers
+/// the whole witness, so it must be created after the type script fields are final.
+fn sorted_script_groups(
+    tx: &TransactionView,
+    tx_dep_provider: &dyn TransactionDependencyProvider,
+    unlockers: &HashMap<ScriptId, Box<dyn ScriptUnlocker>>,
+) -> Result<Vec<ScriptGroup>, TransactionDependencyError> {
+    let ScriptGroups {
+        lock_groups,
+        type_groups,
+    } = gen_script_groups(tx, tx_dep_provider)?;
+    let mut groups: Vec<_> = type_groups
+        .into_values()
+        .filter(|group| unlockers.contains_key(&ScriptId::from(&group.script)))
+        .collect();
+    groups.sort_by_cached_key(|group| {
+        (
+            group_witness_index(group),
+            group.script.calc_script_hash().raw_data(),
+        )
+    });
+    let mut lock_groups: Vec<_> = lock_groups.into_values().collect();
+    lock_groups.sort_by_key(|group| group.input_indices[0]);
+    groups.extend(lock_groups);
+    Ok(groups)
+}
+
+fn first_witness_size(tx: &TransactionView, script_group: &ScriptGroup) -> usize {
+    tx.witnesses()
+        .get(group_witness_index(script_group))
+        .map(|witness| witness.raw_data().len())
+        .unwrap_or_default()
+}
+
+/// Fill placeholder lock script witnesses, and the `input_type`/`output_type` fields
+/// for the type script groups with a registered unlocker
 ///
 /// Return value:
 ///   * The updated transaction
@@ -1012,25 +2080,114 @@ pub fn fill_placeholder_witnesses(
     tx_dep_provider: &dyn TransactionDependencyProvider,
     unlockers: &HashMap<ScriptId, Box<dyn ScriptUnlocker>>,
 ) -> Result<(TransactionView, Vec<ScriptGroup>), UnlockError> {
-    let ScriptGroups { lock_groups, .. } = gen_script_groups(&balanced_tx, tx_dep_provider)?;
+    let (tx, report) =
+        fill_placeholder_witnesses_with_report(balanced_tx, tx_dep_provider, unlockers, false)?;
+    Ok((tx, report.into_not_claimed()))
+}
+
+/// Fill placeholder witnesses and report every lock script group (and every type
+/// script group with a registered unlocker), when
+/// `fail_fast` is true return `UnlockError::UnclaimedScriptGroups` if any group is
+/// not claimed by the `unlockers`.
+pub fn fill_placeholder_witnesses_with_report(
+    balanced_tx: TransactionView,
+    tx_dep_provider: &dyn TransactionDependencyProvider,
+    unlockers: &HashMap<ScriptId, Box<dyn ScriptUnlocker>>,
+    fail_fast: bool,
+) -> Result<(TransactionView, PlaceholderReport), UnlockError> {
     let mut tx = balanced_tx;
-    let mut not_matched = Vec::new();
-    for script_group in lock_groups.values() {
+    let mut report = PlaceholderReport::default();
+    for script_group in sorted_script_groups(&tx, tx_dep_provider, unlockers)? {
         let script_id = ScriptId::from(&script_group.script);
         let script_args = script_group.script.args().raw_data();
+        let mut claimed = false;
         if let Some(unlocker) = unlockers.get(&script_id) {
-            if !unlocker.is_unlocked(&tx, script_group, tx_dep_provider)? {
-                if unlocker.match_args(script_args.as_ref()) {
-                    tx = unlocker.fill_placeholder_witness(&tx, script_group, tx_dep_provider)?;
-                } else {
-                    not_matched.push(script_group.clone());
-                }
+            if unlocker.is_unlocked(&tx, &script_group, tx_dep_provider)? {
+                claimed = true;
+            } else if unlocker.match_args(script_args.as_ref()) {
+                tx = unlocker.fill_placeholder_witness(&tx, &script_group, tx_dep_provider)?;
+                claimed = true;
             }
-        } else {
-            not_matched.push(script_group.clone());
         }
+        report.groups.push(PlaceholderGroupReport {
+            placeholder_size: first_witness_size(&tx, &script_group),
+            script_id,
+            script_group,
+            claimed,
+            unlocked: false,
+        });
     }
-    Ok((tx, not_matched))
+    if fail_fast {
+        report.check_all_claimed()?;
+    }
+    Ok((tx, report))
+}
+
+/// The layout of the witness a lock script group will be signed in, see
+/// [`preview_witnesses`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WitnessPreview {
+    /// The first input of the script group, the witness of which holds the signature
+    pub input_index: usize,
+    /// The index in the lock script groups ordered by the first input index
+    pub group_index: usize,
+    pub lock_script_id: ScriptId,
+    /// The size of the witness (the serialized `WitnessArgs`)
+    pub total_size: usize,
+    /// The offset of the signature in the witness, `None` if the unlocker doesn't know
+    /// the position or no signature is required
+    pub signature_offset: Option<usize>,
+    pub signature_len: usize,
+}
+
+/// Preview the witness layout of every lock script group claimed by the `unlockers`
+/// from the placeholder witnesses, without signing. The signers (e.g. a hardware
+/// wallet) can prepare the witnesses by it before signing starts.
+pub fn preview_witnesses(
+    tx: &TransactionView,
+    tx_dep_provider: &dyn TransactionDependencyProvider,
+    unlockers: &HashMap<ScriptId, Box<dyn ScriptUnlocker>>,
+) -> Result<Vec<WitnessPreview>, UnlockError> {
+    let (filled_tx, report) =
+        fill_placeholder_witnesses_with_report(tx.clone(), tx_dep_provider, unlockers, false)?;
+    let mut previews = Vec::new();
+    let lock_groups = report
+        .groups
+        .into_iter()
+        .filter(|group| group.script_group.group_type == ScriptGroupType::Lock);
+    for (group_index, group) in lock_groups.enumerate() {
+        if !group.claimed {
+            continue;
+        }
+        let input_index = group.script_group.input_indices[0];
+        let witness = filled_tx
+            .witnesses()
+            .get(input_index)
+            .map(|witness| witness.raw_data())
+            .unwrap_or_default();
+        let mut signature_range = None;
+        if !witness.is_empty() {
+            let witness_args = WitnessArgsReader::from_slice(witness.as_ref())
+                .map_err(|_| UnlockError::InvalidWitnessArgs(input_index))?;
+            if let Some(lock) = witness_args.lock().to_opt() {
+                let unlocker = &unlockers[&group.script_id];
+                // skip the 4 bytes length header of the bytes
+                let lock_offset = subslice_offset(witness.as_ref(), lock.as_slice()) + 4;
+                signature_range = unlocker
+                    .placeholder_signature_range(lock.raw_data())?
+                    .map(|(offset, len)| (lock_offset + offset, len));
+            }
+        }
+        previews.push(WitnessPreview {
+            input_index,
+            group_index,
+            lock_script_id: group.script_id,
+            total_size: witness.len(),
+            signature_offset: signature_range.map(|(offset, _)| offset),
+            signature_len: signature_range.map(|(_, len)| len).unwrap_or_default(),
+        });
+    }
+    Ok(previews)
 }
 
 /// Build unlocked transaction that ready to send or for further unlock.
@@ -1043,25 +2200,49 @@ pub fn unlock_tx(
     tx_dep_provider: &dyn TransactionDependencyProvider,
     unlockers: &HashMap<ScriptId, Box<dyn ScriptUnlocker>>,
 ) -> Result<(TransactionView, Vec<ScriptGroup>), UnlockError> {
-    let ScriptGroups { lock_groups, .. } = gen_script_groups(&balanced_tx, tx_dep_provider)?;
+    let (tx, report) = unlock_tx_with_report(balanced_tx, tx_dep_provider, unlockers, None)?;
+    Ok((tx, report.into_not_claimed()))
+}
+
+/// Unlock the transaction and report every lock script group (and every type script
+/// group with a registered unlocker), the type script groups are unlocked first. The
+/// placeholder size
+/// is taken from `fill_report` (the report of the fill step) if the group is found
+/// there, otherwise it's the witness size before unlocking.
+pub fn unlock_tx_with_report(
+    balanced_tx: TransactionView,
+    tx_dep_provider: &dyn TransactionDependencyProvider,
+    unlockers: &HashMap<ScriptId, Box<dyn ScriptUnlocker>>,
+    fill_report: Option<&PlaceholderReport>,
+) -> Result<(TransactionView, PlaceholderReport), UnlockError> {
     let mut tx = balanced_tx;
-    let mut not_unlocked = Vec::new();
-    for script_group in lock_groups.values() {
+    let mut report = PlaceholderReport::default();
+    for script_group in sorted_script_groups(&tx, tx_dep_provider, unlockers)? {
         let script_id = ScriptId::from(&script_group.script);
         let script_args = script_group.script.args().raw_data();
+        let placeholder_size = fill_report
+            .and_then(|fill_report| fill_report.find(&script_group))
+            .map(|group| group.placeholder_size)
+            .unwrap_or_else(|| first_witness_size(&tx, &script_group));
+        let mut claimed = false;
         if let Some(unlocker) = unlockers.get(&script_id) {
-            if unlocker.is_unlocked(&tx, script_group, tx_dep_provider)? {
-                tx = unlocker.clear_placeholder_witness(&tx, script_group)?;
+            if unlocker.is_unlocked(&tx, &script_group, tx_dep_provider)? {
+                tx = unlocker.clear_placeholder_witness(&tx, &script_group)?;
+                claimed = true;
             } else if unlocker.match_args(script_args.as_ref()) {
-                tx = unlocker.unlock(&tx, script_group, tx_dep_provider)?;
-            } else {
-                not_unlocked.push(script_group.clone());
+                tx = unlocker.unlock(&tx, &script_group, tx_dep_provider)?;
+                claimed = true;
             }
-        } else {
-            not_unlocked.push(script_group.clone());
         }
+        report.groups.push(PlaceholderGroupReport {
+            script_id,
+            script_group,
+            placeholder_size,
+            claimed,
+            unlocked: claimed,
+        });
     }
-    Ok((tx, not_unlocked))
+    Ok((tx, report))
 }
 
 #[cfg(test)]
@@ -0,0 +1,518 @@
+//! Merge the open transactions (otx) into one transaction, and complete it with
+//! the matcher's fee-paying inputs and outputs.
+//!
+//! An open transaction part is a transaction with exactly one CoBuild [`Otx`]
+//! witness, the otx commits to all the inputs and outputs of the part, and to the
+//! first `cell_deps`/`header_deps` of the part's cell deps and header deps. The
+//! seals live in the [`Otx`] witness and the signing message only covers the
+//! otx's own ranges (see [`generate_cobuild_otx_message`]), so the part can be
+//! placed anywhere in the merged transaction.
+//!
+//! [`generate_cobuild_otx_message`]: crate::unlock::cobuild::generate_cobuild_otx_message
+use std::collections::{HashMap, HashSet};
+
+use ckb_types::{
+    core::TransactionView,
+    packed::{self, Byte32, CellDep},
+    prelude::*,
+};
+use thiserror::Error;
+
+use super::{
+    rebalance_tx_capacity, tx_fee, unlock_tx, BalanceTxCapacityError, CapacityBalancer,
+    TxBuilderError,
+};
+use crate::traits::{
+    CellCollector, CellDepResolver, HeaderDepResolver, TransactionDependencyProvider,
+};
+use crate::types::cobuild_mol::{Otx, OtxStart, WitnessLayout, WitnessLayoutUnion};
+use crate::types::ScriptGroup;
+use crate::unlock::cobuild::{collect_otxs, parse_witness_layout};
+use crate::unlock::ScriptUnlocker;
+use crate::ScriptId;
+
+#[derive(Error, Debug)]
+pub enum OtxError {
+    #[error("no open transaction part to merge")]
+    NoParts,
+
+    #[error("invalid open transaction part {index}: {reason}")]
+    InvalidPart { index: usize, reason: String },
+
+    #[error("not a merged open transaction: {0}")]
+    NotMerged(String),
+}
+
+fn invalid_part(index: usize, reason: String) -> OtxError {
+    OtxError::InvalidPart { index, reason }
+}
+
+struct OtxPart {
+    tx: TransactionView,
+    otx: Otx,
+    committed_cell_deps: Vec<CellDep>,
+    extra_cell_deps: Vec<CellDep>,
+    committed_header_deps: Vec<Byte32>,
+    extra_header_deps: Vec<Byte32>,
+}
+
+/// Check the part can be merged without invalidating its seals
+fn parse_part(index: usize, part: &TransactionView) -> Result<OtxPart, OtxError> {
+    let mut otx = None;
+    for (witness_idx, witness) in part.witnesses().into_iter().enumerate() {
+        let witness_data = witness.raw_data();
+        match parse_witness_layout(&witness_data).map(|layout| layout.to_enum()) {
+            Some(WitnessLayoutUnion::Otx(item)) => {
+                if otx.is_some() {
+                    return Err(invalid_part(index, "more than one Otx witness".to_string()));
+                }
+                otx = Some(item);
+            }
+            Some(WitnessLayoutUnion::OtxStart(otx_start)) => {
+                let unpack = |value: packed::Uint32| Unpack::<u32>::unpack(&value);
+                if unpack(otx_start.start_input_cell()) != 0
+                    || unpack(otx_start.start_output_cell()) != 0
+                    || unpack(otx_start.start_cell_deps()) != 0
+                    || unpack(otx_start.start_header_deps()) != 0
+                {
+                    return Err(invalid_part(
+                        index,
+                        "the otx must start from the beginning of the part".to_string(),
+                    ));
+                }
+            }
+            Some(_) => {
+                return Err(invalid_part(
+                    index,
+                    format!(
+                        "the SighashAll witness at index {} commits to the whole transaction",
+                        witness_idx
+                    ),
+                ));
+            }
+            None if witness_data.is_empty() => {}
+            None => {
+                return Err(invalid_part(
+                    index,
+                    format!(
+                        "the legacy witness at index {} commits to the whole transaction",
+                        witness_idx
+                    ),
+                ));
+            }
+        }
+    }
+    let otx = otx.ok_or_else(|| invalid_part(index, "no Otx witness".to_string()))?;
+
+    let unpack = |value: packed::Uint32| Unpack::<u32>::unpack(&value) as usize;
+    let check_all = |name: &str, committed: usize, actual: usize| {
+        if committed == actual {
+            Ok(())
+        } else {
+            Err(invalid_part(
+                index,
+                format!(
+                    "the otx commits to {} {} but the part has {}",
+                    committed, name, actual
+                ),
+            ))
+        }
+    };
+    check_all("inputs", unpack(otx.input_cells()), part.inputs().len())?;
+    check_all("outputs", unpack(otx.output_cells()), part.outputs().len())?;
+    let check_prefix = |name: &str, committed: usize, actual: usize| {
+        if committed <= actual {
+            Ok(committed)
+        } else {
+            Err(invalid_part(
+                index,
+                format!(
+                    "the otx commits to {} {} but the part has only {}",
+                    committed, name, actual
+                ),
+            ))
+        }
+    };
+    let cell_deps_len = check_prefix("cell deps", unpack(otx.cell_deps()), part.cell_deps().len())?;
+    let header_deps_len = check_prefix(
+        "header deps",
+        unpack(otx.header_deps()),
+        part.header_deps().len(),
+    )?;
+
+    let mut cell_deps = part.cell_deps().into_iter().collect::<Vec<_>>();
+    let extra_cell_deps = cell_deps.split_off(cell_deps_len);
+    let mut header_deps = part.header_deps().into_iter().collect::<Vec<_>>();
+    let extra_header_deps = header_deps.split_off(header_deps_len);
+    Ok(OtxPart {
+        tx: part.clone(),
+        otx,
+        committed_cell_deps: cell_deps,
+        extra_cell_deps,
+        committed_header_deps: header_deps,
+        extra_header_deps,
+    })
+}
+
+fn layout_witness(layout: WitnessLayoutUnion) -> packed::Bytes {
+    WitnessLayout::new_builder()
+        .set(layout)
+        .build()
+        .as_bytes()
+        .pack()
+}
+
+/// Merge the open transaction parts into one transaction.
+///
+/// The inputs, outputs and the committed cell deps/header deps are concatenated in
+/// the order of `parts`, the uncommitted cell deps/header deps are deduplicated and
+/// appended after them. The witnesses of the inputs are left empty, they are
+/// followed by an [`OtxStart`] witness pointing to the beginning of the transaction
+/// and the [`Otx`] witnesses of the parts.
+///
+/// The parts carrying a legacy or `SighashAll` witness are rejected since those
+/// signatures commit to the whole transaction, so are the parts whose [`Otx`]
+/// ranges do not match their own inputs/outputs/cell deps/header deps.
+pub fn merge(parts: Vec<TransactionView>) -> Result<TransactionView, OtxError> {
+    if parts.is_empty() {
+        return Err(OtxError::NoParts);
+    }
+    let parts = parts
+        .iter()
+        .enumerate()
+        .map(|(index, part)| parse_part(index, part))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // cell deps and header deps must be unique in a transaction
+    #[allow(clippy::mutable_key_type)]
+    let mut cell_dep_owners: HashMap<CellDep, usize> = HashMap::default();
+    #[allow(clippy::mutable_key_type)]
+    let mut header_dep_owners: HashMap<Byte32, usize> = HashMap::default();
+    for (index, part) in parts.iter().enumerate() {
+        for cell_dep in &part.committed_cell_deps {
+            if let Some(other) = cell_dep_owners.insert(cell_dep.clone(), index) {
+                return Err(invalid_part(
+                    index,
+                    format!("the committed cell dep is also committed by part {}", other),
+                ));
+            }
+        }
+        for header_dep in &part.committed_header_deps {
+            if let Some(other) = header_dep_owners.insert(header_dep.clone(), index) {
+                return Err(invalid_part(
+                    index,
+                    format!(
+                        "the committed header dep is also committed by part {}",
+                        other
+                    ),
+                ));
+            }
+        }
+    }
+
+    let mut cell_deps = parts
+        .iter()
+        .flat_map(|part| part.committed_cell_deps.clone())
+        .collect::<Vec<_>>();
+    #[allow(clippy::mutable_key_type)]
+    let mut seen_cell_deps = cell_deps.iter().cloned().collect::<HashSet<_>>();
+    for cell_dep in parts.iter().flat_map(|part| part.extra_cell_deps.iter()) {
+        if seen_cell_deps.insert(cell_dep.clone()) {
+            cell_deps.push(cell_dep.clone());
+        }
+    }
+    let mut header_deps = parts
+        .iter()
+        .flat_map(|part| part.committed_header_deps.clone())
+        .collect::<Vec<_>>();
+    #[allow(clippy::mutable_key_type)]
+    let mut seen_header_deps = header_deps.iter().cloned().collect::<HashSet<_>>();
+    for header_dep in parts.iter().flat_map(|part| part.extra_header_deps.iter()) {
+        if seen_header_deps.insert(header_dep.clone()) {
+            header_deps.push(header_dep.clone());
+        }
+    }
+
+    let inputs_len = parts.iter().map(|part| part.tx.inputs().len()).sum();
+    let mut witnesses = vec![packed::Bytes::default(); inputs_len];
+    witnesses.push(layout_witness(OtxStart::default().into()));
+    witnesses.extend(
+        parts
+            .iter()
+            .map(|part| layout_witness(part.otx.clone().into())),
+    );
+
+    let mut builder = TransactionView::new_advanced_builder()
+        .cell_deps(cell_deps)
+        .set_header_deps(header_deps)
+        .set_witnesses(witnesses);
+    for part in &parts {
+        builder = builder
+            .inputs(part.tx.inputs())
+            .outputs(part.tx.outputs())
+            .outputs_data(part.tx.outputs_data());
+    }
+    Ok(builder.build())
+}
+
+/// Split the otx witnesses off the merged transaction, return the transaction with
+/// only the witnesses of the inputs, and the otx witnesses.
+fn split_otx_witnesses(
+    merged: &TransactionView,
+) -> Result<(TransactionView, Vec<packed::Bytes>), OtxError> {
+    let otxs = collect_otxs(merged).map_err(|err| OtxError::NotMerged(err.to_string()))?;
+    let first = otxs
+        .first()
+        .ok_or_else(|| OtxError::NotMerged("no Otx witness".to_string()))?;
+    let inputs_len = merged.inputs().len();
+    let start_idx = first.witness_index - 1;
+    if start_idx < inputs_len || otxs.last().unwrap().witness_index + 1 != merged.witnesses().len()
+    {
+        return Err(OtxError::NotMerged(
+            "the otx witnesses must be placed after the input witnesses".to_string(),
+        ));
+    }
+    let mut witnesses = merged.witnesses().into_iter().collect::<Vec<_>>();
+    let otx_witnesses = witnesses.split_off(start_idx);
+    witnesses.truncate(inputs_len);
+    let tx = merged
+        .as_advanced_builder()
+        .set_witnesses(witnesses)
+        .build();
+    Ok((tx, otx_witnesses))
+}
+
+fn with_otx_witnesses(tx: &TransactionView, otx_witnesses: &[packed::Bytes]) -> TransactionView {
+    let mut witnesses = tx.witnesses().into_iter().collect::<Vec<_>>();
+    witnesses.resize(tx.inputs().len(), packed::Bytes::default());
+    witnesses.extend(otx_witnesses.iter().cloned());
+    tx.as_advanced_builder().set_witnesses(witnesses).build()
+}
+
+/// Complete the merged open transaction: balance it with the matcher's capacity
+/// provider in `balancer` (the added inputs/outputs are placed after the otxs so
+/// the otx ranges are kept), then sign the matcher's portion with `unlockers`.
+///
+/// The otx witnesses are moved after all the input witnesses, and the fee of them
+/// is also paid by the matcher.
+///
+/// Return value:
+///   * The completed transaction
+///   * The matcher's script groups that not unlocked by given `unlockers`, the
+///     script groups of the otx inputs are unlocked by the seals in the otxs.
+#[allow(clippy::too_many_arguments)]
+pub fn complete(
+    merged: &TransactionView,
+    balancer: &CapacityBalancer,
+    cell_collector: &mut dyn CellCollector,
+    cell_dep_resolver: &dyn CellDepResolver,
+    header_dep_resolver: &dyn HeaderDepResolver,
+    tx_dep_provider: &dyn TransactionDependencyProvider,
+    unlockers: &HashMap<ScriptId, Box<dyn ScriptUnlocker>>,
+) -> Result<(TransactionView, Vec<ScriptGroup>), TxBuilderError> {
+    let (base_tx, otx_witnesses) =
+        split_otx_witnesses(merged).map_err(|err| TxBuilderError::InvalidParameter(err.into()))?;
+    let otx_inputs_len = base_tx.inputs().len();
+
+    let (mut tx, mut change_idx) = rebalance_tx_capacity(
+        &base_tx,
+        balancer,
+        cell_collector,
+        tx_dep_provider,
+        cell_dep_resolver,
+        header_dep_resolver,
+        0,
+        None,
+    )?;
+    // the otx witnesses are not included when balancing, pay the fee for them
+    const MAX_LOOP_TIMES: u32 = 16;
+    let mut n = 0;
+    loop {
+        let full_tx = with_otx_witnesses(&tx, &otx_witnesses);
+        let tx_size = full_tx.data().as_reader().serialized_size_in_block();
+        let min_fee = balancer.fee_rate.fee(tx_size as u64).as_u64();
+        let fee = tx_fee(full_tx.clone(), tx_dep_provider, header_dep_resolver)
+            .map_err(BalanceTxCapacityError::from)?;
+        if fee >= min_fee {
+            tx = full_tx;
+            break;
+        }
+        n += 1;
+        if n > MAX_LOOP_TIMES {
+            return Err(TxBuilderError::ExceedCycleMaxLoopTimes(MAX_LOOP_TIMES));
+        }
+        (tx, change_idx) = balancer.rebalance_tx_capacity(
+            &tx,
+            cell_collector,
+            tx_dep_provider,
+            cell_dep_resolver,
+            header_dep_resolver,
+            min_fee,
+            change_idx,
+        )?;
+    }
+
+    let (tx, still_locked_groups) = unlock_tx(tx, tx_dep_provider, unlockers)?;
+    let still_locked_groups = still_locked_groups
+        .into_iter()
+        .filter(|group| group.input_indices.iter().any(|idx| *idx >= otx_inputs_len))
+        .collect();
+    Ok((tx, still_locked_groups))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_types::{
+        bytes::Bytes,
+        packed::{CellInput, CellOutput, WitnessArgs},
+    };
+
+    use crate::test_util::random_out_point;
+
+    fn build_cell_dep() -> CellDep {
+        CellDep::new_builder().out_point(random_out_point()).build()
+    }
+
+    fn build_part(
+        inputs: usize,
+        outputs: usize,
+        committed_cell_deps: Vec<CellDep>,
+        extra_cell_deps: Vec<CellDep>,
+    ) -> TransactionView {
+        let otx = Otx::new_builder()
+            .input_cells((inputs as u32).pack())
+            .output_cells((outputs as u32).pack())
+            .cell_deps((committed_cell_deps.len() as u32).pack())
+            .build();
+        let mut builder = TransactionView::new_advanced_builder()
+            .cell_deps(committed_cell_deps)
+            .cell_deps(extra_cell_deps)
+            .witness(layout_witness(OtxStart::default().into()))
+            .witness(layout_witness(otx.into()));
+        for _ in 0..inputs {
+            builder = builder.input(CellInput::new(random_out_point(), 0));
+        }
+        for _ in 0..outputs {
+            builder = builder
+                .output(CellOutput::default())
+                .output_data(Bytes::default().pack());
+        }
+        builder.build()
+    }
+
+    fn assert_invalid_part(result: Result<TransactionView, OtxError>, expected: usize) {
+        match result {
+            Err(OtxError::InvalidPart { index, .. }) => assert_eq!(index, expected),
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("the invalid part is merged"),
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let shared_dep = build_cell_dep();
+        let committed_deps = vec![build_cell_dep(), build_cell_dep()];
+        let part0 = build_part(
+            1,
+            2,
+            vec![committed_deps[0].clone()],
+            vec![shared_dep.clone()],
+        );
+        let part1 = build_part(
+            2,
+            1,
+            vec![committed_deps[1].clone()],
+            vec![shared_dep.clone()],
+        );
+
+        let merged = merge(vec![part0.clone(), part1.clone()]).unwrap();
+        let inputs = part0
+            .inputs()
+            .into_iter()
+            .chain(part1.inputs().into_iter())
+            .collect::<Vec<_>>();
+        assert_eq!(merged.inputs().into_iter().collect::<Vec<_>>(), inputs);
+        assert_eq!(merged.outputs().len(), 3);
+        assert_eq!(
+            merged.cell_deps().into_iter().collect::<Vec<_>>(),
+            vec![
+                committed_deps[0].clone(),
+                committed_deps[1].clone(),
+                shared_dep
+            ]
+        );
+        // empty input witnesses + OtxStart + 2 Otx
+        assert_eq!(merged.witnesses().len(), 6);
+        assert!(merged
+            .witnesses()
+            .into_iter()
+            .take(3)
+            .all(|witness| witness.raw_data().is_empty()));
+
+        let otxs = collect_otxs(&merged).unwrap();
+        assert_eq!(otxs.len(), 2);
+        assert_eq!(otxs[0].inputs, 0..1);
+        assert_eq!(otxs[0].outputs, 0..2);
+        assert_eq!(otxs[0].cell_deps, 0..1);
+        assert_eq!(otxs[1].inputs, 1..3);
+        assert_eq!(otxs[1].outputs, 2..3);
+        assert_eq!(otxs[1].cell_deps, 1..2);
+
+        let (base_tx, otx_witnesses) = split_otx_witnesses(&merged).unwrap();
+        assert_eq!(base_tx.witnesses().len(), 3);
+        assert_eq!(otx_witnesses.len(), 3);
+        assert_eq!(
+            with_otx_witnesses(&base_tx, &otx_witnesses).witness_hash(),
+            merged.witness_hash()
+        );
+    }
+
+    #[test]
+    fn test_merge_invalid_parts() {
+        assert!(matches!(merge(Vec::new()), Err(OtxError::NoParts)));
+
+        let valid = build_part(1, 1, Vec::new(), Vec::new());
+        // the otx does not commit to all inputs
+        let part = build_part(2, 1, Vec::new(), Vec::new());
+        let otx = Otx::new_builder()
+            .input_cells(1u32.pack())
+            .output_cells(1u32.pack())
+            .build();
+        let part = part
+            .as_advanced_builder()
+            .set_witnesses(vec![layout_witness(otx.into())])
+            .build();
+        assert_invalid_part(merge(vec![valid.clone(), part]), 1);
+
+        // the legacy witness signs the whole transaction
+        let mut witnesses = valid.witnesses().into_iter().collect::<Vec<_>>();
+        witnesses.insert(
+            0,
+            WitnessArgs::new_builder()
+                .lock(Some(Bytes::from(vec![0u8; 65])).pack())
+                .build()
+                .as_bytes()
+                .pack(),
+        );
+        let part = valid.as_advanced_builder().set_witnesses(witnesses).build();
+        assert_invalid_part(merge(vec![valid.clone(), valid.clone(), part]), 2);
+
+        // no otx witness
+        let part = valid
+            .as_advanced_builder()
+            .set_witnesses(Vec::new())
+            .build();
+        assert_invalid_part(merge(vec![part, valid.clone()]), 0);
+
+        // the committed cell dep is duplicated
+        let cell_dep = build_cell_dep();
+        assert_invalid_part(
+            merge(vec![
+                build_part(1, 1, vec![cell_dep.clone()], Vec::new()),
+                build_part(1, 1, vec![cell_dep], Vec::new()),
+            ]),
+            1,
+        );
+    }
+}
@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use ckb_types::{
+    bytes::Bytes,
+    core::TransactionView,
+    packed::{CellOutput, Script, WitnessArgs},
+    prelude::*,
+};
+
+use super::{
+    transfer::CapacityTransferBuilder,
+    udt::{UdtTargetReceiver, UdtTransferBuilder},
+    BuiltTransaction, CapacityBalancer, TransferAction, TxBuilder, TxBuilderError,
+};
+use crate::constants::{MULTISIG_TYPE_HASH, SIGHASH_TYPE_HASH};
+use crate::traits::{
+    CellCollector, CellDepResolver, HeaderDepResolver, SecpCkbRawKeySigner,
+    TransactionDependencyProvider,
+};
+use crate::types::{Address, AddressPayload, HumanCapacity, NetworkInfo, ScriptGroup, ScriptId};
+use crate::unlock::{MultisigConfig, ScriptUnlocker, SecpMultisigUnlocker, SecpSighashUnlocker};
+use crate::SECP256K1;
+
+/// The default fee rate (shannons/KB) used by [`SimpleTransferBuilder`]
+pub const DEFAULT_SIMPLE_FEE_RATE: u64 = 1000;
+/// The default cache capacity of the transaction dependency provider used by [`SimpleTransferBuilder`]
+pub const DEFAULT_SIMPLE_CACHE_CAPACITY: usize = 10;
+
+/// A high-level facade to transfer CKB or UDT from sighash/multisig addresses.
+///
+/// It is a thin layer on top of [`CapacityTransferBuilder`], [`UdtTransferBuilder`],
+/// [`CapacityBalancer`] and the default unlockers, use them directly for more
+/// complicated cases.
+///
+/// ```ignore
+/// let (built, still_locked_groups) = SimpleTransferBuilder::new(NetworkInfo::testnet())
+///     .add_sighash_key(sender_key)
+///     .transfer(&receiver, HumanCapacity::from_str("100")?)
+///     .build_and_sign()?;
+/// ```
+pub struct SimpleTransferBuilder {
+    network_info: NetworkInfo,
+    fee_rate: u64,
+    cache_capacity: usize,
+    keys: Vec<secp256k1::SecretKey>,
+    multisig_config: Option<MultisigConfig>,
+    sender: Option<Script>,
+    outputs: Vec<(CellOutput, Bytes)>,
+    udt_transfers: Vec<(Script, UdtTargetReceiver)>,
+}
+
+impl SimpleTransferBuilder {
+    pub fn new(network_info: NetworkInfo) -> SimpleTransferBuilder {
+        SimpleTransferBuilder {
+            network_info,
+            fee_rate: DEFAULT_SIMPLE_FEE_RATE,
+            cache_capacity: DEFAULT_SIMPLE_CACHE_CAPACITY,
+            keys: Vec::new(),
+            multisig_config: None,
+            sender: None,
+            outputs: Vec::new(),
+            udt_transfers: Vec::new(),
+        }
+    }
+
+    pub fn network_info(&self) -> &NetworkInfo {
+        &self.network_info
+    }
+
+    /// Add a secp256k1 private key to sign the sighash script groups, the key is
+    /// also used to sign the multisig script group when the multisig config is set.
+    ///
+    /// The first added key's sighash lock is the default sender.
+    pub fn add_sighash_key(mut self, key: secp256k1::SecretKey) -> Self {
+        self.keys.push(key);
+        self
+    }
+
+    /// Transfer from the multisig address of the config, the keys added by
+    /// [`SimpleTransferBuilder::add_sighash_key`] are used to sign the multisig
+    /// script group. The multisig witness is partially signed if the keys are not
+    /// enough, the remaining signatures can be added by [`super::unlock_tx`].
+    pub fn with_multisig(mut self, config: MultisigConfig) -> Self {
+        self.multisig_config = Some(config);
+        self
+    }
+
+    /// Set the sender lock script explicitly, it is also the change lock script.
+    pub fn sender(mut self, lock_script: Script) -> Self {
+        self.sender = Some(lock_script);
+        self
+    }
+
+    /// Set the fee rate (shannons/KB), default is 1000.
+    pub fn fee_rate(mut self, fee_rate: u64) -> Self {
+        self.fee_rate = fee_rate;
+        self
+    }
+
+    /// Set the cache capacity of the transaction dependency provider, default is 10.
+    pub fn cache_capacity(mut self, cache_capacity: usize) -> Self {
+        self.cache_capacity = cache_capacity;
+        self
+    }
+
+    /// Transfer CKB to the address.
+    pub fn transfer(mut self, to: &Address, capacity: HumanCapacity) -> Self {
+        let output = CellOutput::new_builder()
+            .lock(Script::from(to))
+            .capacity(capacity.0.pack())
+            .build();
+        self.outputs.push((output, Bytes::default()));
+        self
+    }
+
+    /// Transfer UDT identified by `udt_type_script` to the address, a new UDT cell
+    /// is created for the receiver with the minimal occupied capacity paid by the
+    /// sender. Only one kind of UDT can be transferred in a transaction.
+    pub fn transfer_udt(mut self, udt_type_script: Script, to: &Address, amount: u128) -> Self {
+        let receiver = UdtTargetReceiver::new(TransferAction::Create, Script::from(to), amount);
+        self.udt_transfers.push((udt_type_script, receiver));
+        self
+    }
+
+    /// The sender lock script: the explicitly set one, or the multisig lock
+    /// script, or the sighash lock script of the first key.
+    pub fn sender_script(&self) -> Option<Script> {
+        if let Some(sender) = self.sender.as_ref() {
+            return Some(sender.clone());
+        }
+        if let Some(config) = self.multisig_config.as_ref() {
+            return Some(Script::from(&config.to_address_payload(None)));
+        }
+        self.keys.first().map(|key| {
+            let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, key);
+            Script::from(&AddressPayload::from_pubkey(&pubkey))
+        })
+    }
+
+    /// Build and sign the transaction with the default providers connected to
+    /// `network_info.url`. The system scripts are resolved from the genesis block,
+    /// and the sUDT cell dep is added on mainnet and testnet.
+    ///
+    /// Return value:
+    ///   * The built transaction
+    ///   * The script groups that not unlocked by given keys
+    #[cfg(feature = "rpc")]
+    pub fn build_and_sign(self) -> Result<(BuiltTransaction, Vec<ScriptGroup>), TxBuilderError> {
+        use crate::rpc::CkbRpcClient;
+        use crate::traits::{
+            DefaultCellCollector, DefaultCellDepResolver, DefaultHeaderDepResolver,
+            DefaultTransactionDependencyProvider,
+        };
+        use crate::transaction::handler::sudt::SudtHandler;
+        use crate::NetworkType;
+        use ckb_types::core::BlockView;
+
+        let url = self.network_info.url.as_str();
+        let mut cell_dep_resolver = {
+            let genesis_block = CkbRpcClient::new(url)
+                .get_block_by_number(0.into())
+                .map_err(|err| TxBuilderError::Other(err.into()))?
+                .ok_or_else(|| TxBuilderError::Other(anyhow!("genesis block not found")))?;
+            DefaultCellDepResolver::from_genesis(&BlockView::from(genesis_block))
+                .map_err(|err| TxBuilderError::Other(err.into()))?
+        };
+        if matches!(
+            self.network_info.network_type,
+            NetworkType::Mainnet | NetworkType::Testnet
+        ) {
+            let sudt = SudtHandler::new_with_network(&self.network_info)?;
+            if let Some(cell_dep) = sudt.cell_deps().first() {
+                cell_dep_resolver.insert(
+                    sudt.sudt_script_id().clone(),
+                    cell_dep.clone(),
+                    "sudt".to_string(),
+                );
+            }
+        }
+        let header_dep_resolver = DefaultHeaderDepResolver::new(url);
+        let mut cell_collector = DefaultCellCollector::new(url);
+        let tx_dep_provider = DefaultTransactionDependencyProvider::new(url, self.cache_capacity);
+        self.build_and_sign_with(
+            &mut cell_collector,
+            &cell_dep_resolver,
+            &header_dep_resolver,
+            &tx_dep_provider,
+        )
+    }
+
+    /// Build and sign the transaction with the given providers.
+    pub fn build_and_sign_with(
+        self,
+        cell_collector: &mut dyn CellCollector,
+        cell_dep_resolver: &dyn CellDepResolver,
+        header_dep_resolver: &dyn HeaderDepResolver,
+        tx_dep_provider: &dyn TransactionDependencyProvider,
+    ) -> Result<(BuiltTransaction, Vec<ScriptGroup>), TxBuilderError> {
+        let sender = self.sender_script().ok_or_else(|| {
+            TxBuilderError::InvalidParameter(anyhow!("no sender or sighash key given"))
+        })?;
+        if self.outputs.is_empty() && self.udt_transfers.is_empty() {
+            return Err(TxBuilderError::InvalidParameter(anyhow!(
+                "no transfer target given"
+            )));
+        }
+
+        let udt_builder = match self.udt_transfers.first() {
+            Some((type_script, _)) => {
+                if self
+                    .udt_transfers
+                    .iter()
+                    .any(|(other, _)| other != type_script)
+                {
+                    return Err(TxBuilderError::InvalidParameter(anyhow!(
+                        "only one kind of udt can be transferred"
+                    )));
+                }
+                Some(UdtTransferBuilder {
+                    type_script: type_script.clone(),
+                    sender: sender.clone(),
+                    receivers: self
+                        .udt_transfers
+                        .iter()
+                        .map(|(_, receiver)| receiver.clone())
+                        .collect(),
+                })
+            }
+            None => None,
+        };
+        let builder = SimpleTxBuilder {
+            udt: udt_builder,
+            capacity: CapacityTransferBuilder::new(self.outputs.clone()),
+        };
+
+        let placeholder_witness = match self.multisig_config.as_ref() {
+            Some(config) => config.placeholder_witness(),
+            None => WitnessArgs::new_builder()
+                .lock(Some(Bytes::from(vec![0u8; 65])).pack())
+                .build(),
+        };
+        let balancer = CapacityBalancer::new_simple(sender, placeholder_witness, self.fee_rate);
+        let unlockers = self.build_unlockers();
+        builder.build_unlocked_result(
+            cell_collector,
+            cell_dep_resolver,
+            header_dep_resolver,
+            tx_dep_provider,
+            &balancer,
+            &unlockers,
+        )
+    }
+
+    fn build_unlockers(&self) -> HashMap<ScriptId, Box<dyn ScriptUnlocker>> {
+        let mut unlockers: HashMap<ScriptId, Box<dyn ScriptUnlocker>> = HashMap::default();
+        let signer = SecpCkbRawKeySigner::new_with_secret_keys(self.keys.clone());
+        unlockers.insert(
+            ScriptId::new_type(SIGHASH_TYPE_HASH.clone()),
+            Box::new(SecpSighashUnlocker::from(Box::new(signer) as Box<_>)),
+        );
+        if let Some(config) = self.multisig_config.as_ref() {
+            let signer = SecpCkbRawKeySigner::new_with_secret_keys(self.keys.clone());
+            unlockers.insert(
+                ScriptId::new_type(MULTISIG_TYPE_HASH.clone()),
+                Box::new(SecpMultisigUnlocker::from((
+                    Box::new(signer) as Box<_>,
+                    config.clone(),
+                ))),
+            );
+        }
+        unlockers
+    }
+}
+
+/// Combine the udt transfer and the capacity transfer into one transaction
+struct SimpleTxBuilder {
+    udt: Option<UdtTransferBuilder>,
+    capacity: CapacityTransferBuilder,
+}
+
+impl TxBuilder for SimpleTxBuilder {
+    fn build_base(
+        &self,
+        cell_collector: &mut dyn CellCollector,
+        cell_dep_resolver: &dyn CellDepResolver,
+        header_dep_resolver: &dyn HeaderDepResolver,
+        tx_dep_provider: &dyn TransactionDependencyProvider,
+    ) -> Result<TransactionView, TxBuilderError> {
+        let capacity_tx = self.capacity.build_base(
+            cell_collector,
+            cell_dep_resolver,
+            header_dep_resolver,
+            tx_dep_provider,
+        )?;
+        let udt = match self.udt.as_ref() {
+            Some(udt) => udt,
+            None => return Ok(capacity_tx),
+        };
+        let udt_tx = udt.build_base(
+            cell_collector,
+            cell_dep_resolver,
+            header_dep_resolver,
+            tx_dep_provider,
+        )?;
+        let new_cell_deps = capacity_tx
+            .cell_deps_iter()
+            .filter(|cell_dep| !udt_tx.cell_deps_iter().any(|item| &item == cell_dep))
+            .collect::<Vec<_>>();
+        Ok(udt_tx
+            .as_advanced_builder()
+            .cell_deps(new_cell_deps)
+            .outputs(capacity_tx.outputs())
+            .outputs_data(capacity_tx.outputs_data())
+            .build())
+    }
+}
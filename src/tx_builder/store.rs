@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use ckb_types::{
+    bytes::{Bytes, BytesMut},
+    core::{Capacity, TransactionView},
+    packed::{CellOutput, OutPoint, Script},
+    prelude::*,
+};
+use thiserror::Error;
+
+use super::{transfer::CapacityTransferBuilder, CapacityBalancer, TxBuilder, TxBuilderError};
+use crate::traits::{
+    CellCollector, CellDepResolver, HeaderDepResolver, TransactionDependencyError,
+    TransactionDependencyProvider,
+};
+use crate::types::{ScriptGroup, ScriptId};
+use crate::unlock::ScriptUnlocker;
+use crate::util::ckb_blake2b_256;
+
+/// The default serialized size limit of the chunk cells in one transaction, the
+/// rest of the block size limit is reserved for inputs, witnesses and cell deps.
+pub const DEFAULT_MAX_CHUNKS_SIZE_PER_TX: usize = 500_000;
+
+const INDEX_HEADER_SIZE: usize = 8 + 32;
+
+/// A builder storing a payload on-chain, the payload is split into chunks of
+/// `chunk_size` bytes, every chunk is stored in a cell locked by `lock`. The
+/// chunk cells are put into as many transactions as the size limit requires,
+/// then an index cell listing the chunk cells is created by the last transaction.
+///
+/// The data of the index cell:
+///   * the payload length: u64 in little endian
+///   * the payload hash: blake2b_256 of the payload, 32 bytes
+///   * the out points of the chunk cells in the payload order: molecule `OutPoint`,
+///     36 bytes each
+///
+/// The payload can be read back by [`read_data`].
+pub struct StoreDataBuilder {
+    pub payload: Bytes,
+    pub chunk_size: usize,
+    pub lock: Script,
+    /// The serialized size limit of the chunk cells in one transaction
+    pub max_chunks_size_per_tx: usize,
+}
+
+/// The transactions built by [`StoreDataBuilder`], they can be sent in any order.
+pub struct StoredData {
+    /// The transactions of the chunk cells, then the transaction of the index cell,
+    /// with the script groups not unlocked by the given unlockers.
+    pub txs: Vec<(TransactionView, Vec<ScriptGroup>)>,
+    pub index_out_point: OutPoint,
+}
+
+impl StoreDataBuilder {
+    pub fn new(payload: Bytes, chunk_size: usize, lock: Script) -> StoreDataBuilder {
+        StoreDataBuilder {
+            payload,
+            chunk_size,
+            lock,
+            max_chunks_size_per_tx: DEFAULT_MAX_CHUNKS_SIZE_PER_TX,
+        }
+    }
+
+    fn output(&self, data: Bytes) -> (CellOutput, Bytes) {
+        let output = CellOutput::new_builder().lock(self.lock.clone()).build();
+        let capacity = output
+            .occupied_capacity(Capacity::bytes(data.len()).expect("data capacity"))
+            .expect("occupied capacity");
+        (output.as_builder().capacity(capacity.pack()).build(), data)
+    }
+
+    /// The builders of the chunk cells, one builder for every transaction
+    pub fn chunk_builders(&self) -> Result<Vec<CapacityTransferBuilder>, TxBuilderError> {
+        if self.chunk_size == 0 || self.chunk_size > self.max_chunks_size_per_tx {
+            return Err(TxBuilderError::InvalidParameter(anyhow!(
+                "chunk size `{}` should be in range [1, {}]",
+                self.chunk_size,
+                self.max_chunks_size_per_tx
+            )));
+        }
+        let outputs = self
+            .payload
+            .chunks(self.chunk_size)
+            .map(|chunk| self.output(Bytes::copy_from_slice(chunk)))
+            .collect();
+        Ok(CapacityTransferBuilder::new(outputs).split_by_size(self.max_chunks_size_per_tx))
+    }
+
+    /// Build and sign the transactions, the capacity of the cells is provided by the
+    /// balancer.
+    pub fn build_unlocked(
+        &self,
+        cell_collector: &mut dyn CellCollector,
+        cell_dep_resolver: &dyn CellDepResolver,
+        header_dep_resolver: &dyn HeaderDepResolver,
+        tx_dep_provider: &dyn TransactionDependencyProvider,
+        balancer: &CapacityBalancer,
+        unlockers: &HashMap<ScriptId, Box<dyn ScriptUnlocker>>,
+    ) -> Result<StoredData, TxBuilderError> {
+        let mut txs = Vec::new();
+        let mut index_data = BytesMut::with_capacity(INDEX_HEADER_SIZE);
+        index_data.extend_from_slice(&(self.payload.len() as u64).to_le_bytes());
+        index_data.extend_from_slice(&ckb_blake2b_256(&self.payload));
+        for builder in self.chunk_builders()? {
+            let (tx, still_locked_groups) = builder.build_unlocked(
+                cell_collector,
+                cell_dep_resolver,
+                header_dep_resolver,
+                tx_dep_provider,
+                balancer,
+                unlockers,
+            )?;
+            // the chunk cells are the first outputs, the change output is appended
+            for index in 0..builder.outputs.len() {
+                index_data.extend_from_slice(OutPoint::new(tx.hash(), index as u32).as_slice());
+            }
+            txs.push((tx, still_locked_groups));
+        }
+
+        let index_builder = CapacityTransferBuilder::new(vec![self.output(index_data.freeze())]);
+        let (tx, still_locked_groups) = index_builder.build_unlocked(
+            cell_collector,
+            cell_dep_resolver,
+            header_dep_resolver,
+            tx_dep_provider,
+            balancer,
+            unlockers,
+        )?;
+        let index_out_point = OutPoint::new(tx.hash(), 0);
+        txs.push((tx, still_locked_groups));
+        Ok(StoredData {
+            txs,
+            index_out_point,
+        })
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ReadDataError {
+    #[error("transaction dependency provider error: `{0}`")]
+    TxDep(#[from] TransactionDependencyError),
+
+    #[error("invalid index cell data length: `{0}`")]
+    InvalidIndexLength(usize),
+
+    #[error("the payload does not match the index, expected length: `{expected}`, actual length: `{actual}`")]
+    PayloadMismatch { expected: u64, actual: u64 },
+}
+
+/// Read the payload stored by [`StoreDataBuilder`] from the index cell, the length
+/// and the hash of the payload are checked against the index.
+pub fn read_data(
+    provider: &dyn TransactionDependencyProvider,
+    index_out_point: &OutPoint,
+) -> Result<Bytes, ReadDataError> {
+    let index_data = provider.get_cell_data(index_out_point)?;
+    let out_point_size = OutPoint::TOTAL_SIZE;
+    if index_data.len() < INDEX_HEADER_SIZE
+        || (index_data.len() - INDEX_HEADER_SIZE) % out_point_size != 0
+    {
+        return Err(ReadDataError::InvalidIndexLength(index_data.len()));
+    }
+    let mut length_bytes = [0u8; 8];
+    length_bytes.copy_from_slice(&index_data[0..8]);
+    let expected = u64::from_le_bytes(length_bytes);
+
+    let mut payload = BytesMut::new();
+    for out_point_bytes in index_data[INDEX_HEADER_SIZE..].chunks(out_point_size) {
+        let out_point = OutPoint::from_slice(out_point_bytes).expect("fixed size out point");
+        payload.extend_from_slice(&provider.get_cell_data(&out_point)?);
+    }
+    if payload.len() as u64 != expected
+        || ckb_blake2b_256(&payload) != index_data[8..INDEX_HEADER_SIZE]
+    {
+        return Err(ReadDataError::PayloadMismatch {
+            expected,
+            actual: payload.len() as u64,
+        });
+    }
+    Ok(payload.freeze())
+}
@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use ckb_types::{
     bytes::Bytes,
@@ -7,11 +7,12 @@ use ckb_types::{
     prelude::*,
 };
 
-use super::{TxBuilder, TxBuilderError};
+use super::{CapacityBalancer, TxBuilder, TxBuilderError};
 use crate::traits::{
     CellCollector, CellDepResolver, HeaderDepResolver, TransactionDependencyProvider,
 };
-use crate::types::ScriptId;
+use crate::types::{ScriptGroup, ScriptId};
+use crate::unlock::ScriptUnlocker;
 
 /// A builder to build a transaction simply transfer capcity to an address. It
 /// will resolve the type script's cell_dep if given.
@@ -23,6 +24,69 @@ impl CapacityTransferBuilder {
     pub fn new(outputs: Vec<(CellOutput, Bytes)>) -> CapacityTransferBuilder {
         CapacityTransferBuilder { outputs }
     }
+
+    /// Split the outputs into several builders when the outputs are too many to
+    /// fit into one transaction, the serialized size of the outputs and data in
+    /// every builder will not exceed `max_outputs_size` (unless a single output
+    /// already exceeds it). The remaining budget of the transaction size should
+    /// be reserved for inputs, witnesses and cell deps.
+    pub fn split_by_size(&self, max_outputs_size: usize) -> Vec<CapacityTransferBuilder> {
+        let mut builders = Vec::new();
+        let mut outputs = Vec::new();
+        let mut outputs_size = 0;
+        for (output, data) in &self.outputs {
+            // 4 bytes offset for output and output data each
+            let size = output.as_slice().len() + data.pack().as_slice().len() + 8;
+            if !outputs.is_empty() && outputs_size + size > max_outputs_size {
+                builders.push(CapacityTransferBuilder::new(std::mem::take(&mut outputs)));
+                outputs_size = 0;
+            }
+            outputs.push((output.clone(), data.clone()));
+            outputs_size += size;
+        }
+        if !outputs.is_empty() {
+            builders.push(CapacityTransferBuilder::new(outputs));
+        }
+        builders
+    }
+
+    /// Build, balance and unlock the transfer, when the balancer's `auto_split`
+    /// is enabled and `max_tx_size` is set, the outputs are split into several
+    /// transactions and each of them takes at most half of `max_tx_size`, the
+    /// other half is reserved for the inputs, witnesses and cell deps.
+    ///
+    /// The cells collected by a transaction are locked in the `cell_collector`, so
+    /// the following transactions will not spend them again.
+    #[allow(clippy::too_many_arguments)]
+    pub fn build_split_unlocked(
+        &self,
+        cell_collector: &mut dyn CellCollector,
+        cell_dep_resolver: &dyn CellDepResolver,
+        header_dep_resolver: &dyn HeaderDepResolver,
+        tx_dep_provider: &dyn TransactionDependencyProvider,
+        balancer: &CapacityBalancer,
+        unlockers: &HashMap<ScriptId, Box<dyn ScriptUnlocker>>,
+    ) -> Result<Vec<(TransactionView, Vec<ScriptGroup>)>, TxBuilderError> {
+        let builders = match balancer.max_tx_size() {
+            Some(max_tx_size) if balancer.auto_split() => {
+                self.split_by_size((max_tx_size / 2) as usize)
+            }
+            _ => vec![CapacityTransferBuilder::new(self.outputs.clone())],
+        };
+        builders
+            .iter()
+            .map(|builder| {
+                builder.build_unlocked(
+                    cell_collector,
+                    cell_dep_resolver,
+                    header_dep_resolver,
+                    tx_dep_provider,
+                    balancer,
+                    unlockers,
+                )
+            })
+            .collect()
+    }
 }
 
 impl TxBuilder for CapacityTransferBuilder {
@@ -0,0 +1,262 @@
+use std::collections::HashMap;
+
+use ckb_jsonrpc_types::JsonBytes;
+use ckb_types::{
+    packed::{Byte32, OutPoint, Script},
+    prelude::*,
+};
+
+use crate::rpc::{
+    ckb_indexer::{Order, SearchKey, SearchMode},
+    CkbRpcInterface,
+};
+use crate::traits::{CellQueryOptions, ValueRangeOption};
+use crate::types::ScriptId;
+use crate::RpcError;
+
+/// The number of cells fetched per `get_cells` request
+pub const UDT_BALANCE_PAGE_SIZE: u32 = 500;
+
+/// The UDT held by a set of lock scripts
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct UdtBalance {
+    pub type_script: Script,
+    pub script_id: ScriptId,
+    /// The sum of the amounts (the first 16 bytes of the cell data)
+    pub total_amount: u128,
+    pub cell_count: u64,
+    /// The total capacity of the UDT cells (in shannons)
+    pub ckb_capacity_locked: u64,
+}
+
+/// A cell with type script skipped by [`get_udt_balances`] since its data is shorter
+/// than 16 bytes, e.g. a Nervos DAO cell.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct MalformedUdtCell {
+    pub out_point: OutPoint,
+    pub type_script: Script,
+    pub data_len: usize,
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct UdtBalances {
+    /// Ordered by the type script hash
+    pub balances: Vec<UdtBalance>,
+    pub malformed_cells: Vec<MalformedUdtCell>,
+}
+
+/// List every UDT held by the lock scripts with the total amount and the number of
+/// cells, the results of all the lock scripts are merged by the type script.
+///
+/// The cells with type script are paged by [`UDT_BALANCE_PAGE_SIZE`] and only the
+/// per type totals are kept, the lock scripts without any cell with type script are
+/// skipped by a `get_cells_capacity` request.
+pub fn get_udt_balances(
+    rpc_client: &dyn CkbRpcInterface,
+    locks: &[Script],
+) -> Result<UdtBalances, RpcError> {
+    let mut balances: HashMap<Byte32, UdtBalance> = HashMap::new();
+    let mut malformed_cells = Vec::new();
+    let mut visited_locks = Vec::new();
+    for lock in locks {
+        if visited_locks.contains(lock) {
+            continue;
+        }
+        visited_locks.push(lock.clone());
+
+        let mut query = CellQueryOptions::new_lock(lock.clone());
+        query.script_search_mode = Some(SearchMode::Exact);
+        // any type script
+        query.secondary_script_len_range = Some(ValueRangeOption::new_min(1));
+        query.with_data = Some(true);
+        let search_key = SearchKey::from(query);
+        let typed_capacity = rpc_client
+            .get_cells_capacity(search_key.clone())?
+            .map(|capacity| capacity.capacity.value())
+            .unwrap_or_default();
+        if typed_capacity == 0 {
+            continue;
+        }
+
+        let mut after: Option<JsonBytes> = None;
+        loop {
+            let page = rpc_client.get_cells(
+                search_key.clone(),
+                Order::Asc,
+                UDT_BALANCE_PAGE_SIZE.into(),
+                after,
+            )?;
+            let page_len = page.objects.len();
+            for cell in page.objects {
+                let type_script: Script = match cell.output.type_ {
+                    Some(type_script) => type_script.into(),
+                    None => continue,
+                };
+                let data = cell
+                    .output_data
+                    .map(|data| data.into_bytes())
+                    .unwrap_or_default();
+                let amount = match data.get(0..16) {
+                    Some(amount_bytes) => {
+                        let mut buf = [0u8; 16];
+                        buf.copy_from_slice(amount_bytes);
+                        u128::from_le_bytes(buf)
+                    }
+                    None => {
+                        malformed_cells.push(MalformedUdtCell {
+                            out_point: cell.out_point.into(),
+                            type_script,
+                            data_len: data.len(),
+                        });
+                        continue;
+                    }
+                };
+                let balance = balances
+                    .entry(type_script.calc_script_hash())
+                    .or_insert_with(|| UdtBalance {
+                        script_id: ScriptId::from(&type_script),
+                        type_script,
+                        total_amount: 0,
+                        cell_count: 0,
+                        ckb_capacity_locked: 0,
+                    });
+                balance.total_amount = balance.total_amount.saturating_add(amount);
+                balance.cell_count += 1;
+                balance.ckb_capacity_locked += cell.output.capacity.value();
+            }
+            if page_len < UDT_BALANCE_PAGE_SIZE as usize {
+                break;
+            }
+            after = Some(page.last_cursor);
+        }
+    }
+    let mut balances: Vec<_> = balances.into_iter().collect();
+    balances.sort_by(|(a, _), (b, _)| a.as_slice().cmp(b.as_slice()));
+    Ok(UdtBalances {
+        balances: balances.into_iter().map(|(_, balance)| balance).collect(),
+        malformed_cells,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use ckb_types::{bytes::Bytes, h256, packed::CellOutput};
+    use httpmock::prelude::*;
+
+    use super::*;
+    use crate::{
+        rpc::{
+            ckb_indexer::{Cell, CellsCapacity, Pagination},
+            CkbRpcClient,
+        },
+        test_util::{random_out_point, MockRpcResult},
+    };
+
+    fn udt_cell(lock: &Script, type_script: &Script, data: Bytes) -> Cell {
+        Cell {
+            output: CellOutput::new_builder()
+                .lock(lock.clone())
+                .type_(Some(type_script.clone()).pack())
+                .capacity(142_0000_0000u64.pack())
+                .build()
+                .into(),
+            output_data: Some(JsonBytes::from_bytes(data)),
+            out_point: random_out_point().into(),
+            block_number: 1.into(),
+            tx_index: 0.into(),
+        }
+    }
+
+    #[test]
+    fn test_get_udt_balances() {
+        let lock1 = Script::new_builder().args(vec![1u8; 20].pack()).build();
+        let lock2 = Script::new_builder().args(vec![2u8; 20].pack()).build();
+        let lock3 = Script::new_builder().args(vec![3u8; 20].pack()).build();
+        let udt = |arg: u8| {
+            Script::new_builder()
+                .code_hash(h256!("0x1234").pack())
+                .args(vec![arg; 32].pack())
+                .build()
+        };
+        let amount = |value: u128| Bytes::from(value.to_le_bytes().to_vec());
+        let (udt_a, udt_b) = (udt(1), udt(2));
+
+        let server = MockServer::start();
+        let mock_lock = |lock: &Script, capacity: u64, cells: Vec<Cell>| {
+            let lock_json = serde_json::to_value(ckb_jsonrpc_types::Script::from(lock.clone()))
+                .unwrap()
+                .to_string();
+            server.mock(|when, then| {
+                when.method(POST)
+                    .path("/")
+                    .body_contains("get_cells_capacity")
+                    .body_contains(&lock_json);
+                then.status(200).body(
+                    MockRpcResult::new(Some(CellsCapacity {
+                        capacity: capacity.into(),
+                        block_hash: Default::default(),
+                        block_number: 1.into(),
+                    }))
+                    .to_json(),
+                );
+            });
+            server.mock(|when, then| {
+                when.method(POST)
+                    .path("/")
+                    .body_contains("\"get_cells\"")
+                    .body_contains(&lock_json);
+                then.status(200).body(
+                    MockRpcResult::new(Pagination {
+                        objects: cells,
+                        last_cursor: Default::default(),
+                    })
+                    .to_json(),
+                );
+            })
+        };
+        let malformed = udt_cell(&lock1, &udt_b, Bytes::from(vec![0u8; 8]));
+        let cells1 = mock_lock(
+            &lock1,
+            1,
+            vec![
+                udt_cell(&lock1, &udt_a, amount(10)),
+                udt_cell(&lock1, &udt_b, amount(20)),
+                malformed.clone(),
+            ],
+        );
+        mock_lock(&lock2, 1, vec![udt_cell(&lock2, &udt_a, amount(5))]);
+        let cells3 = mock_lock(&lock3, 0, Vec::new());
+
+        let rpc_client = CkbRpcClient::new(&server.base_url());
+        let result = get_udt_balances(&rpc_client, &[lock1.clone(), lock2, lock3, lock1]).unwrap();
+        cells1.assert_hits(1);
+        cells3.assert_hits(0);
+
+        let mut expected = vec![
+            UdtBalance {
+                script_id: ScriptId::from(&udt_a),
+                type_script: udt_a,
+                total_amount: 15,
+                cell_count: 2,
+                ckb_capacity_locked: 2 * 142_0000_0000,
+            },
+            UdtBalance {
+                script_id: ScriptId::from(&udt_b),
+                type_script: udt_b.clone(),
+                total_amount: 20,
+                cell_count: 1,
+                ckb_capacity_locked: 142_0000_0000,
+            },
+        ];
+        expected.sort_by_key(|balance| balance.type_script.calc_script_hash().as_slice().to_vec());
+        assert_eq!(result.balances, expected);
+        assert_eq!(
+            result.malformed_cells,
+            vec![MalformedUdtCell {
+                out_point: malformed.out_point.into(),
+                type_script: udt_b,
+                data_len: 8,
+            }]
+        );
+    }
+}
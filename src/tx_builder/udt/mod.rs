@@ -1,3 +1,5 @@
+#[cfg(feature = "rpc")]
+mod balance;
 mod sudt;
 
 use anyhow::anyhow;
@@ -16,6 +18,11 @@ use crate::traits::{
 };
 use crate::types::ScriptId;
 
+#[cfg(feature = "rpc")]
+pub use balance::{
+    get_udt_balances, MalformedUdtCell, UdtBalance, UdtBalances, UDT_BALANCE_PAGE_SIZE,
+};
+
 /// The udt type
 #[derive(Debug, Eq, PartialEq, Hash, Clone)]
 pub enum UdtType {
@@ -3,7 +3,6 @@ use std::fmt;
 use std::str::FromStr;
 
 use bech32::{self, convert_bits, ToBase32, Variant};
-use ckb_hash::blake2b_256;
 use ckb_types::{
     bytes::Bytes,
     core::ScriptHashType,
@@ -12,13 +11,21 @@ use ckb_types::{
     H160, H256,
 };
 use serde_derive::{Deserialize, Serialize};
+use thiserror::Error;
 
 use super::NetworkType;
 use crate::constants::{
     ACP_TYPE_HASH_AGGRON, ACP_TYPE_HASH_LINA, MULTISIG_TYPE_HASH, SIGHASH_TYPE_HASH,
 };
+use crate::util::blake160;
 pub use old_addr::{Address as OldAddress, AddressFormat as OldAddressFormat};
 
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum AddressError {
+    #[error("invalid address: {0}")]
+    Invalid(String),
+}
+
 #[derive(Hash, Eq, PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum AddressType {
@@ -165,9 +172,7 @@ impl AddressPayload {
 
     pub fn from_pubkey(pubkey: &secp256k1::PublicKey) -> AddressPayload {
         // Serialize pubkey as compressed format
-        let hash = H160::from_slice(&blake2b_256(&pubkey.serialize()[..])[0..20])
-            .expect("Generate hash(H160) from pubkey failed");
-        AddressPayload::from_pubkey_hash(hash)
+        AddressPayload::from_pubkey_hash(blake160(&pubkey.serialize()))
     }
 
     pub fn from_pubkey_hash(hash: H160) -> AddressPayload {
@@ -175,9 +180,11 @@ impl AddressPayload {
         AddressPayload::Short { index, hash }
     }
 
+    /// The data1/data2 hash types can only be encoded in the full format, `is_new` is
+    /// ignored for them.
     pub fn display_with_network(&self, network: NetworkType, is_new: bool) -> String {
         let hrp = network.to_prefix();
-        let (data, variant) = if is_new {
+        let (data, variant) = if is_new || self.ty(false) == AddressType::Full {
             // payload = 0x00 | code_hash | hash_type | args
             let code_hash = self.code_hash(Some(network));
             let hash_type = self.hash_type();
@@ -291,7 +298,10 @@ pub struct Address {
 }
 
 impl Address {
+    /// The `is_new` is always true for the data1/data2 hash types, since they have no
+    /// deprecated format.
     pub fn new(network: NetworkType, payload: AddressPayload, is_new: bool) -> Address {
+        let is_new = is_new || payload.ty(false) == AddressType::Full;
         Address {
             network,
             payload,
@@ -306,10 +316,86 @@ impl Address {
     pub fn payload(&self) -> &AddressPayload {
         &self.payload
     }
+    /// The hash of the lock script, some explorers and legacy apis key the cells by it.
+    pub fn lock_hash(&self) -> H256 {
+        Script::from(self).calc_script_hash().unpack()
+    }
+
     /// If true the address is ckb2021 format
     pub fn is_new(&self) -> bool {
         self.is_new
     }
+
+    /// If true the address is encoded in a format deprecated by ckb2021 (the short
+    /// format or the full format with bech32 encoding).
+    pub fn is_deprecated_format(&self) -> bool {
+        !self.is_new
+    }
+
+    /// Convert to the ckb2021 full format, the lock script is not changed.
+    ///
+    /// Return error when the address is a short format anyone-can-pay address and the
+    /// network is not `Mainnet` or `Testnet`, the code hash is unknown in that case.
+    pub fn to_new_full_format(&self) -> Result<Address, String> {
+        if self.payload.is_short_acp()
+            && !matches!(self.network, NetworkType::Mainnet | NetworkType::Testnet)
+        {
+            return Err(format!(
+                "the anyone-can-pay code hash of network `{}` is unknown",
+                self.network
+            ));
+        }
+        let payload = AddressPayload::new_full(
+            self.payload.hash_type(),
+            self.payload.code_hash(Some(self.network)),
+            self.payload.args(),
+        );
+        Ok(Address::new(self.network, payload, true))
+    }
+
+    /// Parse the address and handle the deprecated formats by `mode`.
+    pub fn parse_with_mode(
+        input: &str,
+        mode: DeprecatedFormatMode,
+    ) -> Result<ParsedAddress, String> {
+        let address = Address::from_str(input)?;
+        if !address.is_deprecated_format() {
+            return Ok(ParsedAddress {
+                address,
+                deprecation: None,
+            });
+        }
+        let message = match address.to_new_full_format() {
+            Ok(new_address) => format!(
+                "the address format is deprecated, use the full format instead: {}",
+                new_address
+            ),
+            Err(_) => "the address format is deprecated, use the full format instead".to_string(),
+        };
+        match mode {
+            DeprecatedFormatMode::Reject => Err(message),
+            DeprecatedFormatMode::Accept => Ok(ParsedAddress {
+                address,
+                deprecation: Some(message),
+            }),
+        }
+    }
+}
+
+/// How to handle the deprecated address formats when parsing
+#[derive(Hash, Eq, PartialEq, Debug, Clone, Copy)]
+pub enum DeprecatedFormatMode {
+    /// Return error with the migration message
+    Reject,
+    /// Accept the address and set the migration message in `ParsedAddress::deprecation`
+    Accept,
+}
+
+#[derive(Hash, Eq, PartialEq, Debug, Clone)]
+pub struct ParsedAddress {
+    pub address: Address,
+    /// The migration message if the address format is deprecated
+    pub deprecation: Option<String>,
 }
 
 impl fmt::Debug for Address {
@@ -434,7 +520,7 @@ impl FromStr for Address {
 
 mod old_addr {
     use super::{
-        bech32, blake2b_256, convert_bits, Deserialize, NetworkType, Script, ScriptHashType,
+        bech32, blake160, convert_bits, Deserialize, NetworkType, Script, ScriptHashType,
         Serialize, ToBase32, H160, H256,
     };
     use ckb_crypto::secp::Pubkey;
@@ -504,8 +590,7 @@ mod old_addr {
                 return Err("Only support P2PH for now".to_owned());
             }
             // Serialize pubkey as compressed format
-            let hash = H160::from_slice(&blake2b_256(pubkey.serialize())[0..20])
-                .expect("Generate hash(H160) from pubkey failed");
+            let hash = blake160(&pubkey.serialize());
             Ok(Address { format, hash })
         }
 
@@ -639,6 +724,42 @@ mod test {
         assert_eq!(address, Address::from_str("ckb1qzda0cr08m85hc8jlnfp3zer7xulejywt49kt2rr0vthywaa50xwsq4nnw7qkdnnclfkg59uzn8umtfd2kwxceqcydzyt").unwrap());
     }
 
+    #[test]
+    fn test_address_script_round_trip() {
+        // not a short address code hash
+        let code_hash = h256!("0x1");
+        let args = Bytes::from(
+            h160!("0xb39bbc0b3673c7d36450bc14cfcdad2d559c6c64")
+                .as_bytes()
+                .to_vec(),
+        );
+        for hash_type in [
+            ScriptHashType::Data,
+            ScriptHashType::Type,
+            ScriptHashType::Data1,
+            ScriptHashType::Data2,
+        ] {
+            let script = Script::new_builder()
+                .code_hash(code_hash.pack())
+                .hash_type(hash_type.into())
+                .args(args.pack())
+                .build();
+            for is_new in [true, false] {
+                let payload = AddressPayload::from(script.clone());
+                let address = Address::new(NetworkType::Testnet, payload, is_new);
+                let parsed = Address::from_str(&address.to_string()).unwrap();
+                assert_eq!(parsed, address);
+                assert_eq!(parsed.payload().hash_type(), hash_type);
+                assert_eq!(Script::from(&parsed), script);
+            }
+        }
+        // data1/data2 have no deprecated format
+        let payload = AddressPayload::new_full(ScriptHashType::Data2, code_hash.pack(), args);
+        let address = Address::new(NetworkType::Testnet, payload, false);
+        assert!(address.is_new());
+        assert!(!address.is_deprecated_format());
+    }
+
     #[test]
     fn test_parse_display_address() {
         let addr_str = "ckb1qzda0cr08m85hc8jlnfp3zer7xulejywt49kt2rr0vthywaa50xwsqgvf0k9sc40s3azmpfvhyuudhahpsj72tsr8cx3d";
@@ -779,4 +900,67 @@ mod test {
         assert_eq!(format!("{:?}", payload), "AddressPayload { hash_type: \"data1\", code_hash: Byte32(0x9bd7e06f3ecf4be0f2fcd2188b23f1b9fcc88e5d4b65a8637b17723bbda3cce8), args: b\"abcd\" }");
         assert_eq!(format!("{:?}", address), "Address { network: Mainnet, hash_type: \"data1\", code_hash: Byte32(0x9bd7e06f3ecf4be0f2fcd2188b23f1b9fcc88e5d4b65a8637b17723bbda3cce8), args: b\"abcd\", is_new: true }");
     }
+
+    #[test]
+    fn test_address_lock_hash() {
+        let address = Address::from_str("ckt1qzda0cr08m85hc8jlnfp3zer7xulejywt49kt2rr0vthywaa50xwsqwgx292hnvmn68xf779vmzrshpmm6epn4c0cgwga").unwrap();
+        let lock_hash: H256 = Script::from(&address).calc_script_hash().unpack();
+        assert_eq!(address.lock_hash(), lock_hash);
+        // the deprecated formats of the same lock script have the same lock hash
+        let short = address
+            .payload()
+            .display_with_network(address.network(), false);
+        assert_eq!(Address::from_str(&short).unwrap().lock_hash(), lock_hash);
+    }
+
+    #[test]
+    fn test_deprecated_format_conversion() {
+        // short sighash, short multisig, short anyone-can-pay and the full bech32 format
+        for (input, expected) in [
+            (
+                "ckb1qyqt8xaupvm8837nv3gtc9x0ekkj64vud3jqfwyw5v",
+                "ckb1qzda0cr08m85hc8jlnfp3zer7xulejywt49kt2rr0vthywaa50xwsqdnnw7qkdnnclfkg59uzn8umtfd2kwxceqxwquc4",
+            ),
+            (
+                "ckb1qyq5lv479ewscx3ms620sv34pgeuz6zagaaqklhtgg",
+                "ckb1qpw9q60tppt7l3j7r09qcp7lxnp3vcanvgha8pmvsa3jplykxn32sq20k2lzuhgvrgacd98cxg6s5v7pdpw5w7s0mu7z2",
+            ),
+            (
+                "ckb1qypylv479ewscx3ms620sv34pgeuz6zagaaqvrugu7",
+                "ckb1qrfkjktl73ljn77q637judm4xux3y59c29qvvu8ywx90wy5c8g34gq20k2lzuhgvrgacd98cxg6s5v7pdpw5w7scc3cce",
+            ),
+            (
+                "ckb1qjda0cr08m85hc8jlnfp3zer7xulejywt49kt2rr0vthywaa50xw3vumhs9nvu786dj9p0q5elx66t24n3kxgj53qks",
+                "ckb1qzda0cr08m85hc8jlnfp3zer7xulejywt49kt2rr0vthywaa50xwsqdnnw7qkdnnclfkg59uzn8umtfd2kwxceqxwquc4",
+            ),
+        ] {
+            let address = Address::from_str(input).unwrap();
+            assert!(address.is_deprecated_format());
+            let new_address = address.to_new_full_format().unwrap();
+            assert!(!new_address.is_deprecated_format());
+            assert_eq!(new_address.to_string(), expected);
+            assert_eq!(Script::from(&new_address), Script::from(&address));
+            assert_eq!(new_address.to_new_full_format().unwrap(), new_address);
+
+            assert!(
+                Address::parse_with_mode(input, DeprecatedFormatMode::Reject)
+                    .unwrap_err()
+                    .contains(expected)
+            );
+            let parsed = Address::parse_with_mode(input, DeprecatedFormatMode::Accept).unwrap();
+            assert_eq!(parsed.address, address);
+            assert!(parsed.deprecation.unwrap().contains(expected));
+            let parsed = Address::parse_with_mode(expected, DeprecatedFormatMode::Reject).unwrap();
+            assert_eq!(parsed.address, new_address);
+            assert!(parsed.deprecation.is_none());
+        }
+
+        // the anyone-can-pay code hash is unknown in dev chain
+        let payload = AddressPayload::new_short(
+            CodeHashIndex::Acp,
+            h160!("0x4fb2be2e5d0c1a3b8694f832350a33c1685d477a"),
+        );
+        let address = Address::new(NetworkType::Dev, payload, false);
+        assert!(address.to_new_full_format().is_err());
+    }
 }
@@ -1,5 +1,7 @@
 //! Basic ckb sdk types
 mod address;
+#[allow(clippy::all)]
+pub mod cobuild_mol;
 mod human_capacity;
 mod network_type;
 #[allow(clippy::all)]
@@ -12,7 +14,8 @@ pub mod transaction_with_groups;
 pub mod xudt_rce_mol;
 
 pub use address::{
-    Address, AddressPayload, AddressType, CodeHashIndex, OldAddress, OldAddressFormat,
+    Address, AddressError, AddressPayload, AddressType, CodeHashIndex, DeprecatedFormatMode,
+    OldAddress, OldAddressFormat, ParsedAddress,
 };
 pub use human_capacity::HumanCapacity;
 pub use network_type::{NetworkInfo, NetworkType};
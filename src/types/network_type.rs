@@ -1,9 +1,11 @@
 use std::fmt;
 
+use ckb_types::H256;
 use serde_derive::{Deserialize, Serialize};
 
 use crate::constants::{
-    NETWORK_DEV, NETWORK_MAINNET, NETWORK_STAGING, NETWORK_TESTNET, PREFIX_MAINNET, PREFIX_TESTNET,
+    GENESIS_HASH_MAINNET, GENESIS_HASH_TESTNET, NETWORK_DEV, NETWORK_MAINNET, NETWORK_STAGING,
+    NETWORK_TESTNET, PREFIX_MAINNET, PREFIX_TESTNET,
 };
 
 #[derive(Hash, Eq, PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
@@ -32,6 +34,22 @@ impl NetworkType {
         }
     }
 
+    /// The human readable part used in bech32 encoded addresses, same as [`NetworkType::to_prefix`]
+    pub fn to_hrp(self) -> &'static str {
+        self.to_prefix()
+    }
+
+    /// Detect the network by the genesis block hash, only mainnet and testnet are known.
+    pub fn from_chain_id(chain_id: &H256) -> Option<NetworkType> {
+        if chain_id == &GENESIS_HASH_MAINNET {
+            Some(NetworkType::Mainnet)
+        } else if chain_id == &GENESIS_HASH_TESTNET {
+            Some(NetworkType::Testnet)
+        } else {
+            None
+        }
+    }
+
     pub fn from_raw_str(value: &str) -> Option<NetworkType> {
         match value {
             NETWORK_MAINNET => Some(NetworkType::Mainnet),
@@ -89,3 +107,24 @@ impl NetworkInfo {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_types::h256;
+
+    #[test]
+    fn test_from_chain_id() {
+        assert_eq!(
+            NetworkType::from_chain_id(&GENESIS_HASH_MAINNET),
+            Some(NetworkType::Mainnet)
+        );
+        assert_eq!(
+            NetworkType::from_chain_id(&GENESIS_HASH_TESTNET),
+            Some(NetworkType::Testnet)
+        );
+        assert_eq!(NetworkType::from_chain_id(&h256!("0x1")), None);
+        assert_eq!(NetworkType::Mainnet.to_hrp(), "ckb");
+        assert_eq!(NetworkType::Dev.to_hrp(), "ckt");
+    }
+}
@@ -3,8 +3,15 @@ use std::fmt;
 
 use crate::constants::{DAO_TYPE_HASH, TYPE_ID_CODE_HASH};
 use ckb_types::{core::ScriptHashType, packed::Script, prelude::*, H256};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Hash, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(from = "JsonScriptId", into = "JsonScriptId")
+)]
 pub struct ScriptId {
     pub code_hash: H256,
     pub hash_type: ScriptHashType,
@@ -23,10 +30,18 @@ impl ScriptId {
     pub fn new_data1(code_hash: H256) -> ScriptId {
         Self::new(code_hash, ScriptHashType::Data1)
     }
+    pub fn new_data2(code_hash: H256) -> ScriptId {
+        Self::new(code_hash, ScriptHashType::Data2)
+    }
     pub fn new_type(code_hash: H256) -> ScriptId {
         Self::new(code_hash, ScriptHashType::Type)
     }
 
+    /// Same as `ScriptId::from(script)`.
+    pub fn from_script(script: &Script) -> ScriptId {
+        ScriptId::from(script)
+    }
+
     pub fn is_type_id(&self) -> bool {
         self.code_hash == TYPE_ID_CODE_HASH && self.hash_type == ScriptHashType::Type
     }
@@ -45,6 +60,31 @@ impl ScriptId {
     }
 }
 
+// The json form of `ScriptId`, the hash type is serialized as in the ckb rpc
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct JsonScriptId {
+    code_hash: H256,
+    hash_type: ckb_jsonrpc_types::ScriptHashType,
+}
+
+#[cfg(feature = "serde")]
+impl From<JsonScriptId> for ScriptId {
+    fn from(json: JsonScriptId) -> ScriptId {
+        ScriptId::new(json.code_hash, json.hash_type.into())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<ScriptId> for JsonScriptId {
+    fn from(script_id: ScriptId) -> JsonScriptId {
+        JsonScriptId {
+            code_hash: script_id.code_hash,
+            hash_type: script_id.hash_type.into(),
+        }
+    }
+}
+
 impl From<&Script> for ScriptId {
     fn from(script: &Script) -> ScriptId {
         let code_hash: H256 = script.code_hash().unpack();
@@ -1,6 +1,9 @@
+use std::cmp::Ordering;
+
 use ckb_types::core::EpochNumberWithFraction;
 
 use crate::constants::{LOCK_TYPE_FLAG, METRIC_TYPE_FLAG_MASK, REMAIN_FLAGS_BITS, VALUE_MASK};
+use crate::util::epoch_cmp;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum SinceType {
@@ -69,4 +72,154 @@ impl Since {
         };
         ty_opt.map(|ty| (ty, value))
     }
+    fn metric_value(self, ty: SinceType, is_relative: bool) -> Option<u64> {
+        if !self.flags_is_valid() || self.is_relative() != is_relative {
+            return None;
+        }
+        self.extract_metric()
+            .filter(|(metric_ty, _)| *metric_ty == ty)
+            .map(|(_, value)| value)
+    }
+
+    pub fn absolute_block_value(self) -> Option<u64> {
+        self.metric_value(SinceType::BlockNumber, false)
+    }
+
+    pub fn absolute_epoch_value(self) -> Option<EpochNumberWithFraction> {
+        self.metric_value(SinceType::EpochNumberWithFraction, false)
+            .map(EpochNumberWithFraction::from_full_value)
+    }
+
+    pub fn absolute_timestamp_value(self) -> Option<u64> {
+        self.metric_value(SinceType::Timestamp, false)
+    }
+
+    pub fn relative_block_value(self) -> Option<u64> {
+        self.metric_value(SinceType::BlockNumber, true)
+    }
+
+    pub fn relative_epoch_value(self) -> Option<EpochNumberWithFraction> {
+        self.metric_value(SinceType::EpochNumberWithFraction, true)
+            .map(EpochNumberWithFraction::from_full_value)
+    }
+
+    pub fn relative_timestamp_value(self) -> Option<u64> {
+        self.metric_value(SinceType::Timestamp, true)
+    }
+
+    /// Check the absolute timestamp since against the median time (in milliseconds) of
+    /// the parent block of the block which includes the transaction, the since value is
+    /// in seconds. Return `None` if it is not an absolute timestamp since.
+    pub fn satisfied_by_timestamp(self, median_time: u64) -> Option<bool> {
+        self.absolute_timestamp_value()
+            .map(|timestamp| median_time >= timestamp.saturating_mul(1000))
+    }
+
+    /// Pick the more restrictive one of two since values.
+    ///
+    /// A zero since value has no restriction, so the other one is returned. Return
+    /// `None` if the two values are not comparable (invalid flags, different metric
+    /// types, or one is absolute while the other is relative).
+    pub fn max(a: Since, b: Since) -> Option<Since> {
+        if a.0 == 0 {
+            return Some(b);
+        }
+        if b.0 == 0 {
+            return Some(a);
+        }
+        if !a.flags_is_valid() || !b.flags_is_valid() || a.is_relative() != b.is_relative() {
+            return None;
+        }
+        let (a_ty, a_value) = a.extract_metric()?;
+        let (b_ty, b_value) = b.extract_metric()?;
+        if a_ty != b_ty {
+            return None;
+        }
+        let a_is_less = if a_ty == SinceType::EpochNumberWithFraction {
+            epoch_cmp(
+                EpochNumberWithFraction::from_full_value(a_value),
+                EpochNumberWithFraction::from_full_value(b_value),
+            ) == Ordering::Less
+        } else {
+            a_value < b_value
+        };
+        Some(if a_is_less { b } else { a })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metric_values() {
+        let since = Since::new(SinceType::BlockNumber, 20, false);
+        assert_eq!(since.absolute_block_value(), Some(20));
+        assert_eq!(since.relative_block_value(), None);
+        assert_eq!(since.absolute_timestamp_value(), None);
+
+        let epoch = EpochNumberWithFraction::new(3, 1, 2);
+        let since = Since::new(SinceType::EpochNumberWithFraction, epoch.full_value(), true);
+        assert_eq!(since.relative_epoch_value(), Some(epoch));
+        assert_eq!(since.absolute_epoch_value(), None);
+
+        let since = Since::new(SinceType::Timestamp, 1000, true);
+        assert_eq!(since.relative_timestamp_value(), Some(1000));
+        assert_eq!(since.absolute_block_value(), None);
+
+        let since = Since::from_raw_value(0x6000_0000_0000_0001);
+        assert_eq!(since.absolute_block_value(), None);
+        assert_eq!(since.absolute_timestamp_value(), None);
+    }
+
+    #[test]
+    fn test_satisfied_by_timestamp() {
+        // the since value is in seconds, the median time is in milliseconds
+        let since = Since::new(SinceType::Timestamp, 1_600_000_000, false);
+        assert_eq!(since.satisfied_by_timestamp(1_599_999_999_999), Some(false));
+        assert_eq!(since.satisfied_by_timestamp(1_600_000_000_000), Some(true));
+        let since = Since::new(SinceType::Timestamp, 1_600_000_000, true);
+        assert_eq!(since.satisfied_by_timestamp(1_600_000_000_000), None);
+        let since = Since::new(SinceType::BlockNumber, 20, false);
+        assert_eq!(since.satisfied_by_timestamp(u64::MAX), None);
+    }
+
+    #[test]
+    fn test_max() {
+        let block = |value, is_relative| Since::new(SinceType::BlockNumber, value, is_relative);
+        assert_eq!(
+            Since::max(block(5, false), block(8, false)),
+            Some(block(8, false))
+        );
+        assert_eq!(
+            Since::max(block(8, true), block(5, true)),
+            Some(block(8, true))
+        );
+        assert_eq!(
+            Since::max(Since::from_raw_value(0), block(5, true)),
+            Some(block(5, true))
+        );
+        assert_eq!(Since::max(block(5, false), block(8, true)), None);
+        assert_eq!(
+            Since::max(block(5, false), Since::new(SinceType::Timestamp, 8, false)),
+            None
+        );
+
+        // 2 1/2 epochs is later than 2 2/5 epochs
+        let epoch = |number, index, length| {
+            Since::new(
+                SinceType::EpochNumberWithFraction,
+                EpochNumberWithFraction::new(number, index, length).full_value(),
+                false,
+            )
+        };
+        assert_eq!(
+            Since::max(epoch(2, 1, 2), epoch(2, 2, 5)),
+            Some(epoch(2, 1, 2))
+        );
+        assert_eq!(
+            Since::max(epoch(2, 2, 5), epoch(3, 0, 1)),
+            Some(epoch(3, 0, 1))
+        );
+    }
 }
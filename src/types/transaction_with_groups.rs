@@ -26,6 +26,12 @@ impl TransactionWithScriptGroups {
         self.tx_view = tx_view;
     }
 
+    /// The serialized size of the transaction in block, it is used to calculate the
+    /// transaction fee and must not exceed the block size limit.
+    pub fn serialized_size_in_block(&self) -> usize {
+        self.tx_view.data().as_reader().serialized_size_in_block()
+    }
+
     pub fn get_script_groups(&self) -> &[ScriptGroup] {
         &self.script_groups
     }
@@ -0,0 +1,427 @@
+//! Support for the CoBuild witness layout, scripts adopting CoBuild put their seals
+//! (e.g. signatures) into [`WitnessLayout`] instead of the legacy `WitnessArgs`.
+//!
+//! See: <https://talk.nervos.org/t/ckb-transaction-cobuild-protocol-overview/7702>
+use std::ops::Range;
+
+use anyhow::anyhow;
+use ckb_types::{
+    bytes::Bytes,
+    core::TransactionView,
+    packed::{self, OutPoint},
+    prelude::*,
+};
+
+use super::{ScriptUnlocker, UnlockError};
+use crate::traits::{Signer, TransactionDependencyProvider};
+use crate::types::cobuild_mol::{
+    Message, Otx, SighashAll, SighashAllOnly, WitnessLayout, WitnessLayoutUnion,
+};
+use crate::types::ScriptGroup;
+use crate::util::CkbHasher;
+
+pub const WITNESS_LAYOUT_SIGHASH_ALL: u32 = 0xFF000001;
+pub const WITNESS_LAYOUT_SIGHASH_ALL_ONLY: u32 = 0xFF000002;
+pub const WITNESS_LAYOUT_OTX: u32 = 0xFF000003;
+pub const WITNESS_LAYOUT_OTX_START: u32 = 0xFF000004;
+
+const PERSONALIZATION_SIGHASH_ALL: &[u8] = b"ckb-tcob-sighash";
+const PERSONALIZATION_SIGHASH_ALL_ONLY: &[u8] = b"ckb-tcob-sgohash";
+const PERSONALIZATION_OTX: &[u8] = b"ckb-tcob-otxhash";
+
+/// Parse the witness as [`WitnessLayout`], return `None` when it is a legacy
+/// `WitnessArgs` or other data.
+pub fn parse_witness_layout(witness: &[u8]) -> Option<WitnessLayout> {
+    WitnessLayout::from_slice(witness).ok()
+}
+
+/// Find the message in the unique `SighashAll` witness of the transaction, more
+/// than one `SighashAll` witness is an error.
+pub fn find_sighash_all_message(tx: &TransactionView) -> Result<Option<Message>, UnlockError> {
+    let mut message = None;
+    for (idx, witness) in tx.witnesses().into_iter().enumerate() {
+        if let Some(WitnessLayoutUnion::SighashAll(sighash_all)) =
+            parse_witness_layout(&witness.raw_data()).map(|layout| layout.to_enum())
+        {
+            if message.is_some() {
+                return Err(UnlockError::Other(anyhow!(
+                    "duplicated SighashAll witness at index {}",
+                    idx
+                )));
+            }
+            message = Some(sighash_all.message());
+        }
+    }
+    Ok(message)
+}
+
+fn hash_cell(
+    hasher: &mut CkbHasher,
+    out_point: &OutPoint,
+    tx_dep_provider: &dyn TransactionDependencyProvider,
+) -> Result<(), UnlockError> {
+    let output = tx_dep_provider.get_cell(out_point)?;
+    let data = tx_dep_provider.get_cell_data(out_point)?;
+    hasher.update(output.as_slice());
+    hasher.update(&(data.len() as u32).to_le_bytes());
+    hasher.update(&data);
+    Ok(())
+}
+
+/// Generate the signing message of the `SighashAll`/`SighashAllOnly` mode, it covers:
+///   * the message (only when given)
+///   * the transaction hash
+///   * all input cells and their data
+///   * the witnesses not covered by any inputs
+///
+/// The witnesses at input positions are not covered, so the legacy `WitnessArgs`
+/// and the seals can be filled in any order.
+pub fn generate_cobuild_sighash_all_message(
+    tx: &TransactionView,
+    message: Option<&Message>,
+    tx_dep_provider: &dyn TransactionDependencyProvider,
+) -> Result<Bytes, UnlockError> {
+    let mut hasher = match message {
+        Some(message) => {
+            let mut hasher = CkbHasher::with_personal(PERSONALIZATION_SIGHASH_ALL);
+            hasher.update(message.as_slice());
+            hasher
+        }
+        None => CkbHasher::with_personal(PERSONALIZATION_SIGHASH_ALL_ONLY),
+    };
+    hasher.update(tx.hash().as_slice());
+    for out_point in tx.input_pts_iter() {
+        hash_cell(&mut hasher, &out_point, tx_dep_provider)?;
+    }
+    for witness in tx.witnesses().into_iter().skip(tx.inputs().len()) {
+        let witness = witness.raw_data();
+        hasher.update(&(witness.len() as u32).to_le_bytes());
+        hasher.update(&witness);
+    }
+    Ok(Bytes::from(hasher.finalize().to_vec()))
+}
+
+/// An open transaction (otx) in the transaction, with the ranges of the inputs,
+/// outputs, cell deps and header deps it covers.
+#[derive(Debug, Clone)]
+pub struct CobuildOtx {
+    pub witness_index: usize,
+    pub otx: Otx,
+    pub inputs: Range<usize>,
+    pub outputs: Range<usize>,
+    pub cell_deps: Range<usize>,
+    pub header_deps: Range<usize>,
+}
+
+/// Collect the otxs in the transaction. The otx witnesses must be continuous and
+/// right after the unique `OtxStart` witness, the otx ranges are also continuous
+/// starting from the positions in the `OtxStart`.
+pub fn collect_otxs(tx: &TransactionView) -> Result<Vec<CobuildOtx>, UnlockError> {
+    let layouts = tx
+        .witnesses()
+        .into_iter()
+        .map(|witness| parse_witness_layout(&witness.raw_data()).map(|layout| layout.to_enum()))
+        .collect::<Vec<_>>();
+    let mut start = None;
+    for (idx, layout) in layouts.iter().enumerate() {
+        if let Some(WitnessLayoutUnion::OtxStart(otx_start)) = layout {
+            if start.is_some() {
+                return Err(UnlockError::Other(anyhow!(
+                    "duplicated OtxStart witness at index {}",
+                    idx
+                )));
+            }
+            start = Some((idx, otx_start.clone()));
+        }
+    }
+    let (start_idx, otx_start) = match start {
+        Some(start) => start,
+        None => return Ok(Vec::new()),
+    };
+
+    let unpack = |value: packed::Uint32| Unpack::<u32>::unpack(&value) as usize;
+    let mut input = unpack(otx_start.start_input_cell());
+    let mut output = unpack(otx_start.start_output_cell());
+    let mut cell_dep = unpack(otx_start.start_cell_deps());
+    let mut header_dep = unpack(otx_start.start_header_deps());
+    let mut otxs = Vec::new();
+    for (idx, layout) in layouts.iter().enumerate().skip(start_idx + 1) {
+        let otx = match layout {
+            Some(WitnessLayoutUnion::Otx(otx)) => otx,
+            _ => break,
+        };
+        let next = |start: usize, count: packed::Uint32| start..start + unpack(count);
+        let item = CobuildOtx {
+            witness_index: idx,
+            otx: otx.clone(),
+            inputs: next(input, otx.input_cells()),
+            outputs: next(output, otx.output_cells()),
+            cell_deps: next(cell_dep, otx.cell_deps()),
+            header_deps: next(header_dep, otx.header_deps()),
+        };
+        if item.inputs.end > tx.inputs().len()
+            || item.outputs.end > tx.outputs().len()
+            || item.cell_deps.end > tx.cell_deps().len()
+            || item.header_deps.end > tx.header_deps().len()
+        {
+            return Err(UnlockError::Other(anyhow!(
+                "otx at witness index {} is out of bound",
+                idx
+            )));
+        }
+        input = item.inputs.end;
+        output = item.outputs.end;
+        cell_dep = item.cell_deps.end;
+        header_dep = item.header_deps.end;
+        otxs.push(item);
+    }
+    Ok(otxs)
+}
+
+/// Generate the signing message of an otx, only the parts of the transaction in
+/// the otx ranges are covered, everything outside is skipped so the otx can be
+/// merged with others after signed.
+pub fn generate_cobuild_otx_message(
+    tx: &TransactionView,
+    otx: &CobuildOtx,
+    tx_dep_provider: &dyn TransactionDependencyProvider,
+) -> Result<Bytes, UnlockError> {
+    let mut hasher = CkbHasher::with_personal(PERSONALIZATION_OTX);
+    hasher.update(otx.otx.message().as_slice());
+    hasher.update(&(otx.inputs.len() as u32).to_le_bytes());
+    for idx in otx.inputs.clone() {
+        let input = tx.inputs().get(idx).expect("checked range");
+        hasher.update(input.as_slice());
+        hash_cell(&mut hasher, &input.previous_output(), tx_dep_provider)?;
+    }
+    hasher.update(&(otx.outputs.len() as u32).to_le_bytes());
+    for idx in otx.outputs.clone() {
+        let (output, data) = tx.output_with_data(idx).expect("checked range");
+        hasher.update(output.as_slice());
+        hasher.update(&(data.len() as u32).to_le_bytes());
+        hasher.update(&data);
+    }
+    hasher.update(&(otx.cell_deps.len() as u32).to_le_bytes());
+    for idx in otx.cell_deps.clone() {
+        hasher.update(tx.cell_deps().get(idx).expect("checked range").as_slice());
+    }
+    hasher.update(&(otx.header_deps.len() as u32).to_le_bytes());
+    for idx in otx.header_deps.clone() {
+        hasher.update(tx.header_deps().get(idx).expect("checked range").as_slice());
+    }
+    Ok(Bytes::from(hasher.finalize().to_vec()))
+}
+
+fn build_witness_layout(message: Option<&Message>, seal: Bytes) -> WitnessLayout {
+    let layout: WitnessLayoutUnion = match message {
+        Some(message) => SighashAll::new_builder()
+            .message(message.clone())
+            .seal(seal.pack())
+            .build()
+            .into(),
+        None => SighashAllOnly::new_builder()
+            .seal(seal.pack())
+            .build()
+            .into(),
+    };
+    WitnessLayout::new_builder().set(layout).build()
+}
+
+/// The unlocker for secp256k1 sighash locks in the CoBuild mode, the signature is
+/// put into the `SighashAll` (when the message is given) or `SighashAllOnly`
+/// witness at the first input of the script group.
+///
+/// There should be at most one script group carrying the message in the
+/// transaction, the other groups sign the same message found in the transaction.
+/// The legacy script groups in the same transaction are not affected.
+pub struct CobuildUnlocker {
+    signer: Box<dyn Signer>,
+    message: Option<Message>,
+}
+
+impl CobuildUnlocker {
+    pub fn new(signer: Box<dyn Signer>, message: Option<Message>) -> CobuildUnlocker {
+        CobuildUnlocker { signer, message }
+    }
+
+    pub fn message(&self) -> Option<&Message> {
+        self.message.as_ref()
+    }
+
+    fn fill_seal(
+        &self,
+        tx: &TransactionView,
+        script_group: &ScriptGroup,
+        seal: Bytes,
+    ) -> Result<TransactionView, UnlockError> {
+        let witness_idx = script_group.input_indices[0];
+        let mut witnesses: Vec<packed::Bytes> = tx.witnesses().into_iter().collect();
+        while witnesses.len() <= witness_idx {
+            witnesses.push(Default::default());
+        }
+        let witness_data = witnesses[witness_idx].raw_data();
+        if !witness_data.is_empty() && parse_witness_layout(&witness_data).is_none() {
+            return Err(UnlockError::InvalidWitnessArgs(witness_idx));
+        }
+        witnesses[witness_idx] = build_witness_layout(self.message.as_ref(), seal)
+            .as_bytes()
+            .pack();
+        Ok(tx.as_advanced_builder().set_witnesses(witnesses).build())
+    }
+}
+
+impl ScriptUnlocker for CobuildUnlocker {
+    fn match_args(&self, args: &[u8]) -> bool {
+        args.len() == 20 && self.signer.match_id(args)
+    }
+
+    fn unlock(
+        &self,
+        tx: &TransactionView,
+        script_group: &ScriptGroup,
+        tx_dep_provider: &dyn TransactionDependencyProvider,
+    ) -> Result<TransactionView, UnlockError> {
+        // make sure the witness is in place before the message is generated
+        let tx = self.fill_seal(tx, script_group, Bytes::from(vec![0u8; 65]))?;
+        let message = match self.message.as_ref() {
+            Some(message) => Some(message.clone()),
+            None => find_sighash_all_message(&tx)?,
+        };
+        let signing_message =
+            generate_cobuild_sighash_all_message(&tx, message.as_ref(), tx_dep_provider)?;
+        let args = script_group.script.args().raw_data();
+        let signature = self
+            .signer
+            .sign(args.as_ref(), signing_message.as_ref(), true, &tx)
+            .map_err(|err| UnlockError::ScriptSigner(err.into()))?;
+        self.fill_seal(&tx, script_group, signature)
+    }
+
+    fn fill_placeholder_witness(
+        &self,
+        tx: &TransactionView,
+        script_group: &ScriptGroup,
+        _tx_dep_provider: &dyn TransactionDependencyProvider,
+    ) -> Result<TransactionView, UnlockError> {
+        self.fill_seal(tx, script_group, Bytes::from(vec![0u8; 65]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_types::{
+        core::TransactionBuilder,
+        packed::{CellDep, CellInput, CellOutput, WitnessArgs},
+    };
+
+    use crate::test_util::random_out_point;
+    use crate::types::cobuild_mol::{Action, ActionVec, OtxStart};
+
+    fn build_message() -> Message {
+        let action = Action::new_builder()
+            .script_hash([1u8; 32].pack())
+            .data(Bytes::from(vec![1, 2, 3]).pack())
+            .build();
+        Message::new_builder()
+            .actions(ActionVec::new_builder().push(action).build())
+            .build()
+    }
+
+    #[test]
+    fn test_parse_witness_layout() {
+        let message = build_message();
+        let layout = build_witness_layout(Some(&message), Bytes::from(vec![0u8; 65]));
+        assert_eq!(layout.item_id(), WITNESS_LAYOUT_SIGHASH_ALL);
+        let parsed = parse_witness_layout(layout.as_slice()).unwrap();
+        match parsed.to_enum() {
+            WitnessLayoutUnion::SighashAll(sighash_all) => {
+                assert_eq!(sighash_all.message().as_slice(), message.as_slice());
+                assert_eq!(sighash_all.seal().raw_data().len(), 65);
+            }
+            _ => panic!("expect SighashAll"),
+        }
+        let layout = build_witness_layout(None, Bytes::from(vec![0u8; 65]));
+        assert_eq!(layout.item_id(), WITNESS_LAYOUT_SIGHASH_ALL_ONLY);
+
+        let witness_args = WitnessArgs::new_builder()
+            .lock(Some(Bytes::from(vec![0u8; 65])).pack())
+            .build();
+        assert!(parse_witness_layout(witness_args.as_slice()).is_none());
+        assert!(parse_witness_layout(&[]).is_none());
+    }
+
+    #[test]
+    fn test_find_sighash_all_message() {
+        let message = build_message();
+        let sighash_all = build_witness_layout(Some(&message), Bytes::new());
+        let tx = TransactionBuilder::default()
+            .witness(Bytes::new().pack())
+            .witness(sighash_all.as_bytes().pack())
+            .build();
+        assert_eq!(
+            find_sighash_all_message(&tx).unwrap().map(|m| m.as_bytes()),
+            Some(message.as_bytes())
+        );
+        let tx = tx
+            .as_advanced_builder()
+            .witness(sighash_all.as_bytes().pack())
+            .build();
+        assert!(find_sighash_all_message(&tx).is_err());
+    }
+
+    #[test]
+    fn test_collect_otxs() {
+        let otx_start = OtxStart::new_builder()
+            .start_input_cell(1u32.pack())
+            .start_output_cell(0u32.pack())
+            .build();
+        let otx = |inputs: u32, outputs: u32| {
+            Otx::new_builder()
+                .input_cells(inputs.pack())
+                .output_cells(outputs.pack())
+                .cell_deps(1u32.pack())
+                .message(build_message())
+                .build()
+        };
+        let layout = |item: WitnessLayoutUnion| {
+            WitnessLayout::new_builder()
+                .set(item)
+                .build()
+                .as_bytes()
+                .pack()
+        };
+        let mut builder = TransactionBuilder::default();
+        for _ in 0..4 {
+            builder = builder
+                .input(CellInput::new(random_out_point(), 0))
+                .output(CellOutput::default())
+                .output_data(Bytes::new().pack())
+                .cell_dep(CellDep::new_builder().out_point(random_out_point()).build());
+        }
+        let tx = builder
+            .witness(Bytes::new().pack())
+            .witness(layout(otx_start.into()))
+            .witness(layout(otx(1, 2).into()))
+            .witness(layout(otx(2, 1).into()))
+            .build();
+
+        let otxs = collect_otxs(&tx).unwrap();
+        assert_eq!(otxs.len(), 2);
+        assert_eq!(otxs[0].witness_index, 2);
+        assert_eq!(otxs[0].inputs, 1..2);
+        assert_eq!(otxs[0].outputs, 0..2);
+        assert_eq!(otxs[0].cell_deps, 0..1);
+        assert_eq!(otxs[1].inputs, 2..4);
+        assert_eq!(otxs[1].outputs, 2..3);
+        assert_eq!(otxs[1].cell_deps, 1..2);
+        assert_eq!(otxs[1].header_deps, 0..0);
+
+        // the otx is out of the inputs range
+        let tx = tx
+            .as_advanced_builder()
+            .witness(layout(otx(1, 0).into()))
+            .build();
+        assert!(collect_otxs(&tx).is_err());
+    }
+}
@@ -0,0 +1,188 @@
+//! Sign and verify the human readable messages (e.g. "sign this message to log in"), the
+//! message is hashed as Neuron does: `blake2b_256("Nervos Message:" | message)` with the
+//! ckb personalization, the signature is the 65 bytes recoverable secp256k1 signature.
+use std::convert::TryInto;
+
+use anyhow::anyhow;
+use ckb_crypto::secp::Pubkey;
+use ckb_types::{core::TransactionBuilder, packed::Script, H160, H256};
+
+use crate::{
+    constants::SIGHASH_TYPE_HASH,
+    traits::{Signer, SignerError},
+    util::{blake160, convert_keccak256_hash, keccak160, CkbHasher},
+    Address, ScriptId, SECP256K1,
+};
+
+use super::IdentityFlag;
+
+/// The prefix of the signed message, it makes a signed message can never be a valid
+/// transaction signature.
+pub const MESSAGE_PREFIX: &[u8] = b"Nervos Message:";
+
+/// The hash of the message to be signed.
+pub fn hash_message(message: &[u8]) -> H256 {
+    let mut hasher = CkbHasher::new();
+    hasher.update(MESSAGE_PREFIX).update(message);
+    H256(hasher.finalize())
+}
+
+/// Sign the message for the sighash address or the omni-lock address with a pubkey
+/// hash identity, the `id` is the blake160 of the public key.
+pub fn sign_message(
+    signer: &dyn Signer,
+    id: &[u8],
+    message: &[u8],
+) -> Result<[u8; 65], SignerError> {
+    sign_hash(signer, id, hash_message(message).as_bytes())
+}
+
+/// Sign the message for the omni-lock address with an ethereum identity, the `id` is
+/// the keccak160 of the public key. The message hash is signed in the ethereum
+/// personal message form, the same as the omni-lock ethereum auth.
+pub fn sign_ethereum_message(
+    signer: &dyn Signer,
+    id: &[u8],
+    message: &[u8],
+) -> Result<[u8; 65], SignerError> {
+    let hash = convert_keccak256_hash(hash_message(message).as_bytes());
+    sign_hash(signer, id, hash.as_bytes())
+}
+
+fn sign_hash(signer: &dyn Signer, id: &[u8], hash: &[u8]) -> Result<[u8; 65], SignerError> {
+    // the signer requires a transaction, there is no transaction for the message
+    let tx = TransactionBuilder::default().build();
+    let signature = signer.sign(id, hash, true, &tx)?;
+    signature[..].try_into().map_err(|_| {
+        SignerError::Other(anyhow!(
+            "invalid recoverable signature length: {}",
+            signature.len()
+        ))
+    })
+}
+
+/// Verify the message is signed by the owner of the address, the supported addresses are:
+///   * the sighash address
+///   * the omni-lock address (code hash in `omni_lock_ids`) with the pubkey hash or the
+///     ethereum identity
+///
+/// Return false for the unsupported addresses.
+pub fn verify_message(
+    address: &Address,
+    message: &[u8],
+    signature: &[u8; 65],
+    omni_lock_ids: &[ScriptId],
+) -> bool {
+    let script = Script::from(address);
+    let args = script.args().raw_data();
+    let hash = hash_message(message);
+    if ScriptId::from(&script) == ScriptId::new_type(SIGHASH_TYPE_HASH) {
+        return args.len() == 20
+            && recover_pubkey(hash.as_bytes(), signature)
+                .map(|pubkey| blake160(&pubkey.serialize()).as_bytes() == &args[..])
+                .unwrap_or(false);
+    }
+    if !omni_lock_ids.contains(&ScriptId::from(&script)) || args.len() < 21 {
+        return false;
+    }
+    let auth_content = &args[1..21];
+    let pubkey_hash = match args[0] {
+        flag if flag == IdentityFlag::PubkeyHash as u8 => {
+            recover_pubkey(hash.as_bytes(), signature).map(|pubkey| blake160(&pubkey.serialize()))
+        }
+        flag if flag == IdentityFlag::Ethereum as u8 => {
+            let hash = convert_keccak256_hash(hash.as_bytes());
+            recover_pubkey(hash.as_bytes(), signature)
+                .map(|pubkey| keccak160(Pubkey::from(pubkey).as_ref()))
+        }
+        _ => None,
+    };
+    pubkey_hash
+        .map(|pubkey_hash: H160| pubkey_hash.as_bytes() == auth_content)
+        .unwrap_or(false)
+}
+
+fn recover_pubkey(hash: &[u8], signature: &[u8; 65]) -> Option<secp256k1::PublicKey> {
+    let recovery_id = secp256k1::ecdsa::RecoveryId::from_i32(i32::from(signature[64])).ok()?;
+    let signature =
+        secp256k1::ecdsa::RecoverableSignature::from_compact(&signature[..64], recovery_id).ok()?;
+    let message = secp256k1::Message::from_slice(hash).ok()?;
+    SECP256K1.recover_ecdsa(&message, &signature).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use ckb_types::{bytes::Bytes, core::ScriptHashType, h256, prelude::*};
+
+    use super::*;
+    use crate::{traits::SecpCkbRawKeySigner, unlock::OmniLockConfig, AddressPayload, NetworkType};
+
+    const KEY: H256 = h256!("0x8fdf1d6df54c6c9c0167a657c0f68a9bb3bf4304942ce487880e86ce6099191c");
+
+    fn omni_lock_id() -> ScriptId {
+        ScriptId::new_type(h256!("0x1234"))
+    }
+
+    fn omni_lock_address(args: Bytes) -> Address {
+        let id = omni_lock_id();
+        let payload = AddressPayload::new_full(ScriptHashType::Type, id.code_hash.pack(), args);
+        Address::new(NetworkType::Testnet, payload, true)
+    }
+
+    #[test]
+    fn test_hash_message() {
+        // blake2b(digest_size=32, person=b"ckb-default-hash") of b"Nervos Message:hello"
+        assert_eq!(
+            hash_message(b"hello"),
+            h256!("0xb0b1e8d9bf460ea3d4ad088e50f117b0b43aa414108f92841ce3e11e2ea1366e")
+        );
+    }
+
+    #[test]
+    fn test_sign_and_verify_message() {
+        let key = secp256k1::SecretKey::from_slice(KEY.as_bytes()).unwrap();
+        let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, &key);
+        let signer = SecpCkbRawKeySigner::new_with_secret_keys(vec![key]);
+        let omni_lock_ids = [omni_lock_id()];
+
+        let pubkey_hash = blake160(&pubkey.serialize());
+        let address = Address::new(
+            NetworkType::Testnet,
+            AddressPayload::from_pubkey(&pubkey),
+            true,
+        );
+        let signature = sign_message(&signer, pubkey_hash.as_bytes(), b"log in").unwrap();
+        assert!(verify_message(&address, b"log in", &signature, &[]));
+        assert!(!verify_message(&address, b"log out", &signature, &[]));
+        let config = OmniLockConfig::new_pubkey_hash(pubkey_hash);
+        let omni_address = omni_lock_address(config.build_args());
+        assert!(verify_message(
+            &omni_address,
+            b"log in",
+            &signature,
+            &omni_lock_ids
+        ));
+        // the omni-lock code hash is not known
+        assert!(!verify_message(&omni_address, b"log in", &signature, &[]));
+
+        let eth_pubkey_hash = keccak160(Pubkey::from(pubkey).as_ref());
+        let eth_signer = SecpCkbRawKeySigner::new_with_ethereum_secret_keys(vec![key]);
+        let eth_signature =
+            sign_ethereum_message(&eth_signer, eth_pubkey_hash.as_bytes(), b"log in").unwrap();
+        let config = OmniLockConfig::new_ethereum(eth_pubkey_hash);
+        let eth_address = omni_lock_address(config.build_args());
+        assert!(verify_message(
+            &eth_address,
+            b"log in",
+            &eth_signature,
+            &omni_lock_ids
+        ));
+        assert!(!verify_message(
+            &eth_address,
+            b"log in",
+            &signature,
+            &omni_lock_ids
+        ));
+        assert!(!verify_message(&address, b"log in", &eth_signature, &[]));
+    }
+}
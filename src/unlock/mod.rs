@@ -1,16 +1,22 @@
+pub mod cobuild;
+pub mod message;
 pub(crate) mod omni_lock;
 pub mod rc_data;
 mod signer;
 mod unlocker;
+mod verify;
 
 pub use signer::{
-    generate_message, AcpScriptSigner, ChequeAction, ChequeScriptSigner, MultisigConfig,
-    OmniLockScriptSigner, OmniUnlockMode, ScriptSignError, ScriptSigner, SecpMultisigScriptSigner,
-    SecpSighashScriptSigner,
+    generate_message, generate_message_for_field, group_witness_index, AcpScriptSigner,
+    ChequeAction, ChequeScriptSigner, MultisigConfig, OmniLockScriptSigner, OmniUnlockMode,
+    ScriptSignError, ScriptSigner, SecpMultisigScriptSigner, SecpSighashScriptSigner, WitnessField,
 };
+pub(crate) use unlocker::subslice_offset;
 pub use unlocker::{
-    fill_witness_lock, reset_witness_lock, AcpUnlocker, ChequeUnlocker, OmniLockUnlocker,
-    ScriptUnlocker, SecpMultisigUnlocker, SecpSighashUnlocker, UnlockError,
+    fill_witness_field, fill_witness_lock, reset_witness_lock, AcpUnlocker, ChequeUnlocker,
+    OmniLockUnlocker, ScriptUnlocker, SecpMultisigUnlocker, SecpSighashUnlocker, UnlockError,
 };
 
+pub use cobuild::CobuildUnlocker;
 pub use omni_lock::{IdentityFlag, InfoCellData, OmniLockAcpConfig, OmniLockConfig};
+pub use verify::{verify_signatures, SignatureError};
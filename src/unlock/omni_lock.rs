@@ -642,6 +642,27 @@ impl OmniLockConfig {
         self.id.flag == IdentityFlag::OwnerLock
     }
 
+    /// The multisig config signing in the unlock mode, the one of the admin config in
+    /// the admin mode.
+    pub fn unlock_multisig_config(
+        &self,
+        unlock_mode: OmniUnlockMode,
+    ) -> Result<&MultisigConfig, ConfigError> {
+        match unlock_mode {
+            OmniUnlockMode::Admin => self
+                .admin_config
+                .as_ref()
+                .ok_or(ConfigError::NoAdminConfig)?
+                .multisig_config
+                .as_ref()
+                .ok_or(ConfigError::NoMultiSigConfig),
+            OmniUnlockMode::Normal => self
+                .multisig_config
+                .as_ref()
+                .ok_or(ConfigError::NoMultiSigConfig),
+        }
+    }
+
     pub fn placeholder_witness_lock(
         &self,
         unlock_mode: OmniUnlockMode,
@@ -650,24 +671,10 @@ impl OmniLockConfig {
             IdentityFlag::PubkeyHash | IdentityFlag::Ethereum => OmniLockWitnessLock::new_builder()
                 .signature(Some(Bytes::from(vec![0u8; 65])).pack()),
             IdentityFlag::Multisig => {
-                let multisig_config = match unlock_mode {
-                    OmniUnlockMode::Admin => self
-                        .admin_config
-                        .as_ref()
-                        .ok_or(ConfigError::NoAdminConfig)?
-                        .multisig_config
-                        .as_ref()
-                        .ok_or(ConfigError::NoMultiSigConfig)?,
-                    OmniUnlockMode::Normal => self
-                        .multisig_config
-                        .as_ref()
-                        .ok_or(ConfigError::NoMultiSigConfig)?,
-                };
-                let config_data = multisig_config.to_witness_data();
-                let multisig_len = config_data.len() + multisig_config.threshold() as usize * 65;
-                let mut omni_sig = vec![0u8; multisig_len];
-                omni_sig[..config_data.len()].copy_from_slice(&config_data);
-                OmniLockWitnessLock::new_builder().signature(Some(Bytes::from(omni_sig)).pack())
+                let omni_sig = self
+                    .unlock_multisig_config(unlock_mode)?
+                    .placeholder_witness_lock();
+                OmniLockWitnessLock::new_builder().signature(Some(omni_sig).pack())
             }
             IdentityFlag::OwnerLock => OmniLockWitnessLock::new_builder(),
             _ => todo!("to support other placeholder_witness_lock implementions"),
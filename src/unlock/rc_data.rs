@@ -6,8 +6,8 @@ use crate::types::xudt_rce_mol::{
     RCDataBuilder, RCDataUnion, RCRuleBuilder, SmtProofBuilder, SmtProofEntryBuilder,
     SmtProofEntryVec, SmtProofEntryVecBuilder,
 };
+use crate::util::CkbHasher;
 use bytes::Bytes;
-use ckb_hash::{new_blake2b, Blake2b};
 use ckb_types::{molecule, prelude::*};
 use sparse_merkle_tree::traits::Hasher;
 use thiserror::Error;
@@ -42,23 +42,15 @@ const WHITE_BLACK_LIST_MASK: u8 = 0x2;
 // on(1): emergency halt mode
 // off(0): not int emergency halt mode
 const EMERGENCY_HALT_MODE_MASK: u8 = 0x1;
-pub struct CKBBlake2bHasher(Blake2b);
-
-impl Default for CKBBlake2bHasher {
-    fn default() -> Self {
-        let blake2b = new_blake2b();
-        CKBBlake2bHasher(blake2b)
-    }
-}
+#[derive(Default)]
+pub struct CKBBlake2bHasher(CkbHasher);
 
 impl Hasher for CKBBlake2bHasher {
     fn write_h256(&mut self, h: &SmtH256) {
         self.0.update(h.as_slice());
     }
     fn finish(self) -> SmtH256 {
-        let mut hash = [0u8; 32];
-        self.0.finalize(&mut hash);
-        hash.into()
+        self.0.finalize().into()
     }
     fn write_byte(&mut self, b: u8) {
         self.0.update(&[b][..]);
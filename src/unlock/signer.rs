@@ -1,7 +1,6 @@
 use std::collections::HashSet;
 
 use anyhow::anyhow;
-use ckb_hash::{blake2b_256, new_blake2b};
 use ckb_types::{
     bytes::{Bytes, BytesMut},
     core::{ScriptHashType, TransactionView},
@@ -16,10 +15,10 @@ use thiserror::Error;
 use crate::{constants::MULTISIG_TYPE_HASH, types::omni_lock::OmniLockWitnessLock};
 use crate::{
     traits::{Signer, SignerError},
-    util::convert_keccak256_hash,
+    util::{blake160, ckb_blake2b_256, convert_keccak256_hash, CkbHasher},
 };
 use crate::{
-    types::{AddressPayload, CodeHashIndex, ScriptGroup, Since},
+    types::{AddressPayload, CodeHashIndex, ScriptGroup, ScriptGroupType, Since},
     Address, NetworkType,
 };
 
@@ -91,7 +90,9 @@ impl SecpSighashScriptSigner {
         tx: &TransactionView,
         script_group: &ScriptGroup,
     ) -> Result<TransactionView, ScriptSignError> {
-        let witness_idx = script_group.input_indices[0];
+        // a type script group signs into the `input_type` or `output_type` field
+        let field = WitnessField::of_group(script_group);
+        let witness_idx = group_witness_index(script_group);
         let mut witnesses: Vec<packed::Bytes> = tx.witnesses().into_iter().collect();
         while witnesses.len() <= witness_idx {
             witnesses.push(Default::default());
@@ -101,22 +102,19 @@ impl SecpSighashScriptSigner {
             .set_witnesses(witnesses.clone())
             .build();
 
-        let zero_lock = Bytes::from(vec![0u8; 65]);
-        let message = generate_message(&tx_new, script_group, zero_lock)?;
+        let zero_field = Bytes::from(vec![0u8; 65]);
+        let message = generate_message_for_field(&tx_new, script_group, field, zero_field)?;
 
         let signature = self.signer.sign(owner_id, message.as_ref(), true, tx)?;
 
         // Put signature into witness
         let witness_data = witnesses[witness_idx].raw_data();
-        let mut current_witness: WitnessArgs = if witness_data.is_empty() {
+        let current_witness: WitnessArgs = if witness_data.is_empty() {
             WitnessArgs::default()
         } else {
             WitnessArgs::from_slice(witness_data.as_ref())?
         };
-        current_witness = current_witness
-            .as_builder()
-            .lock(Some(signature).pack())
-            .build();
+        let current_witness = field.set(current_witness, signature);
         witnesses[witness_idx] = current_witness.as_bytes().pack();
         Ok(tx.as_advanced_builder().set_witnesses(witnesses).build())
     }
@@ -149,6 +147,18 @@ impl MultisigConfig {
         require_first_n: u8,
         threshold: u8,
     ) -> Result<MultisigConfig, ScriptSignError> {
+        if sighash_addresses.is_empty() || sighash_addresses.len() > u8::MAX as usize {
+            return Err(ScriptSignError::InvalidMultisigConfig(format!(
+                "Invalid address count {}, expected: 1 ~ {}",
+                sighash_addresses.len(),
+                u8::MAX
+            )));
+        }
+        if threshold == 0 {
+            return Err(ScriptSignError::InvalidMultisigConfig(
+                "Invalid threshold 0".to_string(),
+            ));
+        }
         let mut addr_set: HashSet<&H160> = HashSet::default();
         for addr in &sighash_addresses {
             if !addr_set.insert(addr) {
@@ -194,9 +204,7 @@ impl MultisigConfig {
     }
 
     pub fn hash160(&self) -> H160 {
-        let witness_data = self.to_witness_data();
-        let params_hash = blake2b_256(witness_data);
-        H160::from_slice(&params_hash[0..20]).unwrap()
+        blake160(&self.to_witness_data())
     }
 
     pub fn to_address_payload(&self, since_absolute_epoch: Option<u64>) -> AddressPayload {
@@ -229,12 +237,76 @@ impl MultisigConfig {
         witness_data
     }
 
-    pub fn placeholder_witness(&self) -> WitnessArgs {
+    /// Parse the multisig script (`to_witness_data`), e.g. from the beginning of the
+    /// witness lock of a multisig signed transaction. The lock args only contain the
+    /// hash of the multisig script, the config can not be recovered from them.
+    pub fn from_witness_lock_script(data: &[u8]) -> Result<MultisigConfig, ScriptSignError> {
+        if data.len() < 4 {
+            return Err(ScriptSignError::InvalidMultisigConfig(format!(
+                "Invalid multisig script length: {}",
+                data.len()
+            )));
+        }
+        if data[0] != 0 {
+            return Err(ScriptSignError::InvalidMultisigConfig(format!(
+                "Invalid reserved byte: {}",
+                data[0]
+            )));
+        }
+        let pubkeys_cnt = data[3] as usize;
+        if data.len() != 4 + pubkeys_cnt * 20 {
+            return Err(ScriptSignError::InvalidMultisigConfig(format!(
+                "Invalid multisig script length: {}, expected: {}",
+                data.len(),
+                4 + pubkeys_cnt * 20
+            )));
+        }
+        let sighash_addresses = data[4..]
+            .chunks(20)
+            .map(|chunk| H160::from_slice(chunk).unwrap())
+            .collect();
+        MultisigConfig::new_with(sighash_addresses, data[1], data[2])
+    }
+
+    /// The full serialized multisig script, it is put in the witness lock before the
+    /// signatures.
+    pub fn to_witness_lock_script(&self) -> Bytes {
+        Bytes::from(self.to_witness_data())
+    }
+
+    /// The blake160 of the multisig script, the first 20 bytes of the lock args.
+    pub fn script_hash(&self) -> [u8; 20] {
+        self.hash160().0
+    }
+
+    /// Check if the lock script is a multisig lock of this config, with or without the
+    /// since in the args.
+    pub fn matches_lock_script(&self, script: &Script) -> bool {
+        let args = script.args().raw_data();
+        script.code_hash() == MULTISIG_TYPE_HASH.pack()
+            && script.hash_type() == ScriptHashType::Type.into()
+            && (args.len() == 20 || args.len() == 28)
+            && args[0..20] == self.script_hash()
+    }
+
+    /// The length of the signed witness lock: the multisig script (4 bytes header and 20
+    /// bytes for each address) followed by `threshold` 65 bytes signatures.
+    pub fn witness_lock_len(&self) -> usize {
+        4 + 20 * self.sighash_addresses.len() + 65 * self.threshold as usize
+    }
+
+    /// The witness lock with the multisig script and the zero filled signatures, it has
+    /// the same length as the signed witness lock.
+    pub fn placeholder_witness_lock(&self) -> Bytes {
         let config_data = self.to_witness_data();
-        let mut zero_lock = vec![0u8; config_data.len() + 65 * self.threshold() as usize];
+        let mut zero_lock = vec![0u8; self.witness_lock_len()];
         zero_lock[0..config_data.len()].copy_from_slice(config_data.as_ref());
+        Bytes::from(zero_lock)
+    }
+
+    pub fn placeholder_witness(&self) -> WitnessArgs {
         WitnessArgs::new_builder()
-            .lock(Some(Bytes::from(zero_lock)).pack())
+            .lock(Some(self.placeholder_witness_lock()).pack())
             .build()
     }
 
@@ -263,7 +335,7 @@ pub struct SecpMultisigScriptSigner {
 }
 impl SecpMultisigScriptSigner {
     pub fn new(signer: Box<dyn Signer>, config: MultisigConfig) -> SecpMultisigScriptSigner {
-        let config_hash = blake2b_256(config.to_witness_data());
+        let config_hash = ckb_blake2b_256(config.to_witness_data());
         SecpMultisigScriptSigner {
             signer,
             config,
@@ -280,7 +352,8 @@ impl SecpMultisigScriptSigner {
 
 impl ScriptSigner for SecpMultisigScriptSigner {
     fn match_args(&self, args: &[u8]) -> bool {
-        self.config_hash[0..20] == args[0..20]
+        (args.len() == 20 || args.len() == 28)
+            && self.config_hash[0..20] == args[0..20]
             && self
                 .config
                 .sighash_addresses
@@ -304,9 +377,8 @@ impl ScriptSigner for SecpMultisigScriptSigner {
             .build();
 
         let config_data = self.config.to_witness_data();
-        let mut zero_lock = vec![0u8; config_data.len() + 65 * (self.config.threshold as usize)];
-        zero_lock[0..config_data.len()].copy_from_slice(&config_data);
-        let message = generate_message(&tx_new, script_group, Bytes::from(zero_lock.clone()))?;
+        let zero_lock = self.config.placeholder_witness_lock();
+        let message = generate_message(&tx_new, script_group, zero_lock.clone())?;
 
         let signatures = self
             .config
@@ -327,12 +399,12 @@ impl ScriptSigner for SecpMultisigScriptSigner {
             .lock()
             .to_opt()
             .map(|data| data.raw_data().as_ref().to_vec())
-            .unwrap_or(zero_lock);
-        if lock_field.len() != config_data.len() + self.config.threshold() as usize * 65 {
+            .unwrap_or_else(|| zero_lock.to_vec());
+        if lock_field.len() != self.config.witness_lock_len() {
             return Err(ScriptSignError::Other(anyhow!(
                 "invalid witness lock field length: {}, expected: {}",
                 lock_field.len(),
-                config_data.len() + self.config.threshold() as usize * 65,
+                self.config.witness_lock_len(),
             )));
         }
         for signature in signatures {
@@ -441,6 +513,58 @@ impl ScriptSigner for ChequeScriptSigner {
     }
 }
 
+/// The field of `WitnessArgs` the signature of a script group is put in
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum WitnessField {
+    Lock,
+    InputType,
+    OutputType,
+}
+
+impl WitnessField {
+    /// `lock` for a lock script group, `input_type` for a type script group with
+    /// inputs, otherwise `output_type`.
+    pub fn of_group(script_group: &ScriptGroup) -> WitnessField {
+        match script_group.group_type {
+            ScriptGroupType::Lock => WitnessField::Lock,
+            ScriptGroupType::Type if script_group.input_indices.is_empty() => {
+                WitnessField::OutputType
+            }
+            ScriptGroupType::Type => WitnessField::InputType,
+        }
+    }
+
+    pub fn get(self, witness: &WitnessArgs) -> Option<Bytes> {
+        let field = match self {
+            WitnessField::Lock => witness.lock(),
+            WitnessField::InputType => witness.input_type(),
+            WitnessField::OutputType => witness.output_type(),
+        };
+        field.to_opt().map(|data| data.raw_data())
+    }
+
+    pub fn set(self, witness: WitnessArgs, data: Bytes) -> WitnessArgs {
+        let builder = witness.as_builder();
+        match self {
+            WitnessField::Lock => builder.lock(Some(data).pack()),
+            WitnessField::InputType => builder.input_type(Some(data).pack()),
+            WitnessField::OutputType => builder.output_type(Some(data).pack()),
+        }
+        .build()
+    }
+}
+
+/// The index of the witness holding the signature of the script group: the first
+/// input of the group, or the first output for a type script group without inputs.
+pub fn group_witness_index(script_group: &ScriptGroup) -> usize {
+    script_group
+        .input_indices
+        .first()
+        .or_else(|| script_group.output_indices.first())
+        .copied()
+        .expect("empty script group")
+}
+
 /// Common logic of generate message for certain script group. Overwrite
 /// this method to support special use case.
 pub fn generate_message(
@@ -448,64 +572,100 @@ pub fn generate_message(
     script_group: &ScriptGroup,
     zero_lock: Bytes,
 ) -> Result<Bytes, ScriptSignError> {
-    if tx.witnesses().item_count() <= script_group.input_indices[0] {
+    generate_message_for_field(tx, script_group, WitnessField::Lock, zero_lock)
+}
+
+/// Same as [`generate_message`], except the `field` of the first witness of the
+/// script group is replaced by `zero_field`. The witnesses of a type script group
+/// without inputs are the witnesses of its outputs, they are not hashed again as the
+/// witnesses not covered by any input.
+///
+/// When signing a type field, the lock fields of the hashed witnesses are replaced by
+/// zeros of the same length, so the lock signatures filled after the type signatures
+/// do not change the message.
+pub fn generate_message_for_field(
+    tx: &TransactionView,
+    script_group: &ScriptGroup,
+    field: WitnessField,
+    zero_field: Bytes,
+) -> Result<Bytes, ScriptSignError> {
+    let group_indices = if script_group.input_indices.is_empty() {
+        &script_group.output_indices
+    } else {
+        &script_group.input_indices
+    };
+    let witness_idx = group_witness_index(script_group);
+    if tx.witnesses().item_count() <= witness_idx {
         return Err(ScriptSignError::WitnessNotEnough);
     }
 
     let witnesses: Vec<packed::Bytes> = tx.witnesses().into_iter().collect();
-    let witness_data = witnesses[script_group.input_indices[0]].raw_data();
-    let mut init_witness = if witness_data.is_empty() {
+    let witness_data = witnesses[witness_idx].raw_data();
+    let init_witness = if witness_data.is_empty() {
         WitnessArgs::default()
     } else {
         WitnessArgs::from_slice(witness_data.as_ref())?
     };
-    init_witness = init_witness
-        .as_builder()
-        .lock(Some(zero_lock).pack())
-        .build();
+    let init_witness = field.set(init_witness, zero_field);
+    let init_witness = if field == WitnessField::Lock {
+        init_witness
+    } else {
+        zero_lock_field(init_witness)
+    };
+    let witness_data = |witness: &packed::Bytes| {
+        let data = witness.raw_data();
+        if field == WitnessField::Lock {
+            return data;
+        }
+        match WitnessArgs::from_slice(data.as_ref()) {
+            Ok(witness_args) => zero_lock_field(witness_args).as_bytes(),
+            Err(_) => data,
+        }
+    };
     // Other witnesses in current script group
-    let other_witnesses: Vec<([u8; 8], Bytes)> = script_group
-        .input_indices
+    let other_witnesses: Vec<([u8; 8], Bytes)> = group_indices
         .iter()
         .skip(1)
         .filter_map(|idx| witnesses.get(*idx))
         .map(|witness| {
-            (
-                (witness.item_count() as u64).to_le_bytes(),
-                witness.raw_data(),
-            )
+            let data = witness_data(witness);
+            ((data.len() as u64).to_le_bytes(), data)
         })
         .collect();
     // The witnesses not covered by any inputs
     let outter_witnesses: Vec<([u8; 8], Bytes)> = if tx.inputs().len() < witnesses.len() {
         witnesses[tx.inputs().len()..witnesses.len()]
             .iter()
-            .map(|witness| {
-                (
-                    (witness.item_count() as u64).to_le_bytes(),
-                    witness.raw_data(),
-                )
+            .enumerate()
+            .filter(|(idx, _)| !group_indices.contains(&(tx.inputs().len() + idx)))
+            .map(|(_, witness)| {
+                let data = witness_data(witness);
+                ((data.len() as u64).to_le_bytes(), data)
             })
             .collect()
     } else {
         Default::default()
     };
 
-    let mut blake2b = new_blake2b();
-    blake2b.update(tx.hash().as_slice());
-    blake2b.update(&(init_witness.as_bytes().len() as u64).to_le_bytes());
-    blake2b.update(&init_witness.as_bytes());
+    let mut hasher = CkbHasher::new();
+    hasher
+        .update(tx.hash().as_slice())
+        .update(&(init_witness.as_bytes().len() as u64).to_le_bytes())
+        .update(&init_witness.as_bytes());
     for (len_le, data) in other_witnesses {
-        blake2b.update(&len_le);
-        blake2b.update(&data);
+        hasher.update(&len_le).update(&data);
     }
     for (len_le, data) in outter_witnesses {
-        blake2b.update(&len_le);
-        blake2b.update(&data);
+        hasher.update(&len_le).update(&data);
+    }
+    Ok(Bytes::from(hasher.finalize().to_vec()))
+}
+
+fn zero_lock_field(witness: WitnessArgs) -> WitnessArgs {
+    match WitnessField::Lock.get(&witness) {
+        Some(lock) => WitnessField::Lock.set(witness, Bytes::from(vec![0u8; lock.len()])),
+        None => witness,
     }
-    let mut message = vec![0u8; 32];
-    blake2b.finalize(&mut message);
-    Ok(Bytes::from(message))
 }
 
 /// specify the unlock mode for a omnilock transaction.
@@ -607,12 +767,7 @@ impl OmniLockScriptSigner {
             .signature()
             .to_opt()
             .map(|data| data.raw_data().as_ref().to_vec())
-            .unwrap_or_else(|| {
-                let mut omni_sig =
-                    vec![0u8; config_data.len() + multisig_config.threshold() as usize * 65];
-                omni_sig[..config_data.len()].copy_from_slice(&config_data);
-                omni_sig
-            });
+            .unwrap_or_else(|| multisig_config.placeholder_witness_lock().to_vec());
         for signature in signatures {
             let mut idx = config_data.len();
             while idx < omni_sig.len() {
@@ -812,6 +967,44 @@ impl ScriptSigner for OmniLockScriptSigner {
     }
 }
 
+#[cfg(test)]
+mod multisig_config_tests {
+    use super::*;
+
+    #[test]
+    fn test_multisig_config_witness_lock_script() {
+        let config = MultisigConfig::new_with(
+            vec![H160([1u8; 20]), H160([2u8; 20]), H160([3u8; 20])],
+            1,
+            2,
+        )
+        .unwrap();
+        let data = config.to_witness_lock_script();
+        assert_eq!(data.len(), 64);
+        assert_eq!(
+            MultisigConfig::from_witness_lock_script(&data).unwrap(),
+            config
+        );
+        assert!(MultisigConfig::from_witness_lock_script(&data[..63]).is_err());
+        let mut invalid = data.to_vec();
+        invalid[2] = 4;
+        assert!(MultisigConfig::from_witness_lock_script(&invalid).is_err());
+
+        assert_eq!(config.script_hash(), config.hash160().0);
+        let script = Script::from(&config);
+        assert!(config.matches_lock_script(&script));
+        let payload = config.to_address_payload(Some(100));
+        assert!(config.matches_lock_script(&Script::from(&payload)));
+        let other = MultisigConfig::new_with(vec![H160([1u8; 20])], 0, 1).unwrap();
+        assert!(!other.matches_lock_script(&script));
+        let script = script
+            .as_builder()
+            .hash_type(ScriptHashType::Data.into())
+            .build();
+        assert!(!config.matches_lock_script(&script));
+    }
+}
+
 #[cfg(test)]
 mod anyhow_tests {
     use anyhow::anyhow;
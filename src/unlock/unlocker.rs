@@ -10,12 +10,14 @@ use thiserror::Error;
 use super::{
     omni_lock::{ConfigError, OmniLockFlags},
     signer::{
-        AcpScriptSigner, ChequeAction, ChequeScriptSigner, MultisigConfig, ScriptSignError,
-        ScriptSigner, SecpMultisigScriptSigner, SecpSighashScriptSigner,
+        group_witness_index, AcpScriptSigner, ChequeAction, ChequeScriptSigner, MultisigConfig,
+        ScriptSignError, ScriptSigner, SecpMultisigScriptSigner, SecpSighashScriptSigner,
+        WitnessField,
     },
     OmniLockConfig, OmniLockScriptSigner, OmniUnlockMode,
 };
 use crate::traits::{Signer, TransactionDependencyError, TransactionDependencyProvider};
+use crate::types::omni_lock::OmniLockWitnessLockReader;
 use crate::types::ScriptGroup;
 
 const CHEQUE_CLAIM_SINCE: u64 = 0;
@@ -38,6 +40,9 @@ pub enum UnlockError {
     #[error("sign context is incorrect")]
     SignContextTypeIncorrect,
 
+    #[error("script groups not claimed by any unlocker: `{0:?}`")]
+    UnclaimedScriptGroups(Vec<packed::Script>),
+
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
@@ -85,6 +90,16 @@ pub trait ScriptUnlocker {
         script_group: &ScriptGroup,
         tx_dep_provider: &dyn TransactionDependencyProvider,
     ) -> Result<TransactionView, UnlockError>;
+
+    /// The range (offset and length) of the signature in the witness lock filled by
+    /// `fill_placeholder_witness`, the signature is written there by `unlock`. The
+    /// default implementation returns `None` for unknown.
+    fn placeholder_signature_range(
+        &self,
+        _witness_lock: &[u8],
+    ) -> Result<Option<(usize, usize)>, UnlockError> {
+        Ok(None)
+    }
 }
 
 pub fn fill_witness_lock(
@@ -92,7 +107,18 @@ pub fn fill_witness_lock(
     script_group: &ScriptGroup,
     lock_field: Bytes,
 ) -> Result<TransactionView, UnlockError> {
-    let witness_idx = script_group.input_indices[0];
+    fill_witness_field(tx, script_group, WitnessField::Lock, lock_field)
+}
+
+/// Fill the `field` of the witness of the script group (see [`group_witness_index`])
+/// if the field is empty.
+pub fn fill_witness_field(
+    tx: &TransactionView,
+    script_group: &ScriptGroup,
+    field: WitnessField,
+    field_data: Bytes,
+) -> Result<TransactionView, UnlockError> {
+    let witness_idx = group_witness_index(script_group);
     let mut witnesses: Vec<packed::Bytes> = tx.witnesses().into_iter().collect();
     while witnesses.len() <= witness_idx {
         witnesses.push(Default::default());
@@ -104,8 +130,8 @@ pub fn fill_witness_lock(
         WitnessArgs::from_slice(witness_data.as_ref())
             .map_err(|_| UnlockError::InvalidWitnessArgs(witness_idx))?
     };
-    if witness.lock().is_none() {
-        witness = witness.as_builder().lock(Some(lock_field).pack()).build();
+    if field.get(&witness).is_none() {
+        witness = field.set(witness, field_data);
     }
     witnesses[witness_idx] = witness.as_bytes().pack();
     Ok(tx.as_advanced_builder().set_witnesses(witnesses).build())
@@ -171,7 +197,15 @@ impl ScriptUnlocker for SecpSighashUnlocker {
         script_group: &ScriptGroup,
         _tx_dep_provider: &dyn TransactionDependencyProvider,
     ) -> Result<TransactionView, UnlockError> {
-        fill_witness_lock(tx, script_group, Bytes::from(vec![0u8; 65]))
+        let field = WitnessField::of_group(script_group);
+        fill_witness_field(tx, script_group, field, Bytes::from(vec![0u8; 65]))
+    }
+
+    fn placeholder_signature_range(
+        &self,
+        _witness_lock: &[u8],
+    ) -> Result<Option<(usize, usize)>, UnlockError> {
+        Ok(Some((0, 65)))
     }
 }
 
@@ -208,14 +242,24 @@ impl ScriptUnlocker for SecpMultisigUnlocker {
         script_group: &ScriptGroup,
         _tx_dep_provider: &dyn TransactionDependencyProvider,
     ) -> Result<TransactionView, UnlockError> {
-        let config = self.signer.config();
-        let config_data = config.to_witness_data();
-        let mut zero_lock = vec![0u8; config_data.len() + 65 * (config.threshold() as usize)];
-        zero_lock[0..config_data.len()].copy_from_slice(&config_data);
-        fill_witness_lock(tx, script_group, Bytes::from(zero_lock))
+        let zero_lock = self.signer.config().placeholder_witness_lock();
+        fill_witness_lock(tx, script_group, zero_lock)
+    }
+
+    fn placeholder_signature_range(
+        &self,
+        _witness_lock: &[u8],
+    ) -> Result<Option<(usize, usize)>, UnlockError> {
+        Ok(Some(multisig_signature_range(self.signer.config())))
     }
 }
 
+/// The signatures follow the multisig script in the witness lock
+fn multisig_signature_range(config: &MultisigConfig) -> (usize, usize) {
+    let config_len = config.to_witness_data().len();
+    (config_len, config.witness_lock_len() - config_len)
+}
+
 pub struct AcpUnlocker {
     signer: AcpScriptSigner,
 }
@@ -459,6 +503,13 @@ impl ScriptUnlocker for AcpUnlocker {
             fill_witness_lock(tx, script_group, Bytes::from(vec![0u8; 65]))
         }
     }
+
+    fn placeholder_signature_range(
+        &self,
+        _witness_lock: &[u8],
+    ) -> Result<Option<(usize, usize)>, UnlockError> {
+        Ok(Some((0, 65)))
+    }
 }
 
 pub struct ChequeUnlocker {
@@ -724,6 +775,34 @@ impl ScriptUnlocker for OmniLockUnlocker {
         let lock_field = config.placeholder_witness_lock(self.signer.unlock_mode())?;
         fill_witness_lock(tx, script_group, lock_field)
     }
+
+    fn placeholder_signature_range(
+        &self,
+        witness_lock: &[u8],
+    ) -> Result<Option<(usize, usize)>, UnlockError> {
+        let lock = OmniLockWitnessLockReader::from_slice(witness_lock)
+            .map_err(|err| UnlockError::Other(anyhow!("invalid omni-lock witness: {}", err)))?;
+        let signature = match lock.signature().to_opt() {
+            Some(signature) => signature,
+            None => return Ok(None),
+        };
+        // skip the 4 bytes length header of the bytes
+        let offset = subslice_offset(witness_lock, signature.as_slice()) + 4;
+        let config = self.signer.config();
+        if config.is_multisig() {
+            let unlock_mode = self.signer.unlock_mode();
+            let (sig_offset, sig_len) =
+                multisig_signature_range(config.unlock_multisig_config(unlock_mode)?);
+            Ok(Some((offset + sig_offset, sig_len)))
+        } else {
+            Ok(Some((offset, signature.raw_data().len())))
+        }
+    }
+}
+
+/// The offset of `sub`, which must be a sub-slice of `data`
+pub(crate) fn subslice_offset(data: &[u8], sub: &[u8]) -> usize {
+    sub.as_ptr() as usize - data.as_ptr() as usize
 }
 #[cfg(test)]
 mod anyhow_tests {
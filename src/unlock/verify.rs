@@ -0,0 +1,320 @@
+use std::collections::HashSet;
+
+use ckb_crypto::secp::Pubkey;
+use ckb_types::{
+    bytes::Bytes,
+    core::{ScriptHashType, TransactionView},
+    packed::WitnessArgs,
+    prelude::*,
+    H160,
+};
+use thiserror::Error;
+
+use crate::{
+    constants::{MULTISIG_TYPE_HASH, SIGHASH_TYPE_HASH},
+    traits::{TransactionDependencyError, TransactionDependencyProvider},
+    tx_builder::{gen_script_groups, ScriptGroups},
+    types::{omni_lock::OmniLockWitnessLock, ScriptGroup},
+    util::{blake160, convert_keccak256_hash, keccak160},
+    ScriptId, SECP256K1,
+};
+
+use super::{generate_message, IdentityFlag, ScriptSignError};
+
+#[derive(Error, Debug)]
+pub enum SignatureError {
+    #[error("transaction dependency error: `{0}`")]
+    TxDep(#[from] TransactionDependencyError),
+
+    #[error("generate message error: `{0}`")]
+    Message(#[from] ScriptSignError),
+
+    #[error("invalid witness of input {index}: {reason}")]
+    InvalidWitness { index: usize, reason: String },
+
+    #[error("invalid signature of input {index}: {reason}")]
+    InvalidSignature { index: usize, reason: String },
+}
+
+/// Verify the signatures of all lock script groups in the transaction without a ckb node:
+/// the signing message of each group is recomputed, the public keys are recovered from
+/// the witness signatures and compared with the lock args.
+///
+/// The supported lock scripts are sighash, multisig and the omni-lock (pubkey hash,
+/// ethereum and multisig identities in normal mode) with the code hash in `omni_lock_ids`.
+///
+/// Return the lock script groups skipped since they are not supported.
+pub fn verify_signatures(
+    tx: &TransactionView,
+    tx_dep_provider: &dyn TransactionDependencyProvider,
+    omni_lock_ids: &[ScriptId],
+) -> Result<Vec<ScriptGroup>, SignatureError> {
+    let ScriptGroups { lock_groups, .. } = gen_script_groups(tx, tx_dep_provider)?;
+    let mut skipped = Vec::new();
+    for script_group in lock_groups.values() {
+        let script = &script_group.script;
+        let script_id = ScriptId::from(script);
+        let is_type = script.hash_type() == ScriptHashType::Type.into();
+        if is_type && script.code_hash() == SIGHASH_TYPE_HASH.pack() {
+            verify_sighash(tx, script_group)?;
+        } else if is_type && script.code_hash() == MULTISIG_TYPE_HASH.pack() {
+            verify_multisig(tx, script_group)?;
+        } else if omni_lock_ids.contains(&script_id) {
+            if !verify_omni_lock(tx, script_group)? {
+                skipped.push(script_group.clone());
+            }
+        } else {
+            skipped.push(script_group.clone());
+        }
+    }
+    Ok(skipped)
+}
+
+/// Return the lock field of the first witness in the script group.
+fn witness_lock(tx: &TransactionView, script_group: &ScriptGroup) -> Result<Bytes, SignatureError> {
+    let index = script_group.input_indices[0];
+    let invalid = |reason: &str| SignatureError::InvalidWitness {
+        index,
+        reason: reason.to_string(),
+    };
+    let witness = tx
+        .witnesses()
+        .get(index)
+        .ok_or_else(|| invalid("witness not found"))?;
+    let witness_args = WitnessArgs::from_slice(&witness.raw_data())
+        .map_err(|err| invalid(&format!("not WitnessArgs format: {}", err)))?;
+    witness_args
+        .lock()
+        .to_opt()
+        .map(|lock| lock.raw_data())
+        .ok_or_else(|| invalid("lock field is empty"))
+}
+
+fn recover_pubkey(
+    index: usize,
+    message: &[u8],
+    signature: &[u8],
+) -> Result<secp256k1::PublicKey, SignatureError> {
+    let invalid = |reason: String| SignatureError::InvalidSignature { index, reason };
+    let recovery_id = secp256k1::ecdsa::RecoveryId::from_i32(i32::from(signature[64]))
+        .map_err(|err| invalid(err.to_string()))?;
+    let signature =
+        secp256k1::ecdsa::RecoverableSignature::from_compact(&signature[..64], recovery_id)
+            .map_err(|err| invalid(err.to_string()))?;
+    let message =
+        secp256k1::Message::from_slice(message).map_err(|err| invalid(err.to_string()))?;
+    SECP256K1
+        .recover_ecdsa(&message, &signature)
+        .map_err(|err| invalid(err.to_string()))
+}
+
+fn check_pubkey_hash(index: usize, expected: &[u8], actual: &H160) -> Result<(), SignatureError> {
+    if expected != actual.as_bytes() {
+        return Err(SignatureError::InvalidSignature {
+            index,
+            reason: format!(
+                "signed by `{:#x}`, expected: `0x{}`",
+                actual,
+                hex_string(expected)
+            ),
+        });
+    }
+    Ok(())
+}
+
+fn hex_string(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn verify_sighash(tx: &TransactionView, script_group: &ScriptGroup) -> Result<(), SignatureError> {
+    let index = script_group.input_indices[0];
+    let lock = witness_lock(tx, script_group)?;
+    if lock.len() != 65 {
+        return Err(SignatureError::InvalidWitness {
+            index,
+            reason: format!("invalid signature length: {}", lock.len()),
+        });
+    }
+    let message = generate_message(tx, script_group, Bytes::from(vec![0u8; 65]))?;
+    let pubkey = recover_pubkey(index, &message, &lock)?;
+    let args = script_group.script.args().raw_data();
+    check_pubkey_hash(index, &args, &blake160(&pubkey.serialize()))
+}
+
+/// Verify the multisig lock field (config data + signatures) against the config hash,
+/// the lock field is replaced by `zero_lock` when generating the message.
+fn verify_multisig_lock(
+    index: usize,
+    config_hash: &[u8],
+    multisig_lock: &[u8],
+    message: &[u8],
+) -> Result<(), SignatureError> {
+    let invalid = |reason: String| SignatureError::InvalidWitness { index, reason };
+    if multisig_lock.len() < 4 {
+        return Err(invalid("multisig config is too short".to_string()));
+    }
+    let require_first_n = multisig_lock[1] as usize;
+    let threshold = multisig_lock[2] as usize;
+    let pubkeys_count = multisig_lock[3] as usize;
+    let config_len = 4 + pubkeys_count * 20;
+    if multisig_lock.len() != config_len + threshold * 65 {
+        return Err(invalid(format!(
+            "invalid multisig lock length: {}, expected: {}",
+            multisig_lock.len(),
+            config_len + threshold * 65
+        )));
+    }
+    let config_data = &multisig_lock[..config_len];
+    if blake160(config_data).as_bytes() != config_hash {
+        return Err(invalid(
+            "multisig config not match the lock args".to_string(),
+        ));
+    }
+    let pubkey_hashes: Vec<&[u8]> = config_data[4..].chunks(20).collect();
+    let mut signed = HashSet::new();
+    for signature in multisig_lock[config_len..].chunks(65) {
+        let pubkey = recover_pubkey(index, message, signature)?;
+        let pubkey_hash = blake160(&pubkey.serialize());
+        let position = pubkey_hashes
+            .iter()
+            .position(|hash| *hash == pubkey_hash.as_bytes())
+            .ok_or_else(|| SignatureError::InvalidSignature {
+                index,
+                reason: format!("`{:#x}` is not in the multisig config", pubkey_hash),
+            })?;
+        if !signed.insert(position) {
+            return Err(SignatureError::InvalidSignature {
+                index,
+                reason: format!("`{:#x}` signed more than once", pubkey_hash),
+            });
+        }
+    }
+    if (0..require_first_n).any(|position| !signed.contains(&position)) {
+        return Err(SignatureError::InvalidSignature {
+            index,
+            reason: format!("the first {} pubkeys must sign", require_first_n),
+        });
+    }
+    Ok(())
+}
+
+fn verify_multisig(tx: &TransactionView, script_group: &ScriptGroup) -> Result<(), SignatureError> {
+    let index = script_group.input_indices[0];
+    let lock = witness_lock(tx, script_group)?;
+    let config_len = lock
+        .get(3)
+        .map(|count| 4 + *count as usize * 20)
+        .unwrap_or(0);
+    if config_len == 0 || lock.len() < config_len {
+        return Err(SignatureError::InvalidWitness {
+            index,
+            reason: "multisig config is too short".to_string(),
+        });
+    }
+    let mut zero_lock = vec![0u8; lock.len()];
+    zero_lock[..config_len].copy_from_slice(&lock[..config_len]);
+    let message = generate_message(tx, script_group, Bytes::from(zero_lock))?;
+    let args = script_group.script.args().raw_data();
+    let config_hash = args
+        .get(0..20)
+        .ok_or_else(|| SignatureError::InvalidWitness {
+            index,
+            reason: "invalid multisig lock args".to_string(),
+        })?;
+    verify_multisig_lock(index, config_hash, &lock, &message)
+}
+
+/// Return false if the identity or the unlock mode is not supported.
+fn verify_omni_lock(
+    tx: &TransactionView,
+    script_group: &ScriptGroup,
+) -> Result<bool, SignatureError> {
+    let index = script_group.input_indices[0];
+    let args = script_group.script.args().raw_data();
+    if args.len() < 22 {
+        return Ok(false);
+    }
+    let lock = witness_lock(tx, script_group)?;
+    let witness_lock =
+        OmniLockWitnessLock::from_slice(&lock).map_err(|err| SignatureError::InvalidWitness {
+            index,
+            reason: format!("invalid omni-lock witness lock: {}", err),
+        })?;
+    // the administrator mode is not supported
+    if witness_lock.omni_identity().to_opt().is_some() {
+        return Ok(false);
+    }
+    let signature = witness_lock
+        .signature()
+        .to_opt()
+        .map(|signature| signature.raw_data())
+        .ok_or_else(|| SignatureError::InvalidWitness {
+            index,
+            reason: "omni-lock signature is empty".to_string(),
+        })?;
+    let message = generate_message(tx, script_group, Bytes::from(vec![0u8; lock.len()]))?;
+    let auth_content = &args[1..21];
+    let check_signature_len = |signature: &Bytes| {
+        if signature.len() != 65 {
+            return Err(SignatureError::InvalidWitness {
+                index,
+                reason: format!("invalid signature length: {}", signature.len()),
+            });
+        }
+        Ok(())
+    };
+    match args[0] {
+        flag if flag == IdentityFlag::PubkeyHash as u8 => {
+            check_signature_len(&signature)?;
+            let pubkey = recover_pubkey(index, &message, &signature)?;
+            check_pubkey_hash(index, auth_content, &blake160(&pubkey.serialize()))?;
+        }
+        flag if flag == IdentityFlag::Ethereum as u8 => {
+            check_signature_len(&signature)?;
+            let message = convert_keccak256_hash(&message);
+            let pubkey = recover_pubkey(index, message.as_bytes(), &signature)?;
+            check_pubkey_hash(
+                index,
+                auth_content,
+                &keccak160(Pubkey::from(pubkey).as_ref()),
+            )?;
+        }
+        flag if flag == IdentityFlag::Multisig as u8 => {
+            verify_multisig_lock(index, auth_content, &signature, &message)?;
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use ckb_types::{
+        core::TransactionBuilder,
+        packed::{CellInput, CellOutput, Script},
+    };
+
+    use super::*;
+    use crate::{test_util::random_out_point, traits::OffchainTransactionDependencyProvider};
+
+    #[test]
+    fn test_skip_unsupported_lock() {
+        let lock = Script::new_builder().args(vec![1u8; 20].pack()).build();
+        let prev_tx = TransactionBuilder::default()
+            .input(CellInput::new(random_out_point(), 0))
+            .output(CellOutput::new_builder().lock(lock.clone()).build())
+            .output_data(Bytes::new().pack())
+            .build();
+        let mut provider = OffchainTransactionDependencyProvider::new();
+        provider.apply_tx(prev_tx.data(), 0).unwrap();
+        let tx = TransactionBuilder::default()
+            .input(CellInput::new(
+                ckb_types::packed::OutPoint::new(prev_tx.hash(), 0),
+                0,
+            ))
+            .build();
+        let skipped = verify_signatures(&tx, &provider, &[]).unwrap();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].script, lock);
+    }
+}
@@ -0,0 +1,161 @@
+//! The blake2b hash functions used by ckb: blake2b with 32 bytes digest and the
+//! `ckb-default-hash` personalization.
+
+use ckb_hash::{Blake2b, Blake2bBuilder};
+use ckb_types::{
+    packed::{CellInput, Script},
+    prelude::*,
+    H160, H256,
+};
+
+/// The personalization of the ckb blake2b hash
+pub const CKB_HASH_PERSONALIZATION: &[u8] = b"ckb-default-hash";
+
+/// The digest size of the ckb blake2b hash
+pub const BLAKE2B_256_LEN: usize = 32;
+
+/// The max length of the blake2b personalization
+const BLAKE2B_PERSONAL_LEN: usize = 16;
+
+/// An incremental blake2b-256 hasher.
+pub struct CkbHasher(Blake2b);
+
+impl CkbHasher {
+    /// The hasher with the `ckb-default-hash` personalization
+    pub fn new() -> CkbHasher {
+        CkbHasher::with_personal(CKB_HASH_PERSONALIZATION)
+    }
+
+    /// The hasher with a custom personalization, at most 16 bytes, e.g. the
+    /// personalizations of the cobuild signing messages.
+    ///
+    /// # Panics
+    ///
+    /// When the personalization is longer than 16 bytes this function will panic.
+    pub fn with_personal(personal: &[u8]) -> CkbHasher {
+        assert!(
+            personal.len() <= BLAKE2B_PERSONAL_LEN,
+            "the blake2b personalization is longer than {} bytes",
+            BLAKE2B_PERSONAL_LEN
+        );
+        CkbHasher(
+            Blake2bBuilder::new(BLAKE2B_256_LEN)
+                .personal(personal)
+                .build(),
+        )
+    }
+
+    pub fn update(&mut self, data: &[u8]) -> &mut CkbHasher {
+        self.0.update(data);
+        self
+    }
+
+    pub fn finalize(self) -> [u8; 32] {
+        let mut digest = [0u8; BLAKE2B_256_LEN];
+        self.0.finalize(&mut digest);
+        digest
+    }
+}
+
+impl Default for CkbHasher {
+    fn default() -> Self {
+        CkbHasher::new()
+    }
+}
+
+/// The ckb blake2b-256 hash of the data
+pub fn ckb_blake2b_256<T: AsRef<[u8]>>(data: T) -> [u8; 32] {
+    let mut hasher = CkbHasher::new();
+    hasher.update(data.as_ref());
+    hasher.finalize()
+}
+
+/// The first 20 bytes of the ckb blake2b-256 hash, e.g. the lock args of a
+/// compressed secp256k1 public key.
+pub fn blake160(message: &[u8]) -> H160 {
+    H160::from_slice(&ckb_blake2b_256(message)[..20]).expect("blake160")
+}
+
+/// The hash of the script, same as `Script::calc_script_hash`.
+pub fn calc_script_hash(script: &Script) -> H256 {
+    H256(ckb_blake2b_256(script.as_slice()))
+}
+
+/// The type id (the args of the type id script) of the new output:
+/// blake2b(first input of the transaction | output index in u64 little endian).
+pub fn calc_type_id(first_input: &CellInput, output_index: u64) -> H256 {
+    let mut hasher = CkbHasher::new();
+    hasher
+        .update(first_input.as_slice())
+        .update(&output_index.to_le_bytes());
+    H256(hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use ckb_types::{core::ScriptHashType, h160, h256, packed::OutPoint};
+
+    use super::*;
+    use crate::constants::{SIGHASH_TYPE_HASH, TYPE_ID_CODE_HASH};
+
+    #[test]
+    fn test_ckb_blake2b() {
+        assert_eq!(
+            H256(ckb_blake2b_256([])),
+            h256!("0x44f4c69744d5f8c55d642062949dcae49bc4e7ef43d388c5a12f42b5633d163e")
+        );
+        let mut hasher = CkbHasher::new();
+        hasher.update(b"hello").update(b" ").update(b"world");
+        assert_eq!(hasher.finalize(), ckb_blake2b_256(b"hello world"));
+        assert_ne!(
+            CkbHasher::with_personal(b"ckb-tcob-sighash").finalize(),
+            CkbHasher::new().finalize()
+        );
+
+        // the compressed public key of the secret key `0x01`
+        let pubkey = secp256k1::PublicKey::from_secret_key(
+            &crate::SECP256K1,
+            &secp256k1::SecretKey::from_slice(&[[0u8; 31].as_slice(), &[1u8]].concat()).unwrap(),
+        );
+        assert_eq!(
+            blake160(&pubkey.serialize()),
+            h160!("0x75178f34549c5fe9cd1a0c57aebd01e7ddf9249e")
+        );
+
+        let script = Script::new_builder()
+            .code_hash(SIGHASH_TYPE_HASH.pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(vec![1u8; 20].pack())
+            .build();
+        assert_eq!(
+            calc_script_hash(&script),
+            script.calc_script_hash().unpack()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "the blake2b personalization is longer than 16 bytes")]
+    fn test_personal_too_long() {
+        CkbHasher::with_personal(b"ckb-default-hash!");
+    }
+
+    #[test]
+    fn test_calc_type_id() {
+        let first_input = CellInput::new(OutPoint::new(h256!("0x1").pack(), 2), 0);
+        let type_id = calc_type_id(&first_input, 1);
+        let mut data = first_input.as_slice().to_vec();
+        data.extend_from_slice(&1u64.to_le_bytes());
+        assert_eq!(type_id, H256(ckb_blake2b_256(data)));
+        assert_ne!(type_id, calc_type_id(&first_input, 0));
+
+        let type_id_script = Script::new_builder()
+            .code_hash(TYPE_ID_CODE_HASH.pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(type_id.as_bytes().pack())
+            .build();
+        assert_eq!(
+            calc_script_hash(&type_id_script),
+            type_id_script.calc_script_hash().unpack()
+        );
+    }
+}
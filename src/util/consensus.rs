@@ -0,0 +1,204 @@
+use std::convert::TryInto;
+
+use ckb_chain_spec::consensus::{Consensus, ConsensusBuilder, ProposalWindow};
+use ckb_jsonrpc_types as json_types;
+use ckb_types::{
+    core::{
+        hardfork::{HardForks, CKB2021, CKB2023},
+        Capacity, EpochNumberWithFraction, Ratio,
+    },
+    prelude::*,
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ConsensusConvertError {
+    #[error("unknown hardfork feature: rfc `{0}`")]
+    UnknownHardForkFeature(String),
+
+    #[error("missing hardfork feature: {0}")]
+    MissingHardForkFeature(String),
+
+    #[error("invalid field `{field}`: {reason}")]
+    InvalidField { field: &'static str, reason: String },
+}
+
+/// Build a local `Consensus` from the `get_consensus` rpc response, it is used to run the
+/// scripts locally with the same hardfork switches and limits as the node.
+///
+/// The genesis block and the softfork deployments are not part of the conversion, they are
+/// the defaults of `ConsensusBuilder`. Return error if a hardfork feature is unknown or
+/// missing, or a field is out of range.
+pub fn to_consensus_struct(
+    consensus: &json_types::Consensus,
+) -> Result<Consensus, ConsensusConvertError> {
+    let hardfork_switch = to_hardfork_switch(&consensus.hardfork_features)?;
+    let median_time_block_count = consensus
+        .median_time_block_count
+        .value()
+        .try_into()
+        .map_err(|_| invalid("median_time_block_count", "exceeds usize"))?;
+    let max_uncles_num = consensus
+        .max_uncles_num
+        .value()
+        .try_into()
+        .map_err(|_| invalid("max_uncles_num", "exceeds usize"))?;
+    // the fields of `RationalU256` are private, read them from the json form
+    let proposer_reward_ratio: json_types::Ratio =
+        serde_json::to_value(&consensus.proposer_reward_ratio)
+            .and_then(serde_json::from_value)
+            .map_err(|err| invalid("proposer_reward_ratio", &err.to_string()))?;
+
+    let mut result = ConsensusBuilder::default()
+        .id(consensus.id.clone())
+        .initial_primary_epoch_reward(Capacity::shannons(
+            consensus.initial_primary_epoch_reward.value(),
+        ))
+        .secondary_epoch_reward(Capacity::shannons(consensus.secondary_epoch_reward.value()))
+        .epoch_duration_target(consensus.epoch_duration_target.value())
+        .tx_proposal_window(ProposalWindow(
+            consensus.tx_proposal_window.closest.value(),
+            consensus.tx_proposal_window.farthest.value(),
+        ))
+        .cellbase_maturity(EpochNumberWithFraction::from_full_value(
+            consensus.cellbase_maturity.value(),
+        ))
+        .median_time_block_count(median_time_block_count)
+        .max_block_cycles(consensus.max_block_cycles.value())
+        .max_block_bytes(consensus.max_block_bytes.value())
+        .max_block_proposals_limit(consensus.max_block_proposals_limit.value())
+        .primary_epoch_reward_halving_interval(
+            consensus.primary_epoch_reward_halving_interval.value(),
+        )
+        .permanent_difficulty_in_dummy(consensus.permanent_difficulty_in_dummy)
+        .hardfork_switch(hardfork_switch)
+        .build();
+    // the fields without builder methods, or overridden by the genesis block in `build`
+    result.genesis_hash = consensus.genesis_hash.pack();
+    result.dao_type_hash = consensus.dao_type_hash.pack();
+    result.secp256k1_blake160_sighash_all_type_hash = consensus
+        .secp256k1_blake160_sighash_all_type_hash
+        .as_ref()
+        .map(Pack::pack);
+    result.secp256k1_blake160_multisig_all_type_hash = consensus
+        .secp256k1_blake160_multisig_all_type_hash
+        .as_ref()
+        .map(Pack::pack);
+    result.max_uncles_num = max_uncles_num;
+    result.orphan_rate_target = consensus.orphan_rate_target.clone();
+    result.proposer_reward_ratio = Ratio::new(
+        proposer_reward_ratio.numer.value(),
+        proposer_reward_ratio.denom.value(),
+    );
+    result.block_version = consensus.block_version.value();
+    result.tx_version = consensus.tx_version.value();
+    result.type_id_code_hash = consensus.type_id_code_hash.clone();
+    Ok(result)
+}
+
+fn to_hardfork_switch(
+    features: &json_types::HardForks,
+) -> Result<HardForks, ConsensusConvertError> {
+    // the feature list is private, read it from the json form
+    let features: Vec<json_types::HardForkFeature> = serde_json::to_value(features)
+        .and_then(serde_json::from_value)
+        .map_err(|err| invalid("hardfork_features", &err.to_string()))?;
+    let mut ckb2021 = CKB2021::new_builder();
+    let mut ckb2023 = CKB2023::new_builder();
+    for feature in features {
+        // `None` means the feature is never enabled
+        let epoch = feature
+            .epoch_number
+            .map(|number| number.value())
+            .unwrap_or(u64::MAX);
+        match feature.rfc.as_str() {
+            "0028" => ckb2021 = ckb2021.rfc_0028(epoch),
+            "0029" => ckb2021 = ckb2021.rfc_0029(epoch),
+            "0030" => ckb2021 = ckb2021.rfc_0030(epoch),
+            "0031" => ckb2021 = ckb2021.rfc_0031(epoch),
+            "0032" => ckb2021 = ckb2021.rfc_0032(epoch),
+            "0036" => ckb2021 = ckb2021.rfc_0036(epoch),
+            "0038" => ckb2021 = ckb2021.rfc_0038(epoch),
+            "0048" => ckb2023 = ckb2023.rfc_0048(epoch),
+            "0049" => ckb2023 = ckb2023.rfc_0049(epoch),
+            _ => return Err(ConsensusConvertError::UnknownHardForkFeature(feature.rfc)),
+        }
+    }
+    Ok(HardForks {
+        ckb2021: ckb2021
+            .build()
+            .map_err(ConsensusConvertError::MissingHardForkFeature)?,
+        ckb2023: ckb2023
+            .build()
+            .map_err(ConsensusConvertError::MissingHardForkFeature)?,
+    })
+}
+
+fn invalid(field: &'static str, reason: &str) -> ConsensusConvertError {
+    ConsensusConvertError::InvalidField {
+        field,
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{DAO_TYPE_HASH, SIGHASH_TYPE_HASH};
+
+    const MAINNET_CONSENSUS: &str = include_str!("../test-data/mainnet_consensus.json");
+
+    #[test]
+    fn test_to_consensus_struct() {
+        let json: json_types::Consensus = serde_json::from_str(MAINNET_CONSENSUS).unwrap();
+        let consensus = to_consensus_struct(&json).unwrap();
+        let ckb2021 = &consensus.hardfork_switch.ckb2021;
+        for epoch in [
+            ckb2021.rfc_0028(),
+            ckb2021.rfc_0029(),
+            ckb2021.rfc_0030(),
+            ckb2021.rfc_0031(),
+            ckb2021.rfc_0032(),
+            ckb2021.rfc_0036(),
+            ckb2021.rfc_0038(),
+        ] {
+            assert_eq!(epoch, 5414);
+        }
+        assert_eq!(consensus.hardfork_switch.ckb2023.rfc_0048(), 12293);
+        assert_eq!(consensus.hardfork_switch.ckb2023.rfc_0049(), 12293);
+        assert_eq!(consensus.max_block_cycles, 3_500_000_000);
+        assert_eq!(consensus.max_block_bytes, 597_000);
+        assert_eq!(consensus.max_block_proposals_limit, 1500);
+        assert_eq!(consensus.median_time_block_count, 37);
+        assert_eq!(consensus.tx_proposal_window, ProposalWindow(2, 10));
+        assert_eq!(consensus.cellbase_maturity.number(), 4);
+        assert_eq!(consensus.proposer_reward_ratio, Ratio::new(4, 10));
+        assert_eq!(consensus.dao_type_hash, DAO_TYPE_HASH.pack());
+        assert_eq!(
+            consensus.secp256k1_blake160_sighash_all_type_hash,
+            Some(SIGHASH_TYPE_HASH.pack())
+        );
+        assert_eq!(consensus.id, "ckb");
+    }
+
+    #[test]
+    fn test_invalid_hardfork_features() {
+        let mut value: serde_json::Value = serde_json::from_str(MAINNET_CONSENSUS).unwrap();
+        value["hardfork_features"][8]["rfc"] = "0099".into();
+        let json: json_types::Consensus = serde_json::from_value(value.clone()).unwrap();
+        assert!(matches!(
+            to_consensus_struct(&json),
+            Err(ConsensusConvertError::UnknownHardForkFeature(rfc)) if rfc == "0099"
+        ));
+
+        value["hardfork_features"]
+            .as_array_mut()
+            .unwrap()
+            .truncate(8);
+        let json: json_types::Consensus = serde_json::from_value(value).unwrap();
+        assert!(matches!(
+            to_consensus_struct(&json),
+            Err(ConsensusConvertError::MissingHardForkFeature(_))
+        ));
+    }
+}
@@ -0,0 +1,134 @@
+use std::time::Duration;
+
+use ckb_types::core::{EpochNumber, EpochNumberWithFraction, HeaderView};
+
+use crate::types::{Since, SinceType};
+use crate::util::epoch::estimate_time_of_epoch;
+
+/// The lock period of the DAO deposit cells, the cells can only be withdrawn at the end
+/// of a lock period.
+pub const LOCK_PERIOD_EPOCHS: EpochNumber = 180;
+
+/// The earliest epoch the DAO cell can be withdrawn (phase 2), it is the end of the first
+/// lock period (multiple of 180 epochs since the deposit) covering the withdraw (phase 1)
+/// block, the fraction of the deposit epoch is kept.
+///
+/// <https://github.com/nervosnetwork/ckb-system-scripts/blob/master/c/dao.c#L182-L223>
+pub fn earliest_withdraw_epoch(
+    deposit_header: &HeaderView,
+    withdraw_header: &HeaderView,
+) -> EpochNumberWithFraction {
+    let deposit_point = deposit_header.epoch();
+    let withdraw_point = withdraw_header.epoch();
+    // compare the fractions without rounding: index_a / length_a vs index_b / length_b
+    let withdraw_fraction = withdraw_point.index() * deposit_point.length();
+    let deposit_fraction = deposit_point.index() * withdraw_point.length();
+    let passed_epoch_cnt = if withdraw_fraction > deposit_fraction {
+        withdraw_point.number() - deposit_point.number() + 1
+    } else {
+        withdraw_point.number() - deposit_point.number()
+    };
+    let rest_epoch_cnt =
+        (passed_epoch_cnt + (LOCK_PERIOD_EPOCHS - 1)) / LOCK_PERIOD_EPOCHS * LOCK_PERIOD_EPOCHS;
+    EpochNumberWithFraction::new(
+        deposit_point.number() + rest_epoch_cnt,
+        deposit_point.index(),
+        deposit_point.length(),
+    )
+}
+
+/// The since value of the DAO withdraw (phase 2) input, an absolute epoch since of
+/// `earliest_withdraw_epoch`.
+pub fn withdraw_since(deposit_header: &HeaderView, withdraw_header: &HeaderView) -> Since {
+    let epoch = earliest_withdraw_epoch(deposit_header, withdraw_header);
+    Since::new(
+        SinceType::EpochNumberWithFraction,
+        epoch.full_value(),
+        false,
+    )
+}
+
+/// The estimated time before the DAO cell can be withdrawn (phase 2) from the tip
+/// block, format it by [`format_duration_approx`](crate::util::format_duration_approx)
+/// for the users.
+pub fn estimate_withdraw_wait(
+    deposit_header: &HeaderView,
+    withdraw_header: &HeaderView,
+    current_tip_header: &HeaderView,
+) -> Duration {
+    estimate_time_of_epoch(
+        current_tip_header,
+        earliest_withdraw_epoch(deposit_header, withdraw_header),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use ckb_types::{core::HeaderBuilder, prelude::*};
+
+    use super::*;
+
+    fn header(number: u64, index: u64, length: u64) -> HeaderView {
+        HeaderBuilder::default()
+            .epoch(
+                EpochNumberWithFraction::new(number, index, length)
+                    .full_value()
+                    .pack(),
+            )
+            .build()
+    }
+
+    #[test]
+    fn test_earliest_withdraw_epoch_on_cycle_boundary() {
+        let cases = vec![
+            // the withdraw block is exactly at the end of the first lock period
+            ((5, 5, 1000), (185, 5, 1000), (185, 5, 1000)),
+            // the same fraction with different epoch lengths
+            ((5, 500, 1000), (185, 900, 1800), (185, 500, 1000)),
+            ((5, 500, 1000), (185, 901, 1800), (365, 500, 1000)),
+            ((5, 500, 1000), (185, 899, 1800), (185, 500, 1000)),
+            // the deposit is at the start of an epoch
+            ((5, 0, 1000), (185, 0, 1800), (185, 0, 1000)),
+            ((5, 0, 1000), (185, 1, 1800), (365, 0, 1000)),
+            // the end of the second lock period
+            ((5, 5, 1000), (365, 5, 1000), (365, 5, 1000)),
+            // withdraw right after deposit
+            ((5, 5, 1000), (5, 6, 1000), (185, 5, 1000)),
+        ];
+        for (deposit, withdraw, expected) in cases {
+            let deposit_header = header(deposit.0, deposit.1, deposit.2);
+            let withdraw_header = header(withdraw.0, withdraw.1, withdraw.2);
+            let expected = EpochNumberWithFraction::new(expected.0, expected.1, expected.2);
+            assert_eq!(
+                earliest_withdraw_epoch(&deposit_header, &withdraw_header),
+                expected,
+                "deposit: {:?}, withdraw: {:?}",
+                deposit,
+                withdraw
+            );
+            let since = withdraw_since(&deposit_header, &withdraw_header);
+            assert!(!since.is_relative());
+            assert_eq!(
+                since.extract_metric(),
+                Some((SinceType::EpochNumberWithFraction, expected.full_value()))
+            );
+        }
+    }
+    #[test]
+    fn test_estimate_withdraw_wait() {
+        let deposit_header = header(5, 500, 1000);
+        let withdraw_header = header(100, 0, 1000);
+        assert_eq!(
+            crate::util::format_duration_approx(estimate_withdraw_wait(
+                &deposit_header,
+                &withdraw_header,
+                &header(184, 300, 1000)
+            )),
+            "4 hours 48 minutes"
+        );
+        assert_eq!(
+            estimate_withdraw_wait(&deposit_header, &withdraw_header, &header(185, 500, 1000)),
+            Duration::ZERO
+        );
+    }
+}
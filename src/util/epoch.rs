@@ -0,0 +1,391 @@
+use std::cmp::Ordering;
+#[cfg(feature = "rpc")]
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use ckb_types::core::{EpochNumberWithFraction, HeaderView};
+#[cfg(feature = "rpc")]
+use parking_lot::Mutex;
+use thiserror::Error;
+
+/// The target duration of an epoch
+pub const EPOCH_DURATION_TARGET: Duration = Duration::from_secs(4 * 60 * 60);
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum EpochParseError {
+    #[error("invalid epoch format: `{0}`, expected: `number` or `number.index/length`")]
+    InvalidFormat(String),
+
+    #[error("epoch {0} `{1}` is out of range")]
+    OutOfRange(&'static str, u64),
+
+    #[error("epoch index `{0}` should be less than length `{1}`")]
+    IndexExceedsLength(u64, u64),
+}
+
+fn parse_part(s: &str, name: &'static str, max: u64, input: &str) -> Result<u64, EpochParseError> {
+    let value: u64 = s
+        .parse()
+        .map_err(|_| EpochParseError::InvalidFormat(input.to_string()))?;
+    if value > max {
+        return Err(EpochParseError::OutOfRange(name, value));
+    }
+    Ok(value)
+}
+
+/// Parse the epoch from the human-readable format `number.index/length` (e.g. `"3.5/1800"`),
+/// a single `number` is treated as the start of the epoch.
+pub fn parse_epoch(s: &str) -> Result<EpochNumberWithFraction, EpochParseError> {
+    let input = s.trim();
+    let (number, fraction) = match input.split_once('.') {
+        Some((number, fraction)) => (number, Some(fraction)),
+        None => (input, None),
+    };
+    let number = parse_part(
+        number,
+        "number",
+        EpochNumberWithFraction::NUMBER_MASK,
+        input,
+    )?;
+    let (index, length) = match fraction {
+        Some(fraction) => {
+            let (index, length) = fraction
+                .split_once('/')
+                .ok_or_else(|| EpochParseError::InvalidFormat(input.to_string()))?;
+            let index = parse_part(index, "index", EpochNumberWithFraction::INDEX_MASK, input)?;
+            let length = parse_part(
+                length,
+                "length",
+                EpochNumberWithFraction::LENGTH_MASK,
+                input,
+            )?;
+            if index >= length {
+                return Err(EpochParseError::IndexExceedsLength(index, length));
+            }
+            (index, length)
+        }
+        None => (0, 1),
+    };
+    Ok(EpochNumberWithFraction::new(number, index, length))
+}
+
+/// Format the epoch in the format accepted by [`parse_epoch`], e.g. `"3.5/1800"`.
+pub fn format_epoch(e: EpochNumberWithFraction) -> String {
+    format!("{}.{}/{}", e.number(), e.index(), e.length())
+}
+
+/// The epoch as a float number, e.g. `3.5/10` is `3.5`.
+pub fn epoch_as_rational(e: EpochNumberWithFraction) -> f64 {
+    if e.length() == 0 {
+        e.number() as f64
+    } else {
+        e.number() as f64 + e.index() as f64 / e.length() as f64
+    }
+}
+
+/// Add `blocks` to the epoch, the epoch length is assumed to be unchanged in the
+/// following epochs.
+pub fn epoch_add(a: EpochNumberWithFraction, blocks: u64) -> EpochNumberWithFraction {
+    if a.length() == 0 {
+        return a;
+    }
+    let index = a.index() + blocks;
+    EpochNumberWithFraction::new(
+        a.number() + index / a.length(),
+        index % a.length(),
+        a.length(),
+    )
+}
+
+/// The epoch from which the cellbase outputs of a block in `block_epoch` can be
+/// spent, the fraction of `cellbase_maturity` is rounded up to the blocks of the
+/// epoch length of `block_epoch`.
+pub fn cellbase_mature_epoch(
+    block_epoch: EpochNumberWithFraction,
+    cellbase_maturity: EpochNumberWithFraction,
+) -> EpochNumberWithFraction {
+    let epoch = EpochNumberWithFraction::new(
+        block_epoch.number() + cellbase_maturity.number(),
+        block_epoch.index(),
+        block_epoch.length(),
+    );
+    if cellbase_maturity.index() == 0 || cellbase_maturity.length() == 0 {
+        return epoch;
+    }
+    let blocks = (cellbase_maturity.index() * block_epoch.length() + cellbase_maturity.length()
+        - 1)
+        / cellbase_maturity.length();
+    epoch_add(epoch, blocks)
+}
+
+/// Compare two epochs by their rational values, the epoch with zero length is
+/// treated as the start of the epoch.
+pub fn epoch_cmp(a: EpochNumberWithFraction, b: EpochNumberWithFraction) -> Ordering {
+    let fraction = |e: EpochNumberWithFraction| {
+        if e.length() == 0 {
+            (0, 1)
+        } else {
+            (u128::from(e.index()), u128::from(e.length()))
+        }
+    };
+    let (a_index, a_length) = fraction(a);
+    let (b_index, b_length) = fraction(b);
+    a.number()
+        .cmp(&b.number())
+        .then_with(|| (a_index * b_length).cmp(&(b_index * a_length)))
+}
+
+/// Estimate the time from the tip block to the target epoch by the target epoch
+/// duration, the progress of the current epoch is counted. Returns zero if the target
+/// epoch is already reached.
+pub fn estimate_time_of_epoch(
+    current_tip_header: &HeaderView,
+    target_epoch: EpochNumberWithFraction,
+) -> Duration {
+    let current = current_tip_header.epoch();
+    if epoch_cmp(target_epoch, current) != Ordering::Greater {
+        return Duration::ZERO;
+    }
+    let epoch_millis = EPOCH_DURATION_TARGET.as_millis() as i128;
+    let fraction_millis = |e: EpochNumberWithFraction| {
+        if e.length() == 0 {
+            0
+        } else {
+            epoch_millis * i128::from(e.index()) / i128::from(e.length())
+        }
+    };
+    let millis = i128::from(target_epoch.number() - current.number()) * epoch_millis
+        + fraction_millis(target_epoch)
+        - fraction_millis(current);
+    Duration::from_millis(millis.max(0) as u64)
+}
+
+/// Format the duration with the two most significant units for the users, e.g.
+/// `"3 days 4 hours"`, `"5 minutes"`.
+pub fn format_duration_approx(duration: Duration) -> String {
+    const UNITS: [(&str, u64); 3] = [("day", 24 * 60 * 60), ("hour", 60 * 60), ("minute", 60)];
+    let secs = duration.as_secs();
+    let first = match UNITS.iter().position(|(_, unit_secs)| secs >= *unit_secs) {
+        Some(first) => first,
+        None => return "less than a minute".to_string(),
+    };
+    let mut parts = Vec::new();
+    // the less significant unit is omitted if it's zero
+    for (index, (name, unit_secs)) in UNITS.iter().enumerate().skip(first).take(2) {
+        let value = if index == 0 {
+            secs / unit_secs
+        } else {
+            secs % UNITS[index - 1].1 / unit_secs
+        };
+        if value > 0 {
+            let plural = if value > 1 { "s" } else { "" };
+            parts.push(format!("{} {}{}", value, name, plural));
+        }
+    }
+    parts.join(" ")
+}
+
+/// Cache of the epoch ranges (start block number, epoch number and length) seen by
+/// [`EpochCache::epoch_of_block_number`], so the blocks of a cached epoch are resolved
+/// without requests. There is one entry per epoch.
+#[cfg(feature = "rpc")]
+#[derive(Default)]
+pub struct EpochCache {
+    // start number => (epoch number, length)
+    epochs: Mutex<BTreeMap<u64, (u64, u64)>>,
+}
+
+#[cfg(feature = "rpc")]
+impl EpochCache {
+    pub fn new() -> EpochCache {
+        EpochCache::default()
+    }
+
+    /// Get the epoch of the block, returns `None` if the block is not found.
+    pub fn epoch_of_block_number(
+        &self,
+        client: &crate::rpc::CkbRpcClient,
+        number: u64,
+    ) -> Result<Option<EpochNumberWithFraction>, crate::RpcError> {
+        if let Some((start_number, (epoch_number, length))) =
+            self.epochs.lock().range(..=number).next_back()
+        {
+            if number - start_number < *length {
+                return Ok(Some(EpochNumberWithFraction::new(
+                    *epoch_number,
+                    number - start_number,
+                    *length,
+                )));
+            }
+        }
+        let header: HeaderView = match client.get_header_by_number(number.into())? {
+            Some(header) => header.into(),
+            None => return Ok(None),
+        };
+        let epoch = header.epoch();
+        self.epochs
+            .lock()
+            .insert(number - epoch.index(), (epoch.number(), epoch.length()));
+        Ok(Some(epoch))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_epoch() {
+        assert_eq!(
+            parse_epoch("3.5/1800").unwrap(),
+            EpochNumberWithFraction::new(3, 5, 1800)
+        );
+        assert_eq!(
+            parse_epoch(" 3 ").unwrap(),
+            EpochNumberWithFraction::new(3, 0, 1)
+        );
+        assert!(matches!(
+            parse_epoch("3.5"),
+            Err(EpochParseError::InvalidFormat(_))
+        ));
+        assert!(matches!(
+            parse_epoch("a.5/10"),
+            Err(EpochParseError::InvalidFormat(_))
+        ));
+        assert_eq!(
+            parse_epoch("3.10/10"),
+            Err(EpochParseError::IndexExceedsLength(10, 10))
+        );
+        assert_eq!(
+            parse_epoch("3.1/65536"),
+            Err(EpochParseError::OutOfRange("length", 65536))
+        );
+    }
+
+    #[test]
+    fn test_format_epoch() {
+        let epoch = EpochNumberWithFraction::new(1234, 56, 180);
+        assert_eq!(format_epoch(epoch), "1234.56/180");
+        assert_eq!(parse_epoch(&format_epoch(epoch)).unwrap(), epoch);
+    }
+
+    #[test]
+    fn test_estimate_time_of_epoch() {
+        use ckb_types::{core::HeaderBuilder, prelude::*};
+
+        let tip = HeaderBuilder::default()
+            .epoch(
+                EpochNumberWithFraction::new(10, 450, 1800)
+                    .full_value()
+                    .pack(),
+            )
+            .build();
+        let hours = |h: u64| Duration::from_secs(h * 60 * 60);
+        assert_eq!(
+            estimate_time_of_epoch(&tip, EpochNumberWithFraction::new(11, 0, 1)),
+            hours(3)
+        );
+        assert_eq!(
+            estimate_time_of_epoch(&tip, EpochNumberWithFraction::new(30, 1, 2)),
+            hours(19 * 4 + 5)
+        );
+        assert_eq!(
+            estimate_time_of_epoch(&tip, EpochNumberWithFraction::new(10, 1, 4)),
+            Duration::ZERO
+        );
+        assert_eq!(
+            estimate_time_of_epoch(&tip, EpochNumberWithFraction::new(9, 0, 1)),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn test_format_duration_approx() {
+        let minutes = |m: u64| Duration::from_secs(m * 60);
+        assert_eq!(
+            format_duration_approx(Duration::from_secs(59)),
+            "less than a minute"
+        );
+        assert_eq!(format_duration_approx(minutes(5)), "5 minutes");
+        assert_eq!(format_duration_approx(minutes(61)), "1 hour 1 minute");
+        assert_eq!(
+            format_duration_approx(minutes((3 * 24 + 4) * 60 + 59)),
+            "3 days 4 hours"
+        );
+        assert_eq!(format_duration_approx(minutes(2 * 24 * 60 + 5)), "2 days");
+    }
+
+    #[cfg(feature = "rpc")]
+    #[test]
+    fn test_epoch_cache() {
+        use ckb_types::{core::HeaderBuilder, prelude::*};
+        use httpmock::prelude::*;
+
+        use crate::test_util::MockRpcResult;
+
+        let server = MockServer::start();
+        let header = HeaderBuilder::default()
+            .number(1010.pack())
+            .epoch(EpochNumberWithFraction::new(2, 10, 100).full_value().pack())
+            .build();
+        let get_header = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("get_header_by_number");
+            then.status(200).body(
+                MockRpcResult::new(Some(ckb_jsonrpc_types::HeaderView::from(header))).to_json(),
+            );
+        });
+        let client = crate::rpc::CkbRpcClient::new(&server.base_url());
+        let cache = EpochCache::new();
+        assert_eq!(
+            cache.epoch_of_block_number(&client, 1010).unwrap(),
+            Some(EpochNumberWithFraction::new(2, 10, 100))
+        );
+        assert_eq!(
+            cache.epoch_of_block_number(&client, 1099).unwrap(),
+            Some(EpochNumberWithFraction::new(2, 99, 100))
+        );
+        assert_eq!(
+            cache.epoch_of_block_number(&client, 1000).unwrap(),
+            Some(EpochNumberWithFraction::new(2, 0, 100))
+        );
+        get_header.assert_hits(1);
+    }
+
+    #[test]
+    fn test_epoch_arithmetic() {
+        let epoch = EpochNumberWithFraction::new(3, 5, 10);
+        assert_eq!(epoch_as_rational(epoch), 3.5);
+        assert_eq!(epoch_add(epoch, 4), EpochNumberWithFraction::new(3, 9, 10));
+        assert_eq!(epoch_add(epoch, 5), EpochNumberWithFraction::new(4, 0, 10));
+        assert_eq!(epoch_add(epoch, 27), EpochNumberWithFraction::new(6, 2, 10));
+
+        assert_eq!(
+            epoch_cmp(epoch, EpochNumberWithFraction::new(3, 1, 2)),
+            Ordering::Equal
+        );
+        assert_eq!(
+            epoch_cmp(epoch, EpochNumberWithFraction::new(3, 6, 10)),
+            Ordering::Less
+        );
+        assert_eq!(
+            epoch_cmp(epoch, EpochNumberWithFraction::new(2, 9, 10)),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn test_cellbase_mature_epoch() {
+        let block_epoch = EpochNumberWithFraction::new(10, 7, 10);
+        assert_eq!(
+            cellbase_mature_epoch(block_epoch, EpochNumberWithFraction::new(4, 0, 1)),
+            EpochNumberWithFraction::new(14, 7, 10)
+        );
+        // 1/3 epoch is rounded up to 4 blocks
+        assert_eq!(
+            cellbase_mature_epoch(block_epoch, EpochNumberWithFraction::new(4, 1, 3)),
+            EpochNumberWithFraction::new(15, 1, 10)
+        );
+    }
+}
@@ -0,0 +1,918 @@
+#[cfg(feature = "rpc")]
+use std::convert::TryInto;
+use std::{ptr, sync::atomic};
+
+use ckb_dao_utils::extract_dao_data;
+use ckb_types::{
+    core::{Capacity, EpochNumberWithFraction, HeaderView, TransactionView},
+    packed::{Byte32, CellOutput, OutPoint},
+    prelude::*,
+    H160, H256,
+};
+use sha3::{Digest, Keccak256};
+use thiserror::Error;
+
+#[cfg(feature = "rpc")]
+use crate::rpc::{CkbRpcClient, CkbRpcInterface, RpcError};
+use crate::traits::{
+    HeaderDepResolver, LiveCell, TransactionDependencyError, TransactionDependencyProvider,
+};
+use crate::types::{Since, SinceType};
+#[cfg(feature = "rpc")]
+use ckb_types::U256;
+
+pub mod blake2b;
+#[cfg(feature = "rpc")]
+pub mod consensus;
+pub mod dao;
+pub mod epoch;
+pub mod spore;
+#[cfg(feature = "rpc")]
+pub mod tx_history;
+pub mod types;
+pub mod validation;
+
+pub use blake2b::{blake160, calc_type_id, ckb_blake2b_256, CkbHasher};
+#[cfg(feature = "rpc")]
+pub use consensus::{to_consensus_struct, ConsensusConvertError};
+#[cfg(feature = "rpc")]
+pub use epoch::EpochCache;
+pub use epoch::{
+    cellbase_mature_epoch, epoch_add, epoch_as_rational, epoch_cmp, estimate_time_of_epoch,
+    format_duration_approx, format_epoch, parse_epoch, EpochParseError, EPOCH_DURATION_TARGET,
+};
+pub use spore::{
+    build_spore_type_script, calculate_spore_id, parse_spore_cell, spore_script_id, SporeData,
+    SporeParseError,
+};
+#[cfg(feature = "rpc")]
+pub use tx_history::{TxDirection, TxHistory, TxHistoryError, TxHistoryPage, TxRecord};
+pub use types::{outpoint_eq, outpoint_hash, outpoint_to_string, OutPointKey};
+pub use validation::{
+    validate_cellbase_maturity, validate_transaction_structure, CellbaseMaturityError,
+    ImmatureCellbaseInput, ValidationWarning,
+};
+
+/// The block count used to calculate the median time, same as ckb consensus
+pub const MEDIAN_TIME_BLOCK_COUNT: usize = 37;
+
+pub fn zeroize_privkey(key: &mut secp256k1::SecretKey) {
+    let key_ptr = key.as_mut_ptr();
+    for i in 0..key.len() as isize {
+        unsafe { ptr::write_volatile(key_ptr.offset(i), Default::default()) }
+        atomic::compiler_fence(atomic::Ordering::SeqCst);
+    }
+}
+
+pub fn zeroize_slice(data: &mut [u8]) {
+    for elem in data {
+        unsafe { ptr::write_volatile(elem, Default::default()) }
+        atomic::compiler_fence(atomic::Ordering::SeqCst);
+    }
+}
+
+#[cfg(feature = "rpc")]
+pub fn get_max_mature_number(rpc_client: &CkbRpcClient) -> Result<u64, String> {
+    get_max_mature_number_inner(rpc_client).map_err(|err| err.to_string())
+}
+
+#[cfg(feature = "rpc")]
+pub(crate) fn get_max_mature_number_inner(
+    rpc_client: &dyn CkbRpcInterface,
+) -> Result<u64, RpcError> {
+    let cellbase_maturity = EpochNumberWithFraction::from_full_value(
+        rpc_client.get_consensus()?.cellbase_maturity.value(),
+    );
+    let tip_epoch = rpc_client
+        .get_tip_header()
+        .map(|header| EpochNumberWithFraction::from_full_value(header.inner.epoch.value()))?;
+
+    let tip_epoch_rational = tip_epoch.to_rational();
+    let cellbase_maturity_rational = cellbase_maturity.to_rational();
+
+    if tip_epoch_rational < cellbase_maturity_rational {
+        // No cellbase live cell is mature
+        Ok(0)
+    } else {
+        let difference = tip_epoch_rational - cellbase_maturity_rational;
+        let rounds_down_difference = difference.clone().into_u256();
+        let difference_delta = difference - rounds_down_difference.clone();
+
+        let epoch_number = u64::from_le_bytes(
+            rounds_down_difference.to_le_bytes()[..8]
+                .try_into()
+                .expect("should be u64"),
+        );
+        let max_mature_epoch = rpc_client
+            .get_epoch_by_number(epoch_number)?
+            .ok_or_else(|| anyhow::anyhow!("Can not get epoch less than current epoch number"))?;
+
+        let max_mature_block_number = (difference_delta
+            * U256::from(max_mature_epoch.length.value())
+            + U256::from(max_mature_epoch.start_number.value()))
+        .into_u256();
+
+        Ok(u64::from_le_bytes(
+            max_mature_block_number.to_le_bytes()[..8]
+                .try_into()
+                .expect("should be u64"),
+        ))
+    }
+}
+
+pub fn is_mature(info: &LiveCell, max_mature_number: u64) -> bool {
+    // Not cellbase cell
+    info.tx_index > 0
+    // Live cells in genesis are all mature
+        || info.block_number == 0
+        || info.block_number <= max_mature_number
+}
+
+/// Same as `dao::earliest_withdraw_epoch`
+pub fn minimal_unlock_point(
+    deposit_header: &HeaderView,
+    prepare_header: &HeaderView,
+) -> EpochNumberWithFraction {
+    dao::earliest_withdraw_epoch(deposit_header, prepare_header)
+}
+
+pub fn calculate_dao_maximum_withdraw4(
+    deposit_header: &HeaderView,
+    prepare_header: &HeaderView,
+    output: &CellOutput,
+    occupied_capacity: u64,
+) -> u64 {
+    let (deposit_ar, _, _, _) = extract_dao_data(deposit_header.dao());
+    let (prepare_ar, _, _, _) = extract_dao_data(prepare_header.dao());
+    let output_capacity: Capacity = output.capacity().unpack();
+    let counted_capacity = output_capacity.as_u64() - occupied_capacity;
+    let withdraw_counted_capacity =
+        u128::from(counted_capacity) * u128::from(prepare_ar) / u128::from(deposit_ar);
+    occupied_capacity + withdraw_counted_capacity as u64
+}
+
+/// Since check errors
+#[derive(Error, Debug)]
+pub enum SinceCheckError {
+    #[error("invalid since value `{1:#x}` of input #{0}")]
+    InvalidSince(usize, u64),
+
+    #[error("the block header of transaction `{0}` not found")]
+    HeaderNotFound(Byte32),
+
+    #[error("transaction dependency provider error: `{0}`")]
+    TxDep(#[from] TransactionDependencyError),
+
+    #[error("other error: `{0}`")]
+    Other(#[from] anyhow::Error),
+}
+
+/// The result of checking an input's since against the chain tip
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SinceCheckResult {
+    /// The since is zero or already satisfied, the input can be spent now
+    Satisfied,
+    /// The input can be spent in the block with this number or later
+    EarliestBlockNumber(u64),
+    /// The input can be spent in this epoch or later
+    EarliestEpoch(EpochNumberWithFraction),
+    /// The input can be spent when the median time of the parent block reach this timestamp (in milliseconds)
+    EarliestTimestamp(u64),
+}
+
+impl SinceCheckResult {
+    pub fn is_satisfied(&self) -> bool {
+        *self == SinceCheckResult::Satisfied
+    }
+}
+
+/// Check every input's since of the transaction against the current tip, the
+/// result is in the same order as the inputs.
+///
+/// The transaction is assumed to be committed in the block next to `tip_header`,
+/// the epoch based since is checked against the tip epoch, so the result is a
+/// little conservative. The `header_dep_resolver` is used to find the block
+/// which the input cell created in, and `tx_dep_provider` is used to get the
+/// ancestor headers (by number if supported) for calculating the median time.
+pub fn check_since(
+    tx: &TransactionView,
+    header_dep_resolver: &dyn HeaderDepResolver,
+    tx_dep_provider: &dyn TransactionDependencyProvider,
+    tip_header: &HeaderView,
+) -> Result<Vec<SinceCheckResult>, SinceCheckError> {
+    tx.inputs()
+        .into_iter()
+        .enumerate()
+        .map(|(index, input)| {
+            check_input_since(
+                index,
+                &input.previous_output(),
+                input.since().unpack(),
+                header_dep_resolver,
+                tx_dep_provider,
+                tip_header,
+            )
+        })
+        .collect()
+}
+
+/// Check a single input's since against the current tip, see [`check_since`]
+/// for details, `index` is only used for error reporting.
+pub fn check_input_since(
+    index: usize,
+    out_point: &OutPoint,
+    since_value: u64,
+    header_dep_resolver: &dyn HeaderDepResolver,
+    tx_dep_provider: &dyn TransactionDependencyProvider,
+    tip_header: &HeaderView,
+) -> Result<SinceCheckResult, SinceCheckError> {
+    if since_value == 0 {
+        return Ok(SinceCheckResult::Satisfied);
+    }
+    let since = Since::from_raw_value(since_value);
+    if !since.flags_is_valid() {
+        return Err(SinceCheckError::InvalidSince(index, since_value));
+    }
+    let (ty, value) = since
+        .extract_metric()
+        .ok_or(SinceCheckError::InvalidSince(index, since_value))?;
+    let base_header = if since.is_relative() {
+        let tx_hash = out_point.tx_hash();
+        let header = header_dep_resolver
+            .resolve_by_tx(&tx_hash)?
+            .ok_or(SinceCheckError::HeaderNotFound(tx_hash))?;
+        Some(header)
+    } else {
+        None
+    };
+
+    let result = match ty {
+        SinceType::BlockNumber => {
+            let earliest = base_header
+                .map(|header| header.number().saturating_add(value))
+                .unwrap_or(value);
+            if tip_header.number() + 1 >= earliest {
+                SinceCheckResult::Satisfied
+            } else {
+                SinceCheckResult::EarliestBlockNumber(earliest)
+            }
+        }
+        SinceType::EpochNumberWithFraction => {
+            let epoch = EpochNumberWithFraction::from_full_value(value);
+            let (number, rest, denom, length) = match base_header {
+                Some(header) => {
+                    if !epoch.is_well_formed_increment() {
+                        return Err(SinceCheckError::InvalidSince(index, since_value));
+                    }
+                    add_epoch(header.epoch(), epoch)
+                }
+                None => {
+                    if !epoch.is_well_formed() {
+                        return Err(SinceCheckError::InvalidSince(index, since_value));
+                    }
+                    (
+                        epoch.number(),
+                        u128::from(epoch.index()),
+                        u128::from(epoch.length()),
+                        epoch.length(),
+                    )
+                }
+            };
+            let tip_epoch = tip_header.epoch();
+            let satisfied = tip_epoch.number() > number
+                || (tip_epoch.number() == number
+                    && u128::from(tip_epoch.index()) * denom
+                        >= rest * u128::from(tip_epoch.length()));
+            if satisfied {
+                SinceCheckResult::Satisfied
+            } else {
+                // round up the fraction to the epoch length
+                let index = (rest * u128::from(length) + denom - 1) / denom;
+                let earliest = if index >= u128::from(length) {
+                    EpochNumberWithFraction::new(number + 1, 0, length)
+                } else {
+                    EpochNumberWithFraction::new(number, index as u64, length)
+                };
+                SinceCheckResult::EarliestEpoch(earliest)
+            }
+        }
+        SinceType::Timestamp => {
+            // the since value is in seconds, the median time is in milliseconds
+            let median_time = block_median_time(tx_dep_provider, &tip_header.hash())?;
+            let (earliest, satisfied) = match base_header {
+                Some(header) => {
+                    let base = if header.number() == 0 {
+                        header.timestamp()
+                    } else {
+                        block_median_time(tx_dep_provider, &header.parent_hash())?
+                    };
+                    let earliest = base.saturating_add(value.saturating_mul(1000));
+                    (earliest, median_time >= earliest)
+                }
+                None => (
+                    value.saturating_mul(1000),
+                    since.satisfied_by_timestamp(median_time) == Some(true),
+                ),
+            };
+            if satisfied {
+                SinceCheckResult::Satisfied
+            } else {
+                SinceCheckResult::EarliestTimestamp(earliest)
+            }
+        }
+    };
+    Ok(result)
+}
+
+/// Get the median time (in milliseconds) of the tip block from the ckb node, the timestamp
+/// since of a transaction committed in the next block is checked against it.
+#[cfg(feature = "rpc")]
+pub fn current_median_time(client: &CkbRpcClient) -> Result<u64, RpcError> {
+    let tip_header = client.get_tip_header()?;
+    client
+        .get_block_median_time(tip_header.hash)?
+        .map(|timestamp| timestamp.value())
+        .ok_or_else(|| RpcError::Other(anyhow::anyhow!("tip block median time not found")))
+}
+
+/// Get the main chain header by number from the transaction dependency provider, `None`
+/// if the provider does not support it or the header is not found, the other errors
+/// (e.g. rpc errors) are returned.
+pub fn get_header_by_number(
+    tx_dep_provider: &dyn TransactionDependencyProvider,
+    number: u64,
+) -> Result<Option<HeaderView>, TransactionDependencyError> {
+    match tx_dep_provider.get_header_by_number(number) {
+        Ok(header) => Ok(Some(header)),
+        Err(TransactionDependencyError::Unsupported(_))
+        | Err(TransactionDependencyError::NotFound(_)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Calculate the median time of the block and its ancestors, the block count is [`MEDIAN_TIME_BLOCK_COUNT`].
+pub fn block_median_time(
+    tx_dep_provider: &dyn TransactionDependencyProvider,
+    block_hash: &Byte32,
+) -> Result<u64, TransactionDependencyError> {
+    let mut timestamps = Vec::with_capacity(MEDIAN_TIME_BLOCK_COUNT);
+    let mut header = tx_dep_provider.get_header(block_hash)?;
+    for _ in 0..MEDIAN_TIME_BLOCK_COUNT {
+        timestamps.push(header.timestamp());
+        if header.number() == 0 || timestamps.len() == MEDIAN_TIME_BLOCK_COUNT {
+            break;
+        }
+        // the ancestors are looked up by number, the header of a fork block by hash
+        let parent_hash = header.parent_hash();
+        header = match get_header_by_number(tx_dep_provider, header.number() - 1)? {
+            Some(parent) if parent.hash() == parent_hash => parent,
+            _ => tx_dep_provider.get_header(&parent_hash)?,
+        };
+    }
+    timestamps.sort_unstable();
+    Ok(timestamps[timestamps.len() >> 1])
+}
+
+// Add an epoch increment to base epoch, return (epoch number, fraction
+// numerator, fraction denominator, the base epoch length).
+fn add_epoch(
+    base: EpochNumberWithFraction,
+    increment: EpochNumberWithFraction,
+) -> (u64, u128, u128, u64) {
+    let (base_index, base_length) = if base.length() == 0 {
+        (0, 1)
+    } else {
+        (u128::from(base.index()), u128::from(base.length()))
+    };
+    let (inc_index, inc_length) = if increment.length() == 0 {
+        (0, 1)
+    } else {
+        (
+            u128::from(increment.index()),
+            u128::from(increment.length()),
+        )
+    };
+    let denom = base_length * inc_length;
+    let numer = base_index * inc_length + inc_index * base_length;
+    let number = base.number() + increment.number() + (numer / denom) as u64;
+    (number, numer % denom, denom, base_length as u64)
+}
+
+pub fn serialize_signature(signature: &secp256k1::ecdsa::RecoverableSignature) -> [u8; 65] {
+    let (recov_id, data) = signature.serialize_compact();
+    let mut signature_bytes = [0u8; 65];
+    signature_bytes[0..64].copy_from_slice(&data[0..64]);
+    signature_bytes[64] = recov_id.to_i32() as u8;
+    signature_bytes
+}
+
+/// Do an ethereum style public key hash.
+pub fn keccak160(message: &[u8]) -> H160 {
+    let mut hasher = Keccak256::new();
+    hasher.update(message);
+    let r = hasher.finalize();
+    H160::from_slice(&r[12..]).unwrap()
+}
+
+/// Do an ethereum style message convert before do a signature.
+pub fn convert_keccak256_hash(message: &[u8]) -> H256 {
+    let eth_prefix: &[u8; 28] = b"\x19Ethereum Signed Message:\n32";
+    let mut hasher = Keccak256::new();
+    hasher.update(eth_prefix);
+    hasher.update(message);
+    let r = hasher.finalize();
+    H256::from_slice(r.as_slice()).expect("convert_keccak256_hash")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::MockRpcResult;
+    use ckb_chain_spec::consensus::ConsensusBuilder;
+    use ckb_dao_utils::pack_dao_data;
+    use ckb_jsonrpc_types::{Consensus, EpochView, HeaderView};
+    use ckb_types::{
+        bytes::Bytes,
+        core::{capacity_bytes, EpochNumberWithFraction, HeaderBuilder},
+    };
+    use httpmock::prelude::*;
+
+    #[test]
+    fn test_current_median_time() {
+        let server = MockServer::start();
+        let tip_header: HeaderView = HeaderBuilder::default()
+            .number(100.pack())
+            .epoch(
+                EpochNumberWithFraction::new(0, 100, 1000)
+                    .full_value()
+                    .pack(),
+            )
+            .build()
+            .into();
+        let tip_hash = tip_header.hash.clone();
+        server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("get_tip_header");
+            then.status(200)
+                .body(MockRpcResult::new(tip_header).to_json());
+        });
+        let median_time_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("get_block_median_time")
+                .body_contains(format!("{:#x}", tip_hash));
+            then.status(200).body(
+                MockRpcResult::new(Some(ckb_jsonrpc_types::Timestamp::from(1_600_000_000_000)))
+                    .to_json(),
+            );
+        });
+        let client = CkbRpcClient::new(&server.base_url());
+        assert_eq!(current_median_time(&client).unwrap(), 1_600_000_000_000);
+        median_time_mock.assert_hits(1);
+    }
+
+    #[test]
+    fn test_minimal_unlock_point() {
+        let cases = vec![
+            ((5, 5, 1000), (184, 4, 1000), (5 + 180, 5, 1000)),
+            ((5, 5, 1000), (184, 5, 1000), (5 + 180, 5, 1000)),
+            ((5, 5, 1000), (184, 6, 1000), (5 + 180, 5, 1000)),
+            ((5, 5, 1000), (185, 4, 1000), (5 + 180, 5, 1000)),
+            ((5, 5, 1000), (185, 5, 1000), (5 + 180, 5, 1000)),
+            ((5, 5, 1000), (185, 6, 1000), (5 + 180 * 2, 5, 1000)), // 6/1000 > 5/1000
+            ((5, 5, 1000), (186, 4, 1000), (5 + 180 * 2, 5, 1000)),
+            ((5, 5, 1000), (186, 5, 1000), (5 + 180 * 2, 5, 1000)),
+            ((5, 5, 1000), (186, 6, 1000), (5 + 180 * 2, 5, 1000)),
+            ((5, 5, 1000), (364, 4, 1000), (5 + 180 * 2, 5, 1000)),
+            ((5, 5, 1000), (364, 5, 1000), (5 + 180 * 2, 5, 1000)),
+            ((5, 5, 1000), (364, 6, 1000), (5 + 180 * 2, 5, 1000)),
+            ((5, 5, 1000), (365, 4, 1000), (5 + 180 * 2, 5, 1000)),
+            ((5, 5, 1000), (365, 5, 1000), (5 + 180 * 2, 5, 1000)),
+            ((5, 5, 1000), (365, 6, 1000), (5 + 180 * 3, 5, 1000)),
+            ((5, 5, 1000), (366, 4, 1000), (5 + 180 * 3, 5, 1000)),
+            ((5, 5, 1000), (366, 5, 1000), (5 + 180 * 3, 5, 1000)),
+            ((5, 5, 1000), (366, 6, 1000), (5 + 180 * 3, 5, 1000)),
+        ];
+        for (deposit_point, prepare_point, expected) in cases {
+            let deposit_point =
+                EpochNumberWithFraction::new(deposit_point.0, deposit_point.1, deposit_point.2);
+            let prepare_point =
+                EpochNumberWithFraction::new(prepare_point.0, prepare_point.1, prepare_point.2);
+            let expected = EpochNumberWithFraction::new(expected.0, expected.1, expected.2);
+            let deposit_header = HeaderBuilder::default()
+                .epoch(deposit_point.full_value().pack())
+                .build();
+            let prepare_header = HeaderBuilder::default()
+                .epoch(prepare_point.full_value().pack())
+                .build();
+            let actual = minimal_unlock_point(&deposit_header, &prepare_header);
+            assert_eq!(
+                expected, actual,
+                "minimal_unlock_point deposit_point: {}, prepare_point: {}, expected: {}, actual: {}",
+                deposit_point, prepare_point, expected, actual,
+            );
+        }
+    }
+
+    #[test]
+    fn check_withdraw_calculation() {
+        let data = Bytes::from(vec![1; 10]);
+        let output = CellOutput::new_builder()
+            .capacity(capacity_bytes!(1000000).pack())
+            .build();
+
+        let (deposit_point, prepare_point) = ((5, 5, 1000), (184, 4, 1000));
+        let deposit_number = deposit_point.0 * deposit_point.2 + deposit_point.1;
+        let prepare_number = prepare_point.0 * prepare_point.2 + prepare_point.1;
+        let deposit_point =
+            EpochNumberWithFraction::new(deposit_point.0, deposit_point.1, deposit_point.2);
+        let prepare_point =
+            EpochNumberWithFraction::new(prepare_point.0, prepare_point.1, prepare_point.2);
+        let deposit_header = HeaderBuilder::default()
+            .epoch(deposit_point.full_value().pack())
+            .number(deposit_number.pack())
+            .dao(pack_dao_data(
+                10_000_000_000_123_456,
+                Default::default(),
+                Default::default(),
+                Default::default(),
+            ))
+            .build();
+        let prepare_header = HeaderBuilder::default()
+            .epoch(prepare_point.full_value().pack())
+            .number(prepare_number.pack())
+            .dao(pack_dao_data(
+                10_000_000_001_123_456,
+                Default::default(),
+                Default::default(),
+                Default::default(),
+            ))
+            .build();
+
+        let result = calculate_dao_maximum_withdraw4(
+            &deposit_header,
+            &prepare_header,
+            &output,
+            Capacity::bytes(data.len()).unwrap().as_u64(),
+        );
+        assert_eq!(result, 100_000_000_009_999);
+    }
+
+    #[test]
+    fn test_get_max_mature_number() {
+        {
+            // cellbase maturity is 4, tip epoch is 3(200/400), so the max mature block number is 0
+            let server = MockServer::start();
+            let consensus: Consensus = ConsensusBuilder::default()
+                .cellbase_maturity(EpochNumberWithFraction::new(4, 0, 1))
+                .build()
+                .into();
+            server.mock(|when, then| {
+                when.method(POST).path("/").body_contains("get_consensus");
+                then.status(200)
+                    .body(MockRpcResult::new(consensus).to_json());
+            });
+
+            let tip_header: HeaderView = HeaderBuilder::default()
+                .epoch(
+                    EpochNumberWithFraction::new(3, 200, 400)
+                        .full_value()
+                        .pack(),
+                )
+                .build()
+                .into();
+            server.mock(|when, then| {
+                when.method(POST).path("/").body_contains("get_tip_header");
+                then.status(200)
+                    .body(MockRpcResult::new(tip_header).to_json());
+            });
+
+            let rpc_client = CkbRpcClient::new(server.base_url().as_str());
+            assert_eq!(0, get_max_mature_number(&rpc_client).unwrap());
+        }
+
+        {
+            // cellbase maturity is 3(1/3), tip epoch is 3(300/600), epoch 3 starts at block 1800
+            // so the max mature block number is 1800 + (600 * 1 / 6) = 1900
+            let server = MockServer::start();
+            let consensus: Consensus = ConsensusBuilder::default()
+                .cellbase_maturity(EpochNumberWithFraction::new(3, 1, 3))
+                .build()
+                .into();
+            server.mock(|when, then| {
+                when.method(POST).path("/").body_contains("get_consensus");
+                then.status(200)
+                    .body(MockRpcResult::new(consensus).to_json());
+            });
+
+            let tip_header: HeaderView = HeaderBuilder::default()
+                .epoch(
+                    EpochNumberWithFraction::new(3, 300, 600)
+                        .full_value()
+                        .pack(),
+                )
+                .build()
+                .into();
+            server.mock(|when, then| {
+                when.method(POST).path("/").body_contains("get_tip_header");
+                then.status(200)
+                    .body(MockRpcResult::new(tip_header).to_json());
+            });
+
+            let epoch3: EpochView = EpochView {
+                number: 3.into(),
+                start_number: 1800.into(),
+                length: 600.into(),
+                compact_target: 0.into(),
+            };
+
+            server.mock(|when, then| {
+                when.method(POST)
+                    .path("/")
+                    .body_contains("get_epoch_by_number");
+                then.status(200).body(MockRpcResult::new(epoch3).to_json());
+            });
+
+            let rpc_client = CkbRpcClient::new(server.base_url().as_str());
+            assert_eq!(1900, get_max_mature_number(&rpc_client).unwrap());
+        }
+
+        {
+            // cellbase maturity is 3(2/3), tip epoch is 105(300/600), epoch 101 starts at block 150000 and length is 1800
+            // so the max mature block number is 150000 + (1800 * 5 / 6) = 151500
+            let server = MockServer::start();
+            let consensus: Consensus = ConsensusBuilder::default()
+                .cellbase_maturity(EpochNumberWithFraction::new(3, 2, 3))
+                .build()
+                .into();
+            server.mock(|when, then| {
+                when.method(POST).path("/").body_contains("get_consensus");
+                then.status(200)
+                    .body(MockRpcResult::new(consensus).to_json());
+            });
+
+            let tip_header: HeaderView = HeaderBuilder::default()
+                .epoch(
+                    EpochNumberWithFraction::new(105, 300, 600)
+                        .full_value()
+                        .pack(),
+                )
+                .build()
+                .into();
+            server.mock(|when, then| {
+                when.method(POST).path("/").body_contains("get_tip_header");
+                then.status(200)
+                    .body(MockRpcResult::new(tip_header).to_json());
+            });
+
+            let epoch3: EpochView = EpochView {
+                number: 101.into(),
+                start_number: 150000.into(),
+                length: 1800.into(),
+                compact_target: 0.into(),
+            };
+
+            server.mock(|when, then| {
+                when.method(POST)
+                    .path("/")
+                    .body_contains("get_epoch_by_number");
+                then.status(200).body(MockRpcResult::new(epoch3).to_json());
+            });
+
+            let rpc_client = CkbRpcClient::new(server.base_url().as_str());
+            assert_eq!(151500, get_max_mature_number(&rpc_client).unwrap());
+        }
+    }
+
+    struct MockChain {
+        headers: Vec<ckb_types::core::HeaderView>,
+        // tx hash => block number
+        txs: std::collections::HashMap<Byte32, u64>,
+        // get_header_by_number fails with a rpc error
+        rpc_down: bool,
+    }
+
+    impl MockChain {
+        fn new(length: u64) -> MockChain {
+            let mut headers: Vec<ckb_types::core::HeaderView> = Vec::new();
+            for number in 0..length {
+                let parent_hash = headers
+                    .last()
+                    .map(|header| header.hash())
+                    .unwrap_or_default();
+                let header = HeaderBuilder::default()
+                    .number(number.pack())
+                    .parent_hash(parent_hash)
+                    .timestamp((number * 1000).pack())
+                    .epoch(
+                        EpochNumberWithFraction::new(number / 10, number % 10, 10)
+                            .full_value()
+                            .pack(),
+                    )
+                    .build();
+                headers.push(header);
+            }
+            MockChain {
+                headers,
+                txs: Default::default(),
+                rpc_down: false,
+            }
+        }
+
+        fn add_cell(&mut self, block_number: u64) -> OutPoint {
+            let out_point = crate::test_util::random_out_point();
+            self.txs.insert(out_point.tx_hash(), block_number);
+            out_point
+        }
+    }
+
+    impl HeaderDepResolver for MockChain {
+        fn resolve_by_tx(
+            &self,
+            tx_hash: &Byte32,
+        ) -> Result<Option<ckb_types::core::HeaderView>, anyhow::Error> {
+            Ok(self
+                .txs
+                .get(tx_hash)
+                .map(|number| self.headers[*number as usize].clone()))
+        }
+        fn resolve_by_number(
+            &self,
+            number: u64,
+        ) -> Result<Option<ckb_types::core::HeaderView>, anyhow::Error> {
+            Ok(self.headers.get(number as usize).cloned())
+        }
+    }
+
+    impl TransactionDependencyProvider for MockChain {
+        fn get_transaction(
+            &self,
+            _tx_hash: &Byte32,
+        ) -> Result<TransactionView, TransactionDependencyError> {
+            Err(TransactionDependencyError::NotFound(
+                "transaction".to_string(),
+            ))
+        }
+        fn get_cell(
+            &self,
+            _out_point: &OutPoint,
+        ) -> Result<CellOutput, TransactionDependencyError> {
+            Err(TransactionDependencyError::NotFound("cell".to_string()))
+        }
+        fn get_cell_data(
+            &self,
+            _out_point: &OutPoint,
+        ) -> Result<Bytes, TransactionDependencyError> {
+            Err(TransactionDependencyError::NotFound(
+                "cell data".to_string(),
+            ))
+        }
+        fn get_header(
+            &self,
+            block_hash: &Byte32,
+        ) -> Result<ckb_types::core::HeaderView, TransactionDependencyError> {
+            self.headers
+                .iter()
+                .find(|header| header.hash() == *block_hash)
+                .cloned()
+                .ok_or_else(|| TransactionDependencyError::NotFound("header".to_string()))
+        }
+        fn get_header_by_number(
+            &self,
+            number: u64,
+        ) -> Result<ckb_types::core::HeaderView, TransactionDependencyError> {
+            if self.rpc_down {
+                return Err(RpcError::Other(anyhow::anyhow!("connection refused")).into());
+            }
+            self.headers
+                .get(number as usize)
+                .cloned()
+                .ok_or_else(|| TransactionDependencyError::NotFound("header".to_string()))
+        }
+        fn get_block_extension(
+            &self,
+            _block_hash: &Byte32,
+        ) -> Result<Option<ckb_types::packed::Bytes>, TransactionDependencyError> {
+            Ok(None)
+        }
+    }
+
+    #[test]
+    fn test_get_header_by_number() {
+        let mut chain = MockChain::new(50);
+        assert_eq!(
+            get_header_by_number(&chain, 12).unwrap(),
+            Some(chain.headers[12].clone())
+        );
+        assert_eq!(get_header_by_number(&chain, 50).unwrap(), None);
+        assert_eq!(
+            block_median_time(&chain, &chain.headers[40].hash()).unwrap(),
+            22_000
+        );
+
+        // only the missing header falls back, the rpc errors are returned
+        chain.rpc_down = true;
+        assert!(matches!(
+            get_header_by_number(&chain, 12),
+            Err(TransactionDependencyError::Rpc(_))
+        ));
+        assert!(matches!(
+            block_median_time(&chain, &chain.headers[40].hash()),
+            Err(TransactionDependencyError::Rpc(_))
+        ));
+    }
+
+    #[test]
+    fn test_check_input_since() {
+        let mut chain = MockChain::new(50);
+        let out_point = chain.add_cell(12);
+        let check = |since: u64, tip: u64| {
+            check_input_since(
+                0,
+                &out_point,
+                since,
+                &chain,
+                &chain,
+                &chain.headers[tip as usize],
+            )
+        };
+
+        assert_eq!(check(0, 0).unwrap(), SinceCheckResult::Satisfied);
+
+        // block number
+        let since = Since::new(SinceType::BlockNumber, 20, false).value();
+        assert_eq!(
+            check(since, 18).unwrap(),
+            SinceCheckResult::EarliestBlockNumber(20)
+        );
+        assert!(check(since, 19).unwrap().is_satisfied());
+        let since = Since::new(SinceType::BlockNumber, 5, true).value();
+        assert_eq!(
+            check(since, 15).unwrap(),
+            SinceCheckResult::EarliestBlockNumber(17)
+        );
+        assert!(check(since, 16).unwrap().is_satisfied());
+
+        // epoch, the cell is created in epoch (1, 2, 10)
+        let since = Since::new(
+            SinceType::EpochNumberWithFraction,
+            EpochNumberWithFraction::new(1, 5, 10).full_value(),
+            true,
+        )
+        .value();
+        assert_eq!(
+            check(since, 26).unwrap(),
+            SinceCheckResult::EarliestEpoch(EpochNumberWithFraction::new(2, 7, 10))
+        );
+        assert!(check(since, 27).unwrap().is_satisfied());
+        let since = Since::new(
+            SinceType::EpochNumberWithFraction,
+            EpochNumberWithFraction::new(1, 1, 2).full_value(),
+            true,
+        )
+        .value();
+        assert_eq!(
+            check(since, 26).unwrap(),
+            SinceCheckResult::EarliestEpoch(EpochNumberWithFraction::new(2, 7, 10))
+        );
+        let since = Since::new_absolute_epoch(4).value();
+        assert_eq!(
+            check(since, 39).unwrap(),
+            SinceCheckResult::EarliestEpoch(EpochNumberWithFraction::new(4, 0, 1))
+        );
+        assert!(check(since, 40).unwrap().is_satisfied());
+
+        // timestamp, median time of block 40 is the timestamp of block 22
+        let since = Since::new(SinceType::Timestamp, 25, false).value();
+        assert_eq!(
+            check(since, 40).unwrap(),
+            SinceCheckResult::EarliestTimestamp(25_000)
+        );
+        assert!(check(since, 43).unwrap().is_satisfied());
+        // median time of block 11 is the timestamp of block 6
+        let since = Since::new(SinceType::Timestamp, 10, true).value();
+        assert_eq!(
+            check(since, 30).unwrap(),
+            SinceCheckResult::EarliestTimestamp(16_000)
+        );
+        assert!(check(since, 31).unwrap().is_satisfied());
+
+        // invalid flags
+        assert!(matches!(
+            check(0x0100_0000_0000_0001, 40),
+            Err(SinceCheckError::InvalidSince(0, _))
+        ));
+        // header not found
+        let since = Since::new(SinceType::BlockNumber, 5, true).value();
+        assert!(matches!(
+            check_input_since(
+                0,
+                &crate::test_util::random_out_point(),
+                since,
+                &chain,
+                &chain,
+                &chain.headers[40]
+            ),
+            Err(SinceCheckError::HeaderNotFound(_))
+        ));
+    }
+}
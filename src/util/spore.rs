@@ -0,0 +1,252 @@
+use std::convert::TryInto;
+
+use ckb_types::{
+    bytes::{BufMut, Bytes, BytesMut},
+    core::ScriptHashType,
+    packed::{CellInput, Script},
+    prelude::*,
+    H256,
+};
+use thiserror::Error;
+
+use crate::{
+    constants::{SPORE_DATA_HASH_AGGRON, SPORE_DATA_HASH_LINA},
+    NetworkType, ScriptId,
+};
+
+use super::calc_type_id;
+
+// The number of the fields in `SporeData` table
+const SPORE_DATA_FIELDS: usize = 3;
+
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum SporeParseError {
+    #[error("invalid spore data: {0}")]
+    InvalidData(String),
+
+    #[error("invalid cluster id length: {0}, expected 32")]
+    InvalidClusterId(usize),
+
+    #[error("content type is not valid utf-8")]
+    InvalidContentType,
+}
+
+/// The data of a Spore NFT cell, see:
+/// <https://github.com/sporeprotocol/spore-contract/blob/master/docs/RFC.md>
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SporeData {
+    /// The MIME type of the content, e.g. `image/png`
+    pub content_type: String,
+    pub content: Bytes,
+    /// The type script args (the cluster id) of the cluster cell this spore belongs to
+    pub cluster_id: Option<[u8; 32]>,
+}
+
+impl SporeData {
+    /// Encode the data as the molecule `SporeData` table:
+    ///
+    /// ```text
+    /// table SporeData {
+    ///     content_type: Bytes,
+    ///     content: Bytes,
+    ///     cluster_id: BytesOpt,
+    /// }
+    /// ```
+    pub fn to_bytes(&self) -> Bytes {
+        let fields = [
+            encode_bytes(self.content_type.as_bytes()),
+            encode_bytes(&self.content),
+            self.cluster_id
+                .map(|cluster_id| encode_bytes(&cluster_id))
+                .unwrap_or_default(),
+        ];
+        let header_size = 4 * (SPORE_DATA_FIELDS + 1);
+        let total_size = header_size + fields.iter().map(Vec::len).sum::<usize>();
+        let mut data = BytesMut::with_capacity(total_size);
+        data.put_u32_le(total_size as u32);
+        let mut offset = header_size;
+        for field in &fields {
+            data.put_u32_le(offset as u32);
+            offset += field.len();
+        }
+        for field in &fields {
+            data.put_slice(field);
+        }
+        data.freeze()
+    }
+}
+
+/// The Spore type script of the network, only mainnet and testnet are supported.
+pub fn spore_script_id(network: NetworkType) -> Option<ScriptId> {
+    match network {
+        NetworkType::Mainnet => Some(ScriptId::new(SPORE_DATA_HASH_LINA, ScriptHashType::Data1)),
+        NetworkType::Testnet => Some(ScriptId::new(SPORE_DATA_HASH_AGGRON, ScriptHashType::Data1)),
+        _ => None,
+    }
+}
+
+/// Calculate the spore id (the type script args) of the new spore cell, it is the type
+/// id of the output: blake2b(first input of the transaction | output index).
+pub fn calculate_spore_id(first_input: &CellInput, output_index: u64) -> H256 {
+    calc_type_id(first_input, output_index)
+}
+
+/// Build the type script and the output data of a new Spore NFT cell.
+///
+/// Arguments:
+///   * `script_id` is the Spore type script of the network, see [`spore_script_id`]
+///   * `spore_id` is the type script args, see [`calculate_spore_id`]
+pub fn build_spore_type_script(
+    script_id: &ScriptId,
+    spore_id: &H256,
+    cluster_id: Option<[u8; 32]>,
+    content_type: &str,
+    content: &Bytes,
+) -> (Script, Bytes) {
+    let type_script = Script::new_builder()
+        .code_hash(script_id.code_hash.pack())
+        .hash_type(script_id.hash_type.into())
+        .args(spore_id.as_bytes().pack())
+        .build();
+    let data = SporeData {
+        content_type: content_type.to_string(),
+        content: content.clone(),
+        cluster_id,
+    };
+    (type_script, data.to_bytes())
+}
+
+/// Parse the output data of a Spore NFT cell.
+pub fn parse_spore_cell(data: &Bytes) -> Result<SporeData, SporeParseError> {
+    let read_u32 = |pos: usize| -> Result<usize, SporeParseError> {
+        data.get(pos..pos + 4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+            .ok_or_else(|| SporeParseError::InvalidData("header out of bound".to_string()))
+    };
+    let total_size = read_u32(0)?;
+    if total_size != data.len() {
+        return Err(SporeParseError::InvalidData(format!(
+            "total size {} mismatches the data length {}",
+            total_size,
+            data.len()
+        )));
+    }
+    // the table may have more fields appended by later versions
+    let header_size = read_u32(4)?;
+    if header_size % 4 != 0 || header_size < 4 * (SPORE_DATA_FIELDS + 1) {
+        return Err(SporeParseError::InvalidData(format!(
+            "invalid header size {}",
+            header_size
+        )));
+    }
+    let mut offsets = (1..header_size / 4)
+        .map(|index| read_u32(index * 4))
+        .collect::<Result<Vec<_>, _>>()?;
+    offsets.push(total_size);
+    if offsets[0] != header_size || offsets.windows(2).any(|pair| pair[0] > pair[1]) {
+        return Err(SporeParseError::InvalidData(
+            "invalid field offsets".to_string(),
+        ));
+    }
+    let field = |index: usize| data.slice(offsets[index]..offsets[index + 1]);
+
+    let content_type = decode_bytes(&field(0))?;
+    let content_type = String::from_utf8(content_type.to_vec())
+        .map_err(|_| SporeParseError::InvalidContentType)?;
+    let content = decode_bytes(&field(1))?;
+    let cluster_id = field(2);
+    let cluster_id = if cluster_id.is_empty() {
+        None
+    } else {
+        let cluster_id = decode_bytes(&cluster_id)?;
+        let cluster_id: [u8; 32] = cluster_id[..]
+            .try_into()
+            .map_err(|_| SporeParseError::InvalidClusterId(cluster_id.len()))?;
+        Some(cluster_id)
+    };
+    Ok(SporeData {
+        content_type,
+        content,
+        cluster_id,
+    })
+}
+
+// molecule `Bytes`: the length in u32 little endian followed by the bytes
+fn encode_bytes(bytes: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(4 + bytes.len());
+    data.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    data.extend_from_slice(bytes);
+    data
+}
+
+fn decode_bytes(data: &Bytes) -> Result<Bytes, SporeParseError> {
+    let len = data
+        .get(0..4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+        .ok_or_else(|| SporeParseError::InvalidData("bytes header out of bound".to_string()))?;
+    if data.len() != 4 + len {
+        return Err(SporeParseError::InvalidData(format!(
+            "bytes length {} mismatches the field size {}",
+            len,
+            data.len()
+        )));
+    }
+    Ok(data.slice(4..))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::random_out_point;
+
+    #[test]
+    fn test_spore_data_round_trip() {
+        let script_id = spore_script_id(NetworkType::Testnet).unwrap();
+        let input = CellInput::new(random_out_point(), 0);
+        let spore_id = calculate_spore_id(&input, 0);
+        assert_ne!(spore_id, calculate_spore_id(&input, 1));
+        let content = Bytes::from("hello spore");
+        for cluster_id in [None, Some([3u8; 32])] {
+            let (type_script, data) =
+                build_spore_type_script(&script_id, &spore_id, cluster_id, "text/plain", &content);
+            assert_eq!(ScriptId::from(&type_script), script_id);
+            assert_eq!(type_script.args().raw_data(), spore_id.as_bytes());
+            let spore = parse_spore_cell(&data).unwrap();
+            assert_eq!(spore.content_type, "text/plain");
+            assert_eq!(spore.content, content);
+            assert_eq!(spore.cluster_id, cluster_id);
+        }
+        assert!(spore_script_id(NetworkType::Dev).is_none());
+    }
+
+    #[test]
+    fn test_parse_spore_cell_encoding() {
+        // SporeData { content_type: "a", content: 0x01, cluster_id: None }
+        let data = Bytes::from(
+            hex::decode("1a00000010000000150000001a00000001000000610100000001").unwrap(),
+        );
+        let spore = SporeData {
+            content_type: "a".to_string(),
+            content: Bytes::from(vec![1u8]),
+            cluster_id: None,
+        };
+        assert_eq!(spore.to_bytes(), data);
+        assert_eq!(parse_spore_cell(&data).unwrap(), spore);
+
+        assert!(matches!(
+            parse_spore_cell(&data.slice(..20)),
+            Err(SporeParseError::InvalidData(_))
+        ));
+        let mut spore = spore;
+        spore.cluster_id = Some([0u8; 32]);
+        let mut data = spore.to_bytes().to_vec();
+        // shrink the cluster id to 31 bytes
+        data.pop();
+        data[0] -= 1;
+        data[26] -= 1;
+        assert_eq!(
+            parse_spore_cell(&Bytes::from(data)),
+            Err(SporeParseError::InvalidClusterId(31))
+        );
+    }
+}
@@ -0,0 +1,359 @@
+use std::{collections::HashMap, convert::TryFrom};
+
+use ckb_jsonrpc_types::JsonBytes;
+use ckb_types::{
+    bytes::Bytes,
+    core::TransactionView,
+    packed::{CellOutput, Script},
+    prelude::*,
+    H256,
+};
+use thiserror::Error;
+
+use crate::{
+    rpc::{
+        ckb_indexer::{Order, SearchKey, SearchMode},
+        IndexerRpcClient,
+    },
+    traits::{TransactionDependencyError, TransactionDependencyProvider},
+    tx_builder::acp::known_udt_script_ids,
+    NetworkType, RpcError, ScriptId,
+};
+
+#[derive(Error, Debug)]
+pub enum TxHistoryError {
+    #[error("rpc error: `{0}`")]
+    Rpc(#[from] RpcError),
+
+    #[error("transaction dependency error: `{0}`")]
+    TxDep(#[from] TransactionDependencyError),
+}
+
+/// The direction of a transaction from the view of the lock script
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum TxDirection {
+    /// No input is locked by the lock script
+    Incoming,
+    /// Some inputs are locked by the lock script and some outputs are sent to others
+    Outgoing,
+    /// All outputs are locked by the lock script
+    SelfTransfer,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TxRecord {
+    pub tx_hash: H256,
+    pub block_number: u64,
+    pub tx_index: u32,
+    pub direction: TxDirection,
+    /// The received capacity minus the spent capacity of the lock script (in shannons)
+    pub capacity_delta: i128,
+    /// The received amount minus the spent amount of the lock script by UDT type script,
+    /// only the cells of the known sUDT and xUDT scripts are counted.
+    pub udt_deltas: HashMap<Script, i128>,
+}
+
+pub struct TxHistoryPage {
+    pub records: Vec<TxRecord>,
+    /// Pass it to `TxHistory::fetch` to get the next page, `None` if this is the last page.
+    pub next_cursor: Option<JsonBytes>,
+}
+
+/// Page through the transactions of a lock script by the ckb-indexer, the transactions
+/// and the previous transactions of the inputs are resolved by the
+/// `TransactionDependencyProvider`.
+pub struct TxHistory<'a> {
+    indexer_client: &'a IndexerRpcClient,
+    tx_dep_provider: &'a dyn TransactionDependencyProvider,
+    lock_script: Script,
+    order: Order,
+    page_size: u32,
+    udt_script_ids: Vec<ScriptId>,
+}
+
+impl<'a> TxHistory<'a> {
+    /// Create the helper with the latest transactions first and 20 transactions per page,
+    /// the UDT deltas are counted for the sUDT and xUDT scripts of mainnet and testnet.
+    pub fn new(
+        indexer_client: &'a IndexerRpcClient,
+        tx_dep_provider: &'a dyn TransactionDependencyProvider,
+        lock_script: Script,
+    ) -> TxHistory<'a> {
+        TxHistory {
+            indexer_client,
+            tx_dep_provider,
+            lock_script,
+            order: Order::Desc,
+            page_size: 20,
+            udt_script_ids: [NetworkType::Mainnet, NetworkType::Testnet]
+                .iter()
+                .flat_map(|network| known_udt_script_ids(*network))
+                .collect(),
+        }
+    }
+
+    pub fn with_order(mut self, order: Order) -> Self {
+        self.order = order;
+        self
+    }
+
+    pub fn with_page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    /// Set the UDT script ids (e.g. the ones deployed on a dev chain), the cells of other
+    /// type scripts (e.g. a Spore NFT) are not counted in the UDT deltas.
+    pub fn with_udt_script_ids(mut self, udt_script_ids: Vec<ScriptId>) -> Self {
+        self.udt_script_ids = udt_script_ids;
+        self
+    }
+
+    /// Fetch a page of transactions after the cursor, `None` for the first page.
+    pub fn fetch(&self, after: Option<JsonBytes>) -> Result<TxHistoryPage, TxHistoryError> {
+        let mut search_key = SearchKey::by_lock_script(self.lock_script.clone());
+        search_key.script_search_mode = Some(SearchMode::Exact);
+        let page = self.indexer_client.get_transactions_grouped(
+            search_key,
+            self.order.clone(),
+            self.page_size.into(),
+            after,
+        )?;
+        let next_cursor = if page.objects.len() < self.page_size as usize {
+            None
+        } else {
+            Some(page.last_cursor)
+        };
+        let records = page
+            .objects
+            .into_iter()
+            .map(|tx| {
+                let tx_hash = tx.tx_hash.pack();
+                let tx_view = self.tx_dep_provider.get_transaction(&tx_hash)?;
+                let mut record = self.classify(&tx_view)?;
+                record.block_number = tx.block_number.value();
+                record.tx_index = tx.tx_index.value();
+                Ok(record)
+            })
+            .collect::<Result<Vec<_>, TxHistoryError>>()?;
+        Ok(TxHistoryPage {
+            records,
+            next_cursor,
+        })
+    }
+
+    /// Classify the transaction and calculate the deltas of the lock script, the block
+    /// number and the transaction index are left zero.
+    pub fn classify(&self, tx: &TransactionView) -> Result<TxRecord, TxHistoryError> {
+        let mut capacity_delta = 0i128;
+        let mut udt_deltas: HashMap<Script, i128> = HashMap::new();
+        let mut apply = |output: &CellOutput, data: &Bytes, sign: i128| {
+            let capacity: u64 = output.capacity().unpack();
+            capacity_delta += sign * i128::from(capacity);
+            let udt_script = output
+                .type_()
+                .to_opt()
+                .filter(|script| self.udt_script_ids.contains(&ScriptId::from(script)));
+            if let (Some(type_script), Some(amount)) = (udt_script, udt_amount(data)) {
+                *udt_deltas.entry(type_script).or_default() += sign * amount;
+            }
+        };
+
+        let mut has_input = false;
+        if !tx.is_cellbase() {
+            // the input cells are already spent, resolve them by the previous transactions
+            for out_point in tx.input_pts_iter() {
+                let prev_tx = self.tx_dep_provider.get_transaction(&out_point.tx_hash())?;
+                let index: u32 = out_point.index().unpack();
+                let (output, data) = prev_tx.output_with_data(index as usize).ok_or_else(|| {
+                    TransactionDependencyError::NotFound(format!(
+                        "output of input cell: {}",
+                        out_point
+                    ))
+                })?;
+                if output.lock() != self.lock_script {
+                    continue;
+                }
+                has_input = true;
+                apply(&output, &data, -1);
+            }
+        }
+        let mut has_other_output = false;
+        for (output, data) in tx.outputs_with_data_iter() {
+            if output.lock() != self.lock_script {
+                has_other_output = true;
+                continue;
+            }
+            apply(&output, &data, 1);
+        }
+        udt_deltas.retain(|_, delta| *delta != 0);
+
+        let direction = if !has_input {
+            TxDirection::Incoming
+        } else if has_other_output {
+            TxDirection::Outgoing
+        } else {
+            TxDirection::SelfTransfer
+        };
+        Ok(TxRecord {
+            tx_hash: tx.hash().unpack(),
+            block_number: 0,
+            tx_index: 0,
+            direction,
+            capacity_delta,
+            udt_deltas,
+        })
+    }
+}
+
+// The UDT amount is the first 16 bytes of the cell data in little endian
+fn udt_amount(data: &Bytes) -> Option<i128> {
+    let mut amount_bytes = [0u8; 16];
+    amount_bytes.copy_from_slice(data.get(0..16)?);
+    i128::try_from(u128::from_le_bytes(amount_bytes)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use ckb_types::{
+        core::{ScriptHashType, TransactionBuilder},
+        packed::{CellInput, OutPoint},
+    };
+    use httpmock::prelude::*;
+
+    use super::*;
+    use crate::{
+        constants::{SPORE_DATA_HASH_LINA, SUDT_TYPE_HASH_LINA},
+        rpc::ckb_indexer::{CellType, Pagination, TxWithCells},
+        test_util::{random_out_point, MockRpcResult},
+        traits::OffchainTransactionDependencyProvider,
+    };
+
+    fn build_lock(arg: u8) -> Script {
+        Script::new_builder().args(vec![arg; 20].pack()).build()
+    }
+
+    fn build_output(lock: &Script, capacity: u64, type_script: Option<&Script>) -> CellOutput {
+        CellOutput::new_builder()
+            .lock(lock.clone())
+            .type_(type_script.cloned().pack())
+            .capacity(capacity.pack())
+            .build()
+    }
+
+    fn grouped_tx(tx: &TransactionView, block_number: u64) -> TxWithCells {
+        TxWithCells {
+            tx_hash: tx.hash().unpack(),
+            block_number: block_number.into(),
+            tx_index: 1.into(),
+            cells: vec![(CellType::Output, 0.into())],
+        }
+    }
+
+    #[test]
+    fn test_tx_history() {
+        let owner = build_lock(1);
+        let other = build_lock(2);
+        let udt = Script::new_builder()
+            .code_hash(SUDT_TYPE_HASH_LINA.pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(vec![3u8; 32].pack())
+            .build();
+        // the data of the spore cell is not an UDT amount
+        let spore = Script::new_builder()
+            .code_hash(SPORE_DATA_HASH_LINA.pack())
+            .hash_type(ScriptHashType::Data1.into())
+            .args(vec![4u8; 32].pack())
+            .build();
+        let amount = |value: u128| Bytes::from(value.to_le_bytes().to_vec()).pack();
+
+        let funding = TransactionBuilder::default()
+            .input(CellInput::new(random_out_point(), 0))
+            .output(build_output(&other, 2000, None))
+            .output_data(Bytes::new().pack())
+            .build();
+        // other sends 1000 shannons and 100 UDT to owner
+        let incoming = TransactionBuilder::default()
+            .input(CellInput::new(OutPoint::new(funding.hash(), 0), 0))
+            .output(build_output(&owner, 1000, None))
+            .output_data(Bytes::new().pack())
+            .output(build_output(&owner, 500, Some(&udt)))
+            .output_data(amount(100))
+            .output(build_output(&owner, 500, Some(&spore)))
+            .output_data(Bytes::from(vec![7u8; 64]).pack())
+            .build();
+        // owner sends 600 shannons and 40 UDT back to other
+        let outgoing = TransactionBuilder::default()
+            .input(CellInput::new(OutPoint::new(incoming.hash(), 0), 0))
+            .input(CellInput::new(OutPoint::new(incoming.hash(), 1), 0))
+            .output(build_output(&other, 600, None))
+            .output_data(Bytes::new().pack())
+            .output(build_output(&other, 200, Some(&udt)))
+            .output_data(amount(40))
+            .output(build_output(&owner, 300, Some(&udt)))
+            .output_data(amount(60))
+            .output(build_output(&owner, 300, None))
+            .output_data(Bytes::new().pack())
+            .build();
+        // owner merges the cells
+        let self_transfer = TransactionBuilder::default()
+            .input(CellInput::new(OutPoint::new(outgoing.hash(), 3), 0))
+            .output(build_output(&owner, 299, None))
+            .output_data(Bytes::new().pack())
+            .build();
+        let mut provider = OffchainTransactionDependencyProvider::new();
+        for tx in [&funding, &incoming, &outgoing, &self_transfer] {
+            provider.apply_tx(tx.data(), 0).unwrap();
+        }
+
+        let server = MockServer::start();
+        let first_page = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("get_transactions")
+                .body_contains("\"group_by_transaction\":true")
+                .body_contains("null]");
+            then.status(200).body(
+                MockRpcResult::new(Pagination {
+                    objects: vec![grouped_tx(&self_transfer, 3), grouped_tx(&outgoing, 2)],
+                    last_cursor: JsonBytes::from_vec(vec![1]),
+                })
+                .to_json(),
+            );
+        });
+        let second_page = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("get_transactions")
+                .body_contains("\"0x01\"]");
+            then.status(200).body(
+                MockRpcResult::new(Pagination {
+                    objects: vec![grouped_tx(&incoming, 1)],
+                    last_cursor: JsonBytes::from_vec(vec![2]),
+                })
+                .to_json(),
+            );
+        });
+
+        let client = IndexerRpcClient::new(&server.base_url());
+        let history = TxHistory::new(&client, &provider, owner).with_page_size(2);
+        let page = history.fetch(None).unwrap();
+        first_page.assert_hits(1);
+        assert_eq!(page.records.len(), 2);
+        assert_eq!(page.records[0].direction, TxDirection::SelfTransfer);
+        assert_eq!(page.records[0].capacity_delta, -1);
+        assert_eq!(page.records[0].block_number, 3);
+        assert_eq!(page.records[1].direction, TxDirection::Outgoing);
+        assert_eq!(page.records[1].capacity_delta, -900);
+        assert_eq!(page.records[1].udt_deltas.get(&udt), Some(&-40));
+
+        // resume from the cursor
+        let page = history.fetch(page.next_cursor).unwrap();
+        second_page.assert_hits(1);
+        assert!(page.next_cursor.is_none());
+        assert_eq!(page.records[0].direction, TxDirection::Incoming);
+        assert_eq!(page.records[0].capacity_delta, 2000);
+        assert_eq!(page.records[0].udt_deltas.get(&udt), Some(&100));
+        assert_eq!(page.records[0].udt_deltas.len(), 1);
+    }
+}
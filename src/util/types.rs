@@ -0,0 +1,103 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use ckb_types::{packed::OutPoint, prelude::*, H256};
+
+/// Hash an out point, for using it with a custom hasher in `HashMap`.
+pub fn outpoint_hash(op: &OutPoint) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    OutPointKey::from(op).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Check if two out points are the same.
+pub fn outpoint_eq(a: &OutPoint, b: &OutPoint) -> bool {
+    a.as_slice() == b.as_slice()
+}
+
+/// Format an out point as `<tx_hash>:<index>`.
+pub fn outpoint_to_string(op: &OutPoint) -> String {
+    OutPointKey::from(op).to_string()
+}
+
+/// An unpacked out point (transaction hash and output index), can be used as the
+/// key of `HashMap` or `BTreeMap`.
+#[derive(Clone, Debug, Default, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct OutPointKey(pub H256, pub u32);
+
+impl OutPointKey {
+    pub fn new(tx_hash: H256, index: u32) -> OutPointKey {
+        OutPointKey(tx_hash, index)
+    }
+    pub fn tx_hash(&self) -> &H256 {
+        &self.0
+    }
+    pub fn index(&self) -> u32 {
+        self.1
+    }
+}
+
+impl From<&OutPoint> for OutPointKey {
+    fn from(op: &OutPoint) -> OutPointKey {
+        OutPointKey(op.tx_hash().unpack(), op.index().unpack())
+    }
+}
+
+impl From<OutPoint> for OutPointKey {
+    fn from(op: OutPoint) -> OutPointKey {
+        OutPointKey::from(&op)
+    }
+}
+
+impl From<&OutPointKey> for OutPoint {
+    fn from(key: &OutPointKey) -> OutPoint {
+        OutPoint::new(key.0.pack(), key.1)
+    }
+}
+
+impl From<OutPointKey> for OutPoint {
+    fn from(key: OutPointKey) -> OutPoint {
+        OutPoint::from(&key)
+    }
+}
+
+impl fmt::Display for OutPointKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#x}:{}", self.0, self.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_types::h256;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_outpoint_utils() {
+        let tx_hash = h256!("0x1234");
+        let op1 = OutPoint::new(tx_hash.pack(), 1);
+        let op1_copy = OutPoint::new(tx_hash.pack(), 1);
+        let op2 = OutPoint::new(tx_hash.pack(), 2);
+
+        assert!(outpoint_eq(&op1, &op1_copy));
+        assert!(!outpoint_eq(&op1, &op2));
+        assert_eq!(outpoint_hash(&op1), outpoint_hash(&op1_copy));
+        assert_ne!(outpoint_hash(&op1), outpoint_hash(&op2));
+        assert_eq!(
+            outpoint_to_string(&op1),
+            "0x0000000000000000000000000000000000000000000000000000000000001234:1"
+        );
+
+        let key = OutPointKey::from(&op1);
+        assert_eq!(key, OutPointKey::new(tx_hash, 1));
+        assert!(key < OutPointKey::from(&op2));
+        assert_eq!(OutPoint::from(&key), op1);
+
+        let mut map = HashMap::new();
+        map.insert(OutPointKey::from(&op1), "first");
+        assert_eq!(map.get(&OutPointKey::from(op1_copy)), Some(&"first"));
+        assert_eq!(map.get(&OutPointKey::from(op2)), None);
+    }
+}
@@ -0,0 +1,303 @@
+use std::collections::HashSet;
+
+use ckb_types::{
+    core::{Capacity, EpochNumberWithFraction, TransactionView},
+    packed::{Byte32, CellDep, OutPoint},
+    prelude::*,
+};
+use thiserror::Error;
+
+use super::epoch::{cellbase_mature_epoch, epoch_cmp, format_epoch};
+use crate::traits::{HeaderDepResolver, TransactionDependencyError, TransactionDependencyProvider};
+
+/// The structural problems found by [`validate_transaction_structure`]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ValidationWarning {
+    #[error(
+        "output #{index} capacity `{capacity}` is less than the occupied capacity `{occupied}`"
+    )]
+    InsufficientCapacity {
+        index: usize,
+        capacity: u64,
+        occupied: u64,
+    },
+
+    #[error("cell dep #{0} is duplicated: {1}")]
+    DuplicateCellDep(usize, CellDep),
+
+    #[error("input #{0} is duplicated: {1}")]
+    DuplicateInput(usize, OutPoint),
+
+    #[error("witnesses length `{witnesses}` is less than inputs length `{inputs}`")]
+    MissingWitnesses { witnesses: usize, inputs: usize },
+
+    #[error("header dep #{0} is zero hash")]
+    ZeroHeaderDep(usize),
+}
+
+/// A lightweight structural check before sending the transaction, it checks:
+///   * all outputs have enough capacity for the occupied capacity
+///   * no duplicated cell deps
+///   * no duplicated inputs
+///   * witnesses length is not less than inputs length
+///   * no zero header dep hash
+///
+/// The problems are returned as warnings, the caller can decide whether to abort.
+pub fn validate_transaction_structure(tx: &TransactionView) -> Result<(), Vec<ValidationWarning>> {
+    let mut warnings = Vec::new();
+    for (index, (output, data)) in tx.outputs_with_data_iter().enumerate() {
+        let capacity: u64 = output.capacity().unpack();
+        // the occupied capacity is overflowed, treat it as the maximum value
+        let occupied = output
+            .occupied_capacity(Capacity::bytes(data.len()).unwrap_or(Capacity::zero()))
+            .map(|capacity| capacity.as_u64())
+            .unwrap_or(u64::MAX);
+        if capacity < occupied {
+            warnings.push(ValidationWarning::InsufficientCapacity {
+                index,
+                capacity,
+                occupied,
+            });
+        }
+    }
+
+    let mut cell_deps = HashSet::new();
+    for (index, cell_dep) in tx.cell_deps_iter().enumerate() {
+        if !cell_deps.insert(cell_dep.as_slice().to_vec()) {
+            warnings.push(ValidationWarning::DuplicateCellDep(index, cell_dep));
+        }
+    }
+
+    let mut inputs = HashSet::new();
+    for (index, input) in tx.inputs().into_iter().enumerate() {
+        let out_point = input.previous_output();
+        if !inputs.insert(out_point.as_slice().to_vec()) {
+            warnings.push(ValidationWarning::DuplicateInput(index, out_point));
+        }
+    }
+
+    if tx.witnesses().len() < tx.inputs().len() {
+        warnings.push(ValidationWarning::MissingWitnesses {
+            witnesses: tx.witnesses().len(),
+            inputs: tx.inputs().len(),
+        });
+    }
+
+    for (index, hash) in tx.header_deps_iter().enumerate() {
+        if hash == Byte32::zero() {
+            warnings.push(ValidationWarning::ZeroHeaderDep(index));
+        }
+    }
+
+    if warnings.is_empty() {
+        Ok(())
+    } else {
+        Err(warnings)
+    }
+}
+
+/// An input spending a cellbase output which is not mature yet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImmatureCellbaseInput {
+    pub input_index: usize,
+    pub out_point: OutPoint,
+    /// The number of the block the cellbase transaction belongs to
+    pub block_number: u64,
+    /// The epoch from which the output can be spent
+    pub mature_epoch: EpochNumberWithFraction,
+}
+
+#[derive(Error, Debug)]
+pub enum CellbaseMaturityError {
+    #[error("transaction dependency provider error: `{0}`")]
+    TxDep(#[from] TransactionDependencyError),
+
+    #[error("resolve the header of cellbase transaction `{0}` failed: `{1}`")]
+    ResolveHeader(Byte32, String),
+
+    #[error("immature cellbase inputs: {}", format_immature_inputs(.0))]
+    Immature(Vec<ImmatureCellbaseInput>),
+}
+
+fn format_immature_inputs(inputs: &[ImmatureCellbaseInput]) -> String {
+    inputs
+        .iter()
+        .map(|input| {
+            format!(
+                "input #{} is mature at epoch {}",
+                input.input_index,
+                format_epoch(input.mature_epoch)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Check the inputs spending cellbase outputs are mature at `epoch`, usually the
+/// epoch of the block next to the tip. The cellbase outputs are found by the
+/// previous transactions, their mature epochs are calculated from the headers
+/// resolved by the transaction hashes (the outputs of the genesis block are always
+/// mature).
+///
+/// All the immature inputs are reported by [`CellbaseMaturityError::Immature`] with
+/// the epoch from which they can be spent.
+pub fn validate_cellbase_maturity(
+    tx: &TransactionView,
+    tx_dep_provider: &dyn TransactionDependencyProvider,
+    header_dep_resolver: &dyn HeaderDepResolver,
+    cellbase_maturity: EpochNumberWithFraction,
+    epoch: EpochNumberWithFraction,
+) -> Result<(), CellbaseMaturityError> {
+    if tx.is_cellbase() {
+        return Ok(());
+    }
+    let mut immature_inputs = Vec::new();
+    for (input_index, out_point) in tx.input_pts_iter().enumerate() {
+        let tx_hash = out_point.tx_hash();
+        if !tx_dep_provider.get_transaction(&tx_hash)?.is_cellbase() {
+            continue;
+        }
+        let header = header_dep_resolver
+            .resolve_by_tx(&tx_hash)
+            .map_err(|err| CellbaseMaturityError::ResolveHeader(tx_hash.clone(), err.to_string()))?
+            .ok_or_else(|| {
+                CellbaseMaturityError::ResolveHeader(tx_hash.clone(), "not found".to_string())
+            })?;
+        if header.number() == 0 {
+            continue;
+        }
+        let mature_epoch = cellbase_mature_epoch(header.epoch(), cellbase_maturity);
+        if epoch_cmp(epoch, mature_epoch).is_lt() {
+            immature_inputs.push(ImmatureCellbaseInput {
+                input_index,
+                out_point,
+                block_number: header.number(),
+                mature_epoch,
+            });
+        }
+    }
+    if immature_inputs.is_empty() {
+        Ok(())
+    } else {
+        Err(CellbaseMaturityError::Immature(immature_inputs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_types::{
+        bytes::Bytes,
+        core::TransactionBuilder,
+        packed::{CellInput, CellOutput},
+    };
+
+    use crate::test_util::random_out_point;
+
+    #[test]
+    fn test_validate_transaction_structure() {
+        let out_point = random_out_point();
+        let cell_dep = CellDep::new_builder().out_point(random_out_point()).build();
+        let output = CellOutput::new_builder()
+            .capacity(Capacity::shannons(41_0000_0000).pack())
+            .build();
+        let valid_tx = TransactionBuilder::default()
+            .input(CellInput::new(out_point.clone(), 0))
+            .output(output.clone())
+            .output_data(Bytes::new().pack())
+            .cell_dep(cell_dep.clone())
+            .witness(Bytes::new().pack())
+            .build();
+        assert_eq!(validate_transaction_structure(&valid_tx), Ok(()));
+
+        let invalid_tx = valid_tx
+            .as_advanced_builder()
+            .input(CellInput::new(out_point.clone(), 0))
+            .output(output)
+            .output_data(Bytes::from(vec![0u8; 1]).pack())
+            .cell_dep(cell_dep.clone())
+            .header_dep(Byte32::zero())
+            .build();
+        let warnings = validate_transaction_structure(&invalid_tx).unwrap_err();
+        assert_eq!(
+            warnings,
+            vec![
+                ValidationWarning::InsufficientCapacity {
+                    index: 1,
+                    capacity: 41_0000_0000,
+                    occupied: 42_0000_0000,
+                },
+                ValidationWarning::DuplicateCellDep(1, cell_dep),
+                ValidationWarning::DuplicateInput(1, out_point),
+                ValidationWarning::MissingWitnesses {
+                    witnesses: 1,
+                    inputs: 2,
+                },
+                ValidationWarning::ZeroHeaderDep(0),
+            ]
+        );
+    }
+    #[test]
+    fn test_validate_cellbase_maturity() {
+        use ckb_types::{core::HeaderBuilder, H256};
+
+        use crate::traits::{OffchainHeaderDepResolver, OffchainTransactionDependencyProvider};
+
+        let output = CellOutput::new_builder()
+            .capacity(Capacity::shannons(1000_0000_0000).pack())
+            .build();
+        // the witness of a cellbase carries the miner message instead of `WitnessArgs`
+        let cellbase = TransactionBuilder::default()
+            .input(CellInput::new_cellbase_input(100))
+            .output(output.clone())
+            .output_data(Bytes::new().pack())
+            .witness(Bytes::from(vec![0xffu8; 10]).pack())
+            .build();
+        let normal_tx = TransactionBuilder::default()
+            .input(CellInput::new(random_out_point(), 0))
+            .output(output.clone())
+            .output_data(Bytes::new().pack())
+            .build();
+        let header = HeaderBuilder::default()
+            .number(100.pack())
+            .epoch(EpochNumberWithFraction::new(10, 7, 10).pack())
+            .build();
+
+        let mut tx_dep_provider = OffchainTransactionDependencyProvider::default();
+        let mut header_dep_resolver = OffchainHeaderDepResolver::default();
+        for tx in [&cellbase, &normal_tx] {
+            let tx_hash: H256 = tx.hash().unpack();
+            tx_dep_provider.txs.insert(tx_hash.clone(), tx.clone());
+            tx_dep_provider
+                .cells
+                .insert((tx_hash.clone(), 0), (output.clone(), Bytes::new()));
+            header_dep_resolver
+                .by_tx_hash
+                .insert(tx_hash, header.clone());
+        }
+        assert_eq!(tx_dep_provider.get_input_cells(&cellbase).unwrap(), vec![]);
+
+        let tx = TransactionBuilder::default()
+            .input(CellInput::new(OutPoint::new(normal_tx.hash(), 0), 0))
+            .input(CellInput::new(OutPoint::new(cellbase.hash(), 0), 0))
+            .build();
+        let maturity = EpochNumberWithFraction::new(4, 0, 1);
+        let check = |epoch| {
+            validate_cellbase_maturity(&tx, &tx_dep_provider, &header_dep_resolver, maturity, epoch)
+        };
+        match check(EpochNumberWithFraction::new(14, 6, 10)) {
+            Err(CellbaseMaturityError::Immature(inputs)) => assert_eq!(
+                inputs,
+                vec![ImmatureCellbaseInput {
+                    input_index: 1,
+                    out_point: OutPoint::new(cellbase.hash(), 0),
+                    block_number: 100,
+                    mature_epoch: EpochNumberWithFraction::new(14, 7, 10),
+                }]
+            ),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(check(EpochNumberWithFraction::new(14, 7, 10)).is_ok());
+        assert!(check(EpochNumberWithFraction::new(15, 0, 1)).is_ok());
+    }
+}
@@ -0,0 +1,2 @@
+//! The tests of the ckb-sdk built without the default features (no rpc) for
+//! wasm32-unknown-unknown, run them by `wasm-pack test --node` in this directory.
@@ -0,0 +1,69 @@
+use ckb_sdk::{
+    constants::{ONE_CKB, SIGHASH_TYPE_HASH},
+    traits::OffchainTransactionDependencyProvider,
+    transaction::signer::{SignContexts, TransactionSigner},
+    unlock::verify_signatures,
+    NetworkInfo, ScriptGroup, TransactionWithScriptGroups,
+};
+use ckb_types::{
+    bytes::Bytes,
+    core::{ScriptHashType, TransactionBuilder},
+    h160, h256,
+    packed::{CellInput, CellOutput, OutPoint, Script, WitnessArgs},
+    prelude::*,
+    H160, H256,
+};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+// ckt1qyq86vaa6e8tsruv5ngcd5tp7lcvcewxy7cquuksvj
+const ACCOUNT_KEY: H256 =
+    h256!("0x8fdf1d6df54c6c9c0167a657c0f68a9bb3bf4304942ce487880e86ce6099191c");
+const ACCOUNT_ARG: H160 = h160!("0x7d33bdd64eb80f8ca4d186d161f7f0cc65c627b0");
+
+#[wasm_bindgen_test]
+fn test_sign_sighash_transaction() {
+    let lock = Script::new_builder()
+        .code_hash(SIGHASH_TYPE_HASH.pack())
+        .hash_type(ScriptHashType::Type.into())
+        .args(Bytes::from(ACCOUNT_ARG.as_bytes().to_vec()).pack())
+        .build();
+
+    // the input cell is provided offline, no rpc is needed to sign the transaction
+    let input_tx_hash = h256!("0x1234");
+    let input = CellOutput::new_builder()
+        .capacity((100 * ONE_CKB).pack())
+        .lock(lock.clone())
+        .build();
+    let mut tx_dep_provider = OffchainTransactionDependencyProvider::default();
+    tx_dep_provider
+        .cells
+        .insert((input_tx_hash.clone(), 0), (input, Bytes::new()));
+
+    let output = CellOutput::new_builder()
+        .capacity((99 * ONE_CKB).pack())
+        .lock(lock.clone())
+        .build();
+    let placeholder_witness = WitnessArgs::new_builder()
+        .lock(Some(Bytes::from(vec![0u8; 65])).pack())
+        .build();
+    let tx = TransactionBuilder::default()
+        .input(CellInput::new(OutPoint::new(input_tx_hash.pack(), 0), 0))
+        .output(output)
+        .output_data(Bytes::new().pack())
+        .witness(placeholder_witness.as_bytes().pack())
+        .build();
+    let mut script_group = ScriptGroup::from_lock_script(&lock);
+    script_group.input_indices.push(0);
+    let mut tx_with_groups = TransactionWithScriptGroups::new(tx, vec![script_group]);
+
+    let signed_groups = TransactionSigner::new(&NetworkInfo::testnet())
+        .sign_transaction(
+            &mut tx_with_groups,
+            &SignContexts::new_sighash_h256(vec![ACCOUNT_KEY]).unwrap(),
+        )
+        .unwrap();
+    assert_eq!(signed_groups, vec![0]);
+
+    let skipped = verify_signatures(tx_with_groups.get_tx_view(), &tx_dep_provider, &[]).unwrap();
+    assert!(skipped.is_empty());
+}